@@ -1,13 +1,16 @@
+use crate::error::{Context, ErrorKind, ParseError};
 use classfile::{
     attributes::{self, Tag as AttrTag, TargetInfo, TypeAnnotation},
-    constant_pool, AttributeType, ClassFile, ConstantPool, FieldInfo, MethodInfo, Version,
+    constant_pool, consts, AttributeType, BytesRef, ClassFile, ConstantPool, FieldInfo, MethodInfo,
+    Version,
 };
+use std::convert::TryFrom;
 use std::sync::Arc;
 
 use nom::{
-    call, count, do_parse, named, named_args,
+    call, count, do_parse, map_opt, named, named_args,
     number::streaming::{be_u16, be_u32, be_u8},
-    peek, switch, tag, take, value,
+    peek, switch, take, value,
 };
 
 named!(
@@ -17,7 +20,7 @@ named!(
 
 named!(
     constant_tag<constant_pool::Tag>,
-    do_parse!(tag: be_u8 >> (constant_pool::Tag::from(tag)))
+    map_opt!(be_u8, |tag| constant_pool::Tag::try_from(tag).ok())
 );
 
 // Const generics still not in stable,
@@ -26,13 +29,10 @@ named!(
 macro_rules! gen_take_exact {
     ($count: expr, $name: ident) => {
         fn $name(input: &[u8]) -> nom::IResult<&[u8], [u8; $count]> {
+            let (input, bytes) = nom::bytes::streaming::take($count as usize)(input)?;
             let mut output = [0; $count];
-            // TODO: Nom error
-            assert!(input.len() >= $count);
-            for i in 0..$count {
-                output[i] = input[i];
-            }
-            Ok((&input[$count..], output))
+            output.copy_from_slice(bytes);
+            Ok((input, output))
         }
     };
 }
@@ -104,43 +104,29 @@ named!(
                         desc_index: be_u16 >>
                         (constant_pool::Type::MethodType { desc_index })
                     ) |
+                    constant_pool::Tag::Dynamic => do_parse!(
+                        bootstrap_method_attr_index: be_u16 >>
+                        name_and_type_index: be_u16 >>
+                        (constant_pool::Type::Dynamic { bootstrap_method_attr_index, name_and_type_index })
+                    ) |
                     constant_pool::Tag::InvokeDynamic => do_parse!(
                         bootstrap_method_attr_index: be_u16 >>
                         name_and_type_index: be_u16 >>
                         (constant_pool::Type::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index })
+                    ) |
+                    constant_pool::Tag::Module => do_parse!(
+                        name_index: be_u16 >>
+                        (constant_pool::Type::Module { name_index })
+                    ) |
+                    constant_pool::Tag::Package => do_parse!(
+                        name_index: be_u16 >>
+                        (constant_pool::Type::Package { name_index })
                     )
                 )
             >> (entry)
     )
 );
 
-fn constant_pool(input: &[u8]) -> nom::IResult<&[u8], ConstantPool> {
-    let (mut input, count) = be_u16(input)?;
-
-    let mut output = Vec::with_capacity(count as usize);
-    output.push(constant_pool::Type::Nop);
-
-    let mut i = 1;
-    while i < count {
-        let (new_input, constant_type) = cp_entry(input)?;
-        input = new_input;
-
-        i += 1;
-        output.push(constant_type.clone());
-
-        //spec 4.4.5
-        match constant_type {
-            constant_pool::Type::Long { .. } | constant_pool::Type::Double { .. } => {
-                i += 1;
-                output.push(constant_pool::Type::Nop);
-            }
-            _ => (),
-        }
-    }
-
-    Ok((input, Arc::new(output)))
-}
-
 use attributes::VerificationTypeInfo;
 named!(
     verification_type_info<VerificationTypeInfo>,
@@ -151,8 +137,8 @@ named!(
                     0 => value!(VerificationTypeInfo::Top) |
                     1 => value!(VerificationTypeInfo::Integer) |
                     2 => value!(VerificationTypeInfo::Float) |
-                    3 => value!(VerificationTypeInfo::Long) |
-                    4 => value!(VerificationTypeInfo::Double) |
+                    3 => value!(VerificationTypeInfo::Double) |
+                    4 => value!(VerificationTypeInfo::Long) |
                     5 => value!(VerificationTypeInfo::Null) |
                     6 => value!(VerificationTypeInfo::UninitializedThis) |
                     7 => do_parse!(
@@ -369,14 +355,28 @@ named_args!(element_value_pair(cp: ConstantPool)<attributes::ElementValuePair>,
     (attributes::ElementValuePair {name_index, value})
 ));
 
+//the Utf8 entry, for the nom parsers
+fn cp_utf8<'a>(input: &'a [u8], cp: &ConstantPool, idx: u16) -> nom::IResult<&'a [u8], BytesRef> {
+    match get_utf8(cp, idx) {
+        Some(v) => Ok((input, v.clone())),
+        None => Err(nom::Err::Error((input, nom::error::ErrorKind::Verify))),
+    }
+}
+
 named_args!(annotation_entry(cp: ConstantPool)<attributes::AnnotationEntry>, do_parse!(
     type_index: be_u16 >>
+    type_name: call!(cp_utf8, &cp, type_index) >>
     pair_count: be_u16 >>
     pairs: count!(call!(element_value_pair, cp.clone()), pair_count as usize) >>
-    type_name: value!(constant_pool::get_utf8(&cp, type_index as usize).clone()) >>
     (attributes::AnnotationEntry {type_name, pairs})
 ));
 
+named_args!(parameter_annotations(cp: ConstantPool)<Vec<attributes::AnnotationEntry>>, do_parse!(
+    annotation_count: be_u16 >>
+    annotations: count!(call!(annotation_entry, cp.clone()), annotation_count as usize) >>
+    (annotations)
+));
+
 named!(
     local_var_target_table<attributes::LocalVarTargetTable>,
     do_parse!(
@@ -491,6 +491,92 @@ named!(
     )
 );
 
+named!(
+    module_requires<attributes::ModuleRequires>,
+    do_parse!(
+        requires_index: be_u16
+            >> requires_flags: be_u16
+            >> requires_version_index: be_u16
+            >> (attributes::ModuleRequires {
+                requires_index,
+                requires_flags,
+                requires_version_index,
+            })
+    )
+);
+
+named!(
+    module_exports<attributes::ModuleExports>,
+    do_parse!(
+        exports_index: be_u16
+            >> exports_flags: be_u16
+            >> exports_to_count: be_u16
+            >> exports_to: count!(be_u16, exports_to_count as usize)
+            >> (attributes::ModuleExports {
+                exports_index,
+                exports_flags,
+                exports_to,
+            })
+    )
+);
+
+named!(
+    module_opens<attributes::ModuleOpens>,
+    do_parse!(
+        opens_index: be_u16
+            >> opens_flags: be_u16
+            >> opens_to_count: be_u16
+            >> opens_to: count!(be_u16, opens_to_count as usize)
+            >> (attributes::ModuleOpens {
+                opens_index,
+                opens_flags,
+                opens_to,
+            })
+    )
+);
+
+named!(
+    module_provides<attributes::ModuleProvides>,
+    do_parse!(
+        provides_index: be_u16
+            >> provides_with_count: be_u16
+            >> provides_with: count!(be_u16, provides_with_count as usize)
+            >> (attributes::ModuleProvides {
+                provides_index,
+                provides_with,
+            })
+    )
+);
+
+named!(
+    module<attributes::Module>,
+    do_parse!(
+        name_index: be_u16
+            >> flags: be_u16
+            >> version_index: be_u16
+            >> requires_count: be_u16
+            >> requires: count!(module_requires, requires_count as usize)
+            >> exports_count: be_u16
+            >> exports: count!(module_exports, exports_count as usize)
+            >> opens_count: be_u16
+            >> opens: count!(module_opens, opens_count as usize)
+            >> uses_count: be_u16
+            >> uses: count!(be_u16, uses_count as usize)
+            >> provides_count: be_u16
+            >> provides: count!(module_provides, provides_count as usize)
+            >> (attributes::Module {
+                name_index,
+                flags,
+                version_index,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+            })
+    )
+);
+
 named!(
     code_exception<attributes::CodeException>,
     do_parse!(
@@ -512,22 +598,6 @@ named_args!(attr_sized(tag: AttrTag, self_len: usize, cp: ConstantPool)<Attribut
         constant_value_index: be_u16 >>
         (AttributeType::ConstantValue {constant_value_index})
     ) |
-    AttrTag::Code => do_parse!(
-        max_stack: be_u16 >>
-        max_locals: be_u16 >>
-        len: be_u32 >>
-        code: take!(len) >> // TODO: Parse code in same time?)
-        exception_count: be_u16 >>
-        exceptions: count!(code_exception, exception_count as usize) >>
-        attrs: call!(attr_type_vec, cp) >>
-        (AttributeType::Code(attributes::Code {
-            max_stack,
-            max_locals,
-            code: Arc::new(Vec::from(code)),
-            exceptions,
-            attrs,
-        }))
-    ) |
     AttrTag::StackMapTable => do_parse!(
         frame_count: be_u16 >>
         frames: count!(stack_map_frame, frame_count as usize) >>
@@ -590,14 +660,14 @@ named_args!(attr_sized(tag: AttrTag, self_len: usize, cp: ConstantPool)<Attribut
     ) |
     AttrTag::RuntimeVisibleParameterAnnotations => do_parse!(
         raw: peek!(take!(self_len)) >>
-        annotation_count: be_u16 >>
-        annotations: count!(call!(annotation_entry, cp.clone()), annotation_count as usize) >>
+        parameter_count: be_u8 >>
+        annotations: count!(call!(parameter_annotations, cp.clone()), parameter_count as usize) >>
         (AttributeType::RuntimeVisibleParameterAnnotations {raw: Arc::new(Vec::from(raw)), annotations})
     ) |
     AttrTag::RuntimeInvisibleParameterAnnotations => do_parse!(
         raw: peek!(take!(self_len)) >>
-        annotation_count: be_u16 >>
-        annotations: count!(call!(annotation_entry, cp.clone()), annotation_count as usize) >>
+        parameter_count: be_u8 >>
+        annotations: count!(call!(parameter_annotations, cp.clone()), parameter_count as usize) >>
         (AttributeType::RuntimeInvisibleParameterAnnotations {raw: Arc::new(Vec::from(raw)), annotations})
     ) |
     AttrTag::RuntimeVisibleTypeAnnotations => do_parse!(
@@ -627,88 +697,575 @@ named_args!(attr_sized(tag: AttrTag, self_len: usize, cp: ConstantPool)<Attribut
         parameters: count!(method_parameter, parameter_count as usize) >>
         (AttributeType::MethodParameters {parameters})
     ) |
-    AttrTag::Unknown => do_parse!(
-        _data: take!(self_len) >>
-        (AttributeType::Unknown)
+    AttrTag::Module => do_parse!(
+        module: module >>
+        (AttributeType::Module(module))
+    ) |
+    AttrTag::ModulePackages => do_parse!(
+        package_count: be_u16 >>
+        packages: count!(be_u16, package_count as usize) >>
+        (AttributeType::ModulePackages { packages })
+    ) |
+    AttrTag::ModuleMainClass => do_parse!(
+        main_class_index: be_u16 >>
+        (AttributeType::ModuleMainClass { main_class_index })
+    ) |
+    AttrTag::NestHost => do_parse!(
+        host_class_index: be_u16 >>
+        (AttributeType::NestHost { host_class_index })
+    ) |
+    AttrTag::NestMembers => do_parse!(
+        class_count: be_u16 >>
+        classes: count!(be_u16, class_count as usize) >>
+        (AttributeType::NestMembers { classes })
+    ) |
+    AttrTag::PermittedSubclasses => do_parse!(
+        class_count: be_u16 >>
+        classes: count!(be_u16, class_count as usize) >>
+        (AttributeType::PermittedSubclasses { classes })
     )
 ));
 
-named_args!(attr_tag(cp: ConstantPool)<AttrTag>, do_parse!(
-    name_index: be_u16 >>
-    name: value!(constant_pool::get_utf8(&cp, name_index as usize)) >>
-    inner: value!(AttrTag::from(name.as_slice())) >>
-    (inner)
-));
+/*
+The structures of the class file are parsed by the nom parsers above, driven
+by Reader, which tracks the offset & the structure being parsed to report
+where the class file is malformed. The content of an attribute is parsed
+within the length of the attribute.
+*/
 
-named_args!(attr_type(cp: ConstantPool)<AttributeType>, do_parse!(
-    tag: call!(attr_tag, cp.clone()) >>
-    length: be_u32 >>
-    attr: call!(attr_sized, tag, length as usize, cp) >>
-    (attr)
-));
+type Result<T> = std::result::Result<T, ParseError>;
 
-named_args!(attr_type_vec(cp: ConstantPool)<Vec<AttributeType>>, do_parse!(
-    attrs_count: be_u16 >>
-    attrs: count!(call!(attr_type, cp.clone()), attrs_count as usize) >>
-    (attrs)
-));
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    //the end of the class file, or of the attribute being parsed
+    end: usize,
+    context: Vec<Context>,
+}
 
-named_args!(field(cp: ConstantPool)<FieldInfo>, do_parse!(
-    acc_flags: be_u16 >>
-    name_index: be_u16 >>
-    desc_index: be_u16 >>
-    attrs: call!(attr_type_vec, cp) >>
-    (FieldInfo {
-        acc_flags,
-        name_index,
-        desc_index,
-        attrs,
-    })
-));
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            end: buf.len(),
+            context: vec![],
+        }
+    }
 
-named_args!(method_info(cp: ConstantPool)<MethodInfo>, do_parse!(
-    acc_flags: be_u16 >>
-    name_index: be_u16 >>
-    desc_index: be_u16 >>
-    attrs: call!(attr_type_vec, cp) >>
-    (MethodInfo {
+    fn error(&self, offset: usize, kind: ErrorKind) -> ParseError {
+        ParseError::new(offset, self.context.clone(), kind)
+    }
+
+    //apply a nom parser at the current position
+    fn run<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&'a [u8]) -> nom::IResult<&'a [u8], T>,
+    {
+        match f(&self.buf[self.pos..self.end]) {
+            Ok((rest, v)) => {
+                self.pos = self.end - rest.len();
+                Ok(v)
+            }
+            Err(nom::Err::Incomplete(_)) => Err(self.error(self.end, ErrorKind::Truncated)),
+            Err(nom::Err::Error((rest, kind))) | Err(nom::Err::Failure((rest, kind))) => {
+                Err(self.error(self.end - rest.len(), ErrorKind::Malformed(kind)))
+            }
+        }
+    }
+
+    fn u2(&mut self) -> Result<u16> {
+        self.run(be_u16)
+    }
+
+    fn u4(&mut self) -> Result<u32> {
+        self.run(be_u32)
+    }
+
+    fn with<T>(&mut self, ctx: Context, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.context.push(ctx);
+        let v = f(self)?;
+        self.context.pop();
+        Ok(v)
+    }
+
+    //the content of an attribute, of 'len' bytes exactly
+    fn sized<T>(&mut self, len: u32, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let start = self.pos;
+        let end = match start.checked_add(len as usize) {
+            Some(end) if end <= self.end => end,
+            _ => return Err(self.error(self.end, ErrorKind::Truncated)),
+        };
+
+        let outer = std::mem::replace(&mut self.end, end);
+        let v = f(self);
+        self.end = outer;
+        let v = v?;
+
+        if self.pos != end {
+            let actual = (self.pos - start) as u32;
+            let kind = ErrorKind::BadAttributeLength {
+                declared: len,
+                actual,
+            };
+            return Err(self.error(start, kind));
+        }
+
+        Ok(v)
+    }
+}
+
+fn get_utf8(cp: &ConstantPool, idx: u16) -> Option<&BytesRef> {
+    match cp.get(idx as usize) {
+        Some(constant_pool::Type::Utf8 { bytes }) => Some(bytes),
+        _ => None,
+    }
+}
+
+//the name of a member or a record component, for the context
+fn name_of(cp: &ConstantPool, idx: u16) -> String {
+    match get_utf8(cp, idx) {
+        Some(name) => String::from_utf8_lossy(name.as_slice()).to_string(),
+        None => format!("#{}", idx),
+    }
+}
+
+fn constant_pool(r: &mut Reader) -> Result<ConstantPool> {
+    let count = r.u2()?;
+
+    let mut output = Vec::with_capacity(count as usize);
+    output.push(constant_pool::Type::Nop);
+
+    let mut i = 1;
+    while i < count {
+        let constant_type = r.with(Context::ConstantPool(i), |r| {
+            let start = r.pos;
+            let tag = r.run(nom::combinator::peek(be_u8))?;
+            if constant_pool::Tag::try_from(tag).is_err() {
+                return Err(r.error(start, ErrorKind::UnknownConstantTag(tag)));
+            }
+            r.run(cp_entry)
+        })?;
+
+        i += 1;
+        output.push(constant_type.clone());
+
+        //spec 4.4.5
+        match constant_type {
+            constant_pool::Type::Long { .. } | constant_pool::Type::Double { .. } => {
+                i += 1;
+                output.push(constant_pool::Type::Nop);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Arc::new(output))
+}
+
+fn attr_type_vec(r: &mut Reader, cp: &ConstantPool) -> Result<Vec<AttributeType>> {
+    let attrs_count = r.u2()?;
+    let mut attrs = Vec::with_capacity(attrs_count as usize);
+    for _ in 0..attrs_count {
+        let start = r.pos;
+        let name_index = r.u2()?;
+        let length = r.u4()?;
+        let name = get_utf8(cp, name_index)
+            .ok_or_else(|| r.error(start, ErrorKind::BadConstantIndex(name_index)))?;
+        let tag = AttrTag::from(name.as_slice());
+
+        let ctx = Context::Attribute(String::from_utf8_lossy(name.as_slice()).to_string());
+        let attr = r.with(ctx, |r| {
+            r.sized(length, |r| attr_type(r, cp, name_index, tag, length))
+        })?;
+        attrs.push(attr);
+    }
+
+    Ok(attrs)
+}
+
+//Code & Record have attributes, Unknown keeps the name, the others are parsed by attr_sized
+fn attr_type(
+    r: &mut Reader,
+    cp: &ConstantPool,
+    name_index: u16,
+    tag: AttrTag,
+    len: u32,
+) -> Result<AttributeType> {
+    match tag {
+        AttrTag::Code => {
+            let max_stack = r.u2()?;
+            let max_locals = r.u2()?;
+            let code_len = r.u4()?;
+            let code = r.run(nom::bytes::streaming::take(code_len))?;
+            let exception_count = r.u2()?;
+            let exceptions =
+                r.run(|input| count!(input, code_exception, exception_count as usize))?;
+            let attrs = attr_type_vec(r, cp)?;
+            Ok(AttributeType::Code(attributes::Code {
+                max_stack,
+                max_locals,
+                code: Arc::new(Vec::from(code)),
+                exceptions,
+                attrs,
+            }))
+        }
+        AttrTag::Record => {
+            let component_count = r.u2()?;
+            let mut components = Vec::with_capacity(component_count as usize);
+            for _ in 0..component_count {
+                let name_index = r.u2()?;
+                let desc_index = r.u2()?;
+                let ctx = Context::RecordComponent(name_of(cp, name_index));
+                let attrs = r.with(ctx, |r| attr_type_vec(r, cp))?;
+                components.push(attributes::RecordComponent {
+                    name_index,
+                    desc_index,
+                    attrs,
+                });
+            }
+            Ok(AttributeType::Record { components })
+        }
+        AttrTag::Unknown => {
+            let info = r.run(nom::bytes::streaming::take(len))?;
+            Ok(AttributeType::Unknown {
+                name_index,
+                info: Arc::new(Vec::from(info)),
+            })
+        }
+        _ => r.run(|input| attr_sized(input, tag, len as usize, cp.clone())),
+    }
+}
+
+//acc_flags, name_index, desc_index & attrs of a field or a method
+fn member(
+    r: &mut Reader,
+    cp: &ConstantPool,
+    ctx: fn(String) -> Context,
+) -> Result<(u16, u16, u16, Vec<AttributeType>)> {
+    let acc_flags = r.u2()?;
+    let name_index = r.u2()?;
+    let desc_index = r.u2()?;
+    let attrs = r.with(ctx(name_of(cp, name_index)), |r| attr_type_vec(r, cp))?;
+    Ok((acc_flags, name_index, desc_index, attrs))
+}
+
+fn class_file(r: &mut Reader) -> Result<ClassFile> {
+    let magic = r.u4()?;
+    if magic != consts::MAGIC {
+        return Err(r.error(0, ErrorKind::BadMagic(magic)));
+    }
+    let version = r.run(version)?;
+    let cp = constant_pool(r)?;
+    let acc_flags = r.u2()?;
+
+    //the name of the class is taken before the verification
+    let start = r.pos;
+    let this_class = r.u2()?;
+    let is_class = match cp.get(this_class as usize) {
+        Some(constant_pool::Type::Class { name_index }) => get_utf8(&cp, *name_index).is_some(),
+        _ => false,
+    };
+    if !is_class {
+        return Err(r.error(start, ErrorKind::BadConstantIndex(this_class)));
+    }
+    let super_class = r.u2()?;
+
+    let interfaces = r.with(Context::Interfaces, |r| {
+        let interfaces_count = r.u2()?;
+        r.run(|input| count!(input, be_u16, interfaces_count as usize))
+    })?;
+
+    let fields_count = r.u2()?;
+    let mut fields = Vec::with_capacity(fields_count as usize);
+    for _ in 0..fields_count {
+        let (acc_flags, name_index, desc_index, attrs) = member(r, &cp, Context::Field)?;
+        fields.push(FieldInfo {
+            acc_flags,
+            name_index,
+            desc_index,
+            attrs,
+        });
+    }
+
+    let method_count = r.u2()?;
+    let mut methods = Vec::with_capacity(method_count as usize);
+    for _ in 0..method_count {
+        let (acc_flags, name_index, desc_index, attrs) = member(r, &cp, Context::Method)?;
+        methods.push(MethodInfo {
+            acc_flags,
+            name_index,
+            desc_index,
+            attrs,
+        });
+    }
+
+    let attrs = attr_type_vec(r, &cp)?;
+
+    if r.pos != r.end {
+        return Err(r.error(r.pos, ErrorKind::ExtraBytes(r.end - r.pos)));
+    }
+
+    Ok(ClassFile {
+        version,
+        cp,
         acc_flags,
-        name_index,
-        desc_index,
+        this_class,
+        super_class,
+        interfaces,
+        fields,
+        methods,
         attrs,
     })
-));
+}
 
-named!(
-    class_file<ClassFile>,
-    do_parse!(
-        _magic: tag!(b"\xCA\xFE\xBA\xBE")
-            >> version: version
-            >> cp: constant_pool
-            >> acc_flags: be_u16
-            >> this_class: be_u16
-            >> super_class: be_u16
-            >> interfaces_count: be_u16
-            >> interfaces: count!(be_u16, interfaces_count as usize)
-            >> fields_count: be_u16
-            >> fields: count!(call!(field, cp.clone()), fields_count as usize)
-            >> method_count: be_u16
-            >> methods: count!(call!(method_info, cp.clone()), method_count as usize)
-            >> attrs: call!(attr_type_vec, cp.clone())
-            >> (ClassFile {
-                version,
-                cp: cp.clone(),
-                acc_flags,
-                this_class,
-                super_class,
-                interfaces,
-                fields,
-                methods,
-                attrs
-            })
-    )
-);
+pub fn parse(input: &[u8]) -> Result<ClassFile> {
+    class_file(&mut Reader::new(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u2(v: u16) -> Vec<u8> {
+        v.to_be_bytes().to_vec()
+    }
+
+    fn utf8(s: &str) -> Vec<u8> {
+        let mut v = vec![1];
+        v.extend(u2(s.len() as u16));
+        v.extend(s.as_bytes());
+        v
+    }
 
-pub fn parse(input: &[u8]) -> nom::IResult<&[u8], ClassFile> {
-    class_file(input)
+    fn attr(name_index: u16, info: Vec<u8>) -> Vec<u8> {
+        let mut v = u2(name_index);
+        v.extend((info.len() as u32).to_be_bytes().iter());
+        v.extend(info);
+        v
+    }
+
+    //this_class is #2
+    fn class_file(
+        cp: &[Vec<u8>],
+        acc_flags: u16,
+        methods: &[Vec<u8>],
+        attrs: &[Vec<u8>],
+    ) -> Vec<u8> {
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE];
+        bytes.extend(u2(0));
+        bytes.extend(u2(65));
+        bytes.extend(u2(cp.len() as u16 + 1));
+        bytes.extend(cp.concat());
+        bytes.extend(u2(acc_flags));
+        bytes.extend(u2(2));
+        bytes.extend(u2(0));
+        bytes.extend(u2(0)); //interfaces
+        bytes.extend(u2(0)); //fields
+        bytes.extend(u2(methods.len() as u16));
+        bytes.extend(methods.concat());
+        bytes.extend(u2(attrs.len() as u16));
+        bytes.extend(attrs.concat());
+        bytes
+    }
+
+    #[test]
+    fn t_parse_java_21() {
+        let cp: Vec<Vec<u8>> = vec![
+            utf8("module-info"),         //#1
+            vec![7, 0, 1],               //#2 Class
+            utf8("m"),                   //#3
+            vec![19, 0, 3],              //#4 Module
+            utf8("p"),                   //#5
+            vec![20, 0, 5],              //#6 Package
+            utf8("x"),                   //#7
+            utf8("I"),                   //#8
+            vec![12, 0, 7, 0, 8],        //#9 NameAndType
+            vec![17, 0, 0, 0, 9],        //#10 Dynamic
+            utf8("Module"),              //#11
+            utf8("ModulePackages"),      //#12
+            utf8("ModuleMainClass"),     //#13
+            utf8("NestHost"),            //#14
+            utf8("NestMembers"),         //#15
+            utf8("Record"),              //#16
+            utf8("PermittedSubclasses"), //#17
+            utf8("Signature"),           //#18
+        ];
+
+        let mut module = u2(4); //name
+        module.extend(u2(0)); //flags
+        module.extend(u2(0)); //version
+        module.extend(u2(1)); //requires
+        module.extend(u2(4));
+        module.extend(u2(0x8000));
+        module.extend(u2(0));
+        module.extend(u2(1)); //exports
+        module.extend(u2(6));
+        module.extend(u2(0));
+        module.extend(u2(1));
+        module.extend(u2(4));
+        module.extend(u2(0)); //opens
+        module.extend(u2(1)); //uses
+        module.extend(u2(2));
+        module.extend(u2(1)); //provides
+        module.extend(u2(2));
+        module.extend(u2(2));
+        module.extend(u2(2));
+        module.extend(u2(2));
+
+        let mut record = u2(1);
+        record.extend(u2(7));
+        record.extend(u2(8));
+        record.extend(u2(1));
+        record.extend(attr(18, u2(8)));
+
+        let attrs = [
+            attr(11, module),
+            attr(12, [u2(1), u2(6)].concat()),
+            attr(13, u2(2)),
+            attr(14, u2(2)),
+            attr(15, [u2(2), u2(2), u2(2)].concat()),
+            attr(16, record),
+            attr(17, [u2(1), u2(2)].concat()),
+        ];
+
+        let bytes = class_file(&cp, 0x8000, &[], &attrs); //ACC_MODULE
+        let cf = parse(&bytes).unwrap();
+        assert_eq!(cf.to_bytes().unwrap(), bytes);
+        assert_eq!(cf.version.major, 65);
+        assert!(matches!(
+            cf.cp[4],
+            constant_pool::Type::Module { name_index: 3 }
+        ));
+        assert!(matches!(
+            cf.cp[6],
+            constant_pool::Type::Package { name_index: 5 }
+        ));
+        assert!(matches!(
+            cf.cp[10],
+            constant_pool::Type::Dynamic {
+                bootstrap_method_attr_index: 0,
+                name_and_type_index: 9
+            }
+        ));
+
+        match &cf.attrs[0] {
+            AttributeType::Module(m) => {
+                assert_eq!(m.name_index, 4);
+                assert_eq!(m.requires[0].requires_flags, 0x8000);
+                assert_eq!(m.exports[0].exports_to, vec![4]);
+                assert!(m.opens.is_empty());
+                assert_eq!(m.uses, vec![2]);
+                assert_eq!(m.provides[0].provides_with, vec![2, 2]);
+            }
+            t => panic!("not Module: {:?}", t),
+        }
+        assert!(
+            matches!(&cf.attrs[1], AttributeType::ModulePackages { packages } if packages == &[6])
+        );
+        assert!(matches!(
+            cf.attrs[2],
+            AttributeType::ModuleMainClass {
+                main_class_index: 2
+            }
+        ));
+        assert!(matches!(
+            cf.attrs[3],
+            AttributeType::NestHost {
+                host_class_index: 2
+            }
+        ));
+        assert!(
+            matches!(&cf.attrs[4], AttributeType::NestMembers { classes } if classes == &[2, 2])
+        );
+        match &cf.attrs[5] {
+            AttributeType::Record { components } => {
+                assert_eq!(components.len(), 1);
+                assert_eq!(components[0].desc_index, 8);
+                assert!(matches!(
+                    components[0].attrs[0],
+                    AttributeType::Signature { signature_index: 8 }
+                ));
+            }
+            t => panic!("not Record: {:?}", t),
+        }
+        assert!(
+            matches!(&cf.attrs[6], AttributeType::PermittedSubclasses { classes } if classes == &[2])
+        );
+    }
+
+    #[test]
+    fn t_parse_error() {
+        let cp = vec![
+            utf8("T"),        //#1
+            vec![7, 0, 1],    //#2 Class
+            utf8("main"),     //#3
+            utf8("()V"),      //#4
+            utf8("Code"),     //#5
+            utf8("NestHost"), //#6
+        ];
+        let mut code = u2(1); //max_stack
+        code.extend(u2(1)); //max_locals
+        code.extend(vec![0, 0, 0, 1, 177]); //return
+        code.extend(u2(0)); //exceptions
+        code.extend(u2(0)); //attrs
+        let mut method = u2(0x0009);
+        method.extend(u2(3));
+        method.extend(u2(4));
+        method.extend(u2(1));
+        method.extend(attr(5, code));
+        let methods = vec![method];
+        let bytes = class_file(&cp, 0x0021, &methods, &[]);
+        assert_eq!(parse(&bytes).unwrap().to_bytes().unwrap(), bytes);
+
+        let kind = |bytes: &[u8]| parse(bytes).unwrap_err().kind;
+
+        let mut bad = bytes.clone();
+        bad[0] = 0;
+        assert_eq!(kind(&bad), ErrorKind::BadMagic(0x00FE_BABE));
+
+        let e = parse(&bytes[..bytes.len() - 6]).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::Truncated);
+        assert_eq!(
+            e.context,
+            vec![
+                Context::Method("main".to_string()),
+                Context::Attribute("Code".to_string())
+            ]
+        );
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "method `main`, attribute `Code`: Truncated class file at offset {}",
+                bytes.len() - 6
+            )
+        );
+
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(kind(&extra), ErrorKind::ExtraBytes(1));
+
+        let mut unknown = cp.clone();
+        unknown[3] = vec![2, 0, 0];
+        let e = parse(&class_file(&unknown, 0x0021, &[], &[])).unwrap_err();
+        assert_eq!(e.kind, ErrorKind::UnknownConstantTag(2));
+        assert_eq!(e.context, vec![Context::ConstantPool(4)]);
+
+        //NestHost of 4 bytes
+        let mut nest_host = u2(6);
+        nest_host.extend(vec![0, 0, 0, 4]);
+        nest_host.extend(u2(2));
+        nest_host.extend(u2(0));
+        let bad = class_file(&cp, 0x0021, &[], &[nest_host]);
+        assert_eq!(
+            kind(&bad),
+            ErrorKind::BadAttributeLength {
+                declared: 4,
+                actual: 2
+            }
+        );
+
+        let bad = class_file(&cp, 0x0021, &[], &[attr(2, u2(2))]);
+        assert_eq!(kind(&bad), ErrorKind::BadConstantIndex(2));
+    }
 }
@@ -0,0 +1,101 @@
+use std::fmt;
+
+/// The error of parsing a class file
+///
+/// 'offset' is the position in the class file where the error is found,
+/// 'context' is the structures being parsed, the outermost first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub context: Vec<Context>,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Context {
+    /// the entry #n
+    ConstantPool(u16),
+    Interfaces,
+    /// by the name, or "#n" if the name index is invalid
+    Field(String),
+    Method(String),
+    Attribute(String),
+    RecordComponent(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// the class file, or an attribute, ends before the structure
+    Truncated,
+    BadMagic(u32),
+    UnknownConstantTag(u8),
+    /// the index is out of the constant pool, or of a wrong type
+    BadConstantIndex(u16),
+    /// the length of an attribute differs from the bytes of its content
+    BadAttributeLength {
+        declared: u32,
+        actual: u32,
+    },
+    /// the bytes left after the class file
+    ExtraBytes(usize),
+    /// rejected by the nom parser, a bad tag or frame type...
+    Malformed(nom::error::ErrorKind),
+}
+
+impl ParseError {
+    pub fn new(offset: usize, context: Vec<Context>, kind: ErrorKind) -> Self {
+        Self {
+            offset,
+            context,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Context::ConstantPool(i) => write!(f, "constant pool entry #{}", i),
+            Context::Interfaces => write!(f, "interfaces"),
+            Context::Field(name) => write!(f, "field `{}`", name),
+            Context::Method(name) => write!(f, "method `{}`", name),
+            Context::Attribute(name) => write!(f, "attribute `{}`", name),
+            Context::RecordComponent(name) => write!(f, "record component `{}`", name),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Truncated => write!(f, "Truncated class file"),
+            ErrorKind::BadMagic(v) => write!(f, "Incompatible magic value {:#x}", v),
+            ErrorKind::UnknownConstantTag(tag) => write!(f, "Unknown constant tag {}", tag),
+            ErrorKind::BadConstantIndex(i) => write!(f, "Invalid constant pool index {}", i),
+            ErrorKind::BadAttributeLength { declared, actual } => write!(
+                f,
+                "Wrong attribute length {}, the content is {} bytes",
+                declared, actual
+            ),
+            ErrorKind::ExtraBytes(n) => write!(f, "Extra {} bytes at the end of class file", n),
+            ErrorKind::Malformed(kind) => write!(f, "Malformed class file ({:?})", kind),
+        }
+    }
+}
+
+//"method `main`, attribute `Code`: Truncated class file at offset 312"
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, it) in self.context.iter().enumerate() {
+            let sep = if i + 1 == self.context.len() {
+                ": "
+            } else {
+                ", "
+            };
+            write!(f, "{}{}", it, sep)?;
+        }
+        write!(f, "{} at offset {}", self.kind, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -1,8 +1,9 @@
 #![allow(unused)]
 
 mod class;
+mod error;
 mod signature;
 
 pub use class::parse as parse_class;
+pub use error::{Context, ErrorKind, ParseError};
 pub use signature::{ClassSignature, FieldSignature, MethodSignature};
-
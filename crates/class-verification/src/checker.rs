@@ -1,7 +1,15 @@
-use crate::types::ConstantPool;
+use crate::cp;
+use crate::descriptor::{self, is_valid_class_name, is_valid_unqualified_name};
+use classfile::consts::{J_OBJECT, MAX_METHOD_PARAMS_NUM, METHOD_NAME_CLINIT, METHOD_NAME_INIT};
+use classfile::flags::*;
+use classfile::{
+    AttributeType, ClassFile, ConstantPool, ConstantPoolType, FieldInfo, MethodInfo, U2,
+};
+use std::collections::HashSet;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Err {
+    InvalidCpTag,
     InvalidCpClassNameIdx,
     InvalidCpFieldRefClsIdx,
     InvalidCpFieldRefNameAndTypeIdx,
@@ -17,12 +25,25 @@ pub enum Err {
     InvalidCpMethodTypeDescIdx,
     InvalidCpInvokeDynBootstrapMethodAttrIdx,
     InvalidCpInvokeDynNameAndTypeIdx,
+    InvalidCpDynamicNameAndTypeIdx,
+    InvalidCpModuleNameIdx,
+    InvalidCpPackageNameIdx,
+    InvalidClassAccFlags,
+    InvalidThisClassIdx,
+    InvalidSuperClassIdx,
+    InvalidInterfaceIdx,
     InvalidFieldAccFlags,
     InvalidFieldNameIdx,
     InvalidFieldDescIdx,
+    DuplicateField,
     InvalidMethodAccFlags,
     InvalidMethodNameIdx,
     InvalidMethodDescIdx,
+    DuplicateMethod,
+    MissingCode,
+    UnexpectedCode,
+    InvalidCodeLength,
+    InvalidMaxLocals,
 }
 
 pub type CheckResult = Result<(), Err>;
@@ -30,3 +51,396 @@ pub type CheckResult = Result<(), Err>;
 pub trait Checker {
     fn check(&self, cp: &ConstantPool) -> CheckResult;
 }
+
+impl Checker for ClassFile {
+    fn check(&self, cp: &ConstantPool) -> CheckResult {
+        for it in cp.iter() {
+            it.check(cp)?;
+        }
+        check_bootstrap_methods(self, cp)?;
+
+        let is_interface = self.acc_flags & ACC_INTERFACE != 0;
+        check_class_flags(self.acc_flags)?;
+
+        let this_class =
+            cp::get_class_name(cp, self.this_class as usize).ok_or(Err::InvalidThisClassIdx)?;
+        if this_class.first() == Some(&b'[') {
+            return Err(Err::InvalidThisClassIdx);
+        }
+        if self.super_class == 0 {
+            if this_class.as_slice() != J_OBJECT {
+                return Err(Err::InvalidSuperClassIdx);
+            }
+        } else {
+            let super_class = cp::get_class_name(cp, self.super_class as usize)
+                .ok_or(Err::InvalidSuperClassIdx)?;
+            if super_class.first() == Some(&b'[')
+                || (is_interface && super_class.as_slice() != J_OBJECT)
+            {
+                return Err(Err::InvalidSuperClassIdx);
+            }
+        }
+        for it in self.interfaces.iter() {
+            match cp::get_class_name(cp, *it as usize) {
+                Some(name) if name.first() != Some(&b'[') => (),
+                _ => return Err(Err::InvalidInterfaceIdx),
+            }
+        }
+
+        let mut fields = HashSet::new();
+        for it in self.fields.iter() {
+            it.check(cp)?;
+            let required = ACC_PUBLIC | ACC_STATIC | ACC_FINAL;
+            if is_interface && it.acc_flags & required != required {
+                return Err(Err::InvalidFieldAccFlags);
+            }
+            if !fields.insert((it.name_index, it.desc_index)) {
+                return Err(Err::DuplicateField);
+            }
+        }
+
+        let mut methods = HashSet::new();
+        for it in self.methods.iter() {
+            it.check(cp)?;
+            if is_interface {
+                check_interface_method_flags(self, it)?;
+            }
+            if !methods.insert((it.name_index, it.desc_index)) {
+                return Err(Err::DuplicateMethod);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Checker for ConstantPoolType {
+    fn check(&self, cp: &ConstantPool) -> CheckResult {
+        match self {
+            ConstantPoolType::Nop
+            | ConstantPoolType::Integer { .. }
+            | ConstantPoolType::Float { .. }
+            | ConstantPoolType::Long { .. }
+            | ConstantPoolType::Double { .. }
+            | ConstantPoolType::Utf8 { .. } => Ok(()),
+            ConstantPoolType::Class { name_index } => {
+                match cp::get_utf8(cp, *name_index as usize) {
+                    Some(name) if is_valid_class_name(name.as_slice()) => Ok(()),
+                    _ => Err(Err::InvalidCpClassNameIdx),
+                }
+            }
+            ConstantPoolType::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                check_class_idx(cp, *class_index, Err::InvalidCpFieldRefClsIdx)?;
+                match cp::get_name_and_type(cp, *name_and_type_index as usize) {
+                    Some((name, desc))
+                        if is_valid_unqualified_name(name.as_slice())
+                            && descriptor::parse_field(desc.as_slice()).is_some() =>
+                    {
+                        Ok(())
+                    }
+                    _ => Err(Err::InvalidCpFieldRefNameAndTypeIdx),
+                }
+            }
+            ConstantPoolType::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                check_class_idx(cp, *class_index, Err::InvalidCpMethodRefClsIdx)?;
+                check_method_name_and_type(
+                    cp,
+                    *name_and_type_index,
+                    Err::InvalidCpMethodRefNameAndTypeIdx,
+                )
+            }
+            ConstantPoolType::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                check_class_idx(cp, *class_index, Err::InvalidCpInterfaceMethodRefClsIdx)?;
+                check_method_name_and_type(
+                    cp,
+                    *name_and_type_index,
+                    Err::InvalidCpInterfaceMethodRefNameAndTypeIdx,
+                )
+            }
+            ConstantPoolType::String { string_index } => {
+                match cp::get_utf8(cp, *string_index as usize) {
+                    Some(_) => Ok(()),
+                    None => Err(Err::InvalidCpStrStrIdx),
+                }
+            }
+            ConstantPoolType::NameAndType {
+                name_index,
+                desc_index,
+            } => {
+                if cp::get_utf8(cp, *name_index as usize).is_none() {
+                    return Err(Err::InvalidCpNameAndTypeNameIdx);
+                }
+                if cp::get_utf8(cp, *desc_index as usize).is_none() {
+                    return Err(Err::InvalidCpNameAndTypeDescIdx);
+                }
+                Ok(())
+            }
+            ConstantPoolType::MethodHandle {
+                ref_kind,
+                ref_index,
+            } => {
+                let item = cp.get(*ref_index as usize);
+                let valid = match ref_kind {
+                    //getField, getStatic, putField, putStatic
+                    1..=4 => matches!(item, Some(ConstantPoolType::FieldRef { .. })),
+                    //invokeVirtual, newInvokeSpecial
+                    5 | 8 => matches!(item, Some(ConstantPoolType::MethodRef { .. })),
+                    //invokeStatic, invokeSpecial
+                    6 | 7 => matches!(
+                        item,
+                        Some(ConstantPoolType::MethodRef { .. })
+                            | Some(ConstantPoolType::InterfaceMethodRef { .. })
+                    ),
+                    //invokeInterface
+                    9 => matches!(item, Some(ConstantPoolType::InterfaceMethodRef { .. })),
+                    _ => return Err(Err::InvalidCpMethodHandleRefKind),
+                };
+                if !valid {
+                    return Err(Err::InvalidCpMethodHandleRefIdx);
+                }
+
+                let name = cp::get_member_ref(cp, *ref_index as usize)
+                    .map(|(_, name, _)| name.as_slice())
+                    .ok_or(Err::InvalidCpMethodHandleRefIdx)?;
+                let is_init = name == METHOD_NAME_INIT;
+                if (*ref_kind == 8) != is_init || name == METHOD_NAME_CLINIT {
+                    return Err(Err::InvalidCpMethodHandleRefIdx);
+                }
+                Ok(())
+            }
+            ConstantPoolType::MethodType { desc_index } => {
+                match cp::get_utf8(cp, *desc_index as usize) {
+                    Some(desc) if descriptor::parse_method(desc.as_slice()).is_some() => Ok(()),
+                    _ => Err(Err::InvalidCpMethodTypeDescIdx),
+                }
+            }
+            ConstantPoolType::InvokeDynamic {
+                name_and_type_index,
+                ..
+            } => match cp::get_name_and_type(cp, *name_and_type_index as usize) {
+                Some((name, desc))
+                    if is_valid_unqualified_name(name.as_slice())
+                        && descriptor::parse_method(desc.as_slice()).is_some() =>
+                {
+                    Ok(())
+                }
+                _ => Err(Err::InvalidCpInvokeDynNameAndTypeIdx),
+            },
+            ConstantPoolType::Dynamic {
+                name_and_type_index,
+                ..
+            } => match cp::get_name_and_type(cp, *name_and_type_index as usize) {
+                Some((name, desc))
+                    if is_valid_unqualified_name(name.as_slice())
+                        && descriptor::parse_field(desc.as_slice()).is_some() =>
+                {
+                    Ok(())
+                }
+                _ => Err(Err::InvalidCpDynamicNameAndTypeIdx),
+            },
+            ConstantPoolType::Module { name_index } => {
+                match cp::get_utf8(cp, *name_index as usize) {
+                    Some(_) => Ok(()),
+                    None => Err(Err::InvalidCpModuleNameIdx),
+                }
+            }
+            ConstantPoolType::Package { name_index } => {
+                match cp::get_utf8(cp, *name_index as usize) {
+                    Some(_) => Ok(()),
+                    None => Err(Err::InvalidCpPackageNameIdx),
+                }
+            }
+            ConstantPoolType::Unknown => Err(Err::InvalidCpTag),
+        }
+    }
+}
+
+impl Checker for FieldInfo {
+    fn check(&self, cp: &ConstantPool) -> CheckResult {
+        let flags = self.acc_flags;
+        if !is_one_access_flag(flags) || (flags & ACC_FINAL != 0 && flags & ACC_VOLATILE != 0) {
+            return Err(Err::InvalidFieldAccFlags);
+        }
+
+        match cp::get_utf8(cp, self.name_index as usize) {
+            Some(name) if is_valid_unqualified_name(name.as_slice()) => (),
+            _ => return Err(Err::InvalidFieldNameIdx),
+        }
+
+        match cp::get_utf8(cp, self.desc_index as usize) {
+            Some(desc) if descriptor::parse_field(desc.as_slice()).is_some() => Ok(()),
+            _ => Err(Err::InvalidFieldDescIdx),
+        }
+    }
+}
+
+impl Checker for MethodInfo {
+    fn check(&self, cp: &ConstantPool) -> CheckResult {
+        let flags = self.acc_flags;
+        if !is_one_access_flag(flags) {
+            return Err(Err::InvalidMethodAccFlags);
+        }
+        let is_abstract = flags & ACC_ABSTRACT != 0;
+        let is_native = flags & ACC_NATIVE != 0;
+        if is_abstract
+            && flags & (ACC_PRIVATE | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0
+        {
+            return Err(Err::InvalidMethodAccFlags);
+        }
+
+        let name = match cp::get_utf8(cp, self.name_index as usize) {
+            Some(name) if is_valid_method_name(name.as_slice()) => name,
+            _ => return Err(Err::InvalidMethodNameIdx),
+        };
+        let (params, ret) = cp::get_utf8(cp, self.desc_index as usize)
+            .and_then(|desc| descriptor::parse_method(desc.as_slice()))
+            .ok_or(Err::InvalidMethodDescIdx)?;
+
+        let mut params_size = descriptor::params_size(&params);
+        if flags & ACC_STATIC == 0 {
+            params_size += 1;
+        }
+        if params_size > MAX_METHOD_PARAMS_NUM as usize {
+            return Err(Err::InvalidMethodDescIdx);
+        }
+        if name.as_slice() == METHOD_NAME_INIT {
+            if ret.is_some() || flags & (ACC_STATIC | ACC_FINAL | ACC_ABSTRACT) != 0 {
+                return Err(Err::InvalidMethodDescIdx);
+            }
+        } else if name.as_slice() == METHOD_NAME_CLINIT && ret.is_some() {
+            return Err(Err::InvalidMethodDescIdx);
+        }
+
+        let code = self.attrs.iter().find_map(|it| match it {
+            AttributeType::Code(code) => Some(code),
+            _ => None,
+        });
+        match code {
+            Some(_) if is_abstract || is_native => Err(Err::UnexpectedCode),
+            None if !is_abstract && !is_native => Err(Err::MissingCode),
+            None => Ok(()),
+            Some(code) => {
+                if code.code.is_empty() || code.code.len() > u16::MAX as usize {
+                    return Err(Err::InvalidCodeLength);
+                }
+                if params_size > code.max_locals as usize {
+                    return Err(Err::InvalidMaxLocals);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn check_class_flags(flags: U2) -> CheckResult {
+    let is_interface = flags & ACC_INTERFACE != 0;
+    let is_abstract = flags & ACC_ABSTRACT != 0;
+    let is_final = flags & ACC_FINAL != 0;
+
+    if is_interface && (!is_abstract || is_final || flags & ACC_ENUM != 0) {
+        return Err(Err::InvalidClassAccFlags);
+    }
+    if flags & ACC_ANNOTATION != 0 && !is_interface {
+        return Err(Err::InvalidClassAccFlags);
+    }
+    if is_abstract && is_final {
+        return Err(Err::InvalidClassAccFlags);
+    }
+
+    Ok(())
+}
+
+fn check_bootstrap_methods(cf: &ClassFile, cp: &ConstantPool) -> CheckResult {
+    let methods = cf.attrs.iter().find_map(|it| match it {
+        AttributeType::BootstrapMethods { methods, .. } => Some(methods),
+        _ => None,
+    });
+    let n = methods.map_or(0, |it| it.len());
+
+    for it in methods.iter().flat_map(|it| it.iter()) {
+        match cp.get(it.method_ref as usize) {
+            Some(ConstantPoolType::MethodHandle { .. }) => (),
+            _ => return Err(Err::InvalidCpInvokeDynBootstrapMethodAttrIdx),
+        }
+    }
+
+    for it in cp.iter() {
+        match it {
+            ConstantPoolType::Dynamic {
+                bootstrap_method_attr_index,
+                ..
+            }
+            | ConstantPoolType::InvokeDynamic {
+                bootstrap_method_attr_index,
+                ..
+            } if *bootstrap_method_attr_index as usize >= n => {
+                return Err(Err::InvalidCpInvokeDynBootstrapMethodAttrIdx);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn check_interface_method_flags(cf: &ClassFile, method: &MethodInfo) -> CheckResult {
+    let flags = method.acc_flags;
+    let name = cp::get_utf8(&cf.cp, method.name_index as usize);
+    let is_clinit = name.map(|it| it.as_slice()) == Some(METHOD_NAME_CLINIT);
+    if is_clinit {
+        return Ok(());
+    }
+
+    //default & static interface methods since Java 8
+    let invalid = if cf.version.major >= 52 {
+        ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE
+    } else {
+        ACC_PRIVATE | ACC_PROTECTED | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE
+    };
+    if flags & invalid != 0 || flags & (ACC_PUBLIC | ACC_PRIVATE) == 0 {
+        return Err(Err::InvalidMethodAccFlags);
+    }
+
+    Ok(())
+}
+
+fn check_class_idx(cp: &ConstantPool, idx: U2, err: Err) -> CheckResult {
+    match cp.get(idx as usize) {
+        Some(ConstantPoolType::Class { .. }) => Ok(()),
+        _ => Err(err),
+    }
+}
+
+fn check_method_name_and_type(cp: &ConstantPool, idx: U2, err: Err) -> CheckResult {
+    let (name, desc) = cp::get_name_and_type(cp, idx as usize).ok_or(err)?;
+    let (_, ret) = descriptor::parse_method(desc.as_slice()).ok_or(err)?;
+    let name = name.as_slice();
+    if name == METHOD_NAME_CLINIT || !is_valid_method_name(name) {
+        return Err(err);
+    }
+    if name == METHOD_NAME_INIT && ret.is_some() {
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+fn is_valid_method_name(name: &[u8]) -> bool {
+    name == METHOD_NAME_INIT
+        || name == METHOD_NAME_CLINIT
+        || (is_valid_unqualified_name(name) && !name.iter().any(|c| *c == b'<' || *c == b'>'))
+}
+
+//at most one of public, private & protected
+fn is_one_access_flag(flags: U2) -> bool {
+    (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() <= 1
+}
@@ -0,0 +1,56 @@
+//! Checked accessors of the constant pool
+//!
+//! Unlike classfile::constant_pool, return None instead of panic,
+//! the indices come from an unverified class file.
+
+use classfile::{BytesRef, ConstantPool, ConstantPoolType};
+
+pub fn get_utf8(cp: &ConstantPool, idx: usize) -> Option<&BytesRef> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::Utf8 { bytes }) => Some(bytes),
+        _ => None,
+    }
+}
+
+pub fn get_class_name(cp: &ConstantPool, idx: usize) -> Option<&BytesRef> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::Class { name_index }) => get_utf8(cp, *name_index as usize),
+        _ => None,
+    }
+}
+
+pub fn get_name_and_type(cp: &ConstantPool, idx: usize) -> Option<(&BytesRef, &BytesRef)> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::NameAndType {
+            name_index,
+            desc_index,
+        }) => Some((
+            get_utf8(cp, *name_index as usize)?,
+            get_utf8(cp, *desc_index as usize)?,
+        )),
+        _ => None,
+    }
+}
+
+/// Field, method & interface method refs, returns (class name, name, desc)
+pub fn get_member_ref(cp: &ConstantPool, idx: usize) -> Option<(&BytesRef, &BytesRef, &BytesRef)> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::FieldRef {
+            class_index,
+            name_and_type_index,
+        })
+        | Some(ConstantPoolType::MethodRef {
+            class_index,
+            name_and_type_index,
+        })
+        | Some(ConstantPoolType::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        }) => {
+            let class_name = get_class_name(cp, *class_index as usize)?;
+            let (name, desc) = get_name_and_type(cp, *name_and_type_index as usize)?;
+            Some((class_name, name, desc))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,141 @@
+use crate::types::VType;
+use classfile::consts::MAX_ARRAY_DIMENSIONS;
+
+/// Parse a field descriptor, JVMS 4.3.2
+///
+/// Returns None if 'desc' is not a valid field descriptor.
+pub fn parse_field(desc: &[u8]) -> Option<VType> {
+    let mut pos = 0;
+    let t = parse_one(desc, &mut pos)?;
+    if pos == desc.len() {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Parse a method descriptor, JVMS 4.3.3
+///
+/// Returns the parameter types and the return type, None for 'void'.
+pub fn parse_method(desc: &[u8]) -> Option<(Vec<VType>, Option<VType>)> {
+    if desc.first() != Some(&b'(') {
+        return None;
+    }
+
+    let mut pos = 1;
+    let mut params = Vec::new();
+    loop {
+        match desc.get(pos) {
+            Some(b')') => {
+                pos += 1;
+                break;
+            }
+            Some(_) => params.push(parse_one(desc, &mut pos)?),
+            None => return None,
+        }
+    }
+
+    if desc.get(pos) == Some(&b'V') && pos + 1 == desc.len() {
+        return Some((params, None));
+    }
+
+    let ret = parse_one(desc, &mut pos)?;
+    if pos == desc.len() {
+        Some((params, Some(ret)))
+    } else {
+        None
+    }
+}
+
+/// The number of local slots taken by the parameters
+pub fn params_size(params: &[VType]) -> usize {
+    params.iter().map(|it| it.size()).sum()
+}
+
+/// Unqualified names of fields & methods, JVMS 4.2.2
+pub fn is_valid_unqualified_name(name: &[u8]) -> bool {
+    !name.is_empty() && !name.iter().any(|c| matches!(c, b'.' | b';' | b'[' | b'/'))
+}
+
+/// Binary class names in internal form, JVMS 4.2.1
+pub fn is_valid_class_name(name: &[u8]) -> bool {
+    if name.first() == Some(&b'[') {
+        return parse_field(name).is_some();
+    }
+
+    !name.is_empty() && name.split(|c| *c == b'/').all(is_valid_unqualified_name)
+}
+
+fn parse_one(desc: &[u8], pos: &mut usize) -> Option<VType> {
+    let start = *pos;
+    while desc.get(*pos) == Some(&b'[') {
+        *pos += 1;
+    }
+    let dimension = *pos - start;
+    if dimension > MAX_ARRAY_DIMENSIONS as usize {
+        return None;
+    }
+
+    let t = match desc.get(*pos)? {
+        b'B' | b'C' | b'I' | b'S' | b'Z' => VType::Int,
+        b'F' => VType::Float,
+        b'J' => VType::Long,
+        b'D' => VType::Double,
+        b'L' => {
+            let len = desc[*pos..].iter().position(|c| *c == b';')?;
+            let name = &desc[*pos + 1..*pos + len];
+            if !is_valid_class_name(name) || name.first() == Some(&b'[') {
+                return None;
+            }
+            *pos += len;
+            VType::object(name)
+        }
+        _ => return None,
+    };
+    *pos += 1;
+
+    if dimension == 0 {
+        Some(t)
+    } else {
+        Some(VType::object(&desc[start..*pos]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_parse() {
+        assert_eq!(parse_field(b"I"), Some(VType::Int));
+        assert_eq!(parse_field(b"Z"), Some(VType::Int));
+        assert_eq!(parse_field(b"J"), Some(VType::Long));
+        assert_eq!(
+            parse_field(b"Ljava/lang/String;"),
+            Some(VType::object(b"java/lang/String"))
+        );
+        assert_eq!(parse_field(b"[[I"), Some(VType::object(b"[[I")));
+        assert_eq!(parse_field(b"V"), None);
+        assert_eq!(parse_field(b"II"), None);
+        assert_eq!(parse_field(b"Ljava/lang/String"), None);
+        assert_eq!(parse_field(b"L;"), None);
+        assert_eq!(parse_field(b"Ljava.lang.String;"), None);
+
+        assert_eq!(parse_method(b"()V"), Some((vec![], None)));
+        assert_eq!(
+            parse_method(b"(IJ[Ljava/lang/Object;)D"),
+            Some((
+                vec![
+                    VType::Int,
+                    VType::Long,
+                    VType::object(b"[Ljava/lang/Object;")
+                ],
+                Some(VType::Double)
+            ))
+        );
+        assert_eq!(parse_method(b"(V)V"), None);
+        assert_eq!(parse_method(b"()"), None);
+        assert_eq!(parse_method(b"()VV"), None);
+        assert_eq!(parse_method(b"I"), None);
+    }
+}
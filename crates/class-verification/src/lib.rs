@@ -0,0 +1,181 @@
+//! Verification of class files, JVMS 4.8 & 4.10
+//!
+//! The format checks of the constant pool, fields & methods,
+//! then the method bodies are verified by type checking with
+//! the StackMapTable attribute.
+//!
+//! Verification by type inference is not supported, the class files
+//! older than 51 get the format checks & the structural checks of the
+//! code only, as the failover of HotSpot without the old verifier.
+
+mod checker;
+mod cp;
+pub mod descriptor;
+mod stack_map;
+pub mod types;
+mod verifier;
+
+pub use checker::{CheckResult, Checker, Err as CheckErr};
+pub use types::Constraint;
+pub use verifier::VerifyErr;
+
+use classfile::ClassFile;
+use std::fmt;
+
+/// Class files of version >= 51 must be verified by type checking
+const TYPE_CHECK_MAJOR_VERSION: u16 = 51;
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    //java.lang.ClassFormatError
+    Format(CheckErr),
+    //java.lang.VerifyError
+    Verify(VerifyErr),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Format(e) => write!(f, "{:?}", e),
+            Error::Verify(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Verify the class file, the subtyping assumed is returned to be checked
+/// when the class is linked
+pub fn verify(cf: &ClassFile) -> Result<Vec<Constraint>, Error> {
+    cf.check(&cf.cp).map_err(Error::Format)?;
+
+    //no StackMapTable before 51, the types aren't checked
+    let type_check = cf.version.major >= TYPE_CHECK_MAJOR_VERSION;
+
+    let mut constraints: Vec<Constraint> = vec![];
+    for method in cf.methods.iter() {
+        if let Some(code) = method.get_code() {
+            let found =
+                verifier::verify_method(cf, method, &code, type_check).map_err(Error::Verify)?;
+            for it in found {
+                if !constraints.contains(&it) {
+                    constraints.push(it);
+                }
+            }
+        }
+    }
+
+    Ok(constraints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use classfile::attributes::Code;
+    use classfile::flags::*;
+    use classfile::{AttributeType, ConstantPoolType, MethodInfo, Version};
+    use std::sync::Arc;
+
+    fn new_class(code: Vec<u8>, max_stack: u16) -> ClassFile {
+        let utf8 = |s: &str| ConstantPoolType::Utf8 {
+            bytes: Arc::new(Vec::from(s.as_bytes())),
+        };
+        let cp = vec![
+            ConstantPoolType::Nop,
+            utf8("T"),
+            ConstantPoolType::Class { name_index: 1 },
+            utf8("java/lang/Object"),
+            ConstantPoolType::Class { name_index: 3 },
+            utf8("f"),
+            utf8("()I"),
+        ];
+        let code = Code {
+            max_stack,
+            max_locals: 0,
+            code: Arc::new(code),
+            exceptions: vec![],
+            attrs: vec![],
+        };
+        let method = MethodInfo {
+            acc_flags: ACC_STATIC,
+            name_index: 5,
+            desc_index: 6,
+            attrs: vec![AttributeType::Code(code)],
+        };
+
+        ClassFile {
+            version: Version {
+                minor: 0,
+                major: 52,
+            },
+            cp: Arc::new(cp),
+            acc_flags: ACC_PUBLIC | ACC_SUPER,
+            this_class: 2,
+            super_class: 4,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![method],
+            attrs: vec![],
+        }
+    }
+
+    fn verify_code(code: Vec<u8>, max_stack: u16) -> Result<Vec<Constraint>, Error> {
+        verify(&new_class(code, max_stack))
+    }
+
+    #[test]
+    fn t_verify() {
+        //iconst_1, ireturn
+        assert!(verify_code(vec![4, 172], 1).is_ok());
+        //fconst_0, ireturn
+        assert!(matches!(
+            verify_code(vec![11, 172], 1),
+            Err(Error::Verify(_))
+        ));
+        //iconst_1, iconst_1, iadd, ireturn, with max_stack 1
+        assert!(matches!(
+            verify_code(vec![4, 4, 96, 172], 1),
+            Err(Error::Verify(_))
+        ));
+        //ireturn
+        assert!(matches!(verify_code(vec![172], 1), Err(Error::Verify(_))));
+        //iconst_1
+        assert!(matches!(verify_code(vec![4], 1), Err(Error::Verify(_))));
+        //iconst_1, goto 0, no stack map frame
+        assert!(matches!(
+            verify_code(vec![4, 167, 0xff, 0xff], 1),
+            Err(Error::Verify(_))
+        ));
+        //iconst_1, goto out of code
+        assert!(matches!(
+            verify_code(vec![4, 167, 0, 9], 1),
+            Err(Error::Verify(_))
+        ));
+        //iload_0, out of max_locals
+        assert!(matches!(
+            verify_code(vec![26, 172], 1),
+            Err(Error::Verify(_))
+        ));
+        //bad opcode
+        assert!(matches!(verify_code(vec![0xfe], 1), Err(Error::Verify(_))));
+        //empty code
+        assert!(matches!(
+            verify_code(vec![], 1),
+            Err(Error::Format(CheckErr::InvalidCodeLength))
+        ));
+
+        //no type inference for the class files without StackMapTable,
+        //the structure is checked still
+        let mut cf = new_class(vec![11, 172], 1);
+        cf.version.major = 50;
+        assert!(verify(&cf).is_ok());
+        let mut cf = new_class(vec![4, 167, 0, 9], 1);
+        cf.version.major = 50;
+        assert!(matches!(verify(&cf), Err(Error::Verify(_))));
+
+        let mut cf = new_class(vec![4, 172], 1);
+        cf.super_class = 0;
+        assert!(matches!(
+            verify(&cf),
+            Err(Error::Format(CheckErr::InvalidSuperClassIdx))
+        ));
+    }
+}
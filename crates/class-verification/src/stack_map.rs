@@ -0,0 +1,128 @@
+use crate::cp;
+use crate::types::{Frame, VType};
+use classfile::attributes::{StackMapFrame, VerificationTypeInfo};
+use classfile::ConstantPool;
+
+/// Expand the StackMapTable attribute to the full frames, JVMS 4.7.4
+///
+/// 'initial' is the locals implied by the method descriptor, in the compact
+/// form of the table (Long & Double take one entry).
+/// Returns the frames with their offsets, or the reason of the failure.
+pub fn expand(
+    cp: &ConstantPool,
+    entries: &[StackMapFrame],
+    initial: &[VType],
+    max_locals: usize,
+) -> Result<Vec<(usize, Frame)>, &'static str> {
+    let mut frames = Vec::with_capacity(entries.len());
+    let mut locals = Vec::from(initial);
+    let mut offset = None;
+
+    for entry in entries.iter() {
+        let (delta, stack) = match entry {
+            StackMapFrame::Same { offset_delta, .. }
+            | StackMapFrame::SameExtended { offset_delta, .. } => (*offset_delta, vec![]),
+            StackMapFrame::SameLocals1StackItem {
+                offset_delta,
+                stack,
+                ..
+            }
+            | StackMapFrame::SameLocals1StackItemExtended {
+                offset_delta,
+                stack,
+                ..
+            } => (*offset_delta, vec![to_vtype(cp, &stack[0])?]),
+            StackMapFrame::Chop {
+                tag, offset_delta, ..
+            } => {
+                let k = 251 - *tag as usize;
+                if k > locals.len() {
+                    return Err("Chopping too many locals");
+                }
+                locals.truncate(locals.len() - k);
+                (*offset_delta, vec![])
+            }
+            StackMapFrame::Append {
+                offset_delta,
+                locals: appended,
+                ..
+            } => {
+                for it in appended.iter() {
+                    locals.push(to_vtype(cp, it)?);
+                }
+                (*offset_delta, vec![])
+            }
+            StackMapFrame::Full {
+                offset_delta,
+                locals: full,
+                stack,
+                ..
+            } => {
+                locals.clear();
+                for it in full.iter() {
+                    locals.push(to_vtype(cp, it)?);
+                }
+                let stack = stack
+                    .iter()
+                    .map(|it| to_vtype(cp, it))
+                    .collect::<Result<Vec<_>, _>>()?;
+                (*offset_delta, stack)
+            }
+            StackMapFrame::Reserved(_) => return Err("Invalid frame type"),
+        };
+
+        //the first frame is at 'offset_delta', the others at 'offset_delta + 1' after previous
+        let pc = match offset {
+            None => delta as usize,
+            Some(prev) => prev + delta as usize + 1,
+        };
+        offset = Some(pc);
+
+        let frame = Frame {
+            locals: expand_locals(&locals, max_locals)?,
+            stack,
+        };
+        frames.push((pc, frame));
+    }
+
+    Ok(frames)
+}
+
+/// Locals slots from the compact form, padded with Top to max_locals
+pub fn expand_locals(locals: &[VType], max_locals: usize) -> Result<Vec<VType>, &'static str> {
+    let mut slots = Vec::with_capacity(max_locals);
+    for it in locals.iter() {
+        slots.push(it.clone());
+        if it.is_category2() {
+            slots.push(VType::Top);
+        }
+    }
+
+    if slots.len() > max_locals {
+        return Err("Local variable table overflow");
+    }
+    slots.resize(max_locals, VType::Top);
+
+    Ok(slots)
+}
+
+fn to_vtype(cp: &ConstantPool, it: &VerificationTypeInfo) -> Result<VType, &'static str> {
+    let t = match it {
+        VerificationTypeInfo::Top => VType::Top,
+        VerificationTypeInfo::Integer => VType::Int,
+        VerificationTypeInfo::Float => VType::Float,
+        VerificationTypeInfo::Long => VType::Long,
+        VerificationTypeInfo::Double => VType::Double,
+        VerificationTypeInfo::Null => VType::Null,
+        VerificationTypeInfo::UninitializedThis => VType::UninitializedThis,
+        VerificationTypeInfo::Object { cpool_index } => {
+            match cp::get_class_name(cp, *cpool_index as usize) {
+                Some(name) => VType::Object(name.clone()),
+                None => return Err("Invalid constant pool index in stack map"),
+            }
+        }
+        VerificationTypeInfo::Uninitialized { offset } => VType::Uninitialized(*offset),
+    };
+
+    Ok(t)
+}
@@ -0,0 +1,254 @@
+use classfile::consts::{J_CLONEABLE, J_OBJECT, J_SERIALIZABLE};
+use classfile::BytesRef;
+use std::fmt;
+use std::sync::Arc;
+
+/// Verification types, JVMS 4.10.1.2
+///
+/// Long & Double take two local slots, the second one is Top.
+/// On the operand stack they take one entry, but count two for max_stack.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VType {
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    //offset of the 'new' instruction
+    Uninitialized(u16),
+    //class name, or array descriptor for arrays
+    Object(BytesRef),
+}
+
+impl VType {
+    pub fn object(name: &[u8]) -> Self {
+        VType::Object(Arc::new(Vec::from(name)))
+    }
+
+    pub fn is_category2(&self) -> bool {
+        matches!(self, VType::Long | VType::Double)
+    }
+
+    pub fn size(&self) -> usize {
+        if self.is_category2() {
+            2
+        } else {
+            1
+        }
+    }
+
+    //initialized reference, include null
+    pub fn is_reference(&self) -> bool {
+        matches!(self, VType::Null | VType::Object(_))
+    }
+
+    pub fn is_uninitialized(&self) -> bool {
+        matches!(self, VType::UninitializedThis | VType::Uninitialized(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        match self {
+            VType::Object(name) => name.first() == Some(&b'['),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VType::Top => write!(f, "top"),
+            VType::Int => write!(f, "integer"),
+            VType::Float => write!(f, "float"),
+            VType::Long => write!(f, "long"),
+            VType::Double => write!(f, "double"),
+            VType::Null => write!(f, "null"),
+            VType::UninitializedThis => write!(f, "uninitializedThis"),
+            VType::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+            VType::Object(name) => write!(f, "'{}'", String::from_utf8_lossy(name.as_slice())),
+        }
+    }
+}
+
+/// The assumption of the verifier that class 'from' is assignable to class 'to'
+///
+/// The verifier runs before the class (and its super classes) is linked, it
+/// can't load other classes, so the subtyping between two class types is
+/// recorded and checked by the runtime when the class is linked, by the
+/// defining loader of the class, as isJavaAssignable of JVMS 4.10.1.2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub from: BytesRef,
+    pub to: BytesRef,
+}
+
+/*
+Primitives, arrays & uninitialized objects are checked strictly, the class
+types are assignable if the constraint added to 'constraints' holds.
+*/
+pub fn is_assignable(from: &VType, to: &VType, constraints: &mut Vec<Constraint>) -> bool {
+    match (from, to) {
+        (_, VType::Top) => true,
+        (VType::Int, VType::Int)
+        | (VType::Float, VType::Float)
+        | (VType::Long, VType::Long)
+        | (VType::Double, VType::Double)
+        | (VType::Null, VType::Null)
+        | (VType::Null, VType::Object(_))
+        | (VType::UninitializedThis, VType::UninitializedThis) => true,
+        (VType::Uninitialized(a), VType::Uninitialized(b)) => a == b,
+        (VType::Object(a), VType::Object(b)) => {
+            match is_ref_assignable(a.as_slice(), b.as_slice()) {
+                Assignable::Yes => true,
+                Assignable::No => false,
+                Assignable::If(from, to) => {
+                    let it = Constraint {
+                        from: Arc::new(Vec::from(from)),
+                        to: Arc::new(Vec::from(to)),
+                    };
+                    if !constraints.contains(&it) {
+                        constraints.push(it);
+                    }
+                    true
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+enum Assignable<'a> {
+    Yes,
+    No,
+    //class 'from' is a subclass of 'to', or 'to' is an interface
+    If(&'a [u8], &'a [u8]),
+}
+
+fn is_ref_assignable<'a>(from: &'a [u8], to: &'a [u8]) -> Assignable<'a> {
+    if from == to || to == J_OBJECT {
+        return Assignable::Yes;
+    }
+
+    match (from.first(), to.first()) {
+        (Some(b'['), Some(b'[')) => {
+            let from = &from[1..];
+            let to = &to[1..];
+            match (component_name(from), component_name(to)) {
+                (Some(from), Some(to)) => is_ref_assignable(from, to),
+                //primitive component
+                _ if from == to => Assignable::Yes,
+                _ => Assignable::No,
+            }
+        }
+        (Some(b'['), _) if to == J_CLONEABLE || to == J_SERIALIZABLE => Assignable::Yes,
+        (Some(b'['), _) | (_, Some(b'[')) => Assignable::No,
+        _ => Assignable::If(from, to),
+    }
+}
+
+//"Ljava/lang/String;" -> "java/lang/String", "[I" -> "[I", "I" -> None
+fn component_name(desc: &[u8]) -> Option<&[u8]> {
+    match desc.first() {
+        Some(b'L') if desc.len() > 2 => Some(&desc[1..desc.len() - 1]),
+        Some(b'[') => Some(desc),
+        _ => None,
+    }
+}
+
+//the element type of an array type, None if not an array
+pub fn component_type(array: &VType) -> Option<VType> {
+    match array {
+        VType::Object(name) if name.first() == Some(&b'[') => {
+            crate::descriptor::parse_field(&name[1..])
+        }
+        _ => None,
+    }
+}
+
+/// The type state of a method at some instruction
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub locals: Vec<VType>,
+    pub stack: Vec<VType>,
+}
+
+impl Frame {
+    //frame 'self' can flow into frame 'target'
+    pub fn is_assignable_to(&self, target: &Frame, constraints: &mut Vec<Constraint>) -> bool {
+        self.locals.len() == target.locals.len()
+            && self.stack.len() == target.stack.len()
+            && self
+                .locals
+                .iter()
+                .zip(target.locals.iter())
+                .all(|(a, b)| is_assignable(a, b, constraints))
+            && self
+                .stack
+                .iter()
+                .zip(target.stack.iter())
+                .all(|(a, b)| is_assignable(a, b, constraints))
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack.iter().map(|it| it.size()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_assignable() {
+        let obj = VType::object(b"java/lang/Object");
+        let string = VType::object(b"java/lang/String");
+        let ary_int = VType::object(b"[I");
+        let ary_long = VType::object(b"[J");
+        let ary_string = VType::object(b"[Ljava/lang/String;");
+        let ary_obj = VType::object(b"[Ljava/lang/Object;");
+        let mut c = vec![];
+
+        assert!(is_assignable(&VType::Int, &VType::Top, &mut c));
+        assert!(is_assignable(&VType::Null, &string, &mut c));
+        assert!(is_assignable(&string, &obj, &mut c));
+        assert!(is_assignable(&ary_int, &obj, &mut c));
+        assert!(is_assignable(
+            &ary_int,
+            &VType::object(b"java/lang/Cloneable"),
+            &mut c
+        ));
+        assert!(is_assignable(&ary_string, &ary_obj, &mut c));
+        assert!(!is_assignable(&ary_obj, &ary_int, &mut c));
+        assert!(!is_assignable(&ary_int, &ary_long, &mut c));
+        assert!(!is_assignable(&string, &ary_string, &mut c));
+        assert!(!is_assignable(&VType::Int, &VType::Float, &mut c));
+        assert!(!is_assignable(&VType::Int, &obj, &mut c));
+        assert!(!is_assignable(&VType::UninitializedThis, &obj, &mut c));
+        assert!(!is_assignable(
+            &VType::Uninitialized(1),
+            &VType::Uninitialized(2),
+            &mut c
+        ));
+        assert!(c.is_empty());
+
+        //the subtyping of class types is left to the runtime
+        let number = VType::object(b"java/lang/Number");
+        let ary_number = VType::object(b"[Ljava/lang/Number;");
+        assert!(is_assignable(&string, &number, &mut c));
+        assert!(is_assignable(&ary_string, &ary_number, &mut c));
+        assert!(!is_assignable(&ary_string, &ary_int, &mut c));
+        assert_eq!(
+            c,
+            vec![Constraint {
+                from: Arc::new(Vec::from(&b"java/lang/String"[..])),
+                to: Arc::new(Vec::from(&b"java/lang/Number"[..])),
+            }]
+        );
+
+        assert_eq!(component_type(&ary_string), Some(string));
+        assert_eq!(component_type(&ary_int), Some(VType::Int));
+        assert_eq!(component_type(&obj), None);
+    }
+}
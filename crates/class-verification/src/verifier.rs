@@ -0,0 +1,1193 @@
+use crate::cp;
+use crate::descriptor;
+use crate::stack_map;
+use crate::types::{component_type, is_assignable, Constraint, Frame, VType};
+use classfile::attributes::{Code, CodeException};
+use classfile::consts::{
+    J_CLASS, J_METHODHANDLE, J_METHODTYPE, J_OBJECT, J_STRING, J_THROWABLE, METHOD_NAME_INIT,
+};
+use classfile::flags::ACC_STATIC;
+use classfile::{BytesRef, ClassFile, ConstantPool, ConstantPoolType, MethodInfo};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/*
+Verification by type checking, JVMS 4.10.1
+
+The method body is checked in one linear pass, the type state at branch
+targets & exception handlers is given by the StackMapTable, and the state
+flowing into them must be assignable to it.
+
+The subtyping between class types is not checked here, it is returned as
+the constraints for the runtime.
+*/
+
+#[derive(Debug, Clone)]
+pub struct VerifyErr {
+    pub class: String,
+    pub method: String,
+    pub desc: String,
+    pub pc: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for VerifyErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "(class: {}, method: {} signature: {}) {} at offset {}",
+            self.class, self.method, self.desc, self.reason, self.pc
+        )
+    }
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+//the structure only if not 'type_check', the class files older than 51
+pub fn verify_method(
+    cf: &ClassFile,
+    method: &MethodInfo,
+    code: &Code,
+    type_check: bool,
+) -> std::result::Result<Vec<Constraint>, VerifyErr> {
+    //the format checker has validated these
+    let this_class = cp::get_class_name(&cf.cp, cf.this_class as usize).unwrap();
+    let name = cp::get_utf8(&cf.cp, method.name_index as usize).unwrap();
+    let desc = cp::get_utf8(&cf.cp, method.desc_index as usize).unwrap();
+    let (params, ret) = descriptor::parse_method(desc.as_slice()).unwrap();
+
+    let mut verifier = MethodVerifier {
+        cp: &cf.cp,
+        this_class,
+        code: code.code.as_slice(),
+        handlers: code.exceptions.as_slice(),
+        max_stack: code.max_stack as usize,
+        max_locals: code.max_locals as usize,
+        ret,
+        is_init: name.as_slice() == METHOD_NAME_INIT,
+        starts: vec![],
+        frames: BTreeMap::new(),
+        constraints: RefCell::new(vec![]),
+    };
+
+    let r = verifier.check_structure().and_then(|_| {
+        if !type_check {
+            return Ok(());
+        }
+        let is_static = method.acc_flags & ACC_STATIC != 0;
+        let entries = method.get_stack_map_table().unwrap_or_default();
+        verifier.type_check(is_static, &params, &entries)
+    });
+
+    r.map(|_| verifier.constraints.into_inner())
+        .map_err(|(pc, reason)| VerifyErr {
+            class: String::from_utf8_lossy(this_class.as_slice()).to_string(),
+            method: String::from_utf8_lossy(name.as_slice()).to_string(),
+            desc: String::from_utf8_lossy(desc.as_slice()).to_string(),
+            pc,
+            reason,
+        })
+}
+
+struct MethodVerifier<'a> {
+    cp: &'a ConstantPool,
+    this_class: &'a BytesRef,
+    code: &'a [u8],
+    handlers: &'a [CodeException],
+    max_stack: usize,
+    max_locals: usize,
+    ret: Option<VType>,
+    is_init: bool,
+
+    //instruction boundaries
+    starts: Vec<bool>,
+    //the frames of StackMapTable by offset
+    frames: BTreeMap<usize, Frame>,
+    //the subtyping assumed by the type checking
+    constraints: RefCell<Vec<Constraint>>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn check_structure(&mut self) -> std::result::Result<(), (usize, String)> {
+        let len = self.code.len();
+        self.starts = vec![false; len];
+
+        let mut pc = 0;
+        while pc < len {
+            self.starts[pc] = true;
+            let n = self.instruction_len(pc).map_err(|e| (pc, e))?;
+            if pc + n > len {
+                return Err((pc, "Instruction extends past end of code".to_string()));
+            }
+            pc += n;
+        }
+
+        pc = 0;
+        while pc < len {
+            self.check_instruction(pc).map_err(|e| (pc, e))?;
+            pc += self.instruction_len(pc).unwrap();
+        }
+
+        for it in self.handlers.iter() {
+            let start = it.start_pc as usize;
+            let end = it.end_pc as usize;
+            let handler = it.handler_pc as usize;
+            let valid = start < end
+                && self.is_start(start)
+                && (end == len || self.is_start(end))
+                && self.is_start(handler);
+            if !valid {
+                return Err((handler, "Illegal exception table range".to_string()));
+            }
+            if it.catch_type != 0 && cp::get_class_name(self.cp, it.catch_type as usize).is_none() {
+                return Err((handler, "Catch type is not a class".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn type_check(
+        &mut self,
+        is_static: bool,
+        params: &[VType],
+        entries: &[classfile::attributes::StackMapFrame],
+    ) -> std::result::Result<(), (usize, String)> {
+        let mut initial = Vec::with_capacity(params.len() + 1);
+        if !is_static {
+            if self.is_init && self.this_class.as_slice() != J_OBJECT {
+                initial.push(VType::UninitializedThis);
+            } else {
+                initial.push(VType::Object(self.this_class.clone()));
+            }
+        }
+        initial.extend_from_slice(params);
+
+        let locals =
+            stack_map::expand_locals(&initial, self.max_locals).map_err(|e| (0, e.to_string()))?;
+        let frames = stack_map::expand(self.cp, entries, &initial, self.max_locals)
+            .map_err(|e| (0, e.to_string()))?;
+        for (pc, frame) in frames {
+            if !self.is_start(pc) {
+                return Err((pc, "StackMapTable error: bad offset".to_string()));
+            }
+            if frame.stack_size() > self.max_stack {
+                return Err((
+                    pc,
+                    "StackMapTable error: operand stack overflow".to_string(),
+                ));
+            }
+            self.frames.insert(pc, frame);
+        }
+
+        let mut current = Some(Frame {
+            locals,
+            stack: vec![],
+        });
+        let mut pc = 0;
+        while pc < self.code.len() {
+            if let Some(target) = self.frames.get(&pc) {
+                if let Some(frame) = &current {
+                    if !self.is_frame_assignable(frame, target) {
+                        let e = "Current frame is not assignable to stack map frame";
+                        return Err((pc, e.to_string()));
+                    }
+                }
+                current = Some(target.clone());
+            }
+
+            let mut frame = match current.take() {
+                Some(frame) => frame,
+                None => return Err((pc, "Expecting a stackmap frame".to_string())),
+            };
+
+            self.check_handlers(pc, &frame.locals)
+                .map_err(|e| (pc, e))?;
+            let falls_through = self.execute(pc, &mut frame).map_err(|e| (pc, e))?;
+            self.check_handlers(pc, &frame.locals)
+                .map_err(|e| (pc, e))?;
+
+            if falls_through {
+                current = Some(frame);
+            }
+            pc += self.instruction_len(pc).unwrap();
+        }
+
+        if current.is_some() {
+            return Err((pc, "Falling off the end of the code".to_string()));
+        }
+
+        Ok(())
+    }
+
+    //the locals flowing into the exception handlers of the instruction at 'pc'
+    fn check_handlers(&self, pc: usize, locals: &[VType]) -> Result<()> {
+        for it in self.handlers.iter() {
+            if pc < it.start_pc as usize || pc >= it.end_pc as usize {
+                continue;
+            }
+
+            let target = match self.frames.get(&(it.handler_pc as usize)) {
+                Some(target) => target,
+                None => return Err("Expecting a stackmap frame at exception handler".to_string()),
+            };
+            let catch_type = if it.catch_type == 0 {
+                VType::object(J_THROWABLE)
+            } else {
+                VType::Object(
+                    cp::get_class_name(self.cp, it.catch_type as usize)
+                        .unwrap()
+                        .clone(),
+                )
+            };
+            let frame = Frame {
+                locals: Vec::from(locals),
+                stack: vec![catch_type],
+            };
+            if !self.is_frame_assignable(&frame, target) {
+                return Err("Bad exception handler frame".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    //returns false if the next instruction is not reachable from this one
+    fn execute(&self, pc: usize, frame: &mut Frame) -> Result<bool> {
+        let op = self.code[pc];
+        match op {
+            //nop
+            0 => (),
+            //aconst_null
+            1 => self.push(frame, VType::Null)?,
+            //iconst_m1 ~ iconst_5, bipush, sipush
+            2..=8 | 16 | 17 => self.push(frame, VType::Int)?,
+            //lconst_0, lconst_1
+            9 | 10 => self.push(frame, VType::Long)?,
+            //fconst_0 ~ fconst_2
+            11..=13 => self.push(frame, VType::Float)?,
+            //dconst_0, dconst_1
+            14 | 15 => self.push(frame, VType::Double)?,
+            //ldc, ldc_w, ldc2_w
+            18 => self.ldc(frame, self.u1(pc + 1), false)?,
+            19 => self.ldc(frame, self.u2(pc + 1), false)?,
+            20 => self.ldc(frame, self.u2(pc + 1), true)?,
+            //iload, lload, fload, dload, aload
+            21..=25 => self.load(frame, op - 21, self.u1(pc + 1))?,
+            //xload_<n>
+            26..=45 => self.load(frame, (op - 26) / 4, ((op - 26) % 4) as usize)?,
+            //iaload ~ saload
+            46..=53 => self.array_load(frame, op)?,
+            //istore, lstore, fstore, dstore, astore
+            54..=58 => self.store(frame, op - 54, self.u1(pc + 1))?,
+            //xstore_<n>
+            59..=78 => self.store(frame, (op - 59) / 4, ((op - 59) % 4) as usize)?,
+            //iastore ~ sastore
+            79..=86 => self.array_store(frame, op)?,
+            //pop ~ swap
+            87..=95 => self.stack_op(frame, op)?,
+            //iadd ~ drem
+            96..=115 => {
+                let t = kind_type((op - 96) % 4);
+                self.pop_as(frame, &t)?;
+                self.pop_as(frame, &t)?;
+                self.push(frame, t)?;
+            }
+            //ineg ~ dneg
+            116..=119 => {
+                let t = kind_type(op - 116);
+                self.pop_as(frame, &t)?;
+                self.push(frame, t)?;
+            }
+            //ishl ~ lushr
+            120..=125 => {
+                let t = kind_type(op % 2);
+                self.pop_as(frame, &VType::Int)?;
+                self.pop_as(frame, &t)?;
+                self.push(frame, t)?;
+            }
+            //iand ~ lxor
+            126..=131 => {
+                let t = kind_type(op % 2);
+                self.pop_as(frame, &t)?;
+                self.pop_as(frame, &t)?;
+                self.push(frame, t)?;
+            }
+            //iinc
+            132 => self.check_local(frame, self.u1(pc + 1), &VType::Int)?,
+            //i2l ~ i2s
+            133..=147 => {
+                let (from, to) = CONVERSIONS[(op - 133) as usize];
+                self.pop_as(frame, &kind_type(from))?;
+                self.push(frame, kind_type(to))?;
+            }
+            //lcmp, fcmpl, fcmpg, dcmpl, dcmpg
+            148..=152 => {
+                let t = match op {
+                    148 => VType::Long,
+                    149 | 150 => VType::Float,
+                    _ => VType::Double,
+                };
+                self.pop_as(frame, &t)?;
+                self.pop_as(frame, &t)?;
+                self.push(frame, VType::Int)?;
+            }
+            //ifeq ~ ifle
+            153..=158 => {
+                self.pop_as(frame, &VType::Int)?;
+                self.check_branches(pc, frame)?;
+            }
+            //if_icmpeq ~ if_icmple
+            159..=164 => {
+                self.pop_as(frame, &VType::Int)?;
+                self.pop_as(frame, &VType::Int)?;
+                self.check_branches(pc, frame)?;
+            }
+            //if_acmpeq, if_acmpne
+            165 | 166 => {
+                self.pop_ref(frame)?;
+                self.pop_ref(frame)?;
+                self.check_branches(pc, frame)?;
+            }
+            //goto, goto_w
+            167 | 200 => {
+                self.check_branches(pc, frame)?;
+                return Ok(false);
+            }
+            //jsr, ret, jsr_w
+            168 | 169 | 201 => {
+                return Err("jsr/ret not allowed in class file version >= 51".to_string())
+            }
+            //tableswitch, lookupswitch
+            170 | 171 => {
+                self.pop_as(frame, &VType::Int)?;
+                self.check_branches(pc, frame)?;
+                return Ok(false);
+            }
+            //ireturn ~ return
+            172..=177 => {
+                self.check_return(frame, op)?;
+                return Ok(false);
+            }
+            //getstatic, putstatic, getfield, putfield
+            178..=181 => self.field_op(frame, op, self.u2(pc + 1))?,
+            //invokevirtual ~ invokedynamic
+            182..=186 => self.invoke(pc, frame, op)?,
+            //new
+            187 => {
+                let name = self.class_name(self.u2(pc + 1))?;
+                if name.first() == Some(&b'[') {
+                    return Err("Illegal use of new on array class".to_string());
+                }
+                self.push(frame, VType::Uninitialized(pc as u16))?;
+            }
+            //newarray
+            188 => {
+                let name: &[u8] = match self.u1(pc + 1) {
+                    4 => b"[Z",
+                    5 => b"[C",
+                    6 => b"[F",
+                    7 => b"[D",
+                    8 => b"[B",
+                    9 => b"[S",
+                    10 => b"[I",
+                    11 => b"[J",
+                    _ => return Err("Illegal newarray type".to_string()),
+                };
+                self.pop_as(frame, &VType::Int)?;
+                self.push(frame, VType::object(name))?;
+            }
+            //anewarray
+            189 => {
+                let name = self.class_name(self.u2(pc + 1))?;
+                let mut ary = Vec::with_capacity(name.len() + 3);
+                ary.push(b'[');
+                if name.first() == Some(&b'[') {
+                    ary.extend_from_slice(name.as_slice());
+                } else {
+                    ary.push(b'L');
+                    ary.extend_from_slice(name.as_slice());
+                    ary.push(b';');
+                }
+                if descriptor::parse_field(&ary).is_none() {
+                    return Err("Array with too many dimensions".to_string());
+                }
+                self.pop_as(frame, &VType::Int)?;
+                self.push(frame, VType::object(&ary))?;
+            }
+            //arraylength
+            190 => {
+                self.pop_array(frame)?;
+                self.push(frame, VType::Int)?;
+            }
+            //athrow
+            191 => {
+                self.pop_as(frame, &VType::object(J_THROWABLE))?;
+                return Ok(false);
+            }
+            //checkcast
+            192 => {
+                let name = self.class_name(self.u2(pc + 1))?;
+                self.pop_ref(frame)?;
+                self.push(frame, VType::Object(name.clone()))?;
+            }
+            //instanceof
+            193 => {
+                self.class_name(self.u2(pc + 1))?;
+                self.pop_ref(frame)?;
+                self.push(frame, VType::Int)?;
+            }
+            //monitorenter, monitorexit
+            194 | 195 => {
+                self.pop_ref(frame)?;
+            }
+            //wide
+            196 => {
+                let op = self.code[pc + 1];
+                let idx = self.u2(pc + 2);
+                match op {
+                    21..=25 => self.load(frame, op - 21, idx)?,
+                    54..=58 => self.store(frame, op - 54, idx)?,
+                    132 => self.check_local(frame, idx, &VType::Int)?,
+                    _ => return Err("jsr/ret not allowed in class file version >= 51".to_string()),
+                }
+            }
+            //multianewarray
+            197 => {
+                let name = self.class_name(self.u2(pc + 1))?;
+                let dimensions = self.u1(pc + 3);
+                let n = name.iter().take_while(|c| **c == b'[').count();
+                if dimensions == 0 || dimensions > n {
+                    return Err("Illegal dimension in multianewarray".to_string());
+                }
+                for _ in 0..dimensions {
+                    self.pop_as(frame, &VType::Int)?;
+                }
+                self.push(frame, VType::Object(name.clone()))?;
+            }
+            //ifnull, ifnonnull
+            198 | 199 => {
+                self.pop_ref(frame)?;
+                self.check_branches(pc, frame)?;
+            }
+            _ => return Err("Bad instruction".to_string()),
+        }
+
+        Ok(true)
+    }
+
+    fn ldc(&self, frame: &mut Frame, idx: usize, wide: bool) -> Result<()> {
+        let t = match (self.cp.get(idx), wide) {
+            (Some(ConstantPoolType::Integer { .. }), false) => VType::Int,
+            (Some(ConstantPoolType::Float { .. }), false) => VType::Float,
+            (Some(ConstantPoolType::String { .. }), false) => VType::object(J_STRING),
+            (Some(ConstantPoolType::Class { .. }), false) => VType::object(J_CLASS),
+            (Some(ConstantPoolType::MethodType { .. }), false) => VType::object(J_METHODTYPE),
+            (Some(ConstantPoolType::MethodHandle { .. }), false) => VType::object(J_METHODHANDLE),
+            (Some(ConstantPoolType::Long { .. }), true) => VType::Long,
+            (Some(ConstantPoolType::Double { .. }), true) => VType::Double,
+            _ => return Err("Illegal type in constant pool".to_string()),
+        };
+
+        self.push(frame, t)
+    }
+
+    //kind: 0 int, 1 long, 2 float, 3 double, 4 reference
+    fn load(&self, frame: &mut Frame, kind: u8, idx: usize) -> Result<()> {
+        if kind == 4 {
+            let t = self.local(frame, idx, 1)?.clone();
+            if !t.is_reference() && !t.is_uninitialized() {
+                return Err(format!(
+                    "Bad local variable type, expected reference but found {}",
+                    t
+                ));
+            }
+            self.push(frame, t)
+        } else {
+            let t = kind_type(kind);
+            self.check_local(frame, idx, &t)?;
+            self.push(frame, t)
+        }
+    }
+
+    fn store(&self, frame: &mut Frame, kind: u8, idx: usize) -> Result<()> {
+        let t = if kind == 4 {
+            let t = self.pop(frame)?;
+            if !t.is_reference() && !t.is_uninitialized() {
+                return Err(format!(
+                    "Bad type on operand stack, expected reference but found {}",
+                    t
+                ));
+            }
+            t
+        } else {
+            let t = kind_type(kind);
+            self.pop_as(frame, &t)?;
+            t
+        };
+
+        let size = t.size();
+        self.local(frame, idx, size)?;
+        //the second half of a long or double is overwritten
+        if idx > 0 && frame.locals[idx - 1].is_category2() {
+            frame.locals[idx - 1] = VType::Top;
+        }
+        frame.locals[idx] = t;
+        if size == 2 {
+            frame.locals[idx + 1] = VType::Top;
+        }
+
+        Ok(())
+    }
+
+    fn local<'f>(&self, frame: &'f Frame, idx: usize, size: usize) -> Result<&'f VType> {
+        if idx + size > self.max_locals {
+            return Err("Illegal local variable number".to_string());
+        }
+        Ok(&frame.locals[idx])
+    }
+
+    fn check_local(&self, frame: &Frame, idx: usize, t: &VType) -> Result<()> {
+        let v = self.local(frame, idx, t.size())?;
+        if !self.is_assignable(v, t) {
+            return Err(format!(
+                "Bad local variable type, expected {} but found {}",
+                t, v
+            ));
+        }
+        Ok(())
+    }
+
+    fn array_load(&self, frame: &mut Frame, op: u8) -> Result<()> {
+        self.pop_as(frame, &VType::Int)?;
+        let ary = self.pop_array(frame)?;
+        let t = match op {
+            //aaload
+            50 => match component_type(&ary) {
+                Some(t) if t.is_reference() => t,
+                None if ary == VType::Null => VType::Null,
+                _ => {
+                    return Err(format!(
+                        "Bad type on operand stack in aaload, found {}",
+                        ary
+                    ))
+                }
+            },
+            _ => {
+                let (names, t) = PRIMITIVE_ARRAYS[(op - 46) as usize].clone();
+                self.check_primitive_array(&ary, names)?;
+                t
+            }
+        };
+
+        self.push(frame, t)
+    }
+
+    fn array_store(&self, frame: &mut Frame, op: u8) -> Result<()> {
+        match op {
+            //aastore
+            83 => {
+                self.pop_ref(frame)?;
+                self.pop_as(frame, &VType::Int)?;
+                let ary = self.pop_array(frame)?;
+                match component_type(&ary) {
+                    Some(t) if t.is_reference() => Ok(()),
+                    None if ary == VType::Null => Ok(()),
+                    _ => Err(format!(
+                        "Bad type on operand stack in aastore, found {}",
+                        ary
+                    )),
+                }
+            }
+            _ => {
+                let (names, t) = PRIMITIVE_ARRAYS[(op - 79) as usize].clone();
+                self.pop_as(frame, &t)?;
+                self.pop_as(frame, &VType::Int)?;
+                let ary = self.pop_array(frame)?;
+                self.check_primitive_array(&ary, names)
+            }
+        }
+    }
+
+    fn check_primitive_array(&self, ary: &VType, names: &[&[u8]]) -> Result<()> {
+        match ary {
+            VType::Null => Ok(()),
+            VType::Object(name) if names.contains(&name.as_slice()) => Ok(()),
+            _ => Err(format!("Bad type on operand stack, found {}", ary)),
+        }
+    }
+
+    fn stack_op(&self, frame: &mut Frame, op: u8) -> Result<()> {
+        match op {
+            //pop
+            87 => {
+                self.pop_cat1(frame)?;
+            }
+            //pop2
+            88 => {
+                if !self.pop(frame)?.is_category2() {
+                    self.pop_cat1(frame)?;
+                }
+            }
+            //dup
+            89 => {
+                let v1 = self.pop_cat1(frame)?;
+                self.push_all(frame, &[&v1, &v1])?;
+            }
+            //dup_x1
+            90 => {
+                let v1 = self.pop_cat1(frame)?;
+                let v2 = self.pop_cat1(frame)?;
+                self.push_all(frame, &[&v1, &v2, &v1])?;
+            }
+            //dup_x2
+            91 => {
+                let v1 = self.pop_cat1(frame)?;
+                let v2 = self.pop(frame)?;
+                if v2.is_category2() {
+                    self.push_all(frame, &[&v1, &v2, &v1])?;
+                } else {
+                    let v3 = self.pop_cat1(frame)?;
+                    self.push_all(frame, &[&v1, &v3, &v2, &v1])?;
+                }
+            }
+            //dup2
+            92 => {
+                let v1 = self.pop(frame)?;
+                if v1.is_category2() {
+                    self.push_all(frame, &[&v1, &v1])?;
+                } else {
+                    let v2 = self.pop_cat1(frame)?;
+                    self.push_all(frame, &[&v2, &v1, &v2, &v1])?;
+                }
+            }
+            //dup2_x1
+            93 => {
+                let v1 = self.pop(frame)?;
+                if v1.is_category2() {
+                    let v2 = self.pop_cat1(frame)?;
+                    self.push_all(frame, &[&v1, &v2, &v1])?;
+                } else {
+                    let v2 = self.pop_cat1(frame)?;
+                    let v3 = self.pop_cat1(frame)?;
+                    self.push_all(frame, &[&v2, &v1, &v3, &v2, &v1])?;
+                }
+            }
+            //dup2_x2
+            94 => {
+                let v1 = self.pop(frame)?;
+                if v1.is_category2() {
+                    let v2 = self.pop(frame)?;
+                    if v2.is_category2() {
+                        self.push_all(frame, &[&v1, &v2, &v1])?;
+                    } else {
+                        let v3 = self.pop_cat1(frame)?;
+                        self.push_all(frame, &[&v1, &v3, &v2, &v1])?;
+                    }
+                } else {
+                    let v2 = self.pop_cat1(frame)?;
+                    let v3 = self.pop(frame)?;
+                    if v3.is_category2() {
+                        self.push_all(frame, &[&v2, &v1, &v3, &v2, &v1])?;
+                    } else {
+                        let v4 = self.pop_cat1(frame)?;
+                        self.push_all(frame, &[&v2, &v1, &v4, &v3, &v2, &v1])?;
+                    }
+                }
+            }
+            //swap
+            _ => {
+                let v1 = self.pop_cat1(frame)?;
+                let v2 = self.pop_cat1(frame)?;
+                self.push_all(frame, &[&v1, &v2])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_return(&self, frame: &mut Frame, op: u8) -> Result<()> {
+        match (op, &self.ret) {
+            //return
+            (177, None) => {
+                if self.is_init && frame.locals.contains(&VType::UninitializedThis) {
+                    return Err("Constructor must call super() or this() before return".to_string());
+                }
+                Ok(())
+            }
+            (177, Some(_)) => Err("Method expects a return value".to_string()),
+            (_, None) => Err("Method does not expect a return value".to_string()),
+            (_, Some(ret)) => {
+                let matched = match op {
+                    172 => *ret == VType::Int,
+                    173 => *ret == VType::Long,
+                    174 => *ret == VType::Float,
+                    175 => *ret == VType::Double,
+                    _ => ret.is_reference(),
+                };
+                if !matched {
+                    return Err("Wrong return type in function".to_string());
+                }
+                self.pop_as(frame, ret)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn field_op(&self, frame: &mut Frame, op: u8, idx: usize) -> Result<()> {
+        let (class, _, desc) = match self.cp.get(idx) {
+            Some(ConstantPoolType::FieldRef { .. }) => cp::get_member_ref(self.cp, idx).unwrap(),
+            _ => return Err("Illegal field reference in constant pool".to_string()),
+        };
+        let t = descriptor::parse_field(desc.as_slice()).unwrap();
+        let class = VType::Object(class.clone());
+
+        match op {
+            //getstatic
+            178 => self.push(frame, t),
+            //putstatic
+            179 => self.pop_as(frame, &t).map(|_| ()),
+            //getfield
+            180 => {
+                self.pop_as(frame, &class)?;
+                self.push(frame, t)
+            }
+            //putfield
+            _ => {
+                self.pop_as(frame, &t)?;
+                let obj = self.pop(frame)?;
+                //the fields of this class can be assigned before super()
+                let is_this = obj == VType::UninitializedThis
+                    && class == VType::Object(self.this_class.clone());
+                if !is_this && !self.is_assignable(&obj, &class) {
+                    return Err(format!(
+                        "Bad type on operand stack in putfield, found {}",
+                        obj
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn invoke(&self, pc: usize, frame: &mut Frame, op: u8) -> Result<()> {
+        let idx = self.u2(pc + 1);
+        let (class, name, desc) = match (op, self.cp.get(idx)) {
+            (
+                186,
+                Some(ConstantPoolType::InvokeDynamic {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => {
+                let (name, desc) =
+                    cp::get_name_and_type(self.cp, *name_and_type_index as usize).unwrap();
+                (None, name, desc)
+            }
+            (182, Some(ConstantPoolType::MethodRef { .. }))
+            | (183, Some(ConstantPoolType::MethodRef { .. }))
+            | (183, Some(ConstantPoolType::InterfaceMethodRef { .. }))
+            | (184, Some(ConstantPoolType::MethodRef { .. }))
+            | (184, Some(ConstantPoolType::InterfaceMethodRef { .. }))
+            | (185, Some(ConstantPoolType::InterfaceMethodRef { .. })) => {
+                let (class, name, desc) = cp::get_member_ref(self.cp, idx).unwrap();
+                (Some(class), name, desc)
+            }
+            _ => return Err("Illegal method reference in constant pool".to_string()),
+        };
+
+        let is_init = name.as_slice() == METHOD_NAME_INIT;
+        if name.first() == Some(&b'<') && !(op == 183 && is_init) {
+            return Err("Illegal call to internal method".to_string());
+        }
+
+        let (params, ret) = descriptor::parse_method(desc.as_slice()).unwrap();
+        //invokeinterface
+        if op == 185 {
+            let count = self.u1(pc + 3);
+            if count != descriptor::params_size(&params) + 1 || self.code[pc + 4] != 0 {
+                return Err("Inconsistent args count operand in invokeinterface".to_string());
+            }
+        }
+        //invokedynamic
+        if op == 186 && (self.code[pc + 3] != 0 || self.code[pc + 4] != 0) {
+            return Err("Third and fourth operand bytes of invokedynamic must be zero".to_string());
+        }
+
+        for it in params.iter().rev() {
+            self.pop_as(frame, it)?;
+        }
+
+        match (op, class) {
+            (183, Some(class)) if is_init => {
+                let receiver = self.pop(frame)?;
+                let initialized = match &receiver {
+                    VType::UninitializedThis => VType::Object(self.this_class.clone()),
+                    VType::Uninitialized(offset) => {
+                        let offset = *offset as usize;
+                        if !self.is_start(offset) || self.code[offset] != 187 {
+                            return Err("Bad uninitialized type offset".to_string());
+                        }
+                        let name = self.class_name(self.u2(offset + 1))?;
+                        if name != class {
+                            return Err("Call to wrong initialization method".to_string());
+                        }
+                        VType::Object(name.clone())
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Bad type on operand stack, expected uninitialized but found {}",
+                            receiver
+                        ))
+                    }
+                };
+
+                for it in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+                    if *it == receiver {
+                        *it = initialized.clone();
+                    }
+                }
+            }
+            (182, Some(class)) | (183, Some(class)) | (185, Some(class)) => {
+                self.pop_as(frame, &VType::Object(class.clone()))?;
+            }
+            _ => (),
+        }
+
+        match ret {
+            Some(ret) => self.push(frame, ret),
+            None => Ok(()),
+        }
+    }
+
+    fn check_branches(&self, pc: usize, frame: &Frame) -> Result<()> {
+        for target in self.branch_targets(pc)? {
+            match self.frames.get(&target) {
+                Some(it) if self.is_frame_assignable(frame, it) => (),
+                Some(_) => return Err(format!("Bad stackmap frame at branch target {}", target)),
+                None => {
+                    return Err(format!(
+                        "Expecting a stackmap frame at branch target {}",
+                        target
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&self, frame: &mut Frame, t: VType) -> Result<()> {
+        frame.stack.push(t);
+        if frame.stack_size() > self.max_stack {
+            return Err("Operand stack overflow".to_string());
+        }
+        Ok(())
+    }
+
+    fn push_all(&self, frame: &mut Frame, values: &[&VType]) -> Result<()> {
+        for it in values.iter() {
+            self.push(frame, (*it).clone())?;
+        }
+        Ok(())
+    }
+
+    fn pop(&self, frame: &mut Frame) -> Result<VType> {
+        frame
+            .stack
+            .pop()
+            .ok_or_else(|| "Operand stack underflow".to_string())
+    }
+
+    fn pop_as(&self, frame: &mut Frame, expected: &VType) -> Result<VType> {
+        let t = self.pop(frame)?;
+        if !self.is_assignable(&t, expected) {
+            return Err(format!(
+                "Bad type on operand stack, expected {} but found {}",
+                expected, t
+            ));
+        }
+        Ok(t)
+    }
+
+    fn pop_cat1(&self, frame: &mut Frame) -> Result<VType> {
+        let t = self.pop(frame)?;
+        if t.is_category2() {
+            return Err("Bad type on operand stack, expected category 1 type".to_string());
+        }
+        Ok(t)
+    }
+
+    fn pop_ref(&self, frame: &mut Frame) -> Result<VType> {
+        let t = self.pop(frame)?;
+        if !t.is_reference() {
+            return Err(format!(
+                "Bad type on operand stack, expected reference but found {}",
+                t
+            ));
+        }
+        Ok(t)
+    }
+
+    fn pop_array(&self, frame: &mut Frame) -> Result<VType> {
+        let t = self.pop(frame)?;
+        if t != VType::Null && !t.is_array() {
+            return Err(format!(
+                "Bad type on operand stack, expected array but found {}",
+                t
+            ));
+        }
+        Ok(t)
+    }
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn is_assignable(&self, from: &VType, to: &VType) -> bool {
+        is_assignable(from, to, &mut self.constraints.borrow_mut())
+    }
+
+    fn is_frame_assignable(&self, frame: &Frame, target: &Frame) -> bool {
+        frame.is_assignable_to(target, &mut self.constraints.borrow_mut())
+    }
+
+    fn is_start(&self, pc: usize) -> bool {
+        self.starts.get(pc).cloned().unwrap_or(false)
+    }
+
+    fn u1(&self, pc: usize) -> usize {
+        self.code[pc] as usize
+    }
+
+    fn u2(&self, pc: usize) -> usize {
+        ((self.code[pc] as usize) << 8) | self.code[pc + 1] as usize
+    }
+
+    fn s4(&self, pc: usize) -> i32 {
+        i32::from_be_bytes([
+            self.code[pc],
+            self.code[pc + 1],
+            self.code[pc + 2],
+            self.code[pc + 3],
+        ])
+    }
+
+    fn class_name(&self, idx: usize) -> Result<&'a BytesRef> {
+        cp::get_class_name(self.cp, idx).ok_or_else(|| "Illegal class in constant pool".to_string())
+    }
+
+    fn instruction_len(&self, pc: usize) -> Result<usize> {
+        let code = self.code;
+        let n = match code[pc] {
+            0..=15 | 26..=53 | 59..=131 | 133..=152 | 172..=177 | 190 | 191 | 194 | 195 => 1,
+            16 | 18 | 21..=25 | 54..=58 | 169 | 188 => 2,
+            17 | 19 | 20 | 132 | 153..=168 | 178..=184 | 187 | 189 | 192 | 193 | 198 | 199 => 3,
+            197 => 4,
+            185 | 186 | 200 | 201 => 5,
+            //tableswitch, lookupswitch
+            170 | 171 => {
+                let pad = 3 - pc % 4;
+                let base = pc + 1 + pad;
+                let header = if code[pc] == 170 { 12 } else { 8 };
+                if base + header > code.len() {
+                    return Err("Instruction extends past end of code".to_string());
+                }
+
+                let n = if code[pc] == 170 {
+                    let low = self.s4(base + 4) as i64;
+                    let high = self.s4(base + 8) as i64;
+                    if low > high {
+                        return Err(
+                            "Low must be less than or equal to high in tableswitch".to_string()
+                        );
+                    }
+                    (high - low + 1) * 4
+                } else {
+                    let npairs = self.s4(base + 4) as i64;
+                    if npairs < 0 {
+                        return Err(
+                            "Number of pairs in lookupswitch must be non-negative".to_string()
+                        );
+                    }
+                    npairs * 8
+                };
+                let len = 1 + pad as i64 + header as i64 + n;
+                if pc as i64 + len > code.len() as i64 {
+                    return Err("Instruction extends past end of code".to_string());
+                }
+                len as usize
+            }
+            //wide
+            196 => match code.get(pc + 1) {
+                Some(132) => 6,
+                Some(21..=25) | Some(54..=58) | Some(169) => 4,
+                _ => return Err("Bad wide instruction".to_string()),
+            },
+            _ => return Err("Bad instruction".to_string()),
+        };
+
+        Ok(n)
+    }
+
+    fn branch_targets(&self, pc: usize) -> Result<Vec<usize>> {
+        let offsets = match self.code[pc] {
+            153..=168 | 198 | 199 => vec![self.u2(pc + 1) as i16 as i64],
+            200 | 201 => vec![self.s4(pc + 1) as i64],
+            170 => {
+                let base = pc + 1 + (3 - pc % 4);
+                let low = self.s4(base + 4) as i64;
+                let high = self.s4(base + 8) as i64;
+                let mut offsets = vec![self.s4(base) as i64];
+                for i in 0..(high - low + 1) as usize {
+                    offsets.push(self.s4(base + 12 + i * 4) as i64);
+                }
+                offsets
+            }
+            171 => {
+                let base = pc + 1 + (3 - pc % 4);
+                let npairs = self.s4(base + 4) as usize;
+                let mut offsets = vec![self.s4(base) as i64];
+                let mut prev = None;
+                for i in 0..npairs {
+                    let key = self.s4(base + 8 + i * 8);
+                    if matches!(prev, Some(prev) if key <= prev) {
+                        return Err("Bad lookupswitch instruction".to_string());
+                    }
+                    prev = Some(key);
+                    offsets.push(self.s4(base + 12 + i * 8) as i64);
+                }
+                offsets
+            }
+            _ => vec![],
+        };
+
+        let mut targets = Vec::with_capacity(offsets.len());
+        for offset in offsets {
+            let target = pc as i64 + offset;
+            if target < 0 || !self.is_start(target as usize) {
+                return Err("Illegal target of jump or branch".to_string());
+            }
+            targets.push(target as usize);
+        }
+
+        Ok(targets)
+    }
+
+    //the checks don't need the type state
+    fn check_instruction(&self, pc: usize) -> Result<()> {
+        self.branch_targets(pc)?;
+
+        let op = self.code[pc];
+        let (idx, size) = match op {
+            //xload, xstore, ret
+            21..=25 | 54..=58 => (self.u1(pc + 1), local_size(op)),
+            169 | 132 => (self.u1(pc + 1), 1),
+            26..=45 => (((op - 26) % 4) as usize, local_size(21 + (op - 26) / 4)),
+            59..=78 => (((op - 59) % 4) as usize, local_size(54 + (op - 59) / 4)),
+            196 => (self.u2(pc + 2), local_size(self.code[pc + 1])),
+            _ => (0, 0),
+        };
+        if size != 0 && idx + size > self.max_locals {
+            return Err("Illegal local variable number".to_string());
+        }
+
+        let valid = match op {
+            18 => matches!(
+                self.cp.get(self.u1(pc + 1)),
+                Some(ConstantPoolType::Integer { .. })
+                    | Some(ConstantPoolType::Float { .. })
+                    | Some(ConstantPoolType::String { .. })
+                    | Some(ConstantPoolType::Class { .. })
+                    | Some(ConstantPoolType::MethodType { .. })
+                    | Some(ConstantPoolType::MethodHandle { .. })
+            ),
+            19 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::Integer { .. })
+                    | Some(ConstantPoolType::Float { .. })
+                    | Some(ConstantPoolType::String { .. })
+                    | Some(ConstantPoolType::Class { .. })
+                    | Some(ConstantPoolType::MethodType { .. })
+                    | Some(ConstantPoolType::MethodHandle { .. })
+            ),
+            20 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::Long { .. }) | Some(ConstantPoolType::Double { .. })
+            ),
+            178..=181 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::FieldRef { .. })
+            ),
+            182..=184 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::MethodRef { .. })
+                    | Some(ConstantPoolType::InterfaceMethodRef { .. })
+            ),
+            185 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::InterfaceMethodRef { .. })
+            ),
+            186 => matches!(
+                self.cp.get(self.u2(pc + 1)),
+                Some(ConstantPoolType::InvokeDynamic { .. })
+            ),
+            187 | 189 | 192 | 193 | 197 => cp::get_class_name(self.cp, self.u2(pc + 1)).is_some(),
+            _ => true,
+        };
+        if !valid {
+            return Err("Illegal constant pool index".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+//0 int, 1 long, 2 float, 3 double
+fn kind_type(kind: u8) -> VType {
+    match kind {
+        0 => VType::Int,
+        1 => VType::Long,
+        2 => VType::Float,
+        _ => VType::Double,
+    }
+}
+
+//local slots taken by load & store instruction 'op'
+fn local_size(op: u8) -> usize {
+    match op {
+        //lload, dload, lstore, dstore
+        22 | 24 | 55 | 57 => 2,
+        _ => 1,
+    }
+}
+
+//i2l ~ i2s, (from, to) in kind_type
+const CONVERSIONS: [(u8, u8); 15] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (1, 0),
+    (1, 2),
+    (1, 3),
+    (2, 0),
+    (2, 1),
+    (2, 3),
+    (3, 0),
+    (3, 1),
+    (3, 2),
+    (0, 0),
+    (0, 0),
+    (0, 0),
+];
+
+//xaload & xastore except aaload & aastore, the array types & the element type
+const PRIMITIVE_ARRAYS: [(&[&[u8]], VType); 8] = [
+    (&[b"[I"], VType::Int),
+    (&[b"[J"], VType::Long),
+    (&[b"[F"], VType::Float),
+    (&[b"[D"], VType::Double),
+    (&[], VType::Null),
+    (&[b"[B", b"[Z"], VType::Int),
+    (&[b"[C"], VType::Int),
+    (&[b"[S"], VType::Int),
+];
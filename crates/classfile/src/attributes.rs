@@ -49,11 +49,13 @@ pub enum Type {
     },
     RuntimeVisibleParameterAnnotations {
         raw: BytesRef,
-        annotations: Vec<AnnotationEntry>,
+        //by the parameter
+        annotations: Vec<Vec<AnnotationEntry>>,
     },
     RuntimeInvisibleParameterAnnotations {
         raw: BytesRef,
-        annotations: Vec<AnnotationEntry>,
+        //by the parameter
+        annotations: Vec<Vec<AnnotationEntry>>,
     },
     RuntimeVisibleTypeAnnotations {
         raw: BytesRef,
@@ -74,7 +76,30 @@ pub enum Type {
     MethodParameters {
         parameters: Vec<MethodParameter>,
     },
-    Unknown,
+    Module(Module),
+    ModulePackages {
+        packages: Vec<U2>,
+    },
+    ModuleMainClass {
+        main_class_index: U2,
+    },
+    NestHost {
+        host_class_index: U2,
+    },
+    NestMembers {
+        classes: Vec<U2>,
+    },
+    Record {
+        components: Vec<RecordComponent>,
+    },
+    PermittedSubclasses {
+        classes: Vec<U2>,
+    },
+    //kept as is, to be written back
+    Unknown {
+        name_index: U2,
+        info: BytesRef,
+    },
 }
 
 #[derive(Clone, Copy)]
@@ -102,6 +127,13 @@ pub enum Tag {
     AnnotationDefault,
     BootstrapMethods,
     MethodParameters,
+    Module,
+    ModulePackages,
+    ModuleMainClass,
+    NestHost,
+    NestMembers,
+    Record,
+    PermittedSubclasses,
     Unknown,
 }
 
@@ -131,6 +163,13 @@ impl From<&[u8]> for Tag {
             b"AnnotationDefault" => Tag::AnnotationDefault,
             b"BootstrapMethods" => Tag::BootstrapMethods,
             b"MethodParameters" => Tag::MethodParameters,
+            b"Module" => Tag::Module,
+            b"ModulePackages" => Tag::ModulePackages,
+            b"ModuleMainClass" => Tag::ModuleMainClass,
+            b"NestHost" => Tag::NestHost,
+            b"NestMembers" => Tag::NestMembers,
+            b"Record" => Tag::Record,
+            b"PermittedSubclasses" => Tag::PermittedSubclasses,
             _ => {
                 info!("Unknown attr {}", unsafe {
                     std::str::from_utf8_unchecked(raw)
@@ -416,3 +455,49 @@ pub struct EnclosingMethod {
     pub class_index: U2,
     pub method_index: U2,
 }
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name_index: U2,
+    pub flags: U2,
+    pub version_index: U2,
+    pub requires: Vec<ModuleRequires>,
+    pub exports: Vec<ModuleExports>,
+    pub opens: Vec<ModuleOpens>,
+    pub uses: Vec<U2>,
+    pub provides: Vec<ModuleProvides>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ModuleRequires {
+    pub requires_index: U2,
+    pub requires_flags: U2,
+    pub requires_version_index: U2,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleExports {
+    pub exports_index: U2,
+    pub exports_flags: U2,
+    pub exports_to: Vec<U2>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleOpens {
+    pub opens_index: U2,
+    pub opens_flags: U2,
+    pub opens_to: Vec<U2>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleProvides {
+    pub provides_index: U2,
+    pub provides_with: Vec<U2>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordComponent {
+    pub name_index: U2,
+    pub desc_index: U2,
+    pub attrs: Vec<Type>,
+}
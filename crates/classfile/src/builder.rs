@@ -0,0 +1,1047 @@
+use crate::attributes::{BootstrapMethod, Code, CodeException, Type as AttrType};
+use crate::constant_pool::{construct_string_raw, to_modified_utf8, Type as CpType};
+use crate::consts::MAX_CP_ENTRIES;
+use crate::stack_map;
+use crate::writer::attr_name;
+use crate::{ClassFile, FieldInfo, MethodInfo, OpCode, Version, U1, U2};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/*
+The assembler of class files, to generate proxy & adapter classes
+
+ClassBuilder makes a ClassFile, the constant pool entries are interned by
+ConstantPoolBuilder. The code of a method is given to CodeBuilder, the
+branches refer to Labels, bound before or after:
+  - a branch beyond i16 is widened, goto to goto_w, a conditional one to
+    the opposite branch over a goto_w
+  - tableswitch & lookupswitch are padded by the offsets laid out
+  - max_stack, max_locals & the StackMapTable are computed from the code,
+    as COMPUTE_FRAMES of ASM, see stack_map
+
+The names of the classes are in the internal form, "java/lang/Object".
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// the label #n is used but not bound, or bound twice
+    BadLabel(usize),
+    /// the constant pool is beyond 65535 entries
+    TooManyConstants,
+    BadDescriptor(String),
+    /// the keys of lookupswitch are not unique
+    DuplicateKey(i32),
+    /// the code can't be typed at the offset
+    BadCode(usize, &'static str),
+    /// the code is beyond 65535 bytes, or max_stack, max_locals
+    TooLarge(&'static str),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::BadLabel(i) => write!(f, "Label #{} is not bound, or bound twice", i),
+            BuildError::TooManyConstants => write!(f, "Too many constants"),
+            BuildError::BadDescriptor(desc) => write!(f, "Bad descriptor {}", desc),
+            BuildError::DuplicateKey(key) => write!(f, "Duplicate lookupswitch key {}", key),
+            BuildError::BadCode(pc, msg) => write!(f, "Bad code at offset {}: {}", pc, msg),
+            BuildError::TooLarge(what) => write!(f, "Too large {}", what),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+type Result<T> = std::result::Result<T, BuildError>;
+
+//the common super class of two classes, by the names
+pub(crate) type CommonSuperClass = dyn Fn(&str, &str) -> String;
+
+pub(crate) fn mutf8(s: &str) -> Vec<u8> {
+    to_modified_utf8(&s.encode_utf16().collect::<Vec<u16>>())
+}
+
+pub(crate) fn from_mutf8(bytes: &[u8]) -> String {
+    String::from_utf16_lossy(&construct_string_raw(bytes))
+}
+
+/// The constant pool being built, an equal entry is added once
+pub struct ConstantPoolBuilder {
+    pub(crate) entries: Vec<CpType>,
+    //by the tag & the content as written
+    index: HashMap<Vec<u8>, U2>,
+    //an entry is dropped, reported by build
+    full: bool,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        Self {
+            entries: vec![CpType::Nop],
+            index: HashMap::new(),
+            full: false,
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, key: Vec<u8>, entry: CpType) -> U2 {
+        if let Some(i) = self.index.get(&key) {
+            return *i;
+        }
+
+        let wide = matches!(entry, CpType::Long { .. } | CpType::Double { .. });
+        let size = if wide { 2 } else { 1 };
+        if self.entries.len() + size > MAX_CP_ENTRIES as usize {
+            self.full = true;
+            return 0;
+        }
+
+        let i = self.entries.len() as U2;
+        self.entries.push(entry);
+        //the next one of long & double is unusable
+        if wide {
+            self.entries.push(CpType::Nop);
+        }
+        self.index.insert(key, i);
+        i
+    }
+
+    fn refs(&mut self, tag: U1, indexes: &[U2], entry: CpType) -> U2 {
+        let mut key = vec![tag];
+        for i in indexes {
+            key.extend_from_slice(&i.to_be_bytes());
+        }
+        self.add(key, entry)
+    }
+
+    fn value(&mut self, tag: U1, v: &[u8], entry: CpType) -> U2 {
+        let mut key = vec![tag];
+        key.extend_from_slice(v);
+        self.add(key, entry)
+    }
+
+    pub fn utf8(&mut self, s: &str) -> U2 {
+        self.utf8_bytes(&mutf8(s))
+    }
+
+    pub(crate) fn utf8_bytes(&mut self, bytes: &[u8]) -> U2 {
+        let entry = CpType::Utf8 {
+            bytes: Arc::new(bytes.to_vec()),
+        };
+        self.value(1, bytes, entry)
+    }
+
+    pub fn class(&mut self, name: &str) -> U2 {
+        self.class_bytes(&mutf8(name))
+    }
+
+    pub(crate) fn class_bytes(&mut self, name: &[u8]) -> U2 {
+        let name_index = self.utf8_bytes(name);
+        self.refs(7, &[name_index], CpType::Class { name_index })
+    }
+
+    pub fn string(&mut self, s: &str) -> U2 {
+        let string_index = self.utf8(s);
+        self.refs(8, &[string_index], CpType::String { string_index })
+    }
+
+    pub fn integer(&mut self, v: i32) -> U2 {
+        let v = v.to_be_bytes();
+        self.value(3, &v, CpType::Integer { v })
+    }
+
+    //by the bits, NaNs of different bits are different entries
+    pub fn float(&mut self, v: f32) -> U2 {
+        let v = v.to_bits().to_be_bytes();
+        self.value(4, &v, CpType::Float { v })
+    }
+
+    pub fn long(&mut self, v: i64) -> U2 {
+        let v = v.to_be_bytes();
+        self.value(5, &v, CpType::Long { v })
+    }
+
+    pub fn double(&mut self, v: f64) -> U2 {
+        let v = v.to_bits().to_be_bytes();
+        self.value(6, &v, CpType::Double { v })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, desc: &str) -> U2 {
+        let name_index = self.utf8(name);
+        let desc_index = self.utf8(desc);
+        let entry = CpType::NameAndType {
+            name_index,
+            desc_index,
+        };
+        self.refs(12, &[name_index, desc_index], entry)
+    }
+
+    pub fn field_ref(&mut self, owner: &str, name: &str, desc: &str) -> U2 {
+        let class_index = self.class(owner);
+        let name_and_type_index = self.name_and_type(name, desc);
+        let entry = CpType::FieldRef {
+            class_index,
+            name_and_type_index,
+        };
+        self.refs(9, &[class_index, name_and_type_index], entry)
+    }
+
+    pub fn method_ref(&mut self, owner: &str, name: &str, desc: &str) -> U2 {
+        let class_index = self.class(owner);
+        let name_and_type_index = self.name_and_type(name, desc);
+        let entry = CpType::MethodRef {
+            class_index,
+            name_and_type_index,
+        };
+        self.refs(10, &[class_index, name_and_type_index], entry)
+    }
+
+    pub fn interface_method_ref(&mut self, owner: &str, name: &str, desc: &str) -> U2 {
+        let class_index = self.class(owner);
+        let name_and_type_index = self.name_and_type(name, desc);
+        let entry = CpType::InterfaceMethodRef {
+            class_index,
+            name_and_type_index,
+        };
+        self.refs(11, &[class_index, name_and_type_index], entry)
+    }
+
+    /// 'ref_index' is the entry of field_ref, method_ref or interface_method_ref
+    pub fn method_handle(&mut self, ref_kind: U1, ref_index: U2) -> U2 {
+        let entry = CpType::MethodHandle {
+            ref_kind,
+            ref_index,
+        };
+        let i = ref_index.to_be_bytes();
+        self.value(15, &[ref_kind, i[0], i[1]], entry)
+    }
+
+    pub fn method_type(&mut self, desc: &str) -> U2 {
+        let desc_index = self.utf8(desc);
+        self.refs(16, &[desc_index], CpType::MethodType { desc_index })
+    }
+
+    /// 'bootstrap_method' is the index returned by ClassBuilder::bootstrap_method
+    pub fn dynamic(&mut self, bootstrap_method: U2, name: &str, desc: &str) -> U2 {
+        let name_and_type_index = self.name_and_type(name, desc);
+        let entry = CpType::Dynamic {
+            bootstrap_method_attr_index: bootstrap_method,
+            name_and_type_index,
+        };
+        self.refs(17, &[bootstrap_method, name_and_type_index], entry)
+    }
+
+    pub fn invoke_dynamic(&mut self, bootstrap_method: U2, name: &str, desc: &str) -> U2 {
+        let name_and_type_index = self.name_and_type(name, desc);
+        let entry = CpType::InvokeDynamic {
+            bootstrap_method_attr_index: bootstrap_method,
+            name_and_type_index,
+        };
+        self.refs(18, &[bootstrap_method, name_and_type_index], entry)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.full {
+            Err(BuildError::TooManyConstants)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The builder of a class file
+pub struct ClassBuilder {
+    version: Version,
+    cp: ConstantPoolBuilder,
+    acc_flags: U2,
+    //in modified UTF-8, the type of 'this' in the frames
+    name: Vec<u8>,
+    this_class: U2,
+    super_class: U2,
+    interfaces: Vec<U2>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attrs: Vec<AttrType>,
+    bootstrap_methods: Vec<BootstrapMethod>,
+    common_super_class: Box<CommonSuperClass>,
+}
+
+impl ClassBuilder {
+    /// 'super_name' is None only for java/lang/Object
+    pub fn new(version: Version, acc_flags: U2, name: &str, super_name: Option<&str>) -> Self {
+        let mut cp = ConstantPoolBuilder::new();
+        let this_class = cp.class(name);
+        let super_class = super_name.map_or(0, |it| cp.class(it));
+        Self {
+            version,
+            cp,
+            acc_flags,
+            name: mutf8(name),
+            this_class,
+            super_class,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![],
+            attrs: vec![],
+            bootstrap_methods: vec![],
+            common_super_class: Box::new(|_, _| String::from("java/lang/Object")),
+        }
+    }
+
+    pub fn cp(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.cp
+    }
+
+    /// The common super class of two classes, the type of the merged frames
+    ///
+    /// The class hierarchy is unknown by the builder, java/lang/Object is taken
+    /// by default, it's enough unless the merged value is used as the class.
+    pub fn set_common_super_class<F>(&mut self, f: F)
+    where
+        F: Fn(&str, &str) -> String + 'static,
+    {
+        self.common_super_class = Box::new(f);
+    }
+
+    pub fn interface(&mut self, name: &str) {
+        let i = self.cp.class(name);
+        self.interfaces.push(i);
+    }
+
+    /// Add a field, the attributes may be added to it
+    pub fn field(&mut self, acc_flags: U2, name: &str, desc: &str) -> &mut FieldInfo {
+        let name_index = self.cp.utf8(name);
+        let desc_index = self.cp.utf8(desc);
+        self.fields.push(FieldInfo {
+            acc_flags,
+            name_index,
+            desc_index,
+            attrs: vec![],
+        });
+        self.fields.last_mut().unwrap()
+    }
+
+    /// Add a method without code, abstract or native
+    pub fn method(&mut self, acc_flags: U2, name: &str, desc: &str) -> &mut MethodInfo {
+        let name_index = self.cp.utf8(name);
+        let desc_index = self.cp.utf8(desc);
+        self.methods.push(MethodInfo {
+            acc_flags,
+            name_index,
+            desc_index,
+            attrs: vec![],
+        });
+        self.methods.last_mut().unwrap()
+    }
+
+    /// Add a method with code, it's added by CodeBuilder::finish
+    pub fn code(&mut self, acc_flags: U2, name: &str, desc: &str) -> CodeBuilder<'_> {
+        CodeBuilder {
+            class: self,
+            acc_flags,
+            name: name.to_string(),
+            desc: desc.to_string(),
+            insns: vec![],
+            labels: vec![],
+            handlers: vec![],
+            error: None,
+        }
+    }
+
+    /// Add an entry of BootstrapMethods, the index for invokedynamic
+    pub fn bootstrap_method(&mut self, method_handle: U2, args: Vec<U2>) -> U2 {
+        let found = self
+            .bootstrap_methods
+            .iter()
+            .position(|it| it.method_ref == method_handle && it.args == args);
+        if let Some(i) = found {
+            return i as U2;
+        }
+
+        self.bootstrap_methods.push(BootstrapMethod {
+            method_ref: method_handle,
+            args,
+        });
+        (self.bootstrap_methods.len() - 1) as U2
+    }
+
+    pub fn attr(&mut self, attr: AttrType) {
+        self.attrs.push(attr);
+    }
+
+    pub fn build(mut self) -> Result<ClassFile> {
+        if !self.bootstrap_methods.is_empty() {
+            let methods = std::mem::take(&mut self.bootstrap_methods);
+            self.attrs.push(AttrType::BootstrapMethods {
+                n: methods.len() as U2,
+                methods,
+            });
+        }
+
+        //the writer refers to the names by the Utf8 entries
+        let cp = &mut self.cp;
+        intern_attr_names(cp, &self.attrs);
+        for it in self.fields.iter() {
+            intern_attr_names(cp, &it.attrs);
+        }
+        for it in self.methods.iter() {
+            intern_attr_names(cp, &it.attrs);
+        }
+        cp.check()?;
+
+        Ok(ClassFile {
+            version: self.version,
+            cp: Arc::new(self.cp.entries),
+            acc_flags: self.acc_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attrs: self.attrs,
+        })
+    }
+}
+
+fn intern_attr_names(cp: &mut ConstantPoolBuilder, attrs: &[AttrType]) {
+    for it in attrs {
+        match it {
+            AttrType::Code(code) => intern_attr_names(cp, &code.attrs),
+            AttrType::Record { components } => {
+                for c in components {
+                    intern_attr_names(cp, &c.attrs);
+                }
+            }
+            _ => (),
+        }
+
+        //kept by the name_index
+        if let AttrType::Unknown { .. } = it {
+            continue;
+        }
+        cp.utf8(attr_name(it));
+    }
+}
+
+/// A position of the code, bound by CodeBuilder::bind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(usize);
+
+/// The constant loaded by ldc, ldc_w or ldc2_w
+#[derive(Debug, Clone, Copy)]
+pub enum Constant<'a> {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(&'a str),
+    Class(&'a str),
+    MethodType(&'a str),
+    /// an entry added to the constant pool, MethodHandle, Dynamic...
+    Entry(U2),
+}
+
+enum Insn {
+    Bytes(Vec<U1>),
+    Label(Label),
+    Jump(U1, Label),
+    TableSwitch {
+        low: i32,
+        default: Label,
+        targets: Vec<Label>,
+    },
+    LookupSwitch {
+        default: Label,
+        //sorted by the key
+        pairs: Vec<(i32, Label)>,
+    },
+}
+
+struct Handler {
+    start: Label,
+    end: Label,
+    handler: Label,
+    catch_type: U2,
+}
+
+/// The builder of the code of a method
+///
+/// The first error of the instructions is reported by finish.
+pub struct CodeBuilder<'a> {
+    class: &'a mut ClassBuilder,
+    acc_flags: U2,
+    name: String,
+    desc: String,
+    insns: Vec<Insn>,
+    //bound or not, by the label
+    labels: Vec<bool>,
+    handlers: Vec<Handler>,
+    error: Option<BuildError>,
+}
+
+impl<'a> CodeBuilder<'a> {
+    pub fn cp(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.class.cp
+    }
+
+    fn fail(&mut self, e: BuildError) {
+        if self.error.is_none() {
+            self.error = Some(e);
+        }
+    }
+
+    fn emit(&mut self, bytes: &[U1]) {
+        match self.insns.last_mut() {
+            Some(Insn::Bytes(v)) => v.extend_from_slice(bytes),
+            _ => self.insns.push(Insn::Bytes(bytes.to_vec())),
+        }
+    }
+
+    fn emit_index(&mut self, op: OpCode, index: U2) {
+        let i = index.to_be_bytes();
+        self.emit(&[op as U1, i[0], i[1]]);
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(false);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Bind the label to the next instruction
+    pub fn bind(&mut self, label: Label) {
+        if self.labels[label.0] {
+            self.fail(BuildError::BadLabel(label.0));
+        }
+        self.labels[label.0] = true;
+        self.insns.push(Insn::Label(label));
+    }
+
+    /// An instruction without operands, iadd, dup, areturn...
+    pub fn op(&mut self, op: OpCode) {
+        self.emit(&[op as U1]);
+    }
+
+    /// Push an int by iconst_<n>, bipush, sipush or ldc
+    pub fn push_int(&mut self, v: i32) {
+        match v {
+            -1..=5 => self.emit(&[(OpCode::iconst_0 as i32 + v) as U1]),
+            -128..=127 => self.emit(&[OpCode::bipush as U1, v as U1]),
+            -32768..=32767 => {
+                let v = (v as i16).to_be_bytes();
+                self.emit(&[OpCode::sipush as U1, v[0], v[1]]);
+            }
+            _ => self.ldc(Constant::Int(v)),
+        }
+    }
+
+    /// ldc, ldc_w or ldc2_w by the entry
+    pub fn ldc(&mut self, c: Constant) {
+        let cp = &mut self.class.cp;
+        let index = match c {
+            Constant::Int(v) => cp.integer(v),
+            Constant::Float(v) => cp.float(v),
+            Constant::Long(v) => cp.long(v),
+            Constant::Double(v) => cp.double(v),
+            Constant::String(s) => cp.string(s),
+            Constant::Class(name) => cp.class(name),
+            Constant::MethodType(desc) => cp.method_type(desc),
+            Constant::Entry(i) => i,
+        };
+        let wide = match cp.entries.get(index as usize) {
+            Some(CpType::Long { .. }) | Some(CpType::Double { .. }) => true,
+            Some(CpType::Dynamic {
+                name_and_type_index,
+                ..
+            }) => {
+                let desc = match cp.entries.get(*name_and_type_index as usize) {
+                    Some(CpType::NameAndType { desc_index, .. }) => *desc_index,
+                    _ => 0,
+                };
+                match cp.entries.get(desc as usize) {
+                    Some(CpType::Utf8 { bytes }) => bytes[..] == b"J"[..] || bytes[..] == b"D"[..],
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
+        if wide {
+            self.emit_index(OpCode::ldc2_w, index);
+        } else if index <= 255 {
+            self.emit(&[OpCode::ldc as U1, index as U1]);
+        } else {
+            self.emit_index(OpCode::ldc_w, index);
+        }
+    }
+
+    /// xload & xstore, by the short form, or wide for the index beyond 255
+    pub fn local(&mut self, op: OpCode, index: U2) {
+        let short = match op {
+            OpCode::iload | OpCode::lload | OpCode::fload | OpCode::dload | OpCode::aload => {
+                Some(OpCode::iload_0 as U1 + (op as U1 - OpCode::iload as U1) * 4)
+            }
+            OpCode::istore | OpCode::lstore | OpCode::fstore | OpCode::dstore | OpCode::astore => {
+                Some(OpCode::istore_0 as U1 + (op as U1 - OpCode::istore as U1) * 4)
+            }
+            _ => None,
+        };
+
+        match (short, index) {
+            (Some(short), 0..=3) => self.emit(&[short + index as U1]),
+            (_, 0..=255) => self.emit(&[op as U1, index as U1]),
+            _ => {
+                let i = index.to_be_bytes();
+                self.emit(&[OpCode::wide as U1, op as U1, i[0], i[1]]);
+            }
+        }
+    }
+
+    pub fn iinc(&mut self, index: U2, delta: i16) {
+        if index <= 255 && delta >= i8::MIN as i16 && delta <= i8::MAX as i16 {
+            self.emit(&[OpCode::iinc as U1, index as U1, delta as U1]);
+        } else {
+            let i = index.to_be_bytes();
+            let d = delta.to_be_bytes();
+            self.emit(&[
+                OpCode::wide as U1,
+                OpCode::iinc as U1,
+                i[0],
+                i[1],
+                d[0],
+                d[1],
+            ]);
+        }
+    }
+
+    /// new, anewarray, checkcast or instanceof
+    pub fn type_op(&mut self, op: OpCode, class: &str) {
+        let index = self.class.cp.class(class);
+        self.emit_index(op, index);
+    }
+
+    /// getstatic, putstatic, getfield or putfield
+    pub fn field(&mut self, op: OpCode, owner: &str, name: &str, desc: &str) {
+        let index = self.class.cp.field_ref(owner, name, desc);
+        self.emit_index(op, index);
+    }
+
+    /// invokevirtual, invokespecial, invokestatic or invokeinterface
+    ///
+    /// 'interface' is whether the owner is an interface, it's true for
+    /// invokeinterface.
+    pub fn invoke(&mut self, op: OpCode, owner: &str, name: &str, desc: &str, interface: bool) {
+        let cp = &mut self.class.cp;
+        let index = if interface {
+            cp.interface_method_ref(owner, name, desc)
+        } else {
+            cp.method_ref(owner, name, desc)
+        };
+
+        if op == OpCode::invokeinterface {
+            let count = match stack_map::args_size(&mutf8(desc)) {
+                Some(n) if n < 255 => n + 1,
+                _ => return self.fail(BuildError::BadDescriptor(desc.to_string())),
+            };
+            let i = index.to_be_bytes();
+            self.emit(&[op as U1, i[0], i[1], count as U1, 0]);
+        } else {
+            self.emit_index(op, index);
+        }
+    }
+
+    pub fn invokedynamic(&mut self, bootstrap_method: U2, name: &str, desc: &str) {
+        let index = self.class.cp.invoke_dynamic(bootstrap_method, name, desc);
+        let i = index.to_be_bytes();
+        self.emit(&[OpCode::invokedynamic as U1, i[0], i[1], 0, 0]);
+    }
+
+    /// 'atype' is T_BOOLEAN 4, T_CHAR 5, T_FLOAT 6, T_DOUBLE 7, T_BYTE 8,
+    /// T_SHORT 9, T_INT 10 or T_LONG 11
+    pub fn newarray(&mut self, atype: U1) {
+        self.emit(&[OpCode::newarray as U1, atype]);
+    }
+
+    pub fn multianewarray(&mut self, desc: &str, dimensions: U1) {
+        let i = self.class.cp.class(desc).to_be_bytes();
+        self.emit(&[OpCode::multianewarray as U1, i[0], i[1], dimensions]);
+    }
+
+    /// if<cond>, if_icmp<cond>, if_acmp<cond>, ifnull, ifnonnull or goto
+    pub fn jump(&mut self, op: OpCode, label: Label) {
+        self.insns.push(Insn::Jump(op as U1, label));
+    }
+
+    /// The keys are low..low + targets.len()
+    pub fn tableswitch(&mut self, low: i32, default: Label, targets: &[Label]) {
+        if targets.is_empty() {
+            return self.lookupswitch(default, &[]);
+        }
+
+        self.insns.push(Insn::TableSwitch {
+            low,
+            default,
+            targets: targets.to_vec(),
+        });
+    }
+
+    pub fn lookupswitch(&mut self, default: Label, pairs: &[(i32, Label)]) {
+        let mut pairs = pairs.to_vec();
+        pairs.sort_by_key(|it| it.0);
+        if let Some(w) = pairs.windows(2).find(|w| w[0].0 == w[1].0) {
+            return self.fail(BuildError::DuplicateKey(w[0].0));
+        }
+
+        self.insns.push(Insn::LookupSwitch { default, pairs });
+    }
+
+    /// An entry of the exception table, 'catch_type' None catches all, as finally
+    pub fn try_catch(
+        &mut self,
+        start: Label,
+        end: Label,
+        handler: Label,
+        catch_type: Option<&str>,
+    ) {
+        let catch_type = catch_type.map_or(0, |it| self.class.cp.class(it));
+        self.handlers.push(Handler {
+            start,
+            end,
+            handler,
+            catch_type,
+        });
+    }
+
+    //the offsets of the instructions & the labels, the branches beyond i16 are
+    //widened till all fit, the widened ones are never narrowed, so it ends
+    fn layout(&self) -> (Vec<usize>, Vec<usize>, Vec<bool>) {
+        let mut wide = vec![false; self.insns.len()];
+        loop {
+            let mut offsets = Vec::with_capacity(self.insns.len());
+            let mut labels = vec![0; self.labels.len()];
+            let mut pc = 0;
+            for (i, it) in self.insns.iter().enumerate() {
+                offsets.push(pc);
+                pc += match it {
+                    Insn::Bytes(v) => v.len(),
+                    Insn::Label(label) => {
+                        labels[label.0] = pc;
+                        0
+                    }
+                    Insn::Jump(op, _) if wide[i] => match OpCode::from(*op) {
+                        OpCode::goto | OpCode::jsr => 5,
+                        _ => 8,
+                    },
+                    Insn::Jump(..) => 3,
+                    Insn::TableSwitch { targets, .. } => 1 + padding(pc) + 12 + 4 * targets.len(),
+                    Insn::LookupSwitch { pairs, .. } => 1 + padding(pc) + 8 + 8 * pairs.len(),
+                };
+            }
+
+            let mut done = true;
+            for (i, it) in self.insns.iter().enumerate() {
+                if let Insn::Jump(_, label) = it {
+                    let delta = labels[label.0] as isize - offsets[i] as isize;
+                    if !wide[i] && (delta < i16::MIN as isize || delta > i16::MAX as isize) {
+                        wide[i] = true;
+                        done = false;
+                    }
+                }
+            }
+
+            if done {
+                return (offsets, labels, wide);
+            }
+        }
+    }
+
+    fn check_label(&self, label: Label) -> Result<()> {
+        if self.labels[label.0] {
+            Ok(())
+        } else {
+            Err(BuildError::BadLabel(label.0))
+        }
+    }
+
+    fn assemble(&self) -> Result<(Vec<U1>, Vec<usize>)> {
+        for it in self.insns.iter() {
+            match it {
+                Insn::Jump(_, label) => self.check_label(*label)?,
+                Insn::TableSwitch {
+                    default, targets, ..
+                } => {
+                    self.check_label(*default)?;
+                    for it in targets {
+                        self.check_label(*it)?;
+                    }
+                }
+                Insn::LookupSwitch { default, pairs } => {
+                    self.check_label(*default)?;
+                    for it in pairs {
+                        self.check_label(it.1)?;
+                    }
+                }
+                _ => (),
+            }
+        }
+        for it in self.handlers.iter() {
+            self.check_label(it.start)?;
+            self.check_label(it.end)?;
+            self.check_label(it.handler)?;
+        }
+
+        let (offsets, labels, wide) = self.layout();
+        let mut code = Vec::new();
+        for (i, it) in self.insns.iter().enumerate() {
+            let pc = offsets[i];
+            let rel = |label: &Label| (labels[label.0] as i32 - pc as i32).to_be_bytes();
+            match it {
+                Insn::Bytes(v) => code.extend_from_slice(v),
+                Insn::Label(_) => (),
+                Insn::Jump(op, label) if !wide[i] => {
+                    code.push(*op);
+                    code.extend_from_slice(&rel(label)[2..]);
+                }
+                Insn::Jump(op, label) => match OpCode::from(*op) {
+                    OpCode::goto => {
+                        code.push(OpCode::goto_w as U1);
+                        code.extend_from_slice(&rel(label));
+                    }
+                    OpCode::jsr => {
+                        code.push(OpCode::jsr_w as U1);
+                        code.extend_from_slice(&rel(label));
+                    }
+                    //skip the goto_w if not taken
+                    op => {
+                        code.push(opposite(op) as U1);
+                        code.extend_from_slice(&8i16.to_be_bytes());
+                        let delta = labels[label.0] as i32 - (pc + 3) as i32;
+                        code.push(OpCode::goto_w as U1);
+                        code.extend_from_slice(&delta.to_be_bytes());
+                    }
+                },
+                Insn::TableSwitch {
+                    low,
+                    default,
+                    targets,
+                } => {
+                    code.push(OpCode::tableswitch as U1);
+                    code.resize(code.len() + padding(pc), 0);
+                    code.extend_from_slice(&rel(default));
+                    code.extend_from_slice(&low.to_be_bytes());
+                    let high = *low + targets.len() as i32 - 1;
+                    code.extend_from_slice(&high.to_be_bytes());
+                    for it in targets {
+                        code.extend_from_slice(&rel(it));
+                    }
+                }
+                Insn::LookupSwitch { default, pairs } => {
+                    code.push(OpCode::lookupswitch as U1);
+                    code.resize(code.len() + padding(pc), 0);
+                    code.extend_from_slice(&rel(default));
+                    code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                    for (key, label) in pairs {
+                        code.extend_from_slice(&key.to_be_bytes());
+                        code.extend_from_slice(&rel(label));
+                    }
+                }
+            }
+        }
+
+        if code.len() > u16::MAX as usize {
+            return Err(BuildError::TooLarge("code"));
+        }
+
+        Ok((code, labels))
+    }
+
+    /// Assemble the code, add the method to the class
+    pub fn finish(self) -> Result<&'a mut MethodInfo> {
+        if let Some(e) = self.error.clone() {
+            return Err(e);
+        }
+        self.class.cp.check()?;
+
+        let (mut code, labels) = self.assemble()?;
+        //the empty ranges are dropped, an instruction is in none of them
+        let mut exceptions: Vec<CodeException> = self
+            .handlers
+            .iter()
+            .map(|it| CodeException {
+                start_pc: labels[it.start.0] as U2,
+                end_pc: labels[it.end.0] as U2,
+                handler_pc: labels[it.handler.0] as U2,
+                catch_type: it.catch_type,
+            })
+            .filter(|it| it.start_pc < it.end_pc)
+            .collect();
+
+        let class = self.class;
+        let desc = mutf8(&self.desc);
+        let method = stack_map::Method {
+            this_class: &class.name,
+            acc_flags: self.acc_flags,
+            is_init: self.name == "<init>",
+            desc: &desc,
+        };
+        let computed = stack_map::compute(
+            &mut class.cp,
+            class.common_super_class.as_ref(),
+            &method,
+            &mut code,
+            &mut exceptions,
+        )?;
+        class.cp.check()?;
+
+        //no StackMapTable before 50, it's checked by the type inference
+        let mut attrs = vec![];
+        if !computed.frames.is_empty() && class.version.major >= 50 {
+            attrs.push(AttrType::StackMapTable {
+                entries: computed.frames,
+            });
+        }
+        let code = Code {
+            max_stack: computed.max_stack,
+            max_locals: computed.max_locals,
+            code: Arc::new(code),
+            exceptions,
+            attrs,
+        };
+
+        let info = class.method(self.acc_flags, &self.name, &self.desc);
+        info.attrs.push(AttrType::Code(code));
+        Ok(info)
+    }
+}
+
+//the operands of tableswitch & lookupswitch begin at a multiple of 4
+fn padding(pc: usize) -> usize {
+    (4 - (pc + 1) % 4) % 4
+}
+
+fn opposite(op: OpCode) -> OpCode {
+    match op {
+        OpCode::ifeq => OpCode::ifne,
+        OpCode::ifne => OpCode::ifeq,
+        OpCode::iflt => OpCode::ifge,
+        OpCode::ifge => OpCode::iflt,
+        OpCode::ifgt => OpCode::ifle,
+        OpCode::ifle => OpCode::ifgt,
+        OpCode::if_icmpeq => OpCode::if_icmpne,
+        OpCode::if_icmpne => OpCode::if_icmpeq,
+        OpCode::if_icmplt => OpCode::if_icmpge,
+        OpCode::if_icmpge => OpCode::if_icmplt,
+        OpCode::if_icmpgt => OpCode::if_icmple,
+        OpCode::if_icmple => OpCode::if_icmpgt,
+        OpCode::if_acmpeq => OpCode::if_acmpne,
+        OpCode::if_acmpne => OpCode::if_acmpeq,
+        OpCode::ifnull => OpCode::ifnonnull,
+        _ => OpCode::ifnull,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attributes::StackMapFrame;
+    use crate::flags::ACC_STATIC;
+
+    fn new_class() -> ClassBuilder {
+        let version = Version {
+            minor: 0,
+            major: 52,
+        };
+        ClassBuilder::new(version, 0x0021, "T", Some("java/lang/Object"))
+    }
+
+    #[test]
+    fn t_build_loop() {
+        let mut cb = new_class();
+        let mut c = cb.code(ACC_STATIC, "sum", "(I)I");
+        let (top, cond) = (c.new_label(), c.new_label());
+        c.push_int(0);
+        c.local(OpCode::istore, 1);
+        c.push_int(0);
+        c.local(OpCode::istore, 2);
+        c.jump(OpCode::goto, cond);
+        c.bind(top);
+        c.local(OpCode::iload, 1);
+        c.local(OpCode::iload, 2);
+        c.op(OpCode::iadd);
+        c.local(OpCode::istore, 1);
+        c.iinc(2, 1);
+        c.bind(cond);
+        c.local(OpCode::iload, 2);
+        c.local(OpCode::iload, 0);
+        c.jump(OpCode::if_icmplt, top);
+        c.local(OpCode::iload, 1);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+
+        let cf = cb.build().unwrap();
+        let code = cf.methods[0].get_code().unwrap();
+        assert_eq!(
+            code.code.as_slice(),
+            &[3, 60, 3, 61, 167, 0, 10, 27, 28, 96, 60, 132, 2, 1, 28, 26, 161, 255, 247, 27, 172]
+        );
+        assert_eq!((code.max_stack, code.max_locals), (2, 3));
+        match code.attrs.as_slice() {
+            [AttrType::StackMapTable { entries }] => match entries.as_slice() {
+                [StackMapFrame::Append {
+                    tag: 253,
+                    offset_delta: 7,
+                    locals,
+                }, StackMapFrame::Same { tag: 6, .. }] => assert_eq!(locals.len(), 2),
+                _ => panic!("bad frames {:?}", entries),
+            },
+            _ => panic!("no StackMapTable"),
+        }
+        assert!(cf.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn t_build_wide() {
+        let mut cb = new_class();
+        let mut c = cb.code(ACC_STATIC, "f", "(I)I");
+        let (far, other) = (c.new_label(), c.new_label());
+        c.local(OpCode::iload, 0);
+        c.jump(OpCode::ifeq, far);
+        c.local(OpCode::iload, 0);
+        c.tableswitch(1, other, &[far, other]);
+        c.bind(other);
+        c.push_int(1);
+        c.op(OpCode::ireturn);
+        //unreachable
+        c.push_int(2);
+        for _ in 0..40000 {
+            c.op(OpCode::nop);
+        }
+        c.bind(far);
+        c.push_int(0);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+
+        let cf = cb.build().unwrap();
+        let code = cf.methods[0].get_code().unwrap();
+        let bytes = code.code.as_slice();
+        //ifne over goto_w to 'far'
+        assert_eq!(&bytes[..9], &[26, 154, 0, 8, 200, 0, 0, 0x9c, 0x5f]);
+        //the operands of the tableswitch at 10 begin at 12
+        assert_eq!(&bytes[9..12], &[26, 170, 0]);
+        assert_eq!(&bytes[12..16], &[0, 0, 0, 22]);
+        //nop...athrow
+        assert_eq!((bytes[34], bytes[35], bytes[40034]), (0, 0, 191));
+        assert_eq!(bytes.len(), 40037);
+
+        let mut cb = new_class();
+        let mut c = cb.code(ACC_STATIC, "f", "()V");
+        let label = c.new_label();
+        c.jump(OpCode::goto, label);
+        assert_eq!(c.finish().err(), Some(BuildError::BadLabel(0)));
+    }
+}
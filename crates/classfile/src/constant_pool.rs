@@ -4,6 +4,7 @@ use crate::consts::{
 };
 use crate::{BytesRef, ConstantPool};
 use fmt::Debug;
+use std::convert::TryFrom;
 use std::fmt;
 use std::sync::Arc;
 
@@ -122,6 +123,25 @@ pub fn construct_string_raw(bs: &[u8]) -> Vec<u16> {
     buffer
 }
 
+//the reverse of construct_string_raw, encode to modified UTF-8
+pub fn to_modified_utf8(chars: &[u16]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(chars.len());
+    for &c in chars {
+        if c != 0 && c < 0x80 {
+            buffer.push(c as u8);
+        } else if c < 0x800 {
+            buffer.push((0xC0 | (c >> 6)) as u8);
+            buffer.push((0x80 | (c & 0x3f)) as u8);
+        } else {
+            buffer.push((0xE0 | (c >> 12)) as u8);
+            buffer.push((0x80 | ((c >> 6) & 0x3f)) as u8);
+            buffer.push((0x80 | (c & 0x3f)) as u8);
+        }
+    }
+
+    buffer
+}
+
 #[derive(Debug, Clone)]
 pub enum Type {
     Nop,
@@ -169,10 +189,20 @@ pub enum Type {
     MethodType {
         desc_index: u16,
     },
+    Dynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type_index: u16,
+    },
     InvokeDynamic {
         bootstrap_method_attr_index: u16,
         name_and_type_index: u16,
     },
+    Module {
+        name_index: u16,
+    },
+    Package {
+        name_index: u16,
+    },
     Unknown,
 }
 
@@ -191,12 +221,17 @@ pub enum Tag {
     Utf8,
     MethodHandle,
     MethodType,
+    Dynamic,
     InvokeDynamic,
+    Module,
+    Package,
 }
 
-impl From<u8> for Tag {
-    fn from(tag: u8) -> Self {
-        match tag {
+impl TryFrom<u8> for Tag {
+    type Error = u8;
+
+    fn try_from(tag: u8) -> Result<Self, u8> {
+        let tag = match tag {
             7 => Tag::Class,
             9 => Tag::FieldRef,
             10 => Tag::MethodRef,
@@ -210,9 +245,13 @@ impl From<u8> for Tag {
             1 => Tag::Utf8,
             15 => Tag::MethodHandle,
             16 => Tag::MethodType,
+            17 => Tag::Dynamic,
             18 => Tag::InvokeDynamic,
-            _ => unreachable!(),
-        }
+            19 => Tag::Module,
+            20 => Tag::Package,
+            _ => return Err(tag),
+        };
+        Ok(tag)
     }
 }
 
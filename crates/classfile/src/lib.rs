@@ -5,12 +5,15 @@
 //! The `classfile` crate provides types for describing the
 //! class file format of the Java Virtual Machine.
 //!
-//! It's not class file parser.
+//! It's not class file parser, but a `ClassFile` can be written
+//! back to bytes by `ClassFile::to_bytes`, or be generated by
+//! `ClassBuilder`, the code assembled by `CodeBuilder`.
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate log;
 pub mod attributes;
+mod builder;
 mod classfile;
 pub mod constant_pool;
 pub mod consts;
@@ -19,7 +22,9 @@ pub mod flags;
 mod method_info;
 mod opcode;
 mod signature;
+mod stack_map;
 mod version;
+mod writer;
 
 pub type U1 = u8;
 pub type U2 = u16;
@@ -29,9 +34,11 @@ pub type ConstantPool = std::sync::Arc<Vec<constant_pool::Type>>;
 
 pub use crate::classfile::ClassFile;
 pub use attributes::Type as AttributeType;
+pub use builder::{BuildError, ClassBuilder, CodeBuilder, Constant, ConstantPoolBuilder, Label};
 pub use constant_pool::Type as ConstantPoolType;
 pub use field_info::FieldInfo;
 pub use method_info::MethodInfo;
 pub use opcode::OpCode;
 pub use signature::Type as SignatureType;
 pub use version::Version;
+pub use writer::WriteError;
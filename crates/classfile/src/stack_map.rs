@@ -0,0 +1,1198 @@
+use crate::attributes::{CodeException, StackMapFrame, VerificationTypeInfo};
+use crate::builder::{from_mutf8, mutf8, BuildError, CommonSuperClass, ConstantPoolBuilder};
+use crate::constant_pool::Type as CpType;
+use crate::consts::{J_CLASS, J_METHODHANDLE, J_METHODTYPE, J_OBJECT, J_STRING, J_THROWABLE};
+use crate::flags::ACC_STATIC;
+use crate::{OpCode, U1, U2};
+
+/*
+The max_stack, max_locals & the frames of StackMapTable, by the data flow
+
+A block begins at 0, the targets of the branches & the switches, the
+handlers & the instruction after an unconditional one (goto, xreturn,
+athrow, the switches). The frame of a block is merged from all the incoming
+ones, till nothing changes, a frame is written for each block but the first.
+
+The blocks never reached are replaced by nop...athrow, with the frame of
+a Throwable on the stack, and are removed from the exception table, as ASM.
+
+long & double take 2 slots of the locals, the 2nd one is Top, but they are
+an entry of the stack, as the frames of StackMapTable.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+enum VType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    //by the name of the class, or the descriptor of the array
+    Object(Vec<u8>),
+    //by the offset of new
+    Uninitialized(usize),
+}
+
+impl VType {
+    fn size(&self) -> usize {
+        match self {
+            VType::Long | VType::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Frame {
+    locals: Vec<VType>,
+    stack: Vec<VType>,
+}
+
+pub(crate) struct Method<'a> {
+    pub this_class: &'a [u8],
+    pub acc_flags: U2,
+    pub is_init: bool,
+    pub desc: &'a [u8],
+}
+
+pub(crate) struct Computed {
+    pub max_stack: U2,
+    pub max_locals: U2,
+    pub frames: Vec<StackMapFrame>,
+}
+
+//the type of the field descriptor at the start, & the rest
+fn parse_type(desc: &[u8]) -> Option<(VType, &[u8])> {
+    let (first, rest) = desc.split_first()?;
+    let t = match first {
+        b'B' | b'C' | b'I' | b'S' | b'Z' => VType::Integer,
+        b'F' => VType::Float,
+        b'J' => VType::Long,
+        b'D' => VType::Double,
+        b'L' => {
+            let end = rest.iter().position(|c| *c == b';')?;
+            return Some((VType::Object(rest[..end].to_vec()), &rest[end + 1..]));
+        }
+        b'[' => {
+            let (_, after) = parse_type(rest)?;
+            let len = desc.len() - after.len();
+            return Some((VType::Object(desc[..len].to_vec()), after));
+        }
+        _ => return None,
+    };
+    Some((t, rest))
+}
+
+fn parse_field_desc(desc: &[u8]) -> Option<VType> {
+    match parse_type(desc)? {
+        (t, []) => Some(t),
+        _ => None,
+    }
+}
+
+//the args & the return type, None of void
+fn parse_method_desc(desc: &[u8]) -> Option<(Vec<VType>, Option<VType>)> {
+    let mut rest = desc.strip_prefix(b"(")?;
+    let mut args = vec![];
+    while !rest.starts_with(b")") {
+        let (t, r) = parse_type(rest)?;
+        args.push(t);
+        rest = r;
+    }
+
+    let ret = &rest[1..];
+    if ret == b"V" {
+        Some((args, None))
+    } else {
+        Some((args, Some(parse_field_desc(ret)?)))
+    }
+}
+
+/// The slots of the args, by the method descriptor
+pub(crate) fn args_size(desc: &[u8]) -> Option<usize> {
+    parse_method_desc(desc).map(|(args, _)| args.iter().map(VType::size).sum())
+}
+
+fn utf8(cp: &[CpType], i: U2) -> Option<&[u8]> {
+    match cp.get(i as usize) {
+        Some(CpType::Utf8 { bytes }) => Some(bytes.as_slice()),
+        _ => None,
+    }
+}
+
+fn class_name(cp: &[CpType], i: U2) -> Option<&[u8]> {
+    match cp.get(i as usize) {
+        Some(CpType::Class { name_index }) => utf8(cp, *name_index),
+        _ => None,
+    }
+}
+
+//the name & the descriptor of a field, a method or a dynamic constant
+fn name_and_type(cp: &[CpType], i: U2) -> Option<(&[u8], &[u8])> {
+    let nt = match cp.get(i as usize)? {
+        CpType::FieldRef {
+            name_and_type_index,
+            ..
+        }
+        | CpType::MethodRef {
+            name_and_type_index,
+            ..
+        }
+        | CpType::InterfaceMethodRef {
+            name_and_type_index,
+            ..
+        }
+        | CpType::Dynamic {
+            name_and_type_index,
+            ..
+        }
+        | CpType::InvokeDynamic {
+            name_and_type_index,
+            ..
+        } => *name_and_type_index,
+        _ => return None,
+    };
+    match cp.get(nt as usize)? {
+        CpType::NameAndType {
+            name_index,
+            desc_index,
+        } => Some((utf8(cp, *name_index)?, utf8(cp, *desc_index)?)),
+        _ => None,
+    }
+}
+
+fn constant_type(cp: &[CpType], i: U2) -> Option<VType> {
+    let t = match cp.get(i as usize)? {
+        CpType::Integer { .. } => VType::Integer,
+        CpType::Float { .. } => VType::Float,
+        CpType::Long { .. } => VType::Long,
+        CpType::Double { .. } => VType::Double,
+        CpType::String { .. } => VType::Object(J_STRING.to_vec()),
+        CpType::Class { .. } => VType::Object(J_CLASS.to_vec()),
+        CpType::MethodType { .. } => VType::Object(J_METHODTYPE.to_vec()),
+        CpType::MethodHandle { .. } => VType::Object(J_METHODHANDLE.to_vec()),
+        CpType::Dynamic { .. } => parse_field_desc(name_and_type(cp, i)?.1)?,
+        _ => return None,
+    };
+    Some(t)
+}
+
+//the class of the elements by the descriptor, None if primitive
+fn element_name(desc: &[u8]) -> Option<&[u8]> {
+    match desc.first() {
+        Some(b'L') => Some(&desc[1..desc.len() - 1]),
+        Some(b'[') => Some(desc),
+        _ => None,
+    }
+}
+
+//the descriptor of the array of the class
+fn array_of(name: &[u8]) -> Vec<u8> {
+    let mut desc = vec![b'['];
+    if name.starts_with(b"[") {
+        desc.extend_from_slice(name);
+    } else {
+        desc.push(b'L');
+        desc.extend_from_slice(name);
+        desc.push(b';');
+    }
+    desc
+}
+
+fn u2(code: &[U1], pc: usize) -> U2 {
+    U2::from_be_bytes([code[pc], code[pc + 1]])
+}
+
+fn i4(code: &[U1], pc: usize) -> i32 {
+    i32::from_be_bytes([code[pc], code[pc + 1], code[pc + 2], code[pc + 3]])
+}
+
+fn bad(pc: usize, msg: &'static str) -> BuildError {
+    BuildError::BadCode(pc, msg)
+}
+
+//the length of the instruction at pc
+fn insn_len(code: &[U1], pc: usize) -> Result<usize, BuildError> {
+    let pad = (4 - (pc + 1) % 4) % 4;
+    let n = match OpCode::from(code[pc]) {
+        OpCode::bipush
+        | OpCode::ldc
+        | OpCode::iload
+        | OpCode::lload
+        | OpCode::fload
+        | OpCode::dload
+        | OpCode::aload
+        | OpCode::istore
+        | OpCode::lstore
+        | OpCode::fstore
+        | OpCode::dstore
+        | OpCode::astore
+        | OpCode::ret
+        | OpCode::newarray => 2,
+        OpCode::sipush
+        | OpCode::ldc_w
+        | OpCode::ldc2_w
+        | OpCode::iinc
+        | OpCode::ifeq
+        | OpCode::ifne
+        | OpCode::iflt
+        | OpCode::ifge
+        | OpCode::ifgt
+        | OpCode::ifle
+        | OpCode::if_icmpeq
+        | OpCode::if_icmpne
+        | OpCode::if_icmplt
+        | OpCode::if_icmpge
+        | OpCode::if_icmpgt
+        | OpCode::if_icmple
+        | OpCode::if_acmpeq
+        | OpCode::if_acmpne
+        | OpCode::goto
+        | OpCode::jsr
+        | OpCode::getstatic
+        | OpCode::putstatic
+        | OpCode::getfield
+        | OpCode::putfield
+        | OpCode::invokevirtual
+        | OpCode::invokespecial
+        | OpCode::invokestatic
+        | OpCode::new
+        | OpCode::anewarray
+        | OpCode::checkcast
+        | OpCode::instanceof
+        | OpCode::ifnull
+        | OpCode::ifnonnull => 3,
+        OpCode::multianewarray => 4,
+        OpCode::invokeinterface | OpCode::invokedynamic | OpCode::goto_w | OpCode::jsr_w => 5,
+        OpCode::wide => match code.get(pc + 1).map(|op| OpCode::from(*op)) {
+            Some(OpCode::iinc) => 6,
+            _ => 4,
+        },
+        OpCode::tableswitch if pc + pad + 13 <= code.len() => {
+            let low = i4(code, pc + 1 + pad + 4) as i64;
+            let high = i4(code, pc + 1 + pad + 8) as i64;
+            if high < low {
+                return Err(bad(pc, "tableswitch of high < low"));
+            }
+            1 + pad + 12 + 4 * (high - low + 1) as usize
+        }
+        OpCode::lookupswitch if pc + pad + 9 <= code.len() => {
+            let n = i4(code, pc + 1 + pad + 4);
+            if n < 0 {
+                return Err(bad(pc, "lookupswitch of negative npairs"));
+            }
+            1 + pad + 8 + 8 * n as usize
+        }
+        OpCode::tableswitch | OpCode::lookupswitch => code.len(),
+        _ => 1,
+    };
+
+    if pc + n > code.len() {
+        Err(bad(pc, "truncated instruction"))
+    } else {
+        Ok(n)
+    }
+}
+
+//the local variable accessed, by xload, xstore, iinc & their short & wide forms
+fn local_access(code: &[U1], pc: usize) -> Option<(OpCode, usize)> {
+    let op = code[pc];
+    let (iload_0, istore_0) = (OpCode::iload_0 as U1, OpCode::istore_0 as U1);
+    if op >= iload_0 && op < iload_0 + 20 {
+        let base = OpCode::iload as U1 + (op - iload_0) / 4;
+        return Some((OpCode::from(base), ((op - iload_0) % 4) as usize));
+    }
+    if op >= istore_0 && op < istore_0 + 20 {
+        let base = OpCode::istore as U1 + (op - istore_0) / 4;
+        return Some((OpCode::from(base), ((op - istore_0) % 4) as usize));
+    }
+
+    match OpCode::from(op) {
+        OpCode::iload
+        | OpCode::lload
+        | OpCode::fload
+        | OpCode::dload
+        | OpCode::aload
+        | OpCode::istore
+        | OpCode::lstore
+        | OpCode::fstore
+        | OpCode::dstore
+        | OpCode::astore
+        | OpCode::iinc
+        | OpCode::ret => Some((OpCode::from(op), code[pc + 1] as usize)),
+        OpCode::wide => Some((OpCode::from(code[pc + 1]), u2(code, pc + 2) as usize)),
+        _ => None,
+    }
+}
+
+//the targets of the branch at pc, & whether the next one follows
+fn successors(code: &[U1], pc: usize) -> Result<(Vec<usize>, bool), BuildError> {
+    let target = |delta: i32| -> Result<usize, BuildError> {
+        let t = pc as i64 + delta as i64;
+        if t < 0 || t >= code.len() as i64 {
+            Err(bad(pc, "branch out of the code"))
+        } else {
+            Ok(t as usize)
+        }
+    };
+    let short = || target(i16::from_be_bytes([code[pc + 1], code[pc + 2]]) as i32);
+    let pad = (4 - (pc + 1) % 4) % 4;
+
+    let v = match OpCode::from(code[pc]) {
+        OpCode::ifeq
+        | OpCode::ifne
+        | OpCode::iflt
+        | OpCode::ifge
+        | OpCode::ifgt
+        | OpCode::ifle
+        | OpCode::if_icmpeq
+        | OpCode::if_icmpne
+        | OpCode::if_icmplt
+        | OpCode::if_icmpge
+        | OpCode::if_icmpgt
+        | OpCode::if_icmple
+        | OpCode::if_acmpeq
+        | OpCode::if_acmpne
+        | OpCode::ifnull
+        | OpCode::ifnonnull => (vec![short()?], true),
+        OpCode::goto => (vec![short()?], false),
+        OpCode::goto_w => (vec![target(i4(code, pc + 1))?], false),
+        OpCode::tableswitch => {
+            let base = pc + 1 + pad;
+            let n = (i4(code, base + 8) - i4(code, base + 4) + 1) as usize;
+            let mut targets = vec![target(i4(code, base))?];
+            for i in 0..n {
+                targets.push(target(i4(code, base + 12 + 4 * i))?);
+            }
+            (targets, false)
+        }
+        OpCode::lookupswitch => {
+            let base = pc + 1 + pad;
+            let n = i4(code, base + 4) as usize;
+            let mut targets = vec![target(i4(code, base))?];
+            for i in 0..n {
+                targets.push(target(i4(code, base + 12 + 8 * i))?);
+            }
+            (targets, false)
+        }
+        OpCode::ireturn
+        | OpCode::lreturn
+        | OpCode::freturn
+        | OpCode::dreturn
+        | OpCode::areturn
+        | OpCode::return_void
+        | OpCode::athrow => (vec![], false),
+        OpCode::jsr | OpCode::jsr_w | OpCode::ret => {
+            return Err(bad(pc, "jsr & ret are not supported"))
+        }
+        OpCode::wide if OpCode::from(code[pc + 1]) == OpCode::ret => {
+            return Err(bad(pc, "jsr & ret are not supported"))
+        }
+        _ => (vec![], true),
+    };
+    Ok(v)
+}
+
+struct Analyzer<'a> {
+    cp: &'a [CpType],
+    code: &'a [U1],
+    this_class: &'a [u8],
+    common_super_class: &'a CommonSuperClass,
+    //the frames of the blocks, None if not reached yet
+    frames: Vec<Option<Frame>>,
+    work: Vec<usize>,
+    max_stack: usize,
+    //the instruction executed
+    pc: usize,
+}
+
+impl<'a> Analyzer<'a> {
+    fn bad(&self, msg: &'static str) -> BuildError {
+        bad(self.pc, msg)
+    }
+
+    fn pop(&self, f: &mut Frame) -> Result<VType, BuildError> {
+        f.stack.pop().ok_or_else(|| self.bad("stack underflow"))
+    }
+
+    fn pop_n(&self, f: &mut Frame, n: usize) -> Result<(), BuildError> {
+        for _ in 0..n {
+            self.pop(f)?;
+        }
+        Ok(())
+    }
+
+    //the values of n slots, the bottom first
+    fn pop_slots(&self, f: &mut Frame, n: usize) -> Result<Vec<VType>, BuildError> {
+        let mut v = vec![];
+        let mut size = 0;
+        while size < n {
+            let t = self.pop(f)?;
+            size += t.size();
+            v.insert(0, t);
+        }
+        if size != n {
+            return Err(self.bad("a long or double is split"));
+        }
+        Ok(v)
+    }
+
+    fn push(&mut self, f: &mut Frame, t: VType) {
+        f.stack.push(t);
+        let size = f.stack.iter().map(VType::size).sum();
+        self.max_stack = self.max_stack.max(size);
+    }
+
+    //dup, dup_x1... duplicate 'n' slots, under them 'skip' slots
+    fn dup(&mut self, f: &mut Frame, n: usize, skip: usize) -> Result<(), BuildError> {
+        let top = self.pop_slots(f, n)?;
+        let under = self.pop_slots(f, skip)?;
+        for t in top.iter().chain(under.iter()).chain(top.iter()) {
+            self.push(f, t.clone());
+        }
+        Ok(())
+    }
+
+    fn store(&self, f: &mut Frame, index: usize, t: VType) -> Result<(), BuildError> {
+        if index + t.size() > f.locals.len() {
+            return Err(self.bad("local variable out of max_locals"));
+        }
+        //the 2nd half of a long or double is overwritten
+        if index > 0 && f.locals[index - 1].size() == 2 {
+            f.locals[index - 1] = VType::Top;
+        }
+        if t.size() == 2 {
+            f.locals[index + 1] = VType::Top;
+        }
+        f.locals[index] = t;
+        Ok(())
+    }
+
+    fn local(&mut self, f: &mut Frame, op: OpCode, index: usize) -> Result<(), BuildError> {
+        let load = |t: VType| (Some(t), false);
+        let (t, store) = match op {
+            OpCode::iload => load(VType::Integer),
+            OpCode::lload => load(VType::Long),
+            OpCode::fload => load(VType::Float),
+            OpCode::dload => load(VType::Double),
+            OpCode::aload => (None, false),
+            OpCode::istore => (Some(VType::Integer), true),
+            OpCode::lstore => (Some(VType::Long), true),
+            OpCode::fstore => (Some(VType::Float), true),
+            OpCode::dstore => (Some(VType::Double), true),
+            OpCode::astore => (None, true),
+            OpCode::iinc => return Ok(()),
+            _ => return Err(self.bad("jsr & ret are not supported")),
+        };
+
+        if store {
+            let v = self.pop(f)?;
+            self.store(f, index, t.unwrap_or(v))
+        } else {
+            let v = f
+                .locals
+                .get(index)
+                .cloned()
+                .ok_or_else(|| self.bad("local variable out of max_locals"))?;
+            self.push(f, t.unwrap_or(v));
+            Ok(())
+        }
+    }
+
+    //the instance initialized by <init>, all the references of it
+    fn initialize(&self, f: &mut Frame, receiver: VType) -> Result<(), BuildError> {
+        let init = match &receiver {
+            VType::UninitializedThis => VType::Object(self.this_class.to_vec()),
+            VType::Uninitialized(at) => {
+                let name = class_name(self.cp, u2(self.code, at + 1))
+                    .ok_or_else(|| self.bad("bad class of new"))?;
+                VType::Object(name.to_vec())
+            }
+            _ => return Err(self.bad("<init> of an initialized instance")),
+        };
+
+        for it in f.locals.iter_mut().chain(f.stack.iter_mut()) {
+            if *it == receiver {
+                *it = init.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, f: &mut Frame) -> Result<(), BuildError> {
+        let (code, pc) = (self.code, self.pc);
+        if let Some((op, index)) = local_access(code, pc) {
+            return self.local(f, op, index);
+        }
+
+        let index = || u2(code, pc + 1);
+        let op = OpCode::from(code[pc]);
+        match op {
+            OpCode::nop | OpCode::goto | OpCode::goto_w | OpCode::return_void => (),
+            OpCode::aconst_null => self.push(f, VType::Null),
+            OpCode::iconst_m1
+            | OpCode::iconst_0
+            | OpCode::iconst_1
+            | OpCode::iconst_2
+            | OpCode::iconst_3
+            | OpCode::iconst_4
+            | OpCode::iconst_5
+            | OpCode::bipush
+            | OpCode::sipush => self.push(f, VType::Integer),
+            OpCode::lconst_0 | OpCode::lconst_1 => self.push(f, VType::Long),
+            OpCode::fconst_0 | OpCode::fconst_1 | OpCode::fconst_2 => self.push(f, VType::Float),
+            OpCode::dconst_0 | OpCode::dconst_1 => self.push(f, VType::Double),
+            OpCode::ldc | OpCode::ldc_w | OpCode::ldc2_w => {
+                let i = if op == OpCode::ldc {
+                    code[pc + 1] as U2
+                } else {
+                    index()
+                };
+                let t = constant_type(self.cp, i).ok_or_else(|| self.bad("bad constant"))?;
+                self.push(f, t);
+            }
+            OpCode::iaload | OpCode::baload | OpCode::caload | OpCode::saload => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Integer);
+            }
+            OpCode::laload => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Long);
+            }
+            OpCode::faload => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Float);
+            }
+            OpCode::daload => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Double);
+            }
+            OpCode::aaload => {
+                self.pop(f)?;
+                let t = match self.pop(f)? {
+                    VType::Object(desc) if desc.starts_with(b"[") => {
+                        parse_field_desc(&desc[1..]).ok_or_else(|| self.bad("bad array type"))?
+                    }
+                    VType::Null => VType::Null,
+                    _ => return Err(self.bad("aaload of a non array")),
+                };
+                self.push(f, t);
+            }
+            OpCode::iastore
+            | OpCode::lastore
+            | OpCode::fastore
+            | OpCode::dastore
+            | OpCode::aastore
+            | OpCode::bastore
+            | OpCode::castore
+            | OpCode::sastore => self.pop_n(f, 3)?,
+            OpCode::pop => {
+                self.pop_slots(f, 1)?;
+            }
+            OpCode::pop2 => {
+                self.pop_slots(f, 2)?;
+            }
+            OpCode::dup => self.dup(f, 1, 0)?,
+            OpCode::dup_x1 => self.dup(f, 1, 1)?,
+            OpCode::dup_x2 => self.dup(f, 1, 2)?,
+            OpCode::dup2 => self.dup(f, 2, 0)?,
+            OpCode::dup2_x1 => self.dup(f, 2, 1)?,
+            OpCode::dup2_x2 => self.dup(f, 2, 2)?,
+            OpCode::swap => {
+                let v1 = self.pop(f)?;
+                let v2 = self.pop(f)?;
+                self.push(f, v1);
+                self.push(f, v2);
+            }
+            OpCode::iadd
+            | OpCode::isub
+            | OpCode::imul
+            | OpCode::idiv
+            | OpCode::irem
+            | OpCode::ishl
+            | OpCode::ishr
+            | OpCode::iushr
+            | OpCode::iand
+            | OpCode::ior
+            | OpCode::ixor
+            | OpCode::lcmp
+            | OpCode::fcmpl
+            | OpCode::fcmpg
+            | OpCode::dcmpl
+            | OpCode::dcmpg => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Integer);
+            }
+            OpCode::ladd
+            | OpCode::lsub
+            | OpCode::lmul
+            | OpCode::ldiv
+            | OpCode::lrem
+            | OpCode::lshl
+            | OpCode::lshr
+            | OpCode::lushr
+            | OpCode::land
+            | OpCode::lor
+            | OpCode::lxor => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Long);
+            }
+            OpCode::fadd | OpCode::fsub | OpCode::fmul | OpCode::fdiv | OpCode::frem => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Float);
+            }
+            OpCode::dadd | OpCode::dsub | OpCode::dmul | OpCode::ddiv | OpCode::drem => {
+                self.pop_n(f, 2)?;
+                self.push(f, VType::Double);
+            }
+            OpCode::ineg
+            | OpCode::l2i
+            | OpCode::f2i
+            | OpCode::d2i
+            | OpCode::i2b
+            | OpCode::i2c
+            | OpCode::i2s
+            | OpCode::arraylength
+            | OpCode::instanceof => {
+                self.pop(f)?;
+                self.push(f, VType::Integer);
+            }
+            OpCode::lneg | OpCode::i2l | OpCode::f2l | OpCode::d2l => {
+                self.pop(f)?;
+                self.push(f, VType::Long);
+            }
+            OpCode::fneg | OpCode::i2f | OpCode::l2f | OpCode::d2f => {
+                self.pop(f)?;
+                self.push(f, VType::Float);
+            }
+            OpCode::dneg | OpCode::i2d | OpCode::l2d | OpCode::f2d => {
+                self.pop(f)?;
+                self.push(f, VType::Double);
+            }
+            OpCode::ifeq
+            | OpCode::ifne
+            | OpCode::iflt
+            | OpCode::ifge
+            | OpCode::ifgt
+            | OpCode::ifle
+            | OpCode::ifnull
+            | OpCode::ifnonnull
+            | OpCode::tableswitch
+            | OpCode::lookupswitch
+            | OpCode::ireturn
+            | OpCode::lreturn
+            | OpCode::freturn
+            | OpCode::dreturn
+            | OpCode::areturn
+            | OpCode::athrow
+            | OpCode::monitorenter
+            | OpCode::monitorexit
+            | OpCode::putstatic => self.pop_n(f, 1)?,
+            OpCode::if_icmpeq
+            | OpCode::if_icmpne
+            | OpCode::if_icmplt
+            | OpCode::if_icmpge
+            | OpCode::if_icmpgt
+            | OpCode::if_icmple
+            | OpCode::if_acmpeq
+            | OpCode::if_acmpne
+            | OpCode::putfield => self.pop_n(f, 2)?,
+            OpCode::getstatic | OpCode::getfield => {
+                let t = name_and_type(self.cp, index())
+                    .and_then(|(_, desc)| parse_field_desc(desc))
+                    .ok_or_else(|| self.bad("bad field"))?;
+                if op == OpCode::getfield {
+                    self.pop(f)?;
+                }
+                self.push(f, t);
+            }
+            OpCode::invokevirtual
+            | OpCode::invokespecial
+            | OpCode::invokestatic
+            | OpCode::invokeinterface
+            | OpCode::invokedynamic => {
+                let (name, desc) =
+                    name_and_type(self.cp, index()).ok_or_else(|| self.bad("bad method"))?;
+                let (args, ret) =
+                    parse_method_desc(desc).ok_or_else(|| self.bad("bad method descriptor"))?;
+                self.pop_n(f, args.len())?;
+                if op != OpCode::invokestatic && op != OpCode::invokedynamic {
+                    let receiver = self.pop(f)?;
+                    if op == OpCode::invokespecial && name == b"<init>" {
+                        self.initialize(f, receiver)?;
+                    }
+                }
+                if let Some(t) = ret {
+                    self.push(f, t);
+                }
+            }
+            OpCode::new => self.push(f, VType::Uninitialized(pc)),
+            OpCode::newarray => {
+                let desc: &[u8] = match code[pc + 1] {
+                    4 => b"[Z",
+                    5 => b"[C",
+                    6 => b"[F",
+                    7 => b"[D",
+                    8 => b"[B",
+                    9 => b"[S",
+                    10 => b"[I",
+                    11 => b"[J",
+                    _ => return Err(self.bad("bad newarray type")),
+                };
+                self.pop(f)?;
+                self.push(f, VType::Object(desc.to_vec()));
+            }
+            OpCode::anewarray | OpCode::checkcast => {
+                let name = class_name(self.cp, index()).ok_or_else(|| self.bad("bad class"))?;
+                let t = if op == OpCode::anewarray {
+                    array_of(name)
+                } else {
+                    name.to_vec()
+                };
+                self.pop(f)?;
+                self.push(f, VType::Object(t));
+            }
+            OpCode::multianewarray => {
+                let name = class_name(self.cp, index()).ok_or_else(|| self.bad("bad class"))?;
+                self.pop_n(f, code[pc + 3] as usize)?;
+                self.push(f, VType::Object(name.to_vec()));
+            }
+            _ => return Err(self.bad("unknown instruction")),
+        }
+
+        Ok(())
+    }
+
+    //the common super class of two references, the arrays of references are
+    //covariant, other arrays are merged to Object
+    fn common_super_class(&self, a: &[u8], b: &[u8]) -> Vec<u8> {
+        if a == b {
+            return a.to_vec();
+        }
+
+        match (a.first(), b.first()) {
+            (Some(b'['), Some(b'[')) => match (element_name(&a[1..]), element_name(&b[1..])) {
+                (Some(ea), Some(eb)) => array_of(&self.common_super_class(ea, eb)),
+                _ => J_OBJECT.to_vec(),
+            },
+            (Some(b'['), _) | (_, Some(b'[')) => J_OBJECT.to_vec(),
+            _ => mutf8(&(self.common_super_class)(&from_mutf8(a), &from_mutf8(b))),
+        }
+    }
+
+    fn merge_type(&self, a: &VType, b: &VType) -> VType {
+        match (a, b) {
+            _ if a == b => a.clone(),
+            (VType::Null, VType::Object(_)) => b.clone(),
+            (VType::Object(_), VType::Null) => a.clone(),
+            (VType::Object(a), VType::Object(b)) => VType::Object(self.common_super_class(a, b)),
+            _ => VType::Top,
+        }
+    }
+
+    //merge the frame into the block at pc, it's visited again if changed
+    fn merge(&mut self, pc: usize, f: &Frame) -> Result<(), BuildError> {
+        let merged = match &self.frames[pc] {
+            None => f.clone(),
+            Some(old) => {
+                if old.stack.len() != f.stack.len() {
+                    return Err(bad(pc, "stack heights differ"));
+                }
+
+                let mut stack = Vec::with_capacity(f.stack.len());
+                for (a, b) in old.stack.iter().zip(f.stack.iter()) {
+                    let t = self.merge_type(a, b);
+                    if t == VType::Top && *a != VType::Top {
+                        return Err(bad(pc, "stack types differ"));
+                    }
+                    stack.push(t);
+                }
+                let locals = old
+                    .locals
+                    .iter()
+                    .zip(f.locals.iter())
+                    .map(|(a, b)| self.merge_type(a, b))
+                    .collect();
+                let merged = Frame { locals, stack };
+                if merged == *old {
+                    return Ok(());
+                }
+                merged
+            }
+        };
+
+        self.frames[pc] = Some(merged);
+        self.work.push(pc);
+        Ok(())
+    }
+}
+
+fn verification_type(cp: &mut ConstantPoolBuilder, t: &VType) -> VerificationTypeInfo {
+    match t {
+        VType::Top => VerificationTypeInfo::Top,
+        VType::Integer => VerificationTypeInfo::Integer,
+        VType::Float => VerificationTypeInfo::Float,
+        VType::Long => VerificationTypeInfo::Long,
+        VType::Double => VerificationTypeInfo::Double,
+        VType::Null => VerificationTypeInfo::Null,
+        VType::UninitializedThis => VerificationTypeInfo::UninitializedThis,
+        VType::Object(name) => VerificationTypeInfo::Object {
+            cpool_index: cp.class_bytes(name),
+        },
+        VType::Uninitialized(offset) => VerificationTypeInfo::Uninitialized {
+            offset: *offset as U2,
+        },
+    }
+}
+
+//the locals of a frame, a long or double is an entry, the Tops at the end dropped
+fn frame_locals(locals: &[VType]) -> Vec<VType> {
+    let mut v = vec![];
+    let mut i = 0;
+    while i < locals.len() {
+        v.push(locals[i].clone());
+        i += locals[i].size();
+    }
+    while v.last() == Some(&VType::Top) {
+        v.pop();
+    }
+    v
+}
+
+//the frames of StackMapTable, each one is by the delta of the previous one
+fn encode(
+    cp: &mut ConstantPoolBuilder,
+    initial: &Frame,
+    frames: &[(usize, Frame)],
+) -> Vec<StackMapFrame> {
+    let mut entries = vec![];
+    let mut prev = frame_locals(&initial.locals);
+    let mut prev_pc = None;
+    for (pc, f) in frames {
+        let offset_delta = match prev_pc {
+            None => *pc,
+            Some(prev_pc) => pc - prev_pc - 1,
+        } as U2;
+        let locals = frame_locals(&f.locals);
+        let stack_len = f.stack.len();
+
+        let entry = if stack_len == 0 && locals == prev {
+            if offset_delta < 64 {
+                StackMapFrame::Same {
+                    tag: offset_delta as U1,
+                    offset_delta,
+                }
+            } else {
+                StackMapFrame::SameExtended {
+                    tag: 251,
+                    offset_delta,
+                }
+            }
+        } else if stack_len == 1 && locals == prev {
+            let stack = [verification_type(cp, &f.stack[0])];
+            if offset_delta < 64 {
+                StackMapFrame::SameLocals1StackItem {
+                    tag: 64 + offset_delta as U1,
+                    offset_delta,
+                    stack,
+                }
+            } else {
+                StackMapFrame::SameLocals1StackItemExtended {
+                    tag: 247,
+                    offset_delta,
+                    stack,
+                }
+            }
+        } else if stack_len == 0
+            && locals.len() < prev.len()
+            && prev.len() - locals.len() <= 3
+            && prev.starts_with(&locals)
+        {
+            StackMapFrame::Chop {
+                tag: (251 - (prev.len() - locals.len())) as U1,
+                offset_delta,
+            }
+        } else if stack_len == 0
+            && locals.len() > prev.len()
+            && locals.len() - prev.len() <= 3
+            && locals.starts_with(&prev)
+        {
+            StackMapFrame::Append {
+                tag: (251 + (locals.len() - prev.len())) as U1,
+                offset_delta,
+                locals: locals[prev.len()..]
+                    .iter()
+                    .map(|t| verification_type(cp, t))
+                    .collect(),
+            }
+        } else {
+            StackMapFrame::Full {
+                tag: 255,
+                offset_delta,
+                locals: locals.iter().map(|t| verification_type(cp, t)).collect(),
+                stack: f.stack.iter().map(|t| verification_type(cp, t)).collect(),
+            }
+        };
+
+        entries.push(entry);
+        prev = locals;
+        prev_pc = Some(*pc);
+    }
+
+    entries
+}
+
+//the exception ranges without the unreachable blocks
+fn remove_ranges(exceptions: &mut Vec<CodeException>, dead: &[(usize, usize)]) {
+    let mut v = vec![];
+    for e in exceptions.iter() {
+        let mut ranges = vec![(e.start_pc as usize, e.end_pc as usize)];
+        for &(start, end) in dead {
+            ranges = ranges
+                .into_iter()
+                .flat_map(|(s, t)| {
+                    if end <= s || start >= t {
+                        vec![(s, t)]
+                    } else {
+                        let mut v = vec![];
+                        if s < start {
+                            v.push((s, start));
+                        }
+                        if end < t {
+                            v.push((end, t));
+                        }
+                        v
+                    }
+                })
+                .collect();
+        }
+
+        for (s, t) in ranges {
+            v.push(CodeException {
+                start_pc: s as U2,
+                end_pc: t as U2,
+                ..e.clone()
+            });
+        }
+    }
+    *exceptions = v;
+}
+
+pub(crate) fn compute(
+    cp: &mut ConstantPoolBuilder,
+    common_super_class: &CommonSuperClass,
+    method: &Method,
+    code: &mut Vec<U1>,
+    exceptions: &mut Vec<CodeException>,
+) -> Result<Computed, BuildError> {
+    let (args, _) = parse_method_desc(method.desc)
+        .ok_or_else(|| BuildError::BadDescriptor(from_mutf8(method.desc)))?;
+    let len = code.len();
+    if len == 0 {
+        return Err(bad(0, "empty code"));
+    }
+
+    //the instructions & the blocks
+    let mut is_insn = vec![false; len + 1];
+    let mut is_block = vec![false; len];
+    let mut is_target = vec![false; len];
+    let mut max_locals = 0;
+    let mut pc = 0;
+    while pc < len {
+        is_insn[pc] = true;
+        let n = insn_len(code, pc)?;
+        if let Some((op, index)) = local_access(code, pc) {
+            let size = match op {
+                OpCode::lload | OpCode::dload | OpCode::lstore | OpCode::dstore => 2,
+                _ => 1,
+            };
+            max_locals = max_locals.max(index + size);
+        }
+
+        let (targets, next) = successors(code, pc)?;
+        for t in targets {
+            is_block[t] = true;
+            is_target[t] = true;
+        }
+        if !next && pc + n < len {
+            is_block[pc + n] = true;
+        }
+        pc += n;
+    }
+    is_insn[len] = true;
+    is_block[0] = true;
+    for e in exceptions.iter() {
+        let (start, end, handler) = (
+            e.start_pc as usize,
+            e.end_pc as usize,
+            e.handler_pc as usize,
+        );
+        if handler >= len {
+            return Err(bad(handler, "handler out of the code"));
+        }
+        if !is_insn[start] || !is_insn[end] || !is_insn[handler] {
+            return Err(bad(
+                start,
+                "exception range in the middle of an instruction",
+            ));
+        }
+        is_block[handler] = true;
+        is_target[handler] = true;
+    }
+    for pc in 0..len {
+        if is_block[pc] && !is_insn[pc] {
+            return Err(bad(pc, "branch into the middle of an instruction"));
+        }
+    }
+
+    //the args
+    let mut locals = vec![];
+    if method.acc_flags & ACC_STATIC == 0 {
+        locals.push(if method.is_init {
+            VType::UninitializedThis
+        } else {
+            VType::Object(method.this_class.to_vec())
+        });
+    }
+    for t in args {
+        let size = t.size();
+        locals.push(t);
+        if size == 2 {
+            locals.push(VType::Top);
+        }
+    }
+    let max_locals = max_locals.max(locals.len());
+    locals.resize(max_locals, VType::Top);
+    let initial = Frame {
+        locals,
+        stack: vec![],
+    };
+
+    let handler_types = exceptions
+        .iter()
+        .map(|e| match e.catch_type {
+            0 => Some(J_THROWABLE),
+            i => class_name(&cp.entries, i),
+        })
+        .map(|it| it.map(|name| VType::Object(name.to_vec())))
+        .collect::<Option<Vec<VType>>>()
+        .ok_or_else(|| bad(0, "bad catch type"))?;
+
+    let mut an = Analyzer {
+        cp: &cp.entries,
+        code,
+        this_class: method.this_class,
+        common_super_class,
+        frames: vec![None; len],
+        work: vec![0],
+        max_stack: 0,
+        pc: 0,
+    };
+    an.frames[0] = Some(initial.clone());
+
+    while let Some(start) = an.work.pop() {
+        let mut f = an.frames[start].clone().unwrap();
+        let mut pc = start;
+        loop {
+            an.pc = pc;
+            let stores = match local_access(code, pc) {
+                Some((op, _)) => matches!(
+                    op,
+                    OpCode::istore
+                        | OpCode::lstore
+                        | OpCode::fstore
+                        | OpCode::dstore
+                        | OpCode::astore
+                ),
+                None => false,
+            };
+            let handlers = exceptions
+                .iter()
+                .zip(handler_types.iter())
+                .filter(|(e, _)| e.start_pc as usize <= pc && pc < e.end_pc as usize)
+                .map(|(e, t)| (e.handler_pc as usize, t.clone()))
+                .collect::<Vec<(usize, VType)>>();
+            for (handler, t) in handlers.iter() {
+                let catch = Frame {
+                    locals: f.locals.clone(),
+                    stack: vec![t.clone()],
+                };
+                an.merge(*handler, &catch)?;
+            }
+
+            an.execute(&mut f)?;
+
+            //the locals stored are seen by the handlers too
+            if stores {
+                for (handler, t) in handlers {
+                    let catch = Frame {
+                        locals: f.locals.clone(),
+                        stack: vec![t],
+                    };
+                    an.merge(handler, &catch)?;
+                }
+            }
+
+            let (targets, next) = successors(code, pc)?;
+            for t in targets {
+                an.merge(t, &f)?;
+            }
+            if !next {
+                break;
+            }
+            pc += insn_len(code, pc)?;
+            if pc >= len {
+                return Err(bad(an.pc, "falling off the end of the code"));
+            }
+            if is_block[pc] {
+                an.merge(pc, &f)?;
+                break;
+            }
+        }
+    }
+
+    let mut max_stack = an.max_stack;
+    let frames = an.frames;
+    let blocks: Vec<usize> = (0..len).filter(|pc| is_block[*pc]).collect();
+    let mut out = vec![];
+    let mut dead = vec![];
+    for (i, &start) in blocks.iter().enumerate() {
+        let end = blocks.get(i + 1).copied().unwrap_or(len);
+        match &frames[start] {
+            Some(f) => {
+                if start != 0 || is_target[0] {
+                    out.push((start, f.clone()));
+                }
+            }
+            None => {
+                for it in code[start..end - 1].iter_mut() {
+                    *it = OpCode::nop as U1;
+                }
+                code[end - 1] = OpCode::athrow as U1;
+                let f = Frame {
+                    locals: vec![],
+                    stack: vec![VType::Object(J_THROWABLE.to_vec())],
+                };
+                out.push((start, f));
+                dead.push((start, end));
+                max_stack = max_stack.max(1);
+            }
+        }
+    }
+    remove_ranges(exceptions, &dead);
+
+    if max_stack > U2::MAX as usize {
+        return Err(BuildError::TooLarge("max_stack"));
+    }
+    if max_locals > U2::MAX as usize {
+        return Err(BuildError::TooLarge("max_locals"));
+    }
+
+    Ok(Computed {
+        max_stack: max_stack as U2,
+        max_locals: max_locals as U2,
+        frames: encode(cp, &initial, &out),
+    })
+}
@@ -0,0 +1,622 @@
+use crate::attributes::{StackMapFrame, Type as AttrType, VerificationTypeInfo};
+use crate::constant_pool::Type as CpType;
+use crate::consts::MAGIC;
+use crate::{ClassFile, ConstantPool, U1, U2, U4};
+use std::collections::HashMap;
+use std::fmt;
+
+/*
+The writer of the class file, the reverse of class_parser::parse_class
+
+The constant pool is written as is, the attributes refer to their names by
+the Utf8 entries of the constant pool. The raw bytes of the annotations &
+the unknown attributes are written back, so a class file read by the parser
+is written to the same bytes, unless the constant pool has several Utf8
+entries of the name of an attribute, the first one is taken.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteError {
+    /// the name of the attribute is not a Utf8 entry of the constant pool
+    MissingAttributeName(&'static str),
+    /// the entry #n can't be written, Unknown or a misplaced Nop
+    BadConstant(usize),
+    /// the count or the length is beyond the type of the class file
+    TooLarge(&'static str),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::MissingAttributeName(name) => {
+                write!(f, "No Utf8 entry of the attribute name {}", name)
+            }
+            WriteError::BadConstant(i) => write!(f, "Bad constant pool entry #{}", i),
+            WriteError::TooLarge(what) => write!(f, "Too large {}", what),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+type Result<T> = std::result::Result<T, WriteError>;
+
+impl ClassFile {
+    /// Serialize the class file
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut w = Writer::new(&self.cp);
+        w.u4(MAGIC);
+        w.u2(self.version.minor);
+        w.u2(self.version.major);
+        w.constant_pool()?;
+        w.u2(self.acc_flags);
+        w.u2(self.this_class);
+        w.u2(self.super_class);
+        w.u2s(&self.interfaces, "interfaces")?;
+
+        w.count(self.fields.len(), "fields")?;
+        for it in self.fields.iter() {
+            w.u2(it.acc_flags);
+            w.u2(it.name_index);
+            w.u2(it.desc_index);
+            w.attrs(&it.attrs)?;
+        }
+
+        w.count(self.methods.len(), "methods")?;
+        for it in self.methods.iter() {
+            w.u2(it.acc_flags);
+            w.u2(it.name_index);
+            w.u2(it.desc_index);
+            w.attrs(&it.attrs)?;
+        }
+
+        w.attrs(&self.attrs)?;
+
+        Ok(w.buf)
+    }
+}
+
+struct Writer<'a> {
+    cp: &'a ConstantPool,
+    //the first Utf8 entry of a name
+    names: HashMap<&'a [u8], U2>,
+    buf: Vec<u8>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(cp: &'a ConstantPool) -> Self {
+        let mut names = HashMap::new();
+        for (i, it) in cp.iter().enumerate() {
+            if let CpType::Utf8 { bytes } = it {
+                names.entry(bytes.as_slice()).or_insert(i as U2);
+            }
+        }
+
+        Self {
+            cp,
+            names,
+            buf: Vec::new(),
+        }
+    }
+
+    fn u1(&mut self, v: U1) {
+        self.buf.push(v);
+    }
+
+    fn u2(&mut self, v: U2) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn u4(&mut self, v: U4) {
+        self.buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn bytes(&mut self, v: &[u8]) {
+        self.buf.extend_from_slice(v);
+    }
+
+    fn count(&mut self, n: usize, what: &'static str) -> Result<()> {
+        if n > U2::MAX as usize {
+            return Err(WriteError::TooLarge(what));
+        }
+        self.u2(n as U2);
+        Ok(())
+    }
+
+    fn u2s(&mut self, v: &[U2], what: &'static str) -> Result<()> {
+        self.count(v.len(), what)?;
+        for it in v {
+            self.u2(*it);
+        }
+        Ok(())
+    }
+
+    fn constant_pool(&mut self) -> Result<()> {
+        let cp = self.cp;
+        self.count(cp.len(), "constant pool")?;
+
+        //#0 & the entry after Long or Double are Nop
+        let mut skip = true;
+        for (i, it) in cp.iter().enumerate() {
+            if skip {
+                skip = false;
+                if let CpType::Nop = it {
+                    continue;
+                }
+                return Err(WriteError::BadConstant(i));
+            }
+
+            match it {
+                CpType::Nop | CpType::Unknown => return Err(WriteError::BadConstant(i)),
+                CpType::Utf8 { bytes } => {
+                    self.u1(1);
+                    self.count(bytes.len(), "Utf8")?;
+                    self.bytes(bytes.as_slice());
+                }
+                CpType::Integer { v } => {
+                    self.u1(3);
+                    self.bytes(v);
+                }
+                CpType::Float { v } => {
+                    self.u1(4);
+                    self.bytes(v);
+                }
+                CpType::Long { v } => {
+                    self.u1(5);
+                    self.bytes(v);
+                    skip = true;
+                }
+                CpType::Double { v } => {
+                    self.u1(6);
+                    self.bytes(v);
+                    skip = true;
+                }
+                CpType::Class { name_index } => {
+                    self.u1(7);
+                    self.u2(*name_index);
+                }
+                CpType::String { string_index } => {
+                    self.u1(8);
+                    self.u2(*string_index);
+                }
+                CpType::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.u1(9);
+                    self.u2(*class_index);
+                    self.u2(*name_and_type_index);
+                }
+                CpType::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.u1(10);
+                    self.u2(*class_index);
+                    self.u2(*name_and_type_index);
+                }
+                CpType::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.u1(11);
+                    self.u2(*class_index);
+                    self.u2(*name_and_type_index);
+                }
+                CpType::NameAndType {
+                    name_index,
+                    desc_index,
+                } => {
+                    self.u1(12);
+                    self.u2(*name_index);
+                    self.u2(*desc_index);
+                }
+                CpType::MethodHandle {
+                    ref_kind,
+                    ref_index,
+                } => {
+                    self.u1(15);
+                    self.u1(*ref_kind);
+                    self.u2(*ref_index);
+                }
+                CpType::MethodType { desc_index } => {
+                    self.u1(16);
+                    self.u2(*desc_index);
+                }
+                CpType::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.u1(17);
+                    self.u2(*bootstrap_method_attr_index);
+                    self.u2(*name_and_type_index);
+                }
+                CpType::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.u1(18);
+                    self.u2(*bootstrap_method_attr_index);
+                    self.u2(*name_and_type_index);
+                }
+                CpType::Module { name_index } => {
+                    self.u1(19);
+                    self.u2(*name_index);
+                }
+                CpType::Package { name_index } => {
+                    self.u1(20);
+                    self.u2(*name_index);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn attrs(&mut self, attrs: &[AttrType]) -> Result<()> {
+        self.count(attrs.len(), "attributes")?;
+        for it in attrs {
+            self.attr(it)?;
+        }
+        Ok(())
+    }
+
+    fn attr(&mut self, attr: &AttrType) -> Result<()> {
+        let name_index = match attr {
+            AttrType::Unknown { name_index, .. } => *name_index,
+            _ => {
+                let name = attr_name(attr);
+                *self
+                    .names
+                    .get(name.as_bytes())
+                    .ok_or(WriteError::MissingAttributeName(name))?
+            }
+        };
+        self.u2(name_index);
+
+        //the length is patched after the content
+        let pos = self.buf.len();
+        self.u4(0);
+        self.attr_content(attr)?;
+        let len = self.buf.len() - pos - 4;
+        if len > U4::MAX as usize {
+            return Err(WriteError::TooLarge("attribute"));
+        }
+        self.buf[pos..pos + 4].copy_from_slice(&(len as U4).to_be_bytes());
+
+        Ok(())
+    }
+
+    fn attr_content(&mut self, attr: &AttrType) -> Result<()> {
+        match attr {
+            AttrType::ConstantValue {
+                constant_value_index,
+            } => self.u2(*constant_value_index),
+            AttrType::Code(code) => {
+                self.u2(code.max_stack);
+                self.u2(code.max_locals);
+                if code.code.len() > U4::MAX as usize {
+                    return Err(WriteError::TooLarge("code"));
+                }
+                self.u4(code.code.len() as U4);
+                self.bytes(code.code.as_slice());
+                self.count(code.exceptions.len(), "exception table")?;
+                for it in code.exceptions.iter() {
+                    self.u2(it.start_pc);
+                    self.u2(it.end_pc);
+                    self.u2(it.handler_pc);
+                    self.u2(it.catch_type);
+                }
+                self.attrs(&code.attrs)?;
+            }
+            AttrType::StackMapTable { entries } => {
+                self.count(entries.len(), "stack map frames")?;
+                for it in entries {
+                    self.stack_map_frame(it)?;
+                }
+            }
+            AttrType::Exceptions { exceptions } => self.u2s(exceptions, "exceptions")?,
+            AttrType::InnerClasses { classes } => {
+                self.count(classes.len(), "inner classes")?;
+                for it in classes {
+                    self.u2(it.inner_class_info_index);
+                    self.u2(it.outer_class_info_index);
+                    self.u2(it.inner_name_index);
+                    self.u2(it.inner_class_access_flags);
+                }
+            }
+            AttrType::EnclosingMethod { em } => {
+                self.u2(em.class_index);
+                self.u2(em.method_index);
+            }
+            AttrType::Synthetic | AttrType::Deprecated => (),
+            AttrType::Signature { signature_index } => self.u2(*signature_index),
+            AttrType::SourceFile { source_file_index } => self.u2(*source_file_index),
+            AttrType::SourceDebugExtension { debug_extension } => {
+                self.bytes(debug_extension.as_slice())
+            }
+            AttrType::LineNumberTable { tables } => {
+                self.count(tables.len(), "line numbers")?;
+                for it in tables {
+                    self.u2(it.start_pc);
+                    self.u2(it.number);
+                }
+            }
+            AttrType::LocalVariableTable { tables }
+            | AttrType::LocalVariableTypeTable { tables } => {
+                self.count(tables.len(), "local variables")?;
+                for it in tables {
+                    self.u2(it.start_pc);
+                    self.u2(it.length);
+                    self.u2(it.name_index);
+                    self.u2(it.signature_index);
+                    self.u2(it.index);
+                }
+            }
+            //the raw bytes are the content, as parsed
+            AttrType::RuntimeVisibleAnnotations { raw, .. }
+            | AttrType::RuntimeInvisibleAnnotations { raw, .. }
+            | AttrType::RuntimeVisibleParameterAnnotations { raw, .. }
+            | AttrType::RuntimeInvisibleParameterAnnotations { raw, .. }
+            | AttrType::RuntimeVisibleTypeAnnotations { raw, .. }
+            | AttrType::RuntimeInvisibleTypeAnnotations { raw, .. }
+            | AttrType::AnnotationDefault { raw, .. } => self.bytes(raw.as_slice()),
+            AttrType::BootstrapMethods { methods, .. } => {
+                self.count(methods.len(), "bootstrap methods")?;
+                for it in methods {
+                    self.u2(it.method_ref);
+                    self.u2s(&it.args, "bootstrap arguments")?;
+                }
+            }
+            AttrType::MethodParameters { parameters } => {
+                if parameters.len() > U1::MAX as usize {
+                    return Err(WriteError::TooLarge("method parameters"));
+                }
+                self.u1(parameters.len() as U1);
+                for it in parameters {
+                    self.u2(it.name_index);
+                    self.u2(it.acc_flags);
+                }
+            }
+            AttrType::Module(module) => {
+                self.u2(module.name_index);
+                self.u2(module.flags);
+                self.u2(module.version_index);
+                self.count(module.requires.len(), "requires")?;
+                for it in module.requires.iter() {
+                    self.u2(it.requires_index);
+                    self.u2(it.requires_flags);
+                    self.u2(it.requires_version_index);
+                }
+                self.count(module.exports.len(), "exports")?;
+                for it in module.exports.iter() {
+                    self.u2(it.exports_index);
+                    self.u2(it.exports_flags);
+                    self.u2s(&it.exports_to, "exports to")?;
+                }
+                self.count(module.opens.len(), "opens")?;
+                for it in module.opens.iter() {
+                    self.u2(it.opens_index);
+                    self.u2(it.opens_flags);
+                    self.u2s(&it.opens_to, "opens to")?;
+                }
+                self.u2s(&module.uses, "uses")?;
+                self.count(module.provides.len(), "provides")?;
+                for it in module.provides.iter() {
+                    self.u2(it.provides_index);
+                    self.u2s(&it.provides_with, "provides with")?;
+                }
+            }
+            AttrType::ModulePackages { packages } => self.u2s(packages, "packages")?,
+            AttrType::ModuleMainClass { main_class_index } => self.u2(*main_class_index),
+            AttrType::NestHost { host_class_index } => self.u2(*host_class_index),
+            AttrType::NestMembers { classes } | AttrType::PermittedSubclasses { classes } => {
+                self.u2s(classes, "classes")?
+            }
+            AttrType::Record { components } => {
+                self.count(components.len(), "record components")?;
+                for it in components {
+                    self.u2(it.name_index);
+                    self.u2(it.desc_index);
+                    self.attrs(&it.attrs)?;
+                }
+            }
+            AttrType::Unknown { info, .. } => self.bytes(info.as_slice()),
+        }
+
+        Ok(())
+    }
+
+    fn stack_map_frame(&mut self, frame: &StackMapFrame) -> Result<()> {
+        match frame {
+            StackMapFrame::Same { tag, .. } => self.u1(*tag),
+            //247 is the extended one, as the parser reads
+            StackMapFrame::SameLocals1StackItem {
+                tag,
+                offset_delta,
+                stack,
+            } => {
+                self.u1(*tag);
+                if *tag == 247 {
+                    self.u2(*offset_delta);
+                }
+                self.verification_type_info(&stack[0]);
+            }
+            StackMapFrame::SameLocals1StackItemExtended {
+                offset_delta,
+                stack,
+                ..
+            } => {
+                self.u1(247);
+                self.u2(*offset_delta);
+                self.verification_type_info(&stack[0]);
+            }
+            StackMapFrame::Chop { tag, offset_delta }
+            | StackMapFrame::SameExtended { tag, offset_delta } => {
+                self.u1(*tag);
+                self.u2(*offset_delta);
+            }
+            StackMapFrame::Append {
+                tag,
+                offset_delta,
+                locals,
+            } => {
+                self.u1(*tag);
+                self.u2(*offset_delta);
+                for it in locals {
+                    self.verification_type_info(it);
+                }
+            }
+            StackMapFrame::Full {
+                tag,
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                self.u1(*tag);
+                self.u2(*offset_delta);
+                self.count(locals.len(), "locals")?;
+                for it in locals {
+                    self.verification_type_info(it);
+                }
+                self.count(stack.len(), "stack")?;
+                for it in stack {
+                    self.verification_type_info(it);
+                }
+            }
+            StackMapFrame::Reserved(tag) => self.u1(*tag),
+        }
+
+        Ok(())
+    }
+
+    fn verification_type_info(&mut self, v: &VerificationTypeInfo) {
+        match v {
+            VerificationTypeInfo::Top => self.u1(0),
+            VerificationTypeInfo::Integer => self.u1(1),
+            VerificationTypeInfo::Float => self.u1(2),
+            VerificationTypeInfo::Double => self.u1(3),
+            VerificationTypeInfo::Long => self.u1(4),
+            VerificationTypeInfo::Null => self.u1(5),
+            VerificationTypeInfo::UninitializedThis => self.u1(6),
+            VerificationTypeInfo::Object { cpool_index } => {
+                self.u1(7);
+                self.u2(*cpool_index);
+            }
+            VerificationTypeInfo::Uninitialized { offset } => {
+                self.u1(8);
+                self.u2(*offset);
+            }
+        }
+    }
+}
+
+pub(crate) fn attr_name(attr: &AttrType) -> &'static str {
+    match attr {
+        AttrType::ConstantValue { .. } => "ConstantValue",
+        AttrType::Code(_) => "Code",
+        AttrType::StackMapTable { .. } => "StackMapTable",
+        AttrType::Exceptions { .. } => "Exceptions",
+        AttrType::InnerClasses { .. } => "InnerClasses",
+        AttrType::EnclosingMethod { .. } => "EnclosingMethod",
+        AttrType::Synthetic => "Synthetic",
+        AttrType::Signature { .. } => "Signature",
+        AttrType::SourceFile { .. } => "SourceFile",
+        AttrType::SourceDebugExtension { .. } => "SourceDebugExtension",
+        AttrType::LineNumberTable { .. } => "LineNumberTable",
+        AttrType::LocalVariableTable { .. } => "LocalVariableTable",
+        AttrType::LocalVariableTypeTable { .. } => "LocalVariableTypeTable",
+        AttrType::Deprecated => "Deprecated",
+        AttrType::RuntimeVisibleAnnotations { .. } => "RuntimeVisibleAnnotations",
+        AttrType::RuntimeInvisibleAnnotations { .. } => "RuntimeInvisibleAnnotations",
+        AttrType::RuntimeVisibleParameterAnnotations { .. } => "RuntimeVisibleParameterAnnotations",
+        AttrType::RuntimeInvisibleParameterAnnotations { .. } => {
+            "RuntimeInvisibleParameterAnnotations"
+        }
+        AttrType::RuntimeVisibleTypeAnnotations { .. } => "RuntimeVisibleTypeAnnotations",
+        AttrType::RuntimeInvisibleTypeAnnotations { .. } => "RuntimeInvisibleTypeAnnotations",
+        AttrType::AnnotationDefault { .. } => "AnnotationDefault",
+        AttrType::BootstrapMethods { .. } => "BootstrapMethods",
+        AttrType::MethodParameters { .. } => "MethodParameters",
+        AttrType::Module(_) => "Module",
+        AttrType::ModulePackages { .. } => "ModulePackages",
+        AttrType::ModuleMainClass { .. } => "ModuleMainClass",
+        AttrType::NestHost { .. } => "NestHost",
+        AttrType::NestMembers { .. } => "NestMembers",
+        AttrType::Record { .. } => "Record",
+        AttrType::PermittedSubclasses { .. } => "PermittedSubclasses",
+        AttrType::Unknown { .. } => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MethodInfo, Version};
+    use std::sync::Arc;
+
+    fn new_class(cp: Vec<CpType>, attrs: Vec<AttrType>) -> ClassFile {
+        ClassFile {
+            version: Version {
+                minor: 0,
+                major: 52,
+            },
+            cp: Arc::new(cp),
+            acc_flags: 0x0021,
+            this_class: 2,
+            super_class: 0,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![MethodInfo {
+                acc_flags: 0x0009,
+                name_index: 1,
+                desc_index: 1,
+                attrs,
+            }],
+            attrs: vec![],
+        }
+    }
+
+    #[test]
+    fn t_to_bytes() {
+        let utf8 = |s: &str| CpType::Utf8 {
+            bytes: Arc::new(Vec::from(s.as_bytes())),
+        };
+        let cp = vec![
+            CpType::Nop,
+            utf8("T"),
+            CpType::Class { name_index: 1 },
+            CpType::Long {
+                v: [0, 0, 0, 0, 0, 0, 0, 1],
+            },
+            CpType::Nop,
+            utf8("Deprecated"),
+        ];
+
+        let bytes = new_class(cp.clone(), vec![AttrType::Deprecated])
+            .to_bytes()
+            .unwrap();
+        let expected_cp: &[u8] = &[
+            0, 6, 1, 0, 1, b'T', 7, 0, 1, 5, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 10,
+        ];
+        assert_eq!(&bytes[..4], &[0xCA, 0xFE, 0xBA, 0xBE]);
+        assert_eq!(&bytes[8..8 + expected_cp.len()], expected_cp);
+        //the method: flags, name, desc, 1 attribute Deprecated of 0 bytes, no class attributes
+        assert_eq!(
+            &bytes[bytes.len() - 16..],
+            &[0, 9, 0, 1, 0, 1, 0, 1, 0, 5, 0, 0, 0, 0, 0, 0]
+        );
+
+        assert_eq!(
+            new_class(cp.clone(), vec![AttrType::Synthetic]).to_bytes(),
+            Err(WriteError::MissingAttributeName("Synthetic"))
+        );
+
+        let mut bad = cp;
+        bad[4] = utf8("x");
+        assert_eq!(
+            new_class(bad, vec![]).to_bytes(),
+            Err(WriteError::BadConstant(4))
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::new_br;
+use crate::oop::{Class, Oop};
+use crate::runtime;
+use crate::types::FieldIdRef;
+use std::io;
+
+/*
+The helpers shared by the natives doing IO: java.io, java.net, java.util.zip,
+sun.nio & UNIXProcess
+
+The fields of the objects are accessed by name, the classes are initialized
+by the java side already.
+*/
+
+fn field_id(obj: &Oop, name: &str, desc: &str) -> FieldIdRef {
+    let rf = obj.extract_ref();
+    let inst = rf.extract_inst();
+    let cls = inst.class.get_class();
+    cls.get_field_id(&new_br(name), &new_br(desc), false)
+}
+
+pub fn get_field(obj: &Oop, name: &str, desc: &str) -> Oop {
+    let fid = field_id(obj, name, desc);
+    Class::get_field_value(obj.extract_ref(), fid)
+}
+
+pub fn put_field(obj: &Oop, name: &str, desc: &str, v: Oop) {
+    let fid = field_id(obj, name, desc);
+    Class::put_field_value(obj.extract_ref(), fid, v);
+}
+
+//the fd of java.io.FileDescriptor
+pub fn fd_value(fd_obj: &Oop) -> i32 {
+    match fd_obj {
+        Oop::Null => -1,
+        _ => get_field(fd_obj, "fd", "I").extract_int(),
+    }
+}
+
+//run the blocking call in a safe region, gc needn't wait for it
+pub fn blocking<T, F: FnOnce() -> T>(f: F) -> T {
+    runtime::gc::enter_safe_region();
+    let v = f();
+    runtime::gc::leave_safe_region();
+    v
+}
+
+//the strerror message, as the jdk: "Connection refused"
+pub fn error_message(err: &io::Error) -> String {
+    match err.raw_os_error() {
+        Some(errno) => nix::errno::Errno::from_i32(errno).desc().to_string(),
+        None => err.to_string(),
+    }
+}
@@ -1,2 +1,5 @@
 mod check_format;
+pub mod io;
+pub mod net;
+pub mod nio;
 pub mod reflect;
@@ -0,0 +1,275 @@
+use crate::native::common::io::{error_message, fd_value, get_field, put_field};
+use crate::new_br;
+use crate::oop::{self, Oop};
+use crate::runtime::{self, exception, require_class3};
+use crate::util;
+use classfile::consts as cls_consts;
+use std::io;
+use std::mem;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+/*
+The helpers of the java.net natives
+
+Only IPv4 is supported, InetAddressImplFactory.isIPv6Supported is false, so
+the addresses are Inet4Address. The fd of a socket is kept by the
+java.io.FileDescriptor of its SocketImpl or DatagramSocketImpl, -1 once
+closed.
+
+The calls which may block (connect, accept, read, receive...) are done in a
+gc safe region, by io::blocking, no oop is touched there: the data is read into
+a buffer, then copied to the java array.
+*/
+
+//java.net.SocketOptions
+pub const TCP_NODELAY: i32 = 0x0001;
+pub const IP_TOS: i32 = 0x0003;
+pub const SO_REUSEADDR: i32 = 0x0004;
+pub const SO_KEEPALIVE: i32 = 0x0008;
+pub const SO_REUSEPORT: i32 = 0x000E;
+pub const SO_BINDADDR: i32 = 0x000F;
+pub const IP_MULTICAST_IF: i32 = 0x0010;
+pub const IP_MULTICAST_LOOP: i32 = 0x0012;
+pub const IP_MULTICAST_IF2: i32 = 0x001F;
+pub const SO_BROADCAST: i32 = 0x0020;
+pub const SO_LINGER: i32 = 0x0080;
+pub const SO_SNDBUF: i32 = 0x1001;
+pub const SO_RCVBUF: i32 = 0x1002;
+pub const SO_OOBINLINE: i32 = 0x1003;
+pub const SO_TIMEOUT: i32 = 0x1006;
+
+//InetAddress.IPv4
+const FAMILY_IPV4: i32 = 1;
+
+pub const SOCKADDR_IN_LEN: libc::socklen_t = mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+
+//the fd of SocketImpl & DatagramSocketImpl
+pub fn socket_fd(this: &Oop) -> i32 {
+    let fd_obj = get_field(this, "fd", "Ljava/io/FileDescriptor;");
+    fd_value(&fd_obj)
+}
+
+pub fn set_socket_fd(this: &Oop, fd: i32) {
+    let fd_obj = get_field(this, "fd", "Ljava/io/FileDescriptor;");
+    if let Oop::Ref(_) = fd_obj {
+        put_field(&fd_obj, "fd", "I", Oop::new_int(fd));
+    }
+}
+
+pub fn check_open(fd: i32) -> Result<i32, Oop> {
+    if fd < 0 {
+        Err(exception::new(
+            cls_consts::J_SOCKET_EXCEPTION,
+            Some("Socket closed".to_string()),
+        ))
+    } else {
+        Ok(fd)
+    }
+}
+
+//InetAddress.holder.address
+pub fn inet_address(addr: &Oop) -> Ipv4Addr {
+    let holder = get_field(addr, "holder", "Ljava/net/InetAddress$InetAddressHolder;");
+    let v = get_field(&holder, "address", "I").extract_int();
+    Ipv4Addr::from(v as u32)
+}
+
+pub fn set_inet_address(addr: &Oop, ip: Ipv4Addr) {
+    let holder = get_field(addr, "holder", "Ljava/net/InetAddress$InetAddressHolder;");
+    put_field(&holder, "address", "I", Oop::new_int(u32::from(ip) as i32));
+    put_field(&holder, "family", "I", Oop::new_int(FAMILY_IPV4));
+}
+
+//new Inet4Address(host, addr)
+pub fn new_inet_address(ip: Ipv4Addr, host: Option<&str>) -> Oop {
+    let cls = oop::class::load_and_init(b"java/net/Inet4Address");
+    let host = host.map_or(Oop::Null, util::oop::new_java_lang_string2);
+    let addr = Oop::new_byte_ary2(ip.octets().to_vec());
+    let obj = Oop::new_inst(cls.clone());
+    let args = vec![obj.clone(), host, addr];
+    runtime::invoke::invoke_ctor(cls, new_br("(Ljava/lang/String;[B)V"), args);
+    obj
+}
+
+pub fn new_integer(v: i32) -> Oop {
+    new_boxed(b"java/lang/Integer", "(I)V", Oop::new_int(v))
+}
+
+pub fn new_boolean(v: bool) -> Oop {
+    new_boxed(b"java/lang/Boolean", "(Z)V", Oop::new_int(v as i32))
+}
+
+fn new_boxed(name: &[u8], desc: &str, v: Oop) -> Oop {
+    let cls = require_class3(None, name).unwrap();
+    let obj = Oop::new_inst(cls.clone());
+    runtime::invoke::invoke_ctor(cls, new_br(desc), vec![obj.clone(), v]);
+    obj
+}
+
+//Integer or Boolean
+pub fn boxed_int(obj: &Oop) -> i32 {
+    let rf = obj.extract_ref();
+    let inst = rf.extract_inst();
+    let is_boolean = inst.class.get_class().name.as_slice() == b"java/lang/Boolean";
+    if is_boolean {
+        get_field(obj, "value", "Z").extract_int()
+    } else {
+        get_field(obj, "value", "I").extract_int()
+    }
+}
+
+pub fn to_sockaddr(ip: Ipv4Addr, port: i32) -> libc::sockaddr_in {
+    let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+    sa.sin_family = libc::AF_INET as libc::sa_family_t;
+    sa.sin_port = (port as u16).to_be();
+    sa.sin_addr = libc::in_addr {
+        s_addr: u32::from(ip).to_be(),
+    };
+    sa
+}
+
+pub fn from_sockaddr(sa: &libc::sockaddr_in) -> (Ipv4Addr, i32) {
+    let ip = Ipv4Addr::from(u32::from_be(sa.sin_addr.s_addr));
+    (ip, u16::from_be(sa.sin_port) as i32)
+}
+
+//the address & port the socket is bound to
+pub fn local_address(fd: i32) -> io::Result<(Ipv4Addr, i32)> {
+    let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+    let mut len = SOCKADDR_IN_LEN;
+    let r = unsafe { libc::getsockname(fd, &mut sa as *mut _ as *mut libc::sockaddr, &mut len) };
+    if r < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(from_sockaddr(&sa))
+    }
+}
+
+//wait for the events, false if timed out, the timeout <= 0 means forever
+pub fn poll(fd: i32, events: i16, timeout: i32) -> io::Result<bool> {
+    let deadline = if timeout > 0 {
+        Some(Instant::now() + Duration::from_millis(timeout as u64))
+    } else {
+        None
+    };
+
+    loop {
+        let ms = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(false);
+                }
+                (deadline - now).as_millis().max(1) as i32
+            }
+            None => -1,
+        };
+
+        let mut pfd = libc::pollfd {
+            fd,
+            events,
+            revents: 0,
+        };
+        let n = unsafe { libc::poll(&mut pfd, 1, ms) };
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+
+        return Ok(n > 0);
+    }
+}
+
+pub fn set_int_option(fd: i32, level: i32, name: i32, v: i32) -> io::Result<()> {
+    let p = &v as *const i32 as *const libc::c_void;
+    let len = mem::size_of::<i32>() as libc::socklen_t;
+    if unsafe { libc::setsockopt(fd, level, name, p, len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+pub fn get_int_option(fd: i32, level: i32, name: i32) -> io::Result<i32> {
+    let mut v = 0i32;
+    let mut len = mem::size_of::<i32>() as libc::socklen_t;
+    let p = &mut v as *mut i32 as *mut libc::c_void;
+    if unsafe { libc::getsockopt(fd, level, name, p, &mut len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(v)
+    }
+}
+
+//the level & name of setsockopt, for the options of int or boolean value
+pub fn map_option(opt: i32) -> Option<(i32, i32)> {
+    let v = match opt {
+        TCP_NODELAY => (libc::IPPROTO_TCP, libc::TCP_NODELAY),
+        IP_TOS => (libc::IPPROTO_IP, libc::IP_TOS),
+        SO_REUSEADDR => (libc::SOL_SOCKET, libc::SO_REUSEADDR),
+        SO_REUSEPORT => (libc::SOL_SOCKET, libc::SO_REUSEPORT),
+        SO_KEEPALIVE => (libc::SOL_SOCKET, libc::SO_KEEPALIVE),
+        SO_BROADCAST => (libc::SOL_SOCKET, libc::SO_BROADCAST),
+        SO_SNDBUF => (libc::SOL_SOCKET, libc::SO_SNDBUF),
+        SO_RCVBUF => (libc::SOL_SOCKET, libc::SO_RCVBUF),
+        SO_OOBINLINE => (libc::SOL_SOCKET, libc::SO_OOBINLINE),
+        IP_MULTICAST_LOOP => (libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP),
+        _ => return None,
+    };
+    Some(v)
+}
+
+pub fn new_exception(name: &[u8], err: &io::Error) -> Oop {
+    exception::new(name, Some(error_message(err)))
+}
+
+pub fn socket_exception(err: &io::Error) -> Oop {
+    new_exception(cls_consts::J_SOCKET_EXCEPTION, err)
+}
+
+//the exception of connect, as NET_ThrowByNameWithLastError
+pub fn connect_exception(err: &io::Error) -> Oop {
+    let name = match err.raw_os_error() {
+        Some(libc::ECONNREFUSED) | Some(libc::ETIMEDOUT) => cls_consts::J_CONNECT_EXCEPTION,
+        Some(libc::EHOSTUNREACH) | Some(libc::ENETUNREACH) => {
+            cls_consts::J_NO_ROUTE_TO_HOST_EXCEPTION
+        }
+        _ => cls_consts::J_SOCKET_EXCEPTION,
+    };
+    new_exception(name, err)
+}
+
+pub fn bind_exception(err: &io::Error) -> Oop {
+    let name = match err.raw_os_error() {
+        Some(libc::EADDRINUSE) | Some(libc::EADDRNOTAVAIL) | Some(libc::EACCES) => {
+            cls_consts::J_BIND_EXCEPTION
+        }
+        _ => cls_consts::J_SOCKET_EXCEPTION,
+    };
+    new_exception(name, err)
+}
+
+pub fn timeout_exception(msg: &str) -> Oop {
+    exception::new(
+        cls_consts::J_SOCKET_TIMEOUT_EXCEPTION,
+        Some(msg.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_sockaddr, to_sockaddr};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn t_sockaddr() {
+        let ip = Ipv4Addr::new(127, 0, 0, 1);
+        let sa = to_sockaddr(ip, 8080);
+        assert_eq!(sa.sin_port, 8080u16.to_be());
+        assert_eq!(from_sockaddr(&sa), (ip, 8080));
+    }
+}
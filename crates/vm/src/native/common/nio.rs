@@ -0,0 +1,68 @@
+use crate::native::common::io::error_message;
+use crate::new_br;
+use crate::oop::{self, Oop};
+use crate::runtime::{self, exception};
+use classfile::consts as cls_consts;
+use std::io;
+
+/*
+The helpers of the sun.nio natives
+
+The reads & writes return the count, or a code of sun.nio.ch.IOStatus, the
+other errors are thrown as IOException. The natives of sun.nio.fs throw
+UnixException with the errno, translated by the java side.
+*/
+
+//sun.nio.ch.IOStatus
+pub const EOF: i64 = -1;
+pub const UNAVAILABLE: i64 = -2;
+pub const INTERRUPTED: i64 = -3;
+pub const UNSUPPORTED: i64 = -4;
+pub const UNSUPPORTED_CASE: i64 = -6;
+
+//as convertReturnVal of the jdk, 'n' is the result of read or write
+pub fn convert_return(n: isize, reading: bool) -> Result<i64, Oop> {
+    if n > 0 {
+        Ok(n as i64)
+    } else if n == 0 {
+        Ok(if reading { EOF } else { 0 })
+    } else {
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EAGAIN) => Ok(UNAVAILABLE),
+            Some(libc::EINTR) => Ok(INTERRUPTED),
+            _ => Err(io_exception(&err)),
+        }
+    }
+}
+
+pub fn io_exception(err: &io::Error) -> Oop {
+    exception::new(cls_consts::J_IOEXCEPTION, Some(error_message(err)))
+}
+
+//the fd of java.io.FileDescriptor, IOException if closed
+pub fn fd_value(fd_obj: &Oop) -> Result<i32, Oop> {
+    let fd = super::io::fd_value(fd_obj);
+    if fd < 0 {
+        Err(exception::new(
+            cls_consts::J_IOEXCEPTION,
+            Some("Stream Closed".to_string()),
+        ))
+    } else {
+        Ok(fd)
+    }
+}
+
+//new sun.nio.fs.UnixException(errno)
+pub fn unix_exception(errno: i32) -> Oop {
+    let cls = oop::class::load_and_init(b"sun/nio/fs/UnixException");
+    let ex = Oop::new_inst(cls.clone());
+    let args = vec![ex.clone(), Oop::new_int(errno)];
+    runtime::invoke::invoke_ctor(cls, new_br("(I)V"), args);
+    ex
+}
+
+pub fn last_unix_exception() -> Oop {
+    let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    unix_exception(errno)
+}
@@ -1,24 +1,34 @@
 #![allow(non_snake_case)]
 
-use crate::native::java_lang_Class;
-use crate::oop::{self, Class, Oop, OopPtr};
-use crate::runtime::{self, require_class3};
+use crate::native::{java_lang_Class, java_lang_System};
+use crate::new_br;
+use crate::oop::{self, Class, Oop};
+use crate::runtime::{self, require_class2, require_class3, ClassLoader};
 use crate::types::*;
 use crate::util;
 use class_parser::{FieldSignature, MethodSignature};
 use classfile::consts as cls_const;
-use classfile::SignatureType;
+use classfile::{constant_pool, BytesRef, SignatureType};
 use std::sync::Arc;
 
 pub fn new_field(fir: FieldIdRef) -> Oop {
     let field_cls = runtime::require_class3(None, cls_const::J_FIELD).unwrap();
 
-    let clazz = fir.field.class.get_class().get_mirror();
+    let (clazz, cl) = {
+        let cls = fir.field.class.get_class();
+        (cls.get_mirror(), cls.class_loader)
+    };
 
     let field_sig = FieldSignature::new(fir.field.desc.as_slice());
-    let typ_mirror = create_value_type(field_sig.field_type);
-    let desc = unsafe { std::str::from_utf8_unchecked(fir.field.desc.as_slice()) };
-    let signature = util::oop::new_java_lang_string2(desc);
+    let typ_mirror = create_value_type2(field_sig.field_type, cl);
+    let signature = new_signature(fir.field.get_signature());
+    let annotations = {
+        let raw = fir.field.get_annotation();
+        match raw {
+            Some(raw) => Oop::new_byte_ary2(raw.to_vec()),
+            None => Oop::Null,
+        }
+    };
 
     let field_name = unsafe { std::str::from_utf8_unchecked(fir.field.name.as_slice()) };
     let mut desc = Vec::new();
@@ -34,7 +44,7 @@ pub fn new_field(fir: FieldIdRef) -> Oop {
         ("modifiers", "I", Oop::new_int(fir.field.acc_flags as i32)),
         ("slot", "I", Oop::new_int(fir.offset as i32)),
         ("signature", "Ljava/lang/String;", signature),
-        ("annotations", "[B", Oop::Null),
+        ("annotations", "[B", annotations),
     ]
     .iter()
     .map(|(_, t, v)| {
@@ -55,29 +65,30 @@ pub fn new_method_ctor(mir: MethodIdRef) -> Oop {
     let ctor_cls = require_class3(None, cls_const::J_METHOD_CTOR).unwrap();
 
     //declaringClass
-    let declaring_cls = mir.method.class.get_class().get_mirror();
+    let (declaring_cls, cl) = {
+        let cls = mir.method.class.get_class();
+        (cls.get_mirror(), cls.class_loader)
+    };
 
     //parameterTypes
     let signature = MethodSignature::new(mir.method.desc.as_slice());
     let params: Vec<Oop> = signature
         .args
         .iter()
-        .map(|t| create_value_type(t.clone()))
+        .map(|t| create_value_type2(t.clone(), cl))
         .collect();
     let cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
     let parameter_types = Oop::new_ref_ary2(cls, params);
 
-    //fixme: checkedExceptions
-    let cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
-    let checked_exceptions = Oop::new_ref_ary2(cls, vec![]);
+    //checkedExceptions
+    let checked_exceptions = new_checked_exceptions(&mir);
 
     //modifiers
     let modifiers = mir.method.acc_flags;
     //slot
     let slot = mir.offset;
     //signature
-    let desc = unsafe { std::str::from_utf8_unchecked(mir.method.desc.as_slice()) };
-    let signature = util::oop::new_java_lang_string2(desc);
+    let signature = new_signature(mir.method.get_signature());
     let annotations = {
         let raw = mir.method.get_annotation();
         match raw {
@@ -128,7 +139,10 @@ pub fn new_method_normal(mir: MethodIdRef) -> Oop {
     let ctor_cls = require_class3(None, cls_const::J_METHOD).unwrap();
 
     //declaringClass
-    let declaring_cls = mir.method.class.get_class().get_mirror();
+    let (declaring_cls, cl) = {
+        let cls = mir.method.class.get_class();
+        (cls.get_mirror(), cls.class_loader)
+    };
 
     //name
     let name = {
@@ -141,27 +155,23 @@ pub fn new_method_normal(mir: MethodIdRef) -> Oop {
     let params: Vec<Oop> = signature
         .args
         .iter()
-        .map(|t| create_value_type(t.clone()))
+        .map(|t| create_value_type2(t.clone(), cl))
         .collect();
     let cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
     let parameter_types = Oop::new_ref_ary2(cls, params);
 
     //returnType
-    let return_type = create_value_type(signature.retype);
+    let return_type = create_value_type2(signature.retype, cl);
 
-    //fixme: checkedExceptions
-    let cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
-    let checked_exceptions = Oop::new_ref_ary2(cls, vec![]);
+    //checkedExceptions
+    let checked_exceptions = new_checked_exceptions(&mir);
 
     //modifiers
     let modifiers = mir.method.acc_flags;
     //slot
     let slot = mir.offset;
     //signature
-    let signature = {
-        let desc = unsafe { std::str::from_utf8_unchecked(mir.method.desc.as_slice()) };
-        util::oop::new_java_lang_string2(desc)
-    };
+    let signature = new_signature(mir.method.get_signature());
     let annotations = {
         let raw = mir.method.get_annotation();
         match raw {
@@ -218,54 +228,300 @@ pub fn new_method_normal(mir: MethodIdRef) -> Oop {
     oop
 }
 
-pub fn get_Constructor_clazz(ctor: &Oop) -> Oop {
-    //todo: optimize, avoid obtain class
+//the generic signature, null if not generic
+fn new_signature(sig: Option<BytesRef>) -> Oop {
+    match sig {
+        Some(sig) => util::oop::new_java_lang_string3(sig.as_slice()),
+        None => Oop::Null,
+    }
+}
+
+//the classes in the throws clause
+fn new_checked_exceptions(mir: &MethodIdRef) -> Oop {
+    let cls = mir.method.class.get_class();
+    let cp = &mir.method.class_file.cp;
+    let elms: Vec<Oop> = mir
+        .method
+        .get_exceptions()
+        .iter()
+        .filter_map(|it| require_class2(cls.class_loader, *it, cp))
+        .map(|it| it.get_class().get_mirror())
+        .collect();
+    let ary_cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
+    Oop::new_ref_ary2(ary_cls, elms)
+}
+
+//java.lang.reflect.Parameter of the MethodParameters attribute, null if absent
+pub fn new_parameters(executable: &Oop, mir: &MethodIdRef) -> Oop {
+    let params = match mir.method.get_parameters() {
+        Some(params) => params,
+        None => return Oop::Null,
+    };
+
+    let param_cls = require_class3(None, cls_const::J_PARAMETER).unwrap();
+    let cp = &mir.method.class_file.cp;
+    let elms = params
+        .iter()
+        .enumerate()
+        .map(|(i, it)| {
+            let name = if it.name_index == 0 {
+                Oop::Null
+            } else {
+                let name = constant_pool::get_utf8(cp, it.name_index as usize);
+                util::oop::new_java_lang_string3(name.as_slice())
+            };
+
+            let oop = Oop::new_inst(param_cls.clone());
+            let args = vec![
+                oop.clone(),
+                name,
+                Oop::new_int(it.acc_flags as i32),
+                executable.clone(),
+                Oop::new_int(i as i32),
+            ];
+            runtime::invoke::invoke_ctor(
+                param_cls.clone(),
+                new_br("(Ljava/lang/String;ILjava/lang/reflect/Executable;I)V"),
+                args,
+            );
+
+            oop
+        })
+        .collect();
+
+    let ary_cls = require_class3(None, b"[Ljava/lang/reflect/Parameter;").unwrap();
+    Oop::new_ref_ary2(ary_cls, elms)
+}
+
+//the declaring class & the slot of a Field, Method or Constructor
+pub fn get_clazz_slot(obj: &Oop) -> (ClassRef, usize) {
     let cls = {
-        let rf = ctor.extract_ref();
+        let rf = obj.extract_ref();
         let inst = rf.extract_inst();
         inst.class.clone()
     };
 
-    //todo: optimize, avoid obtain id
     let cls = cls.get_class();
     let id = cls.get_field_id(&util::S_CLAZZ, &util::S_JAVA_LANG_CLASS, false);
-    Class::get_field_value(ctor.extract_ref(), id)
+    let clazz = Class::get_field_value(obj.extract_ref(), id);
+    let id = cls.get_field_id(&util::S_SLOT, &util::S_I, false);
+    let slot = Class::get_field_value(obj.extract_ref(), id);
+
+    let rf = clazz.extract_ref();
+    let mirror = rf.extract_mirror();
+    let target = mirror.target.clone().unwrap();
+    (target, slot.extract_int() as usize)
 }
 
-/*
-pub fn get_Constructor_slot(ctor: &Oop) -> i32 {
-    let cls = {
-        let v = util::oop::extract_ref(ctor);
-        let v = v.read().unwrap();
-        match &v.v {
-            oop::RefKind::Inst(inst) => inst.class.clone(),
-            _ => unreachable!(),
+//the slot of a Method or Constructor is the index in the methods of the class file
+pub fn get_method_by_slot(cls: &ClassRef, slot: usize) -> MethodIdRef {
+    let inst = cls.extract_inst();
+    let cf = &inst.class_file;
+    let mi = cf.methods.get(slot).unwrap();
+    let name = constant_pool::get_utf8(&cf.cp, mi.name_index as usize).clone();
+    let desc = constant_pool::get_utf8(&cf.cp, mi.desc_index as usize).clone();
+    inst.all_methods.get(&(name, desc)).unwrap().clone()
+}
+
+//the slot of a Field is the offset, of the static or the instance fields
+pub fn get_field_by_slot(cls: &ClassRef, slot: usize, is_static: bool) -> FieldIdRef {
+    let inst = cls.extract_inst();
+    let fields = if is_static {
+        &inst.static_fields
+    } else {
+        &inst.inst_fields
+    };
+    fields
+        .values()
+        .find(|it| it.offset == slot)
+        .cloned()
+        .unwrap()
+}
+
+//the arguments, unboxed & widened to the parameter types
+pub fn unbox_args(mir: &MethodIdRef, args: &Oop) -> Result<Vec<Oop>, Oop> {
+    let args = match args {
+        Oop::Null => vec![],
+        v => {
+            let rf = v.extract_ref();
+            let ary = rf.extract_array();
+            ary.to_vec()
         }
     };
 
-    let cls = cls.read().unwrap();
-    let id = cls.get_field_id(b"slot", b"I", false);
-    let v = cls.get_field_value(ctor, id);
-    util::oop::extract_int(&v)
+    let params = &mir.method.signature.args;
+    if args.len() != params.len() {
+        return Err(runtime::exception::new(
+            cls_const::J_ILLEGAL_ARGUMENT,
+            Some("wrong number of arguments".to_string()),
+        ));
+    }
+
+    let cl = mir.method.class.get_class().class_loader;
+    params
+        .iter()
+        .zip(args)
+        .map(|(t, v)| {
+            let r = match t {
+                SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+                    if is_assignable(&v, t, cl) {
+                        Some(v)
+                    } else {
+                        None
+                    }
+                }
+                _ => unbox(&v).and_then(|(from, v)| widen(&from, v, t)),
+            };
+
+            r.ok_or_else(|| {
+                runtime::exception::new(
+                    cls_const::J_ILLEGAL_ARGUMENT,
+                    Some("argument type mismatch".to_string()),
+                )
+            })
+        })
+        .collect()
 }
-*/
 
-pub fn get_Constructor_signature(ctor: &Oop) -> String {
-    //todo: optimisze, cache Constructor cls, avoid obtain class
-    let cls = {
-        let rf = ctor.extract_ref();
-        let inst = rf.extract_inst();
-        inst.class.clone()
+pub fn is_instance(v: &Oop, cls: &ClassRef) -> bool {
+    match java_lang_System::class_of(v) {
+        Some(v_cls) => runtime::cmp::instance_of(v_cls, cls.clone()),
+        None => false,
+    }
+}
+
+//null is assignable to any reference type
+fn is_assignable(v: &Oop, t: &SignatureType, cl: Option<ClassLoader>) -> bool {
+    let name = match t {
+        SignatureType::Object(desc, _, _) => &desc.as_slice()[1..desc.len() - 1],
+        SignatureType::Array(desc) => desc.as_slice(),
+        _ => unreachable!(),
     };
 
-    //todo: optimize, cache id
+    match v {
+        Oop::Null => true,
+        v => match require_class3(cl, name) {
+            Some(cls) => is_instance(v, &cls),
+            None => false,
+        },
+    }
+}
+
+//the primitive in a box, with the type of it
+fn unbox(v: &Oop) -> Option<(SignatureType, Oop)> {
+    let cls = java_lang_System::class_of(v)?;
     let cls = cls.get_class();
-    let id = cls.get_field_id(&util::S_SIGNATURE, &util::S_JAVA_LANG_STRING, false);
-    let v = Class::get_field_value(ctor.extract_ref(), id);
-    OopPtr::java_lang_string(v.extract_ref())
+    let (t, desc) = match cls.name.as_slice() {
+        b"java/lang/Boolean" => (SignatureType::Boolean, "Z"),
+        b"java/lang/Byte" => (SignatureType::Byte, "B"),
+        b"java/lang/Character" => (SignatureType::Char, "C"),
+        b"java/lang/Short" => (SignatureType::Short, "S"),
+        b"java/lang/Integer" => (SignatureType::Int, "I"),
+        b"java/lang/Long" => (SignatureType::Long, "J"),
+        b"java/lang/Float" => (SignatureType::Float, "F"),
+        b"java/lang/Double" => (SignatureType::Double, "D"),
+        _ => return None,
+    };
+
+    let id = cls.get_field_id(&new_br("value"), &new_br(desc), false);
+    Some((t, Class::get_field_value(v.extract_ref(), id)))
 }
 
-fn create_value_type(t: SignatureType) -> Oop {
+//JLS 5.1.2, widening primitive conversion
+fn widen(from: &SignatureType, v: Oop, to: &SignatureType) -> Option<Oop> {
+    use SignatureType::*;
+
+    let v = match (from, to) {
+        _ if from == to => v,
+        (Byte, Short) | (Byte | Short | Char, Int) => v,
+        (Byte | Short | Char | Int, Long) => Oop::new_long(v.extract_int() as i64),
+        (Byte | Short | Char | Int, Float) => Oop::new_float(v.extract_int() as f32),
+        (Byte | Short | Char | Int, Double) => Oop::new_double(v.extract_int() as f64),
+        (Long, Float) => Oop::new_float(v.extract_long() as f32),
+        (Long, Double) => Oop::new_double(v.extract_long() as f64),
+        (Float, Double) => Oop::new_double(v.extract_float() as f64),
+        _ => return None,
+    };
+
+    Some(v)
+}
+
+//a new box, as hotspot, the caches of valueOf are not used
+pub fn box_value(v: Oop, t: &SignatureType) -> Oop {
+    let (name, desc): (&[u8], &str) = match t {
+        SignatureType::Boolean => (b"java/lang/Boolean", "(Z)V"),
+        SignatureType::Byte => (b"java/lang/Byte", "(B)V"),
+        SignatureType::Char => (b"java/lang/Character", "(C)V"),
+        SignatureType::Short => (b"java/lang/Short", "(S)V"),
+        SignatureType::Int => (b"java/lang/Integer", "(I)V"),
+        SignatureType::Long => (b"java/lang/Long", "(J)V"),
+        SignatureType::Float => (b"java/lang/Float", "(F)V"),
+        SignatureType::Double => (b"java/lang/Double", "(D)V"),
+        _ => return v,
+    };
+
+    let cls = require_class3(None, name).unwrap();
+    oop::class::init_class(&cls);
+    oop::class::init_class_fully(&cls);
+
+    let obj = Oop::new_inst(cls.clone());
+    runtime::invoke::invoke_ctor(cls, new_br(desc), vec![obj.clone(), v]);
+    obj
+}
+
+//invoke the method, the result is boxed, the exception thrown is wrapped
+//by InvocationTargetException
+pub fn invoke(mir: MethodIdRef, args: Vec<Oop>, force_no_resolve: bool) -> Result<Oop, Oop> {
+    let mut jc = runtime::invoke::JavaCall::new_with_args(mir, args);
+    let area = runtime::DataArea::new(1);
+    jc.invoke(Some(&area), force_no_resolve);
+
+    if runtime::thread::is_meet_ex() {
+        let ex = {
+            let jt = runtime::thread::current_java_thread();
+            let mut jt = jt.write().unwrap();
+            jt.take_ex().unwrap()
+        };
+        return Err(new_invocation_target_exception(ex));
+    }
+
+    let retype = &jc.mir.method.signature.retype;
+    let v = {
+        let mut stack = area.stack.borrow_mut();
+        match retype {
+            SignatureType::Byte
+            | SignatureType::Char
+            | SignatureType::Boolean
+            | SignatureType::Short
+            | SignatureType::Int => Oop::new_int(stack.pop_int()),
+            SignatureType::Long => Oop::new_long(stack.pop_long()),
+            SignatureType::Float => Oop::new_float(stack.pop_float()),
+            SignatureType::Double => Oop::new_double(stack.pop_double()),
+            SignatureType::Object(_, _, _) | SignatureType::Array(_) => stack.pop_ref(),
+            SignatureType::Void => Oop::Null,
+        }
+    };
+
+    Ok(box_value(v, retype))
+}
+
+fn new_invocation_target_exception(target: Oop) -> Oop {
+    let cls = require_class3(None, cls_const::J_INVOCATION_TARGET_EXCEPTION).unwrap();
+    oop::class::init_class(&cls);
+    oop::class::init_class_fully(&cls);
+
+    let ex = Oop::new_inst(cls.clone());
+    let args = vec![ex.clone(), target];
+    runtime::invoke::invoke_ctor(cls, new_br("(Ljava/lang/Throwable;)V"), args);
+    ex
+}
+
+pub fn create_value_type(t: SignatureType) -> Oop {
+    create_value_type2(t, None)
+}
+
+//the classes are loaded by 'class_loader', the loader of the declaring class
+pub fn create_value_type2(t: SignatureType, class_loader: Option<ClassLoader>) -> Oop {
     match t {
         SignatureType::Byte => java_lang_Class::get_primitive_class_mirror("B").unwrap(),
         SignatureType::Char => java_lang_Class::get_primitive_class_mirror("C").unwrap(),
@@ -276,17 +532,48 @@ fn create_value_type(t: SignatureType) -> Oop {
         SignatureType::Object(desc, _, _) => {
             let len = desc.len();
             let name = &desc.as_slice()[1..len - 1];
-            let cls = require_class3(None, name).unwrap();
+            let cls = require_class3(class_loader, name).unwrap();
             let cls = cls.get_class();
             cls.get_mirror()
         }
         SignatureType::Short => java_lang_Class::get_primitive_class_mirror("S").unwrap(),
         SignatureType::Boolean => java_lang_Class::get_primitive_class_mirror("Z").unwrap(),
         SignatureType::Array(desc) => {
-            let cls = require_class3(None, desc.as_slice()).unwrap();
+            let cls = require_class3(class_loader, desc.as_slice()).unwrap();
             let cls = cls.get_class();
             cls.get_mirror()
         }
         SignatureType::Void => java_lang_Class::get_primitive_class_mirror("V").unwrap(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::widen;
+    use crate::oop::Oop;
+    use classfile::SignatureType;
+
+    #[test]
+    fn t_widen() {
+        let v = widen(&SignatureType::Char, Oop::new_int(65), &SignatureType::Long);
+        assert_eq!(v.unwrap().extract_long(), 65);
+        let v = widen(&SignatureType::Int, Oop::new_int(3), &SignatureType::Double);
+        assert_eq!(v.unwrap().extract_double(), 3.0);
+        let v = widen(
+            &SignatureType::Float,
+            Oop::new_float(1.5),
+            &SignatureType::Double,
+        );
+        assert_eq!(v.unwrap().extract_double(), 1.5);
+
+        //narrowing, or between char & short, or with boolean
+        assert!(widen(&SignatureType::Long, Oop::new_long(1), &SignatureType::Int).is_none());
+        assert!(widen(&SignatureType::Char, Oop::new_int(1), &SignatureType::Short).is_none());
+        assert!(widen(
+            &SignatureType::Boolean,
+            Oop::new_int(1),
+            &SignatureType::Int
+        )
+        .is_none());
+    }
+}
@@ -1,4 +1,5 @@
 #![allow(non_snake_case)]
+use crate::native::common::io::blocking;
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{Class, Oop, OopPtr};
 use crate::runtime::{self, require_class3};
@@ -71,7 +72,8 @@ fn jvm_readBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
         let (_, ptr) = ary.split_at_mut(off as usize);
         let ptr = ptr.as_mut_ptr() as *mut libc::c_void;
-        let n = unsafe { libc::read(fd, ptr, len as usize) };
+        //stdin or a pipe may block
+        let n = blocking(|| unsafe { libc::read(fd, ptr, len as usize) });
         // error!("readBytes n = {}", n);
         if n > 0 {
             n as i32
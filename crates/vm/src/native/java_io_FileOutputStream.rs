@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 
+use crate::native::common::io::blocking;
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{Class, Oop, OopPtr};
 use crate::runtime::require_class3;
@@ -54,10 +55,12 @@ fn jvm_writeBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         if append == 1 {
             libc::lseek(fd, 0, libc::SEEK_END);
         }
+    }
 
-        if -1 == libc::write(fd, ary.as_ptr() as *const libc::c_void, len) {
-            panic!("write failed");
-        }
+    //a full pipe blocks
+    let n = blocking(|| unsafe { libc::write(fd, ary.as_ptr() as *const libc::c_void, len) });
+    if n == -1 {
+        panic!("write failed");
     }
 
     Ok(None)
@@ -2,8 +2,8 @@
 
 use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Class, ClassKind, Oop, OopPtr, ValueType};
-use crate::runtime::{self, require_class2, require_class3};
-use crate::types::{ClassRef, MethodIdRef};
+use crate::runtime::{self, arguments, require_class2, require_class3, ClassLoader};
+use crate::types::ClassRef;
 use crate::util;
 use classfile::{constant_pool, consts as cls_consts, flags as acc};
 use rustc_hash::FxHashMap;
@@ -89,6 +89,11 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             Box::new(jvm_getInterfaces0),
         ),
         new_fn("getRawAnnotations", "()[B", Box::new(jvm_getRawAnnotations)),
+        new_fn(
+            "getRawTypeAnnotations",
+            "()[B",
+            Box::new(jvm_getRawTypeAnnotations),
+        ),
         new_fn(
             "getConstantPool",
             "()Lsun/reflect/ConstantPool;",
@@ -167,6 +172,7 @@ pub fn create_mirror(cls: ClassRef) {
 
     if is_fixed {
         let mirror = Oop::new_mirror(cls.clone());
+        set_mirror_class_loader(&mirror, &cls);
         let cls = cls.get_mut_class();
         trace!("mirror created: {}", unsafe {
             std::str::from_utf8_unchecked(cls.name.as_slice())
@@ -190,6 +196,27 @@ pub fn create_mirror(cls: ClassRef) {
     }
 }
 
+//the classes of the bootstrap loader keep it null
+fn set_mirror_class_loader(mirror: &Oop, cls: &ClassRef) {
+    let loader = match cls.get_class().class_loader {
+        Some(class_loader) => class_loader.to_oop(),
+        None => return,
+    };
+    if loader.is_null() {
+        return;
+    }
+
+    let class = require_class3(None, cls_consts::J_CLASS).unwrap();
+    let fid = class.get_class().find_field_id(
+        &util::S_CLASS_LOADER,
+        &util::S_JAVA_LANG_CLASS_LOADER,
+        false,
+    );
+    if let Some(fid) = fid {
+        Class::put_field_value(mirror.extract_ref(), fid, loader);
+    }
+}
+
 /*
 called after 'java/lang/Class' inited in init_vm.rs
 */
@@ -261,8 +288,23 @@ fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(None)
 }
 
-fn jvm_desiredAssertionStatus0(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
-    Ok(Some(Oop::new_int(0)))
+//the classes of the bootstrap loader are the system classes, -esa & -dsa
+fn jvm_desiredAssertionStatus0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mirror = args.first().unwrap();
+    let target = {
+        let rf = mirror.extract_ref();
+        rf.extract_mirror().target.clone()
+    };
+    let v = match target {
+        Some(cls) => {
+            let cls = cls.get_class();
+            let name = String::from_utf8_lossy(cls.name.as_slice()).replace("/", ".");
+            let system = !matches!(cls.class_loader, Some(ClassLoader::User(_)));
+            arguments::assertion_enabled(&name, system)
+        }
+        None => false,
+    };
+    Ok(Some(Oop::new_int(v as i32)))
 }
 
 fn jvm_getPrimitiveClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -286,32 +328,34 @@ fn jvm_getDeclaredFields0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         arg1.extract_int() == 1
     };
 
-    //fixme: super fields
-    //obtain inst&static fields
-    let (inst_fields, static_fields) = {
+    //in the order of the class file
+    let selected_fields = {
         let inst = mirror_target.extract_inst();
-        (inst.inst_fields.clone(), inst.static_fields.clone())
-    };
-
-    //build fields ary
-    let mut fields = Vec::new();
-    for (_, it) in inst_fields {
-        if public_only && !it.field.is_public() {
-            continue;
+        let cls_name = mirror_target.name();
+        let cp = &inst.class_file.cp;
+        let mut selected_fields = Vec::with_capacity(inst.class_file.fields.len());
+        for it in inst.class_file.fields.iter() {
+            let name = constant_pool::get_utf8(cp, it.name_index as usize).clone();
+            let desc = constant_pool::get_utf8(cp, it.desc_index as usize).clone();
+            let k = (cls_name.clone(), name, desc);
+            let fir = inst
+                .inst_fields
+                .get(&k)
+                .or_else(|| inst.static_fields.get(&k))
+                .unwrap();
+            if !public_only || fir.field.is_public() {
+                selected_fields.push(fir.clone());
+            }
         }
 
-        let v = common::reflect::new_field(it);
-        fields.push(v);
-    }
-
-    for (_, it) in static_fields {
-        if public_only && !it.field.is_public() {
-            continue;
-        }
+        selected_fields
+    };
 
-        let v = common::reflect::new_field(it);
-        fields.push(v);
-    }
+    //build fields ary
+    let fields: Vec<Oop> = selected_fields
+        .into_iter()
+        .map(common::reflect::new_field)
+        .collect();
 
     //build oop field ar
     let ary_cls = require_class3(None, b"[Ljava/lang/reflect/Field;").unwrap();
@@ -354,13 +398,10 @@ fn jvm_forName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         let arg1 = args.get(1).unwrap();
         arg1.extract_int() != 0
     };
-    let java_cls_loader = args.get(2).unwrap();
-    {
-        match java_cls_loader {
-            Oop::Null => (),
-            _ => unimplemented!("app class loader, unimpl"),
-        }
-    }
+    let class_loader = {
+        let arg2 = args.get(2).unwrap();
+        runtime::ClassLoader::from_oop(arg2)
+    };
 
     let _caller_mirror = args.get(3).unwrap();
 
@@ -376,7 +417,7 @@ fn jvm_forName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
             //fixme: skip, cause jvm start very slow
             None
         } else {
-            require_class3(None, java_name.as_bytes())
+            require_class3(class_loader, java_name.as_bytes())
         }
     };
 
@@ -390,6 +431,12 @@ fn jvm_forName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
             let mirror = cls.get_class().get_mirror();
             Ok(Some(mirror))
         }
+        None if runtime::thread::is_meet_ex() => {
+            //VerifyError, ClassFormatError... thrown by class loader
+            let jt = runtime::thread::current_java_thread();
+            let ex = jt.write().unwrap().take_ex().unwrap();
+            Err(ex)
+        }
         None => {
             // error!("forName0, NotFound: {}", java_name);
             let msg = Some(java_name);
@@ -478,7 +525,7 @@ fn jvm_getDeclaredConstructors0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     get_declared_method_helper(mirror_target, public_only, true)
 }
 
-pub fn jvm_getModifiers(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+fn jvm_getModifiers(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let v = args.get(0).unwrap();
     let v = {
         let rf = v.extract_ref();
@@ -594,12 +641,16 @@ fn jvm_getEnclosingMethod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         }
     };
 
-    let (cls_file, em) = match target {
+    let (cls_file, em, cl) = match target {
         Some(target) => {
             let cls = target.get_class();
             match &cls.kind {
                 ClassKind::Instance(cls) => match &cls.enclosing_method {
-                    Some(em) => (cls.class_file.clone(), em.clone()),
+                    Some(em) => (
+                        cls.class_file.clone(),
+                        em.clone(),
+                        target.get_class().class_loader,
+                    ),
                     None => return Ok(Some(Oop::Null)),
                 },
                 _ => return Ok(Some(Oop::Null)),
@@ -612,7 +663,7 @@ fn jvm_getEnclosingMethod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     if em.class_index == 0 {
         panic!();
     }
-    let em_class = require_class2(em.class_index, &cls_file.cp).unwrap();
+    let em_class = require_class2(cl, em.class_index, &cls_file.cp).unwrap();
     let em_class_mirror = {
         let cls = em_class.get_class();
         cls.get_mirror()
@@ -670,18 +721,20 @@ fn jvm_getDeclaringClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         None => return Ok(Some(Oop::Null)),
     };
 
+    let cl = target.get_class().class_loader;
     for it in inner_classes.iter() {
         if it.inner_class_info_index == 0 {
             continue;
         }
 
-        let inner_class = require_class2(it.inner_class_info_index, &cls_file.cp).unwrap();
+        let inner_class = require_class2(cl, it.inner_class_info_index, &cls_file.cp).unwrap();
 
         if Arc::ptr_eq(&inner_class, &target) {
             return if it.outer_class_info_index == 0 {
                 Ok(Some(Oop::Null))
             } else {
-                let outer_class = require_class2(it.outer_class_info_index, &cls_file.cp).unwrap();
+                let outer_class =
+                    require_class2(cl, it.outer_class_info_index, &cls_file.cp).unwrap();
                 let v = outer_class.get_class();
                 Ok(Some(v.get_mirror()))
             };
@@ -743,7 +796,7 @@ fn jvm_getInterfaces0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
             let mut elms = Vec::with_capacity(inst.class_file.interfaces.len());
             let cp = &inst.class_file.cp;
             inst.class_file.interfaces.iter().for_each(|it| {
-                let cls = require_class2(*it, cp).unwrap();
+                let cls = require_class2(v.class_loader, *it, cp).unwrap();
                 let cls = cls.get_class();
                 elms.push(cls.get_mirror());
             });
@@ -795,6 +848,28 @@ fn jvm_getRawAnnotations(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(annotations))
 }
 
+fn jvm_getRawTypeAnnotations(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let target = {
+        let rf = this.extract_ref();
+        let mirror = rf.extract_mirror();
+        mirror.target.clone()
+    };
+
+    let annotations = match target {
+        Some(target) if target.get_class().is_instance() => {
+            let raw = target.get_class().get_type_annotation();
+            match raw {
+                Some(raw) => Oop::new_byte_ary2(raw),
+                None => Oop::Null,
+            }
+        }
+        _ => Oop::Null,
+    };
+
+    Ok(Some(annotations))
+}
+
 fn jvm_getConstantPool(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let this = args.get(0).unwrap();
     let cp_oop = match this {
@@ -832,7 +907,9 @@ fn jvm_getDeclaredClasses0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
                     let mut inners = Vec::with_capacity(inner_classes.len());
                     for it in inner_classes {
                         if it.outer_class_info_index == this_class {
-                            let inner_clz = require_class2(it.inner_class_info_index, cp).unwrap();
+                            let cl = target_class.class_loader;
+                            let inner_clz =
+                                require_class2(cl, it.inner_class_info_index, cp).unwrap();
                             let v = inner_clz.get_class().get_mirror();
                             inners.push(v);
                         }
@@ -881,35 +958,22 @@ fn get_declared_method_helper(
     public_only: bool,
     want_constructor: bool,
 ) -> JNIResult {
-    //fixme: super methods
+    //in the order of the class file, <clinit> is not reflected
     let selected_methods = {
         let cls = mirror_target.get_class();
         match &cls.kind {
             oop::class::ClassKind::Instance(inst) => {
-                fn chooser1(want_constructor: bool, name: &[u8]) -> bool {
-                    if want_constructor {
-                        name == b"<init>"
-                    } else {
-                        name != b"<init>"
-                    }
-                }
-
-                fn chooser2(want_constructor: bool, m: &MethodIdRef) -> bool {
-                    if want_constructor {
-                        m.method.name.as_slice() == b"<init>" && !m.method.is_static()
-                    } else {
-                        m.method.name.as_slice() != b"<init>"
-                    }
-                }
-
                 let mut selected_methods = Vec::new();
-                for (k, m) in inst.all_methods.iter() {
-                    if !chooser1(want_constructor, k.0.as_slice()) {
+                for i in 0..inst.class_file.methods.len() {
+                    let m = common::reflect::get_method_by_slot(&mirror_target, i);
+                    let name = m.method.name.as_slice();
+                    let is_ctor = name == b"<init>";
+                    if is_ctor != want_constructor || name == b"<clinit>" {
                         continue;
                     }
 
-                    if chooser2(want_constructor, &m) && (!public_only || m.method.is_public()) {
-                        selected_methods.push(m.clone());
+                    if !public_only || m.method.is_public() {
+                        selected_methods.push(m);
                     }
                 }
 
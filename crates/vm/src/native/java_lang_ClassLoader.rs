@@ -1,9 +1,11 @@
 #![allow(non_snake_case)]
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::{self, Oop, OopPtr};
-use crate::runtime;
-use crate::util;
+use crate::oop::{self, Class, Oop, OopPtr};
+use crate::runtime::arguments::{self, Assertion};
+use crate::runtime::{self, require_class3, thread, ClassLoader};
+use crate::{new_br, util};
+use classfile::consts::{J_ARRAY_INDEX_OUT_OF_BOUNDS, J_NPE};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -23,6 +25,21 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             "(Ljava/lang/String;)Ljava/lang/Class;",
             Box::new(jvm_findBootstrapClass),
         ),
+        new_fn(
+            "defineClass1",
+            "(Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+            Box::new(jvm_defineClass1),
+        ),
+        new_fn(
+            "defineClass2",
+            "(Ljava/lang/String;Ljava/nio/ByteBuffer;IILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+            Box::new(jvm_defineClass2),
+        ),
+        new_fn(
+            "retrieveDirectives",
+            "()Ljava/lang/AssertionStatusDirectives;",
+            Box::new(jvm_retrieveDirectives),
+        ),
     ]
 }
 
@@ -37,12 +54,15 @@ fn jvm_findBuiltinLib(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(None)
 }
 
+//the classes loaded by 'this' as the initiating loader
 fn jvm_findLoadedClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let class_loader = ClassLoader::from_oop(args.first().unwrap());
     let name = args.get(1).unwrap();
     let name = OopPtr::java_lang_string(name.extract_ref());
     info!("findLoadedClass0: {}", name);
     let name = name.replace(".", util::FILE_SEP);
-    let v = match runtime::sys_dic_find(name.as_bytes()) {
+    let class_loader = class_loader.unwrap_or(ClassLoader::Bootstrap);
+    let v = match runtime::sys_dic_find(class_loader, name.as_bytes()) {
         Some(cls) => {
             let cls = cls.get_class();
             cls.get_mirror()
@@ -52,8 +72,159 @@ fn jvm_findLoadedClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(v))
 }
 
-// fixme: Is this correct? uncertain
+//null if not found
 fn jvm_findBootstrapClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    info!("findBootstrapClass");
-    jvm_findLoadedClass0(_env, args)
+    let name = args.get(1).unwrap();
+    let name = OopPtr::java_lang_string(name.extract_ref());
+    info!("findBootstrapClass: {}", name);
+    let name = name.replace(".", util::FILE_SEP);
+    match require_class3(None, name.as_bytes()) {
+        Some(cls) => {
+            let cls = cls.get_class();
+            Ok(Some(cls.get_mirror()))
+        }
+        None => take_ex(),
+    }
+}
+
+fn jvm_defineClass1(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int();
+    let len = args.get(4).unwrap().extract_int();
+
+    let buf = match b {
+        Oop::Null => {
+            let ex = runtime::exception::new(J_NPE, None);
+            return Err(ex);
+        }
+        b => {
+            let rf = b.extract_ref();
+            let ary = rf.extract_type_array();
+            let ary = ary.extract_bytes();
+            if off < 0 || len < 0 || (off as usize + len as usize) > ary.len() {
+                let ex = runtime::exception::new(J_ARRAY_INDEX_OUT_OF_BOUNDS, None);
+                return Err(ex);
+            }
+            ary[off as usize..(off + len) as usize].to_vec()
+        }
+    };
+
+    define_class(args, &buf)
+}
+
+//only the direct buffers, the others are copied to byte[] by the java side
+fn jvm_defineClass2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int();
+    let len = args.get(4).unwrap().extract_int();
+
+    let b = match b {
+        Oop::Null => {
+            let ex = runtime::exception::new(J_NPE, None);
+            return Err(ex);
+        }
+        b => b.extract_ref(),
+    };
+
+    let (address, capacity) = {
+        let cls = require_class3(None, b"java/nio/Buffer").unwrap();
+        let (address, capacity) = {
+            let cls = cls.get_class();
+            (
+                cls.get_field_id(&new_br("address"), &new_br("J"), false),
+                cls.get_field_id(&new_br("capacity"), &new_br("I"), false),
+            )
+        };
+        (
+            Class::get_field_value(b.clone(), address).extract_long(),
+            Class::get_field_value(b, capacity).extract_int(),
+        )
+    };
+
+    if off < 0 || len < 0 || off as i64 + len as i64 > capacity as i64 {
+        let ex = runtime::exception::new(J_ARRAY_INDEX_OUT_OF_BOUNDS, None);
+        return Err(ex);
+    }
+
+    let ptr = (address + off as i64) as *const u8;
+    let buf = unsafe { std::slice::from_raw_parts(ptr, len as usize) };
+    define_class(args, buf)
+}
+
+//args: this, name, bytes, off, len, pd, source
+fn define_class(args: &[Oop], buf: &[u8]) -> JNIResult {
+    let class_loader = ClassLoader::from_oop(args.first().unwrap());
+    let name = match args.get(1).unwrap() {
+        Oop::Null => None,
+        v => {
+            let name = OopPtr::java_lang_string(v.extract_ref());
+            Some(name.replace(".", util::FILE_SEP))
+        }
+    };
+
+    let name = name.as_ref().map(|it| it.as_bytes());
+    match runtime::define_class(class_loader, name, buf) {
+        Some(cls) => {
+            let cls = cls.get_class();
+            Ok(Some(cls.get_mirror()))
+        }
+        None => take_ex(),
+    }
+}
+
+//the exception thrown by class loader, or null
+fn take_ex() -> JNIResult {
+    if thread::is_meet_ex() {
+        let jt = thread::current_java_thread();
+        let ex = jt.write().unwrap().take_ex().unwrap();
+        Err(ex)
+    } else {
+        Ok(Some(Oop::Null))
+    }
+}
+
+//-ea & -da, applied by the class loaders of Java, the system classes are by desiredAssertionStatus0
+fn jvm_retrieveDirectives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let (mut classes, mut class_enabled) = (Vec::new(), Vec::new());
+    let (mut packages, mut package_enabled) = (Vec::new(), Vec::new());
+    let mut deflt = false;
+    for it in arguments::get().assertions.iter() {
+        match it {
+            Assertion::All(enabled) => deflt = *enabled,
+            Assertion::Package(name, enabled) => {
+                packages.push(util::oop::new_java_lang_string2(name));
+                package_enabled.push(*enabled as u8);
+            }
+            Assertion::Class(name, enabled) => {
+                classes.push(util::oop::new_java_lang_string2(name));
+                class_enabled.push(*enabled as u8);
+            }
+        }
+    }
+
+    let cls = oop::class::load_and_init(b"java/lang/AssertionStatusDirectives");
+    let ary_cls = require_class3(None, b"[Ljava/lang/String;").unwrap();
+    let directives = Oop::new_inst(cls.clone());
+    let fields = vec![
+        (
+            "classes",
+            "[Ljava/lang/String;",
+            Oop::new_ref_ary2(ary_cls.clone(), classes),
+        ),
+        ("classEnabled", "[Z", Oop::new_bool_ary2(class_enabled)),
+        (
+            "packages",
+            "[Ljava/lang/String;",
+            Oop::new_ref_ary2(ary_cls, packages),
+        ),
+        ("packageEnabled", "[Z", Oop::new_bool_ary2(package_enabled)),
+        ("deflt", "Z", Oop::new_int(deflt as i32)),
+    ];
+    let cls = cls.get_class();
+    for (name, desc, v) in fields {
+        let id = cls.get_field_id(&new_br(name), &new_br(desc), false);
+        Class::put_field_value(directives.extract_ref(), id, v);
+    }
+
+    Ok(Some(directives))
 }
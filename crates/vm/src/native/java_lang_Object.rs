@@ -2,6 +2,8 @@
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop};
+use crate::runtime::{self, thread::ThreadState};
+use classfile::consts::{J_ILLEGAL_ARGUMENT, J_ILLEGAL_MONITOR_STATE, J_INTERRUPTED_EXCEPTION};
 use std::time::Duration;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
@@ -10,8 +12,10 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("hashCode", "()I", Box::new(jvm_hashCode)),
         new_fn("clone", "()Ljava/lang/Object;", Box::new(jvm_clone)),
         new_fn("getClass", "()Ljava/lang/Class;", Box::new(jvm_getClass)),
+        new_fn("notify", "()V", Box::new(jvm_notify)),
         new_fn("notifyAll", "()V", Box::new(jvm_notifyAll)),
         new_fn("wait", "(J)V", Box::new(jvm_wait)),
+        new_fn("wait", "(JI)V", Box::new(jvm_wait2)),
     ]
 }
 
@@ -52,23 +56,94 @@ fn jvm_getClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(mirror))
 }
 
+fn jvm_notify(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let rf = this.extract_ref();
+    if rf.notify() {
+        Ok(None)
+    } else {
+        Err(not_owner())
+    }
+}
+
 fn jvm_notifyAll(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let this = args.get(0).unwrap();
+    let this = args.first().unwrap();
     let rf = this.extract_ref();
-    rf.notify_all();
-    Ok(None)
+    if rf.notify_all() {
+        Ok(None)
+    } else {
+        Err(not_owner())
+    }
 }
 
 fn jvm_wait(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let this = args.get(0).unwrap();
+    let this = args.first().unwrap();
     let millis = args.get(1).unwrap().extract_long();
+    if millis < 0 {
+        let msg = Some("timeout value is negative".to_string());
+        return Err(runtime::exception::new(J_ILLEGAL_ARGUMENT, msg));
+    }
+
+    wait(this, Duration::from_millis(millis as u64))
+}
+
+//plain java in jdk8, for the class libraries declaring it native
+fn jvm_wait2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let millis = args.get(1).unwrap().extract_long();
+    let nanos = args.get(2).unwrap().extract_int();
+    if millis < 0 {
+        let msg = Some("timeout value is negative".to_string());
+        return Err(runtime::exception::new(J_ILLEGAL_ARGUMENT, msg));
+    }
+    if !(0..=999_999).contains(&nanos) {
+        let msg = Some("nanosecond timeout value out of range".to_string());
+        return Err(runtime::exception::new(J_ILLEGAL_ARGUMENT, msg));
+    }
+
+    let timeout = Duration::from_millis(millis as u64) + Duration::from_nanos(nanos as u64);
+    wait(this, timeout)
+}
 
+//zero timeout means forever
+fn wait(this: &Oop, timeout: Duration) -> JNIResult {
     let rf = this.extract_ref();
-    if millis == 0 {
+    if !rf.is_monitor_owner() {
+        return Err(not_owner());
+    }
+
+    //Thread.interrupt notifies the object waited on
+    let jt = runtime::thread::current_java_thread();
+    let parker = jt.read().unwrap().parker.clone();
+    if !parker.begin_wait(this.clone()) {
+        return Err(runtime::exception::new(J_INTERRUPTED_EXCEPTION, None));
+    }
+
+    let forever = timeout == Duration::from_millis(0);
+    let state = if forever {
+        ThreadState::Waiting
+    } else {
+        ThreadState::TimedWaiting
+    };
+    jt.write().unwrap().set_state(state);
+
+    runtime::gc::enter_safe_region();
+    if forever {
         rf.wait();
     } else {
-        rf.wait_timeout(Duration::from_millis(millis as u64));
+        rf.wait_timeout(timeout);
+    }
+    runtime::gc::leave_safe_region();
+
+    jt.write().unwrap().set_state(ThreadState::Runnable);
+    if !parker.end_wait() {
+        return Err(runtime::exception::new(J_INTERRUPTED_EXCEPTION, None));
     }
 
     Ok(None)
 }
+
+fn not_owner() -> Oop {
+    let msg = Some("current thread is not owner".to_string());
+    runtime::exception::new(J_ILLEGAL_MONITOR_STATE, msg)
+}
@@ -0,0 +1,21 @@
+#![allow(non_snake_case)]
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime::require_class3;
+use std::os::unix::ffi::OsStrExt;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn("environ", "()[[B", Box::new(jvm_environ))]
+}
+
+//name & value, one after another
+fn jvm_environ(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mut elms = Vec::new();
+    for (k, v) in std::env::vars_os() {
+        elms.push(Oop::new_byte_ary2(k.as_bytes().to_vec()));
+        elms.push(Oop::new_byte_ary2(v.as_bytes().to_vec()));
+    }
+
+    let ary_cls = require_class3(None, b"[[B").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, elms)))
+}
@@ -1,7 +1,8 @@
 #![allow(non_snake_case)]
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::Oop;
+use crate::oop::{heap, Oop};
+use crate::runtime::{self, arguments};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -11,6 +12,9 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             Box::new(jvm_availableProcessors),
         ),
         new_fn("gc", "()V", Box::new(jvm_gc)),
+        new_fn("maxMemory", "()J", Box::new(jvm_maxMemory)),
+        new_fn("totalMemory", "()J", Box::new(jvm_totalMemory)),
+        new_fn("freeMemory", "()J", Box::new(jvm_freeMemory)),
     ]
 }
 
@@ -20,5 +24,22 @@ fn jvm_availableProcessors(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_gc(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    runtime::gc::gc();
     Ok(None)
 }
+
+fn jvm_maxMemory(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let v = arguments::max_heap_size().min(i64::MAX as usize);
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+//the heap isn't reserved ahead, it may grow to the max
+fn jvm_totalMemory(env: JNIEnv, args: &[Oop]) -> JNIResult {
+    jvm_maxMemory(env, args)
+}
+
+fn jvm_freeMemory(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let max = arguments::max_heap_size().min(i64::MAX as usize);
+    let v = max.saturating_sub(heap::used_size());
+    Ok(Some(Oop::new_long(v as i64)))
+}
@@ -1,12 +1,36 @@
 #![allow(non_snake_case)]
 
-use crate::native::{self, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{self, jni, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop, OopPtr};
-use crate::runtime::{self, thread, JavaCall};
+use crate::runtime::{
+    self, arguments, cmp, exception, require_class3, thread, ClassLoader, JavaCall,
+};
+use crate::types::ClassRef;
 use crate::{new_br, util};
+use classfile::consts::{
+    J_ARRAY_INDEX_OUT_OF_BOUNDS, J_ARRAY_STORE_EXCEPTION, J_CLASS, J_NPE, J_SYSTEM,
+    J_UNSATISFIED_LINK_ERROR,
+};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::SystemTime;
 
+//loaded by jdk classes, the natives are implemented in vm
+const BUILTIN_LIBRARIES: &[&str] = &[
+    "awt",
+    "instrument",
+    "j2pkcs11",
+    "jaas_unix",
+    "java",
+    "management",
+    "net",
+    "nio",
+    "prefs",
+    "sunec",
+    "verify",
+    "zip",
+];
+
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
         new_fn(
@@ -28,6 +52,7 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             "(Ljava/lang/String;)Ljava/lang/String;",
             Box::new(jvm_mapLibraryName),
         ),
+        new_fn("load", "(Ljava/lang/String;)V", Box::new(jvm_load)),
         new_fn(
             "loadLibrary",
             "(Ljava/lang/String;)V",
@@ -49,48 +74,119 @@ fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(None)
 }
 
+/*
+The checks are done in the order of the spec: null, array types, bounds,
+then the elements one by one if the reference array types don't match, the
+elements before the failed one are copied.
+*/
 fn jvm_arraycopy(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let src = args.get(0).unwrap();
+    let src = args.first().unwrap();
     let src_pos = args.get(1).unwrap().extract_int();
     let dest = args.get(2).unwrap();
     let dest_pos = args.get(3).unwrap().extract_int();
     let length = args.get(4).unwrap().extract_int();
 
-    //todo: do check & throw exception
+    let (src_rf, dest_rf) = match (src, dest) {
+        (Oop::Ref(src), Oop::Ref(dest)) => (src.clone(), dest.clone()),
+        _ => return Err(exception::new(J_NPE, None)),
+    };
+
+    let src_len = match array_len(&src_rf) {
+        Some(len) => len as i64,
+        None => {
+            let msg = format!("arraycopy: source type {} is not an array", type_name(src));
+            return Err(exception::new(J_ARRAY_STORE_EXCEPTION, Some(msg)));
+        }
+    };
+    let dest_len = match array_len(&dest_rf) {
+        Some(len) => len as i64,
+        None => {
+            let msg = format!(
+                "arraycopy: destination type {} is not an array",
+                type_name(dest)
+            );
+            return Err(exception::new(J_ARRAY_STORE_EXCEPTION, Some(msg)));
+        }
+    };
+
+    if !is_same_array_kind(&src_rf, &dest_rf) {
+        let msg = format!(
+            "arraycopy: type mismatch: can not copy {} into {}",
+            type_name(src),
+            type_name(dest)
+        );
+        return Err(exception::new(J_ARRAY_STORE_EXCEPTION, Some(msg)));
+    }
+
+    let (src_pos, dest_pos, length) = (src_pos as i64, dest_pos as i64, length as i64);
+    if src_pos < 0
+        || dest_pos < 0
+        || length < 0
+        || src_pos + length > src_len
+        || dest_pos + length > dest_len
+    {
+        let msg = format!(
+            "arraycopy: src {} of {}, dest {} of {}, length {}",
+            src_pos, src_len, dest_pos, dest_len, length
+        );
+        return Err(exception::new(J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg)));
+    }
 
     if length == 0 {
         return Ok(None);
     }
 
-    let is_same_obj = OopPtr::is_eq(src, dest);
+    let (src_pos, dest_pos, length) = (src_pos as usize, dest_pos as usize, length as usize);
+    if OopPtr::is_eq(src, dest) {
+        arraycopy_same_obj(src_rf, src_pos, dest_pos, length);
+        return Ok(None);
+    }
 
-    if is_same_obj {
-        arraycopy_same_obj(
-            src.extract_ref(),
-            src_pos as usize,
-            dest_pos as usize,
-            length as usize,
-        );
-    } else {
-        arraycopy_diff_obj(
-            src.extract_ref(),
-            src_pos as usize,
-            dest.extract_ref(),
-            dest_pos as usize,
-            length as usize,
-        );
+    let is_ref_ary = unsafe { matches!(&(*src_rf.get_raw_ptr()).v, oop::RefKind::Array(_)) };
+    if is_ref_ary {
+        let (src_cls, dest_cls) = {
+            let src = src_rf.extract_array();
+            let dest = dest_rf.extract_array();
+            (src.class.clone(), dest.class.clone())
+        };
+
+        //Integer[] -> Object[], no element need to check
+        if !cmp::instance_of(src_cls, dest_cls.clone()) {
+            let elm_cls = element_class(dest_cls.clone());
+            let src_ary = src_rf.extract_array();
+            let failed =
+                src_ary
+                    .iter()
+                    .skip(src_pos)
+                    .take(length)
+                    .position(|v| match class_of(&v) {
+                        Some(cls) => !cmp::instance_of(cls, elm_cls.clone()),
+                        None => false,
+                    });
+
+            if let Some(n) = failed {
+                let elm = &src_ary.get(src_pos + n);
+                arraycopy_diff_obj(src_rf.clone(), src_pos, dest_rf, dest_pos, n);
+                let msg = format!(
+                    "arraycopy: element type {} cannot be stored to destination array of type {}",
+                    type_name(elm),
+                    external_name(dest_cls.get_class().name.as_slice())
+                );
+                return Err(exception::new(J_ARRAY_STORE_EXCEPTION, Some(msg)));
+            }
+        }
     }
 
+    arraycopy_diff_obj(src_rf, src_pos, dest_rf, dest_pos, length);
+
     Ok(None)
 }
 
 fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    //fixme:
     let props = vec![
         ("file.encoding.pkg", "sun.io"),
         ("file.encoding", "US-ASCII"),
         ("file.separator", util::FILE_SEP),
-        ("java.class.path", "."),
         ("java.class.version", "52.0"),
         ("java.security.egd", "file:/dev/random"),
         // ("java.security.debug", "all"),
@@ -103,19 +199,13 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         ("java.vendor.url.bug", "https://github.com/douchuan/jvm"),
         ("java.version", "1.8"),
         ("line.separator", util::LINE_SEP),
-        ("os.arch", "x86_64"),
-        ("os.name", "Mac OS X"),
-        ("os.version", "18.7.0"),
         ("path.separator", util::PATH_SEP),
-        ("sun.arch.data.model", "64"),
-        ("sun.cpu.endian", "little"),
         ("sun.cpu.isalist", ""),
         // ("sun.misc.URLClassPath.debug", "true"),
         // ("sun.misc.URLClassPath.debugLookupCache", "true"),
         ("sun.stdout.encoding", "UTF-8"),
         ("sun.stderr.encoding", "UTF-8"),
         ("user.language", "en"),
-        ("user.name", "chuan"),
         ("user.region", "US"),
         //        ("java.security.manager", ""),
         //        ("sun.jnu.encoding", "UTF-8"),
@@ -127,6 +217,26 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         put_props_kv(props_oop, k, v);
     }
 
+    //os.name picks the platform classes, such as the FileSystemProvider of java.nio.file
+    let (os_name, os_version, os_arch) = uname();
+    put_props_kv(props_oop, "os.name", &os_name);
+    put_props_kv(props_oop, "os.version", &os_version);
+    put_props_kv(props_oop, "os.arch", &os_arch);
+
+    let data_model = (std::mem::size_of::<usize>() * 8).to_string();
+    put_props_kv(props_oop, "sun.arch.data.model", &data_model);
+    let endian = if cfg!(target_endian = "little") {
+        "little"
+    } else {
+        "big"
+    };
+    put_props_kv(props_oop, "sun.cpu.endian", endian);
+
+    //user.name & user.home
+    let (user_name, user_home) = user();
+    put_props_kv(props_oop, "user.name", &user_name);
+    put_props_kv(props_oop, "user.home", &user_home);
+
     //user.dir
     let v = std::env::current_dir().expect("current_dir failed");
     let v = v.to_str().expect("current_dir to_str faield");
@@ -137,10 +247,9 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let v = v.to_str().expect("temp_dir to_str failed");
     put_props_kv(props_oop, "java.io.tmpdir", v);
 
-    //user.home
-    let v = dirs::home_dir().expect("get home_dir failed");
-    let v = v.to_str().expect("home_dir to_str failed");
-    put_props_kv(props_oop, "user.home", v);
+    //java.library.path
+    let v = default_library_path();
+    put_props_kv(props_oop, "java.library.path", v.as_str());
 
     //JAVA_HOME
     let v = std::env::var("JAVA_HOME").expect("Please Setup JAVA_HOME env");
@@ -151,6 +260,13 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         put_props_kv(props_oop, "test.src", v.as_str());
     }
 
+    //java.class.path & -D, the latter overrides any of above
+    let args = arguments::get();
+    put_props_kv(props_oop, "java.class.path", &args.class_path);
+    for (k, v) in args.properties.iter() {
+        put_props_kv(props_oop, k, v);
+    }
+
     if thread::is_meet_ex() {
         unreachable!("jvm_initProperties meet ex");
     }
@@ -158,6 +274,61 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(props_oop.clone()))
 }
 
+//os.name, os.version & os.arch of the host, named as the JDK
+fn uname() -> (String, String, String) {
+    let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return (
+            std::env::consts::OS.to_string(),
+            String::new(),
+            std::env::consts::ARCH.to_string(),
+        );
+    }
+
+    let field = |v: &[libc::c_char]| {
+        let v = unsafe { std::ffi::CStr::from_ptr(v.as_ptr()) };
+        v.to_string_lossy().to_string()
+    };
+    let sysname = field(&name.sysname);
+    let release = field(&name.release);
+    let machine = field(&name.machine);
+
+    let os_name = match sysname.as_str() {
+        "Darwin" => String::from("Mac OS X"),
+        _ => sysname,
+    };
+    let os_arch = match machine.as_str() {
+        "x86_64" if os_name != "Mac OS X" => String::from("amd64"),
+        "i486" | "i586" | "i686" => String::from("i386"),
+        _ => machine,
+    };
+
+    (os_name, release, os_arch)
+}
+
+//the name & the home directory of the user, by the password database
+fn user() -> (String, String) {
+    let pw = unsafe { libc::getpwuid(libc::getuid()) };
+    if pw.is_null() {
+        let name = std::env::var("USER").unwrap_or_else(|_| String::from("?"));
+        let home = dirs::home_dir()
+            .and_then(|it| it.to_str().map(String::from))
+            .unwrap_or_else(|| String::from("?"));
+        return (name, home);
+    }
+
+    let (name, home) = unsafe {
+        (
+            std::ffi::CStr::from_ptr((*pw).pw_name),
+            std::ffi::CStr::from_ptr((*pw).pw_dir),
+        )
+    };
+    (
+        name.to_string_lossy().to_string(),
+        home.to_string_lossy().to_string(),
+    )
+}
+
 fn put_props_kv(props: &Oop, k: &str, v: &str) {
     //todo: optimize me
     let cls = {
@@ -213,27 +384,11 @@ fn jvm_setErr0(env: JNIEnv, args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_mapLibraryName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let v = args.get(0).unwrap();
+    let v = args.first().unwrap();
     let s = OopPtr::java_lang_string(v.extract_ref());
 
     trace!("mapLibraryName libname = {}", s);
-    let mut name = String::new();
-    if cfg!(target_os = "macos") {
-        name.push_str("lib");
-        name.push_str(&s);
-        name.push_str(".dylib");
-    } else if cfg!(target_os = "windows") {
-        unimplemented!();
-    //        name.extend_from_slice(s.as_bytes());
-    //        name.extend_from_slice(".dll".as_bytes());
-    } else if cfg!(target_os = "linux") {
-        unimplemented!();
-    //        name.extend_from_slice("lib".as_bytes());
-    //        name.extend_from_slice(s.as_bytes());
-    //        name.extend_from_slice(".so".as_bytes());
-    } else {
-        unimplemented!()
-    }
+    let name = map_library_name(&s);
     trace!("mapLibraryName name = {}", name);
 
     let v = util::oop::new_java_lang_string2(&name);
@@ -241,8 +396,132 @@ fn jvm_mapLibraryName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(v))
 }
 
-fn jvm_loadLibrary(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
-    Ok(None)
+fn jvm_load(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let v = args.first().unwrap();
+    let file_name = OopPtr::java_lang_string(v.extract_ref());
+
+    if !Path::new(&file_name).is_absolute() {
+        let msg = format!("Expecting an absolute path of the library: {}", file_name);
+        return Err(exception::new(J_UNSATISFIED_LINK_ERROR, Some(msg)));
+    }
+
+    if !Path::new(&file_name).exists() {
+        let msg = format!("Can't load library: {}", file_name);
+        return Err(exception::new(J_UNSATISFIED_LINK_ERROR, Some(msg)));
+    }
+
+    load_library(&file_name, caller_loader())
+}
+
+fn jvm_loadLibrary(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let v = args.first().unwrap();
+    let lib_name = OopPtr::java_lang_string(v.extract_ref());
+
+    //the natives of jdk libraries are built in vm
+    if BUILTIN_LIBRARIES.contains(&lib_name.as_str()) {
+        return Ok(None);
+    }
+
+    let file_name = map_library_name(&lib_name);
+    let paths = get_property("java.library.path").unwrap_or_default();
+    let found = paths
+        .split(util::PATH_SEP)
+        .filter(|it| !it.is_empty())
+        .map(|it| Path::new(it).join(&file_name))
+        .find(|it| it.exists());
+
+    match found {
+        Some(path) => load_library(&path.to_string_lossy(), caller_loader()),
+        None => {
+            let msg = format!("no {} in java.library.path", lib_name);
+            Err(exception::new(J_UNSATISFIED_LINK_ERROR, Some(msg)))
+        }
+    }
+}
+
+fn load_library(path: &str, loader: Option<ClassLoader>) -> JNIResult {
+    match jni::library::load(path, loader) {
+        //JNI_OnLoad may throw
+        Ok(_) if thread::is_meet_ex() => {
+            let ex = thread::current_java_thread()
+                .write()
+                .unwrap()
+                .take_ex()
+                .unwrap();
+            Err(ex)
+        }
+        Ok(_) => Ok(None),
+        Err(msg) => Err(exception::new(J_UNSATISFIED_LINK_ERROR, Some(msg))),
+    }
+}
+
+//the loader of the class calling System.load/loadLibrary, the natives of its
+//classes are looked up in the library
+fn caller_loader() -> Option<ClassLoader> {
+    let jt = thread::current_java_thread();
+    let frames = { jt.read().unwrap().frames.clone() };
+    //the top frame is System.load/loadLibrary itself
+    let caller = frames.iter().rev().nth(1)?;
+    let caller = caller.try_read().unwrap();
+    let cls = caller.mir.method.class.get_class();
+    cls.class_loader
+}
+
+fn map_library_name(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("lib{}.dylib", name)
+    } else if cfg!(target_os = "windows") {
+        format!("{}.dll", name)
+    } else {
+        format!("lib{}.so", name)
+    }
+}
+
+//the same as hotspot on linux
+fn default_library_path() -> String {
+    let mut paths = Vec::new();
+    if let Ok(v) = std::env::var("LD_LIBRARY_PATH") {
+        paths.push(v);
+    }
+    paths.push(String::from(
+        "/usr/java/packages/lib:/usr/lib64:/lib64:/lib:/usr/lib",
+    ));
+    paths.join(util::PATH_SEP)
+}
+
+//System.getProperty
+fn get_property(key: &str) -> Option<String> {
+    let props = {
+        let cls = runtime::require_class3(None, J_SYSTEM).unwrap();
+        let cls = cls.get_class();
+        let id = cls.get_field_id(&new_br("props"), &new_br("Ljava/util/Properties;"), true);
+        cls.get_static_field_value(id)
+    };
+
+    let mir = {
+        let cls = props.extract_ref().extract_inst().class.clone();
+        let cls = cls.get_class();
+        cls.get_virtual_method(
+            &new_br("getProperty"),
+            &new_br("(Ljava/lang/String;)Ljava/lang/String;"),
+        )
+        .unwrap()
+    };
+
+    let args = vec![props, util::oop::new_java_lang_string2(key)];
+    let mut jc = JavaCall::new_with_args(mir, args);
+    let area = runtime::DataArea::new(1);
+    jc.invoke(Some(&area), false);
+
+    if thread::is_meet_ex() {
+        return None;
+    }
+
+    let v = area.stack.borrow_mut().pop_ref();
+    match v {
+        Oop::Null => None,
+        v => Some(OopPtr::java_lang_string(v.extract_ref())),
+    }
 }
 
 fn jvm_identityHashCode(env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -287,50 +566,17 @@ fn jvm_getProperty(jt: &mut JavaThread, env: JNIEnv, args: Vec<OopRef>) -> JNIRe
 */
 
 fn arraycopy_same_obj(buf: Arc<OopPtr>, src_pos: usize, dest_pos: usize, length: usize) {
-    let is_type_ary = {
-        let ptr = buf.get_raw_ptr();
-        unsafe {
-            match &(*ptr).v {
-                oop::RefKind::TypeArray(_) => true,
-                oop::RefKind::Array(_) => false,
-                _ => unreachable!(),
-            }
+    let ptr = buf.get_mut_raw_ptr();
+    unsafe {
+        match &mut (*ptr).v {
+            oop::RefKind::TypeArray(ary) => ary.copy_within(src_pos, dest_pos, length),
+            oop::RefKind::Array(ary) => ary.copy_within(src_pos, dest_pos, length),
+            t => unreachable!("t = {:?}", t),
         }
-    };
-
-    if is_type_ary {
-        let ptr = buf.get_mut_raw_ptr();
-        unsafe {
-            match &mut (*ptr).v {
-                oop::RefKind::TypeArray(ary) => match ary {
-                    oop::TypeArrayDesc::Char(ary) => {
-                        ary.copy_within(src_pos..(src_pos + length), dest_pos)
-                    }
-                    oop::TypeArrayDesc::Byte(ary) => {
-                        ary.copy_within(src_pos..(src_pos + length), dest_pos)
-                    }
-                    t => unreachable!("t = {:?}", t),
-                },
-
-                _ => unreachable!(),
-            }
-        }
-    } else {
-        let tmp = {
-            let ary = buf.extract_array();
-            let mut tmp = vec![Oop::Null; length];
-
-            let (_, ary) = ary.elements.split_at(src_pos);
-            tmp.clone_from_slice(&ary[..length]);
-            tmp
-        };
-
-        let ary = buf.extract_mut_array();
-        let (_, ary) = ary.elements.split_at_mut(dest_pos);
-        ary[..length].clone_from_slice(&tmp[..]);
     }
 }
 
+//the types are checked by caller
 pub fn arraycopy_diff_obj(
     src: Arc<OopPtr>,
     src_pos: usize,
@@ -338,62 +584,91 @@ pub fn arraycopy_diff_obj(
     dest_pos: usize,
     length: usize,
 ) {
-    let is_type_ary = {
-        let ptr = src.get_raw_ptr();
-        unsafe {
-            match &(*ptr).v {
-                oop::RefKind::TypeArray(_) => true,
-                oop::RefKind::Array(_) => false,
-                _ => unreachable!(),
+    let src_ptr = src.get_raw_ptr();
+    let dest_ptr = dest.get_mut_raw_ptr();
+    unsafe {
+        match (&(*src_ptr).v, &mut (*dest_ptr).v) {
+            (oop::RefKind::TypeArray(src), oop::RefKind::TypeArray(dest)) => {
+                let copied = dest.copy_from(src, src_pos, dest_pos, length);
+                debug_assert!(copied);
+            }
+            (oop::RefKind::Array(src), oop::RefKind::Array(dest)) => {
+                dest.copy_from(src, src_pos, dest_pos, length)
             }
+            t => unreachable!("t = {:?}", t),
         }
-    };
-
-    // error!("src={}, dest={}, length={}, is_type_ary={}", src_pos, dest_pos, length, is_type_ary);
+    }
+}
 
-    let src_ptr = src.get_raw_ptr();
+//none if it's not an array
+fn array_len(rf: &OopPtr) -> Option<usize> {
+    let ptr = rf.get_raw_ptr();
+    unsafe {
+        match &(*ptr).v {
+            oop::RefKind::TypeArray(ary) => Some(ary.len()),
+            oop::RefKind::Array(ary) => Some(ary.len()),
+            _ => None,
+        }
+    }
+}
 
-    if is_type_ary {
-        unsafe {
-            match &(*src_ptr).v {
-                oop::RefKind::TypeArray(src_ary) => match src_ary {
-                    oop::TypeArrayDesc::Byte(src_ary) => {
-                        let dest_ary = dest.extract_mut_type_array();
-                        let dest_ary = dest_ary.extract_mut_bytes();
-                        let (_, dest_ptr) = dest_ary.split_at_mut(dest_pos);
-                        let (_, src_ptr) = src_ary.split_at(src_pos);
-                        dest_ptr[..length].copy_from_slice(&src_ptr[..length]);
-                    }
-                    oop::TypeArrayDesc::Char(src_ary) => {
-                        let dest_ary = dest.extract_mut_type_array();
-                        let dest_ary = dest_ary.extract_mut_chars();
-                        let (_, dest_ptr) = dest_ary.split_at_mut(dest_pos);
-                        let (_, src_ptr) = src_ary.split_at(src_pos);
-                        dest_ptr[..length].copy_from_slice(&src_ptr[..length]);
-                    }
-                    oop::TypeArrayDesc::Int(src_ary) => {
-                        let dest_ary = dest.extract_mut_type_array();
-                        let dest_ary = dest_ary.extract_mut_ints();
-                        let (_, dest_ptr) = dest_ary.split_at_mut(dest_pos);
-                        let (_, src_ptr) = src_ary.split_at(src_pos);
-                        dest_ptr[..length].copy_from_slice(&src_ptr[..length]);
-                    }
-                    t => unreachable!("t = {:?}", t),
-                },
-                _ => unreachable!(),
+//both reference arrays, or the primitive arrays of the same type
+fn is_same_array_kind(src: &OopPtr, dest: &OopPtr) -> bool {
+    let src = src.get_raw_ptr();
+    let dest = dest.get_raw_ptr();
+    unsafe {
+        match (&(*src).v, &(*dest).v) {
+            (oop::RefKind::TypeArray(src), oop::RefKind::TypeArray(dest)) => {
+                src.class_name() == dest.class_name()
             }
+            (oop::RefKind::Array(_), oop::RefKind::Array(_)) => true,
+            _ => false,
         }
-    } else {
-        let src = src.extract_array();
-        let dest = dest.extract_mut_array();
+    }
+}
 
-        let (_, src_ptr) = src.elements.split_at(src_pos);
-        let (_, dest_ptr) = dest.elements.split_at_mut(dest_pos);
+//the class of an object, none if null
+pub fn class_of(v: &Oop) -> Option<ClassRef> {
+    let rf = match v {
+        Oop::Ref(rf) => rf,
+        _ => return None,
+    };
 
-        dest_ptr[..length].clone_from_slice(&src_ptr[..length]);
+    let ptr = rf.get_raw_ptr();
+    let cls = unsafe {
+        match &(*ptr).v {
+            oop::RefKind::Inst(inst) => inst.class.clone(),
+            oop::RefKind::Array(ary) => ary.class.clone(),
+            oop::RefKind::TypeArray(ary) => require_class3(None, ary.class_name()).unwrap(),
+            oop::RefKind::Mirror(_) => require_class3(None, J_CLASS).unwrap(),
+        }
+    };
+    Some(cls)
+}
+
+//String[][] -> String[], String[] -> String
+fn element_class(ary_cls: ClassRef) -> ClassRef {
+    let cls = ary_cls.get_class();
+    match &cls.kind {
+        oop::class::ClassKind::ObjectArray(ary) => match &ary.down_type {
+            Some(down_type) => down_type.clone(),
+            None => ary.component.clone().unwrap(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn type_name(v: &Oop) -> String {
+    match class_of(v) {
+        Some(cls) => external_name(cls.get_class().name.as_slice()),
+        None => "null".to_string(),
     }
 }
 
+fn external_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).replace("/", ".")
+}
+
 fn jvm_nanoTime(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     let v = match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
         Ok(n) => n.as_nanos(),
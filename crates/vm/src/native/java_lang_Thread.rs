@@ -1,10 +1,14 @@
 #![allow(non_snake_case)]
 
-use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{java_lang_Throwable, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::new_br;
 use crate::oop::{Class, Oop, OopPtr};
+use crate::runtime::thread::ThreadState;
 use crate::runtime::vm::get_vm;
-use crate::runtime::{self, vm, JavaCall, JavaThread};
+use crate::runtime::{self, require_class3, vm, JavaCall, JavaThread};
+use crate::types::JavaThreadRef;
+use classfile::consts::{J_ILLEGAL_ARGUMENT, J_INTERRUPTED_EXCEPTION, J_NPE, J_OOM};
+use std::time::Duration;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -18,6 +22,29 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("isAlive", "()Z", Box::new(jvm_isAlive)),
         new_fn("start0", "()V", Box::new(jvm_start0)),
         new_fn("isInterrupted", "(Z)Z", Box::new(jvm_isInterrupted)),
+        new_fn("interrupt0", "()V", Box::new(jvm_interrupt0)),
+        new_fn("sleep", "(J)V", Box::new(jvm_sleep)),
+        new_fn("yield", "()V", Box::new(jvm_yield)),
+        new_fn(
+            "holdsLock",
+            "(Ljava/lang/Object;)Z",
+            Box::new(jvm_holdsLock),
+        ),
+        new_fn(
+            "getThreads",
+            "()[Ljava/lang/Thread;",
+            Box::new(jvm_getThreads),
+        ),
+        new_fn(
+            "dumpThreads",
+            "([Ljava/lang/Thread;)[[Ljava/lang/StackTraceElement;",
+            Box::new(jvm_dumpThreads),
+        ),
+        new_fn(
+            "setNativeName",
+            "(Ljava/lang/String;)V",
+            Box::new(jvm_setNativeName),
+        ),
     ]
 }
 
@@ -38,18 +65,11 @@ fn jvm_setPriority0(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
 //'_jt' is caller's thread context, can't be used here
 //should find by 'eetop' in thread pool
 fn jvm_isAlive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let this = args.get(0).unwrap();
-    let eetop = OopPtr::java_lang_thread_eetop(this.extract_ref());
-    let vm = get_vm();
-
-    let r = match vm.threads.find_java_thread(eetop) {
+    let this = args.first().unwrap();
+    let r = match find_java_thread(this) {
         Some(jt) => {
             info!("native thread tag = {}", jt.read().unwrap().tag);
-            if jt.read().unwrap().is_alive {
-                1
-            } else {
-                0
-            }
+            1
         }
         None => 0,
     };
@@ -58,7 +78,7 @@ fn jvm_isAlive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_start0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let thread_oop = args.get(0).unwrap().clone();
+    let thread_oop = args.first().unwrap().clone();
     let clazz = {
         let rf = thread_oop.extract_ref();
         let inst = rf.extract_inst();
@@ -66,54 +86,206 @@ fn jvm_start0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     };
 
     let cls = clazz.get_class();
-    if cls.name.as_slice() == "java/lang/ref/Reference$ReferenceHandler".as_bytes() {
-        Ok(None)
-    } else {
-        let vm = vm::get_vm();
-
-        let jt = JavaThread::new(None, vm.threads.next_id());
-        vm.threads.attach_java_thread(jt.clone());
-
-        let args = vec![thread_oop.clone()];
-        vm.threads.spawn_java_thread(move || {
-            //setup current thread
-            let current_thread = jt.clone();
-            runtime::thread::THREAD.with(|t| {
-                *t.borrow_mut() = current_thread;
-            });
-
-            let cls = clazz.get_class();
-            let mir = {
-                //setup eetop
-                let eetop = jt.read().unwrap().eetop;
-                let fid = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
-                Class::put_field_value(thread_oop.extract_ref(), fid, Oop::new_long(eetop));
-
-                //obtain 'run' method
-                cls.get_virtual_method(&new_br("run"), &new_br("()V"))
-                    .unwrap()
-            };
+    let vm = vm::get_vm();
+    let jt = JavaThread::new(None, vm.threads.next_id());
+
+    //setup before spawned, Thread.isAlive is true once Thread.start returns
+    let (mir, daemon) = {
+        //setup eetop
+        let eetop = jt.read().unwrap().eetop;
+        let fid = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
+        Class::put_field_value(thread_oop.extract_ref(), fid, Oop::new_long(eetop));
 
-            //invoke 'run'
-            let mut jc = JavaCall::new_with_args(mir, args);
-            jt.write().unwrap().is_alive = true;
-            jt.write().unwrap().java_thread_obj = Some(thread_oop.clone());
-            jc.invoke(None, false);
-            jt.write().unwrap().is_alive = false;
+        let fid = cls.get_field_id(&new_br("daemon"), &new_br("Z"), false);
+        let daemon = Class::get_field_value(thread_oop.extract_ref(), fid).extract_int() != 0;
 
-            //notify thread that invoke 'join'
-            let v = thread_oop.extract_ref();
-            v.notify_all();
+        //obtain 'run' method
+        let mir = cls
+            .get_virtual_method(&new_br("run"), &new_br("()V"))
+            .unwrap();
+        (mir, daemon)
+    };
+    {
+        let mut jt = jt.write().unwrap();
+        jt.daemon = daemon;
+        jt.set_java_thread_obj(thread_oop.clone());
+        jt.set_state(ThreadState::Runnable);
+    }
+    vm.threads.attach_java_thread(jt.clone());
 
-            vm.threads.detach_current_thread();
+    let name = jt.read().unwrap().tag.clone();
+    let current_thread = jt.clone();
+    let args = vec![thread_oop];
+    let r = vm.threads.spawn_java_thread(name, move || {
+        //setup current thread
+        runtime::thread::THREAD.with(|t| {
+            *t.borrow_mut() = current_thread;
         });
 
+        //invoke 'run'
+        let mut jc = JavaCall::new_with_args(mir, args);
+        jc.invoke(None, false);
+
+        vm.threads.exit_current_thread();
+    });
+
+    match r {
+        Ok(_) => Ok(None),
+        Err(e) => {
+            jt.write().unwrap().set_state(ThreadState::Terminated);
+            vm.threads.detach_java_thread(&jt);
+            let msg = format!("unable to create new native thread: {}", e);
+            Err(runtime::exception::new(J_OOM, Some(msg)))
+        }
+    }
+}
+
+fn jvm_isInterrupted(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let clear = args.get(1).unwrap().extract_int() != 0;
+    let r = match find_java_thread(this) {
+        Some(jt) => {
+            let parker = jt.read().unwrap().parker.clone();
+            parker.is_interrupted(clear)
+        }
+        None => false,
+    };
+
+    Ok(Some(Oop::new_int(r as i32)))
+}
+
+fn jvm_interrupt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    if let Some(jt) = find_java_thread(this) {
+        let parker = jt.read().unwrap().parker.clone();
+
+        //wake up Object.wait, the monitor isn't entered: its owner may wait for this thread
+        if let Some(obj) = parker.interrupt() {
+            let rf = obj.extract_ref();
+            rf.wake_waiters();
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_sleep(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let millis = args.first().unwrap().extract_long();
+    if millis < 0 {
+        let msg = Some("timeout value is negative".to_string());
+        return Err(runtime::exception::new(J_ILLEGAL_ARGUMENT, msg));
+    }
+
+    let jt = runtime::thread::current_java_thread();
+    let parker = jt.read().unwrap().parker.clone();
+    jt.write().unwrap().set_state(ThreadState::TimedWaiting);
+    runtime::gc::enter_safe_region();
+    let completed = parker.sleep(Duration::from_millis(millis as u64));
+    runtime::gc::leave_safe_region();
+    jt.write().unwrap().set_state(ThreadState::Runnable);
+
+    if completed {
         Ok(None)
+    } else {
+        let msg = Some("sleep interrupted".to_string());
+        Err(runtime::exception::new(J_INTERRUPTED_EXCEPTION, msg))
+    }
+}
+
+fn jvm_yield(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    std::thread::yield_now();
+    Ok(None)
+}
+
+fn jvm_holdsLock(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.first().unwrap();
+    let r = match obj {
+        Oop::Null => return Err(runtime::exception::new(J_NPE, None)),
+        obj => obj.extract_ref().is_monitor_owner(),
+    };
+
+    Ok(Some(Oop::new_int(r as i32)))
+}
+
+fn jvm_getThreads(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let vm = get_vm();
+    let threads = vm
+        .threads
+        .java_threads()
+        .iter()
+        .filter_map(|jt| {
+            let jt = jt.read().unwrap();
+            if jt.is_alive() {
+                jt.java_thread_obj.clone()
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let ary_cls = require_class3(None, b"[Ljava/lang/Thread;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, threads)))
+}
+
+//the stack traces of the threads, empty for the not alive one
+fn jvm_dumpThreads(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let threads = match args.first().unwrap() {
+        Oop::Null => return Err(runtime::exception::new(J_NPE, None)),
+        v => v.extract_ref(),
+    };
+    let threads = threads.extract_array().to_vec();
+
+    let elm_ary_cls = require_class3(None, b"[Ljava/lang/StackTraceElement;").unwrap();
+    let traces = threads
+        .iter()
+        .map(|thread| {
+            let frames = match thread {
+                Oop::Null => vec![],
+                thread => match find_java_thread(thread) {
+                    Some(jt) => {
+                        let jt = jt.read().unwrap();
+                        java_lang_Throwable::snapshot_frames(&jt.frames)
+                    }
+                    None => vec![],
+                },
+            };
+            let elms = java_lang_Throwable::new_stack_trace_elements2(&frames);
+            Oop::new_ref_ary2(elm_ary_cls.clone(), elms)
+        })
+        .collect();
+
+    let ary_cls = require_class3(None, b"[[Ljava/lang/StackTraceElement;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, traces)))
+}
+
+//only for the current thread, as hotspot
+fn jvm_setNativeName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let name = args.get(1).unwrap();
+    let jt = runtime::thread::current_java_thread();
+    let is_current = match &jt.read().unwrap().java_thread_obj {
+        Some(obj) => OopPtr::is_eq(obj, this),
+        None => false,
+    };
+
+    if is_current && !name.is_null() {
+        jt.write().unwrap().tag = OopPtr::java_lang_string(name.extract_ref());
     }
+
+    Ok(None)
 }
 
-fn jvm_isInterrupted(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
-    //todo: fix me
-    let v = Oop::new_int(0);
-    Ok(Some(v))
+//the native thread of a java.lang.Thread, none if not started or terminated
+pub fn find_java_thread(thread: &Oop) -> Option<JavaThreadRef> {
+    let eetop = OopPtr::java_lang_thread_eetop(thread.extract_ref());
+    let vm = get_vm();
+
+    //eetop of the not started thread is 0 too, same as the main thread
+    vm.threads.find_java_thread(eetop).filter(|jt| {
+        let jt = jt.read().unwrap();
+        match &jt.java_thread_obj {
+            Some(obj) => jt.is_alive() && OopPtr::is_eq(obj, thread),
+            None => false,
+        }
+    })
 }
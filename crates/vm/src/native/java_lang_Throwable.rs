@@ -3,6 +3,7 @@
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Class, Oop};
 use crate::runtime::{self, require_class3};
+use crate::types::{FrameRef, MethodIdRef};
 use crate::{new_br, util};
 use std::sync::atomic::Ordering;
 
@@ -29,7 +30,6 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
 fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let jt = runtime::thread::current_java_thread();
 
-    let elm_cls = oop::class::load_and_init(b"java/lang/StackTraceElement");
     let ary_cls = require_class3(None, b"[Ljava/lang/StackTraceElement;").unwrap();
 
     let throwable_oop = args.get(0).unwrap();
@@ -76,48 +76,7 @@ fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         backtrace.pop();
     }
 
-    let mut traces = Vec::new();
-    for caller in backtrace.iter().rev() {
-        let (mir, pc) = {
-            let caller = caller.try_read().unwrap();
-            let pc = caller.pc.load(Ordering::Relaxed);
-            (caller.mir.clone(), pc)
-        };
-
-        let cls = mir.method.class.get_class();
-        let cls_name = unsafe { std::str::from_utf8_unchecked(cls.name.as_slice()) };
-        let cls_name = cls_name.replace("/", ".");
-        let method_name = unsafe { std::str::from_utf8_unchecked(mir.method.name.as_slice()) };
-        let src_file = {
-            let cls = mir.method.class.get_class();
-            cls.get_source_file()
-        };
-        let src_file = match src_file {
-            Some(name) => {
-                let name = unsafe { std::str::from_utf8_unchecked(name.as_slice()) };
-                util::oop::new_java_lang_string2(name)
-            }
-            None => util::oop::new_java_lang_string2(""),
-        };
-        let line_num = mir.method.get_line_num((pc - 1) as u16);
-
-        let elm = Oop::new_inst(elm_cls.clone());
-        let args = vec![
-            elm.clone(),
-            util::oop::new_java_lang_string2(&cls_name),
-            util::oop::new_java_lang_string2(method_name),
-            src_file,
-            Oop::new_int(line_num),
-        ];
-        runtime::invoke::invoke_ctor(
-            elm_cls.clone(),
-            new_br("(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;I)V"),
-            args,
-        );
-
-        traces.push(elm);
-    }
-
+    let traces = new_stack_trace_elements(&backtrace);
     let stack_trace_ary = Oop::new_ref_ary2(ary_cls, traces);
     let throwable_cls = require_class3(None, b"java/lang/Throwable").unwrap();
     {
@@ -152,7 +111,7 @@ fn jvm_getStackTraceDepth(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         Oop::Null => Oop::new_int(0),
         Oop::Ref(rf) => {
             let ary = rf.extract_array();
-            let len = ary.elements.len();
+            let len = ary.len();
             Oop::new_int(len as i32)
         }
         _ => unreachable!(),
@@ -178,8 +137,8 @@ fn jvm_getStackTraceElement(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let v = {
         let rf = backtrace.extract_ref();
         let ary = rf.extract_array();
-        if index >= 0 && (index as usize) < ary.elements.len() {
-            ary.elements[index as usize].clone()
+        if index >= 0 && (index as usize) < ary.len() {
+            ary.get(index as usize)
         } else {
             Oop::Null
         }
@@ -187,3 +146,66 @@ fn jvm_getStackTraceElement(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
     Ok(Some(v))
 }
+
+//the frames are from the bottom to the top, the elements from the top
+pub fn new_stack_trace_elements(frames: &[FrameRef]) -> Vec<Oop> {
+    let frames = snapshot_frames(frames);
+    new_stack_trace_elements2(&frames)
+}
+
+//copy the method & pc of the frames, they may belong to another thread,
+//so block on the lock instead of assuming it's free
+pub fn snapshot_frames(frames: &[FrameRef]) -> Vec<(MethodIdRef, i32)> {
+    frames
+        .iter()
+        .map(|frame| {
+            let frame = frame.read().unwrap();
+            let pc = frame.pc.load(Ordering::Relaxed);
+            (frame.mir.clone(), pc)
+        })
+        .collect()
+}
+
+pub fn new_stack_trace_elements2(frames: &[(MethodIdRef, i32)]) -> Vec<Oop> {
+    let elm_cls = oop::class::load_and_init(b"java/lang/StackTraceElement");
+
+    let mut traces = Vec::with_capacity(frames.len());
+    for (mir, pc) in frames.iter().rev() {
+        let (mir, pc) = (mir.clone(), *pc);
+
+        let cls = mir.method.class.get_class();
+        let cls_name = unsafe { std::str::from_utf8_unchecked(cls.name.as_slice()) };
+        let cls_name = cls_name.replace("/", ".");
+        let method_name = unsafe { std::str::from_utf8_unchecked(mir.method.name.as_slice()) };
+        let src_file = {
+            let cls = mir.method.class.get_class();
+            cls.get_source_file()
+        };
+        let src_file = match src_file {
+            Some(name) => {
+                let name = unsafe { std::str::from_utf8_unchecked(name.as_slice()) };
+                util::oop::new_java_lang_string2(name)
+            }
+            None => util::oop::new_java_lang_string2(""),
+        };
+        let line_num = mir.method.get_line_num((pc - 1) as u16);
+
+        let elm = Oop::new_inst(elm_cls.clone());
+        let args = vec![
+            elm.clone(),
+            util::oop::new_java_lang_string2(&cls_name),
+            util::oop::new_java_lang_string2(method_name),
+            src_file,
+            Oop::new_int(line_num),
+        ];
+        runtime::invoke::invoke_ctor(
+            elm_cls.clone(),
+            new_br("(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;I)V"),
+            args,
+        );
+
+        traces.push(elm);
+    }
+
+    traces
+}
@@ -0,0 +1,392 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::blocking;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use nix::errno::Errno;
+use nix::sys::signal::{self, Signal};
+use nix::sys::wait::{self, WaitStatus};
+use nix::unistd::{self, ForkResult, Pid};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::ptr;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "waitForProcessExit",
+            "(I)I",
+            Box::new(jvm_waitForProcessExit),
+        ),
+        new_fn(
+            "forkAndExec",
+            "(I[B[B[BI[BI[B[IZ)I",
+            Box::new(jvm_forkAndExec),
+        ),
+        new_fn("destroyProcess", "(IZ)V", Box::new(jvm_destroyProcess)),
+    ]
+}
+
+/*
+forkAndExec
+
+Everything the child needs is prepared before fork, the child only calls the
+async-signal-safe functions: redirect the stdio, chdir, close the other fds
+& exec. If exec fails, the child writes the errno to the fail pipe, which is
+close-on-exec, so the parent reads nothing if the exec succeeded.
+
+fds[i] is -1 for a pipe, or the fd the stream redirected to. On return, it's
+the parent end of the pipe, or -1.
+*/
+
+//the fail pipe in the child, after stdio
+const FAIL_FD: i32 = 3;
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//the exit code, or 0x80 + signal as the jdk
+fn jvm_waitForProcessExit(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let pid = Pid::from_raw(args.get(1).unwrap().extract_int());
+    let code = blocking(|| loop {
+        match wait::waitpid(pid, None) {
+            Ok(WaitStatus::Exited(_, code)) => break code,
+            Ok(WaitStatus::Signaled(_, sig, _)) => break 0x80 + sig as i32,
+            Ok(_) => (),
+            Err(nix::Error::Sys(Errno::EINTR)) => (),
+            Err(nix::Error::Sys(Errno::ECHILD)) => break 0,
+            Err(_) => break -1,
+        }
+    });
+    Ok(Some(Oop::new_int(code)))
+}
+
+fn jvm_forkAndExec(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    //args[1] mode & args[2] helperpath, always fork
+    let prog = c_string(args.get(3).unwrap()).unwrap();
+    let argv = c_strings(args.get(4).unwrap(), args.get(5).unwrap().extract_int());
+    let envp = match args.get(6).unwrap() {
+        Oop::Null => std::env::vars_os()
+            .filter_map(|(k, v)| {
+                let mut kv = k.as_bytes().to_vec();
+                kv.push(b'=');
+                kv.extend_from_slice(v.as_bytes());
+                CString::new(kv).ok()
+            })
+            .collect(),
+        env => c_strings(env, args.get(7).unwrap().extract_int()),
+    };
+    let dir = c_string(args.get(8).unwrap());
+    let fds = args.get(9).unwrap();
+    let redirect_error_stream = args.get(10).unwrap().extract_int() != 0;
+
+    let mut std_fds = [0i32; 3];
+    {
+        let rf = fds.extract_ref();
+        let ary = rf.extract_type_array();
+        std_fds.copy_from_slice(&ary.extract_ints()[..3]);
+    }
+    //the child's stderr is its stdout
+    if redirect_error_stream {
+        std_fds[2] = 1;
+    }
+
+    //argv[0] is prog, as given
+    let argv = {
+        let mut v = vec![prog.clone()];
+        v.extend(argv);
+        v
+    };
+    let paths = search_paths(&prog);
+    let argv_ptrs = to_ptrs(&argv);
+    let envp_ptrs = to_ptrs(&envp);
+
+    //(parent end, child end) of the stdio pipes
+    let mut pipes = [(-1, -1); 3];
+    let mut opened = Vec::new();
+    for (i, fd) in std_fds.iter().enumerate() {
+        if *fd == -1 {
+            let (r, w) = match new_pipe() {
+                Ok(p) => p,
+                Err(e) => {
+                    close_all(&opened);
+                    return Err(io_exception(e));
+                }
+            };
+            opened.push(r);
+            opened.push(w);
+            pipes[i] = if i == 0 { (w, r) } else { (r, w) };
+        }
+    }
+    let (fail_r, fail_w) = match new_pipe() {
+        Ok(p) => p,
+        Err(e) => {
+            close_all(&opened);
+            return Err(io_exception(e));
+        }
+    };
+    let child_fds = [
+        if std_fds[0] == -1 {
+            pipes[0].1
+        } else {
+            std_fds[0]
+        },
+        if std_fds[1] == -1 {
+            pipes[1].1
+        } else {
+            std_fds[1]
+        },
+        if std_fds[2] == -1 {
+            pipes[2].1
+        } else {
+            std_fds[2]
+        },
+    ];
+    let max_fd = max_fd();
+
+    let pid = match unsafe { unistd::fork() } {
+        Ok(ForkResult::Child) => unsafe {
+            child_process(
+                &child_fds,
+                fail_w,
+                max_fd,
+                dir.as_ref(),
+                &paths,
+                &argv_ptrs,
+                &envp_ptrs,
+            )
+        },
+        Ok(ForkResult::Parent { child }) => child,
+        Err(e) => {
+            close_all(&opened);
+            close_all(&[fail_r, fail_w]);
+            let e = match e {
+                nix::Error::Sys(errno) => errno as i32,
+                _ => libc::EAGAIN,
+            };
+            return Err(io_exception(e));
+        }
+    };
+
+    //the child ends
+    close_all(&[fail_w, pipes[0].1, pipes[1].1, pipes[2].1]);
+
+    let mut errno = [0u8; 4];
+    let n = blocking(|| loop {
+        let n = unsafe { libc::read(fail_r, errno.as_mut_ptr() as *mut libc::c_void, 4) };
+        if n >= 0 || Errno::last() != Errno::EINTR {
+            break n;
+        }
+    });
+    close_all(&[fail_r]);
+
+    //exec failed, reap the child
+    if n == 4 {
+        close_all(&[pipes[0].0, pipes[1].0, pipes[2].0]);
+        blocking(|| {
+            let _ = wait::waitpid(pid, None);
+        });
+        return Err(io_exception(i32::from_ne_bytes(errno)));
+    }
+
+    {
+        let rf = fds.extract_ref();
+        let ary = rf.extract_mut_type_array();
+        let ary = ary.extract_mut_ints();
+        for (i, pipe) in pipes.iter().enumerate() {
+            ary[i] = pipe.0;
+        }
+    }
+
+    Ok(Some(Oop::new_int(pid.as_raw())))
+}
+
+fn jvm_destroyProcess(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let pid = Pid::from_raw(args.first().unwrap().extract_int());
+    let force = args.get(1).unwrap().extract_int() != 0;
+    let sig = if force {
+        Signal::SIGKILL
+    } else {
+        Signal::SIGTERM
+    };
+    let _ = signal::kill(pid, sig);
+    Ok(None)
+}
+
+unsafe fn child_process(
+    fds: &[i32; 3],
+    fail_w: i32,
+    max_fd: i32,
+    dir: Option<&CString>,
+    paths: &[CString],
+    argv: &[*const libc::c_char],
+    envp: &[*const libc::c_char],
+) -> ! {
+    let fail = |errno: i32| -> ! {
+        let errno = errno.to_ne_bytes();
+        libc::write(FAIL_FD, errno.as_ptr() as *const libc::c_void, 4);
+        libc::_exit(127)
+    };
+
+    //the fail pipe may be on the way of stdio
+    let fail_w = if fail_w < FAIL_FD {
+        libc::fcntl(fail_w, libc::F_DUPFD_CLOEXEC, FAIL_FD)
+    } else {
+        fail_w
+    };
+    for (i, fd) in fds.iter().enumerate() {
+        let i = i as i32;
+        if *fd != i && libc::dup2(*fd, i) < 0 {
+            libc::_exit(127);
+        }
+    }
+    if fail_w != FAIL_FD && libc::dup2(fail_w, FAIL_FD) < 0 {
+        libc::_exit(127);
+    }
+    libc::fcntl(FAIL_FD, libc::F_SETFD, libc::FD_CLOEXEC);
+    close_from(FAIL_FD + 1, max_fd);
+
+    //the vm ignores SIGPIPE, and blocks some signals
+    libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    let mut set: libc::sigset_t = std::mem::zeroed();
+    libc::sigemptyset(&mut set);
+    libc::sigprocmask(libc::SIG_SETMASK, &set, ptr::null_mut());
+
+    if let Some(dir) = dir {
+        if libc::chdir(dir.as_ptr()) < 0 {
+            fail(Errno::last() as i32);
+        }
+    }
+
+    //as execvp, EACCES is reported if no other file found
+    let mut errno = libc::ENOENT;
+    for path in paths {
+        libc::execve(path.as_ptr(), argv.as_ptr(), envp.as_ptr());
+        match Errno::last() {
+            Errno::EACCES => errno = libc::EACCES,
+            Errno::ENOENT | Errno::ENOTDIR | Errno::ELOOP | Errno::ENAMETOOLONG => (),
+            e => fail(e as i32),
+        }
+    }
+    fail(errno)
+}
+
+//the files to exec, by PATH of the vm if no '/' in prog
+fn search_paths(prog: &CString) -> Vec<CString> {
+    let prog = prog.as_bytes();
+    if prog.contains(&b'/') {
+        return vec![CString::new(prog).unwrap()];
+    }
+
+    let path = std::env::var_os("PATH");
+    let path = path
+        .as_ref()
+        .map_or(&b"/bin:/usr/bin"[..], |path| path.as_bytes());
+    path.split(|&b| b == b':')
+        .filter_map(|dir| {
+            let mut file = if dir.is_empty() {
+                b".".to_vec()
+            } else {
+                dir.to_vec()
+            };
+            file.push(b'/');
+            file.extend_from_slice(prog);
+            CString::new(file).ok()
+        })
+        .collect()
+}
+
+//null terminated, the strings must live longer than the pointers
+fn to_ptrs(v: &[CString]) -> Vec<*const libc::c_char> {
+    let mut ptrs: Vec<*const libc::c_char> = v.iter().map(|s| s.as_ptr()).collect();
+    ptrs.push(ptr::null());
+    ptrs
+}
+
+//the bytes of UNIXProcess.toCString, null terminated
+fn c_string(v: &Oop) -> Option<CString> {
+    match v {
+        Oop::Null => None,
+        v => {
+            let rf = v.extract_ref();
+            let ary = rf.extract_type_array();
+            let bytes = ary.extract_bytes();
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            Some(CString::new(&bytes[..end]).unwrap())
+        }
+    }
+}
+
+//'count' strings, each null terminated, as argBlock & envBlock
+fn c_strings(v: &Oop, count: i32) -> Vec<CString> {
+    if let Oop::Null = v {
+        return vec![];
+    }
+
+    let rf = v.extract_ref();
+    let ary = rf.extract_type_array();
+    ary.extract_bytes()
+        .split(|&b| b == 0)
+        .take(count.max(0) as usize)
+        .map(|s| CString::new(s).unwrap())
+        .collect()
+}
+
+//close-on-exec, not to leak into the other children
+fn new_pipe() -> Result<(i32, i32), i32> {
+    let (r, w) = match unistd::pipe() {
+        Ok(fds) => fds,
+        Err(nix::Error::Sys(errno)) => return Err(errno as i32),
+        Err(_) => return Err(libc::EMFILE),
+    };
+    for fd in [r, w].iter() {
+        unsafe {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    Ok((r, w))
+}
+
+fn close_all(fds: &[i32]) {
+    for fd in fds {
+        if *fd >= 0 {
+            unsafe {
+                libc::close(*fd);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn close_from(fd: i32, max_fd: i32) {
+    if libc::syscall(libc::SYS_close_range, fd as u32, u32::MAX, 0) < 0 {
+        for fd in fd..max_fd {
+            libc::close(fd);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn close_from(fd: i32, max_fd: i32) {
+    for fd in fd..max_fd {
+        libc::close(fd);
+    }
+}
+
+fn max_fd() -> i32 {
+    let max = unsafe { libc::sysconf(libc::_SC_OPEN_MAX) };
+    if max < 0 {
+        1024
+    } else {
+        max.min(i32::MAX as libc::c_long) as i32
+    }
+}
+
+//"error=2, No such file or directory", as the jdk
+fn io_exception(errno: i32) -> Oop {
+    let msg = format!("error={}, {}", errno, Errno::from_i32(errno).desc());
+    runtime::exception::new(cls_consts::J_IOEXCEPTION, Some(msg))
+}
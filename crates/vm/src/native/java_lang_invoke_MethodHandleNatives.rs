@@ -0,0 +1,333 @@
+#![allow(non_snake_case)]
+use crate::native::common::reflect;
+use crate::native::{new_fn, sun_misc_Unsafe, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{Class, Oop, OopPtr};
+use crate::runtime::method_handle::{self as mh, *};
+use crate::runtime::{exception, ClassLoader};
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use crate::{new_br, util};
+use classfile::consts as cls_consts;
+use classfile::flags::ACC_STATIC;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("registerNatives", "()V", Box::new(jvm_registerNatives)),
+        new_fn("getConstant", "(I)I", Box::new(jvm_getConstant)),
+        new_fn(
+            "init",
+            "(Ljava/lang/invoke/MemberName;Ljava/lang/Object;)V",
+            Box::new(jvm_init),
+        ),
+        new_fn(
+            "expand",
+            "(Ljava/lang/invoke/MemberName;)V",
+            Box::new(jvm_expand),
+        ),
+        new_fn(
+            "resolve",
+            "(Ljava/lang/invoke/MemberName;Ljava/lang/Class;)Ljava/lang/invoke/MemberName;",
+            Box::new(jvm_resolve),
+        ),
+        new_fn(
+            "getMembers",
+            "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/String;ILjava/lang/Class;I[Ljava/lang/invoke/MemberName;)I",
+            Box::new(jvm_getMembers),
+        ),
+        new_fn(
+            "objectFieldOffset",
+            "(Ljava/lang/invoke/MemberName;)J",
+            Box::new(jvm_objectFieldOffset),
+        ),
+        new_fn(
+            "staticFieldOffset",
+            "(Ljava/lang/invoke/MemberName;)J",
+            Box::new(jvm_staticFieldOffset),
+        ),
+        new_fn(
+            "staticFieldBase",
+            "(Ljava/lang/invoke/MemberName;)Ljava/lang/Object;",
+            Box::new(jvm_staticFieldBase),
+        ),
+        new_fn(
+            "getMemberVMInfo",
+            "(Ljava/lang/invoke/MemberName;)Ljava/lang/Object;",
+            Box::new(jvm_getMemberVMInfo),
+        ),
+        new_fn(
+            "setCallSiteTargetNormal",
+            "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+            Box::new(jvm_setCallSiteTarget),
+        ),
+        new_fn(
+            "setCallSiteTargetVolatile",
+            "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+            Box::new(jvm_setCallSiteTarget),
+        ),
+    ]
+}
+
+fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//no VM constants are overridden, the defaults of MethodHandleNatives.Constants are used
+fn jvm_getConstant(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(0)))
+}
+
+//init(MemberName self, Object ref), ref is a Method, a Constructor or a Field
+fn jvm_init(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let target = args.get(1).unwrap();
+
+    let target_cls = {
+        let rf = target.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.get_class().name.clone()
+    };
+
+    let (cls, slot) = reflect::get_clazz_slot(target);
+    match target_cls.as_slice() {
+        b"java/lang/reflect/Field" => {
+            let is_static = {
+                let cls = target.extract_ref();
+                let cls = cls.extract_inst().class.clone();
+                let cls = cls.get_class();
+                let id = cls.get_field_id(&util::S_MODIFIERS, &util::S_I, false);
+                let v = Class::get_field_value(target.extract_ref(), id);
+                v.extract_int() as u16 & ACC_STATIC == ACC_STATIC
+            };
+            let fid = reflect::get_field_by_slot(&cls, slot, is_static);
+            let ref_kind = if is_static {
+                REF_GET_STATIC
+            } else {
+                REF_GET_FIELD
+            };
+            set_member(mn, &fid.field.class, field_flags(&fid, ref_kind));
+            set_member_name_type(mn, fid.field.name.as_slice(), fid.field.desc.as_slice());
+        }
+        _ => {
+            let mir = reflect::get_method_by_slot(&cls, slot);
+            let flags = method_flags(&mir, method_ref_kind(&mir));
+            set_member(mn, &mir.method.class, flags);
+        }
+    }
+
+    Ok(None)
+}
+
+//the name & type are filled by init or resolve already
+fn jvm_expand(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//resolve(MemberName self, Class<?> caller)
+fn jvm_resolve(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let caller = args.get(1).unwrap();
+
+    let flags = member_flags(mn);
+    let ref_kind = member_ref_kind(mn);
+
+    if flags & MN_IS_FIELD != 0 {
+        let fid = mh::member_field(mn)?;
+        set_member(mn, &fid.field.class, field_flags(&fid, ref_kind));
+        return Ok(Some(mn.clone()));
+    }
+
+    let mir = mh::member_method(mn, class_loader(caller))?;
+    if (ref_kind == REF_INVOKE_STATIC) != mir.method.is_static() {
+        let msg = format!(
+            "{}.{}{}",
+            String::from_utf8_lossy(mir.method.cls_name.as_slice()).replace("/", "."),
+            String::from_utf8_lossy(mir.method.name.as_slice()),
+            String::from_utf8_lossy(mir.method.desc.as_slice())
+        );
+        return Err(exception::new(
+            cls_consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR,
+            Some(msg),
+        ));
+    }
+
+    let ref_kind = match ref_kind {
+        REF_INVOKE_STATIC | REF_INVOKE_SPECIAL | REF_NEW_INVOKE_SPECIAL => ref_kind,
+        _ => method_ref_kind(&mir),
+    };
+    set_member(mn, &mir.method.class, method_flags(&mir, ref_kind));
+
+    Ok(Some(mn.clone()))
+}
+
+//getMembers(Class<?> defc, String matchName, String matchSig, int matchFlags,
+//  Class<?> caller, int skip, MemberName[] results)
+//the declared members only, the count of the matched members after 'skip'
+fn jvm_getMembers(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let defc = args.first().unwrap();
+    let match_name = args.get(1).unwrap();
+    let match_sig = args.get(2).unwrap();
+    let match_flags = args.get(3).unwrap().extract_int();
+    let skip = args.get(5).unwrap().extract_int() as usize;
+    let results = args.get(6).unwrap();
+
+    if match_flags & (MN_SEARCH_SUPERCLASSES | MN_SEARCH_INTERFACES) != 0 {
+        return Ok(Some(Oop::new_int(-99)));
+    }
+
+    let cls = {
+        let rf = defc.extract_ref();
+        rf.extract_mirror().target.clone()
+    };
+    let cls = match cls {
+        Some(cls) => cls,
+        None => return Ok(Some(Oop::new_int(0))),
+    };
+
+    let match_name = match match_name {
+        Oop::Null => None,
+        v => Some(OopPtr::java_lang_string(v.extract_ref())),
+    };
+    let match_sig = match match_sig {
+        Oop::Null => None,
+        v => Some(OopPtr::java_lang_string(v.extract_ref())),
+    };
+    let is_match = |name: &[u8], desc: &[u8]| {
+        match_name.as_ref().is_none_or(|it| it.as_bytes() == name)
+            && match_sig.as_ref().is_none_or(|it| it.as_bytes() == desc)
+    };
+
+    let (methods, fields) = declared_members(&cls);
+
+    let mut members: Vec<(ClassRef, i32, &[u8], &[u8])> = Vec::new();
+    for mir in methods.iter() {
+        let method = &mir.method;
+        let kind = if method.name.as_slice() == b"<init>" {
+            MN_IS_CONSTRUCTOR
+        } else {
+            MN_IS_METHOD
+        };
+        if match_flags & kind != 0 && is_match(method.name.as_slice(), method.desc.as_slice()) {
+            let flags = method_flags(mir, method_ref_kind(mir));
+            members.push((
+                method.class.clone(),
+                flags,
+                method.name.as_slice(),
+                method.desc.as_slice(),
+            ));
+        }
+    }
+    for fid in fields.iter() {
+        let field = &fid.field;
+        if match_flags & MN_IS_FIELD != 0 && is_match(field.name.as_slice(), field.desc.as_slice())
+        {
+            let ref_kind = if field.acc_flags & ACC_STATIC == ACC_STATIC {
+                REF_GET_STATIC
+            } else {
+                REF_GET_FIELD
+            };
+            let flags = field_flags(fid, ref_kind);
+            members.push((
+                field.class.clone(),
+                flags,
+                field.name.as_slice(),
+                field.desc.as_slice(),
+            ));
+        }
+    }
+
+    let rf = results.extract_ref();
+    let ary = rf.extract_array();
+    for (mn, (cls, flags, name, desc)) in ary.iter().zip(members.iter().skip(skip)) {
+        set_member(&mn, cls, *flags);
+        set_member_name_type(&mn, name, desc);
+    }
+
+    let n = members.len().saturating_sub(skip);
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn jvm_objectFieldOffset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let fid = mh::member_field(mn)?;
+    Ok(Some(Oop::new_long(fid.offset as i64)))
+}
+
+fn jvm_staticFieldOffset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let fid = mh::member_field(mn)?;
+    let offset = sun_misc_Unsafe::static_field_offset(&fid);
+    Ok(Some(Oop::new_long(offset)))
+}
+
+fn jvm_staticFieldBase(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let fid = mh::member_field(mn)?;
+    let mirror = fid.field.class.get_class().get_mirror();
+    Ok(Some(mirror))
+}
+
+//used by MemberName.toString for debugging only
+fn jvm_getMemberVMInfo(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::Null))
+}
+
+fn jvm_setCallSiteTarget(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let call_site = args.first().unwrap();
+    let target = args.get(1).unwrap();
+
+    let cls = {
+        let rf = call_site.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.clone()
+    };
+    let fid = {
+        let cls = cls.get_class();
+        cls.get_field_id(
+            &new_br("target"),
+            &new_br("Ljava/lang/invoke/MethodHandle;"),
+            false,
+        )
+    };
+    Class::put_field_value(call_site.extract_ref(), fid, target.clone());
+
+    Ok(None)
+}
+
+////////helper
+
+fn class_loader(mirror: &Oop) -> Option<ClassLoader> {
+    match mirror {
+        Oop::Null => None,
+        v => {
+            let rf = v.extract_ref();
+            let mirror = rf.extract_mirror();
+            mirror
+                .target
+                .as_ref()
+                .and_then(|it| it.get_class().class_loader)
+        }
+    }
+}
+
+//the methods & fields declared by the class, in the order of the class file
+fn declared_members(cls: &ClassRef) -> (Vec<MethodIdRef>, Vec<FieldIdRef>) {
+    let class = cls.get_class();
+    let cls_obj = match &class.kind {
+        crate::oop::ClassKind::Instance(cls_obj) => cls_obj,
+        _ => return (vec![], vec![]),
+    };
+
+    let methods = (0..cls_obj.class_file.methods.len())
+        .map(|slot| reflect::get_method_by_slot(cls, slot))
+        .collect();
+
+    let mut fields: Vec<FieldIdRef> = cls_obj
+        .static_fields
+        .values()
+        .chain(cls_obj.inst_fields.values())
+        .filter(|it| std::ptr::eq(&*it.field.class, &**cls))
+        .cloned()
+        .collect();
+    fields.sort_by_key(|it| (it.field.acc_flags & ACC_STATIC, it.offset));
+
+    (methods, fields)
+}
@@ -44,7 +44,7 @@ fn jvm_getLength(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let len = unsafe {
         match &(*ptr).v {
             oop::RefKind::TypeArray(ary) => ary.len(),
-            oop::RefKind::Array(ary) => ary.elements.len(),
+            oop::RefKind::Array(ary) => ary.len(),
             _ => unreachable!(),
         }
     };
@@ -0,0 +1,39 @@
+#![allow(non_snake_case)]
+
+use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn(
+            "getParameters0",
+            "()[Ljava/lang/reflect/Parameter;",
+            Box::new(jvm_getParameters0),
+        ),
+        new_fn(
+            "getTypeAnnotationBytes0",
+            "()[B",
+            Box::new(jvm_getTypeAnnotationBytes0),
+        ),
+    ]
+}
+
+//null without the MethodParameters attribute, then Executable synthesizes argN
+fn jvm_getParameters0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let (cls, slot) = common::reflect::get_clazz_slot(this);
+    let mir = common::reflect::get_method_by_slot(&cls, slot);
+    Ok(Some(common::reflect::new_parameters(this, &mir)))
+}
+
+fn jvm_getTypeAnnotationBytes0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let (cls, slot) = common::reflect::get_clazz_slot(this);
+    let mir = common::reflect::get_method_by_slot(&cls, slot);
+    let v = match mir.method.get_type_annotation() {
+        Some(raw) => Oop::new_byte_ary2(raw),
+        None => Oop::Null,
+    };
+
+    Ok(Some(v))
+}
@@ -0,0 +1,37 @@
+#![allow(non_snake_case)]
+
+use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{Class, Oop};
+use crate::util;
+use classfile::flags::ACC_STATIC;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn(
+        "getTypeAnnotationBytes0",
+        "()[B",
+        Box::new(jvm_getTypeAnnotationBytes0),
+    )]
+}
+
+fn jvm_getTypeAnnotationBytes0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let (cls, slot) = common::reflect::get_clazz_slot(this);
+
+    //the slot is the offset of the static or the instance fields
+    let is_static = {
+        let rf = this.extract_ref();
+        let inst = rf.extract_inst();
+        let field_cls = inst.class.get_class();
+        let id = field_cls.get_field_id(&util::S_MODIFIERS, &util::S_I, false);
+        let modifiers = Class::get_field_value(this.extract_ref(), id).extract_int();
+        (modifiers as u16 & ACC_STATIC) != 0
+    };
+
+    let fir = common::reflect::get_field_by_slot(&cls, slot, is_static);
+    let v = match fir.field.get_type_annotation() {
+        Some(raw) => Oop::new_byte_ary2(raw),
+        None => Oop::Null,
+    };
+
+    Ok(Some(v))
+}
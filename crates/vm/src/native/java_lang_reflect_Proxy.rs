@@ -1,13 +1,8 @@
 #![allow(non_snake_case)]
 
-use crate::native;
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::class::ClassPtr;
-use crate::oop::{self, Class, Oop, OopPtr};
-use crate::runtime;
-use crate::types::ClassRef;
-use class_parser::parse_class;
-use std::sync::Arc;
+use crate::oop::{Oop, OopPtr};
+use crate::runtime::{self, thread, ClassLoader};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![new_fn(
@@ -18,41 +13,32 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
 }
 
 fn jvm_defineClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let _loader = args.get(0).unwrap();
+    let class_loader = ClassLoader::from_oop(args.first().unwrap());
     let name = args.get(1).unwrap();
     let name = OopPtr::java_lang_string(name.extract_ref());
     let b = args.get(2).unwrap();
-    let off = args.get(3).unwrap().extract_int();
-    let len = args.get(4).unwrap().extract_int();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
 
     let name = name.replace(".", "/");
 
-    //parse bytes => class, put in sys_dic
-    let class = do_parse_class(b, off as usize, len as usize);
-    runtime::sys_dic_put(name.as_bytes(), class.clone());
-    {
-        let this_ref = class.clone();
-        let cls = class.get_mut_class();
-        cls.set_class_state(oop::class::State::Loaded);
-        cls.link_class(this_ref);
-    }
-    native::java_lang_Class::create_mirror(class.clone());
-
-    let v = Oop::new_mirror(class);
-    Ok(Some(v))
-}
+    //the proxy class is defined by the loader of the interfaces
+    let class = {
+        let rf = b.extract_ref();
+        let ary = rf.extract_type_array();
+        let ary = ary.extract_bytes();
+        runtime::define_class(class_loader, Some(name.as_bytes()), &ary[off..(off + len)])
+    };
 
-fn do_parse_class(v: &Oop, off: usize, len: usize) -> ClassRef {
-    let rf = v.extract_ref();
-    let ary = rf.extract_type_array();
-    let ary = ary.extract_bytes();
-    match parse_class(&ary[off..(off + len)]) {
-        Ok(r) => {
-            let cfr = Arc::new(Box::new(r.1));
-            //fixme: setup classloader
-            let class = Class::new_class(cfr, None);
-            ClassPtr::new(class)
+    match class {
+        Some(class) => {
+            let cls = class.get_class();
+            Ok(Some(cls.get_mirror()))
+        }
+        None => {
+            let jt = thread::current_java_thread();
+            let ex = jt.write().unwrap().take_ex().unwrap();
+            Err(ex)
         }
-        Err(e) => unreachable!("e = {:?}", e),
     }
 }
@@ -0,0 +1,125 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::blocking;
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{Oop, OopPtr};
+use crate::runtime::{self, require_class3};
+use crate::util;
+use classfile::consts as cls_consts;
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn(
+            "getLocalHostName",
+            "()Ljava/lang/String;",
+            Box::new(jvm_getLocalHostName),
+        ),
+        new_fn(
+            "lookupAllHostAddr",
+            "(Ljava/lang/String;)[Ljava/net/InetAddress;",
+            Box::new(jvm_lookupAllHostAddr),
+        ),
+        new_fn(
+            "getHostByAddr",
+            "([B)Ljava/lang/String;",
+            Box::new(jvm_getHostByAddr),
+        ),
+        new_fn("isReachable0", "([BI[BI)Z", Box::new(jvm_isReachable0)),
+    ]
+}
+
+fn jvm_getLocalHostName(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mut buf = [0u8; 256];
+    let r = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    let name = if r == 0 {
+        let name = unsafe { CStr::from_ptr(buf.as_ptr() as *const libc::c_char) };
+        name.to_string_lossy().into_owned()
+    } else {
+        "localhost".to_string()
+    };
+
+    Ok(Some(util::oop::new_java_lang_string2(&name)))
+}
+
+fn jvm_lookupAllHostAddr(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let host = args.get(1).unwrap();
+    let host = OopPtr::java_lang_string(host.extract_ref());
+
+    let addrs = blocking(|| (host.as_str(), 0).to_socket_addrs());
+    let mut ips: Vec<Ipv4Addr> = Vec::new();
+    if let Ok(addrs) = addrs {
+        for addr in addrs {
+            if let SocketAddr::V4(addr) = addr {
+                if !ips.contains(addr.ip()) {
+                    ips.push(*addr.ip());
+                }
+            }
+        }
+    }
+
+    if ips.is_empty() {
+        let msg = format!("{}: Name or service not known", host);
+        let ex = runtime::exception::new(cls_consts::J_UNKNOWN_HOST_EXCEPTION, Some(msg));
+        return Err(ex);
+    }
+
+    let elms = ips
+        .iter()
+        .map(|ip| net::new_inet_address(*ip, Some(host.as_str())))
+        .collect();
+    let ary_cls = require_class3(None, b"[Ljava/net/InetAddress;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, elms)))
+}
+
+fn jvm_getHostByAddr(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let ip = to_ipv4(args.get(1).unwrap());
+
+    let sa = net::to_sockaddr(ip, 0);
+    let mut buf = [0 as libc::c_char; libc::NI_MAXHOST as usize];
+    let r = blocking(|| unsafe {
+        libc::getnameinfo(
+            &sa as *const _ as *const libc::sockaddr,
+            net::SOCKADDR_IN_LEN,
+            buf.as_mut_ptr(),
+            buf.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    });
+
+    if r != 0 {
+        let ex = runtime::exception::new(cls_consts::J_UNKNOWN_HOST_EXCEPTION, None);
+        return Err(ex);
+    }
+
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    let name = name.to_string_lossy();
+    Ok(Some(util::oop::new_java_lang_string2(&name)))
+}
+
+//no raw socket for ICMP, try the TCP echo port, as the jdk does without root
+fn jvm_isReachable0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let ip = to_ipv4(args.get(1).unwrap());
+    let timeout = args.get(2).unwrap().extract_int().max(1);
+
+    let addr = SocketAddr::new(IpAddr::V4(ip), 7);
+    let timeout = Duration::from_millis(timeout as u64);
+    let reachable = blocking(|| match TcpStream::connect_timeout(&addr, timeout) {
+        Ok(_) => true,
+        //the host answered, no echo service
+        Err(e) => e.raw_os_error() == Some(libc::ECONNREFUSED),
+    });
+
+    Ok(Some(Oop::new_int(reachable as i32)))
+}
+
+fn to_ipv4(ary: &Oop) -> Ipv4Addr {
+    let rf = ary.extract_ref();
+    let ary = rf.extract_type_array();
+    let bytes = ary.extract_bytes();
+    Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])
+}
@@ -0,0 +1,16 @@
+#![allow(non_snake_case)]
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn(
+        "isIPv6Supported",
+        "()Z",
+        Box::new(jvm_isIPv6Supported),
+    )]
+}
+
+//only IPv4, InetAddress.impl is Inet4AddressImpl
+fn jvm_isIPv6Supported(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(0)))
+}
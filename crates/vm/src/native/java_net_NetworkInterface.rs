@@ -0,0 +1,341 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::put_field;
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::new_br;
+use crate::oop::{self, Oop, OopPtr};
+use crate::runtime::{self, require_class3};
+use crate::util;
+use classfile::consts as cls_consts;
+use nix::net::if_::InterfaceFlags;
+use nix::sys::socket::SockAddr;
+use std::ffi::CString;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "getAll",
+            "()[Ljava/net/NetworkInterface;",
+            Box::new(jvm_getAll),
+        ),
+        new_fn(
+            "getByName0",
+            "(Ljava/lang/String;)Ljava/net/NetworkInterface;",
+            Box::new(jvm_getByName0),
+        ),
+        new_fn(
+            "getByIndex0",
+            "(I)Ljava/net/NetworkInterface;",
+            Box::new(jvm_getByIndex0),
+        ),
+        new_fn(
+            "getByInetAddress0",
+            "(Ljava/net/InetAddress;)Ljava/net/NetworkInterface;",
+            Box::new(jvm_getByInetAddress0),
+        ),
+        new_fn("isUp0", "(Ljava/lang/String;I)Z", Box::new(jvm_isUp0)),
+        new_fn(
+            "isLoopback0",
+            "(Ljava/lang/String;I)Z",
+            Box::new(jvm_isLoopback0),
+        ),
+        new_fn(
+            "supportsMulticast0",
+            "(Ljava/lang/String;I)Z",
+            Box::new(jvm_supportsMulticast0),
+        ),
+        new_fn("isP2P0", "(Ljava/lang/String;I)Z", Box::new(jvm_isP2P0)),
+        new_fn(
+            "getMacAddr0",
+            "([BLjava/lang/String;I)[B",
+            Box::new(jvm_getMacAddr0),
+        ),
+        new_fn("getMTU0", "(Ljava/lang/String;I)I", Box::new(jvm_getMTU0)),
+    ]
+}
+
+//an interface by getifaddrs, with the IPv4 addresses
+struct Interface {
+    name: String,
+    index: i32,
+    flags: InterfaceFlags,
+    mac: Option<[u8; 6]>,
+    addrs: Vec<Address>,
+}
+
+struct Address {
+    ip: Ipv4Addr,
+    broadcast: Option<Ipv4Addr>,
+    mask_len: i16,
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_getAll(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let elms = interfaces()?.iter().map(new_network_interface).collect();
+    let ary_cls = require_class3(None, b"[Ljava/net/NetworkInterface;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, elms)))
+}
+
+fn jvm_getByName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = OopPtr::java_lang_string(args.first().unwrap().extract_ref());
+    let itf = interfaces()?.into_iter().find(|itf| itf.name == name);
+    Ok(Some(
+        itf.map_or(Oop::Null, |itf| new_network_interface(&itf)),
+    ))
+}
+
+fn jvm_getByIndex0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let index = args.first().unwrap().extract_int();
+    let itf = interfaces()?.into_iter().find(|itf| itf.index == index);
+    Ok(Some(
+        itf.map_or(Oop::Null, |itf| new_network_interface(&itf)),
+    ))
+}
+
+fn jvm_getByInetAddress0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let ip = net::inet_address(args.first().unwrap());
+    Ok(Some(get_by_address(ip)?))
+}
+
+fn jvm_isUp0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let flags = get_flags(args)?;
+    let up = flags.contains(InterfaceFlags::IFF_UP | InterfaceFlags::IFF_RUNNING);
+    Ok(Some(Oop::new_int(up as i32)))
+}
+
+fn jvm_isLoopback0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let flags = get_flags(args)?;
+    let v = flags.contains(InterfaceFlags::IFF_LOOPBACK);
+    Ok(Some(Oop::new_int(v as i32)))
+}
+
+fn jvm_supportsMulticast0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let flags = get_flags(args)?;
+    let v = flags.contains(InterfaceFlags::IFF_MULTICAST);
+    Ok(Some(Oop::new_int(v as i32)))
+}
+
+fn jvm_isP2P0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let flags = get_flags(args)?;
+    let v = flags.contains(InterfaceFlags::IFF_POINTOPOINT);
+    Ok(Some(Oop::new_int(v as i32)))
+}
+
+//null if none, such as the loopback
+fn jvm_getMacAddr0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = OopPtr::java_lang_string(args.get(1).unwrap().extract_ref());
+    let mac = interfaces()?
+        .into_iter()
+        .find(|itf| itf.name == name)
+        .and_then(|itf| itf.mac)
+        .filter(|mac| mac.iter().any(|&b| b != 0));
+    Ok(Some(
+        mac.map_or(Oop::Null, |mac| Oop::new_byte_ary2(mac.to_vec())),
+    ))
+}
+
+fn jvm_getMTU0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = OopPtr::java_lang_string(args.first().unwrap().extract_ref());
+    Ok(Some(Oop::new_int(get_mtu(&name))))
+}
+
+//the interface of the address, null if none
+pub fn get_by_address(ip: Ipv4Addr) -> Result<Oop, Oop> {
+    let itf = interfaces()?
+        .into_iter()
+        .find(|itf| itf.addrs.iter().any(|addr| addr.ip == ip));
+    Ok(itf.map_or(Oop::Null, |itf| new_network_interface(&itf)))
+}
+
+//as the jdk, the interface of the any address has index -1
+pub fn new_any_interface() -> Oop {
+    let itf = Interface {
+        name: "0.0.0.0".to_string(),
+        index: -1,
+        flags: InterfaceFlags::empty(),
+        mac: None,
+        addrs: vec![Address {
+            ip: Ipv4Addr::UNSPECIFIED,
+            broadcast: None,
+            mask_len: 0,
+        }],
+    };
+    new_network_interface(&itf)
+}
+
+fn interfaces() -> Result<Vec<Interface>, Oop> {
+    let ifaddrs = match nix::ifaddrs::getifaddrs() {
+        Ok(ifaddrs) => ifaddrs,
+        Err(e) => {
+            let ex = runtime::exception::new(cls_consts::J_SOCKET_EXCEPTION, Some(e.to_string()));
+            return Err(ex);
+        }
+    };
+
+    let mut interfaces: Vec<Interface> = Vec::new();
+    for ifaddr in ifaddrs {
+        let pos = match interfaces
+            .iter()
+            .position(|itf| itf.name == ifaddr.interface_name)
+        {
+            Some(pos) => pos,
+            None => {
+                interfaces.push(Interface {
+                    index: name_to_index(&ifaddr.interface_name),
+                    name: ifaddr.interface_name.clone(),
+                    flags: ifaddr.flags,
+                    mac: None,
+                    addrs: Vec::new(),
+                });
+                interfaces.len() - 1
+            }
+        };
+        let itf = &mut interfaces[pos];
+
+        match ifaddr.address {
+            Some(SockAddr::Inet(addr)) => {
+                if let SocketAddr::V4(addr) = addr.to_std() {
+                    let mask_len = match ifaddr.netmask.as_ref().and_then(to_ipv4) {
+                        Some(mask) => u32::from(mask).count_ones() as i16,
+                        None => 0,
+                    };
+                    itf.addrs.push(Address {
+                        ip: *addr.ip(),
+                        broadcast: ifaddr.broadcast.as_ref().and_then(to_ipv4),
+                        mask_len,
+                    });
+                }
+            }
+            Some(SockAddr::Link(link)) => itf.mac = Some(link.addr()),
+            _ => (),
+        }
+    }
+
+    Ok(interfaces)
+}
+
+fn to_ipv4(addr: &SockAddr) -> Option<Ipv4Addr> {
+    match addr {
+        SockAddr::Inet(addr) => match addr.to_std().ip() {
+            IpAddr::V4(ip) => Some(ip),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn name_to_index(name: &str) -> i32 {
+    match CString::new(name) {
+        Ok(name) => unsafe { libc::if_nametoindex(name.as_ptr()) as i32 },
+        Err(_) => 0,
+    }
+}
+
+//the flags of the interface named by args[0]
+fn get_flags(args: &[Oop]) -> Result<InterfaceFlags, Oop> {
+    let name = OopPtr::java_lang_string(args.first().unwrap().extract_ref());
+    match interfaces()?.into_iter().find(|itf| itf.name == name) {
+        Some(itf) => Ok(itf.flags),
+        None => {
+            let ex = runtime::exception::new(
+                cls_consts::J_SOCKET_EXCEPTION,
+                Some("No such device".to_string()),
+            );
+            Err(ex)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_mtu(name: &str) -> i32 {
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return -1;
+    }
+
+    let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in req.ifr_name.iter_mut().zip(name.bytes()) {
+        *dst = src as libc::c_char;
+    }
+    let r = unsafe { libc::ioctl(fd, libc::SIOCGIFMTU, &mut req) };
+    unsafe {
+        libc::close(fd);
+    }
+
+    if r < 0 {
+        -1
+    } else {
+        unsafe { req.ifr_ifru.ifru_mtu }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_mtu(_name: &str) -> i32 {
+    -1
+}
+
+fn new_network_interface(itf: &Interface) -> Oop {
+    let cls = oop::class::load_and_init(b"java/net/NetworkInterface");
+
+    let addrs = itf
+        .addrs
+        .iter()
+        .map(|addr| net::new_inet_address(addr.ip, None))
+        .collect();
+    let ary_cls = require_class3(None, b"[Ljava/net/InetAddress;").unwrap();
+    let addrs = Oop::new_ref_ary2(ary_cls, addrs);
+
+    let name = util::oop::new_java_lang_string2(&itf.name);
+    let obj = Oop::new_inst(cls.clone());
+    let args = vec![obj.clone(), name.clone(), Oop::new_int(itf.index), addrs];
+    runtime::invoke::invoke_ctor(
+        cls,
+        new_br("(Ljava/lang/String;I[Ljava/net/InetAddress;)V"),
+        args,
+    );
+    put_field(&obj, "displayName", "Ljava/lang/String;", name);
+
+    let bindings = itf.addrs.iter().map(new_interface_address).collect();
+    let ary_cls = require_class3(None, b"[Ljava/net/InterfaceAddress;").unwrap();
+    put_field(
+        &obj,
+        "bindings",
+        "[Ljava/net/InterfaceAddress;",
+        Oop::new_ref_ary2(ary_cls, bindings),
+    );
+
+    let ary_cls = require_class3(None, b"[Ljava/net/NetworkInterface;").unwrap();
+    put_field(
+        &obj,
+        "childs",
+        "[Ljava/net/NetworkInterface;",
+        Oop::new_ref_ary2(ary_cls, vec![]),
+    );
+
+    obj
+}
+
+fn new_interface_address(addr: &Address) -> Oop {
+    let cls = oop::class::load_and_init(b"java/net/InterfaceAddress");
+    let obj = Oop::new_inst(cls.clone());
+    runtime::invoke::invoke_ctor(cls, new_br("()V"), vec![obj.clone()]);
+
+    let broadcast = addr
+        .broadcast
+        .map_or(Oop::Null, |ip| net::new_inet_address(ip, None));
+    put_field(
+        &obj,
+        "address",
+        "Ljava/net/InetAddress;",
+        net::new_inet_address(addr.ip, None),
+    );
+    put_field(&obj, "broadcast", "Ljava/net/Inet4Address;", broadcast);
+    put_field(&obj, "maskLength", "S", Oop::new_int(addr.mask_len as i32));
+
+    obj
+}
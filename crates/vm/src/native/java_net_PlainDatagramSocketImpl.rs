@@ -0,0 +1,579 @@
+#![allow(non_snake_case)]
+use super::java_net_NetworkInterface;
+use crate::native::common::io::{blocking, get_field, put_field};
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use std::io;
+use std::mem;
+use std::net::Ipv4Addr;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "datagramSocketCreate",
+            "()V",
+            Box::new(jvm_datagramSocketCreate),
+        ),
+        new_fn(
+            "datagramSocketClose",
+            "()V",
+            Box::new(jvm_datagramSocketClose),
+        ),
+        new_fn("bind0", "(ILjava/net/InetAddress;)V", Box::new(jvm_bind0)),
+        new_fn("send", "(Ljava/net/DatagramPacket;)V", Box::new(jvm_send)),
+        new_fn("peek", "(Ljava/net/InetAddress;)I", Box::new(jvm_peek)),
+        new_fn(
+            "peekData",
+            "(Ljava/net/DatagramPacket;)I",
+            Box::new(jvm_peekData),
+        ),
+        new_fn(
+            "receive0",
+            "(Ljava/net/DatagramPacket;)V",
+            Box::new(jvm_receive0),
+        ),
+        new_fn("setTimeToLive", "(I)V", Box::new(jvm_setTimeToLive)),
+        new_fn("getTimeToLive", "()I", Box::new(jvm_getTimeToLive)),
+        new_fn("setTTL", "(B)V", Box::new(jvm_setTimeToLive)),
+        new_fn("getTTL", "()B", Box::new(jvm_getTTL)),
+        new_fn(
+            "join",
+            "(Ljava/net/InetAddress;Ljava/net/NetworkInterface;)V",
+            Box::new(jvm_join),
+        ),
+        new_fn(
+            "leave",
+            "(Ljava/net/InetAddress;Ljava/net/NetworkInterface;)V",
+            Box::new(jvm_leave),
+        ),
+        //socketSetOption0 since 8u, socketSetOption before
+        new_fn(
+            "socketSetOption0",
+            "(ILjava/lang/Object;)V",
+            Box::new(jvm_socketSetOption),
+        ),
+        new_fn(
+            "socketSetOption",
+            "(ILjava/lang/Object;)V",
+            Box::new(jvm_socketSetOption),
+        ),
+        new_fn(
+            "socketGetOption",
+            "(I)Ljava/lang/Object;",
+            Box::new(jvm_socketGetOption),
+        ),
+        new_fn(
+            "connect0",
+            "(Ljava/net/InetAddress;I)V",
+            Box::new(jvm_connect0),
+        ),
+        new_fn("disconnect0", "(I)V", Box::new(jvm_disconnect0)),
+        new_fn("dataAvailable", "()I", Box::new(jvm_dataAvailable)),
+    ]
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_datagramSocketCreate(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    //as the jdk, broadcast is enabled by default
+    if let Err(e) = net::set_int_option(fd, libc::SOL_SOCKET, libc::SO_BROADCAST, 1) {
+        unsafe {
+            libc::close(fd);
+        }
+        return Err(net::socket_exception(&e));
+    }
+
+    net::set_socket_fd(this, fd);
+
+    Ok(None)
+}
+
+fn jvm_datagramSocketClose(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::socket_fd(this);
+
+    if fd >= 0 {
+        net::set_socket_fd(this, -1);
+        unsafe {
+            libc::close(fd);
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_bind0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let port = args.get(1).unwrap().extract_int();
+    let address = args.get(2).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let ip = if address.is_null() {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        net::inet_address(address)
+    };
+    let sa = net::to_sockaddr(ip, port);
+    let r = unsafe {
+        libc::bind(
+            fd,
+            &sa as *const _ as *const libc::sockaddr,
+            net::SOCKADDR_IN_LEN,
+        )
+    };
+    if r < 0 {
+        return Err(net::bind_exception(&io::Error::last_os_error()));
+    }
+
+    let port = match net::local_address(fd) {
+        Ok((_, port)) => port,
+        Err(e) => return Err(net::socket_exception(&e)),
+    };
+    put_field(this, "localPort", "I", Oop::new_int(port));
+
+    Ok(None)
+}
+
+fn jvm_send(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let packet = args.get(1).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let buf = {
+        let buf = get_field(packet, "buf", "[B");
+        let off = get_field(packet, "offset", "I").extract_int() as usize;
+        let len = get_field(packet, "length", "I").extract_int() as usize;
+        let rf = buf.extract_ref();
+        let ary = rf.extract_type_array();
+        let ary = ary.extract_bytes();
+        ary[off..off + len].to_vec()
+    };
+
+    //a connected socket sends to the peer it's connected to
+    let connected = get_field(this, "connected", "Z").extract_int() != 0;
+    let sa = if connected {
+        None
+    } else {
+        let address = get_field(packet, "address", "Ljava/net/InetAddress;");
+        let port = get_field(packet, "port", "I").extract_int();
+        Some(net::to_sockaddr(net::inet_address(&address), port))
+    };
+
+    let r = blocking(|| loop {
+        let p = buf.as_ptr() as *const libc::c_void;
+        let n = match &sa {
+            Some(sa) => unsafe {
+                libc::sendto(
+                    fd,
+                    p,
+                    buf.len(),
+                    0,
+                    sa as *const _ as *const libc::sockaddr,
+                    net::SOCKADDR_IN_LEN,
+                )
+            },
+            None => unsafe { libc::send(fd, p, buf.len(), 0) },
+        };
+        if n >= 0 {
+            return Ok(());
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    });
+
+    r.map(|_| None).map_err(|e| datagram_exception(&e))
+}
+
+//the address of the next packet is set to 'i', its port returned
+fn jvm_peek(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let address = args.get(1).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+    let timeout = get_field(this, "timeout", "I").extract_int();
+
+    let mut buf = [0u8; 1];
+    let (_, (ip, port)) = match recv(fd, &mut buf, libc::MSG_PEEK, timeout) {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(net::timeout_exception("Peek timed out")),
+        Err(e) => return Err(datagram_exception(&e)),
+    };
+    net::set_inet_address(address, ip);
+
+    Ok(Some(Oop::new_int(port)))
+}
+
+fn jvm_peekData(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let packet = args.get(1).unwrap();
+    let port = receive(this, packet, libc::MSG_PEEK, "Peek timed out")?;
+    Ok(Some(Oop::new_int(port)))
+}
+
+fn jvm_receive0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let packet = args.get(1).unwrap();
+    receive(this, packet, 0, "Receive timed out")?;
+    Ok(None)
+}
+
+//setTimeToLive(I) & setTTL(B)
+fn jvm_setTimeToLive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let ttl = args.get(1).unwrap().extract_int() & 0xFF;
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    net::set_int_option(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_TTL, ttl)
+        .map(|_| None)
+        .map_err(|e| net::socket_exception(&e))
+}
+
+fn jvm_getTimeToLive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    net::get_int_option(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_TTL)
+        .map(|ttl| Some(Oop::new_int(ttl)))
+        .map_err(|e| net::socket_exception(&e))
+}
+
+fn jvm_getTTL(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    net::get_int_option(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_TTL)
+        .map(|ttl| Some(Oop::new_int(ttl as i8 as i32)))
+        .map_err(|e| net::socket_exception(&e))
+}
+
+fn jvm_join(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    membership(args, libc::IP_ADD_MEMBERSHIP)
+}
+
+fn jvm_leave(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    membership(args, libc::IP_DROP_MEMBERSHIP)
+}
+
+fn jvm_socketSetOption(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let opt = args.get(1).unwrap().extract_int();
+    let value = args.get(2).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    if value.is_null() {
+        let ex = runtime::exception::new(cls_consts::J_NPE, Some("value argument".to_string()));
+        return Err(ex);
+    }
+
+    let r = match opt {
+        //AbstractPlainDatagramSocketImpl.timeout is used by receive
+        net::SO_TIMEOUT => Ok(()),
+        //an InetAddress, or a NetworkInterface
+        net::IP_MULTICAST_IF | net::IP_MULTICAST_IF2 => {
+            let ip = if opt == net::IP_MULTICAST_IF {
+                net::inet_address(value)
+            } else {
+                interface_address(value)
+            };
+            set_multicast_if(fd, ip)
+        }
+        //true disables the loopback
+        net::IP_MULTICAST_LOOP => {
+            let disabled = net::boxed_int(value) != 0;
+            net::set_int_option(
+                fd,
+                libc::IPPROTO_IP,
+                libc::IP_MULTICAST_LOOP,
+                (!disabled) as i32,
+            )
+        }
+        _ => match net::map_option(opt) {
+            Some((level, name)) => net::set_int_option(fd, level, name, net::boxed_int(value)),
+            None => return Err(unsupported_option()),
+        },
+    };
+
+    r.map(|_| None).map_err(|e| net::socket_exception(&e))
+}
+
+fn jvm_socketGetOption(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let opt = args.get(1).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let v = match opt {
+        net::SO_BINDADDR => match net::local_address(fd) {
+            Ok((ip, _)) => net::new_inet_address(ip, None),
+            Err(e) => return Err(net::socket_exception(&e)),
+        },
+        net::IP_MULTICAST_IF | net::IP_MULTICAST_IF2 => {
+            let ip = match get_multicast_if(fd) {
+                Ok(ip) => ip,
+                Err(e) => return Err(net::socket_exception(&e)),
+            };
+            if opt == net::IP_MULTICAST_IF {
+                net::new_inet_address(ip, None)
+            } else {
+                match java_net_NetworkInterface::get_by_address(ip)? {
+                    Oop::Null => java_net_NetworkInterface::new_any_interface(),
+                    itf => itf,
+                }
+            }
+        }
+        net::IP_MULTICAST_LOOP => {
+            match net::get_int_option(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_LOOP) {
+                Ok(v) => net::new_boolean(v == 0),
+                Err(e) => return Err(net::socket_exception(&e)),
+            }
+        }
+        net::SO_BROADCAST | net::SO_REUSEADDR | net::SO_REUSEPORT => {
+            let (level, name) = net::map_option(opt).unwrap();
+            match net::get_int_option(fd, level, name) {
+                Ok(v) => net::new_boolean(v != 0),
+                Err(e) => return Err(net::socket_exception(&e)),
+            }
+        }
+        _ => match net::map_option(opt) {
+            Some((level, name)) => match net::get_int_option(fd, level, name) {
+                Ok(v) => net::new_integer(v),
+                Err(e) => return Err(net::socket_exception(&e)),
+            },
+            None => return Err(unsupported_option()),
+        },
+    };
+
+    Ok(Some(v))
+}
+
+fn jvm_connect0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let address = args.get(1).unwrap();
+    let port = args.get(2).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let sa = net::to_sockaddr(net::inet_address(address), port);
+    let r = unsafe {
+        libc::connect(
+            fd,
+            &sa as *const _ as *const libc::sockaddr,
+            net::SOCKADDR_IN_LEN,
+        )
+    };
+    if r < 0 {
+        return Err(net::connect_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+//connect to AF_UNSPEC dissolves the association
+fn jvm_disconnect0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::socket_fd(this);
+
+    if fd >= 0 {
+        let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+        sa.sin_family = libc::AF_UNSPEC as libc::sa_family_t;
+        unsafe {
+            libc::connect(
+                fd,
+                &sa as *const _ as *const libc::sockaddr,
+                net::SOCKADDR_IN_LEN,
+            );
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_dataAvailable(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let mut n: libc::c_int = 0;
+    if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut n) } < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(Some(Oop::new_int(n)))
+}
+
+//receive into the packet, the port of the sender returned
+fn receive(this: &Oop, packet: &Oop, flags: i32, timeout_msg: &str) -> Result<i32, Oop> {
+    let fd = net::check_open(net::socket_fd(this))?;
+    let timeout = get_field(this, "timeout", "I").extract_int();
+
+    let buf_ary = get_field(packet, "buf", "[B");
+    let off = get_field(packet, "offset", "I").extract_int() as usize;
+    let len = get_field(packet, "bufLength", "I").extract_int() as usize;
+
+    let mut buf = vec![0u8; len];
+    let (n, (ip, port)) = match recv(fd, &mut buf, flags, timeout) {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(net::timeout_exception(timeout_msg)),
+        Err(e) => return Err(datagram_exception(&e)),
+    };
+
+    //truncated if longer than the buffer
+    let n = n.min(len);
+    {
+        let rf = buf_ary.extract_ref();
+        let ary = rf.extract_mut_type_array();
+        let ary = ary.extract_mut_bytes();
+        ary[off..off + n].copy_from_slice(&buf[..n]);
+    }
+
+    put_field(packet, "length", "I", Oop::new_int(n as i32));
+    put_field(
+        packet,
+        "address",
+        "Ljava/net/InetAddress;",
+        net::new_inet_address(ip, None),
+    );
+    put_field(packet, "port", "I", Oop::new_int(port));
+
+    Ok(port)
+}
+
+//the length & the sender, none if timed out
+fn recv(
+    fd: i32,
+    buf: &mut [u8],
+    flags: i32,
+    timeout: i32,
+) -> io::Result<Option<(usize, (Ipv4Addr, i32))>> {
+    blocking(|| {
+        if timeout > 0 && !net::poll(fd, libc::POLLIN, timeout)? {
+            return Ok(None);
+        }
+
+        loop {
+            let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+            let mut sa_len = net::SOCKADDR_IN_LEN;
+            let n = unsafe {
+                libc::recvfrom(
+                    fd,
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    flags,
+                    &mut sa as *mut _ as *mut libc::sockaddr,
+                    &mut sa_len,
+                )
+            };
+            if n >= 0 {
+                return Ok(Some((n as usize, net::from_sockaddr(&sa))));
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    })
+}
+
+fn membership(args: &[Oop], name: i32) -> JNIResult {
+    let this = args.first().unwrap();
+    let group = args.get(1).unwrap();
+    let itf = args.get(2).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let ip = if itf.is_null() {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        interface_address(itf)
+    };
+    let req = libc::ip_mreq {
+        imr_multiaddr: libc::in_addr {
+            s_addr: u32::from(net::inet_address(group)).to_be(),
+        },
+        imr_interface: libc::in_addr {
+            s_addr: u32::from(ip).to_be(),
+        },
+    };
+
+    let p = &req as *const libc::ip_mreq as *const libc::c_void;
+    let len = mem::size_of::<libc::ip_mreq>() as libc::socklen_t;
+    if unsafe { libc::setsockopt(fd, libc::IPPROTO_IP, name, p, len) } < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+//the first address of NetworkInterface
+fn interface_address(itf: &Oop) -> Ipv4Addr {
+    let addrs = get_field(itf, "addrs", "[Ljava/net/InetAddress;");
+    if addrs.is_null() {
+        return Ipv4Addr::UNSPECIFIED;
+    }
+
+    let rf = addrs.extract_ref();
+    let ary = rf.extract_array();
+    if ary.is_empty() {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        net::inet_address(&ary.get(0))
+    }
+}
+
+fn set_multicast_if(fd: i32, ip: Ipv4Addr) -> io::Result<()> {
+    let addr = libc::in_addr {
+        s_addr: u32::from(ip).to_be(),
+    };
+    let p = &addr as *const libc::in_addr as *const libc::c_void;
+    let len = mem::size_of::<libc::in_addr>() as libc::socklen_t;
+    if unsafe { libc::setsockopt(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_IF, p, len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn get_multicast_if(fd: i32) -> io::Result<Ipv4Addr> {
+    let mut addr = libc::in_addr { s_addr: 0 };
+    let mut len = mem::size_of::<libc::in_addr>() as libc::socklen_t;
+    let p = &mut addr as *mut libc::in_addr as *mut libc::c_void;
+    if unsafe { libc::getsockopt(fd, libc::IPPROTO_IP, libc::IP_MULTICAST_IF, p, &mut len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(Ipv4Addr::from(u32::from_be(addr.s_addr)))
+    }
+}
+
+//the ICMP port unreachable of the last send
+fn datagram_exception(err: &io::Error) -> Oop {
+    match err.raw_os_error() {
+        Some(libc::ECONNREFUSED) => runtime::exception::new(
+            cls_consts::J_PORT_UNREACHABLE_EXCEPTION,
+            Some("ICMP Port Unreachable".to_string()),
+        ),
+        Some(libc::EBADF) => runtime::exception::new(
+            cls_consts::J_SOCKET_EXCEPTION,
+            Some("Socket closed".to_string()),
+        ),
+        _ => net::socket_exception(err),
+    }
+}
+
+fn unsupported_option() -> Oop {
+    runtime::exception::new(
+        cls_consts::J_SOCKET_EXCEPTION,
+        Some("Unsupported socket option".to_string()),
+    )
+}
@@ -0,0 +1,426 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{blocking, get_field, put_field};
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use std::io;
+use std::mem;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initProto", "()V", Box::new(jvm_initProto)),
+        new_fn("socketCreate", "(Z)V", Box::new(jvm_socketCreate)),
+        new_fn(
+            "socketConnect",
+            "(Ljava/net/InetAddress;II)V",
+            Box::new(jvm_socketConnect),
+        ),
+        new_fn(
+            "socketBind",
+            "(Ljava/net/InetAddress;I)V",
+            Box::new(jvm_socketBind),
+        ),
+        new_fn("socketListen", "(I)V", Box::new(jvm_socketListen)),
+        new_fn(
+            "socketAccept",
+            "(Ljava/net/SocketImpl;)V",
+            Box::new(jvm_socketAccept),
+        ),
+        new_fn("socketAvailable", "()I", Box::new(jvm_socketAvailable)),
+        new_fn("socketClose0", "(Z)V", Box::new(jvm_socketClose0)),
+        new_fn("socketShutdown", "(I)V", Box::new(jvm_socketShutdown)),
+        //socketSetOption0 since 8u, socketSetOption before
+        new_fn(
+            "socketSetOption0",
+            "(IZLjava/lang/Object;)V",
+            Box::new(jvm_socketSetOption),
+        ),
+        new_fn(
+            "socketSetOption",
+            "(IZLjava/lang/Object;)V",
+            Box::new(jvm_socketSetOption),
+        ),
+        new_fn(
+            "socketGetOption",
+            "(ILjava/lang/Object;)I",
+            Box::new(jvm_socketGetOption),
+        ),
+        new_fn(
+            "socketSendUrgentData",
+            "(I)V",
+            Box::new(jvm_socketSendUrgentData),
+        ),
+    ]
+}
+
+fn jvm_initProto(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_socketCreate(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let stream = args.get(1).unwrap().extract_int() != 0;
+
+    let ty = if stream {
+        libc::SOCK_STREAM
+    } else {
+        libc::SOCK_DGRAM
+    };
+    let fd = unsafe { libc::socket(libc::AF_INET, ty, 0) };
+    if fd < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    //a server socket, rebind the port in TIME_WAIT
+    let server_socket = get_field(this, "serverSocket", "Ljava/net/ServerSocket;");
+    if !server_socket.is_null() {
+        if let Err(e) = net::set_int_option(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, 1) {
+            unsafe {
+                libc::close(fd);
+            }
+            return Err(net::socket_exception(&e));
+        }
+    }
+
+    net::set_socket_fd(this, fd);
+
+    Ok(None)
+}
+
+fn jvm_socketConnect(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let address = args.get(1).unwrap();
+    let port = args.get(2).unwrap().extract_int();
+    let timeout = args.get(3).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    if address.is_null() {
+        let ex = runtime::exception::new(
+            cls_consts::J_NPE,
+            Some("inet address argument is null.".to_string()),
+        );
+        return Err(ex);
+    }
+
+    let sa = net::to_sockaddr(net::inet_address(address), port);
+    let r = blocking(|| connect(fd, &sa, timeout));
+    match r {
+        Ok(true) => (),
+        Ok(false) => return Err(net::timeout_exception("connect timed out")),
+        Err(e) => return Err(net::connect_exception(&e)),
+    }
+
+    put_field(this, "address", "Ljava/net/InetAddress;", address.clone());
+    put_field(this, "port", "I", Oop::new_int(port));
+    let local_port = get_field(this, "localport", "I").extract_int();
+    if local_port == 0 {
+        if let Ok((_, port)) = net::local_address(fd) {
+            put_field(this, "localport", "I", Oop::new_int(port));
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_socketBind(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let address = args.get(1).unwrap();
+    let port = args.get(2).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let sa = net::to_sockaddr(net::inet_address(address), port);
+    let r = unsafe {
+        libc::bind(
+            fd,
+            &sa as *const _ as *const libc::sockaddr,
+            net::SOCKADDR_IN_LEN,
+        )
+    };
+    if r < 0 {
+        return Err(net::bind_exception(&io::Error::last_os_error()));
+    }
+
+    put_field(this, "address", "Ljava/net/InetAddress;", address.clone());
+    let port = if port == 0 {
+        match net::local_address(fd) {
+            Ok((_, port)) => port,
+            Err(e) => return Err(net::socket_exception(&e)),
+        }
+    } else {
+        port
+    };
+    put_field(this, "localport", "I", Oop::new_int(port));
+
+    Ok(None)
+}
+
+fn jvm_socketListen(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let count = args.get(1).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    if unsafe { libc::listen(fd, count.max(1)) } < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+fn jvm_socketAccept(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let s = args.get(1).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+    let timeout = get_field(this, "timeout", "I").extract_int();
+
+    let r = blocking(|| {
+        if !net::poll(fd, libc::POLLIN, timeout)? {
+            return Ok(None);
+        }
+
+        loop {
+            let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+            let mut len = net::SOCKADDR_IN_LEN;
+            let new_fd =
+                unsafe { libc::accept(fd, &mut sa as *mut _ as *mut libc::sockaddr, &mut len) };
+            if new_fd >= 0 {
+                return Ok(Some((new_fd, sa)));
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    });
+
+    let (new_fd, sa) = match r {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(net::timeout_exception("Accept timed out")),
+        Err(e) => return Err(net::socket_exception(&e)),
+    };
+
+    let (ip, port) = net::from_sockaddr(&sa);
+    let local_port = get_field(this, "localport", "I");
+    net::set_socket_fd(s, new_fd);
+    put_field(
+        s,
+        "address",
+        "Ljava/net/InetAddress;",
+        net::new_inet_address(ip, None),
+    );
+    put_field(s, "port", "I", Oop::new_int(port));
+    put_field(s, "localport", "I", local_port);
+
+    Ok(None)
+}
+
+fn jvm_socketAvailable(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let mut n: libc::c_int = 0;
+    if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut n) } < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(Some(Oop::new_int(n)))
+}
+
+//the threads blocked in accept or read are woken up by shutdown
+fn jvm_socketClose0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = net::socket_fd(this);
+
+    if fd >= 0 {
+        net::set_socket_fd(this, -1);
+        unsafe {
+            libc::shutdown(fd, libc::SHUT_RDWR);
+            libc::close(fd);
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_socketShutdown(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let how = args.get(1).unwrap().extract_int();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    //AbstractPlainSocketImpl.SHUT_RD & SHUT_WR
+    let how = if how == 0 {
+        libc::SHUT_RD
+    } else {
+        libc::SHUT_WR
+    };
+    unsafe {
+        libc::shutdown(fd, how);
+    }
+
+    Ok(None)
+}
+
+fn jvm_socketSetOption(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let cmd = args.get(1).unwrap().extract_int();
+    let on = args.get(2).unwrap().extract_int() != 0;
+    let value = args.get(3).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let r = match cmd {
+        //AbstractPlainSocketImpl.timeout is used by accept & read
+        net::SO_TIMEOUT => Ok(()),
+        net::SO_LINGER => {
+            let linger = libc::linger {
+                l_onoff: on as i32,
+                l_linger: if on { net::boxed_int(value) } else { 0 },
+            };
+            set_linger(fd, &linger)
+        }
+        _ => match net::map_option(cmd) {
+            Some((level, name)) => {
+                let v = if value.is_null() {
+                    on as i32
+                } else {
+                    net::boxed_int(value)
+                };
+                net::set_int_option(fd, level, name, v)
+            }
+            None => return Err(unsupported_option()),
+        },
+    };
+
+    r.map(|_| None).map_err(|e| net::socket_exception(&e))
+}
+
+//the booleans are 1 or -1
+fn jvm_socketGetOption(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let cmd = args.get(1).unwrap().extract_int();
+    let container = args.get(2).unwrap();
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let r = match cmd {
+        net::SO_BINDADDR => net::local_address(fd).map(|(ip, _)| {
+            let addr = net::new_inet_address(ip, None);
+            put_field(container, "addr", "Ljava/net/InetAddress;", addr);
+            0
+        }),
+        net::SO_LINGER => get_linger(fd).map(|linger| {
+            if linger.l_onoff != 0 {
+                linger.l_linger
+            } else {
+                -1
+            }
+        }),
+        net::TCP_NODELAY
+        | net::SO_OOBINLINE
+        | net::SO_KEEPALIVE
+        | net::SO_REUSEADDR
+        | net::SO_REUSEPORT => {
+            let (level, name) = net::map_option(cmd).unwrap();
+            net::get_int_option(fd, level, name).map(|v| if v == 0 { -1 } else { 1 })
+        }
+        _ => match net::map_option(cmd) {
+            Some((level, name)) => net::get_int_option(fd, level, name),
+            None => return Err(unsupported_option()),
+        },
+    };
+
+    r.map(|v| Some(Oop::new_int(v)))
+        .map_err(|e| net::socket_exception(&e))
+}
+
+fn jvm_socketSendUrgentData(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let data = args.get(1).unwrap().extract_int() as u8;
+    let fd = net::check_open(net::socket_fd(this))?;
+
+    let p = &data as *const u8 as *const libc::c_void;
+    if unsafe { libc::send(fd, p, 1, libc::MSG_OOB) } < 0 {
+        return Err(net::socket_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+//connected, or timed out
+fn connect(fd: i32, sa: &libc::sockaddr_in, timeout: i32) -> io::Result<bool> {
+    let sa = sa as *const _ as *const libc::sockaddr;
+
+    if timeout <= 0 {
+        loop {
+            if unsafe { libc::connect(fd, sa, net::SOCKADDR_IN_LEN) } == 0 {
+                return Ok(true);
+            }
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => continue,
+                //interrupted before, connecting in the background
+                Some(libc::EALREADY) | Some(libc::EINPROGRESS) => break,
+                _ => return Err(err),
+            }
+        }
+        return wait_connected(fd, 0);
+    }
+
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    unsafe {
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+    let r = if unsafe { libc::connect(fd, sa, net::SOCKADDR_IN_LEN) } == 0 {
+        Ok(true)
+    } else {
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINPROGRESS) | Some(libc::EINTR) => wait_connected(fd, timeout),
+            _ => Err(err),
+        }
+    };
+    unsafe {
+        libc::fcntl(fd, libc::F_SETFL, flags);
+    }
+
+    r
+}
+
+fn wait_connected(fd: i32, timeout: i32) -> io::Result<bool> {
+    if !net::poll(fd, libc::POLLOUT, timeout)? {
+        return Ok(false);
+    }
+
+    match net::get_int_option(fd, libc::SOL_SOCKET, libc::SO_ERROR)? {
+        0 => Ok(true),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+fn set_linger(fd: i32, linger: &libc::linger) -> io::Result<()> {
+    let p = linger as *const libc::linger as *const libc::c_void;
+    let len = mem::size_of::<libc::linger>() as libc::socklen_t;
+    if unsafe { libc::setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, p, len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn get_linger(fd: i32) -> io::Result<libc::linger> {
+    let mut linger: libc::linger = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::linger>() as libc::socklen_t;
+    let p = &mut linger as *mut libc::linger as *mut libc::c_void;
+    if unsafe { libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, p, &mut len) } < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(linger)
+    }
+}
+
+fn unsupported_option() -> Oop {
+    runtime::exception::new(
+        cls_consts::J_SOCKET_EXCEPTION,
+        Some("Unsupported socket option".to_string()),
+    )
+}
@@ -0,0 +1,81 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{blocking, fd_value};
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "socketRead0",
+            "(Ljava/io/FileDescriptor;[BIII)I",
+            Box::new(jvm_socketRead0),
+        ),
+    ]
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_socketRead0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = fd_value(args.get(1).unwrap());
+    let byte_ary = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+    let timeout = args.get(5).unwrap().extract_int();
+    let fd = net::check_open(fd)?;
+
+    let mut buf = vec![0u8; len];
+    let r = blocking(|| {
+        if timeout > 0 && !net::poll(fd, libc::POLLIN, timeout)? {
+            return Ok(None);
+        }
+
+        loop {
+            let p = buf.as_mut_ptr() as *mut libc::c_void;
+            let n = unsafe { libc::recv(fd, p, len, 0) };
+            if n >= 0 {
+                return Ok(Some(n as usize));
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    });
+
+    let n = match r {
+        Ok(Some(0)) => return Ok(Some(Oop::new_int(-1))),
+        Ok(Some(n)) => n,
+        Ok(None) => return Err(net::timeout_exception("Read timed out")),
+        Err(e) => return Err(read_exception(&e)),
+    };
+
+    let rf = byte_ary.extract_ref();
+    let ary = rf.extract_mut_type_array();
+    let ary = ary.extract_mut_bytes();
+    ary[off..off + n].copy_from_slice(&buf[..n]);
+
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn read_exception(err: &io::Error) -> Oop {
+    match err.raw_os_error() {
+        Some(libc::ECONNRESET) | Some(libc::EPIPE) => runtime::exception::new(
+            cls_consts::J_CONNECTION_RESET_EXCEPTION,
+            Some("Connection reset".to_string()),
+        ),
+        //closed by another thread
+        Some(libc::EBADF) => runtime::exception::new(
+            cls_consts::J_SOCKET_EXCEPTION,
+            Some("Socket closed".to_string()),
+        ),
+        _ => net::socket_exception(err),
+    }
+}
@@ -0,0 +1,69 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{blocking, fd_value};
+use crate::native::common::net;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "socketWrite0",
+            "(Ljava/io/FileDescriptor;[BII)V",
+            Box::new(jvm_socketWrite0),
+        ),
+    ]
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_socketWrite0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = fd_value(args.get(1).unwrap());
+    let byte_ary = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+    let fd = net::check_open(fd)?;
+
+    let buf = {
+        let rf = byte_ary.extract_ref();
+        let ary = rf.extract_type_array();
+        let ary = ary.extract_bytes();
+        ary[off..off + len].to_vec()
+    };
+
+    let r = blocking(|| {
+        let mut pos = 0;
+        while pos < buf.len() {
+            let p = buf[pos..].as_ptr() as *const libc::c_void;
+            let n = unsafe { libc::send(fd, p, buf.len() - pos, 0) };
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            pos += n as usize;
+        }
+        Ok(())
+    });
+
+    match r {
+        Ok(()) => Ok(None),
+        Err(e) => {
+            let ex = match e.raw_os_error() {
+                Some(libc::ECONNRESET) => runtime::exception::new(
+                    cls_consts::J_CONNECTION_RESET_EXCEPTION,
+                    Some("Connection reset".to_string()),
+                ),
+                _ => net::socket_exception(&e),
+            };
+            Err(ex)
+        }
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(non_snake_case)]
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+
+/*
+The copies between the arrays and the direct buffers of the other byte order,
+each element is swapped. The positions & the lengths are in bytes.
+*/
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn(
+            "copyFromShortArray",
+            "(Ljava/lang/Object;JJJ)V",
+            Box::new(jvm_copyFromShortArray),
+        ),
+        new_fn(
+            "copyToShortArray",
+            "(JLjava/lang/Object;JJ)V",
+            Box::new(jvm_copyToShortArray),
+        ),
+        new_fn(
+            "copyFromIntArray",
+            "(Ljava/lang/Object;JJJ)V",
+            Box::new(jvm_copyFromIntArray),
+        ),
+        new_fn(
+            "copyToIntArray",
+            "(JLjava/lang/Object;JJ)V",
+            Box::new(jvm_copyToIntArray),
+        ),
+        new_fn(
+            "copyFromLongArray",
+            "(Ljava/lang/Object;JJJ)V",
+            Box::new(jvm_copyFromLongArray),
+        ),
+        new_fn(
+            "copyToLongArray",
+            "(JLjava/lang/Object;JJ)V",
+            Box::new(jvm_copyToLongArray),
+        ),
+    ]
+}
+
+fn jvm_copyFromShortArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_from_array(args, 2)
+}
+
+fn jvm_copyToShortArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_to_array(args, 2)
+}
+
+fn jvm_copyFromIntArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_from_array(args, 4)
+}
+
+fn jvm_copyToIntArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_to_array(args, 4)
+}
+
+fn jvm_copyFromLongArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_from_array(args, 8)
+}
+
+fn jvm_copyToLongArray(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    copy_to_array(args, 8)
+}
+
+//(Object src, long srcPos, long dstAddr, long length)
+fn copy_from_array(args: &[Oop], size: usize) -> JNIResult {
+    let src = args.first().unwrap();
+    let src_pos = args.get(1).unwrap().extract_long() as usize;
+    let dst_addr = args.get(2).unwrap().extract_long() as *mut u8;
+    let len = args.get(3).unwrap().extract_long() as usize;
+
+    let rf = src.extract_ref();
+    let ary = rf.extract_mut_type_array();
+    let src = &ary.as_mut_bytes()[src_pos..src_pos + len];
+    let dst = unsafe { std::slice::from_raw_parts_mut(dst_addr, len) };
+    swap_copy(src, dst, size);
+
+    Ok(None)
+}
+
+//(long srcAddr, Object dst, long dstPos, long length)
+fn copy_to_array(args: &[Oop], size: usize) -> JNIResult {
+    let src_addr = args.first().unwrap().extract_long() as *const u8;
+    let dst = args.get(1).unwrap();
+    let dst_pos = args.get(2).unwrap().extract_long() as usize;
+    let len = args.get(3).unwrap().extract_long() as usize;
+
+    let rf = dst.extract_ref();
+    let ary = rf.extract_mut_type_array();
+    let dst = &mut ary.as_mut_bytes()[dst_pos..dst_pos + len];
+    let src = unsafe { std::slice::from_raw_parts(src_addr, len) };
+    swap_copy(src, dst, size);
+
+    Ok(None)
+}
+
+fn swap_copy(src: &[u8], dst: &mut [u8], size: usize) {
+    for (s, d) in src.chunks_exact(size).zip(dst.chunks_exact_mut(size)) {
+        for (i, b) in s.iter().rev().enumerate() {
+            d[i] = *b;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::swap_copy;
+
+    #[test]
+    fn t_swap_copy() {
+        let src = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        let mut dst = [0u8; 8];
+        swap_copy(&src, &mut dst, 4);
+        assert_eq!(dst, [4, 3, 2, 1, 8, 7, 6, 5]);
+        swap_copy(&src, &mut dst, 2);
+        assert_eq!(dst, [2, 1, 4, 3, 6, 5, 8, 7]);
+    }
+}
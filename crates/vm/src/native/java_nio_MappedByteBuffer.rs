@@ -0,0 +1,55 @@
+#![allow(non_snake_case)]
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("isLoaded0", "(JJI)Z", Box::new(jvm_isLoaded0)),
+        new_fn("load0", "(JJ)V", Box::new(jvm_load0)),
+        new_fn(
+            "force0",
+            "(Ljava/io/FileDescriptor;JJ)V",
+            Box::new(jvm_force0),
+        ),
+    ]
+}
+
+//all the pages resident
+fn jvm_isLoaded0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(2).unwrap().extract_long() as usize;
+    let page_count = args.get(3).unwrap().extract_int() as usize;
+
+    let mut vec = vec![0u8; page_count];
+    let r = unsafe { libc::mincore(addr, len, vec.as_mut_ptr() as *mut _) };
+    if r < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    let loaded = vec.iter().all(|&v| v & 1 != 0);
+    Ok(Some(Oop::new_int(loaded as i32)))
+}
+
+fn jvm_load0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(2).unwrap().extract_long() as usize;
+
+    if unsafe { libc::madvise(addr, len, libc::MADV_WILLNEED) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+fn jvm_force0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(2).unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(3).unwrap().extract_long() as usize;
+
+    if unsafe { libc::msync(addr, len, libc::MS_SYNC) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,35 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::get_field;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime::require_class3;
+use crate::util;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn(
+        "getMetaInfEntryNames",
+        "()[Ljava/lang/String;",
+        Box::new(jvm_getMetaInfEntryNames),
+    )]
+}
+
+//the names of the entries in META-INF, null if none
+fn jvm_getMetaInfEntryNames(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let jzfile = get_field(this, "jzfile", "J");
+    let jzfile = super::java_util_zip_ZipFile::to_jzfile(&jzfile);
+
+    let names: Vec<Oop> = jzfile
+        .entries()
+        .iter()
+        .map(|entry| entry.name())
+        .filter(|name| name.len() > 9 && name[..9].eq_ignore_ascii_case(b"META-INF/"))
+        .map(|name| util::oop::new_java_lang_string2(&String::from_utf8_lossy(name)))
+        .collect();
+    if names.is_empty() {
+        return Ok(Some(Oop::Null));
+    }
+
+    let ary_cls = require_class3(None, b"[Ljava/lang/String;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, names)))
+}
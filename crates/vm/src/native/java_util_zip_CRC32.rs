@@ -0,0 +1,51 @@
+#![allow(non_snake_case)]
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crc32fast::Hasher;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("update", "(II)I", Box::new(jvm_update)),
+        new_fn("updateBytes", "(I[BII)I", Box::new(jvm_updateBytes)),
+        new_fn(
+            "updateByteBuffer",
+            "(IJII)I",
+            Box::new(jvm_updateByteBuffer),
+        ),
+    ]
+}
+
+fn jvm_update(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let crc = args.first().unwrap().extract_int();
+    let b = args.get(1).unwrap().extract_int();
+    Ok(Some(Oop::new_int(update(crc, &[b as u8]))))
+}
+
+fn jvm_updateBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let crc = args.first().unwrap().extract_int();
+    let b = args.get(1).unwrap();
+    let off = args.get(2).unwrap().extract_int() as usize;
+    let len = args.get(3).unwrap().extract_int() as usize;
+
+    let rf = b.extract_ref();
+    let ary = rf.extract_type_array();
+    let bytes = &ary.extract_bytes()[off..off + len];
+    Ok(Some(Oop::new_int(update(crc, bytes))))
+}
+
+//the memory of a direct ByteBuffer
+fn jvm_updateByteBuffer(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let crc = args.first().unwrap().extract_int();
+    let addr = args.get(1).unwrap().extract_long();
+    let off = args.get(2).unwrap().extract_int() as usize;
+    let len = args.get(3).unwrap().extract_int() as usize;
+
+    let bytes = unsafe { std::slice::from_raw_parts((addr as *const u8).add(off), len) };
+    Ok(Some(Oop::new_int(update(crc, bytes))))
+}
+
+fn update(crc: i32, bytes: &[u8]) -> i32 {
+    let mut hasher = Hasher::new_with_initial(crc as u32);
+    hasher.update(bytes);
+    hasher.finalize() as i32
+}
@@ -0,0 +1,150 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{get_field, put_field};
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use miniz_oxide::deflate::core::{create_comp_flags_from_zip_params, CompressorOxide};
+use miniz_oxide::deflate::stream;
+use miniz_oxide::{MZError, MZFlush, MZStatus};
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()V", Box::new(jvm_initIDs)),
+        new_fn("init", "(IIZ)J", Box::new(jvm_init)),
+        new_fn("setDictionary", "(J[BII)V", Box::new(jvm_setDictionary)),
+        new_fn("deflateBytes", "(J[BIII)I", Box::new(jvm_deflateBytes)),
+        new_fn("getAdler", "(J)I", Box::new(jvm_getAdler)),
+        new_fn("reset", "(J)V", Box::new(jvm_reset)),
+        new_fn("end", "(J)V", Box::new(jvm_end)),
+    ]
+}
+
+//Deflater.DEFAULT_COMPRESSION
+const DEFAULT_LEVEL: i32 = 6;
+
+struct Deflate {
+    compressor: CompressorOxide,
+    nowrap: bool,
+    //bytes consumed since init or reset
+    total_in: usize,
+}
+
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_init(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let level = args.first().unwrap().extract_int();
+    let strategy = args.get(1).unwrap().extract_int();
+    let nowrap = args.get(2).unwrap().extract_int() != 0;
+    let deflate = Box::new(Deflate {
+        compressor: CompressorOxide::new(comp_flags(level, strategy, nowrap)),
+        nowrap,
+        total_in: 0,
+    });
+    Ok(Some(Oop::new_long(Box::into_raw(deflate) as i64)))
+}
+
+//miniz has no preset dictionary
+fn jvm_setDictionary(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let ex = runtime::exception::new(
+        cls_consts::J_ILLEGAL_ARGUMENT,
+        Some("preset dictionary not supported".to_string()),
+    );
+    Err(ex)
+}
+
+//consumes this.buf[off..off + len], updates off, len, setParams & finished
+fn jvm_deflateBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let deflate = to_deflate(args.get(1).unwrap());
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+    let flush = args.get(5).unwrap().extract_int();
+
+    if get_field(this, "setParams", "Z").extract_int() != 0 {
+        let level = get_field(this, "level", "I").extract_int();
+        let strategy = get_field(this, "strategy", "I").extract_int();
+        //the strategy can't be changed after the compression started
+        if deflate.total_in == 0 {
+            deflate.compressor = CompressorOxide::new(comp_flags(level, strategy, deflate.nowrap));
+        } else {
+            let level = if level < 0 { DEFAULT_LEVEL } else { level };
+            deflate.compressor.set_compression_level_raw(level as u8);
+        }
+        put_field(this, "setParams", "Z", Oop::new_int(0));
+        return Ok(Some(Oop::new_int(0)));
+    }
+
+    let in_off = get_field(this, "off", "I").extract_int() as usize;
+    let in_len = get_field(this, "len", "I").extract_int() as usize;
+    let input = {
+        let buf = get_field(this, "buf", "[B");
+        let rf = buf.extract_ref();
+        let ary = rf.extract_type_array();
+        ary.extract_bytes()[in_off..in_off + in_len].to_vec()
+    };
+    let flush = if get_field(this, "finish", "Z").extract_int() != 0 {
+        MZFlush::Finish
+    } else {
+        MZFlush::new(flush).unwrap_or(MZFlush::None)
+    };
+
+    let rf = b.extract_ref();
+    let ary = rf.extract_mut_type_array();
+    let output = &mut ary.extract_mut_bytes()[off..off + len];
+    let r = stream::deflate(&mut deflate.compressor, &input, output, flush);
+
+    match r.status {
+        Ok(status) => {
+            let consumed = r.bytes_consumed as i32;
+            deflate.total_in += r.bytes_consumed;
+            put_field(this, "off", "I", Oop::new_int((in_off as i32) + consumed));
+            put_field(this, "len", "I", Oop::new_int((in_len as i32) - consumed));
+            if status == MZStatus::StreamEnd {
+                put_field(this, "finished", "Z", Oop::new_int(1));
+            }
+            Ok(Some(Oop::new_int(r.bytes_written as i32)))
+        }
+        //no progress, need more input or more output space
+        Err(MZError::Buf) => Ok(Some(Oop::new_int(0))),
+        Err(e) => {
+            let ex =
+                runtime::exception::new(cls_consts::J_INTERNAL_ERROR, Some(format!("{:?}", e)));
+            Err(ex)
+        }
+    }
+}
+
+fn jvm_getAdler(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let deflate = to_deflate(args.first().unwrap());
+    Ok(Some(Oop::new_int(deflate.compressor.adler32() as i32)))
+}
+
+fn jvm_reset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let deflate = to_deflate(args.first().unwrap());
+    deflate.compressor.reset();
+    deflate.total_in = 0;
+    Ok(None)
+}
+
+fn jvm_end(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.first().unwrap().extract_long();
+    unsafe {
+        drop(Box::from_raw(addr as *mut Deflate));
+    }
+    Ok(None)
+}
+
+//the strategies of Deflater have the values of zlib, as miniz
+fn comp_flags(level: i32, strategy: i32, nowrap: bool) -> u32 {
+    let window_bits = if nowrap { -15 } else { 15 };
+    create_comp_flags_from_zip_params(level, window_bits, strategy)
+}
+
+fn to_deflate<'a>(addr: &Oop) -> &'a mut Deflate {
+    let addr = addr.extract_long();
+    unsafe { &mut *(addr as *mut Deflate) }
+}
@@ -0,0 +1,372 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{get_field, put_field};
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use adler32::RollingAdler32;
+use classfile::consts as cls_consts;
+use miniz_oxide::inflate::core::{
+    decompress, inflate_flags, DecompressorOxide, TINFL_LZ_DICT_SIZE,
+};
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::DataFormat;
+use std::cmp;
+use std::io::Cursor;
+
+const FDICT: u8 = 0x20;
+const WINDOW_MASK: usize = TINFL_LZ_DICT_SIZE - 1;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()V", Box::new(jvm_initIDs)),
+        new_fn("init", "(Z)J", Box::new(jvm_init)),
+        new_fn("setDictionary", "(J[BII)V", Box::new(jvm_setDictionary)),
+        new_fn("inflateBytes", "(J[BII)I", Box::new(jvm_inflateBytes)),
+        new_fn("getAdler", "(J)I", Box::new(jvm_getAdler)),
+        new_fn("reset", "(J)V", Box::new(jvm_reset)),
+        new_fn("end", "(J)V", Box::new(jvm_end)),
+    ]
+}
+
+/*
+the z_stream of the jdk, the address is held by ZStreamRef
+
+miniz has no preset dictionary, it even rejects a zlib header with FDICT set.
+So the stream::inflate loop is redone here over a window of our own: the
+header is held back until setDictionary, the dictionary is copied into the
+window, and the header goes to the decompressor with FDICT cleared.
+*/
+struct Inflate {
+    decomp: DecompressorOxide,
+    format: DataFormat,
+    //the sliding window, decompress writes at pos
+    window: Box<[u8; TINFL_LZ_DICT_SIZE]>,
+    pos: usize,
+    //inflated bytes at pos, not yet copied out
+    avail: usize,
+    status: TINFLStatus,
+    //the zlib header, CMF FLG [DICTID]
+    header: Vec<u8>,
+    header_done: bool,
+}
+
+impl Inflate {
+    fn new(format: DataFormat) -> Self {
+        Self {
+            decomp: DecompressorOxide::new(),
+            format,
+            window: Box::new([0; TINFL_LZ_DICT_SIZE]),
+            pos: 0,
+            avail: 0,
+            status: TINFLStatus::NeedsMoreInput,
+            header: Vec::with_capacity(6),
+            header_done: format != DataFormat::Zlib,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.format);
+    }
+
+    fn header_len(&self) -> usize {
+        match self.header.get(1) {
+            Some(flg) if flg & FDICT != 0 => 6,
+            _ => 2,
+        }
+    }
+
+    //the DICTID of the zlib header, while the dictionary is missing
+    fn dict_id(&self) -> Option<u32> {
+        if !self.header_done && self.header.len() == 6 {
+            let mut id = [0; 4];
+            id.copy_from_slice(&self.header[2..]);
+            Some(u32::from_be_bytes(id))
+        } else {
+            None
+        }
+    }
+
+    fn need_dict(&self) -> bool {
+        self.dict_id().is_some()
+    }
+
+    fn finished(&self) -> bool {
+        self.status == TINFLStatus::Done && self.avail == 0
+    }
+
+    fn failed(&self) -> bool {
+        (self.status as i32) < 0
+    }
+
+    fn flags(&self) -> u32 {
+        let mut flags =
+            inflate_flags::TINFL_FLAG_COMPUTE_ADLER32 | inflate_flags::TINFL_FLAG_HAS_MORE_INPUT;
+        if self.format == DataFormat::Zlib {
+            flags |= inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER;
+        }
+        flags
+    }
+
+    fn set_dictionary(&mut self, dict: &[u8]) -> Result<(), &'static str> {
+        if self.format == DataFormat::Zlib {
+            match self.dict_id() {
+                Some(id) if id == RollingAdler32::from_buffer(dict).hash() => (),
+                Some(_) => return Err("incorrect dictionary"),
+                None => return Err("dictionary not needed"),
+            }
+        }
+
+        //only the last window size bytes can be referred to
+        let dict = &dict[dict.len().saturating_sub(TINFL_LZ_DICT_SIZE)..];
+        let start = self.pos.wrapping_sub(dict.len());
+        for (i, b) in dict.iter().enumerate() {
+            self.window[(start + i) & WINDOW_MASK] = *b;
+        }
+
+        if self.format == DataFormat::Zlib {
+            let cmf = self.header[0];
+            let mut flg = self.header[1];
+            if (u16::from(cmf) * 256 + u16::from(flg)) % 31 == 0 {
+                flg &= !(FDICT | 0x1f);
+                flg |= (31 - (u16::from(cmf) * 256 + u16::from(flg)) % 31) as u8 % 31;
+            }
+            self.feed_header(&[cmf, flg]);
+        }
+        Ok(())
+    }
+
+    fn feed_header(&mut self, header: &[u8]) {
+        let flags = self.flags();
+        let mut cursor = Cursor::new(&mut self.window[..]);
+        cursor.set_position(self.pos as u64);
+        let (status, _, _) = decompress(&mut self.decomp, header, &mut cursor, flags);
+        self.status = status;
+        self.header_done = true;
+    }
+
+    //returns (consumed, written)
+    fn inflate(&mut self, input: &[u8], output: &mut [u8]) -> (usize, usize) {
+        let mut consumed = 0;
+        if !self.header_done {
+            while consumed < input.len() && self.header.len() < self.header_len() {
+                self.header.push(input[consumed]);
+                consumed += 1;
+            }
+            if self.header.len() < self.header_len() || self.need_dict() {
+                return (consumed, 0);
+            }
+            let header = self.header.clone();
+            self.feed_header(&header);
+        }
+
+        let mut written = self.flush(output);
+        while self.avail == 0 && written < output.len() && !self.finished() && !self.failed() {
+            let flags = self.flags();
+            let mut cursor = Cursor::new(&mut self.window[..]);
+            cursor.set_position(self.pos as u64);
+            let (status, n_in, n_out) =
+                decompress(&mut self.decomp, &input[consumed..], &mut cursor, flags);
+            consumed += n_in;
+            self.avail = n_out;
+            self.status = status;
+            written += self.flush(&mut output[written..]);
+
+            //the window wrapped, anything else needs the caller
+            if status != TINFLStatus::HasMoreOutput {
+                break;
+            }
+        }
+        (consumed, written)
+    }
+
+    fn flush(&mut self, output: &mut [u8]) -> usize {
+        let n = cmp::min(self.avail, output.len());
+        output[..n].copy_from_slice(&self.window[self.pos..self.pos + n]);
+        self.avail -= n;
+        self.pos = (self.pos + n) & WINDOW_MASK;
+        n
+    }
+
+    fn adler(&self) -> u32 {
+        self.dict_id()
+            .or_else(|| self.decomp.adler32())
+            .unwrap_or(1)
+    }
+}
+
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_init(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let nowrap = args.first().unwrap().extract_int() != 0;
+    let format = if nowrap {
+        DataFormat::Raw
+    } else {
+        DataFormat::Zlib
+    };
+    let inflate = Box::new(Inflate::new(format));
+    Ok(Some(Oop::new_long(Box::into_raw(inflate) as i64)))
+}
+
+fn jvm_setDictionary(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let inflate = to_inflate(args.first().unwrap());
+    let b = args.get(1).unwrap();
+    let off = args.get(2).unwrap().extract_int() as usize;
+    let len = args.get(3).unwrap().extract_int() as usize;
+
+    let rf = b.extract_ref();
+    let ary = rf.extract_type_array();
+    match inflate.set_dictionary(&ary.extract_bytes()[off..off + len]) {
+        Ok(()) => Ok(None),
+        Err(msg) => {
+            let ex = runtime::exception::new(cls_consts::J_ILLEGAL_ARGUMENT, Some(msg.to_string()));
+            Err(ex)
+        }
+    }
+}
+
+//consumes this.buf[off..off + len], updates off, len, finished & needDict
+fn jvm_inflateBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let inflate = to_inflate(args.get(1).unwrap());
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+
+    let in_off = get_field(this, "off", "I").extract_int() as usize;
+    let in_len = get_field(this, "len", "I").extract_int() as usize;
+    let input = {
+        let buf = get_field(this, "buf", "[B");
+        let rf = buf.extract_ref();
+        let ary = rf.extract_type_array();
+        ary.extract_bytes()[in_off..in_off + in_len].to_vec()
+    };
+
+    let rf = b.extract_ref();
+    let ary = rf.extract_mut_type_array();
+    let output = &mut ary.extract_mut_bytes()[off..off + len];
+    let (consumed, written) = inflate.inflate(&input, output);
+
+    if inflate.failed() {
+        let ex = runtime::exception::new(
+            cls_consts::J_DATA_FORMAT_EXCEPTION,
+            Some("invalid data".to_string()),
+        );
+        return Err(ex);
+    }
+
+    let consumed = consumed as i32;
+    put_field(this, "off", "I", Oop::new_int((in_off as i32) + consumed));
+    put_field(this, "len", "I", Oop::new_int((in_len as i32) - consumed));
+    if inflate.finished() {
+        put_field(this, "finished", "Z", Oop::new_int(1));
+    }
+    if inflate.need_dict() {
+        put_field(this, "needDict", "Z", Oop::new_int(1));
+    }
+    Ok(Some(Oop::new_int(written as i32)))
+}
+
+fn jvm_getAdler(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let inflate = to_inflate(args.first().unwrap());
+    Ok(Some(Oop::new_int(inflate.adler() as i32)))
+}
+
+fn jvm_reset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let inflate = to_inflate(args.first().unwrap());
+    inflate.reset();
+    Ok(None)
+}
+
+fn jvm_end(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.first().unwrap().extract_long();
+    unsafe {
+        drop(Box::from_raw(addr as *mut Inflate));
+    }
+    Ok(None)
+}
+
+fn to_inflate<'a>(addr: &Oop) -> &'a mut Inflate {
+    let addr = addr.extract_long();
+    unsafe { &mut *(addr as *mut Inflate) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICT: &[u8] = b"hello world, hello dictionary";
+    const DATA: &[u8] = b"hello world, hello dictionary!";
+
+    //zlib.compressobj(zdict = DICT)
+    const ZLIB: &[u8] = &[
+        120, 187, 162, 230, 11, 19, 203, 192, 39, 169, 8, 0, 174, 26, 11, 52,
+    ];
+    //zlib.compressobj(wbits = -15, zdict = DICT)
+    const RAW: &[u8] = &[203, 192, 39, 169, 8, 0];
+
+    #[test]
+    fn t_set_dictionary() {
+        //fed one byte at a time, the DICTID may arrive split
+        let mut inflate = Inflate::new(DataFormat::Zlib);
+        let mut out = vec![0u8; 64];
+        let mut written = 0;
+        for b in ZLIB {
+            let (consumed, n) = inflate.inflate(std::slice::from_ref(b), &mut out[written..]);
+            assert_eq!(consumed, 1);
+            written += n;
+            if inflate.need_dict() {
+                assert_eq!(inflate.adler(), 0xa2e6_0b13);
+                assert!(inflate.set_dictionary(b"wrong").is_err());
+                inflate.set_dictionary(DICT).unwrap();
+            }
+        }
+        assert!(!inflate.failed());
+        assert!(inflate.finished());
+        assert_eq!(&out[..written], DATA);
+        assert_eq!(inflate.adler(), RollingAdler32::from_buffer(DATA).hash());
+
+        let mut inflate = Inflate::new(DataFormat::Raw);
+        assert!(!inflate.need_dict());
+        inflate.set_dictionary(DICT).unwrap();
+        let (consumed, written) = inflate.inflate(RAW, &mut out);
+        assert_eq!(consumed, RAW.len());
+        assert!(inflate.finished());
+        assert_eq!(&out[..written], DATA);
+
+        //no FDICT in the header
+        inflate = Inflate::new(DataFormat::Zlib);
+        inflate.inflate(&ZLIB[..1], &mut out);
+        assert_eq!(inflate.set_dictionary(DICT), Err("dictionary not needed"));
+    }
+
+    #[test]
+    fn t_inflate() {
+        //more than the window, read out in small pieces
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i % 251) as u8 ^ (i / 997) as u8)
+            .collect();
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(&data, 6);
+        let mut inflate = Inflate::new(DataFormat::Zlib);
+        let mut out = Vec::new();
+        let mut input = &zlib[..];
+        while !inflate.finished() {
+            let mut buf = [0u8; 1000];
+            let (consumed, written) =
+                inflate.inflate(&input[..cmp::min(input.len(), 700)], &mut buf);
+            assert!(!inflate.failed());
+            input = &input[consumed..];
+            out.extend_from_slice(&buf[..written]);
+        }
+        assert!(input.is_empty());
+        assert_eq!(out, data);
+        assert_eq!(inflate.adler(), RollingAdler32::from_buffer(&data).hash());
+
+        inflate.reset();
+        assert!(!inflate.finished());
+        let (_, written) = inflate.inflate(&[0x78, 0, 0, 0], &mut [0u8; 16]);
+        assert_eq!(written, 0);
+        assert!(inflate.failed());
+    }
+}
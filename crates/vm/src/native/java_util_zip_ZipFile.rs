@@ -0,0 +1,527 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::error_message;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{Oop, OopPtr};
+use crate::runtime;
+use classfile::consts as cls_consts;
+use rustc_hash::FxHashMap;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()V", Box::new(jvm_initIDs)),
+        new_fn("open", "(Ljava/lang/String;IJZ)J", Box::new(jvm_open)),
+        new_fn("getTotal", "(J)I", Box::new(jvm_getTotal)),
+        new_fn("startsWithLOC", "(J)Z", Box::new(jvm_startsWithLOC)),
+        new_fn("getEntry", "(J[BZ)J", Box::new(jvm_getEntry)),
+        new_fn("freeEntry", "(JJ)V", Box::new(jvm_freeEntry)),
+        new_fn("getNextEntry", "(JI)J", Box::new(jvm_getNextEntry)),
+        new_fn("close", "(J)V", Box::new(jvm_close)),
+        new_fn("read", "(JJJ[BII)I", Box::new(jvm_read)),
+        new_fn("getEntryTime", "(J)J", Box::new(jvm_getEntryTime)),
+        new_fn("getEntryCrc", "(J)J", Box::new(jvm_getEntryCrc)),
+        new_fn("getEntryCSize", "(J)J", Box::new(jvm_getEntryCSize)),
+        new_fn("getEntrySize", "(J)J", Box::new(jvm_getEntrySize)),
+        new_fn("getEntryMethod", "(J)I", Box::new(jvm_getEntryMethod)),
+        new_fn("getEntryFlag", "(J)I", Box::new(jvm_getEntryFlag)),
+        new_fn("getCommentBytes", "(J)[B", Box::new(jvm_getCommentBytes)),
+        new_fn("getEntryBytes", "(JI)[B", Box::new(jvm_getEntryBytes)),
+        new_fn(
+            "getZipMessage",
+            "(J)Ljava/lang/String;",
+            Box::new(jvm_getZipMessage),
+        ),
+    ]
+}
+
+/*
+The jzfile & jzentry of the jdk
+
+The central directory is read at open, the entries are looked up by the raw
+name bytes. ZipFile reads the raw data of an entry, and inflates it with
+Inflater itself, so only the offset of the data is needed here.
+*/
+
+const LOC_SIG: u32 = 0x0403_4b50;
+const CEN_SIG: u32 = 0x0201_4b50;
+const END_SIG: u32 = 0x0605_4b50;
+const ZIP64_END_SIG: u32 = 0x0606_4b50;
+const ZIP64_LOC_SIG: u32 = 0x0706_4b50;
+
+const LOC_HDR: usize = 30;
+const CEN_HDR: usize = 46;
+const END_HDR: usize = 22;
+const ZIP64_END_HDR: usize = 56;
+const ZIP64_LOC_HDR: usize = 20;
+const END_MAX_COMMENT: usize = 0xFFFF;
+
+const ZIP64_EXTID: u16 = 0x0001;
+
+//ZipFile.JZENTRY_*
+const JZENTRY_NAME: i32 = 0;
+const JZENTRY_EXTRA: i32 = 1;
+const JZENTRY_COMMENT: i32 = 2;
+
+pub struct JzFile {
+    file: File,
+    entries: Vec<JzEntry>,
+    names: FxHashMap<Vec<u8>, usize>,
+    comment: Vec<u8>,
+    starts_with_loc: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct JzEntry {
+    name: Vec<u8>,
+    extra: Vec<u8>,
+    comment: Vec<u8>,
+    //msdos date & time
+    time: u32,
+    crc: u32,
+    csize: u64,
+    size: u64,
+    method: u16,
+    flag: u16,
+    loc_pos: u64,
+    //the offset of the data, known by the first read
+    data_pos: Option<u64>,
+}
+
+impl JzFile {
+    pub fn open(path: &str) -> io::Result<JzFile> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        //END is at the end, followed by the comment
+        let tail_len = len.min((END_HDR + END_MAX_COMMENT) as u64) as usize;
+        let tail_pos = len - tail_len as u64;
+        let tail = read_exact_at(&file, tail_pos, tail_len)?;
+        let end = (0..=tail_len.saturating_sub(END_HDR))
+            .rev()
+            .find(|&i| {
+                u32_at(&tail, i) == END_SIG
+                    && i + END_HDR + u16_at(&tail, i + 20) as usize <= tail_len
+            })
+            .ok_or_else(|| zip_error("zip END header not found"))?;
+        let end_pos = tail_pos + end as u64;
+
+        let comment_len = u16_at(&tail, end + 20) as usize;
+        let comment = tail[end + END_HDR..end + END_HDR + comment_len].to_vec();
+        let mut total = u16_at(&tail, end + 10) as u64;
+        let mut cen_len = u32_at(&tail, end + 12) as u64;
+        let mut cen_off = u32_at(&tail, end + 16) as u64;
+        let mut cen_end = end_pos;
+
+        //ZIP64 END, located by the locator before END
+        if end_pos >= ZIP64_LOC_HDR as u64 {
+            let loc = read_exact_at(&file, end_pos - ZIP64_LOC_HDR as u64, ZIP64_LOC_HDR)?;
+            if u32_at(&loc, 0) == ZIP64_LOC_SIG {
+                let end64_pos = u64_at(&loc, 8);
+                let end64 = read_exact_at(&file, end64_pos, ZIP64_END_HDR)?;
+                if u32_at(&end64, 0) != ZIP64_END_SIG {
+                    return Err(zip_error("invalid zip64 END header"));
+                }
+                total = u64_at(&end64, 32);
+                cen_len = u64_at(&end64, 40);
+                cen_off = u64_at(&end64, 48);
+                cen_end = end64_pos;
+            }
+        }
+
+        if cen_len > cen_end {
+            return Err(zip_error("invalid END header (bad central directory size)"));
+        }
+        let cen_pos = cen_end - cen_len;
+        //the bytes prepended to the zip, such as a self-extracting stub
+        let base = cen_pos
+            .checked_sub(cen_off)
+            .ok_or_else(|| zip_error("invalid END header (bad central directory offset)"))?;
+        let cen = read_exact_at(&file, cen_pos, cen_len as usize)?;
+
+        let mut entries = Vec::with_capacity(total as usize);
+        let mut names = FxHashMap::default();
+        let mut pos = 0;
+        while pos + CEN_HDR <= cen.len() {
+            if u32_at(&cen, pos) != CEN_SIG {
+                return Err(zip_error("invalid CEN header (bad signature)"));
+            }
+            let name_len = u16_at(&cen, pos + 28) as usize;
+            let extra_len = u16_at(&cen, pos + 30) as usize;
+            let comment_len = u16_at(&cen, pos + 32) as usize;
+            let next = pos + CEN_HDR + name_len + extra_len + comment_len;
+            if next > cen.len() {
+                return Err(zip_error("invalid CEN header (bad header size)"));
+            }
+
+            let name = cen[pos + CEN_HDR..pos + CEN_HDR + name_len].to_vec();
+            let extra_pos = pos + CEN_HDR + name_len;
+            let extra = cen[extra_pos..extra_pos + extra_len].to_vec();
+            let mut entry = JzEntry {
+                extra,
+                comment: cen[extra_pos + extra_len..next].to_vec(),
+                time: u32_at(&cen, pos + 12),
+                crc: u32_at(&cen, pos + 16),
+                csize: u32_at(&cen, pos + 20) as u64,
+                size: u32_at(&cen, pos + 24) as u64,
+                method: u16_at(&cen, pos + 10),
+                flag: u16_at(&cen, pos + 8),
+                loc_pos: u32_at(&cen, pos + 42) as u64,
+                data_pos: None,
+                name: name.clone(),
+            };
+            entry.read_zip64_extra();
+            entry.loc_pos += base;
+
+            names.insert(name, entries.len());
+            entries.push(entry);
+            pos = next;
+        }
+
+        let starts_with_loc = match read_exact_at(&file, 0, 4) {
+            Ok(sig) => u32_at(&sig, 0) == LOC_SIG,
+            Err(_) => false,
+        };
+
+        Ok(JzFile {
+            file,
+            entries,
+            names,
+            comment,
+            starts_with_loc,
+        })
+    }
+
+    pub fn get_entry(&self, name: &[u8], add_slash: bool) -> Option<&JzEntry> {
+        let i = match self.names.get(name) {
+            Some(i) => Some(*i),
+            None if add_slash && !name.ends_with(b"/") => {
+                let mut dir = name.to_vec();
+                dir.push(b'/');
+                self.names.get(&dir).cloned()
+            }
+            None => None,
+        };
+        i.map(|i| &self.entries[i])
+    }
+
+    pub fn entries(&self) -> &[JzEntry] {
+        &self.entries
+    }
+
+    //the raw data of the entry at 'pos'
+    pub fn read(&self, entry: &mut JzEntry, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let data_pos = match entry.data_pos {
+            Some(data_pos) => data_pos,
+            None => {
+                let loc = read_exact_at(&self.file, entry.loc_pos, LOC_HDR)?;
+                if u32_at(&loc, 0) != LOC_SIG {
+                    return Err(zip_error("invalid LOC header (bad signature)"));
+                }
+                let data_pos = entry.loc_pos
+                    + LOC_HDR as u64
+                    + u16_at(&loc, 26) as u64
+                    + u16_at(&loc, 28) as u64;
+                entry.data_pos = Some(data_pos);
+                data_pos
+            }
+        };
+        self.file.read_at(buf, data_pos + pos)
+    }
+}
+
+impl JzEntry {
+    pub fn name(&self) -> &[u8] {
+        &self.name
+    }
+
+    //the values of 0xFFFFFFFF in CEN are in the zip64 extra, in this order
+    fn read_zip64_extra(&mut self) {
+        let mut pos = 0;
+        while pos + 4 <= self.extra.len() {
+            let id = u16_at(&self.extra, pos);
+            let len = u16_at(&self.extra, pos + 2) as usize;
+            let data = pos + 4;
+            if data + len > self.extra.len() {
+                break;
+            }
+
+            if id == ZIP64_EXTID {
+                let mut off = data;
+                for v in [&mut self.size, &mut self.csize, &mut self.loc_pos].iter_mut() {
+                    if **v == 0xFFFF_FFFF && off + 8 <= data + len {
+                        **v = u64_at(&self.extra, off);
+                        off += 8;
+                    }
+                }
+                break;
+            }
+
+            pos = data + len;
+        }
+    }
+}
+
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+fn jvm_open(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = OopPtr::java_lang_string(args.first().unwrap().extract_ref());
+    match JzFile::open(&name) {
+        Ok(jzfile) => Ok(Some(Oop::new_long(Box::into_raw(Box::new(jzfile)) as i64))),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let ex = runtime::exception::new(cls_consts::J_ZIP_EXCEPTION, Some(e.to_string()));
+            Err(ex)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let msg = format!("{} ({})", name, error_message(&e));
+            let ex = runtime::exception::new(cls_consts::J_FILE_NOT_FOUND_EXCEPTION, Some(msg));
+            Err(ex)
+        }
+        Err(e) => {
+            let msg = error_message(&e);
+            let ex = runtime::exception::new(cls_consts::J_ZIP_EXCEPTION, Some(msg));
+            Err(ex)
+        }
+    }
+}
+
+fn jvm_getTotal(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    Ok(Some(Oop::new_int(jzfile.entries.len() as i32)))
+}
+
+fn jvm_startsWithLOC(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    Ok(Some(Oop::new_int(jzfile.starts_with_loc as i32)))
+}
+
+//0 if not found, the entry is freed by freeEntry
+fn jvm_getEntry(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    let name = args.get(1).unwrap();
+    let add_slash = args.get(2).unwrap().extract_int() != 0;
+
+    let rf = name.extract_ref();
+    let ary = rf.extract_type_array();
+    let entry = jzfile.get_entry(ary.extract_bytes(), add_slash);
+    Ok(Some(new_jzentry(entry)))
+}
+
+fn jvm_freeEntry(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    if addr != 0 {
+        unsafe {
+            drop(Box::from_raw(addr as *mut JzEntry));
+        }
+    }
+    Ok(None)
+}
+
+fn jvm_getNextEntry(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    let i = args.get(1).unwrap().extract_int();
+    let entry = if i >= 0 {
+        jzfile.entries.get(i as usize)
+    } else {
+        None
+    };
+    Ok(Some(new_jzentry(entry)))
+}
+
+fn jvm_close(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.first().unwrap().extract_long();
+    unsafe {
+        drop(Box::from_raw(addr as *mut JzFile));
+    }
+    Ok(None)
+}
+
+fn jvm_read(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    let entry = to_jzentry(args.get(1).unwrap());
+    let pos = args.get(2).unwrap().extract_long() as u64;
+    let b = args.get(3).unwrap();
+    let off = args.get(4).unwrap().extract_int() as usize;
+    let len = args.get(5).unwrap().extract_int() as usize;
+
+    let mut buf = vec![0u8; len];
+    match jzfile.read(entry, pos, &mut buf) {
+        Ok(n) => {
+            let rf = b.extract_ref();
+            let ary = rf.extract_mut_type_array();
+            let ary = ary.extract_mut_bytes();
+            ary[off..off + n].copy_from_slice(&buf[..n]);
+            Ok(Some(Oop::new_int(n as i32)))
+        }
+        Err(e) => {
+            let ex = runtime::exception::new(cls_consts::J_ZIP_EXCEPTION, Some(e.to_string()));
+            Err(ex)
+        }
+    }
+}
+
+fn jvm_getEntryTime(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    Ok(Some(Oop::new_long(entry.time as i64)))
+}
+
+fn jvm_getEntryCrc(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    Ok(Some(Oop::new_long(entry.crc as i64)))
+}
+
+fn jvm_getEntryCSize(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    //as the jdk, stored entries have no compressed size
+    let csize = if entry.method == 0 {
+        entry.size
+    } else {
+        entry.csize
+    };
+    Ok(Some(Oop::new_long(csize as i64)))
+}
+
+fn jvm_getEntrySize(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    Ok(Some(Oop::new_long(entry.size as i64)))
+}
+
+fn jvm_getEntryMethod(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    Ok(Some(Oop::new_int(entry.method as i32)))
+}
+
+fn jvm_getEntryFlag(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    Ok(Some(Oop::new_int(entry.flag as i32)))
+}
+
+//null if no comment
+fn jvm_getCommentBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let jzfile = to_jzfile(args.first().unwrap());
+    Ok(Some(new_bytes(&jzfile.comment)))
+}
+
+fn jvm_getEntryBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let entry = to_jzentry(args.first().unwrap());
+    let v = match args.get(1).unwrap().extract_int() {
+        JZENTRY_NAME => Oop::new_byte_ary2(entry.name.clone()),
+        JZENTRY_EXTRA => new_bytes(&entry.extra),
+        JZENTRY_COMMENT => new_bytes(&entry.comment),
+        _ => Oop::Null,
+    };
+    Ok(Some(v))
+}
+
+//the errors are thrown by open & read
+fn jvm_getZipMessage(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::Null))
+}
+
+pub fn to_jzfile<'a>(addr: &Oop) -> &'a JzFile {
+    let addr = addr.extract_long();
+    unsafe { &*(addr as *const JzFile) }
+}
+
+fn to_jzentry<'a>(addr: &Oop) -> &'a mut JzEntry {
+    let addr = addr.extract_long();
+    unsafe { &mut *(addr as *mut JzEntry) }
+}
+
+fn new_jzentry(entry: Option<&JzEntry>) -> Oop {
+    let addr = match entry {
+        Some(entry) => Box::into_raw(Box::new(entry.clone())) as i64,
+        None => 0,
+    };
+    Oop::new_long(addr)
+}
+
+fn new_bytes(v: &[u8]) -> Oop {
+    if v.is_empty() {
+        Oop::Null
+    } else {
+        Oop::new_byte_ary2(v.to_vec())
+    }
+}
+
+fn zip_error(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_exact_at(file: &File, pos: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    file.read_exact_at(&mut buf, pos)?;
+    Ok(buf)
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    let mut v = [0u8; 4];
+    v.copy_from_slice(&buf[pos..pos + 4]);
+    u32::from_le_bytes(v)
+}
+
+fn u64_at(buf: &[u8], pos: usize) -> u64 {
+    let mut v = [0u8; 8];
+    v.copy_from_slice(&buf[pos..pos + 8]);
+    u64::from_le_bytes(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::CompressionMethod;
+
+    #[test]
+    fn t_jzfile() {
+        let path = std::env::temp_dir().join(format!("t_jzfile_{}.zip", std::process::id()));
+        let content = b"Manifest-Version: 1.0\n".repeat(16);
+        {
+            let mut w = zip::ZipWriter::new(File::create(&path).unwrap());
+            let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+            w.add_directory("META-INF/", stored).unwrap();
+            w.start_file("META-INF/MANIFEST.MF", stored).unwrap();
+            w.write_all(&content).unwrap();
+            let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+            w.start_file("a/b.txt", deflated).unwrap();
+            w.write_all(&content).unwrap();
+            w.finish().unwrap();
+        }
+
+        let jzfile = JzFile::open(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(jzfile.entries().len(), 3);
+        assert!(jzfile.starts_with_loc);
+        assert!(jzfile.get_entry(b"META-INF", false).is_none());
+        assert!(jzfile.get_entry(b"META-INF", true).is_some());
+        assert!(jzfile.get_entry(b"a/c.txt", true).is_none());
+
+        let mut entry = jzfile
+            .get_entry(b"META-INF/MANIFEST.MF", false)
+            .cloned()
+            .unwrap();
+        assert_eq!(entry.method, 0);
+        assert_eq!(entry.size, content.len() as u64);
+        let mut buf = vec![0u8; entry.size as usize];
+        assert_eq!(jzfile.read(&mut entry, 0, &mut buf).unwrap(), buf.len());
+        assert_eq!(buf, content);
+
+        let mut entry = jzfile.get_entry(b"a/b.txt", false).cloned().unwrap();
+        assert_eq!(entry.method, 8);
+        let mut buf = vec![0u8; entry.csize as usize];
+        assert_eq!(jzfile.read(&mut entry, 0, &mut buf).unwrap(), buf.len());
+        let data = miniz_oxide::inflate::decompress_to_vec(&buf).unwrap();
+        assert_eq!(data, content);
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data);
+        assert_eq!(hasher.finalize(), entry.crc);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::native::jni::refs::{self, JObject};
+use crate::oop::Oop;
+use classfile::SignatureType;
+
+/*
+Call a C function with the native calling convention of the platform
+
+The args are classified as the C compiler does, integers & pointers go to
+the general purpose registers, float & double go to the fp registers, and the
+rest are passed on the stack in order. The function is then called through a
+fixed prototype which covers all the registers and STACK_SLOTS stack slots,
+the unused ones are ignored by the callee.
+
+A float is passed in the low 32 bits of a fp register or a stack slot, an
+integer is sign extended to 64 bits, so they are good for the narrower C types.
+
+Supported: x86_64 System V, aarch64 AAPCS64 (not Apple's variant), the call
+fails on the other platforms.
+*/
+
+#[cfg(target_arch = "x86_64")]
+const GPR_COUNT: usize = 6;
+#[cfg(not(target_arch = "x86_64"))]
+const GPR_COUNT: usize = 8;
+const FPR_COUNT: usize = 8;
+const STACK_SLOTS: usize = 16;
+
+#[derive(Default)]
+pub struct Args {
+    gpr: [i64; GPR_COUNT],
+    fpr: [f64; FPR_COUNT],
+    stack: [i64; STACK_SLOTS],
+    n_gpr: usize,
+    n_fpr: usize,
+    n_stack: usize,
+}
+
+impl Args {
+    pub fn push_int(&mut self, v: i64) -> Result<(), ()> {
+        if self.n_gpr < GPR_COUNT {
+            self.gpr[self.n_gpr] = v;
+            self.n_gpr += 1;
+            Ok(())
+        } else {
+            self.push_stack(v)
+        }
+    }
+
+    pub fn push_float(&mut self, v: f32) -> Result<(), ()> {
+        let bits = v.to_bits() as u64;
+        if self.n_fpr < FPR_COUNT {
+            self.fpr[self.n_fpr] = f64::from_bits(bits);
+            self.n_fpr += 1;
+            Ok(())
+        } else {
+            self.push_stack(bits as i64)
+        }
+    }
+
+    pub fn push_double(&mut self, v: f64) -> Result<(), ()> {
+        if self.n_fpr < FPR_COUNT {
+            self.fpr[self.n_fpr] = v;
+            self.n_fpr += 1;
+            Ok(())
+        } else {
+            self.push_stack(v.to_bits() as i64)
+        }
+    }
+
+    pub fn push_ptr(&mut self, v: JObject) -> Result<(), ()> {
+        self.push_int(v as i64)
+    }
+
+    //push a java value, references are turned into local references
+    pub fn push_oop(&mut self, t: &SignatureType, v: &Oop) -> Result<(), ()> {
+        match t {
+            SignatureType::Boolean
+            | SignatureType::Byte
+            | SignatureType::Char
+            | SignatureType::Short
+            | SignatureType::Int => self.push_int(v.extract_int() as i64),
+            SignatureType::Long => self.push_int(v.extract_long()),
+            SignatureType::Float => self.push_float(v.extract_float()),
+            SignatureType::Double => self.push_double(v.extract_double()),
+            SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+                self.push_ptr(refs::new_local(v))
+            }
+            SignatureType::Void => unreachable!(),
+        }
+    }
+
+    fn push_stack(&mut self, v: i64) -> Result<(), ()> {
+        if self.n_stack < STACK_SLOTS {
+            self.stack[self.n_stack] = v;
+            self.n_stack += 1;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Call 'f', and convert the result by 'retype'
+///
+/// Err if the calling convention of the platform is not supported.
+///
+/// # Safety
+///
+/// 'f' should be a C function taking the args, and returning 'retype'.
+pub unsafe fn call(f: usize, args: &Args, retype: &SignatureType) -> Result<Option<Oop>, ()> {
+    let v = match retype {
+        SignatureType::Float => {
+            let v: f64 = invoke(f, args)?;
+            Oop::new_float(f32::from_bits(v.to_bits() as u32))
+        }
+        SignatureType::Double => Oop::new_double(invoke(f, args)?),
+        _ => {
+            let v: i64 = invoke(f, args)?;
+            match retype {
+                SignatureType::Boolean => Oop::new_int((v as u8 != 0) as i32),
+                SignatureType::Byte => Oop::new_int(v as i8 as i32),
+                SignatureType::Char => Oop::new_int(v as u16 as i32),
+                SignatureType::Short => Oop::new_int(v as i16 as i32),
+                SignatureType::Int => Oop::new_int(v as i32),
+                SignatureType::Long => Oop::new_long(v),
+                SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+                    refs::from_jobject(v as JObject)
+                }
+                SignatureType::Void => return Ok(None),
+                _ => unreachable!(),
+            }
+        }
+    };
+
+    Ok(Some(v))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[rustfmt::skip]
+type Prototype<R> = extern "C" fn(
+    i64, i64, i64, i64, i64, i64,
+    f64, f64, f64, f64, f64, f64, f64, f64,
+    i64, i64, i64, i64, i64, i64, i64, i64,
+    i64, i64, i64, i64, i64, i64, i64, i64,
+) -> R;
+
+#[cfg(not(target_arch = "x86_64"))]
+#[rustfmt::skip]
+type Prototype<R> = extern "C" fn(
+    i64, i64, i64, i64, i64, i64, i64, i64,
+    f64, f64, f64, f64, f64, f64, f64, f64,
+    i64, i64, i64, i64, i64, i64, i64, i64,
+    i64, i64, i64, i64, i64, i64, i64, i64,
+) -> R;
+
+#[cfg(target_arch = "x86_64")]
+#[rustfmt::skip]
+unsafe fn invoke<R>(f: usize, a: &Args) -> Result<R, ()> {
+    let f: Prototype<R> = std::mem::transmute(f);
+    let (g, x, s) = (&a.gpr, &a.fpr, &a.stack);
+    Ok(f(
+        g[0], g[1], g[2], g[3], g[4], g[5],
+        x[0], x[1], x[2], x[3], x[4], x[5], x[6], x[7],
+        s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7],
+        s[8], s[9], s[10], s[11], s[12], s[13], s[14], s[15],
+    ))
+}
+
+#[cfg(all(target_arch = "aarch64", not(target_os = "macos")))]
+#[rustfmt::skip]
+unsafe fn invoke<R>(f: usize, a: &Args) -> Result<R, ()> {
+    let f: Prototype<R> = std::mem::transmute(f);
+    let (g, x, s) = (&a.gpr, &a.fpr, &a.stack);
+    Ok(f(
+        g[0], g[1], g[2], g[3], g[4], g[5], g[6], g[7],
+        x[0], x[1], x[2], x[3], x[4], x[5], x[6], x[7],
+        s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7],
+        s[8], s[9], s[10], s[11], s[12], s[13], s[14], s[15],
+    ))
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", not(target_os = "macos"))
+)))]
+unsafe fn invoke<R>(_f: usize, _a: &Args) -> Result<R, ()> {
+    Err(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::too_many_arguments)]
+    extern "C" fn mixed(
+        a: i32,
+        b: f32,
+        c: i64,
+        d: f64,
+        e: u16,
+        f: i8,
+        g: f32,
+        h: i32,
+        i: i32,
+        j: f64,
+        k: f64,
+        l: f64,
+        m: f64,
+        n: f64,
+        o: f32,
+        p: i64,
+        q: i32,
+        r: i32,
+        s: f64,
+    ) -> f64 {
+        let ints = [
+            a as i64, c, e as i64, f as i64, h as i64, i as i64, p, q as i64, r as i64,
+        ];
+        let floats = [b as f64, d, g as f64, j, k, l, m, n, o as f64, s];
+        let mut v = 0.0;
+        for (n, it) in ints.iter().enumerate() {
+            v += (n + 1) as f64 * *it as f64;
+        }
+        for (n, it) in floats.iter().enumerate() {
+            v += (n + 10) as f64 * *it;
+        }
+        v
+    }
+
+    extern "C" fn neg_short(v: i16) -> i16 {
+        -v
+    }
+
+    #[test]
+    fn t_call() {
+        let mut args = Args::default();
+        let types = "(IFJDCBFIIDDDDDFJIID)D";
+        let sig = class_parser::MethodSignature::new(types.as_bytes());
+        let values = vec![
+            Oop::new_int(-1),
+            Oop::new_float(1.5),
+            Oop::new_long(1 << 40),
+            Oop::new_double(2.25),
+            Oop::new_int(0xffff),
+            Oop::new_int(-128),
+            Oop::new_float(-3.5),
+            Oop::new_int(7),
+            Oop::new_int(8),
+            Oop::new_double(9.0),
+            Oop::new_double(10.0),
+            Oop::new_double(11.0),
+            Oop::new_double(12.0),
+            Oop::new_double(13.0),
+            Oop::new_float(14.0),
+            Oop::new_long(-15),
+            Oop::new_int(16),
+            Oop::new_int(17),
+            Oop::new_double(18.0),
+        ];
+        for (t, v) in sig.args.iter().zip(values.iter()) {
+            args.push_oop(t, v).unwrap();
+        }
+
+        let r = unsafe { call(mixed as *const () as usize, &args, &sig.retype) }
+            .unwrap()
+            .unwrap();
+        let expected = mixed(
+            -1,
+            1.5,
+            1 << 40,
+            2.25,
+            0xffff,
+            -128,
+            -3.5,
+            7,
+            8,
+            9.0,
+            10.0,
+            11.0,
+            12.0,
+            13.0,
+            14.0,
+            -15,
+            16,
+            17,
+            18.0,
+        );
+        assert_eq!(r.extract_double(), expected);
+
+        let mut args = Args::default();
+        args.push_int(300).unwrap();
+        let r = unsafe {
+            call(
+                neg_short as *const () as usize,
+                &args,
+                &SignatureType::Short,
+            )
+        }
+        .unwrap()
+        .unwrap();
+        assert_eq!(r.extract_int(), -300);
+    }
+}
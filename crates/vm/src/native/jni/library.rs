@@ -0,0 +1,176 @@
+use crate::native::jni::{self, refs};
+use crate::runtime::ClassLoader;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+use std::sync::Mutex;
+
+type JNIOnLoad = extern "C" fn(vm: *mut c_void, reserved: *mut c_void) -> i32;
+
+struct Library {
+    path: String,
+    //the loader of the class which loaded it, None for the bootstrap loader
+    loader: Option<ClassLoader>,
+    //the handle returned by dlopen
+    handle: usize,
+}
+
+lazy_static! {
+    //in the loading order, the symbols of a loader are looked up from its first one
+    static ref LIBRARIES: Mutex<Vec<Library>> = Mutex::new(Vec::new());
+}
+
+/// dlopen the shared library at 'path' for 'loader', and run its JNI_OnLoad
+///
+/// Loading the same library again by the same loader does nothing, a library
+/// can't be loaded by two loaders, as the natives of a class are only looked
+/// up in the libraries of its loader. The library is unloaded if
+/// JNI_OnLoad requires an unsupported version. Nothing is loaded without the
+/// function table of JNIEnv, see jni::init.
+pub fn load(path: &str, loader: Option<ClassLoader>) -> Result<(), String> {
+    if !jni::is_inited() {
+        return Err(format!(
+            "Can't load library: {}, JNI is not available",
+            path
+        ));
+    }
+
+    {
+        let libs = LIBRARIES.lock().unwrap();
+        match libs.iter().find(|it| it.path == path) {
+            Some(it) if it.loader == loader => return Ok(()),
+            Some(_) => {
+                return Err(format!(
+                    "Native Library {} already loaded in another classloader",
+                    path
+                ))
+            }
+            None => (),
+        }
+    }
+
+    let c_path = CString::new(path).map_err(|_| format!("Invalid library path: {}", path))?;
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_LAZY) };
+    if handle.is_null() {
+        return Err(dl_error().unwrap_or_else(|| format!("Can't load library: {}", path)));
+    }
+
+    {
+        let mut libs = LIBRARIES.lock().unwrap();
+        libs.push(Library {
+            path: path.to_string(),
+            loader,
+            handle: handle as usize,
+        });
+    }
+    info!("load library: {}", path);
+
+    //the lock is released, JNI_OnLoad usually calls RegisterNatives
+    let on_load = unsafe { libc::dlsym(handle, b"JNI_OnLoad\0".as_ptr() as *const _) };
+    if !on_load.is_null() {
+        let on_load: JNIOnLoad = unsafe { std::mem::transmute(on_load) };
+        refs::push_local_frame();
+        let version = on_load(jni::java_vm(), std::ptr::null_mut());
+        refs::pop_local_frame();
+
+        if !jni::is_supported_version(version) {
+            unload(path, on_load as usize);
+            return Err(format!(
+                "Unsupported JNI version 0x{:x} required by {}",
+                version, path
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+//forget the library & the natives registered by its JNI_OnLoad, then dlclose it
+fn unload(path: &str, on_load: usize) {
+    let handle = {
+        let mut libs = LIBRARIES.lock().unwrap();
+        let i = libs.iter().position(|it| it.path == path).unwrap();
+        libs.remove(i).handle
+    };
+
+    if let Some(base) = image_base(on_load) {
+        jni::unregister_library(|fnptr| image_base(fnptr) == Some(base));
+    }
+
+    unsafe {
+        libc::dlclose(handle as *mut c_void);
+    }
+    info!("unload library: {}", path);
+}
+
+//the address the library of 'addr' is loaded at
+fn image_base(addr: usize) -> Option<usize> {
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    let found = unsafe { libc::dladdr(addr as *const c_void, &mut info) };
+    if found == 0 {
+        None
+    } else {
+        Some(info.dli_fbase as usize)
+    }
+}
+
+/// Find the symbol in the libraries loaded by 'loader'
+pub fn find_symbol(loader: Option<ClassLoader>, name: &str) -> Option<usize> {
+    let name = CString::new(name).ok()?;
+    let libs = LIBRARIES.lock().unwrap();
+    libs.iter().filter(|it| it.loader == loader).find_map(|it| {
+        let sym = unsafe { libc::dlsym(it.handle as *mut c_void, name.as_ptr()) };
+        if sym.is_null() {
+            None
+        } else {
+            Some(sym as usize)
+        }
+    })
+}
+
+fn dl_error() -> Option<String> {
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        None
+    } else {
+        let err = unsafe { CStr::from_ptr(err) };
+        Some(err.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the path of libc, it has no JNI_OnLoad
+    fn libc_path() -> String {
+        unsafe {
+            let strlen = libc::dlsym(libc::RTLD_DEFAULT, b"strlen\0".as_ptr() as *const _);
+            let mut info: libc::Dl_info = std::mem::zeroed();
+            assert_ne!(libc::dladdr(strlen, &mut info), 0);
+            CStr::from_ptr(info.dli_fname)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    #[test]
+    fn t_symbol_by_loader() {
+        static FUNCTIONS: u8 = 0;
+        if !jni::is_inited() {
+            jni::init(
+                &FUNCTIONS as *const u8 as *const c_void,
+                std::ptr::null_mut(),
+            );
+        }
+
+        let path = libc_path();
+        let loader = Some(ClassLoader::User(10009));
+        assert!(load(&path, loader).is_ok());
+        assert!(load(&path, loader).is_ok());
+        assert!(load(&path, None).is_err());
+
+        assert!(find_symbol(loader, "strlen").is_some());
+        assert!(find_symbol(None, "strlen").is_none());
+        assert!(find_symbol(Some(ClassLoader::User(10010)), "strlen").is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use classfile::constant_pool::construct_string_raw;
+use std::fmt::Write;
+
+/*
+JNI native method names, see "Resolving Native Method Names" of the JNI spec
+
+  short name: Java_<mangled class name>_<mangled method name>
+  long name:  <short name>__<mangled argument signature>
+
+  '/' => '_'
+  '_' => "_1"
+  ';' => "_2"
+  '[' => "_3"
+  other non alphanumeric chars => "_0xxxx", the UTF-16 code in lowercase hex
+*/
+
+pub fn short_name(class: &[u8], name: &[u8]) -> String {
+    let mut s = String::from("Java_");
+    mangle(class, &mut s);
+    s.push('_');
+    mangle(name, &mut s);
+    s
+}
+
+pub fn long_name(class: &[u8], name: &[u8], desc: &[u8]) -> String {
+    let mut s = short_name(class, name);
+    s.push_str("__");
+    //the argument signature, without '(' & ')ret'
+    let end = desc.iter().position(|c| *c == b')').unwrap_or(desc.len());
+    let args = if desc.first() == Some(&b'(') {
+        &desc[1..end]
+    } else {
+        &desc[..end]
+    };
+    mangle(args, &mut s);
+    s
+}
+
+fn mangle(v: &[u8], out: &mut String) {
+    for c in construct_string_raw(v) {
+        match c {
+            0x2F => out.push('_'),
+            0x5F => out.push_str("_1"),
+            0x3B => out.push_str("_2"),
+            0x5B => out.push_str("_3"),
+            c if c < 0x80 && (c as u8).is_ascii_alphanumeric() => out.push(c as u8 as char),
+            c => {
+                let _ = write!(out, "_0{:04x}", c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_mangle() {
+        assert_eq!(short_name(b"Hello", b"sayHi"), "Java_Hello_sayHi");
+        assert_eq!(
+            short_name(b"com/foo/my_lib/Native", b"do_it"),
+            "Java_com_foo_my_1lib_Native_do_1it"
+        );
+        assert_eq!(
+            long_name(b"p/Q", b"f", b"(I[Ljava/lang/String;J)V"),
+            "Java_p_Q_f__I_3Ljava_lang_String_2J"
+        );
+        assert_eq!(long_name(b"p/Q", b"f", b"()V"), "Java_p_Q_f__");
+        assert_eq!(short_name(b"p/Q$R", b"f"), "Java_p_Q_00024R_f");
+        assert_eq!(
+            short_name("p/Caf\u{e9}".as_bytes(), b"f"),
+            "Java_p_Caf_000e9_f"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::native::{self, JNIEnv, JNINativeMethod};
+use crate::oop::Oop;
+use crate::runtime::method::Method;
+use crate::runtime::{exception, gc, thread, ClassLoader};
+use crate::types::ClassRef;
+use classfile::consts::{J_INTERNAL_ERROR, J_UNSATISFIED_LINK_ERROR};
+use classfile::BytesRef;
+use rustc_hash::FxHashMap;
+use std::cell::UnsafeCell;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, RwLock};
+
+mod bridge;
+pub mod library;
+mod mangle;
+pub mod refs;
+
+pub use refs::{from_jobject, new_local, JObject};
+
+/*
+Native methods implemented in user libraries (System.loadLibrary)
+
+The function table of JNIEnv & the JavaVM are provided by libjvm through
+'init', native code gets a JNIEnv* which points to the table.
+
+A native method is bound to the function registered by RegisterNatives, or
+to the symbol found by the short name, then the long name, in the libraries
+loaded by the loader of its class, the binding is cached. The classes of different loaders may have the
+same name, the natives are kept by the defining loader & the class name.
+*/
+
+pub const JNI_VERSION_1_1: i32 = 0x0001_0001;
+pub const JNI_VERSION_1_2: i32 = 0x0001_0002;
+pub const JNI_VERSION_1_4: i32 = 0x0001_0004;
+pub const JNI_VERSION_1_6: i32 = 0x0001_0006;
+pub const JNI_VERSION_1_8: i32 = 0x0001_0008;
+
+static FUNCTIONS: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+static JAVA_VM: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
+type NativeKey = (Option<ClassLoader>, BytesRef, BytesRef, BytesRef);
+
+lazy_static! {
+    //(class loader, class name, method name, method signature) -> fnptr, by RegisterNatives
+    static ref REGISTERED: RwLock<FxHashMap<NativeKey, usize>> = RwLock::new(FxHashMap::default());
+    //the resolved ones, with the fnptr
+    static ref BOUND: RwLock<FxHashMap<NativeKey, (usize, JNINativeMethod)>> = RwLock::new(FxHashMap::default());
+}
+
+//the layout of JNIEnv in C, 'struct JNINativeInterface_ *'
+#[repr(C)]
+struct JNIEnvRaw {
+    functions: *const c_void,
+}
+
+thread_local! {
+    static ENV: UnsafeCell<JNIEnvRaw> = const { UnsafeCell::new(JNIEnvRaw { functions: ptr::null() }) };
+}
+
+/// Register the function table of JNIEnv & the JavaVM
+pub fn init(functions: *const c_void, java_vm: *mut c_void) {
+    FUNCTIONS.store(functions as *mut c_void, Ordering::Relaxed);
+    JAVA_VM.store(java_vm, Ordering::Relaxed);
+}
+
+/// Whether the function table is registered, the launcher may be built without it
+pub fn is_inited() -> bool {
+    !FUNCTIONS.load(Ordering::Relaxed).is_null()
+}
+
+/// The JNIEnv* of current thread
+pub fn current_env() -> *mut c_void {
+    ENV.with(|env| {
+        let env = env.get();
+        unsafe {
+            (*env).functions = FUNCTIONS.load(Ordering::Relaxed);
+        }
+        env as *mut c_void
+    })
+}
+
+/// The JavaVM*
+pub fn java_vm() -> *mut c_void {
+    JAVA_VM.load(Ordering::Relaxed)
+}
+
+pub fn is_supported_version(version: i32) -> bool {
+    matches!(
+        version,
+        JNI_VERSION_1_1 | JNI_VERSION_1_2 | JNI_VERSION_1_4 | JNI_VERSION_1_6 | JNI_VERSION_1_8
+    )
+}
+
+/// Bind the native 'method' of 'class' to 'fnptr', for RegisterNatives
+///
+/// Returns false if there is no such native method in 'class'.
+pub fn register_native(class: &ClassRef, name: &[u8], desc: &[u8], fnptr: usize) -> bool {
+    let cls = class.get_class();
+    let name = Arc::new(Vec::from(name));
+    let desc = Arc::new(Vec::from(desc));
+    match cls.get_this_class_method(&name, &desc) {
+        Ok(mir) if mir.method.is_native() => (),
+        _ => return false,
+    }
+
+    let k = (cls.class_loader, cls.name.clone(), name, desc);
+    BOUND.write().unwrap().remove(&k);
+    REGISTERED.write().unwrap().insert(k, fnptr);
+    true
+}
+
+/// Unbind the natives of 'class' registered by RegisterNatives
+pub fn unregister_natives(class: &ClassRef) {
+    let cls = class.get_class();
+    let of_class = |k: &NativeKey| k.0 == cls.class_loader && k.1 == cls.name;
+    REGISTERED.write().unwrap().retain(|k, _| !of_class(k));
+    BOUND.write().unwrap().retain(|k, _| !of_class(k));
+}
+
+/// Unbind the natives implemented by the functions 'in_library', it's unloaded
+pub fn unregister_library(in_library: impl Fn(usize) -> bool) {
+    REGISTERED
+        .write()
+        .unwrap()
+        .retain(|_, fnptr| !in_library(*fnptr));
+    BOUND
+        .write()
+        .unwrap()
+        .retain(|_, (fnptr, _)| !in_library(*fnptr));
+}
+
+/// Find the implementation of the native 'method' in user libraries
+pub fn find_native(method: &Method) -> Option<JNINativeMethod> {
+    let class_loader = { method.class.get_class().class_loader };
+    let k = (
+        class_loader,
+        method.cls_name.clone(),
+        method.name.clone(),
+        method.desc.clone(),
+    );
+
+    if let Some((_, it)) = BOUND.read().unwrap().get(&k) {
+        return Some(it.clone());
+    }
+
+    let registered = REGISTERED.read().unwrap().get(&k).cloned();
+    let fnptr = registered.or_else(|| {
+        let short = mangle::short_name(&method.cls_name, &method.name);
+        library::find_symbol(class_loader, &short).or_else(|| {
+            let long = mangle::long_name(&method.cls_name, &method.name, &method.desc);
+            library::find_symbol(class_loader, &long)
+        })
+    })?;
+
+    let it = new_native_method(fnptr, method);
+    BOUND.write().unwrap().insert(k, (fnptr, it.clone()));
+    Some(it)
+}
+
+fn new_native_method(fnptr: usize, method: &Method) -> JNINativeMethod {
+    let signature = method.signature.clone();
+    let is_static = method.is_static();
+
+    //not in the builtin table, name & signature are not used
+    native::new_fn(
+        "",
+        "",
+        Box::new(move |env: JNIEnv, args: &[Oop]| {
+            refs::push_local_frame();
+
+            let mut c_args = bridge::Args::default();
+            let _ = c_args.push_ptr(current_env());
+            let (this, args) = if is_static {
+                let cls = env.read().unwrap().class.clone();
+                let mirror = cls.get_class().get_mirror();
+                (mirror, args)
+            } else {
+                (args[0].clone(), &args[1..])
+            };
+            let _ = c_args.push_ptr(refs::new_local(&this));
+
+            let pushed = signature
+                .args
+                .iter()
+                .zip(args.iter())
+                .try_for_each(|(t, v)| c_args.push_oop(t, v));
+
+            let r = match pushed {
+                Ok(_) => {
+                    //native code may block, gc goes on without it,
+                    //the JNI functions leave the safe region while they run
+                    gc::enter_safe_region();
+                    let v = unsafe { bridge::call(fnptr, &c_args, &signature.retype) };
+                    gc::leave_safe_region();
+                    v.map_err(|_| {
+                        let msg = "Native method call is not supported on this platform";
+                        exception::new(J_UNSATISFIED_LINK_ERROR, Some(msg.to_string()))
+                    })
+                }
+                Err(_) => {
+                    let msg = "Too many arguments for native method".to_string();
+                    Err(exception::new(J_INTERNAL_ERROR, Some(msg)))
+                }
+            };
+
+            refs::pop_local_frame();
+
+            //the exception thrown by native code is pending in thread
+            match r {
+                Ok(_) if thread::is_meet_ex() => Ok(None),
+                r => r,
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_native, register_native, unregister_library, unregister_natives};
+    use crate::oop::heap::tests::HEAP_TEST;
+    use crate::runtime::class_loader::tests::new_object;
+    use crate::runtime::gc::tests::{attach, detach};
+    use crate::runtime::method::Method;
+    use crate::runtime::{self, ClassLoader};
+    use crate::types::ClassRef;
+    use classfile::flags::{ACC_NATIVE, ACC_PUBLIC, ACC_STATIC};
+    use classfile::{ClassBuilder, Version};
+    use std::sync::Arc;
+
+    //the classes of the same name in two loaders, the natives are bound apart
+    #[test]
+    fn t_register_by_loader() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        let new_class = |loader| {
+            let mut cb = ClassBuilder::new(
+                Version {
+                    minor: 0,
+                    major: 52,
+                },
+                ACC_PUBLIC,
+                "t/N",
+                Some("java/lang/Object"),
+            );
+            cb.method(ACC_STATIC | ACC_NATIVE, "f", "()I");
+            let buf = cb.build().unwrap().to_bytes().unwrap();
+            runtime::sys_dic_put(loader, b"java/lang/Object", new_object());
+            runtime::define_class(Some(loader), None, &buf).unwrap()
+        };
+        let (a, b) = (
+            new_class(ClassLoader::User(10006)),
+            new_class(ClassLoader::User(10007)),
+        );
+        let method = |cls: &ClassRef| -> Method {
+            let (name, desc) = (Arc::new(b"f".to_vec()), Arc::new(b"()I".to_vec()));
+            let cls = cls.get_class();
+            cls.get_this_class_method(&name, &desc)
+                .unwrap()
+                .method
+                .clone()
+        };
+
+        assert!(register_native(&a, b"f", b"()I", 1));
+        assert!(!register_native(&a, b"g", b"()I", 1));
+        assert!(find_native(&method(&a)).is_some());
+        assert!(find_native(&method(&b)).is_none());
+
+        assert!(register_native(&b, b"f", b"()I", 2));
+        unregister_natives(&a);
+        assert!(find_native(&method(&a)).is_none());
+        assert!(find_native(&method(&b)).is_some());
+
+        //the library of the function 2 is unloaded
+        unregister_library(|fnptr| fnptr == 2);
+        assert!(find_native(&method(&b)).is_none());
+        detach();
+    }
+}
@@ -0,0 +1,227 @@
+use crate::oop::{heap, Oop, OopPtr};
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::{Arc, Mutex, Weak};
+
+/*
+References handed out to native code
+
+A jobject is the address of the OopPtr, the handle that keeps the object
+alive is held by:
+  local reference: the local reference frame of the current thread, the frame
+    is pushed before calling into a native method, and popped after it returns.
+  global reference: the global table, until DeleteGlobalRef, the same object
+    may be referenced several times, the references are counted.
+
+While a handle is held, the object is treated as a gc root by oop::heap.
+
+A weak global reference doesn't keep the object alive, it is the address of a
+boxed Weak<OopPtr> with the lowest bit set, which can't be the address of an
+OopPtr, it is resolved to null after the object is collected.
+*/
+
+pub type JObject = *mut c_void;
+
+const WEAK_TAG: usize = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RefType {
+    Invalid,
+    Local,
+    Global,
+    WeakGlobal,
+}
+
+thread_local! {
+    static LOCAL_FRAMES: RefCell<Vec<Vec<Arc<OopPtr>>>> = const { RefCell::new(Vec::new()) };
+}
+
+lazy_static! {
+    //jobject -> (handle, count)
+    static ref GLOBALS: Mutex<FxHashMap<usize, (Arc<OopPtr>, usize)>> = Mutex::new(FxHashMap::default());
+}
+
+pub fn push_local_frame() {
+    LOCAL_FRAMES.with(|frames| frames.borrow_mut().push(Vec::new()));
+}
+
+pub fn pop_local_frame() {
+    LOCAL_FRAMES.with(|frames| {
+        let _ = frames.borrow_mut().pop();
+    });
+}
+
+//create a local reference in the top frame
+pub fn new_local(v: &Oop) -> JObject {
+    match v {
+        Oop::Ref(rf) => {
+            let obj = Arc::as_ptr(rf) as JObject;
+            LOCAL_FRAMES.with(|frames| {
+                let mut frames = frames.borrow_mut();
+                if frames.is_empty() {
+                    frames.push(Vec::new());
+                }
+                frames.last_mut().unwrap().push(rf.clone());
+            });
+            obj
+        }
+        Oop::Null => ptr::null_mut(),
+        t => unreachable!("not a reference: {:?}", t),
+    }
+}
+
+//release one local reference to 'obj' of the top frame
+pub fn delete_local(obj: JObject) {
+    LOCAL_FRAMES.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        if let Some(frame) = frames.last_mut() {
+            let ptr = obj as *const OopPtr;
+            if let Some(pos) = frame.iter().rposition(|it| Arc::as_ptr(it) == ptr) {
+                frame.swap_remove(pos);
+            }
+        }
+    });
+}
+
+pub fn new_global(v: &Oop) -> JObject {
+    match v {
+        Oop::Ref(rf) => {
+            let obj = Arc::as_ptr(rf) as JObject;
+            let mut globals = GLOBALS.lock().unwrap();
+            let entry = globals.entry(obj as usize).or_insert((rf.clone(), 0));
+            entry.1 += 1;
+            obj
+        }
+        Oop::Null => ptr::null_mut(),
+        t => unreachable!("not a reference: {:?}", t),
+    }
+}
+
+pub fn delete_global(obj: JObject) {
+    let mut globals = GLOBALS.lock().unwrap();
+    let k = obj as usize;
+    let remove = match globals.get_mut(&k) {
+        Some(entry) => {
+            entry.1 -= 1;
+            entry.1 == 0
+        }
+        None => false,
+    };
+    if remove {
+        globals.remove(&k);
+    }
+}
+
+pub fn new_weak_global(v: &Oop) -> JObject {
+    match v {
+        Oop::Ref(rf) => {
+            let weak = Box::into_raw(Box::new(Arc::downgrade(rf)));
+            (weak as usize | WEAK_TAG) as JObject
+        }
+        Oop::Null => ptr::null_mut(),
+        t => unreachable!("not a reference: {:?}", t),
+    }
+}
+
+/// Release the weak global reference
+///
+/// # Safety
+///
+/// 'obj' should be a weak global reference created by 'new_weak_global',
+/// and not deleted yet.
+pub unsafe fn delete_weak_global(obj: JObject) {
+    if is_weak(obj) {
+        let weak = (obj as usize & !WEAK_TAG) as *mut Weak<OopPtr>;
+        drop(Box::from_raw(weak));
+    }
+}
+
+pub fn ref_type(obj: JObject) -> RefType {
+    if obj.is_null() {
+        return RefType::Invalid;
+    }
+    if is_weak(obj) {
+        return RefType::WeakGlobal;
+    }
+
+    let ptr = obj as *const OopPtr;
+    let is_local = LOCAL_FRAMES.with(|frames| {
+        let frames = frames.borrow();
+        frames
+            .iter()
+            .any(|frame| frame.iter().any(|it| Arc::as_ptr(it) == ptr))
+    });
+    if is_local {
+        RefType::Local
+    } else if GLOBALS.lock().unwrap().contains_key(&(obj as usize)) {
+        RefType::Global
+    } else {
+        RefType::Invalid
+    }
+}
+
+/// Resolve a jobject to the object
+///
+/// # Safety
+///
+/// 'obj' should be null, or a live reference created by this module.
+pub unsafe fn from_jobject(obj: JObject) -> Oop {
+    if obj.is_null() {
+        Oop::Null
+    } else if is_weak(obj) {
+        let weak = (obj as usize & !WEAK_TAG) as *const Weak<OopPtr>;
+        match heap::upgrade(&*weak) {
+            Some(rf) => Oop::Ref(rf),
+            None => Oop::Null,
+        }
+    } else {
+        let ptr = obj as *const OopPtr;
+        Arc::increment_strong_count(ptr);
+        Oop::Ref(Arc::from_raw(ptr))
+    }
+}
+
+fn is_weak(obj: JObject) -> bool {
+    obj as usize & WEAK_TAG != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oop::heap::tests::HEAP_TEST;
+
+    #[test]
+    fn t_refs() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let obj = Oop::new_int_ary2(vec![1, 2, 3]);
+        let rf = obj.extract_ref();
+
+        push_local_frame();
+        let local = new_local(&obj);
+        assert_eq!(ref_type(local), RefType::Local);
+        let global = new_global(&obj);
+        let _ = new_global(&obj);
+        let weak = new_weak_global(&obj);
+        assert_eq!(ref_type(weak), RefType::WeakGlobal);
+        assert!(Arc::ptr_eq(
+            &unsafe { from_jobject(weak) }.extract_ref(),
+            &rf
+        ));
+        delete_local(local);
+        pop_local_frame();
+        assert_eq!(ref_type(global), RefType::Global);
+
+        delete_global(global);
+        assert_eq!(ref_type(global), RefType::Global);
+        delete_global(global);
+        assert_eq!(ref_type(global), RefType::Invalid);
+
+        //no handle left, not kept by the weak reference
+        drop(rf);
+        drop(obj);
+        assert!(unsafe { from_jobject(weak) }.is_null());
+        unsafe { delete_weak_global(weak) };
+    }
+}
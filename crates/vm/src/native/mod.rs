@@ -5,7 +5,8 @@ use crate::types::ClassRef;
 use rustc_hash::FxHashMap;
 use std::sync::{Arc, RwLock};
 
-mod common;
+pub mod common;
+pub mod jni;
 
 mod java_io_FileDescriptor;
 mod java_io_FileInputStream;
@@ -16,20 +17,52 @@ mod java_lang_ClassLoader;
 mod java_lang_Double;
 mod java_lang_Float;
 mod java_lang_Object;
+mod java_lang_ProcessEnvironment;
 mod java_lang_Runtime;
 mod java_lang_String;
 mod java_lang_System;
 mod java_lang_Thread;
 mod java_lang_Throwable;
+mod java_lang_UNIXProcess;
+mod java_lang_invoke_MethodHandleNatives;
 mod java_lang_reflect_Array;
+mod java_lang_reflect_Executable;
+mod java_lang_reflect_Field;
 mod java_lang_reflect_Proxy;
+mod java_net_DatagramPacket;
+mod java_net_Inet4Address;
+mod java_net_Inet4AddressImpl;
+mod java_net_Inet6Address;
+mod java_net_InetAddress;
+mod java_net_InetAddressImplFactory;
+mod java_net_NetworkInterface;
+mod java_net_PlainDatagramSocketImpl;
+mod java_net_PlainSocketImpl;
+mod java_net_SocketInputStream;
+mod java_net_SocketOutputStream;
+mod java_nio_Bits;
+mod java_nio_MappedByteBuffer;
 mod java_security_AccessController;
 mod java_util_concurrent_atomic_AtomicLong;
+mod java_util_jar_JarFile;
+mod java_util_zip_Adler32;
+mod java_util_zip_CRC32;
+mod java_util_zip_Deflater;
+mod java_util_zip_Inflater;
+mod java_util_zip_ZipFile;
 mod sun_misc_Signal;
 mod sun_misc_URLClassPath;
-mod sun_misc_Unsafe;
+pub(crate) mod sun_misc_Unsafe;
 mod sun_misc_VM;
+mod sun_nio_ch_FileChannelImpl;
+mod sun_nio_ch_FileDispatcherImpl;
+mod sun_nio_ch_FileKey;
+mod sun_nio_ch_IOUtil;
+mod sun_nio_ch_NativeThread;
 mod sun_nio_cs_StreamEncoder;
+mod sun_nio_fs_LinuxNativeDispatcher;
+mod sun_nio_fs_UnixCopyFile;
+mod sun_nio_fs_UnixNativeDispatcher;
 mod sun_reflect_ConstantPool;
 mod sun_reflect_NativeConstructorAccessorImpl;
 mod sun_reflect_NativeMethodAccessorImpl;
@@ -121,10 +154,22 @@ fn create_native_fn_tables(
         ("java/lang/Double", java_lang_Double::get_native_methods()),
         ("java/lang/Float", java_lang_Float::get_native_methods()),
         ("java/lang/Object", java_lang_Object::get_native_methods()),
+        (
+            "java/lang/ProcessEnvironment",
+            java_lang_ProcessEnvironment::get_native_methods(),
+        ),
         (
             "java/lang/reflect/Array",
             java_lang_reflect_Array::get_native_methods(),
         ),
+        (
+            "java/lang/reflect/Executable",
+            java_lang_reflect_Executable::get_native_methods(),
+        ),
+        (
+            "java/lang/reflect/Field",
+            java_lang_reflect_Field::get_native_methods(),
+        ),
         (
             "java/lang/reflect/Proxy",
             java_lang_reflect_Proxy::get_native_methods(),
@@ -137,6 +182,63 @@ fn create_native_fn_tables(
             "java/lang/Throwable",
             java_lang_Throwable::get_native_methods(),
         ),
+        (
+            "java/lang/UNIXProcess",
+            java_lang_UNIXProcess::get_native_methods(),
+        ),
+        (
+            "java/lang/invoke/MethodHandleNatives",
+            java_lang_invoke_MethodHandleNatives::get_native_methods(),
+        ),
+        (
+            "java/net/DatagramPacket",
+            java_net_DatagramPacket::get_native_methods(),
+        ),
+        (
+            "java/net/Inet4Address",
+            java_net_Inet4Address::get_native_methods(),
+        ),
+        (
+            "java/net/Inet4AddressImpl",
+            java_net_Inet4AddressImpl::get_native_methods(),
+        ),
+        (
+            "java/net/Inet6Address",
+            java_net_Inet6Address::get_native_methods(),
+        ),
+        (
+            "java/net/InetAddress",
+            java_net_InetAddress::get_native_methods(),
+        ),
+        (
+            "java/net/InetAddressImplFactory",
+            java_net_InetAddressImplFactory::get_native_methods(),
+        ),
+        (
+            "java/net/NetworkInterface",
+            java_net_NetworkInterface::get_native_methods(),
+        ),
+        (
+            "java/net/PlainDatagramSocketImpl",
+            java_net_PlainDatagramSocketImpl::get_native_methods(),
+        ),
+        (
+            "java/net/PlainSocketImpl",
+            java_net_PlainSocketImpl::get_native_methods(),
+        ),
+        (
+            "java/net/SocketInputStream",
+            java_net_SocketInputStream::get_native_methods(),
+        ),
+        (
+            "java/net/SocketOutputStream",
+            java_net_SocketOutputStream::get_native_methods(),
+        ),
+        ("java/nio/Bits", java_nio_Bits::get_native_methods()),
+        (
+            "java/nio/MappedByteBuffer",
+            java_nio_MappedByteBuffer::get_native_methods(),
+        ),
         (
             "java/security/AccessController",
             java_security_AccessController::get_native_methods(),
@@ -145,6 +247,30 @@ fn create_native_fn_tables(
             "java/util/concurrent/atomic/AtomicLong",
             java_util_concurrent_atomic_AtomicLong::get_native_methods(),
         ),
+        (
+            "java/util/jar/JarFile",
+            java_util_jar_JarFile::get_native_methods(),
+        ),
+        (
+            "java/util/zip/Adler32",
+            java_util_zip_Adler32::get_native_methods(),
+        ),
+        (
+            "java/util/zip/CRC32",
+            java_util_zip_CRC32::get_native_methods(),
+        ),
+        (
+            "java/util/zip/Deflater",
+            java_util_zip_Deflater::get_native_methods(),
+        ),
+        (
+            "java/util/zip/Inflater",
+            java_util_zip_Inflater::get_native_methods(),
+        ),
+        (
+            "java/util/zip/ZipFile",
+            java_util_zip_ZipFile::get_native_methods(),
+        ),
         ("sun/misc/Signal", sun_misc_Signal::get_native_methods()),
         ("sun/misc/Unsafe", sun_misc_Unsafe::get_native_methods()),
         (
@@ -152,10 +278,39 @@ fn create_native_fn_tables(
             sun_misc_URLClassPath::get_native_methods(),
         ),
         ("sun/misc/VM", sun_misc_VM::get_native_methods()),
+        (
+            "sun/nio/ch/FileChannelImpl",
+            sun_nio_ch_FileChannelImpl::get_native_methods(),
+        ),
+        (
+            "sun/nio/ch/FileDispatcherImpl",
+            sun_nio_ch_FileDispatcherImpl::get_native_methods(),
+        ),
+        (
+            "sun/nio/ch/FileKey",
+            sun_nio_ch_FileKey::get_native_methods(),
+        ),
+        ("sun/nio/ch/IOUtil", sun_nio_ch_IOUtil::get_native_methods()),
+        (
+            "sun/nio/ch/NativeThread",
+            sun_nio_ch_NativeThread::get_native_methods(),
+        ),
         (
             "sun/nio/cs/StreamEncoder",
             sun_nio_cs_StreamEncoder::get_native_methods(),
         ),
+        (
+            "sun/nio/fs/LinuxNativeDispatcher",
+            sun_nio_fs_LinuxNativeDispatcher::get_native_methods(),
+        ),
+        (
+            "sun/nio/fs/UnixCopyFile",
+            sun_nio_fs_UnixCopyFile::get_native_methods(),
+        ),
+        (
+            "sun/nio/fs/UnixNativeDispatcher",
+            sun_nio_fs_UnixNativeDispatcher::get_native_methods(),
+        ),
         (
             "sun/reflect/ConstantPool",
             sun_reflect_ConstantPool::get_native_methods(),
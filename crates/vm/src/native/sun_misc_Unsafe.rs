@@ -1,13 +1,16 @@
 #![allow(non_snake_case)]
 
-use crate::native::{java_lang_System, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{java_lang_Thread, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop;
-use crate::oop::{Class, Oop, OopPtr};
-use crate::runtime::require_class3;
+use crate::oop::class::State;
+use crate::oop::{Class, ClassKind, Oop, OopPtr, RefKind, ValueType};
+use crate::runtime::{self, require_class3, thread::ThreadState};
+use crate::types::FieldIdRef;
 use crate::util;
+use classfile::consts as cls_consts;
 use classfile::flags::ACC_STATIC;
-use std::os::raw::c_void;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::{atomic, Arc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -36,7 +39,7 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn(
             "getIntVolatile",
             "(Ljava/lang/Object;J)I",
-            Box::new(jvm_getIntVolatile),
+            Box::new(jvm_getInt2),
         ),
         new_fn(
             "compareAndSwapInt",
@@ -45,8 +48,21 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         ),
         new_fn("allocateMemory", "(J)J", Box::new(jvm_allocateMemory)),
         new_fn("freeMemory", "(J)V", Box::new(jvm_freeMemory)),
+        new_fn("reallocateMemory", "(JJ)J", Box::new(jvm_reallocateMemory)),
         new_fn("putLong", "(JJ)V", Box::new(jvm_putLong)),
+        new_fn("getLong", "(J)J", Box::new(jvm_getLong)),
         new_fn("getByte", "(J)B", Box::new(jvm_getByte)),
+        new_fn("putByte", "(JB)V", Box::new(jvm_putByte2)),
+        new_fn("getShort", "(J)S", Box::new(jvm_getShort)),
+        new_fn("putShort", "(JS)V", Box::new(jvm_putShort)),
+        new_fn("getInt", "(J)I", Box::new(jvm_getInt)),
+        new_fn("putInt", "(JI)V", Box::new(jvm_putInt)),
+        new_fn("getFloat", "(J)F", Box::new(jvm_getFloat)),
+        new_fn("putFloat", "(JF)V", Box::new(jvm_putFloat)),
+        new_fn("getDouble", "(J)D", Box::new(jvm_getDouble)),
+        new_fn("putDouble", "(JD)V", Box::new(jvm_putDouble)),
+        new_fn("getAddress", "(J)J", Box::new(jvm_getLong)),
+        new_fn("putAddress", "(JJ)V", Box::new(jvm_putLong)),
         new_fn(
             "compareAndSwapLong",
             "(Ljava/lang/Object;JJJ)Z",
@@ -55,13 +71,13 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn(
             "getObjectVolatile",
             "(Ljava/lang/Object;J)Ljava/lang/Object;",
-            Box::new(jvm_getObjectVolatile),
+            Box::new(jvm_getObject),
         ),
         new_fn("pageSize", "()I", Box::new(jvm_pageSize)),
         new_fn(
             "getLongVolatile",
             "(Ljava/lang/Object;J)J",
-            Box::new(jvm_getLongVolatile),
+            Box::new(jvm_getLong2),
         ),
         new_fn(
             "setMemory",
@@ -98,9 +114,173 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("putByte", "(Ljava/lang/Object;JB)V", Box::new(jvm_putByte)),
         new_fn("getByte", "(Ljava/lang/Object;J)B", Box::new(jvm_getByte2)),
         new_fn("park", "(ZJ)V", Box::new(jvm_park)),
+        new_fn("unpark", "(Ljava/lang/Object;)V", Box::new(jvm_unpark)),
+        new_fn(
+            "getObject",
+            "(Ljava/lang/Object;J)Ljava/lang/Object;",
+            Box::new(jvm_getObject),
+        ),
+        new_fn(
+            "putObjectVolatile",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            Box::new(jvm_putObject),
+        ),
+        new_fn(
+            "putOrderedObject",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            Box::new(jvm_putObject),
+        ),
+        new_fn(
+            "getBoolean",
+            "(Ljava/lang/Object;J)Z",
+            Box::new(jvm_getBoolean),
+        ),
+        new_fn(
+            "putBoolean",
+            "(Ljava/lang/Object;JZ)V",
+            Box::new(jvm_putBoolean),
+        ),
+        new_fn(
+            "getBooleanVolatile",
+            "(Ljava/lang/Object;J)Z",
+            Box::new(jvm_getBoolean),
+        ),
+        new_fn(
+            "putBooleanVolatile",
+            "(Ljava/lang/Object;JZ)V",
+            Box::new(jvm_putBoolean),
+        ),
+        new_fn(
+            "getByteVolatile",
+            "(Ljava/lang/Object;J)B",
+            Box::new(jvm_getByte2),
+        ),
+        new_fn(
+            "putByteVolatile",
+            "(Ljava/lang/Object;JB)V",
+            Box::new(jvm_putByte),
+        ),
+        new_fn(
+            "getShort",
+            "(Ljava/lang/Object;J)S",
+            Box::new(jvm_getShort2),
+        ),
+        new_fn(
+            "putShort",
+            "(Ljava/lang/Object;JS)V",
+            Box::new(jvm_putShort2),
+        ),
+        new_fn(
+            "getShortVolatile",
+            "(Ljava/lang/Object;J)S",
+            Box::new(jvm_getShort2),
+        ),
+        new_fn(
+            "putShortVolatile",
+            "(Ljava/lang/Object;JS)V",
+            Box::new(jvm_putShort2),
+        ),
+        new_fn("getChar", "(Ljava/lang/Object;J)C", Box::new(jvm_getChar2)),
+        new_fn("putChar", "(Ljava/lang/Object;JC)V", Box::new(jvm_putChar2)),
+        new_fn(
+            "getCharVolatile",
+            "(Ljava/lang/Object;J)C",
+            Box::new(jvm_getChar2),
+        ),
+        new_fn(
+            "putCharVolatile",
+            "(Ljava/lang/Object;JC)V",
+            Box::new(jvm_putChar2),
+        ),
+        new_fn("getInt", "(Ljava/lang/Object;J)I", Box::new(jvm_getInt2)),
+        new_fn("putInt", "(Ljava/lang/Object;JI)V", Box::new(jvm_putInt2)),
+        new_fn(
+            "putIntVolatile",
+            "(Ljava/lang/Object;JI)V",
+            Box::new(jvm_putInt2),
+        ),
+        new_fn(
+            "putOrderedInt",
+            "(Ljava/lang/Object;JI)V",
+            Box::new(jvm_putInt2),
+        ),
+        new_fn("getLong", "(Ljava/lang/Object;J)J", Box::new(jvm_getLong2)),
+        new_fn("putLong", "(Ljava/lang/Object;JJ)V", Box::new(jvm_putLong2)),
+        new_fn(
+            "putLongVolatile",
+            "(Ljava/lang/Object;JJ)V",
+            Box::new(jvm_putLong2),
+        ),
+        new_fn(
+            "putOrderedLong",
+            "(Ljava/lang/Object;JJ)V",
+            Box::new(jvm_putLong2),
+        ),
+        new_fn(
+            "getFloat",
+            "(Ljava/lang/Object;J)F",
+            Box::new(jvm_getFloat2),
+        ),
+        new_fn(
+            "putFloat",
+            "(Ljava/lang/Object;JF)V",
+            Box::new(jvm_putFloat2),
+        ),
+        new_fn(
+            "getFloatVolatile",
+            "(Ljava/lang/Object;J)F",
+            Box::new(jvm_getFloat2),
+        ),
+        new_fn(
+            "putFloatVolatile",
+            "(Ljava/lang/Object;JF)V",
+            Box::new(jvm_putFloat2),
+        ),
+        new_fn(
+            "getDouble",
+            "(Ljava/lang/Object;J)D",
+            Box::new(jvm_getDouble2),
+        ),
+        new_fn(
+            "putDouble",
+            "(Ljava/lang/Object;JD)V",
+            Box::new(jvm_putDouble2),
+        ),
+        new_fn(
+            "getDoubleVolatile",
+            "(Ljava/lang/Object;J)D",
+            Box::new(jvm_getDouble2),
+        ),
+        new_fn(
+            "putDoubleVolatile",
+            "(Ljava/lang/Object;JD)V",
+            Box::new(jvm_putDouble2),
+        ),
+        new_fn(
+            "allocateInstance",
+            "(Ljava/lang/Class;)Ljava/lang/Object;",
+            Box::new(jvm_allocateInstance),
+        ),
+        new_fn(
+            "shouldBeInitialized",
+            "(Ljava/lang/Class;)Z",
+            Box::new(jvm_shouldBeInitialized),
+        ),
+        new_fn(
+            "defineAnonymousClass",
+            "(Ljava/lang/Class;[B[Ljava/lang/Object;)Ljava/lang/Class;",
+            Box::new(jvm_defineAnonymousClass),
+        ),
+        new_fn("loadFence", "()V", Box::new(jvm_fullFence)),
+        new_fn("storeFence", "()V", Box::new(jvm_fullFence)),
+        new_fn("fullFence", "()V", Box::new(jvm_fullFence)),
     ]
 }
 
+//the offsets of static fields are marked, the base is the mirror of the class,
+//whose own fields are at the unmarked offsets
+const STATIC_FIELD_OFFSET: i64 = 1 << 40;
+
 fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(None)
 }
@@ -109,8 +289,20 @@ fn jvm_arrayBaseOffset(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(Some(Oop::new_int(0)))
 }
 
-fn jvm_arrayIndexScale(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
-    Ok(Some(Oop::new_int(1)))
+//the offsets of primitive arrays are in bytes, of object arrays are the indexes
+fn jvm_arrayIndexScale(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let clazz = args.get(1).unwrap();
+    let target = {
+        let rf = clazz.extract_ref();
+        rf.extract_mirror().target.clone()
+    };
+    let scale = match target.as_ref().map(|it| &it.get_class().kind) {
+        Some(ClassKind::TypeArray(ary)) if ary.value_type != ValueType::ARRAY => {
+            value_size(ary.value_type)
+        }
+        _ => 1,
+    };
+    Ok(Some(Oop::new_int(scale as i32)))
 }
 
 fn jvm_addressSize(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
@@ -130,36 +322,26 @@ fn jvm_compareAndSwapObject(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let old_data = args.get(3).unwrap();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
+    let v_at_offset = get_value(owner, offset, ValueType::OBJECT)?;
 
     if OopPtr::is_eq(&v_at_offset, old_data) {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
+        put_value(owner, offset, ValueType::OBJECT, new_data.clone())?;
         Ok(Some(Oop::new_int(1)))
     } else {
         Ok(Some(Oop::new_int(0)))
     }
 }
 
-fn jvm_getIntVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let owner = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long();
-    let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
-    Ok(Some(v))
-}
-
 fn jvm_compareAndSwapInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
     let old_data = args.get(3).unwrap().extract_int();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = {
-        let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
-        v.extract_int()
-    };
+    let v_at_offset = get_value(owner, offset, ValueType::INT)?.extract_int();
 
     if v_at_offset == old_data {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
+        put_value(owner, offset, ValueType::INT, new_data.clone())?;
         Ok(Some(Oop::new_int(1)))
     } else {
         Ok(Some(Oop::new_int(0)))
@@ -184,60 +366,118 @@ fn jvm_freeMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(None)
 }
 
-fn jvm_putLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+fn jvm_reallocateMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let ptr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
-    let l = args.get(2).unwrap().extract_long();
-    let v = l.to_be_bytes();
-    unsafe {
-        libc::memcpy(ptr, v.as_ptr() as *const c_void, 8);
-    }
+    let size = args.get(2).unwrap().extract_long() as usize;
+    let v = unsafe { libc::realloc(ptr, size) };
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+/*
+The accessors of the absolute addresses, by DirectByteBuffer & friends.
+
+The memory is in native byte order, Bits.byteOrder probes it by putLong
+& getByte. The address may be unaligned.
+*/
 
+fn jvm_putLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_long();
+    unsafe { write(addr, v) };
     Ok(None)
 }
 
+fn jvm_getLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v: i64 = unsafe { read(addr) };
+    Ok(Some(Oop::new_long(v)))
+}
+
 fn jvm_getByte(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let ptr = args.get(1).unwrap().extract_long() as *const u8;
-    let v = unsafe { *ptr };
+    let addr = args.get(1).unwrap().extract_long();
+    let v: i8 = unsafe { read(addr) };
     Ok(Some(Oop::new_int(v as i32)))
 }
 
+fn jvm_putByte2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_int() as i8;
+    unsafe { write(addr, v) };
+    Ok(None)
+}
+
+fn jvm_getShort(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v: i16 = unsafe { read(addr) };
+    Ok(Some(Oop::new_int(v as i32)))
+}
+
+fn jvm_putShort(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_int() as i16;
+    unsafe { write(addr, v) };
+    Ok(None)
+}
+
+fn jvm_getInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v: i32 = unsafe { read(addr) };
+    Ok(Some(Oop::new_int(v)))
+}
+
+fn jvm_putInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_int();
+    unsafe { write(addr, v) };
+    Ok(None)
+}
+
+fn jvm_getFloat(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v: f32 = unsafe { read(addr) };
+    Ok(Some(Oop::new_float(v)))
+}
+
+fn jvm_putFloat(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_float();
+    unsafe { write(addr, v) };
+    Ok(None)
+}
+
+fn jvm_getDouble(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v: f64 = unsafe { read(addr) };
+    Ok(Some(Oop::new_double(v)))
+}
+
+fn jvm_putDouble(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_double();
+    unsafe { write(addr, v) };
+    Ok(None)
+}
+
 fn jvm_compareAndSwapLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
     let old_data = args.get(3).unwrap().extract_long();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = {
-        let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
-        v.extract_long()
-    };
+    let v_at_offset = get_value(owner, offset, ValueType::LONG)?.extract_long();
 
     if v_at_offset == old_data {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
+        put_value(owner, offset, ValueType::LONG, new_data.clone())?;
         Ok(Some(Oop::new_int(1)))
     } else {
         Ok(Some(Oop::new_int(0)))
     }
 }
 
-fn jvm_getObjectVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let owner = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long();
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
-    Ok(Some(v_at_offset))
-}
-
 fn jvm_pageSize(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(Some(Oop::new_int(4 * 1024)))
 }
 
-fn jvm_getLongVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let owner = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long();
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
-    Ok(Some(v_at_offset))
-}
-
 fn jvm_setMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let _this = args.get(0).unwrap();
     let obj = args.get(1).unwrap();
@@ -245,36 +485,18 @@ fn jvm_setMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let size = args.get(3).unwrap().extract_long() as usize;
     let value = args.get(4).unwrap().extract_int();
 
-    match obj {
-        Oop::Null => {
-            let dest = offset as *mut libc::c_void;
-            unsafe {
-                libc::memset(dest, value, size as usize);
-            }
-        }
-        Oop::Ref(rf) => {
-            let ary = rf.extract_mut_type_array();
-            let bytes = ary.extract_mut_bytes();
-            unsafe {
-                let addr = bytes.as_mut_ptr();
-                let addr = addr.add(offset);
-                std::ptr::write_bytes(addr, value as u8, size);
-            }
-        }
-        _ => unimplemented!(),
+    let dest = raw_address(obj, offset, size)?;
+    unsafe {
+        std::ptr::write_bytes(dest, value as u8, size);
     }
 
     Ok(None)
 }
 
 fn jvm_putChar(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let dest = args.get(1).unwrap().extract_long() as *mut libc::c_void;
-    let value = args.get(2).unwrap().extract_int();
-
-    unsafe {
-        libc::memset(dest, value, 1);
-    }
-
+    let addr = args.get(1).unwrap().extract_long();
+    let v = args.get(2).unwrap().extract_int() as u16;
+    unsafe { write(addr, v) };
     Ok(None)
 }
 
@@ -286,82 +508,84 @@ fn jvm_copyMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let dest_offset = args.get(4).unwrap().extract_long() as usize;
     let size = args.get(5).unwrap().extract_long() as usize;
 
-    match src_obj {
-        Oop::Null => {
-            match dest_obj {
-                //raw -> raw
-                Oop::Null => {
-                    let src_ptr = src_offset as *const u8;
-                    let dest_ptr = dest_offset as *mut u8;
-                    unsafe {
-                        std::ptr::copy(src_ptr, dest_ptr, size);
-                    }
-                }
-                //raw -> byte[]
-                Oop::Ref(dest) => {
-                    let dest = dest.extract_mut_type_array();
-                    let dest = dest.extract_mut_bytes();
-                    let dest = &mut dest[dest_offset..];
-                    let src_ptr = src_offset as *const u8;
-                    unsafe {
-                        for i in 0..size {
-                            let p = src_ptr.add(i);
-                            dest[i] = *p;
-                        }
-                    }
-                }
-                _ => unimplemented!(),
-            }
-        }
-        Oop::Ref(src) => {
-            match dest_obj {
-                //byte[] -> raw
-                Oop::Null => {
-                    let src = src.extract_type_array();
-                    let src = src.extract_bytes();
-                    let ptr = dest_offset as *mut u8;
-                    unsafe {
-                        for i in 0..size {
-                            let p = ptr.add(i);
-                            *p = src[src_offset + i];
-                        }
-                    }
-                }
-                //byte[] -> byte[]
-                Oop::Ref(dest) => {
-                    java_lang_System::arraycopy_diff_obj(
-                        src.clone(),
-                        src_offset,
-                        dest.clone(),
-                        dest_offset,
-                        size,
-                    );
-                }
-                _ => unimplemented!(),
-            }
-        }
-        _ => unreachable!(),
+    //the primitive arrays of any type, or the raw memory
+    let src = raw_address(src_obj, src_offset, size)?;
+    let dest = raw_address(dest_obj, dest_offset, size)?;
+    unsafe {
+        std::ptr::copy(src, dest, size);
     }
 
     Ok(None)
 }
 
 fn jvm_getChar(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let arg1 = args.get(1).unwrap();
-    let ptr = arg1.extract_long() as *const u16;
-    let v = unsafe { *ptr };
+    let addr = args.get(1).unwrap().extract_long();
+    let v: u16 = unsafe { read(addr) };
     Ok(Some(Oop::new_int(v as i32)))
 }
 
+fn jvm_getObject(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::OBJECT)
+}
+
 fn jvm_putObject(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let _this = args.get(0).unwrap();
-    let o = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long() as usize;
-    let x = args.get(3).unwrap();
+    put(args, ValueType::OBJECT)
+}
 
-    let rf = o.extract_ref();
-    Class::put_field_value2(rf, offset, x.clone());
-    Ok(None)
+fn jvm_getBoolean(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::BOOLEAN)
+}
+
+fn jvm_putBoolean(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::BOOLEAN)
+}
+
+fn jvm_getShort2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::SHORT)
+}
+
+fn jvm_putShort2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::SHORT)
+}
+
+fn jvm_getChar2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::CHAR)
+}
+
+fn jvm_putChar2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::CHAR)
+}
+
+fn jvm_getInt2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::INT)
+}
+
+fn jvm_putInt2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::INT)
+}
+
+fn jvm_getLong2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::LONG)
+}
+
+fn jvm_putLong2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::LONG)
+}
+
+fn jvm_getFloat2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::FLOAT)
+}
+
+fn jvm_putFloat2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::FLOAT)
+}
+
+fn jvm_getDouble2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::DOUBLE)
+}
+
+fn jvm_putDouble2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::DOUBLE)
 }
 
 fn jvm_ensureClassInitialized(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -376,7 +600,8 @@ fn jvm_ensureClassInitialized(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
 fn jvm_staticFieldOffset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let field = args.get(1).unwrap();
-    objectFieldOffset(field, true)
+    let offset = objectFieldOffset(field, true)?.unwrap().extract_long();
+    Ok(Some(Oop::new_long(STATIC_FIELD_OFFSET | offset)))
 }
 
 fn jvm_staticFieldBase(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -389,74 +614,278 @@ fn jvm_staticFieldBase(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_putByte(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    put(args, ValueType::BYTE)
+}
+
+fn jvm_getByte2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    get(args, ValueType::BYTE)
+}
+
+fn jvm_park(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let _this = args.get(0).unwrap();
-    let obj = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long() as usize;
-    let x = args.get(3).unwrap().extract_int();
+    let is_absolute = args.get(1).unwrap().extract_int() != 0;
+    let time = args.get(2).unwrap().extract_long();
 
-    match obj {
-        Oop::Null => {
-            let dest = offset as *mut libc::c_void;
-            unsafe {
-                libc::memset(dest, x, 1);
-            }
+    //absolute: millis since the epoch, relative: nanos, 0 means forever
+    let deadline = if is_absolute {
+        let epoch_duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let time = Duration::from_millis(time.max(0) as u64);
+        match time.checked_sub(epoch_duration) {
+            Some(diff) => Instant::now().checked_add(diff),
+            None => return Ok(None),
         }
-        Oop::Ref(rf) => {
-            let ary = rf.extract_mut_type_array();
-            let bytes = ary.extract_mut_bytes();
-            bytes[offset] = x as u8;
+    } else if time > 0 {
+        Instant::now().checked_add(Duration::from_nanos(time as u64))
+    } else if time == 0 {
+        None
+    } else {
+        return Ok(None);
+    };
+
+    let jt = runtime::thread::current_java_thread();
+    let parker = jt.read().unwrap().parker.clone();
+    let state = if deadline.is_some() {
+        ThreadState::TimedWaiting
+    } else {
+        ThreadState::Waiting
+    };
+    jt.write().unwrap().set_state(state);
+    runtime::gc::enter_safe_region();
+    parker.park(deadline);
+    runtime::gc::leave_safe_region();
+    jt.write().unwrap().set_state(ThreadState::Runnable);
+
+    Ok(None)
+}
+
+fn jvm_unpark(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let thread = args.get(1).unwrap();
+    if let Oop::Ref(_) = thread {
+        if let Some(jt) = java_lang_Thread::find_java_thread(thread) {
+            let parker = jt.read().unwrap().parker.clone();
+            parker.unpark();
         }
-        t => unimplemented!("{:?}", t),
     }
 
     Ok(None)
 }
 
-fn jvm_getByte2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let _this = args.get(0).unwrap();
-    let obj = args.get(1).unwrap();
-    let offset = args.get(2).unwrap().extract_long() as usize;
+fn jvm_allocateInstance(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let clazz = args.get(1).unwrap();
+    let target = {
+        let rf = clazz.extract_ref();
+        rf.extract_mirror().target.clone()
+    };
 
-    let v = match obj {
-        Oop::Null => {
-            let ptr = offset as *const u8;
-            let v = unsafe { *ptr };
-            Oop::new_int(v as i32)
+    match target {
+        Some(cls) if cls.get_class().is_instance() && !cls.get_class().is_abstract() => {
+            oop::class::init_class(&cls);
+            oop::class::init_class_fully(&cls);
+            if runtime::thread::is_meet_ex() {
+                return Ok(None);
+            }
+            Ok(Some(Oop::new_inst(cls)))
         }
-        Oop::Ref(rf) => {
-            let ary = rf.extract_mut_type_array();
-            let bytes = ary.extract_bytes();
-            let v = bytes[offset];
-            Oop::new_int(v as i32)
+        cls => {
+            let name =
+                cls.map(|it| String::from_utf8_lossy(it.name().as_slice()).replace("/", "."));
+            Err(runtime::exception::new(
+                cls_consts::J_INSTANTIATION_EXCEPTION,
+                name,
+            ))
         }
-        t => unimplemented!("{:?}", t),
-    };
+    }
+}
 
-    Ok(Some(v))
+fn jvm_shouldBeInitialized(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let clazz = args.get(1).unwrap();
+    let rf = clazz.extract_ref();
+    let mirror = rf.extract_mirror();
+    let v = match &mirror.target {
+        Some(cls) => cls.get_class().get_class_state() != State::FullyIni,
+        None => false,
+    };
+    Ok(Some(Oop::new_int(v as i32)))
 }
 
-fn jvm_park(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let _this = args.get(0).unwrap();
-    let is_absolute = args.get(1).unwrap().extract_int() != 0;
-    let time = args.get(2).unwrap().extract_long() as u64;
+fn jvm_defineAnonymousClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let host = args.get(1).unwrap();
+    let data = args.get(2).unwrap();
+    let cp_patches = args.get(3).unwrap();
 
-    if is_absolute {
-        let epoch_duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let diff = Duration::from_millis(time) - epoch_duration;
-        std::thread::park_timeout(diff);
-    } else {
-        if time != 0 {
-            std::thread::park_timeout(Duration::from_nanos(time));
-        } else {
-            std::thread::park();
+    let host = {
+        let rf = host.extract_ref();
+        rf.extract_mirror().target.clone().unwrap()
+    };
+    let buf = {
+        let rf = data.extract_ref();
+        let ary = rf.extract_type_array();
+        ary.extract_bytes().to_vec()
+    };
+    let cp_patches = match cp_patches {
+        Oop::Null => vec![],
+        v => {
+            let rf = v.extract_ref();
+            rf.extract_array().to_vec()
         }
-    }
+    };
 
+    //None with the exception pending
+    let cls = runtime::define_anonymous_class(&host, &buf, &cp_patches);
+    Ok(cls.map(|it| it.get_class().get_mirror()))
+}
+
+fn jvm_fullFence(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    atomic::fence(atomic::Ordering::SeqCst);
     Ok(None)
 }
 
 ////////helper
 
+/// The offset of a static field for Unsafe, the base is the mirror of the declaring class
+pub fn static_field_offset(fid: &FieldIdRef) -> i64 {
+    STATIC_FIELD_OFFSET | fid.offset as i64
+}
+
+//getX(Object o, long offset)
+fn get(args: &[Oop], t: ValueType) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    Ok(Some(get_value(obj, offset, t)?))
+}
+
+//putX(Object o, long offset, X x)
+fn put(args: &[Oop], t: ValueType) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let x = args.get(3).unwrap();
+    put_value(obj, offset, t, x.clone())?;
+    Ok(None)
+}
+
+//the value at 'offset' of an object, an array, the static fields of a class,
+//or the memory if 'obj' is null
+fn get_value(obj: &Oop, offset: i64, t: ValueType) -> Result<Oop, Oop> {
+    let rf = match obj {
+        Oop::Null => return unsafe { read_value(offset, t) },
+        v => v.extract_ref(),
+    };
+
+    unsafe {
+        match &(*rf.get_raw_ptr()).v {
+            RefKind::TypeArray(_) => {
+                let src = raw_address(obj, offset as usize, value_size(t))?;
+                read_value(src as i64, t)
+            }
+            RefKind::Mirror(mirror) if offset & STATIC_FIELD_OFFSET != 0 => {
+                let cls = mirror.target.clone().unwrap();
+                let offset = (offset & !STATIC_FIELD_OFFSET) as usize;
+                let cls = cls.get_class();
+                if offset < cls.n_static_fields() {
+                    Ok(cls.get_static_field_value2(offset))
+                } else {
+                    Err(bad_offset(offset as i64))
+                }
+            }
+            _ if (offset as usize) < n_slots(&rf) => {
+                Ok(Class::get_field_value2(rf.clone(), offset as usize))
+            }
+            _ => Err(bad_offset(offset)),
+        }
+    }
+}
+
+fn put_value(obj: &Oop, offset: i64, t: ValueType, v: Oop) -> Result<(), Oop> {
+    let rf = match obj {
+        Oop::Null => return unsafe { write_value(offset, t, &v) },
+        v => v.extract_ref(),
+    };
+
+    unsafe {
+        match &(*rf.get_raw_ptr()).v {
+            RefKind::TypeArray(_) => {
+                let dest = raw_address(obj, offset as usize, value_size(t))?;
+                write_value(dest as i64, t, &v)
+            }
+            RefKind::Mirror(mirror) if offset & STATIC_FIELD_OFFSET != 0 => {
+                let cls = mirror.target.clone().unwrap();
+                let offset = (offset & !STATIC_FIELD_OFFSET) as usize;
+                let cls = cls.get_mut_class();
+                if offset < cls.n_static_fields() {
+                    cls.put_static_field_value2(offset, v);
+                    Ok(())
+                } else {
+                    Err(bad_offset(offset as i64))
+                }
+            }
+            _ if (offset as usize) < n_slots(&rf) => {
+                Class::put_field_value2(rf.clone(), offset as usize, v);
+                Ok(())
+            }
+            _ => Err(bad_offset(offset)),
+        }
+    }
+}
+
+//the field slots of an object or a mirror, the elements of a reference array
+unsafe fn n_slots(rf: &Arc<OopPtr>) -> usize {
+    match &(*rf.get_raw_ptr()).v {
+        RefKind::Inst(inst) => inst.field_values.len(),
+        RefKind::Mirror(mirror) => mirror.field_values.len(),
+        RefKind::Array(ary) => ary.len(),
+        RefKind::TypeArray(_) => 0,
+    }
+}
+
+//no object lives in the raw memory or in a primitive array
+unsafe fn read_value(addr: i64, t: ValueType) -> Result<Oop, Oop> {
+    let v = match t {
+        ValueType::BOOLEAN => Oop::new_int(read::<u8>(addr) as i32),
+        ValueType::BYTE => Oop::new_int(read::<i8>(addr) as i32),
+        ValueType::CHAR => Oop::new_int(read::<u16>(addr) as i32),
+        ValueType::SHORT => Oop::new_int(read::<i16>(addr) as i32),
+        ValueType::INT => Oop::new_int(read(addr)),
+        ValueType::LONG => Oop::new_long(read(addr)),
+        ValueType::FLOAT => Oop::new_float(read(addr)),
+        ValueType::DOUBLE => Oop::new_double(read(addr)),
+        ValueType::OBJECT | ValueType::ARRAY | ValueType::VOID => return Err(bad_type(t)),
+    };
+    Ok(v)
+}
+
+unsafe fn write_value(addr: i64, t: ValueType, v: &Oop) -> Result<(), Oop> {
+    match t {
+        ValueType::BOOLEAN | ValueType::BYTE => write(addr, v.extract_int() as u8),
+        ValueType::CHAR | ValueType::SHORT => write(addr, v.extract_int() as u16),
+        ValueType::INT => write(addr, v.extract_int()),
+        ValueType::LONG => write(addr, v.extract_long()),
+        ValueType::FLOAT => write(addr, v.extract_float()),
+        ValueType::DOUBLE => write(addr, v.extract_double()),
+        ValueType::OBJECT | ValueType::ARRAY | ValueType::VOID => return Err(bad_type(t)),
+    }
+    Ok(())
+}
+
+fn bad_offset(offset: i64) -> Oop {
+    let msg = format!("bad offset: {}", offset);
+    runtime::exception::new(cls_consts::J_ILLEGAL_ARGUMENT, Some(msg))
+}
+
+fn bad_type(t: ValueType) -> Oop {
+    let msg = format!("not a primitive value: {:?}", t);
+    runtime::exception::new(cls_consts::J_ILLEGAL_ARGUMENT, Some(msg))
+}
+
+fn value_size(t: ValueType) -> usize {
+    match t {
+        ValueType::BOOLEAN | ValueType::BYTE => 1,
+        ValueType::CHAR | ValueType::SHORT => 2,
+        ValueType::INT | ValueType::FLOAT => 4,
+        ValueType::LONG | ValueType::DOUBLE => 8,
+        _ => 1,
+    }
+}
+
 fn objectFieldOffset(field: &Oop, is_static: bool) -> JNIResult {
     let cls = require_class3(None, b"java/lang/reflect/Field").unwrap();
 
@@ -479,3 +908,39 @@ fn objectFieldOffset(field: &Oop, is_static: bool) -> JNIResult {
 
     Ok(Some(Oop::new_long(slot as i64)))
 }
+
+//the address of 'offset' in a primitive array, or 'offset' itself if the
+//array is null, 'size' bytes are checked in the bounds of the array
+fn raw_address(obj: &Oop, offset: usize, size: usize) -> Result<*mut u8, Oop> {
+    let rf = match obj {
+        Oop::Null => return Ok(offset as *mut u8),
+        v => v.extract_ref(),
+    };
+
+    unsafe {
+        match &mut (*rf.get_mut_raw_ptr()).v {
+            RefKind::TypeArray(ary) => {
+                let bytes = ary.as_mut_bytes();
+                match offset.checked_add(size) {
+                    Some(end) if end <= bytes.len() => Ok(bytes[offset..end].as_mut_ptr()),
+                    _ => Err(bad_offset(offset as i64)),
+                }
+            }
+            _ => {
+                let msg = "not a primitive array".to_string();
+                Err(runtime::exception::new(
+                    cls_consts::J_ILLEGAL_ARGUMENT,
+                    Some(msg),
+                ))
+            }
+        }
+    }
+}
+
+unsafe fn read<T>(addr: i64) -> T {
+    std::ptr::read_unaligned(addr as *const T)
+}
+
+unsafe fn write<T>(addr: i64, v: T) {
+    std::ptr::write_unaligned(addr as *mut T, v)
+}
@@ -0,0 +1,140 @@
+#![allow(non_snake_case)]
+use super::sun_nio_ch_FileDispatcherImpl;
+use crate::native::common::io::{blocking, error_message, get_field};
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use crate::runtime;
+use classfile::consts as cls_consts;
+use std::io;
+
+//FileChannelImpl.MAP_RO, MAP_RW & MAP_PV
+const MAP_RO: i32 = 0;
+const MAP_RW: i32 = 1;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()J", Box::new(jvm_initIDs)),
+        new_fn("map0", "(IJJ)J", Box::new(jvm_map0)),
+        new_fn("unmap0", "(JJ)I", Box::new(jvm_unmap0)),
+        new_fn(
+            "transferTo0",
+            "(Ljava/io/FileDescriptor;JJLjava/io/FileDescriptor;)J",
+            Box::new(jvm_transferTo0),
+        ),
+        //before 8u, seek0 of FileDispatcherImpl after
+        new_fn(
+            "position0",
+            "(Ljava/io/FileDescriptor;J)J",
+            Box::new(jvm_position0),
+        ),
+    ]
+}
+
+//the allocation granularity of map0
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let v = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+fn jvm_map0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let prot = args.get(1).unwrap().extract_int();
+    let position = args.get(2).unwrap().extract_long();
+    let len = args.get(3).unwrap().extract_long();
+
+    let fd_obj = get_field(this, "fd", "Ljava/io/FileDescriptor;");
+    let fd = nio::fd_value(&fd_obj)?;
+
+    let (protections, flags) = match prot {
+        MAP_RO => (libc::PROT_READ, libc::MAP_SHARED),
+        MAP_RW => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED),
+        _ => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_PRIVATE),
+    };
+
+    let addr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len as usize,
+            protections,
+            flags,
+            fd,
+            position as libc::off_t,
+        )
+    };
+
+    if addr == libc::MAP_FAILED {
+        let err = io::Error::last_os_error();
+        //the java side collects the unreferenced buffers, then tries again
+        let name = if err.raw_os_error() == Some(libc::ENOMEM) {
+            cls_consts::J_OOM
+        } else {
+            cls_consts::J_IOEXCEPTION
+        };
+        let ex = runtime::exception::new(name, Some("Map failed".to_string()));
+        return Err(ex);
+    }
+
+    Ok(Some(Oop::new_long(addr as i64)))
+}
+
+fn jvm_unmap0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.first().unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(1).unwrap().extract_long() as usize;
+
+    if unsafe { libc::munmap(addr, len) } < 0 {
+        let err = io::Error::last_os_error();
+        let ex = runtime::exception::new(
+            cls_consts::J_IOEXCEPTION,
+            Some(format!("Unmap failed: {}", error_message(&err))),
+        );
+        return Err(ex);
+    }
+
+    Ok(Some(Oop::new_int(0)))
+}
+
+fn jvm_transferTo0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let src = nio::fd_value(args.get(1).unwrap())?;
+    let position = args.get(2).unwrap().extract_long();
+    let count = args.get(3).unwrap().extract_long();
+    let dst = nio::fd_value(args.get(4).unwrap())?;
+
+    transfer(src, position, count, dst).map(|n| Some(Oop::new_long(n)))
+}
+
+fn jvm_position0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.get(1).unwrap())?;
+    let offset = args.get(2).unwrap().extract_long();
+    sun_nio_ch_FileDispatcherImpl::seek(fd, offset).map(|v| Some(Oop::new_long(v)))
+}
+
+#[cfg(target_os = "linux")]
+fn transfer(src: i32, position: i64, count: i64, dst: i32) -> Result<i64, Oop> {
+    let mut offset = position as libc::off_t;
+    let n = blocking(|| unsafe { libc::sendfile(dst, src, &mut offset, count as usize) });
+    if n >= 0 {
+        return Ok(n as i64);
+    }
+
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EAGAIN) => Ok(nio::UNAVAILABLE),
+        //not a file to a file or a socket, such as a pipe
+        Some(libc::EINVAL) | Some(libc::ENOSYS) if count >= 0 => Ok(nio::UNSUPPORTED_CASE),
+        Some(libc::EINTR) => Ok(nio::INTERRUPTED),
+        _ => {
+            let ex = runtime::exception::new(
+                cls_consts::J_IOEXCEPTION,
+                Some("Transfer failed".to_string()),
+            );
+            Err(ex)
+        }
+    }
+}
+
+//the java side copies by the buffers
+#[cfg(not(target_os = "linux"))]
+fn transfer(_src: i32, _position: i64, _count: i64, _dst: i32) -> Result<i64, Oop> {
+    Ok(nio::UNSUPPORTED)
+}
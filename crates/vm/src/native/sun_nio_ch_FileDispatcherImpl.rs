@@ -0,0 +1,318 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{blocking, fd_value};
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+//FileDispatcher
+const NO_LOCK: i32 = -1;
+const LOCKED: i32 = 0;
+const INTERRUPTED: i32 = 2;
+
+//the half closed end of a socketpair, dup2 to the fd closing
+static PRE_CLOSE_FD: AtomicI32 = AtomicI32::new(-1);
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn(
+            "read0",
+            "(Ljava/io/FileDescriptor;JI)I",
+            Box::new(jvm_read0),
+        ),
+        new_fn(
+            "pread0",
+            "(Ljava/io/FileDescriptor;JIJ)I",
+            Box::new(jvm_pread0),
+        ),
+        new_fn(
+            "readv0",
+            "(Ljava/io/FileDescriptor;JI)J",
+            Box::new(jvm_readv0),
+        ),
+        new_fn(
+            "write0",
+            "(Ljava/io/FileDescriptor;JI)I",
+            Box::new(jvm_write0),
+        ),
+        new_fn(
+            "pwrite0",
+            "(Ljava/io/FileDescriptor;JIJ)I",
+            Box::new(jvm_pwrite0),
+        ),
+        new_fn(
+            "writev0",
+            "(Ljava/io/FileDescriptor;JI)J",
+            Box::new(jvm_writev0),
+        ),
+        new_fn("seek0", "(Ljava/io/FileDescriptor;J)J", Box::new(jvm_seek0)),
+        new_fn(
+            "force0",
+            "(Ljava/io/FileDescriptor;Z)I",
+            Box::new(jvm_force0),
+        ),
+        new_fn(
+            "truncate0",
+            "(Ljava/io/FileDescriptor;J)I",
+            Box::new(jvm_truncate0),
+        ),
+        new_fn("size0", "(Ljava/io/FileDescriptor;)J", Box::new(jvm_size0)),
+        new_fn(
+            "lock0",
+            "(Ljava/io/FileDescriptor;ZJJZ)I",
+            Box::new(jvm_lock0),
+        ),
+        new_fn(
+            "release0",
+            "(Ljava/io/FileDescriptor;JJ)V",
+            Box::new(jvm_release0),
+        ),
+        new_fn(
+            "close0",
+            "(Ljava/io/FileDescriptor;)V",
+            Box::new(jvm_close0),
+        ),
+        new_fn(
+            "preClose0",
+            "(Ljava/io/FileDescriptor;)V",
+            Box::new(jvm_preClose0),
+        ),
+        new_fn("closeIntFD", "(I)V", Box::new(jvm_closeIntFD)),
+    ]
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mut sp = [0; 2];
+    if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, sp.as_mut_ptr()) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    unsafe {
+        libc::close(sp[1]);
+    }
+    PRE_CLOSE_FD.store(sp[0], Ordering::Relaxed);
+
+    Ok(None)
+}
+
+fn jvm_read0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let addr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(2).unwrap().extract_int() as usize;
+
+    let n = blocking(|| unsafe { libc::read(fd, addr, len) });
+    let n = nio::convert_return(n, true)?;
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn jvm_pread0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let addr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let len = args.get(2).unwrap().extract_int() as usize;
+    let position = args.get(3).unwrap().extract_long();
+
+    let n = blocking(|| unsafe { libc::pread(fd, addr, len, position as libc::off_t) });
+    let n = nio::convert_return(n, true)?;
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+//the address of the iovec array built by IOVecWrapper
+fn jvm_readv0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let iov = args.get(1).unwrap().extract_long() as *const libc::iovec;
+    let len = args.get(2).unwrap().extract_int();
+
+    let n = blocking(|| unsafe { libc::readv(fd, iov, len) });
+    let n = nio::convert_return(n, true)?;
+    Ok(Some(Oop::new_long(n)))
+}
+
+fn jvm_write0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let addr = args.get(1).unwrap().extract_long() as *const libc::c_void;
+    let len = args.get(2).unwrap().extract_int() as usize;
+
+    let n = blocking(|| unsafe { libc::write(fd, addr, len) });
+    let n = nio::convert_return(n, false)?;
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn jvm_pwrite0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let addr = args.get(1).unwrap().extract_long() as *const libc::c_void;
+    let len = args.get(2).unwrap().extract_int() as usize;
+    let position = args.get(3).unwrap().extract_long();
+
+    let n = blocking(|| unsafe { libc::pwrite(fd, addr, len, position as libc::off_t) });
+    let n = nio::convert_return(n, false)?;
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn jvm_writev0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let iov = args.get(1).unwrap().extract_long() as *const libc::iovec;
+    let len = args.get(2).unwrap().extract_int();
+
+    let n = blocking(|| unsafe { libc::writev(fd, iov, len) });
+    let n = nio::convert_return(n, false)?;
+    Ok(Some(Oop::new_long(n)))
+}
+
+//the offset < 0 queries the position
+fn jvm_seek0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let offset = args.get(1).unwrap().extract_long();
+    seek(fd, offset).map(|v| Some(Oop::new_long(v)))
+}
+
+fn jvm_force0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let meta_data = args.get(1).unwrap().extract_int() != 0;
+
+    let r = blocking(|| unsafe {
+        if meta_data {
+            libc::fsync(fd)
+        } else {
+            sync_data(fd)
+        }
+    });
+    if r < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(Some(Oop::new_int(0)))
+}
+
+fn jvm_truncate0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let size = args.get(1).unwrap().extract_long();
+
+    if unsafe { libc::ftruncate(fd, size as libc::off_t) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(Some(Oop::new_int(0)))
+}
+
+fn jvm_size0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+
+    let mut st: libc::stat = unsafe { mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut st) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(Some(Oop::new_long(st.st_size as i64)))
+}
+
+fn jvm_lock0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let wait = args.get(1).unwrap().extract_int() != 0;
+    let pos = args.get(2).unwrap().extract_long();
+    let size = args.get(3).unwrap().extract_long();
+    let shared = args.get(4).unwrap().extract_int() != 0;
+
+    let ty = if shared { libc::F_RDLCK } else { libc::F_WRLCK };
+    let fl = new_flock(ty, pos, size);
+    let cmd = if wait { libc::F_SETLKW } else { libc::F_SETLK };
+
+    let r = blocking(|| unsafe { libc::fcntl(fd, cmd, &fl) });
+    if r < 0 {
+        let err = io::Error::last_os_error();
+        let v = match err.raw_os_error() {
+            Some(libc::EAGAIN) | Some(libc::EACCES) if !wait => NO_LOCK,
+            Some(libc::EINTR) if wait => INTERRUPTED,
+            _ => return Err(nio::io_exception(&err)),
+        };
+        return Ok(Some(Oop::new_int(v)));
+    }
+
+    Ok(Some(Oop::new_int(LOCKED)))
+}
+
+fn jvm_release0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let pos = args.get(1).unwrap().extract_long();
+    let size = args.get(2).unwrap().extract_long();
+
+    let fl = new_flock(libc::F_UNLCK, pos, size);
+    if unsafe { libc::fcntl(fd, libc::F_SETLK, &fl) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+fn jvm_close0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = fd_value(args.first().unwrap());
+    close(fd)
+}
+
+//the threads blocked on fd see EOF, the real close comes later
+fn jvm_preClose0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = fd_value(args.first().unwrap());
+    let pre_close = PRE_CLOSE_FD.load(Ordering::Relaxed);
+
+    if fd >= 0 && pre_close >= 0 && unsafe { libc::dup2(pre_close, fd) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+fn jvm_closeIntFD(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = args.first().unwrap().extract_int();
+    close(fd)
+}
+
+pub fn seek(fd: i32, offset: i64) -> Result<i64, Oop> {
+    let v = if offset < 0 {
+        unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) }
+    } else {
+        unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_SET) }
+    };
+
+    if v < 0 {
+        Err(nio::io_exception(&io::Error::last_os_error()))
+    } else {
+        Ok(v as i64)
+    }
+}
+
+fn close(fd: i32) -> JNIResult {
+    if fd >= 0 && unsafe { libc::close(fd) } < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(nio::io_exception(&err));
+        }
+    }
+
+    Ok(None)
+}
+
+//the size Long.MAX_VALUE locks to the end of file, as 0
+fn new_flock(ty: i32, pos: i64, size: i64) -> libc::flock {
+    let mut fl: libc::flock = unsafe { mem::zeroed() };
+    fl.l_whence = libc::SEEK_SET as libc::c_short;
+    fl.l_start = pos as libc::off_t;
+    fl.l_len = if size == i64::MAX {
+        0
+    } else {
+        size as libc::off_t
+    };
+    fl.l_type = ty as libc::c_short;
+    fl
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn sync_data(fd: i32) -> i32 {
+    libc::fdatasync(fd)
+}
+
+#[cfg(not(target_os = "linux"))]
+unsafe fn sync_data(fd: i32) -> i32 {
+    libc::fsync(fd)
+}
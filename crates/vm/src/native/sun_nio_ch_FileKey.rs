@@ -0,0 +1,33 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::put_field;
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()V", Box::new(jvm_initIDs)),
+        new_fn("init", "(Ljava/io/FileDescriptor;)V", Box::new(jvm_init)),
+    ]
+}
+
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//the device & inode identify the file, by FileLockTable
+fn jvm_init(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.first().unwrap();
+    let fd = nio::fd_value(args.get(1).unwrap())?;
+
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut st) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    put_field(this, "st_dev", "J", Oop::new_long(st.st_dev as i64));
+    put_field(this, "st_ino", "J", Oop::new_long(st.st_ino as i64));
+
+    Ok(None)
+}
@@ -0,0 +1,149 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{fd_value, put_field};
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("initIDs", "()V", Box::new(jvm_initIDs)),
+        new_fn("randomBytes", "([B)Z", Box::new(jvm_randomBytes)),
+        new_fn("makePipe", "(Z)J", Box::new(jvm_makePipe)),
+        new_fn("drain", "(I)Z", Box::new(jvm_drain)),
+        new_fn(
+            "configureBlocking",
+            "(Ljava/io/FileDescriptor;Z)V",
+            Box::new(jvm_configureBlocking),
+        ),
+        new_fn("fdVal", "(Ljava/io/FileDescriptor;)I", Box::new(jvm_fdVal)),
+        new_fn(
+            "setfdVal",
+            "(Ljava/io/FileDescriptor;I)V",
+            Box::new(jvm_setfdVal),
+        ),
+        new_fn("fdLimit", "()I", Box::new(jvm_fdLimit)),
+        new_fn("iovMax", "()I", Box::new(jvm_iovMax)),
+    ]
+}
+
+fn jvm_initIDs(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//unsupported, the java side falls back to SecureRandom
+fn jvm_randomBytes(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(0)))
+}
+
+//the read end in the high 32 bits, the write end in the low
+fn jvm_makePipe(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let blocking = args.first().unwrap().extract_int() != 0;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    if !blocking {
+        for &fd in fds.iter() {
+            if let Err(e) = set_blocking(fd, false) {
+                unsafe {
+                    libc::close(fds[0]);
+                    libc::close(fds[1]);
+                }
+                return Err(nio::io_exception(&e));
+            }
+        }
+    }
+
+    let v = ((fds[0] as i64) << 32) | (fds[1] as u32 as i64);
+    Ok(Some(Oop::new_long(v)))
+}
+
+//read all available, true if any
+fn jvm_drain(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = args.first().unwrap().extract_int();
+
+    let mut buf = [0u8; 128];
+    let mut drained = false;
+    loop {
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n > 0 {
+            drained = true;
+            if (n as usize) < buf.len() {
+                break;
+            }
+            continue;
+        }
+
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EAGAIN) => (),
+                Some(libc::EINTR) => continue,
+                _ => return Err(nio::io_exception(&err)),
+            }
+        }
+        break;
+    }
+
+    Ok(Some(Oop::new_int(drained as i32)))
+}
+
+fn jvm_configureBlocking(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = nio::fd_value(args.first().unwrap())?;
+    let blocking = args.get(1).unwrap().extract_int() != 0;
+
+    match set_blocking(fd, blocking) {
+        Ok(()) => Ok(None),
+        Err(e) => Err(nio::io_exception(&e)),
+    }
+}
+
+fn jvm_fdVal(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = fd_value(args.first().unwrap());
+    Ok(Some(Oop::new_int(fd)))
+}
+
+fn jvm_setfdVal(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd_obj = args.first().unwrap();
+    let v = args.get(1).unwrap().clone();
+    put_field(fd_obj, "fd", "I", v);
+    Ok(None)
+}
+
+fn jvm_fdLimit(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mut rlp: libc::rlimit = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlp) } < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    let limit = if rlp.rlim_max == libc::RLIM_INFINITY || rlp.rlim_max > i32::MAX as libc::rlim_t {
+        i32::MAX
+    } else {
+        rlp.rlim_max as i32
+    };
+    Ok(Some(Oop::new_int(limit)))
+}
+
+fn jvm_iovMax(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let v = unsafe { libc::sysconf(libc::_SC_IOV_MAX) };
+    Ok(Some(Oop::new_int(v as i32)))
+}
+
+fn set_blocking(fd: i32, blocking: bool) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    let new_flags = if blocking {
+        flags & !libc::O_NONBLOCK
+    } else {
+        flags | libc::O_NONBLOCK
+    };
+
+    if flags < 0 || (new_flags != flags && unsafe { libc::fcntl(fd, libc::F_SETFL, new_flags) } < 0)
+    {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
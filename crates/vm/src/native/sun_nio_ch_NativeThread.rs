@@ -0,0 +1,62 @@
+#![allow(non_snake_case)]
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+
+/*
+A thread blocked in an I/O call of a channel is signalled when the channel is
+closed, or the thread interrupted. The handler does nothing, the call fails
+by EINTR, then the java side sees the channel closed.
+*/
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()V", Box::new(jvm_init)),
+        new_fn("current", "()J", Box::new(jvm_current)),
+        new_fn("signal", "(J)V", Box::new(jvm_signal)),
+    ]
+}
+
+extern "C" fn interrupted(_sig: libc::c_int) {}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    //no SA_RESTART, the blocking call returns
+    let r = unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        sa.sa_sigaction = interrupted as extern "C" fn(libc::c_int) as usize;
+        libc::sigemptyset(&mut sa.sa_mask);
+        libc::sigaction(interrupt_signal(), &sa, std::ptr::null_mut())
+    };
+    if r < 0 {
+        return Err(nio::io_exception(&io::Error::last_os_error()));
+    }
+
+    Ok(None)
+}
+
+fn jvm_current(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let v = unsafe { libc::pthread_self() };
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+fn jvm_signal(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let thread = args.first().unwrap().extract_long() as libc::pthread_t;
+
+    let r = unsafe { libc::pthread_kill(thread, interrupt_signal()) };
+    if r != 0 {
+        return Err(nio::io_exception(&io::Error::from_raw_os_error(r)));
+    }
+
+    Ok(None)
+}
+
+#[cfg(target_os = "linux")]
+fn interrupt_signal() -> libc::c_int {
+    libc::SIGRTMAX() - 2
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interrupt_signal() -> libc::c_int {
+    libc::SIGIO
+}
@@ -0,0 +1,137 @@
+#![allow(non_snake_case)]
+use crate::native::JNINativeMethod;
+
+//the mount table & the extended attributes, only on linux
+#[cfg(target_os = "linux")]
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    linux::get_native_methods()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![]
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use crate::native::common::io::put_field;
+    use crate::native::common::nio;
+    use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+    use crate::oop::Oop;
+    use std::ffi::CStr;
+
+    pub fn get_native_methods() -> Vec<JNINativeMethod> {
+        vec![
+            new_fn("init", "()V", Box::new(jvm_init)),
+            new_fn("setmntent0", "(JJ)J", Box::new(jvm_setmntent0)),
+            new_fn(
+                "getmntent",
+                "(JLsun/nio/fs/UnixMountEntry;)I",
+                Box::new(jvm_getmntent),
+            ),
+            new_fn("endmntent", "(J)V", Box::new(jvm_endmntent)),
+            new_fn("fgetxattr0", "(IJJI)I", Box::new(jvm_fgetxattr0)),
+            new_fn("fsetxattr0", "(IJJI)V", Box::new(jvm_fsetxattr0)),
+            new_fn("fremovexattr0", "(IJ)V", Box::new(jvm_fremovexattr0)),
+            new_fn("flistxattr", "(IJI)I", Box::new(jvm_flistxattr)),
+        ]
+    }
+
+    fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+        Ok(None)
+    }
+
+    fn jvm_setmntent0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let path = args.first().unwrap().extract_long() as *const libc::c_char;
+        let mode = args.get(1).unwrap().extract_long() as *const libc::c_char;
+
+        let fp = unsafe { libc::setmntent(path, mode) };
+        if fp.is_null() {
+            return Err(nio::last_unix_exception());
+        }
+
+        Ok(Some(Oop::new_long(fp as i64)))
+    }
+
+    //-1 at the end
+    fn jvm_getmntent(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fp = args.first().unwrap().extract_long() as *mut libc::FILE;
+        let entry = args.get(1).unwrap();
+
+        let ent = unsafe { libc::getmntent(fp) };
+        if ent.is_null() {
+            return Ok(Some(Oop::new_int(-1)));
+        }
+
+        let bytes = |p: *const libc::c_char| {
+            let v = unsafe { CStr::from_ptr(p) };
+            Oop::new_byte_ary2(v.to_bytes().to_vec())
+        };
+        let ent = unsafe { &*ent };
+        put_field(entry, "name", "[B", bytes(ent.mnt_fsname));
+        put_field(entry, "dir", "[B", bytes(ent.mnt_dir));
+        put_field(entry, "fstype", "[B", bytes(ent.mnt_type));
+        put_field(entry, "opts", "[B", bytes(ent.mnt_opts));
+
+        Ok(Some(Oop::new_int(0)))
+    }
+
+    fn jvm_endmntent(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fp = args.first().unwrap().extract_long() as *mut libc::FILE;
+        unsafe {
+            libc::endmntent(fp);
+        }
+        Ok(None)
+    }
+
+    fn jvm_fgetxattr0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fd = args.first().unwrap().extract_int();
+        let name = args.get(1).unwrap().extract_long() as *const libc::c_char;
+        let value = args.get(2).unwrap().extract_long() as *mut libc::c_void;
+        let len = args.get(3).unwrap().extract_int() as usize;
+
+        let n = unsafe { libc::fgetxattr(fd, name, value, len) };
+        if n < 0 {
+            return Err(nio::last_unix_exception());
+        }
+
+        Ok(Some(Oop::new_int(n as i32)))
+    }
+
+    fn jvm_fsetxattr0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fd = args.first().unwrap().extract_int();
+        let name = args.get(1).unwrap().extract_long() as *const libc::c_char;
+        let value = args.get(2).unwrap().extract_long() as *const libc::c_void;
+        let len = args.get(3).unwrap().extract_int() as usize;
+
+        if unsafe { libc::fsetxattr(fd, name, value, len, 0) } < 0 {
+            return Err(nio::last_unix_exception());
+        }
+
+        Ok(None)
+    }
+
+    fn jvm_fremovexattr0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fd = args.first().unwrap().extract_int();
+        let name = args.get(1).unwrap().extract_long() as *const libc::c_char;
+
+        if unsafe { libc::fremovexattr(fd, name) } < 0 {
+            return Err(nio::last_unix_exception());
+        }
+
+        Ok(None)
+    }
+
+    fn jvm_flistxattr(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+        let fd = args.first().unwrap().extract_int();
+        let list = args.get(1).unwrap().extract_long() as *mut libc::c_char;
+        let len = args.get(2).unwrap().extract_int() as usize;
+
+        let n = unsafe { libc::flistxattr(fd, list, len) };
+        if n < 0 {
+            return Err(nio::last_unix_exception());
+        }
+
+        Ok(Some(Oop::new_int(n as i32)))
+    }
+}
@@ -0,0 +1,57 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::blocking;
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::io;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn("transfer", "(IIJ)V", Box::new(jvm_transfer))]
+}
+
+//copy src to dst, stop if the int at the address is set, by Files.copy cancelled
+fn jvm_transfer(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dst = args.first().unwrap().extract_int();
+    let src = args.get(1).unwrap().extract_int();
+    let cancel = args.get(2).unwrap().extract_long() as *const i32;
+
+    let r = blocking(|| {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            let n = unsafe { libc::read(src, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n == 0 {
+                return Ok(());
+            }
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err.raw_os_error().unwrap_or(libc::EIO));
+            }
+
+            let mut pos = 0;
+            while pos < n as usize {
+                let p = buf[pos..].as_ptr() as *const libc::c_void;
+                let m = unsafe { libc::write(dst, p, n as usize - pos) };
+                if m < 0 {
+                    let err = io::Error::last_os_error();
+                    if err.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(err.raw_os_error().unwrap_or(libc::EIO));
+                }
+                pos += m as usize;
+            }
+
+            if !cancel.is_null() && unsafe { std::ptr::read_volatile(cancel) } != 0 {
+                return Err(libc::ECANCELED);
+            }
+        }
+    });
+
+    match r {
+        Ok(()) => Ok(None),
+        Err(errno) => Err(nio::unix_exception(errno)),
+    }
+}
@@ -0,0 +1,586 @@
+#![allow(non_snake_case)]
+use crate::native::common::io::{blocking, error_message, put_field};
+use crate::native::common::nio;
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::Oop;
+use std::ffi::CStr;
+use std::io;
+use std::mem;
+
+//UnixNativeDispatcher, the capabilities returned by init
+const SUPPORTS_OPENAT: i32 = 1 << 1;
+const SUPPORTS_FUTIMES: i32 = 1 << 2;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("init", "()I", Box::new(jvm_init)),
+        new_fn("getcwd", "()[B", Box::new(jvm_getcwd)),
+        new_fn("dup", "(I)I", Box::new(jvm_dup)),
+        new_fn("open0", "(JII)I", Box::new(jvm_open0)),
+        new_fn("openat0", "(IJII)I", Box::new(jvm_openat0)),
+        new_fn("close", "(I)V", Box::new(jvm_close)),
+        new_fn("fopen0", "(JJ)J", Box::new(jvm_fopen0)),
+        new_fn("fclose", "(J)V", Box::new(jvm_fclose)),
+        new_fn("link0", "(JJ)V", Box::new(jvm_link0)),
+        new_fn("unlink0", "(J)V", Box::new(jvm_unlink0)),
+        new_fn("unlinkat0", "(IJI)V", Box::new(jvm_unlinkat0)),
+        new_fn("mknod0", "(JIJ)V", Box::new(jvm_mknod0)),
+        new_fn("rename0", "(JJ)V", Box::new(jvm_rename0)),
+        new_fn("renameat0", "(IJIJ)V", Box::new(jvm_renameat0)),
+        new_fn("mkdir0", "(JI)V", Box::new(jvm_mkdir0)),
+        new_fn("rmdir0", "(J)V", Box::new(jvm_rmdir0)),
+        new_fn("readlink0", "(J)[B", Box::new(jvm_readlink0)),
+        new_fn("realpath0", "(J)[B", Box::new(jvm_realpath0)),
+        new_fn("symlink0", "(JJ)V", Box::new(jvm_symlink0)),
+        new_fn(
+            "stat0",
+            "(JLsun/nio/fs/UnixFileAttributes;)V",
+            Box::new(jvm_stat0),
+        ),
+        new_fn(
+            "lstat0",
+            "(JLsun/nio/fs/UnixFileAttributes;)V",
+            Box::new(jvm_lstat0),
+        ),
+        new_fn(
+            "fstat",
+            "(ILsun/nio/fs/UnixFileAttributes;)V",
+            Box::new(jvm_fstat),
+        ),
+        new_fn(
+            "fstatat0",
+            "(IJILsun/nio/fs/UnixFileAttributes;)V",
+            Box::new(jvm_fstatat0),
+        ),
+        new_fn("chown0", "(JII)V", Box::new(jvm_chown0)),
+        new_fn("lchown0", "(JII)V", Box::new(jvm_lchown0)),
+        new_fn("fchown", "(III)V", Box::new(jvm_fchown)),
+        new_fn("chmod0", "(JI)V", Box::new(jvm_chmod0)),
+        new_fn("fchmod", "(II)V", Box::new(jvm_fchmod)),
+        new_fn("utimes0", "(JJJ)V", Box::new(jvm_utimes0)),
+        new_fn("futimes", "(IJJ)V", Box::new(jvm_futimes)),
+        new_fn("opendir0", "(J)J", Box::new(jvm_opendir0)),
+        new_fn("fdopendir", "(I)J", Box::new(jvm_fdopendir)),
+        new_fn("closedir", "(J)V", Box::new(jvm_closedir)),
+        new_fn("readdir", "(J)[B", Box::new(jvm_readdir)),
+        new_fn("read", "(IJI)I", Box::new(jvm_read)),
+        new_fn("write", "(IJI)I", Box::new(jvm_write)),
+        new_fn("access0", "(JI)V", Box::new(jvm_access0)),
+        new_fn("getpwuid", "(I)[B", Box::new(jvm_getpwuid)),
+        new_fn("getgrgid", "(I)[B", Box::new(jvm_getgrgid)),
+        new_fn("getpwnam0", "(J)I", Box::new(jvm_getpwnam0)),
+        new_fn("getgrnam0", "(J)I", Box::new(jvm_getgrnam0)),
+        new_fn(
+            "statvfs0",
+            "(JLsun/nio/fs/UnixFileStoreAttributes;)V",
+            Box::new(jvm_statvfs0),
+        ),
+        new_fn("pathconf0", "(JI)J", Box::new(jvm_pathconf0)),
+        new_fn("fpathconf", "(II)J", Box::new(jvm_fpathconf)),
+        new_fn("strerror", "(I)[B", Box::new(jvm_strerror)),
+    ]
+}
+
+fn jvm_init(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(SUPPORTS_OPENAT | SUPPORTS_FUTIMES)))
+}
+
+fn jvm_getcwd(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mut buf = vec![0u8; libc::PATH_MAX as usize + 1];
+    let p = unsafe { libc::getcwd(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if p.is_null() {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(new_bytes(p)))
+}
+
+fn jvm_dup(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let v = check(restartable(|| unsafe { libc::dup(fd) }))?;
+    Ok(Some(Oop::new_int(v)))
+}
+
+fn jvm_open0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let flags = int_arg(args, 1);
+    let mode = int_arg(args, 2) as libc::c_uint;
+
+    let fd = blocking(|| restartable(|| unsafe { libc::open(path, flags, mode) }));
+    let fd = check(fd)?;
+    Ok(Some(Oop::new_int(fd)))
+}
+
+fn jvm_openat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dfd = int_arg(args, 0);
+    let path = path_arg(args, 1);
+    let flags = int_arg(args, 2);
+    let mode = int_arg(args, 3) as libc::c_uint;
+
+    let fd = blocking(|| restartable(|| unsafe { libc::openat(dfd, path, flags, mode) }));
+    let fd = check(fd)?;
+    Ok(Some(Oop::new_int(fd)))
+}
+
+fn jvm_close(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    unsafe {
+        libc::close(fd);
+    }
+    Ok(None)
+}
+
+fn jvm_fopen0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let mode = path_arg(args, 1);
+
+    let fp = unsafe { libc::fopen(path, mode) };
+    if fp.is_null() {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(Oop::new_long(fp as i64)))
+}
+
+fn jvm_fclose(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fp = args.first().unwrap().extract_long() as *mut libc::FILE;
+    check(unsafe { libc::fclose(fp) })?;
+    Ok(None)
+}
+
+fn jvm_link0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let existing = path_arg(args, 0);
+    let new = path_arg(args, 1);
+    check(unsafe { libc::link(existing, new) })?;
+    Ok(None)
+}
+
+fn jvm_unlink0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    check(unsafe { libc::unlink(path) })?;
+    Ok(None)
+}
+
+fn jvm_unlinkat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dfd = int_arg(args, 0);
+    let path = path_arg(args, 1);
+    let flag = int_arg(args, 2);
+    check(unsafe { libc::unlinkat(dfd, path, flag) })?;
+    Ok(None)
+}
+
+fn jvm_mknod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let mode = int_arg(args, 1) as libc::mode_t;
+    let dev = args.get(2).unwrap().extract_long() as libc::dev_t;
+    check(unsafe { libc::mknod(path, mode, dev) })?;
+    Ok(None)
+}
+
+fn jvm_rename0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let from = path_arg(args, 0);
+    let to = path_arg(args, 1);
+    check(unsafe { libc::rename(from, to) })?;
+    Ok(None)
+}
+
+fn jvm_renameat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let from_fd = int_arg(args, 0);
+    let from = path_arg(args, 1);
+    let to_fd = int_arg(args, 2);
+    let to = path_arg(args, 3);
+    check(unsafe { libc::renameat(from_fd, from, to_fd, to) })?;
+    Ok(None)
+}
+
+fn jvm_mkdir0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let mode = int_arg(args, 1) as libc::mode_t;
+    check(unsafe { libc::mkdir(path, mode) })?;
+    Ok(None)
+}
+
+fn jvm_rmdir0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    check(unsafe { libc::rmdir(path) })?;
+    Ok(None)
+}
+
+fn jvm_readlink0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+
+    let mut buf = vec![0u8; libc::PATH_MAX as usize + 1];
+    let n = unsafe { libc::readlink(path, buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if n < 0 {
+        return Err(nio::last_unix_exception());
+    }
+    //truncated
+    if n as usize == buf.len() {
+        return Err(nio::unix_exception(libc::ENAMETOOLONG));
+    }
+
+    buf.truncate(n as usize);
+    Ok(Some(Oop::new_byte_ary2(buf)))
+}
+
+fn jvm_realpath0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+
+    let mut buf = vec![0u8; libc::PATH_MAX as usize + 1];
+    let p = unsafe { libc::realpath(path, buf.as_mut_ptr() as *mut libc::c_char) };
+    if p.is_null() {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(new_bytes(p)))
+}
+
+fn jvm_symlink0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let target = path_arg(args, 0);
+    let link = path_arg(args, 1);
+    check(unsafe { libc::symlink(target, link) })?;
+    Ok(None)
+}
+
+fn jvm_stat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let attrs = args.get(1).unwrap();
+
+    let mut st: libc::stat = unsafe { mem::zeroed() };
+    check(restartable(|| unsafe { libc::stat(path, &mut st) }))?;
+    put_attributes(attrs, &st);
+
+    Ok(None)
+}
+
+fn jvm_lstat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let attrs = args.get(1).unwrap();
+
+    let mut st: libc::stat = unsafe { mem::zeroed() };
+    check(restartable(|| unsafe { libc::lstat(path, &mut st) }))?;
+    put_attributes(attrs, &st);
+
+    Ok(None)
+}
+
+fn jvm_fstat(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let attrs = args.get(1).unwrap();
+
+    let mut st: libc::stat = unsafe { mem::zeroed() };
+    check(restartable(|| unsafe { libc::fstat(fd, &mut st) }))?;
+    put_attributes(attrs, &st);
+
+    Ok(None)
+}
+
+fn jvm_fstatat0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dfd = int_arg(args, 0);
+    let path = path_arg(args, 1);
+    let flag = int_arg(args, 2);
+    let attrs = args.get(3).unwrap();
+
+    let mut st: libc::stat = unsafe { mem::zeroed() };
+    check(restartable(|| unsafe {
+        libc::fstatat(dfd, path, &mut st, flag)
+    }))?;
+    put_attributes(attrs, &st);
+
+    Ok(None)
+}
+
+fn jvm_chown0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let uid = int_arg(args, 1) as libc::uid_t;
+    let gid = int_arg(args, 2) as libc::gid_t;
+    check(unsafe { libc::chown(path, uid, gid) })?;
+    Ok(None)
+}
+
+fn jvm_lchown0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let uid = int_arg(args, 1) as libc::uid_t;
+    let gid = int_arg(args, 2) as libc::gid_t;
+    check(unsafe { libc::lchown(path, uid, gid) })?;
+    Ok(None)
+}
+
+fn jvm_fchown(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let uid = int_arg(args, 1) as libc::uid_t;
+    let gid = int_arg(args, 2) as libc::gid_t;
+    check(unsafe { libc::fchown(fd, uid, gid) })?;
+    Ok(None)
+}
+
+fn jvm_chmod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let mode = int_arg(args, 1) as libc::mode_t;
+    check(unsafe { libc::chmod(path, mode) })?;
+    Ok(None)
+}
+
+fn jvm_fchmod(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let mode = int_arg(args, 1) as libc::mode_t;
+    check(unsafe { libc::fchmod(fd, mode) })?;
+    Ok(None)
+}
+
+//the access & modification times in microseconds
+fn jvm_utimes0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let times = to_timevals(args.get(1).unwrap(), args.get(2).unwrap());
+    check(unsafe { libc::utimes(path, times.as_ptr()) })?;
+    Ok(None)
+}
+
+fn jvm_futimes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let times = to_timevals(args.get(1).unwrap(), args.get(2).unwrap());
+    check(unsafe { libc::futimes(fd, times.as_ptr()) })?;
+    Ok(None)
+}
+
+fn jvm_opendir0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+
+    let dir = unsafe { libc::opendir(path) };
+    if dir.is_null() {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(Oop::new_long(dir as i64)))
+}
+
+fn jvm_fdopendir(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+
+    let dir = unsafe { libc::fdopendir(fd) };
+    if dir.is_null() {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(Oop::new_long(dir as i64)))
+}
+
+fn jvm_closedir(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dir = args.first().unwrap().extract_long() as *mut libc::DIR;
+    check(unsafe { libc::closedir(dir) })?;
+    Ok(None)
+}
+
+//the name of the next entry, '.' & '..' skipped, null at the end
+fn jvm_readdir(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let dir = args.first().unwrap().extract_long() as *mut libc::DIR;
+
+    loop {
+        let entry = unsafe { libc::readdir(dir) };
+        if entry.is_null() {
+            return Ok(Some(Oop::Null));
+        }
+
+        let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+        let name = name.to_bytes();
+        if name != b"." && name != b".." {
+            return Ok(Some(Oop::new_byte_ary2(name.to_vec())));
+        }
+    }
+}
+
+fn jvm_read(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let buf = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let len = int_arg(args, 2) as usize;
+
+    let n = blocking(|| restartable(|| unsafe { libc::read(fd, buf, len) as i32 }));
+    let n = check(n)?;
+    Ok(Some(Oop::new_int(n)))
+}
+
+fn jvm_write(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let buf = args.get(1).unwrap().extract_long() as *const libc::c_void;
+    let len = int_arg(args, 2) as usize;
+
+    let n = blocking(|| restartable(|| unsafe { libc::write(fd, buf, len) as i32 }));
+    let n = check(n)?;
+    Ok(Some(Oop::new_int(n)))
+}
+
+fn jvm_access0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let mode = int_arg(args, 1);
+    check(restartable(|| unsafe { libc::access(path, mode) }))?;
+    Ok(None)
+}
+
+fn jvm_getpwuid(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let uid = int_arg(args, 0) as libc::uid_t;
+
+    let pw = unsafe { libc::getpwuid(uid) };
+    if pw.is_null() {
+        return Err(not_found());
+    }
+
+    Ok(Some(new_bytes(unsafe { (*pw).pw_name })))
+}
+
+fn jvm_getgrgid(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let gid = int_arg(args, 0) as libc::gid_t;
+
+    let gr = unsafe { libc::getgrgid(gid) };
+    if gr.is_null() {
+        return Err(not_found());
+    }
+
+    Ok(Some(new_bytes(unsafe { (*gr).gr_name })))
+}
+
+//-1 if not found
+fn jvm_getpwnam0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = path_arg(args, 0);
+
+    let pw = unsafe { libc::getpwnam(name) };
+    let uid = if pw.is_null() {
+        -1
+    } else {
+        unsafe { (*pw).pw_uid as i32 }
+    };
+
+    Ok(Some(Oop::new_int(uid)))
+}
+
+fn jvm_getgrnam0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let name = path_arg(args, 0);
+
+    let gr = unsafe { libc::getgrnam(name) };
+    let gid = if gr.is_null() {
+        -1
+    } else {
+        unsafe { (*gr).gr_gid as i32 }
+    };
+
+    Ok(Some(Oop::new_int(gid)))
+}
+
+fn jvm_statvfs0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let attrs = args.get(1).unwrap();
+
+    let mut st: libc::statvfs = unsafe { mem::zeroed() };
+    check(restartable(|| unsafe { libc::statvfs(path, &mut st) }))?;
+
+    put_field(attrs, "f_frsize", "J", Oop::new_long(st.f_frsize as i64));
+    put_field(attrs, "f_blocks", "J", Oop::new_long(st.f_blocks as i64));
+    put_field(attrs, "f_bfree", "J", Oop::new_long(st.f_bfree as i64));
+    put_field(attrs, "f_bavail", "J", Oop::new_long(st.f_bavail as i64));
+
+    Ok(None)
+}
+
+fn jvm_pathconf0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let path = path_arg(args, 0);
+    let name = int_arg(args, 1);
+
+    let v = unsafe { libc::pathconf(path, name) };
+    if v < 0 {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+fn jvm_fpathconf(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let fd = int_arg(args, 0);
+    let name = int_arg(args, 1);
+
+    let v = unsafe { libc::fpathconf(fd, name) };
+    if v < 0 {
+        return Err(nio::last_unix_exception());
+    }
+
+    Ok(Some(Oop::new_long(v as i64)))
+}
+
+fn jvm_strerror(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let errno = int_arg(args, 0);
+    let msg = error_message(&io::Error::from_raw_os_error(errno));
+    Ok(Some(Oop::new_byte_ary2(msg.into_bytes())))
+}
+
+////////helper
+
+fn int_arg(args: &[Oop], i: usize) -> i32 {
+    args.get(i).unwrap().extract_int()
+}
+
+//the paths are the NUL terminated strings of NativeBuffer
+fn path_arg(args: &[Oop], i: usize) -> *const libc::c_char {
+    args.get(i).unwrap().extract_long() as *const libc::c_char
+}
+
+fn new_bytes(p: *const libc::c_char) -> Oop {
+    let v = unsafe { CStr::from_ptr(p) };
+    Oop::new_byte_ary2(v.to_bytes().to_vec())
+}
+
+fn check(r: i32) -> Result<i32, Oop> {
+    if r < 0 {
+        Err(nio::last_unix_exception())
+    } else {
+        Ok(r)
+    }
+}
+
+//retried if interrupted by a signal
+fn restartable<F: FnMut() -> i32>(mut f: F) -> i32 {
+    loop {
+        let r = f();
+        if r != -1 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            return r;
+        }
+    }
+}
+
+fn not_found() -> Oop {
+    match io::Error::last_os_error().raw_os_error() {
+        Some(errno) if errno != 0 => nio::unix_exception(errno),
+        _ => nio::unix_exception(libc::ENOENT),
+    }
+}
+
+fn to_timevals(access: &Oop, modification: &Oop) -> [libc::timeval; 2] {
+    let to_timeval = |micros: i64| libc::timeval {
+        tv_sec: (micros / 1_000_000) as libc::time_t,
+        tv_usec: (micros % 1_000_000) as libc::suseconds_t,
+    };
+
+    [
+        to_timeval(access.extract_long()),
+        to_timeval(modification.extract_long()),
+    ]
+}
+
+//the stat field types differ among the platforms
+#[allow(clippy::unnecessary_cast)]
+fn put_attributes(attrs: &Oop, st: &libc::stat) {
+    let int = |name: &str, v: i64| put_field(attrs, name, "I", Oop::new_int(v as i32));
+    let long = |name: &str, v: i64| put_field(attrs, name, "J", Oop::new_long(v));
+
+    int("st_mode", st.st_mode as i64);
+    long("st_ino", st.st_ino as i64);
+    long("st_dev", st.st_dev as i64);
+    long("st_rdev", st.st_rdev as i64);
+    int("st_nlink", st.st_nlink as i64);
+    int("st_uid", st.st_uid as i64);
+    int("st_gid", st.st_gid as i64);
+    long("st_size", st.st_size as i64);
+    long("st_atime_sec", st.st_atime as i64);
+    long("st_atime_nsec", st.st_atime_nsec as i64);
+    long("st_mtime_sec", st.st_mtime as i64);
+    long("st_mtime_nsec", st.st_mtime_nsec as i64);
+    long("st_ctime_sec", st.st_ctime as i64);
+    long("st_ctime_nsec", st.st_ctime_nsec as i64);
+}
@@ -1,37 +1,160 @@
 #![allow(non_snake_case)]
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop};
+use crate::runtime::{self, require_class2};
 use crate::util;
-use classfile::constant_pool;
+use classfile::{constant_pool, consts as cls_consts, ConstantPool, ConstantPoolType};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
-    vec![new_fn(
-        "getUTF8At0",
-        "(Ljava/lang/Object;I)Ljava/lang/String;",
-        Box::new(jvm_getUTF8At0),
-    )]
+    vec![
+        new_fn("getSize0", "(Ljava/lang/Object;)I", Box::new(jvm_getSize0)),
+        new_fn(
+            "getClassAt0",
+            "(Ljava/lang/Object;I)Ljava/lang/Class;",
+            Box::new(jvm_getClassAt0),
+        ),
+        new_fn(
+            "getIntAt0",
+            "(Ljava/lang/Object;I)I",
+            Box::new(jvm_getIntAt0),
+        ),
+        new_fn(
+            "getLongAt0",
+            "(Ljava/lang/Object;I)J",
+            Box::new(jvm_getLongAt0),
+        ),
+        new_fn(
+            "getFloatAt0",
+            "(Ljava/lang/Object;I)F",
+            Box::new(jvm_getFloatAt0),
+        ),
+        new_fn(
+            "getDoubleAt0",
+            "(Ljava/lang/Object;I)D",
+            Box::new(jvm_getDoubleAt0),
+        ),
+        new_fn(
+            "getStringAt0",
+            "(Ljava/lang/Object;I)Ljava/lang/String;",
+            Box::new(jvm_getStringAt0),
+        ),
+        new_fn(
+            "getUTF8At0",
+            "(Ljava/lang/Object;I)Ljava/lang/String;",
+            Box::new(jvm_getUTF8At0),
+        ),
+    ]
 }
 
-fn jvm_getUTF8At0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let _this = args.get(0).unwrap();
+fn jvm_getSize0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    Ok(Some(Oop::new_int(cp.len() as i32)))
+}
+
+fn jvm_getClassAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let cp_oop = args.get(1).unwrap();
-    let index = {
-        let index = args.get(2).unwrap();
-        index.extract_int()
-    };
+    let index = args.get(2).unwrap().extract_int() as usize;
+    let cp = get_cp(cp_oop);
+    match cp.get(index) {
+        Some(ConstantPoolType::Class { .. }) => {
+            let cl = {
+                let rf = cp_oop.extract_ref();
+                let mirror = rf.extract_mirror();
+                let target = mirror.target.clone().unwrap();
+                let cls = target.get_class();
+                cls.class_loader
+            };
+            match require_class2(cl, index as u16, &cp) {
+                Some(cls) => Ok(Some(cls.get_class().get_mirror())),
+                None => {
+                    let name = constant_pool::get_class_name(&cp, index);
+                    let name = String::from_utf8_lossy(name.as_slice()).replace("/", ".");
+                    Err(runtime::exception::new(
+                        cls_consts::J_NO_CLASS_DEF_FOUND_ERROR,
+                        Some(name),
+                    ))
+                }
+            }
+        }
+        _ => Err(wrong_type()),
+    }
+}
+
+fn jvm_getIntAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::Integer { v }) => Ok(Some(Oop::new_int(i32::from_be_bytes(*v)))),
+        _ => Err(wrong_type()),
+    }
+}
+
+fn jvm_getLongAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::Long { v }) => Ok(Some(Oop::new_long(i64::from_be_bytes(*v)))),
+        _ => Err(wrong_type()),
+    }
+}
+
+fn jvm_getFloatAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::Float { v }) => Ok(Some(Oop::new_float(f32::from_be_bytes(*v)))),
+        _ => Err(wrong_type()),
+    }
+}
 
+fn jvm_getDoubleAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::Double { v }) => Ok(Some(Oop::new_double(f64::from_be_bytes(*v)))),
+        _ => Err(wrong_type()),
+    }
+}
+
+fn jvm_getStringAt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::String { string_index }) => {
+            let s = constant_pool::get_utf8(&cp, *string_index as usize);
+            Ok(Some(util::oop::new_java_lang_string3(s.as_slice())))
+        }
+        _ => Err(wrong_type()),
+    }
+}
+
+fn jvm_getUTF8At0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let cp = get_cp(args.get(1).unwrap());
+    let index = args.get(2).unwrap().extract_int() as usize;
+    match cp.get(index) {
+        Some(ConstantPoolType::Utf8 { .. }) => {
+            let s = constant_pool::get_utf8(&cp, index);
+            Ok(Some(util::oop::new_java_lang_string3(s.as_slice())))
+        }
+        _ => Err(wrong_type()),
+    }
+}
+
+//the constantPoolOop is the mirror of the class
+fn get_cp(cp_oop: &Oop) -> ConstantPool {
     let rf = cp_oop.extract_ref();
     let mirror = rf.extract_mirror();
     let target = mirror.target.clone().unwrap();
     let cls = target.get_class();
     match &cls.kind {
-        oop::class::ClassKind::Instance(inst) => {
-            let cp = &inst.class_file.cp;
-            let s = constant_pool::get_utf8(cp, index as usize);
-            let s = unsafe { std::str::from_utf8_unchecked(s.as_slice()) };
-            let r = util::oop::new_java_lang_string2(s);
-            Ok(Some(r))
-        }
+        oop::class::ClassKind::Instance(inst) => inst.class_file.cp.clone(),
         _ => unimplemented!(),
     }
 }
+
+fn wrong_type() -> Oop {
+    runtime::exception::new(
+        cls_consts::J_ILLEGAL_ARGUMENT,
+        Some("Wrong type at constant pool index".to_string()),
+    )
+}
@@ -1,8 +1,8 @@
 #![allow(non_snake_case)]
 
 use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::Oop;
-use crate::{new_br, runtime};
+use crate::oop::{self, Oop};
+use crate::runtime;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![new_fn(
@@ -13,37 +13,24 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
 }
 
 fn jvm_newInstance0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let ctor = args.get(0).unwrap();
+    let ctor = args.first().unwrap();
     let arguments = args.get(1).unwrap();
 
-    let clazz = common::reflect::get_Constructor_clazz(ctor);
-    let target_cls = {
-        let rf = clazz.extract_ref();
-        let mirror = rf.extract_mirror();
-        mirror.target.clone().unwrap()
-    };
-
-    let signature = common::reflect::get_Constructor_signature(ctor);
-
-    let cls = target_cls.get_class();
-    let name = unsafe { std::str::from_utf8_unchecked(cls.name.as_slice()) };
-    info!("newInstance0 {}:{}", name, signature);
-
-    let mut ctor_args = Vec::new();
-    {
-        match arguments {
-            Oop::Null => (),
-            Oop::Ref(rf) => {
-                let ary = rf.extract_array();
-                ctor_args.extend_from_slice(ary.elements.as_slice());
-            }
-            _ => unreachable!(),
-        }
+    let (target_cls, slot) = common::reflect::get_clazz_slot(ctor);
+    let mir = common::reflect::get_method_by_slot(&target_cls, slot);
+    info!("newInstance0 {:?}", mir);
+
+    let mut ctor_args = common::reflect::unbox_args(&mir, arguments)?;
+
+    oop::class::init_class(&target_cls);
+    oop::class::init_class_fully(&target_cls);
+    if runtime::thread::is_meet_ex() {
+        return Ok(None);
     }
 
-    let oop = Oop::new_inst(target_cls.clone());
+    let oop = Oop::new_inst(target_cls);
     ctor_args.insert(0, oop.clone());
-    runtime::invoke::invoke_ctor(target_cls, new_br(signature.as_str()), ctor_args);
+    common::reflect::invoke(mir, ctor_args, true)?;
 
     Ok(Some(oop))
 }
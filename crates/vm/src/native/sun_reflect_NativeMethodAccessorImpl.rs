@@ -1,11 +1,9 @@
 #![allow(non_snake_case)]
 
-use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::new_br;
-use crate::oop::{self, Class, Oop, OopPtr};
-use crate::runtime::{self, require_class3};
-use crate::util;
-use classfile::{consts as cls_consts, SignatureType};
+use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{self, Oop};
+use crate::runtime;
+use classfile::consts as cls_consts;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![new_fn(
@@ -16,97 +14,38 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
 }
 
 fn jvm_invoke0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let method = args.get(0).unwrap();
+    let method = args.first().unwrap();
     let obj = args.get(1).unwrap();
     let args = args.get(2).unwrap();
 
-    let cls = require_class3(None, cls_consts::J_METHOD).unwrap();
+    let (clz, slot) = common::reflect::get_clazz_slot(method);
+    let mir = common::reflect::get_method_by_slot(&clz, slot);
+    let mut args = common::reflect::unbox_args(&mir, args)?;
 
-    let (m_clazz, m_name, m_signature) = {
-        let cls = cls.get_class();
-
-        let fid = cls.get_field_id(&util::S_CLAZZ, &util::S_JAVA_LANG_CLASS, false);
-        let method_clazz = Class::get_field_value(method.extract_ref(), fid);
-
-        let fid = cls.get_field_id(&util::S_NAME, &util::S_JAVA_LANG_STRING, false);
-        let method_name = Class::get_field_value(method.extract_ref(), fid);
-        let method_name = OopPtr::java_lang_string(method_name.extract_ref());
-        let method_name = new_br(method_name.as_str());
-
-        let fid = cls.get_field_id(&util::S_SIGNATURE, &util::S_JAVA_LANG_STRING, false);
-        let signature = Class::get_field_value(method.extract_ref(), fid);
-        let signature = OopPtr::java_lang_string(signature.extract_ref());
-        let signature = new_br(signature.as_str());
-
-        (method_clazz, method_name, signature)
-    };
-
-    let clz = {
-        let rf = m_clazz.extract_ref();
-        let mirror = rf.extract_mirror();
-        mirror.target.clone().unwrap()
-    };
-
-    let mir = {
-        let clz = clz.get_class();
-        clz.get_class_method(&m_name, &m_signature).unwrap()
-    };
-
-    // {
-    //     let cls = clz.read().unwrap();
-    //     let cls_name = cls.name.clone();
-    //     error!(
-    //         "invoke0 {}:{}:{} static={}, native={}",
-    //         String::from_utf8_lossy(cls_name.as_slice()),
-    //         String::from_utf8_lossy(mir.method.name.as_slice()),
-    //         String::from_utf8_lossy(mir.method.desc.as_slice()),
-    //         mir.method.is_static(),
-    //         mir.method.is_native(),
-    //     );
-    // }
+    if mir.method.is_static() {
+        oop::class::init_class(&clz);
+        oop::class::init_class_fully(&clz);
+        if runtime::thread::is_meet_ex() {
+            return Ok(None);
+        }
+    } else {
+        if obj.is_null() {
+            return Err(runtime::exception::new(cls_consts::J_NPE, None));
+        }
 
-    let mut args = {
-        let rf = args.extract_ref();
-        let ary = rf.extract_array();
-        ary.elements.to_vec()
-    };
+        if !common::reflect::is_instance(obj, &clz) {
+            return Err(runtime::exception::new(
+                cls_consts::J_ILLEGAL_ARGUMENT,
+                Some("object is not an instance of declaring class".to_string()),
+            ));
+        }
 
-    if !mir.method.is_static() {
         args.insert(0, obj.clone());
     }
 
-    let force_no_resolve = mir.method.name.as_slice() == b"<init>" || mir.method.is_static();
-    let mut jc = runtime::invoke::JavaCall::new_with_args(mir, args);
-    let area = runtime::DataArea::new(0);
-    jc.invoke(Some(&area), force_no_resolve);
-
-    let r = {
-        let mut stack = area.stack.borrow_mut();
-        match jc.mir.method.signature.retype {
-            SignatureType::Byte
-            | SignatureType::Char
-            | SignatureType::Boolean
-            | SignatureType::Short
-            | SignatureType::Int => {
-                let v = stack.pop_int();
-                Some(oop::Oop::new_int(v))
-            }
-            SignatureType::Double => {
-                let v = stack.pop_double();
-                Some(oop::Oop::new_double(v))
-            }
-            SignatureType::Float => {
-                let v = stack.pop_float();
-                Some(oop::Oop::new_float(v))
-            }
-            SignatureType::Long => {
-                let v = stack.pop_long();
-                Some(oop::Oop::new_long(v))
-            }
-            SignatureType::Object(_, _, _) | SignatureType::Array(_) => Some(stack.pop_ref()),
-            SignatureType::Void => Some(Oop::Null),
-        }
-    };
+    //the virtual methods are selected by the class of the receiver
+    let force_no_resolve = mir.method.is_static();
+    let r = common::reflect::invoke(mir, args, force_no_resolve)?;
 
-    Ok(r)
+    Ok(Some(r))
 }
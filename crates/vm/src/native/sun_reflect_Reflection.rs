@@ -1,8 +1,10 @@
 #![allow(non_snake_case)]
 
-use crate::native::{self, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::Oop;
-use crate::runtime;
+use crate::runtime::{self, require_class3};
+use crate::types::MethodIdRef;
+use classfile::flags::{ACC_ABSTRACT, ACC_FINAL, ACC_PUBLIC};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -41,6 +43,7 @@ fn jvm_getCallerClass(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
         if caller
             .method
             .check_annotation(b"Lsun/reflect/CallerSensitive;")
+            || is_ignored_by_security_stack_walk(&caller)
         {
             continue;
         }
@@ -51,6 +54,27 @@ fn jvm_getCallerClass(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     }
 }
 
-fn jvm_getClassAccessFlags(env: JNIEnv, args: &[Oop]) -> JNIResult {
-    native::java_lang_Class::jvm_getModifiers(env, args)
+//the frames of the reflective invocation, Method.invoke is caller sensitive
+fn is_ignored_by_security_stack_walk(mir: &MethodIdRef) -> bool {
+    match require_class3(None, b"sun/reflect/MethodAccessorImpl") {
+        Some(accessor) => runtime::cmp::instance_of(mir.method.class.clone(), accessor),
+        None => false,
+    }
+}
+
+//the flags of the class file, not of the InnerClasses attribute as Class.getModifiers
+fn jvm_getClassAccessFlags(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mirror = args.first().unwrap();
+    let target = {
+        let rf = mirror.extract_ref();
+        let mirror = rf.extract_mirror();
+        mirror.target.clone()
+    };
+
+    let v = match target {
+        Some(target) => target.get_class().acc_flags,
+        None => ACC_ABSTRACT | ACC_FINAL | ACC_PUBLIC,
+    };
+
+    Ok(Some(Oop::new_int(v as i32)))
 }
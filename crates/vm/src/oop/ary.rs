@@ -4,7 +4,8 @@ use crate::types::*;
 #[derive(Debug, Clone)]
 pub struct ArrayOopDesc {
     pub class: ClassRef,
-    pub elements: Vec<Oop>,
+    //each element holds a count of its object, as a field does
+    pub(crate) elements: Vec<Oop>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +31,18 @@ pub enum TypeArrayEnum {
     Long,
 }
 
+impl TypeArrayEnum {
+    /// The bytes of an element
+    pub fn elm_size(&self) -> usize {
+        match self {
+            TypeArrayEnum::Boolean | TypeArrayEnum::Byte => 1,
+            TypeArrayEnum::Char | TypeArrayEnum::Short => 2,
+            TypeArrayEnum::Int | TypeArrayEnum::Float => 4,
+            TypeArrayEnum::Long | TypeArrayEnum::Double => 8,
+        }
+    }
+}
+
 impl ArrayOopDesc {
     pub fn new(class: ClassRef, elements: Vec<Oop>) -> Self {
         {
@@ -39,6 +52,31 @@ impl ArrayOopDesc {
         Self { class, elements }
     }
 
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The element at 'index', panics if out of bounds
+    pub fn get(&self, index: usize) -> Oop {
+        self.elements[index].clone()
+    }
+
+    pub fn set(&mut self, index: usize, v: &Oop) {
+        self.elements[index] = v.clone();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Oop> + '_ {
+        self.elements.iter().cloned()
+    }
+
+    pub fn to_vec(&self) -> Vec<Oop> {
+        self.iter().collect()
+    }
+
     pub fn get_dimension(&self) -> usize {
         let class = self.class.get_class();
         match &class.kind {
@@ -49,6 +87,33 @@ impl ArrayOopDesc {
     }
 }
 
+//System.arraycopy of the reference arrays
+impl ArrayOopDesc {
+    //the ranges may overlap, copied in the order not overwriting the source first
+    pub fn copy_within(&mut self, src_pos: usize, dest_pos: usize, length: usize) {
+        if dest_pos <= src_pos {
+            for i in 0..length {
+                self.elements[dest_pos + i] = self.elements[src_pos + i].clone();
+            }
+        } else {
+            for i in (0..length).rev() {
+                self.elements[dest_pos + i] = self.elements[src_pos + i].clone();
+            }
+        }
+    }
+
+    pub fn copy_from(
+        &mut self,
+        src: &ArrayOopDesc,
+        src_pos: usize,
+        dest_pos: usize,
+        length: usize,
+    ) {
+        let src = &src.elements[src_pos..src_pos + length];
+        self.elements[dest_pos..dest_pos + length].clone_from_slice(src);
+    }
+}
+
 impl TypeArrayDesc {
     pub fn len(&self) -> usize {
         match self {
@@ -62,6 +127,114 @@ impl TypeArrayDesc {
             TypeArrayDesc::Long(ary) => ary.len(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //the bytes of the elements
+    pub fn bytes_len(&self) -> usize {
+        match self {
+            TypeArrayDesc::Char(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Byte(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Bool(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Short(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Float(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Double(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Int(ary) => std::mem::size_of_val(&ary[..]),
+            TypeArrayDesc::Long(ary) => std::mem::size_of_val(&ary[..]),
+        }
+    }
+
+    pub fn class_name(&self) -> &'static [u8] {
+        match self {
+            TypeArrayDesc::Char(_) => b"[C",
+            TypeArrayDesc::Byte(_) => b"[B",
+            TypeArrayDesc::Bool(_) => b"[Z",
+            TypeArrayDesc::Short(_) => b"[S",
+            TypeArrayDesc::Float(_) => b"[F",
+            TypeArrayDesc::Double(_) => b"[D",
+            TypeArrayDesc::Int(_) => b"[I",
+            TypeArrayDesc::Long(_) => b"[J",
+        }
+    }
+}
+
+//System.arraycopy of the primitive arrays, a memmove
+impl TypeArrayDesc {
+    //the ranges may overlap
+    pub fn copy_within(&mut self, src_pos: usize, dest_pos: usize, length: usize) {
+        let src = src_pos..src_pos + length;
+        match self {
+            TypeArrayDesc::Char(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Byte(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Bool(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Short(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Float(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Double(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Int(ary) => ary.copy_within(src, dest_pos),
+            TypeArrayDesc::Long(ary) => ary.copy_within(src, dest_pos),
+        }
+    }
+
+    //false if the element types are different
+    pub fn copy_from(
+        &mut self,
+        src: &TypeArrayDesc,
+        src_pos: usize,
+        dest_pos: usize,
+        length: usize,
+    ) -> bool {
+        let s = src_pos..src_pos + length;
+        let d = dest_pos..dest_pos + length;
+        match (self, src) {
+            (TypeArrayDesc::Char(dest), TypeArrayDesc::Char(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Byte(dest), TypeArrayDesc::Byte(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Bool(dest), TypeArrayDesc::Bool(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Short(dest), TypeArrayDesc::Short(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Float(dest), TypeArrayDesc::Float(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Double(dest), TypeArrayDesc::Double(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            (TypeArrayDesc::Int(dest), TypeArrayDesc::Int(src)) => dest[d].copy_from_slice(&src[s]),
+            (TypeArrayDesc::Long(dest), TypeArrayDesc::Long(src)) => {
+                dest[d].copy_from_slice(&src[s])
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+//Unsafe.copyMemory & friends, the elements as raw memory, in native byte order
+impl TypeArrayDesc {
+    pub fn as_mut_bytes(&mut self) -> &mut [u8] {
+        fn raw<T>(v: &mut [T]) -> &mut [u8] {
+            let len = std::mem::size_of_val(v);
+            unsafe { std::slice::from_raw_parts_mut(v.as_mut_ptr() as *mut u8, len) }
+        }
+
+        match self {
+            TypeArrayDesc::Char(ary) => raw(ary),
+            TypeArrayDesc::Byte(ary) => raw(ary),
+            TypeArrayDesc::Bool(ary) => raw(ary),
+            TypeArrayDesc::Short(ary) => raw(ary),
+            TypeArrayDesc::Float(ary) => raw(ary),
+            TypeArrayDesc::Double(ary) => raw(ary),
+            TypeArrayDesc::Int(ary) => raw(ary),
+            TypeArrayDesc::Long(ary) => raw(ary),
+        }
+    }
 }
 
 impl TypeArrayDesc {
@@ -193,3 +366,61 @@ impl From<u8> for TypeArrayEnum {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ArrayOopDesc, TypeArrayDesc};
+    use crate::oop::{class::ClassPtr, Class, Oop, OopPtr, ValueType};
+    use crate::runtime::ClassLoader;
+
+    //the element i is an int[] of {i}
+    fn new_ary(n: i32) -> ArrayOopDesc {
+        let class = ClassPtr::new(Class::new_prime_ary(ClassLoader::Bootstrap, ValueType::INT));
+        let elements = (0..n).map(|i| Oop::new_int_ary2(vec![i])).collect();
+        ArrayOopDesc::new(class, elements)
+    }
+
+    fn ints(ary: &ArrayOopDesc) -> Vec<i32> {
+        ary.iter()
+            .map(|v| v.extract_ref().extract_type_array().extract_ints()[0])
+            .collect()
+    }
+
+    #[test]
+    fn t_copy_within_overlapped() {
+        let mut ary = new_ary(8);
+        ary.copy_within(1, 0, 6);
+        assert_eq!(ints(&ary), vec![1, 2, 3, 4, 5, 6, 6, 7]);
+
+        let mut ary = new_ary(8);
+        ary.copy_within(0, 2, 5);
+        assert_eq!(ints(&ary), vec![0, 1, 0, 1, 2, 3, 4, 7]);
+
+        let mut ary = new_ary(8);
+        ary.copy_within(5, 0, 3);
+        assert_eq!(ints(&ary), vec![5, 6, 7, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn t_copy_from() {
+        let mut dest = new_ary(4);
+        let src = new_ary(3);
+        dest.copy_from(&src, 1, 2, 2);
+        assert_eq!(ints(&dest), vec![0, 1, 1, 2]);
+        assert!(OopPtr::is_eq(&dest.get(3), &src.get(2)));
+
+        dest.set(0, &Oop::Null);
+        assert!(dest.get(0).is_null());
+    }
+
+    #[test]
+    fn t_type_array_copy() {
+        let mut dest = TypeArrayDesc::Int(Box::new(vec![0; 4]));
+        let src = TypeArrayDesc::Int(Box::new(vec![1, 2, 3]));
+        assert!(dest.copy_from(&src, 1, 2, 2));
+        assert_eq!(dest.extract_ints().as_slice(), &[0, 0, 2, 3]);
+
+        let bytes = TypeArrayDesc::Byte(Box::new(vec![1, 2, 3]));
+        assert!(!dest.copy_from(&bytes, 0, 0, 1));
+    }
+}
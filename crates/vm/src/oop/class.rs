@@ -6,13 +6,13 @@ use std::sync::{Arc, Mutex};
 use rustc_hash::FxHashMap;
 
 use classfile::{
-    attributes::EnclosingMethod, attributes::InnerClass, constant_pool,
-    constant_pool::get_utf8 as get_cp_utf8, consts, flags::*, AttributeType, BytesRef, U2,
+    attributes::BootstrapMethod, attributes::EnclosingMethod, attributes::InnerClass,
+    constant_pool, constant_pool::get_utf8 as get_cp_utf8, consts, flags::*, AttributeType,
+    BytesRef, OpCode, U2,
 };
 
 use crate::oop::{self, consts as oop_consts, field, Oop, OopPtr, RefKindDesc, ValueType};
 use crate::runtime::method::MethodId;
-use crate::runtime::thread::ReentrantMutex;
 use crate::runtime::{
     self, method, require_class2, ClassLoader, ConstantPoolCache, JavaCall, JavaThread,
 };
@@ -89,7 +89,6 @@ impl Debug for ClassPtr {
 
 pub struct Class {
     clinit_mutex: Arc<std::sync::Mutex<()>>,
-    mutex: ReentrantMutex,
     state: std::sync::atomic::AtomicU8,
 
     pub name: BytesRef,
@@ -117,6 +116,15 @@ pub enum ClassKindType {
     TypAry,
 }
 
+//the subclasses of java.lang.ref.Reference, the referent is treated by gc
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReferenceType {
+    Soft,
+    Weak,
+    Final,
+    Phantom,
+}
+
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
 pub enum State {
     Allocated,
@@ -161,7 +169,14 @@ pub struct ClassObject {
 
     //  FxHashMap<(name, desc), MethodIdRef>
     pub all_methods: FxHashMap<(BytesRef, BytesRef), MethodIdRef>,
-    v_table: FxHashMap<(BytesRef, BytesRef), MethodIdRef>,
+
+    //the instance methods selected for the class, the slots of the super class first;
+    //an interface has its own methods only, they are the layout of its itable
+    v_table: Vec<VTableEntry>,
+    //  FxHashMap<(name, desc), index of v_table>
+    v_table_index: FxHashMap<(BytesRef, BytesRef), usize>,
+    //the interfaces implemented directly or not, with the v_table index of each itable slot
+    i_tables: Vec<(ClassRef, Vec<usize>)>,
 
     //  FxHashMap<(package, name, desc), FieldIdRef>
     pub static_fields: FxHashMap<(BytesRef, BytesRef, BytesRef), FieldIdRef>,
@@ -177,8 +192,30 @@ pub struct ClassObject {
     pub source_file: Option<BytesRef>,
     pub enclosing_method: Option<EnclosingMethod>,
     pub inner_classes: Option<Vec<InnerClass>>,
+    pub bootstrap_methods: Option<Vec<BootstrapMethod>>,
 
     pub cp_cache: ConstantPoolCache,
+    //the objects of the String entries, patched by Unsafe.defineAnonymousClass
+    pub cp_patches: FxHashMap<usize, Oop>,
+
+    //inherited from the super class
+    pub ref_type: Option<ReferenceType>,
+    pub has_finalizer: bool,
+}
+
+#[derive(Clone)]
+pub enum VTableEntry {
+    Method(MethodIdRef),
+    //more than one maximally-specific default method, invoking it is an IncompatibleClassChangeError
+    Conflict(MethodIdRef, MethodIdRef),
+}
+
+//the slot of a method dispatched by the class of the receiver
+#[derive(Clone)]
+pub enum DispatchIndex {
+    VTable(usize),
+    //the interface declaring the method, the slot in its itable
+    ITable(ClassRef, usize),
 }
 
 pub struct ArrayClassObject {
@@ -253,6 +290,127 @@ pub fn load_and_init(name: &[u8]) -> ClassRef {
     class
 }
 
+//none if the method is not selected by the receiver: static, private, final, <init>
+pub fn dispatch_index(mir: &MethodIdRef) -> Option<DispatchIndex> {
+    let method = &mir.method;
+    if !is_virtual(method) || method.is_final() {
+        return None;
+    }
+
+    let cls = method.class.get_class();
+    let i = match &cls.kind {
+        ClassKind::Instance(cls_obj) => {
+            let k = (method.name.clone(), method.desc.clone());
+            *cls_obj.v_table_index.get(&k)?
+        }
+        _ => return None,
+    };
+
+    if cls.is_interface() {
+        Some(DispatchIndex::ITable(method.class.clone(), i))
+    } else {
+        Some(DispatchIndex::VTable(i))
+    }
+}
+
+fn is_virtual(method: &method::Method) -> bool {
+    !method.is_static()
+        && !method.is_private()
+        && method.name.as_slice() != b"<init>"
+        && method.name.as_slice() != b"<clinit>"
+}
+
+//JVM spec, 5.4.5, 'm' is overridden by a method of the class 'cls_name' defined by 'class_loader'
+fn can_override(class_loader: ClassLoader, cls_name: &[u8], m: &MethodIdRef) -> bool {
+    let method = &m.method;
+    if method.is_public() || method.is_protected() {
+        return true;
+    }
+
+    //package-private, in the same runtime package
+    let cls = method.class.get_class();
+    cls.class_loader.unwrap_or(ClassLoader::Bootstrap) == class_loader
+        && package_name(cls.name.as_slice()) == package_name(cls_name)
+}
+
+fn package_name(cls_name: &[u8]) -> &[u8] {
+    match cls_name.iter().rposition(|&c| c == b'/') {
+        Some(i) => &cls_name[..i],
+        None => &[],
+    }
+}
+
+//the interfaces implemented by a class, in the itables
+fn collect_interfaces(cls: &ClassRef, out: &mut Vec<ClassRef>) {
+    if let ClassKind::Instance(cls) = &cls.get_class().kind {
+        for (itf, _) in cls.i_tables.iter() {
+            collect_interface(itf, out);
+        }
+    }
+}
+
+//an interface and its super interfaces
+fn collect_interface(itf: &ClassRef, out: &mut Vec<ClassRef>) {
+    if out.iter().any(|it| Arc::ptr_eq(it, itf)) {
+        return;
+    }
+
+    out.push(itf.clone());
+    if let ClassKind::Instance(cls) = &itf.get_class().kind {
+        for it in cls.interfaces.values() {
+            collect_interface(it, out);
+        }
+    }
+}
+
+//the methods declared by an interface, in the order of its itable
+fn interface_methods(itf: &ClassRef) -> Vec<(BytesRef, BytesRef)> {
+    match &itf.get_class().kind {
+        ClassKind::Instance(cls) => cls
+            .v_table
+            .iter()
+            .filter_map(|it| match it {
+                VTableEntry::Method(m) => Some((m.method.name.clone(), m.method.desc.clone())),
+                VTableEntry::Conflict(_, _) => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+//JVM spec, 5.4.3.3, the maximally-specific superinterface methods
+fn select_interface_method(interfaces: &[ClassRef], k: &(BytesRef, BytesRef)) -> VTableEntry {
+    let candidates: Vec<MethodIdRef> = interfaces
+        .iter()
+        .filter_map(|itf| match &itf.get_class().kind {
+            ClassKind::Instance(cls) => match cls.v_table_index.get(k).map(|i| &cls.v_table[*i]) {
+                Some(VTableEntry::Method(m)) => Some(m.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    //not declared again by a subinterface
+    let specific: Vec<&MethodIdRef> = candidates
+        .iter()
+        .filter(|m| {
+            !candidates.iter().any(|other| {
+                let other = &other.method.class;
+                !Arc::ptr_eq(other, &m.method.class)
+                    && other.get_class().check_interface(m.method.class.clone())
+            })
+        })
+        .collect();
+
+    let mut defaults = specific.iter().filter(|m| !m.method.is_abstract());
+    match (defaults.next(), defaults.next()) {
+        (Some(m), None) => VTableEntry::Method((*m).clone()),
+        (Some(m1), Some(m2)) => VTableEntry::Conflict((*m1).clone(), (*m2).clone()),
+        _ => VTableEntry::Method(specific[0].clone()),
+    }
+}
+
 impl Class {
     pub fn get_class_state(&self) -> State {
         let v = self.state.load(Ordering::Relaxed);
@@ -299,19 +457,8 @@ impl Class {
         (self.acc_flags & ACC_INTERFACE) == ACC_INTERFACE
     }
 
-    pub fn monitor_enter(&self) {
-        unsafe {
-            self.mutex.lock();
-        }
-    }
-
-    pub fn monitor_exit(&self) {
-        unsafe {
-            self.mutex.unlock();
-        }
-    }
-
     pub fn link_class(&mut self, self_ref: ClassRef) {
+        let is_interface = self.is_interface();
         match &mut self.kind {
             ClassKind::Instance(class_obj) => {
                 self.super_class = class_obj.link_super_class(self.name.clone(), self.class_loader);
@@ -326,9 +473,12 @@ impl Class {
                     None => 0,
                 };
                 class_obj.link_fields(self_ref.clone(), self.name.clone(), n);
-                class_obj.link_interfaces();
+                class_obj.link_interfaces(self.class_loader);
                 class_obj.link_methods(self_ref, self.name.clone());
+                let class_loader = self.class_loader.unwrap_or(ClassLoader::Bootstrap);
+                class_obj.link_v_table(&self.super_class, is_interface, class_loader, &self.name);
                 class_obj.link_attributes();
+                class_obj.link_ref_type(self.name.as_slice(), &self.super_class);
             }
 
             ClassKind::ObjectArray(ary_class_obj) => {
@@ -484,34 +634,62 @@ impl Class {
         self.get_interface_method_inner(name, desc)
     }
 
+    //the method selected for a receiver of this class, none if the interface is not implemented
+    pub fn select_method(&self, index: &DispatchIndex) -> Option<&VTableEntry> {
+        let cls = match &self.kind {
+            ClassKind::Instance(cls) => cls,
+            _ => return None,
+        };
+
+        match index {
+            DispatchIndex::VTable(i) => cls.v_table.get(*i),
+            DispatchIndex::ITable(itf, i) => cls
+                .i_tables
+                .iter()
+                .find(|(it, _)| Arc::ptr_eq(it, itf))
+                .and_then(|(_, slots)| cls.v_table.get(slots[*i])),
+        }
+    }
+
     pub fn get_field_id(&self, name: &BytesRef, desc: &BytesRef, is_static: bool) -> FieldIdRef {
+        match self.find_field_id(name, desc, is_static) {
+            Some(fid) => fid,
+            None => panic!(
+                "NoSuchField: {}:{}:{}",
+                String::from_utf8_lossy(self.name.as_slice()),
+                String::from_utf8_lossy(name.as_slice()),
+                String::from_utf8_lossy(desc.as_slice())
+            ),
+        }
+    }
+
+    //like get_field_id, None if not found, for the lookup by name from native code
+    pub fn find_field_id(
+        &self,
+        name: &BytesRef,
+        desc: &BytesRef,
+        is_static: bool,
+    ) -> Option<FieldIdRef> {
         let k = (self.name.clone(), name.clone(), desc.clone());
 
-        if is_static {
-            match &self.kind {
-                ClassKind::Instance(cls_obj) => {
-                    if let Some(fid) = cls_obj.static_fields.get(&k) {
-                        return fid.clone();
-                    }
-                }
-                _ => unreachable!(),
-            }
-        } else {
-            match &self.kind {
-                ClassKind::Instance(cls_obj) => {
-                    if let Some(fid) = cls_obj.inst_fields.get(&k) {
-                        return fid.clone();
-                    }
+        match &self.kind {
+            ClassKind::Instance(cls_obj) => {
+                let fields = if is_static {
+                    &cls_obj.static_fields
+                } else {
+                    &cls_obj.inst_fields
+                };
+                if let Some(fid) = fields.get(&k) {
+                    return Some(fid.clone());
                 }
-                _ => unreachable!(),
             }
+            _ => return None,
         }
 
         let super_class = self.super_class.clone();
-        super_class
-            .unwrap()
+        super_class?
             .get_class()
-            .get_field_id(name, desc, is_static)
+            .find_field_id(name, desc, is_static)
     }
 
     pub fn put_field_value(rf: Arc<OopPtr>, fir: FieldIdRef, v: Oop) {
@@ -524,7 +702,7 @@ impl Class {
             match &mut (*ptr).v {
                 oop::RefKind::Inst(inst) => inst.field_values[offset] = v,
                 oop::RefKind::Mirror(mirror) => mirror.field_values[offset] = v,
-                oop::RefKind::Array(ary) => ary.elements[offset] = v,
+                oop::RefKind::Array(ary) => ary.set(offset, &v),
                 t => unreachable!("t = {:?}", t),
             }
         }
@@ -543,7 +721,7 @@ impl Class {
                     Some(v) => v.clone(),
                     _ => unreachable!("mirror = {:?}", mirror),
                 },
-                oop::RefKind::Array(ary) => ary.elements[offset].clone(),
+                oop::RefKind::Array(ary) => ary.get(offset),
                 t => unreachable!("t = {:?}", t),
             }
         }
@@ -590,6 +768,51 @@ impl Class {
         }
     }
 
+    //the static field at 'offset' of this class, for Unsafe
+    pub fn get_static_field_value2(&self, offset: usize) -> Oop {
+        match &self.kind {
+            ClassKind::Instance(cls_obj) => cls_obj.static_field_values[offset].clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn n_static_fields(&self) -> usize {
+        match &self.kind {
+            ClassKind::Instance(cls_obj) => cls_obj.static_field_values.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn put_static_field_value2(&mut self, offset: usize, v: Oop) {
+        match &mut self.kind {
+            ClassKind::Instance(cls_obj) => cls_obj.static_field_values[offset] = v,
+            _ => unreachable!(),
+        }
+    }
+
+    //gc roots, the mirror, the values of static fields, the patched constants & the call sites
+    pub fn oops(&self) -> Vec<Oop> {
+        let (mirror, values, patches, call_sites) = match &self.kind {
+            ClassKind::Instance(cls_obj) => (
+                &cls_obj.mirror,
+                &cls_obj.static_field_values[..],
+                Some(&cls_obj.cp_patches),
+                cls_obj.cp_cache.oops(),
+            ),
+            ClassKind::ObjectArray(obj_ary) => (&obj_ary.mirror, &[][..], None, vec![]),
+            ClassKind::TypeArray(typ_ary) => (&typ_ary.mirror, &[][..], None, vec![]),
+        };
+
+        mirror
+            .iter()
+            .chain(values.iter())
+            .chain(patches.into_iter().flat_map(|it| it.values()))
+            .chain(call_sites.iter())
+            .filter(|it| matches!(it, Oop::Ref(_)))
+            .cloned()
+            .collect()
+    }
+
     pub fn check_interface(&self, intf: ClassRef) -> bool {
         match &self.kind {
             ClassKind::Instance(inst) => {
@@ -627,6 +850,9 @@ impl Class {
                     let mut method = it.method.clone();
                     method.acc_flags |= ACC_NATIVE;
                     let m = method::MethodId::new(it.offset, method);
+                    if let Some(i) = cls.v_table_index.get(&k) {
+                        cls.v_table[*i] = VTableEntry::Method(m.clone());
+                    }
                     cls.all_methods.insert(k, m.clone());
 
                     m
@@ -655,7 +881,9 @@ impl Class {
             class_file,
             n_inst_fields: 0,
             all_methods: FxHashMap::default(),
-            v_table: FxHashMap::default(),
+            v_table: Vec::new(),
+            v_table_index: FxHashMap::default(),
+            i_tables: Vec::new(),
             static_fields: FxHashMap::default(),
             inst_fields: FxHashMap::default(),
             static_field_values: vec![],
@@ -665,13 +893,11 @@ impl Class {
             source_file: None,
             enclosing_method: None,
             inner_classes: None,
-            cp_cache: ConstantPoolCache::new(cp),
-        };
-
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
+            bootstrap_methods: None,
+            cp_cache: ConstantPoolCache::new(cp, class_loader),
+            cp_patches: FxHashMap::default(),
+            ref_type: None,
+            has_finalizer: false,
         };
 
         Self {
@@ -682,7 +908,6 @@ impl Class {
             super_class: None,
             class_loader,
             kind: ClassKind::Instance(class_obj),
-            mutex,
         }
     }
 
@@ -697,12 +922,6 @@ impl Class {
             mirror: None,
         };
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
             clinit_mutex: Arc::new(Mutex::new(())),
             name,
@@ -711,7 +930,6 @@ impl Class {
             super_class: None,
             class_loader: Some(class_loader),
             kind: ClassKind::ObjectArray(ary_cls_obj),
-            mutex,
         }
     }
 
@@ -727,12 +945,6 @@ impl Class {
         name.push(b'[');
         name.extend_from_slice(value_type.into());
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
             clinit_mutex: Arc::new(Mutex::new(())),
             name: Arc::new(name),
@@ -741,7 +953,6 @@ impl Class {
             super_class: None,
             class_loader: Some(class_loader),
             kind: ClassKind::TypeArray(ary_cls_obj),
-            mutex,
         }
     }
 
@@ -779,12 +990,6 @@ impl Class {
             }
         };
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
             clinit_mutex: Arc::new(Mutex::new(())),
             name: Arc::new(name2),
@@ -793,7 +998,6 @@ impl Class {
             super_class: None,
             class_loader: Some(class_loader),
             kind,
-            mutex,
         }
     }
 }
@@ -835,8 +1039,15 @@ impl ClassObject {
         let mut n_static = 0;
         let mut offset_field = num_field_of_super;
 
-        cls_file.fields.iter().for_each(|it| {
-            let field = field::Field::new(cp, it, cls_name.clone(), self_ref.clone());
+        cls_file.fields.iter().enumerate().for_each(|(i, it)| {
+            let field = field::Field::new(
+                cp,
+                it,
+                self_ref.clone(),
+                cls_file.clone(),
+                i,
+                cls_name.clone(),
+            );
             let k = (cls_name.clone(), field.name.clone(), field.desc.clone());
 
             if field.is_static() {
@@ -862,14 +1073,12 @@ impl ClassObject {
         self.static_field_values = vec![Oop::Null; n_static];
     }
 
-    fn link_interfaces(&mut self) {
+    fn link_interfaces(&mut self, class_loader: Option<ClassLoader>) {
         let class_file = self.class_file.clone();
         let cp = &class_file.cp;
 
-        class_file
-            .interfaces
-            .iter()
-            .for_each(|it| match runtime::require_class2(*it, cp) {
+        class_file.interfaces.iter().for_each(|it| {
+            match runtime::require_class2(class_loader, *it, cp) {
                 Some(class) => {
                     let name = class.get_class().name.clone();
                     self.interfaces.insert(name, class);
@@ -878,7 +1087,8 @@ impl ClassObject {
                     let name = constant_pool::get_class_name(cp, *it as usize);
                     error!("link interface failed {:?}", name);
                 }
-            });
+            }
+        });
     }
 
     fn link_methods(&mut self, this_ref: ClassRef, cls_name: BytesRef) {
@@ -898,12 +1108,133 @@ impl ClassObject {
             let name = method_id.method.name.clone();
             let desc = method_id.method.desc.clone();
             let k = (name, desc);
-            self.all_methods.insert(k.clone(), method_id.clone());
+            self.all_methods.insert(k, method_id);
+        });
+    }
 
-            if !method_id.method.is_static() {
-                self.v_table.insert(k, method_id);
+    /*
+    JVM spec, 5.4.6 method selection, done at link time
+
+    A class takes the v_table of the super class, the methods it declares
+    override the slots of the same name & desc (5.4.5, a package-private one
+    only within the same runtime package), or take new slots. So a name & desc
+    may have more than one slot, v_table_index has the one of the latest
+    declaration, the slot which the methods of the class resolve to. Then each
+    interface method not implemented by a class method gets the slot of the
+    maximally-specific default method, or of the abstract method (the miranda
+    method, AbstractMethodError if invoked). The itable of an interface maps
+    its methods to the slots of the v_table.
+    */
+    fn link_v_table(
+        &mut self,
+        super_class: &Option<ClassRef>,
+        is_interface: bool,
+        class_loader: ClassLoader,
+        cls_name: &[u8],
+    ) {
+        let mut methods: Vec<MethodIdRef> = self
+            .all_methods
+            .values()
+            .filter(|it| is_virtual(&it.method))
+            .cloned()
+            .collect();
+        //in the order of the class file
+        methods.sort_by_key(|it| it.offset);
+
+        if !is_interface {
+            if let Some(ClassKind::Instance(super_cls)) =
+                super_class.as_ref().map(|it| &it.get_class().kind)
+            {
+                self.v_table = super_cls.v_table.clone();
+                self.v_table_index = super_cls.v_table_index.clone();
             }
-        });
+        }
+
+        for m in methods {
+            self.override_v_table(m, class_loader, cls_name);
+        }
+
+        if is_interface {
+            return;
+        }
+
+        let mut interfaces = Vec::new();
+        if let Some(super_cls) = super_class {
+            collect_interfaces(super_cls, &mut interfaces);
+        }
+        for itf in self.interfaces.values() {
+            collect_interface(itf, &mut interfaces);
+        }
+
+        for itf in interfaces.iter() {
+            for k in interface_methods(itf) {
+                let implemented = match self.v_table_index.get(&k) {
+                    Some(i) => match &self.v_table[*i] {
+                        VTableEntry::Method(m) => !m.method.class.get_class().is_interface(),
+                        VTableEntry::Conflict(_, _) => false,
+                    },
+                    None => false,
+                };
+
+                if !implemented {
+                    let entry = select_interface_method(&interfaces, &k);
+                    self.put_v_table(k, entry);
+                }
+            }
+        }
+
+        self.i_tables = interfaces
+            .into_iter()
+            .map(|itf| {
+                let slots = interface_methods(&itf)
+                    .iter()
+                    .map(|k| self.v_table_index[k])
+                    .collect();
+                (itf, slots)
+            })
+            .collect();
+    }
+
+    //the slots overridden by 'm', a method of the class, or a new slot
+    fn override_v_table(&mut self, m: MethodIdRef, class_loader: ClassLoader, cls_name: &[u8]) {
+        let (name, desc) = (&m.method.name, &m.method.desc);
+        let mut slot = None;
+        for (i, it) in self.v_table.iter_mut().enumerate() {
+            let overridden = match it {
+                VTableEntry::Method(it) => {
+                    &it.method.name == name
+                        && &it.method.desc == desc
+                        && can_override(class_loader, cls_name, it)
+                }
+                //the interface methods are public
+                VTableEntry::Conflict(it, _) => &it.method.name == name && &it.method.desc == desc,
+            };
+            if overridden {
+                *it = VTableEntry::Method(m.clone());
+                slot.get_or_insert(i);
+            }
+        }
+
+        let k = (name.clone(), desc.clone());
+        match slot {
+            Some(i) => {
+                self.v_table_index.insert(k, i);
+            }
+            None => {
+                self.v_table_index.insert(k, self.v_table.len());
+                self.v_table.push(VTableEntry::Method(m));
+            }
+        }
+    }
+
+    fn put_v_table(&mut self, k: (BytesRef, BytesRef), entry: VTableEntry) {
+        match self.v_table_index.get(&k) {
+            Some(i) => self.v_table[*i] = entry,
+            None => {
+                self.v_table_index.insert(k, self.v_table.len());
+                self.v_table.push(entry);
+            }
+        }
     }
 
     fn link_attributes(&mut self) {
@@ -925,10 +1256,44 @@ impl ClassObject {
             AttributeType::InnerClasses { classes } => {
                 self.inner_classes = Some(classes.clone());
             }
+            AttributeType::BootstrapMethods { methods, .. } => {
+                self.bootstrap_methods = Some(methods.clone());
+            }
             _ => (),
         });
     }
 
+    //as hotspot, Object.finalize is empty, the classes overriding it need finalization
+    fn link_ref_type(&mut self, cls_name: &[u8], super_class: &Option<ClassRef>) {
+        let (ref_type, has_finalizer) = match super_class.as_ref().map(|it| it.get_class()) {
+            Some(Class {
+                kind: ClassKind::Instance(super_cls),
+                ..
+            }) => (super_cls.ref_type, super_cls.has_finalizer),
+            _ => (None, false),
+        };
+
+        self.ref_type = match cls_name {
+            consts::J_SOFT_REFERENCE => Some(ReferenceType::Soft),
+            consts::J_WEAK_REFERENCE => Some(ReferenceType::Weak),
+            consts::J_FINAL_REFERENCE => Some(ReferenceType::Final),
+            consts::J_PHANTOM_REFERENCE => Some(ReferenceType::Phantom),
+            _ => ref_type,
+        };
+
+        let k = (util::S_FINALIZE.clone(), util::S_FINALIZE_SIG.clone());
+        let finalize = self
+            .all_methods
+            .get(&k)
+            .and_then(|it| it.method.code.as_ref());
+        self.has_finalizer = has_finalizer
+            || match finalize.map(|it| it.code.as_slice()) {
+                Some([op]) => OpCode::from(*op) != OpCode::return_void,
+                Some(_) => true,
+                None => false,
+            };
+    }
+
     fn init_static_fields(&mut self) {
         let values = &mut self.static_field_values;
         self.static_fields.iter().for_each(|(_, it)| {
@@ -985,11 +1350,15 @@ impl Class {
     ) -> Result<MethodIdRef, ()> {
         let k = (name.clone(), desc.clone());
         match &self.kind {
-            ClassKind::Instance(cls_obj) => {
-                if let Some(m) = cls_obj.v_table.get(&k) {
-                    return Ok(m.clone());
-                }
-            }
+            ClassKind::Instance(cls_obj) => match cls_obj.v_table_index.get(&k) {
+                Some(i) => match &cls_obj.v_table[*i] {
+                    VTableEntry::Method(m) => return Ok(m.clone()),
+                    VTableEntry::Conflict(_, _) => return Err(()),
+                },
+                //the v_table of an interface has its own methods only
+                None if self.is_interface() => (),
+                None => return Err(()),
+            },
             _ => unreachable!(),
         }
 
@@ -999,6 +1368,7 @@ impl Class {
         }
     }
 
+    //JVM spec, 5.4.3.4 interface method resolution, by name
     pub fn get_interface_method_inner(
         &self,
         name: &BytesRef,
@@ -1006,7 +1376,7 @@ impl Class {
     ) -> Result<MethodIdRef, ()> {
         let k = (name.clone(), desc.clone());
         match &self.kind {
-            ClassKind::Instance(cls_obj) => match cls_obj.v_table.get(&k) {
+            ClassKind::Instance(cls_obj) => match cls_obj.all_methods.get(&k) {
                 Some(m) => return Ok(m.clone()),
                 None => {
                     for (_, itf) in cls_obj.interfaces.iter() {
@@ -1029,3 +1399,208 @@ impl Class {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{dispatch_index, Class, ClassPtr, VTableEntry};
+    use crate::new_br;
+    use crate::runtime::{sys_dic_put, ClassLoader};
+    use crate::types::{ClassRef, MethodIdRef};
+    use classfile::flags::{ACC_ABSTRACT, ACC_INTERFACE, ACC_PUBLIC};
+    use classfile::{ClassBuilder, OpCode, Version};
+    use std::sync::Arc;
+
+    const VERSION: Version = Version {
+        minor: 0,
+        major: 52,
+    };
+
+    //the methods are "()V", abstract if 'body' is false
+    pub(crate) fn define(
+        class_loader: ClassLoader,
+        acc_flags: u16,
+        name: &str,
+        super_name: Option<&str>,
+        interfaces: &[&str],
+        methods: &[(u16, &str, bool)],
+    ) -> ClassRef {
+        let mut cb = ClassBuilder::new(VERSION, acc_flags, name, super_name);
+        for it in interfaces {
+            cb.interface(it);
+        }
+        for (acc_flags, name, body) in methods {
+            if *body {
+                let mut c = cb.code(*acc_flags, name, "()V");
+                c.op(OpCode::return_void);
+                c.finish().unwrap();
+            } else {
+                cb.method(*acc_flags | ACC_ABSTRACT, name, "()V");
+            }
+        }
+        let cf = cb.build().unwrap();
+
+        let cls = ClassPtr::new(Class::new_class(Arc::new(Box::new(cf)), Some(class_loader)));
+        let name = cls.get_class().name.clone();
+        sys_dic_put(class_loader, name.as_slice(), cls.clone());
+        cls.get_mut_class().link_class(cls.clone());
+        cls
+    }
+
+    pub(crate) fn define_object(class_loader: ClassLoader) {
+        define(class_loader, ACC_PUBLIC, "java/lang/Object", None, &[], &[]);
+    }
+
+    pub(crate) fn method(cls: &ClassRef, name: &str) -> MethodIdRef {
+        let cls = cls.get_class();
+        cls.get_this_class_method(&new_br(name), &new_br("()V"))
+            .unwrap()
+    }
+
+    //the method selected for a receiver of 'cls' by the invoke of 'mir'
+    fn select(cls: &ClassRef, mir: &MethodIdRef) -> VTableEntry {
+        let index = dispatch_index(mir).unwrap();
+        cls.get_class().select_method(&index).unwrap().clone()
+    }
+
+    fn is_selected(entry: VTableEntry, mir: &MethodIdRef) -> bool {
+        match entry {
+            VTableEntry::Method(m) => Arc::ptr_eq(&m, mir),
+            VTableEntry::Conflict(_, _) => false,
+        }
+    }
+
+    //JVM spec, 5.4.5, a package-private method is overridden in its runtime package only
+    #[test]
+    fn t_package_private() {
+        let (l1, l2) = (ClassLoader::User(20000), ClassLoader::User(20001));
+        define_object(l1);
+        define_object(l2);
+
+        let pkg = 0;
+        let a = define(
+            l1,
+            ACC_PUBLIC,
+            "p1/A",
+            Some("java/lang/Object"),
+            &[],
+            &[(pkg, "m", true), (ACC_PUBLIC, "n", true)],
+        );
+        let b = define(
+            l1,
+            ACC_PUBLIC,
+            "p2/B",
+            Some("p1/A"),
+            &[],
+            &[(ACC_PUBLIC, "m", true), (ACC_PUBLIC, "n", true)],
+        );
+        let c = define(
+            l1,
+            ACC_PUBLIC,
+            "p1/C",
+            Some("p2/B"),
+            &[],
+            &[(pkg, "m", true)],
+        );
+        let (a_m, a_n, b_m, b_n, c_m) = (
+            method(&a, "m"),
+            method(&a, "n"),
+            method(&b, "m"),
+            method(&b, "n"),
+            method(&c, "m"),
+        );
+
+        //B.m is in another package, a new slot
+        assert!(is_selected(select(&b, &a_m), &a_m));
+        assert!(is_selected(select(&b, &b_m), &b_m));
+        assert!(is_selected(select(&b, &a_n), &b_n));
+        //C.m overrides both, A.m in the same package, B.m public
+        assert!(is_selected(select(&c, &a_m), &c_m));
+        assert!(is_selected(select(&c, &b_m), &c_m));
+        assert!(is_selected(select(&c, &a_n), &b_n));
+
+        //the same package name, but another loader
+        sys_dic_put(l2, b"p1/A", a.clone());
+        let d = define(
+            l2,
+            ACC_PUBLIC,
+            "p1/D",
+            Some("p1/A"),
+            &[],
+            &[(pkg, "m", true)],
+        );
+        let d_m = method(&d, "m");
+        assert!(is_selected(select(&d, &a_m), &a_m));
+        assert!(is_selected(select(&d, &d_m), &d_m));
+    }
+
+    //JVM spec, 5.4.3.3 & 5.4.6, the maximally-specific default methods
+    #[test]
+    fn t_interface_dispatch() {
+        let l = ClassLoader::User(20002);
+        define_object(l);
+
+        let itf = ACC_PUBLIC | ACC_INTERFACE | ACC_ABSTRACT;
+        let object = Some("java/lang/Object");
+        let i1 = define(
+            l,
+            itf,
+            "t/I1",
+            object,
+            &[],
+            &[(ACC_PUBLIC, "d", true), (ACC_PUBLIC, "a", false)],
+        );
+        let i2 = define(l, itf, "t/I2", object, &[], &[(ACC_PUBLIC, "d", true)]);
+        let i3 = define(
+            l,
+            itf,
+            "t/I3",
+            object,
+            &["t/I1"],
+            &[(ACC_PUBLIC, "d", true)],
+        );
+        let (i1_d, i1_a, i2_d, i3_d) = (
+            method(&i1, "d"),
+            method(&i1, "a"),
+            method(&i2, "d"),
+            method(&i3, "d"),
+        );
+
+        //two defaults, none more specific
+        let k = define(l, ACC_PUBLIC, "t/K", object, &["t/I1", "t/I2"], &[]);
+        for mir in [&i1_d, &i2_d].iter() {
+            match select(&k, mir) {
+                VTableEntry::Conflict(m1, m2) => {
+                    let mut pair = [m1, m2];
+                    pair.sort_by_key(|it| Arc::ptr_eq(it, &i2_d));
+                    assert!(Arc::ptr_eq(&pair[0], &i1_d) && Arc::ptr_eq(&pair[1], &i2_d));
+                }
+                VTableEntry::Method(_) => panic!("should be a conflict"),
+            }
+        }
+        //the miranda method
+        assert!(is_selected(select(&k, &i1_a), &i1_a));
+
+        //I3.d is more specific than I1.d
+        let l3 = define(l, ACC_PUBLIC, "t/L", object, &["t/I3", "t/I1"], &[]);
+        assert!(is_selected(select(&l3, &i1_d), &i3_d));
+        assert!(is_selected(select(&l3, &i3_d), &i3_d));
+
+        //a class method wins, by the super class too
+        let m = define(
+            l,
+            ACC_PUBLIC,
+            "t/M",
+            object,
+            &["t/I1", "t/I2"],
+            &[(ACC_PUBLIC, "d", true), (ACC_PUBLIC, "a", true)],
+        );
+        let (m_d, m_a) = (method(&m, "d"), method(&m, "a"));
+        let n = define(l, ACC_PUBLIC, "t/N", Some("t/M"), &["t/I3"], &[]);
+        for cls in [&m, &n].iter() {
+            assert!(is_selected(select(cls, &i1_d), &m_d));
+            assert!(is_selected(select(cls, &i2_d), &m_d));
+            assert!(is_selected(select(cls, &i1_a), &m_a));
+        }
+        assert!(is_selected(select(&n, &i3_d), &m_d));
+    }
+}
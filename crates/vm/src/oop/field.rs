@@ -1,5 +1,5 @@
 use crate::oop::{self, consts as oop_consts, Oop, ValueType};
-use crate::runtime::require_class2;
+use crate::runtime::{require_class2, ClassLoader};
 use crate::types::ClassRef;
 use crate::types::*;
 use crate::util;
@@ -12,11 +12,16 @@ use std::fmt;
 use std::ops::Deref;
 use std::sync::Arc;
 
-pub fn get_field_ref(cp: &ConstantPool, idx: usize, is_static: bool) -> FieldIdRef {
+pub fn get_field_ref(
+    class_loader: Option<ClassLoader>,
+    cp: &ConstantPool,
+    idx: usize,
+    is_static: bool,
+) -> FieldIdRef {
     let (class_index, name_and_type_index) = constant_pool::get_field_ref(cp, idx);
 
     //load Field's Class, then init it
-    let class = require_class2(class_index, cp).unwrap_or_else(|| {
+    let class = require_class2(class_loader, class_index, cp).unwrap_or_else(|| {
         panic!(
             "Unknown field class {:?}",
             cp.get(class_index as usize)
@@ -96,16 +101,26 @@ pub struct FieldId {
 #[derive(Clone)]
 pub struct Field {
     pub class: ClassRef,
+    pub class_file: ClassFileRef,
     pub cls_name: BytesRef,
     pub name: BytesRef,
     pub desc: BytesRef,
     pub acc_flags: U2,
     pub value_type: ValueType,
     pub attr_constant_value: Option<Oop>,
+
+    field_info_index: usize,
 }
 
 impl Field {
-    pub fn new(cp: &ConstantPool, fi: &FieldInfo, cls_name: BytesRef, class: ClassRef) -> Self {
+    pub fn new(
+        cp: &ConstantPool,
+        fi: &FieldInfo,
+        class: ClassRef,
+        class_file: ClassFileRef,
+        field_info_index: usize,
+        cls_name: BytesRef,
+    ) -> Self {
         let name = constant_pool::get_utf8(cp, fi.name_index as usize).clone();
         let desc = constant_pool::get_utf8(cp, fi.desc_index as usize).clone();
         let value_type = desc.first().unwrap().into();
@@ -125,12 +140,14 @@ impl Field {
 
         Self {
             class,
+            class_file,
             cls_name,
             name,
             desc,
             acc_flags,
             value_type,
             attr_constant_value,
+            field_info_index,
         }
     }
 
@@ -176,6 +193,27 @@ impl Field {
     pub fn get_attr_constant_value(&self) -> Option<Oop> {
         self.attr_constant_value.clone()
     }
+
+    pub fn get_annotation(&self) -> Option<Vec<u8>> {
+        let field_info = self.class_file.fields.get(self.field_info_index).unwrap();
+        util::attributes::assemble_annotation(&field_info.attrs)
+    }
+
+    pub fn get_type_annotation(&self) -> Option<Vec<u8>> {
+        let field_info = self.class_file.fields.get(self.field_info_index).unwrap();
+        util::attributes::assemble_type_annotation(&field_info.attrs)
+    }
+
+    //the generic signature, none if the field type is not generic
+    pub fn get_signature(&self) -> Option<BytesRef> {
+        let field_info = self.class_file.fields.get(self.field_info_index).unwrap();
+        let idx = util::attributes::get_signature(&field_info.attrs);
+        if idx != 0 {
+            Some(constant_pool::get_utf8(&self.class_file.cp, idx as usize).clone())
+        } else {
+            None
+        }
+    }
 }
 
 impl fmt::Debug for Field {
@@ -0,0 +1,712 @@
+use crate::oop::{ClassKind, Oop, OopPtr, RefKind, ReferenceType};
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+/*
+The heap keeps a registry of every allocated object, the object itself is
+owned by the Arc<OopPtr> handles, the fields & the elements of the reference
+arrays included. Each thread registers its objects in its own buffer, the
+buffers are merged into the registry by the collection.
+
+Reference counting reclaims acyclic garbage as soon as the last handle is
+dropped, the mark-sweep collector here reclaims what reference counting can't,
+the cycles (doubly-linked lists, parent/child trees...).
+
+Roots are:
+  the oops given by the caller (thread frames, static fields, mirrors...)
+  any object with a handle held outside of the heap, by the runtime itself
+  (Local, JavaCall args, JNI references, native methods...). Every reference
+  holds a count, an object is such one when its strong count is bigger than
+  the number of references from other objects & the one taken by 'collect'.
+
+Unmarked objects are garbage, they are referenced only by each other. Their
+references are cleared to break the cycles, then reference counting frees
+them, a body is never freed while a handle is left. A Weak<OopPtr> is
+upgraded by 'upgrade', never to a garbage object being cleared.
+
+The caller should stop the world, see runtime::gc
+
+java.lang.ref, as the ReferenceProcessor of hotspot: the referent of an
+active Reference (next is null) isn't traced. After marking,
+  soft & weak: the referents not marked are cleared
+  final: the referents not marked are marked again, with the objects they
+    reach, finalize() is run on them later
+  phantom: the referents still not marked are cleared
+The processed references are made inactive (next = this), and returned to
+the caller to link them to Reference.pending. A softly reachable referent
+is treated as strong, unless it's not accessed for a while.
+
+The size of an object is estimated, a header & 8 bytes per field or element,
+the bytes of the elements for a primitive array. The objects freed by reference
+counting are counted as used until the next collection, which is triggered
+once the used bytes reach -Xmx too. An allocation beyond -Xmx collects first,
+if the live objects still leave no room, OutOfMemoryError is thrown, see
+runtime::gc::reserve.
+*/
+
+//objects allocated since last collection, for triggering gc
+const GC_THRESHOLD: usize = 256 * 1024;
+
+type Buffer = Arc<Mutex<Vec<Weak<OopPtr>>>>;
+
+lazy_static! {
+    //the objects merged by the last collection
+    static ref HEAP: Mutex<Vec<Weak<OopPtr>>> = Mutex::new(Vec::new());
+    //the allocation buffers of the threads, kept until merged after the thread exits
+    static ref BUFFERS: Mutex<Vec<Buffer>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    //uncontended, but by collect
+    static BUFFER: Buffer = {
+        let buf = Buffer::default();
+        BUFFERS.lock().unwrap().push(buf.clone());
+        buf
+    };
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+//the estimated bytes of the objects registered
+static USED: AtomicUsize = AtomicUsize::new(0);
+//the used bytes to trigger the next collection, never below -Xmx
+static USED_LIMIT: AtomicUsize = AtomicUsize::new(usize::MAX);
+//-Xmx, set by runtime::arguments
+static MAX_SIZE: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+const OBJECT_HEADER_SIZE: usize = 16;
+/// The estimated bytes of a field, or an element of a reference array
+pub const FIELD_SIZE: usize = 8;
+
+pub fn register(rf: &Arc<OopPtr>) {
+    let mut v = Some(Arc::downgrade(rf));
+    let _ = BUFFER.try_with(|buf| buf.lock().unwrap().extend(v.take()));
+    //the thread is exiting, its buffer is gone
+    if let Some(v) = v {
+        HEAP.lock().unwrap().push(v);
+    }
+    ALLOCATED.fetch_add(1, Ordering::Relaxed);
+    USED.fetch_add(object_size(rf), Ordering::Relaxed);
+}
+
+#[inline]
+pub fn need_collect() -> bool {
+    ALLOCATED.load(Ordering::Relaxed) >= GC_THRESHOLD
+        || USED.load(Ordering::Relaxed) >= USED_LIMIT.load(Ordering::Relaxed)
+}
+
+/// The estimated bytes of the objects, the dead ones not collected included
+pub fn used_size() -> usize {
+    USED.load(Ordering::Relaxed)
+}
+
+/// Whether an object of 'size' bytes, the header excluded, fits in -Xmx
+///
+/// The dead objects not collected yet are counted, the caller collects
+/// before giving up.
+pub fn has_room(size: usize) -> bool {
+    let size = size.saturating_add(OBJECT_HEADER_SIZE);
+    USED.load(Ordering::Relaxed).saturating_add(size) <= MAX_SIZE.load(Ordering::Relaxed)
+}
+
+/// Set -Xmx, the used bytes to trigger the next collection are updated
+pub fn set_max_size(size: usize) {
+    MAX_SIZE.store(size, Ordering::Relaxed);
+    set_used_limit(used_size());
+}
+
+/// Set the used bytes to trigger the next collection, by -Xmx & the live objects
+///
+/// The live objects beyond -Xmx don't make each safepoint collect.
+pub fn set_used_limit(used: usize) {
+    let limit = MAX_SIZE.load(Ordering::Relaxed).max(used.saturating_mul(2));
+    USED_LIMIT.store(limit, Ordering::Relaxed);
+}
+
+fn object_size(rf: &OopPtr) -> usize {
+    let ptr = rf.get_raw_ptr();
+    let size = unsafe {
+        match &(*ptr).v {
+            RefKind::Inst(inst) => inst.field_values.len() * FIELD_SIZE,
+            RefKind::Array(ary) => ary.len() * FIELD_SIZE,
+            RefKind::TypeArray(ary) => ary.bytes_len(),
+            RefKind::Mirror(mirror) => mirror.field_values.len() * FIELD_SIZE,
+        }
+    };
+    OBJECT_HEADER_SIZE + size
+}
+
+pub fn reset_allocated() {
+    ALLOCATED.store(0, Ordering::Relaxed);
+}
+
+//the field offsets of java.lang.ref.Reference
+pub struct ReferenceLayout {
+    pub referent: usize,
+    pub next: usize,
+    //SoftReference.timestamp, none if SoftReference not loaded
+    pub timestamp: Option<usize>,
+    //the soft referents accessed before it are cleared
+    pub soft_expired: i64,
+}
+
+//return the number of collected objects, and the references to enqueue
+pub fn collect(roots: &[Oop], refs: Option<&ReferenceLayout>) -> (usize, Vec<Oop>) {
+    let mut heap = HEAP.lock().unwrap();
+    merge_buffers(&mut heap);
+    let objects: Vec<Arc<OopPtr>> = heap.iter().filter_map(|it| it.upgrade()).collect();
+    let index: FxHashMap<u64, usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(i, it)| (it.0, i))
+        .collect();
+
+    //count the references from other objects, by the fields & the elements
+    let mut internal = vec![0usize; objects.len()];
+    for it in objects.iter() {
+        for_each_ref(it, None, |v| {
+            if let Some(&i) = index.get(&v.0) {
+                internal[i] += 1;
+            }
+        });
+    }
+
+    //the active references, the referent isn't traced
+    let mut discovered = Vec::new();
+    if let Some(refs) = refs {
+        for (i, it) in objects.iter().enumerate() {
+            if let Some(ref_type) = discover(it, refs) {
+                discovered.push((i, ref_type));
+            }
+        }
+    }
+
+    let mut marker = Marker {
+        objects: &objects,
+        index: &index,
+        marked: vec![false; objects.len()],
+        skip_referent: vec![false; objects.len()],
+        referent: refs.map(|it| it.referent),
+    };
+    for (i, _) in discovered.iter() {
+        marker.skip_referent[*i] = true;
+    }
+
+    //mark
+    let mut pending: Vec<usize> = roots
+        .iter()
+        .filter_map(|it| match it {
+            Oop::Ref(rf) => index.get(&rf.0).cloned(),
+            _ => None,
+        })
+        .collect();
+    for (i, it) in objects.iter().enumerate() {
+        //one taken by 'objects'
+        if Arc::strong_count(it) - 1 > internal[i] {
+            pending.push(i);
+        }
+    }
+    marker.mark(pending);
+
+    let enqueued = match refs {
+        Some(refs) => marker.process_refs(&discovered, refs),
+        None => vec![],
+    };
+    let marked = marker.marked;
+
+    //sweep
+    let mut garbage = Vec::new();
+    for (i, it) in objects.iter().enumerate() {
+        if !marked[i] {
+            clear_refs(it, &mut garbage);
+        }
+    }
+
+    heap.clear();
+    let mut used = 0;
+    for (i, it) in objects.iter().enumerate() {
+        if marked[i] {
+            heap.push(Arc::downgrade(it));
+            used += object_size(it);
+        }
+    }
+    reset_allocated();
+    USED.store(used, Ordering::Relaxed);
+    set_used_limit(used);
+
+    //freed with the lock held, no weak reference is upgraded meanwhile
+    let n = marked.iter().filter(|it| !**it).count();
+    drop(garbage);
+    drop(objects);
+    drop(heap);
+
+    (n, enqueued)
+}
+
+/// Upgrade a weak reference to an object, none if it has been collected
+///
+/// Not by Weak::upgrade directly, the object may be cleared by a collection
+/// running meanwhile, while the thread is in a safe region.
+pub fn upgrade(rf: &Weak<OopPtr>) -> Option<Arc<OopPtr>> {
+    let _heap = HEAP.lock().unwrap();
+    rf.upgrade()
+}
+
+//move the objects allocated since the last collection to the heap
+fn merge_buffers(heap: &mut Vec<Weak<OopPtr>>) {
+    let mut buffers = BUFFERS.lock().unwrap();
+    buffers.retain(|buf| {
+        //checked first, nothing is registered in it once the thread exited
+        let alive = Arc::strong_count(buf) > 1;
+        heap.append(&mut buf.lock().unwrap());
+        alive
+    });
+}
+
+struct Marker<'a> {
+    objects: &'a [Arc<OopPtr>],
+    index: &'a FxHashMap<u64, usize>,
+    marked: Vec<bool>,
+    //the discovered references
+    skip_referent: Vec<bool>,
+    referent: Option<usize>,
+}
+
+impl<'a> Marker<'a> {
+    fn mark(&mut self, mut pending: Vec<usize>) {
+        while let Some(i) = pending.pop() {
+            if self.marked[i] {
+                continue;
+            }
+
+            self.marked[i] = true;
+            let skip = if self.skip_referent[i] {
+                self.referent
+            } else {
+                None
+            };
+            let (index, marked) = (self.index, &self.marked);
+            for_each_ref(&self.objects[i], skip, |v| {
+                if let Some(&i) = index.get(&v.0) {
+                    if !marked[i] {
+                        pending.push(i);
+                    }
+                }
+            });
+        }
+    }
+
+    //the referent isn't reachable but by the reference
+    fn is_referent_dead(&self, i: usize, refs: &ReferenceLayout) -> bool {
+        let inst = self.objects[i].extract_inst();
+        match &inst.field_values[refs.referent] {
+            Oop::Ref(rf) => match self.index.get(&rf.0) {
+                Some(&r) => !self.marked[r],
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn process_refs(
+        &mut self,
+        discovered: &[(usize, ReferenceType)],
+        refs: &ReferenceLayout,
+    ) -> Vec<Oop> {
+        let mut enqueued = Vec::new();
+        let dead = |marker: &Self, i: usize| marker.marked[i] && marker.is_referent_dead(i, refs);
+
+        for (i, _) in discovered
+            .iter()
+            .filter(|(_, t)| *t == ReferenceType::Soft || *t == ReferenceType::Weak)
+        {
+            if dead(self, *i) {
+                self.set_field(*i, refs.referent, Oop::Null);
+                enqueued.push(*i);
+            }
+        }
+
+        let mut resurrected = Vec::new();
+        for (i, _) in discovered
+            .iter()
+            .filter(|(_, t)| *t == ReferenceType::Final)
+        {
+            if dead(self, *i) {
+                let inst = self.objects[*i].extract_inst();
+                let referent = inst.field_values[refs.referent].extract_ref();
+                resurrected.push(self.index[&referent.0]);
+                enqueued.push(*i);
+            }
+        }
+        //the references reached from now on are not processed, their referents are kept
+        for (i, _) in discovered.iter() {
+            if !self.marked[*i] {
+                self.skip_referent[*i] = false;
+            }
+        }
+        self.mark(resurrected);
+
+        for (i, _) in discovered
+            .iter()
+            .filter(|(_, t)| *t == ReferenceType::Phantom)
+        {
+            if dead(self, *i) {
+                self.set_field(*i, refs.referent, Oop::Null);
+                enqueued.push(*i);
+            }
+        }
+
+        enqueued
+            .into_iter()
+            .map(|i| {
+                let rf = Oop::Ref(self.objects[i].clone());
+                self.set_field(i, refs.next, rf.clone());
+                rf
+            })
+            .collect()
+    }
+
+    fn set_field(&self, i: usize, offset: usize, v: Oop) -> Oop {
+        let ptr = self.objects[i].get_mut_raw_ptr();
+        unsafe {
+            match &mut (*ptr).v {
+                RefKind::Inst(inst) => std::mem::replace(&mut inst.field_values[offset], v),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+//an active reference, with a referent not cleared
+fn discover(rf: &Arc<OopPtr>, refs: &ReferenceLayout) -> Option<ReferenceType> {
+    let ptr = rf.get_raw_ptr();
+    let inst = unsafe {
+        match &(*ptr).v {
+            RefKind::Inst(inst) => inst,
+            _ => return None,
+        }
+    };
+
+    let ref_type = match &inst.class.get_class().kind {
+        ClassKind::Instance(cls) => cls.ref_type?,
+        _ => return None,
+    };
+
+    let values = &inst.field_values;
+    if !values[refs.next].is_null() || values[refs.referent].is_null() {
+        return None;
+    }
+
+    //not expired, as strong
+    if ref_type == ReferenceType::Soft {
+        if let Some(timestamp) = refs.timestamp {
+            if values[timestamp].extract_long() >= refs.soft_expired {
+                return None;
+            }
+        }
+    }
+
+    Some(ref_type)
+}
+
+//the field at 'skip' isn't visited
+fn for_each_ref<F: FnMut(&OopPtr)>(rf: &Arc<OopPtr>, skip: Option<usize>, mut f: F) {
+    let ptr = rf.get_raw_ptr();
+    let values = unsafe {
+        match &(*ptr).v {
+            RefKind::Inst(inst) => &inst.field_values,
+            RefKind::Mirror(mirror) => &mirror.field_values,
+            RefKind::Array(ary) => &ary.elements,
+            RefKind::TypeArray(_) => return,
+        }
+    };
+
+    for (i, v) in values.iter().enumerate() {
+        if let Oop::Ref(v) = v {
+            if skip != Some(i) {
+                f(v);
+            }
+        }
+    }
+}
+
+//take the references of a garbage object, dropped once the cycles are all broken
+fn clear_refs(rf: &OopPtr, garbage: &mut Vec<Oop>) {
+    let ptr = rf.get_mut_raw_ptr();
+    let values = unsafe {
+        match &mut (*ptr).v {
+            RefKind::Inst(inst) => &mut inst.field_values,
+            RefKind::Array(ary) => &mut ary.elements,
+            RefKind::Mirror(mirror) => &mut mirror.field_values,
+            RefKind::TypeArray(_) => return,
+        }
+    };
+
+    for v in values.iter_mut() {
+        if let Oop::Ref(_) = v {
+            garbage.push(std::mem::replace(v, Oop::Null));
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::ReferenceLayout;
+    use crate::oop::class::ClassPtr;
+    use crate::oop::{Class, ClassKind, Oop, OopPtr, ReferenceType, ValueType};
+    use crate::runtime::ClassLoader;
+    use crate::types::ClassRef;
+    use classfile::{flags::ACC_PUBLIC, ClassBuilder, Version};
+    use std::sync::{Arc, Mutex, Weak};
+
+    lazy_static! {
+        //the heap is shared by the tests
+        pub(crate) static ref HEAP_TEST: Mutex<()> = Mutex::new(());
+    }
+
+    //a class with the object fields, not loaded by any loader
+    pub(crate) fn new_class(n_fields: usize, ref_type: Option<ReferenceType>) -> ClassRef {
+        let version = Version {
+            minor: 0,
+            major: 52,
+        };
+        let mut cb = ClassBuilder::new(version, ACC_PUBLIC, "java/lang/Object", None);
+        for i in 0..n_fields {
+            cb.field(0, &format!("f{}", i), "Ljava/lang/Object;");
+        }
+        let cf = cb.build().unwrap();
+
+        let cls = ClassPtr::new(Class::new_class(Arc::new(Box::new(cf)), None));
+        let class = cls.get_mut_class();
+        class.link_class(cls.clone());
+        if let ClassKind::Instance(class) = &mut class.kind {
+            class.ref_type = ref_type;
+        }
+        cls
+    }
+
+    //the references are laid out as: referent, next
+    fn layout() -> ReferenceLayout {
+        ReferenceLayout {
+            referent: 0,
+            next: 1,
+            timestamp: None,
+            soft_expired: 0,
+        }
+    }
+
+    fn new_ref(ref_type: ReferenceType, referent: &Oop) -> Oop {
+        let rf = Oop::new_inst(new_class(2, Some(ref_type)));
+        Class::put_field_value2(rf.extract_ref(), 0, referent.clone());
+        rf
+    }
+
+    pub(crate) fn get(rf: &Oop, offset: usize) -> Oop {
+        Class::get_field_value2(rf.extract_ref(), offset)
+    }
+
+    //the handle is dropped, to observe the object freed
+    pub(crate) fn downgrade(v: Oop) -> Weak<OopPtr> {
+        Arc::downgrade(&v.extract_ref())
+    }
+
+    //a.f0 = b, b.f0 = a
+    pub(crate) fn new_cycle() -> (Oop, Oop) {
+        let cls = new_class(1, None);
+        let a = Oop::new_inst(cls.clone());
+        let b = Oop::new_inst(cls);
+        Class::put_field_value2(a.extract_ref(), 0, b.clone());
+        Class::put_field_value2(b.extract_ref(), 0, a.clone());
+        (a, b)
+    }
+
+    fn is_same(a: &Oop, b: &Oop) -> bool {
+        Arc::ptr_eq(&a.extract_ref(), &b.extract_ref())
+    }
+
+    #[test]
+    fn t_clear_weak_and_phantom() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let cls = new_class(1, None);
+
+        let held = Oop::new_inst(cls.clone());
+        let weak_held = new_ref(ReferenceType::Weak, &held);
+        let obj = Oop::new_inst(cls.clone());
+        let weak = new_ref(ReferenceType::Weak, &obj);
+        let obj2 = Oop::new_inst(cls);
+        let phantom = new_ref(ReferenceType::Phantom, &obj2);
+        let (obj, obj2) = (downgrade(obj), downgrade(obj2));
+
+        let (_, enqueued) = super::collect(&[], Some(&layout()));
+        assert!(obj.upgrade().is_none());
+        assert!(obj2.upgrade().is_none());
+        assert!(get(&weak, 0).is_null() && get(&phantom, 0).is_null());
+        assert!(is_same(&get(&weak_held, 0), &held));
+        assert!(get(&weak_held, 1).is_null());
+
+        //inactive, next = this
+        assert!(is_same(&get(&weak, 1), &weak));
+        assert!(is_same(&get(&phantom, 1), &phantom));
+        assert_eq!(enqueued.len(), 2);
+        assert!(is_same(&enqueued[0], &weak) && is_same(&enqueued[1], &phantom));
+
+        //processed once
+        let (_, enqueued) = super::collect(&[], Some(&layout()));
+        assert!(enqueued.is_empty());
+    }
+
+    //an array in a cycle is collected with its elements, the ones held elsewhere are kept
+    #[test]
+    fn t_collect_ref_array() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let cls = new_class(1, None);
+        let ary_cls = ClassPtr::new(Class::new_prime_ary(ClassLoader::Bootstrap, ValueType::INT));
+        let obj = Oop::new_inst(cls.clone());
+        let held = Oop::new_inst(cls);
+        let ary = Oop::new_ref_ary2(ary_cls, vec![obj.clone(), held.clone()]);
+        Class::put_field_value2(obj.extract_ref(), 0, ary.clone());
+        let (obj, ary) = (downgrade(obj), downgrade(ary));
+
+        super::collect(&[], None);
+        assert!(obj.upgrade().is_none() && ary.upgrade().is_none());
+        assert!(get(&held, 0).is_null());
+        assert_eq!(Arc::strong_count(&held.extract_ref()), 2);
+    }
+
+    #[test]
+    fn t_soft_by_timestamp() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let cls = new_class(1, None);
+        let refs = ReferenceLayout {
+            referent: 0,
+            next: 1,
+            timestamp: Some(2),
+            soft_expired: 100,
+        };
+        let new_soft = |timestamp| {
+            let rf = Oop::new_inst(new_class(3, Some(ReferenceType::Soft)));
+            Class::put_field_value2(rf.extract_ref(), 0, Oop::new_inst(cls.clone()));
+            Class::put_field_value2(rf.extract_ref(), 2, Oop::new_long(timestamp));
+            rf
+        };
+
+        let recent = new_soft(100);
+        let expired = new_soft(99);
+        let (_, enqueued) = super::collect(&[], Some(&refs));
+        assert!(!get(&recent, 0).is_null());
+        assert!(get(&expired, 0).is_null());
+        assert_eq!(enqueued.len(), 1);
+        assert!(is_same(&enqueued[0], &expired));
+    }
+
+    //the dead objects are counted until collected, the live ones beyond -Xmx leave no room
+    #[test]
+    fn t_max_size() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        const MB: usize = 1024 * 1024;
+        super::collect(&[], None);
+        let base = super::used_size();
+
+        let live = Oop::new_byte_ary(MB);
+        drop(Oop::new_byte_ary(MB));
+        super::set_max_size(base + MB + MB / 2);
+        assert!(!super::has_room(MB / 4));
+
+        super::collect(&[], None);
+        assert!(super::has_room(MB / 4));
+        assert!(!super::has_room(MB));
+        drop(live);
+
+        super::set_max_size(usize::MAX);
+    }
+
+    //weak cleared, then finalized, phantom cleared after finalize() done
+    #[test]
+    fn t_finalization_order() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let obj = Oop::new_inst(new_class(1, None));
+        let weak = new_ref(ReferenceType::Weak, &obj);
+        let finalizer = new_ref(ReferenceType::Final, &obj);
+        let phantom = new_ref(ReferenceType::Phantom, &obj);
+        let obj = downgrade(obj);
+
+        let (_, enqueued) = super::collect(&[], Some(&layout()));
+        assert_eq!(enqueued.len(), 2);
+        assert!(is_same(&enqueued[0], &weak) && is_same(&enqueued[1], &finalizer));
+        assert!(get(&weak, 0).is_null());
+        //resurrected for finalize()
+        assert!(obj.upgrade().is_some());
+        assert!(!get(&finalizer, 0).is_null());
+        assert!(!get(&phantom, 0).is_null() && get(&phantom, 1).is_null());
+
+        //Finalizer clears the referent once finalize() is run
+        Class::put_field_value2(finalizer.extract_ref(), 0, Oop::Null);
+        let (_, enqueued) = super::collect(&[], Some(&layout()));
+        assert_eq!(enqueued.len(), 1);
+        assert!(is_same(&enqueued[0], &phantom));
+        assert!(get(&phantom, 0).is_null());
+        assert!(obj.upgrade().is_none());
+    }
+
+    #[test]
+    fn t_collect_cycle() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let (a, b) = new_cycle();
+        let (a, b) = (downgrade(a), downgrade(b));
+        //no handle left, kept by each other
+        assert!(a.upgrade().is_some() && b.upgrade().is_some());
+        //no cycle, freed by reference counting
+        let c = downgrade(Oop::new_inst(new_class(1, None)));
+        assert!(c.upgrade().is_none());
+
+        let (n, _) = super::collect(&[], None);
+        assert!(n >= 2);
+        assert!(a.upgrade().is_none() && b.upgrade().is_none());
+    }
+
+    //the objects reachable from the roots or a handle survive
+    #[test]
+    fn t_keep_rooted() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let cls = new_class(1, None);
+        let (root, held) = (Oop::new_inst(cls.clone()), Oop::new_inst(cls));
+        let (a, b) = new_cycle();
+        let (c, d) = new_cycle();
+        Class::put_field_value2(root.extract_ref(), 0, a);
+        Class::put_field_value2(held.extract_ref(), 0, c);
+        let roots = vec![root];
+        let root = Arc::downgrade(&roots[0].extract_ref());
+        let (b, d) = (downgrade(b), downgrade(d));
+
+        super::collect(&roots, None);
+        assert!(b.upgrade().is_some() && d.upgrade().is_some());
+        assert!(is_same(
+            &get(&get(&roots[0], 0), 0),
+            &Oop::Ref(b.upgrade().unwrap())
+        ));
+        assert!(is_same(
+            &get(&get(&held, 0), 0),
+            &Oop::Ref(d.upgrade().unwrap())
+        ));
+
+        drop(roots);
+        super::collect(&[], None);
+        assert!(root.upgrade().is_none() && b.upgrade().is_none());
+        assert!(d.upgrade().is_some());
+    }
+
+    //the buffer of an exited thread is merged
+    #[test]
+    fn t_allocated_by_exited_thread() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        let (a, b) = std::thread::spawn(|| {
+            let (a, b) = new_cycle();
+            (downgrade(a), downgrade(b))
+        })
+        .join()
+        .unwrap();
+
+        super::collect(&[], None);
+        assert!(a.upgrade().is_none() && b.upgrade().is_none());
+    }
+}
@@ -12,7 +12,7 @@ use crate::types::*;
 use crate::util::oop::{get_java_lang_integer_value_offset, get_java_lang_string_value_offset};
 
 pub use self::ary::{ArrayOopDesc, TypeArrayDesc, TypeArrayEnum};
-pub use self::class::{Class, ClassKind};
+pub use self::class::{Class, ClassKind, ReferenceType};
 pub use self::inst::InstOopDesc;
 pub use self::mirror::MirrorOopDesc;
 pub use self::reference::{RefKind, RefKindDesc};
@@ -22,6 +22,7 @@ pub mod ary;
 pub mod class;
 pub mod consts;
 pub mod field;
+pub mod heap;
 pub mod inst;
 pub mod mirror;
 pub mod reference;
@@ -250,7 +251,7 @@ impl Oop {
         let field_values = field::build_inited_field_values(java_lang_class);
         let v = MirrorOopDesc {
             target: Some(target),
-            field_values: vec![],
+            field_values,
             value_type,
         };
 
@@ -278,6 +279,7 @@ impl Oop {
         let v = Box::new(v);
         let ptr = Box::into_raw(v) as u64;
         let rf = Arc::new(OopPtr(ptr));
+        heap::register(&rf);
         Oop::Ref(rf)
     }
 }
@@ -395,25 +397,42 @@ impl OopPtr {
         unsafe { (*ptr).monitor_enter() };
     }
 
-    pub fn monitor_exit(&self) {
+    //false if the current thread is not the owner, the same below
+    pub fn monitor_exit(&self) -> bool {
+        let ptr = self.get_raw_ptr();
+        unsafe { (*ptr).monitor_exit() }
+    }
+
+    pub fn is_monitor_owner(&self) -> bool {
+        let ptr = self.get_raw_ptr();
+        unsafe { (*ptr).is_monitor_owner() }
+    }
+
+    pub fn notify(&self) -> bool {
         let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).monitor_exit() };
+        unsafe { (*ptr).notify() }
     }
 
-    pub fn notify_all(&self) {
+    pub fn notify_all(&self) -> bool {
         let ptr = self.get_raw_ptr();
         unsafe { (*ptr).notify_all() }
     }
 
-    pub fn wait(&self) {
+    pub fn wait(&self) -> bool {
         let ptr = self.get_raw_ptr();
         unsafe { (*ptr).wait() }
     }
 
-    pub fn wait_timeout(&self, duration: std::time::Duration) {
+    pub fn wait_timeout(&self, duration: std::time::Duration) -> bool {
         let ptr = self.get_raw_ptr();
         unsafe { (*ptr).wait_timeout(duration) }
     }
+
+    //the monitor needn't be owned
+    pub fn wake_waiters(&self) {
+        let ptr = self.get_raw_ptr();
+        unsafe { (*ptr).wake_waiters() }
+    }
 }
 
 impl OopPtr {
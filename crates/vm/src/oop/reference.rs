@@ -1,5 +1,5 @@
 use crate::oop::{ArrayOopDesc, InstOopDesc, MirrorOopDesc, TypeArrayDesc};
-use crate::runtime::thread::{Condvar, ReentrantMutex};
+use crate::runtime::thread::Monitor;
 use std::fmt;
 use std::fmt::Formatter;
 use std::time::Duration;
@@ -16,62 +16,51 @@ pub struct RefKindDesc {
     pub v: RefKind,
     pub hash_code: Option<i32>,
 
-    mutex: ReentrantMutex,
-    cond_var: Condvar,
+    monitor: Monitor,
 }
 
 impl RefKindDesc {
     pub fn new(v: RefKind) -> Self {
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
-        let cond_var = unsafe {
-            let mut cond = Condvar::new();
-            cond.init();
-            cond
-        };
-
         Self {
             v,
             hash_code: None,
-            mutex,
-            cond_var,
+            monitor: Monitor::default(),
         }
     }
 }
 
+//false if the current thread is not the owner of the monitor
 impl RefKindDesc {
     pub fn monitor_enter(&self) {
-        unsafe {
-            self.mutex.lock();
-        }
+        self.monitor.enter();
     }
 
-    pub fn monitor_exit(&self) {
-        unsafe {
-            self.mutex.unlock();
-        }
+    pub fn monitor_exit(&self) -> bool {
+        self.monitor.exit()
     }
 
-    pub fn wait(&self) {
-        unsafe {
-            self.cond_var.wait(&self.mutex);
-        }
+    pub fn is_monitor_owner(&self) -> bool {
+        self.monitor.is_owner()
     }
 
-    pub fn wait_timeout(&self, duration: Duration) {
-        unsafe {
-            self.cond_var.wait_timeout(&self.mutex, duration);
-        }
+    pub fn wait(&self) -> bool {
+        self.monitor.wait(None)
     }
 
-    pub fn notify_all(&self) {
-        unsafe {
-            self.cond_var.notify_all();
-        }
+    pub fn wait_timeout(&self, duration: Duration) -> bool {
+        self.monitor.wait(Some(duration))
+    }
+
+    pub fn notify(&self) -> bool {
+        self.monitor.notify()
+    }
+
+    pub fn notify_all(&self) -> bool {
+        self.monitor.notify_all()
+    }
+
+    pub fn wake_waiters(&self) {
+        self.monitor.wake_waiters()
     }
 }
 
@@ -121,20 +110,64 @@ impl RefKind {
     }
 }
 
-impl Drop for RefKindDesc {
-    fn drop(&mut self) {
-        unsafe {
-            self.mutex.destroy();
-        }
-    }
-}
-
 impl fmt::Debug for RefKindDesc {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("RefKindDesc")
             .field("v", &self.v)
             .field("hash_code", &self.hash_code)
-            .field("mutex", &"mutex")
+            .field("monitor", &"monitor")
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{RefKind, RefKindDesc};
+    use crate::oop::TypeArrayDesc;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn new_desc() -> RefKindDesc {
+        let ary = TypeArrayDesc::Byte(Box::default());
+        RefKindDesc::new(RefKind::TypeArray(ary))
+    }
+
+    #[test]
+    fn t_monitor_owner() {
+        let desc = new_desc();
+        assert!(!desc.monitor_exit());
+        assert!(!desc.notify());
+
+        desc.monitor_enter();
+        desc.monitor_enter();
+        assert!(desc.is_monitor_owner());
+        assert!(desc.monitor_exit());
+        assert!(desc.is_monitor_owner());
+        assert!(desc.monitor_exit());
+        assert!(!desc.is_monitor_owner());
+        assert!(!desc.monitor_exit());
+    }
+
+    //the recursive entries are released while waiting
+    #[test]
+    fn t_wait_recursive() {
+        let desc = Arc::new(new_desc());
+        desc.monitor_enter();
+        desc.monitor_enter();
+
+        let other = {
+            let desc = desc.clone();
+            std::thread::spawn(move || {
+                desc.monitor_enter();
+                desc.notify_all();
+                desc.monitor_exit()
+            })
+        };
+
+        assert!(desc.wait_timeout(Duration::from_secs(10)));
+        assert!(other.join().unwrap());
+        assert!(desc.monitor_exit());
+        assert!(desc.is_monitor_owner());
+        assert!(desc.monitor_exit());
+    }
+}
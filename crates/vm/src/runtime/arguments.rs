@@ -0,0 +1,317 @@
+use crate::oop::heap;
+use crate::runtime::consts::THREAD_MAX_STACK_FRAMES;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{RwLock, RwLockReadGuard};
+
+/*
+The VM options, as Arguments of HotSpot
+
+They are given by the launcher or JNI_CreateJavaVM, before the VM is initialized:
+  -Dkey=value       a system property, overrides the one computed from the host
+  -ea[:name] -da[:name] -esa -dsa
+                    the assertion status, see assertion_enabled
+  -Xss<size>        the stack of a Java thread, the frames limit is scaled by it
+  -Xmx<size>        the max heap, Runtime.maxMemory & the collection of oop::heap
+  -verbose:class    print the classes loaded
+
+The options of the launcher itself (-cp, -jar, -version...) are parsed by it.
+*/
+
+//HotSpot's default -Xss of x86_64, the stack of THREAD_MAX_STACK_FRAMES frames
+pub const DEFAULT_THREAD_STACK_SIZE: usize = 1024 * 1024;
+//the smallest -Xss accepted, the VM can't be initialized with less
+const MIN_THREAD_STACK_SIZE: usize = 160 * 1024;
+//the native stack taken by a frame of the interpreter
+const NATIVE_STACK_PER_FRAME: usize = 16 * 1024;
+
+lazy_static! {
+    static ref ARGUMENTS: RwLock<Arguments> = RwLock::new(Arguments::default());
+}
+
+//read by each invocation & class loading, kept out of the lock
+static MAX_STACK_FRAMES: AtomicUsize = AtomicUsize::new(THREAD_MAX_STACK_FRAMES);
+static VERBOSE_CLASS: AtomicBool = AtomicBool::new(false);
+
+/// -ea & -da, the name is in the binary form, "" is the unnamed package
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    All(bool),
+    Package(String, bool),
+    Class(String, bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Arguments {
+    /// -Dkey=value, in the order given
+    pub properties: Vec<(String, String)>,
+    /// java.class.path, searched by the application class loader
+    pub class_path: String,
+    /// -ea & -da, in the order given, the later ones take precedence
+    pub assertions: Vec<Assertion>,
+    /// -esa & -dsa
+    pub system_assertions: bool,
+    /// -Xss, in bytes
+    pub thread_stack_size: usize,
+    /// -Xmx, in bytes
+    pub max_heap_size: Option<usize>,
+    /// -verbose:class
+    pub verbose_class: bool,
+}
+
+impl Default for Arguments {
+    fn default() -> Self {
+        Self {
+            properties: vec![],
+            class_path: String::from("."),
+            assertions: vec![],
+            system_assertions: false,
+            thread_stack_size: DEFAULT_THREAD_STACK_SIZE,
+            max_heap_size: None,
+            verbose_class: false,
+        }
+    }
+}
+
+impl Arguments {
+    /// Parse a VM option
+    ///
+    /// Ok(false) if it's not a VM option, Err with the message if it's malformed.
+    pub fn parse_vm_option(&mut self, option: &str) -> Result<bool, String> {
+        if let Some(prop) = option.strip_prefix("-D") {
+            let (k, v) = match prop.find('=') {
+                Some(i) => (&prop[..i], &prop[i + 1..]),
+                None => (prop, ""),
+            };
+            if k.is_empty() {
+                return Err(format!("Invalid property: {}", option));
+            }
+            self.properties.push((k.to_string(), v.to_string()));
+            return Ok(true);
+        }
+
+        let (name, arg) = match option.find(':') {
+            Some(i) => (&option[..i], Some(&option[i + 1..])),
+            None => (option, None),
+        };
+        match name {
+            "-ea" | "-enableassertions" => self.assertions.push(parse_assertion(arg, true)),
+            "-da" | "-disableassertions" => self.assertions.push(parse_assertion(arg, false)),
+            "-esa" | "-enablesystemassertions" => self.system_assertions = true,
+            "-dsa" | "-disablesystemassertions" => self.system_assertions = false,
+            "-verbose" => match arg {
+                None | Some("class") => self.verbose_class = true,
+                //-verbose:gc & -verbose:jni print nothing
+                Some("gc") | Some("jni") => (),
+                Some(_) => return Err(format!("Unrecognized option: {}", option)),
+            },
+            "-XX" => warn!("ignored option: {}", option),
+            _ if option.starts_with("-Xss") => {
+                let size = parse_size(&option[4..])
+                    .ok_or_else(|| format!("Invalid thread stack size: {}", option))?;
+                if size < MIN_THREAD_STACK_SIZE {
+                    return Err(format!(
+                        "The stack size specified is too small, Specify at least {}k",
+                        MIN_THREAD_STACK_SIZE / 1024
+                    ));
+                }
+                self.thread_stack_size = size;
+            }
+            _ if option.starts_with("-Xmx") => {
+                let size = parse_size(&option[4..])
+                    .ok_or_else(|| format!("Invalid maximum heap size: {}", option))?;
+                self.max_heap_size = Some(size);
+            }
+            //the heap isn't reserved ahead
+            _ if option.starts_with("-Xms") => (),
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Whether the assertions of the class 'name' (in the binary form) are enabled
+    ///
+    /// As JavaAssertions of HotSpot, the option of the class is taken first, then
+    /// the options of its package & the outer packages, the innermost one, the
+    /// default at last; the system classes (by the bootstrap loader) are by -esa.
+    pub fn assertion_enabled(&self, name: &str, system: bool) -> bool {
+        let find =
+            |f: &dyn Fn(&Assertion) -> Option<bool>| self.assertions.iter().rev().find_map(f);
+
+        let v = find(&|it| match it {
+            Assertion::Class(cls, enabled) if cls == name => Some(*enabled),
+            _ => None,
+        });
+        if let Some(v) = v {
+            return v;
+        }
+
+        let mut pkg = match name.rfind('.') {
+            Some(i) => &name[..i],
+            None => "",
+        };
+        loop {
+            let v = find(&|it| match it {
+                Assertion::Package(p, enabled) if p == pkg => Some(*enabled),
+                _ => None,
+            });
+            if let Some(v) = v {
+                return v;
+            }
+
+            match pkg.rfind('.') {
+                Some(i) => pkg = &pkg[..i],
+                None => break,
+            }
+        }
+
+        if system {
+            self.system_assertions
+        } else {
+            find(&|it| match it {
+                Assertion::All(enabled) => Some(*enabled),
+                _ => None,
+            })
+            .unwrap_or(false)
+        }
+    }
+}
+
+fn parse_assertion(arg: Option<&str>, enabled: bool) -> Assertion {
+    match arg {
+        None | Some("") => Assertion::All(enabled),
+        Some("...") => Assertion::Package(String::new(), enabled),
+        Some(name) => match name.strip_suffix("...") {
+            Some(pkg) => Assertion::Package(pkg.to_string(), enabled),
+            None => Assertion::Class(name.to_string(), enabled),
+        },
+    }
+}
+
+/// The size of -Xss, -Xmx..., the bytes with the unit suffix k, m or g
+pub fn parse_size(s: &str) -> Option<usize> {
+    let (digits, unit) = match s.chars().last()? {
+        'k' | 'K' => (&s[..s.len() - 1], 1024),
+        'm' | 'M' => (&s[..s.len() - 1], 1024 * 1024),
+        'g' | 'G' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// Set the options, before the VM is initialized
+pub fn set(args: Arguments) {
+    let frames = args.thread_stack_size / (DEFAULT_THREAD_STACK_SIZE / THREAD_MAX_STACK_FRAMES);
+    MAX_STACK_FRAMES.store(frames, Ordering::Relaxed);
+    VERBOSE_CLASS.store(args.verbose_class, Ordering::Relaxed);
+    *ARGUMENTS.write().unwrap() = args;
+    heap::set_max_size(max_heap_size());
+}
+
+pub fn get() -> RwLockReadGuard<'static, Arguments> {
+    ARGUMENTS.read().unwrap()
+}
+
+/// The frames limit of a thread, StackOverflowError is thrown beyond it
+#[inline]
+pub fn max_stack_frames() -> usize {
+    MAX_STACK_FRAMES.load(Ordering::Relaxed)
+}
+
+/// The native stack of a thread, to run max_stack_frames frames
+pub fn native_stack_size() -> usize {
+    max_stack_frames() * NATIVE_STACK_PER_FRAME
+}
+
+#[inline]
+pub fn verbose_class() -> bool {
+    VERBOSE_CLASS.load(Ordering::Relaxed)
+}
+
+/// -Xmx, a quarter of the physical memory by default, as HotSpot
+pub fn max_heap_size() -> usize {
+    if let Some(size) = get().max_heap_size {
+        return size;
+    }
+
+    let (pages, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_PHYS_PAGES),
+            libc::sysconf(libc::_SC_PAGESIZE),
+        )
+    };
+    if pages > 0 && page_size > 0 {
+        (pages as usize).saturating_mul(page_size as usize) / 4
+    } else {
+        usize::MAX
+    }
+}
+
+pub fn assertion_enabled(name: &str, system: bool) -> bool {
+    get().assertion_enabled(name, system)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("256k"), Some(256 * 1024));
+        assert_eq!(parse_size("2M"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("m"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn t_parse_vm_option() {
+        let mut args = Arguments::default();
+        assert_eq!(args.parse_vm_option("-Dfoo=bar=1"), Ok(true));
+        assert_eq!(args.parse_vm_option("-Dempty"), Ok(true));
+        assert_eq!(args.parse_vm_option("-ea:com.foo..."), Ok(true));
+        assert_eq!(args.parse_vm_option("-da:com.foo.Bar"), Ok(true));
+        assert_eq!(args.parse_vm_option("-Xss2m"), Ok(true));
+        assert!(args.parse_vm_option("-Xss1k").is_err());
+        assert_eq!(args.parse_vm_option("-jar"), Ok(false));
+
+        assert_eq!(
+            args.properties,
+            vec![
+                ("foo".to_string(), "bar=1".to_string()),
+                ("empty".to_string(), String::new())
+            ]
+        );
+        assert_eq!(
+            args.assertions,
+            vec![
+                Assertion::Package("com.foo".to_string(), true),
+                Assertion::Class("com.foo.Bar".to_string(), false)
+            ]
+        );
+        assert_eq!(args.thread_stack_size, 2 * 1024 * 1024);
+    }
+
+    #[test]
+    fn t_assertion_enabled() {
+        let mut args = Arguments::default();
+        for it in &[
+            "-ea",
+            "-da:com.foo...",
+            "-ea:com.foo.bar...",
+            "-ea:com.foo.Baz",
+            "-ea:...",
+        ] {
+            assert_eq!(args.parse_vm_option(it), Ok(true));
+        }
+
+        assert!(args.assertion_enabled("Main", false));
+        assert!(args.assertion_enabled("org.Main", false));
+        assert!(!args.assertion_enabled("com.foo.Main", false));
+        assert!(!args.assertion_enabled("com.foo.x.Main", false));
+        assert!(args.assertion_enabled("com.foo.bar.Main", false));
+        assert!(args.assertion_enabled("com.foo.Baz", false));
+        assert!(!args.assertion_enabled("java.lang.String", true));
+    }
+}
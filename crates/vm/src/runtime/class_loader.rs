@@ -1,27 +1,49 @@
 use crate::native;
 use crate::oop::class::ClassPtr;
-use crate::oop::{self, Class, ValueType};
-use crate::runtime::{self, ClassPathResult};
+use crate::oop::{self, Class, Oop, ValueType};
+use crate::runtime::{self, arguments, exception, thread, ClassPathResult, DataArea, JavaCall};
 use crate::types::*;
-use crate::util;
+use crate::{new_br, util};
 use class_parser::parse_class;
-use classfile::{constant_pool, BytesRef, ConstantPool, U2};
-use std::sync::{Arc, Mutex};
+use class_verification::{Constraint, Error as VerifyError};
+use classfile::consts::{
+    J_CLASS_FORMAT_ERROR, J_ILLEGAL_ARGUMENT, J_LINKAGE_ERROR, J_NO_CLASS_DEF_FOUND_ERROR,
+    J_VERIFY_ERROR,
+};
+use classfile::{constant_pool, BytesRef, ClassFile, ConstantPool, ConstantPoolType, U2};
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ClassLoader {
     Base,
     Bootstrap,
+    //a java.lang.ClassLoader object, the index in LOADERS
+    User(usize),
 }
 
+lazy_static! {
+    //the loader objects are kept alive, classes are never unloaded
+    static ref LOADERS: RwLock<Vec<Oop>> = RwLock::new(Vec::new());
+}
+
+//the suffix of the names of anonymous classes
+static ANONYMOUS_CLASSES: AtomicUsize = AtomicUsize::new(1);
+
 pub fn require_class(class_loader: Option<ClassLoader>, name: &BytesRef) -> Option<ClassRef> {
     require_class3(class_loader, name.as_slice())
 }
 
-pub fn require_class2(index: U2, cp: &ConstantPool) -> Option<ClassRef> {
+//'class_loader' is the defining loader of the class which 'cp' belongs to
+pub fn require_class2(
+    class_loader: Option<ClassLoader>,
+    index: U2,
+    cp: &ConstantPool,
+) -> Option<ClassRef> {
     let class = constant_pool::get_class_name(cp, index as usize);
     // trace!("require_class2 class = {}", String::from_utf8_lossy(class.as_slice()));
-    require_class3(None, class.as_slice())
+    require_class3(class_loader, class.as_slice())
 }
 
 pub fn require_class3(class_loader: Option<ClassLoader>, name: &[u8]) -> Option<ClassRef> {
@@ -29,14 +51,55 @@ pub fn require_class3(class_loader: Option<ClassLoader>, name: &[u8]) -> Option<
     class_loader.load_class(name)
 }
 
+impl ClassLoader {
+    /// The loader of a java.lang.ClassLoader object, None for null, the bootstrap loader
+    pub fn from_oop(loader: &Oop) -> Option<ClassLoader> {
+        let rf = match loader {
+            Oop::Null => return None,
+            v => v.extract_ref(),
+        };
+
+        {
+            let loaders = LOADERS.read().unwrap();
+            let pos = loaders
+                .iter()
+                .position(|it| Arc::ptr_eq(&it.extract_ref(), &rf));
+            if let Some(pos) = pos {
+                return Some(ClassLoader::User(pos));
+            }
+        }
+
+        let mut loaders = LOADERS.write().unwrap();
+        let pos = loaders
+            .iter()
+            .position(|it| Arc::ptr_eq(&it.extract_ref(), &rf))
+            .unwrap_or_else(|| {
+                loaders.push(loader.clone());
+                loaders.len() - 1
+            });
+        Some(ClassLoader::User(pos))
+    }
+
+    /// The java.lang.ClassLoader object, null for the bootstrap loader
+    pub fn to_oop(&self) -> Oop {
+        match self {
+            ClassLoader::User(pos) => {
+                let loaders = LOADERS.read().unwrap();
+                loaders[*pos].clone()
+            }
+            _ => Oop::Null,
+        }
+    }
+}
+
 impl ClassLoader {
     fn load_class(&self, name: &[u8]) -> Option<ClassRef> {
         debug_assert!(!name.contains(&b'.'));
         //        error!("load_class name = {}", String::from_utf8_lossy(name));
         match self {
             ClassLoader::Base => (),
-            ClassLoader::Bootstrap => {
-                let it = runtime::sys_dic_find(name);
+            ClassLoader::Bootstrap | ClassLoader::User(_) => {
+                let it = runtime::sys_dic_find(*self, name);
                 if it.is_some() {
                     //                    info!("load_class in dic: {}", String::from_utf8_lossy(name));
                     return it;
@@ -46,13 +109,15 @@ impl ClassLoader {
 
         if is_array(name) {
             self.load_array_class(name)
+        } else if let ClassLoader::User(_) = self {
+            self.load_class_by_java(name)
         } else {
             let class = self.load_class_from_path(name);
             if let Some(class) = &class {
                 match self {
                     ClassLoader::Base => (),
-                    ClassLoader::Bootstrap => {
-                        runtime::sys_dic_put(name, class.clone());
+                    _ => {
+                        runtime::sys_dic_put(*self, name, class.clone());
                         let this_ref = class.clone();
 
                         {
@@ -71,6 +136,24 @@ impl ClassLoader {
     }
 
     fn load_array_class(&self, name: &[u8]) -> Option<ClassRef> {
+        //JVM spec, 5.3.3 the array class is defined by the loader of the element class
+        if let ClassLoader::User(_) = self {
+            let elm_loader = match element_name(name) {
+                Some(elm) => {
+                    let elm = self.load_class(elm)?;
+                    let cls = elm.get_class();
+                    cls.class_loader.unwrap_or(ClassLoader::Bootstrap)
+                }
+                None => ClassLoader::Bootstrap,
+            };
+
+            if elm_loader != *self {
+                let class = elm_loader.load_class(name)?;
+                runtime::sys_dic_put(*self, name, class.clone());
+                return Some(class);
+            }
+        }
+
         match calc_dimension(name) {
             Some(1) => {
                 // dimension == 1
@@ -87,12 +170,7 @@ impl ClassLoader {
                                     let mut class = class.get_mut_class();
                                     class.link_class(this_ref);
                                 }
-                                match self {
-                                    ClassLoader::Base => (),
-                                    ClassLoader::Bootstrap => {
-                                        runtime::sys_dic_put(name, class.clone());
-                                    }
-                                }
+                                self.put_array_class(name, &class);
 
                                 native::java_lang_Class::create_mirror(class.clone());
 
@@ -114,12 +192,7 @@ impl ClassLoader {
                             class.link_class(this_ref);
                         }
 
-                        match self {
-                            ClassLoader::Base => (),
-                            ClassLoader::Bootstrap => {
-                                runtime::sys_dic_put(name, class.clone());
-                            }
-                        }
+                        self.put_array_class(name, &class);
 
                         //mirror has been created when vm inited
 
@@ -137,12 +210,7 @@ impl ClassLoader {
                     Some(down_type) => {
                         let class = Class::new_wrapped_ary(*self, down_type);
                         let class = ClassPtr::new(class);
-                        match self {
-                            ClassLoader::Base => (),
-                            ClassLoader::Bootstrap => {
-                                runtime::sys_dic_put(name, class.clone());
-                            }
-                        }
+                        self.put_array_class(name, &class);
 
                         native::java_lang_Class::create_mirror(class.clone());
 
@@ -155,24 +223,331 @@ impl ClassLoader {
         }
     }
 
+    fn put_array_class(&self, name: &[u8], class: &ClassRef) {
+        match self {
+            ClassLoader::Base => (),
+            _ => runtime::sys_dic_put(*self, name, class.clone()),
+        }
+    }
+
+    //delegate to the java.lang.ClassLoader object, by loadClass(String)
+    fn load_class_by_java(&self, name: &[u8]) -> Option<ClassRef> {
+        let loader = self.to_oop();
+        let mir = {
+            let rf = loader.extract_ref();
+            let inst = rf.extract_inst();
+            let cls = inst.class.get_class();
+            cls.get_virtual_method(
+                &new_br("loadClass"),
+                &new_br("(Ljava/lang/String;)Ljava/lang/Class;"),
+            )
+            .unwrap()
+        };
+
+        let binary_name: Vec<u8> = name
+            .iter()
+            .map(|&c| if c == b'/' { b'.' } else { c })
+            .collect();
+        let args = vec![loader, util::oop::new_java_lang_string3(&binary_name)];
+        let mut jc = JavaCall::new_with_args(mir, args);
+        let area = DataArea::new(1);
+        jc.invoke(Some(&area), false);
+
+        //ClassNotFoundException... thrown by loadClass
+        if thread::is_meet_ex() {
+            return None;
+        }
+
+        let mirror = area.stack.borrow_mut().pop_ref();
+        let class = match mirror {
+            Oop::Null => None,
+            mirror => mirror.extract_ref().extract_mirror().target.clone(),
+        };
+        match class {
+            Some(class) if class.get_class().name.as_slice() == name => {
+                runtime::sys_dic_put(*self, name, class.clone());
+                Some(class)
+            }
+            _ => {
+                let name = String::from_utf8_lossy(name).to_string();
+                throw_ex(J_NO_CLASS_DEF_FOUND_ERROR, name);
+                None
+            }
+        }
+    }
+
     fn load_class_from_path(&self, name: &[u8]) -> Option<ClassRef> {
         let name = unsafe { std::str::from_utf8_unchecked(name) };
         match runtime::find_class_in_classpath(name) {
-            Ok(ClassPathResult(_, buf)) => match parse_class(&buf) {
-                Ok(cf) => {
-                    let cfr = Arc::new(Box::new(cf.1));
-                    let class = Class::new_class(cfr, Some(*self));
-                    Some(ClassPtr::new(class))
-                }
+            Ok(ClassPathResult(source, buf)) => {
+                //the boot class path is trusted, as BytecodeVerificationLocal of HotSpot
+                let (class, _) = self.new_class(Some(name.as_bytes()), &buf, false)?;
+                trace_loaded(name.as_bytes(), &source);
+                Some(class)
+            }
+            Err(_) => None,
+        }
+    }
 
-                Err(e) => unreachable!("name={}, {}", name, e),
-            },
+    //parse & verify the class file, the exception is pending if failed
+    //
+    //the constraints of the verifier are checked when the class is linked
+    fn new_class(
+        &self,
+        name: Option<&[u8]>,
+        buf: &[u8],
+        need_verify: bool,
+    ) -> Option<(ClassRef, Vec<Constraint>)> {
+        let (cf, constraints) = parse_and_verify(name, buf, need_verify)?;
+        let cfr = Arc::new(Box::new(cf));
+        let class = Class::new_class(cfr, Some(*self));
+        Some((ClassPtr::new(class), constraints))
+    }
+}
 
-            Err(_) => None,
+//the exception is pending if failed
+fn parse_and_verify(
+    name: Option<&[u8]>,
+    buf: &[u8],
+    need_verify: bool,
+) -> Option<(ClassFile, Vec<Constraint>)> {
+    //the offset & the structure malformed, as "method `main`, attribute `Code`: ..."
+    let cf = match parse_class(buf) {
+        Ok(cf) => cf,
+        Err(e) => {
+            let name = String::from_utf8_lossy(name.unwrap_or(b""));
+            throw_ex(J_CLASS_FORMAT_ERROR, format!("{}: {}", name, e));
+            return None;
+        }
+    };
+
+    let this_name = constant_pool::get_class_name(&cf.cp, cf.this_class as usize).clone();
+    let name = match name {
+        Some(name) if name != this_name.as_slice() => {
+            let msg = format!(
+                "{} (wrong name: {})",
+                String::from_utf8_lossy(name),
+                String::from_utf8_lossy(this_name.as_slice())
+            );
+            throw_ex(J_NO_CLASS_DEF_FOUND_ERROR, msg);
+            return None;
+        }
+        _ => unsafe { std::str::from_utf8_unchecked(this_name.as_slice()) },
+    };
+
+    let constraints = if need_verify {
+        verify(name.as_bytes(), &cf)?
+    } else {
+        vec![]
+    };
+
+    Some((cf, constraints))
+}
+
+/// Define a class from the class file bytes, for ClassLoader.defineClass & JNI DefineClass
+///
+/// None with the exception pending, if the class file is malformed, or the
+/// class has been defined or loaded by 'class_loader'.
+pub fn define_class(
+    class_loader: Option<ClassLoader>,
+    name: Option<&[u8]>,
+    buf: &[u8],
+) -> Option<ClassRef> {
+    let class_loader = class_loader.unwrap_or(ClassLoader::Bootstrap);
+    let (class, constraints) = class_loader.new_class(name, buf, true)?;
+    let name = class.get_class().name.clone();
+    if runtime::sys_dic_find(class_loader, name.as_slice()).is_some() {
+        let msg = format!(
+            "duplicate class definition: {}",
+            String::from_utf8_lossy(name.as_slice())
+        );
+        throw_ex(J_LINKAGE_ERROR, msg);
+        return None;
+    }
+
+    runtime::sys_dic_put(class_loader, name.as_slice(), class.clone());
+    link_new_class(&class);
+    if !check_constraints(&class, &constraints) {
+        runtime::sys_dic_remove(class_loader, name.as_slice());
+        return None;
+    }
+    trace_loaded(name.as_slice(), "__JVM_DefineClass__");
+
+    Some(class)
+}
+
+/// Define an anonymous class for Unsafe.defineAnonymousClass, by the loader of 'host'
+///
+/// The class is renamed "name/N" as HotSpot does, so it never clashes with the
+/// classes of the loader; the references to the class itself in its constant
+/// pool are renamed too. The non-null elements of 'cp_patches' are the objects
+/// of the String entries, the other entries can't be patched.
+pub fn define_anonymous_class(host: &ClassRef, buf: &[u8], cp_patches: &[Oop]) -> Option<ClassRef> {
+    let class_loader = host
+        .get_class()
+        .class_loader
+        .unwrap_or(ClassLoader::Bootstrap);
+    let mut cf = match parse_class(buf) {
+        Ok(cf) => cf,
+        Err(e) => {
+            throw_ex(J_CLASS_FORMAT_ERROR, e.to_string());
+            return None;
+        }
+    };
+
+    let this_name = constant_pool::get_class_name(&cf.cp, cf.this_class as usize).clone();
+    let mut constraints = verify(this_name.as_slice(), &cf)?;
+
+    let mut patches = FxHashMap::default();
+    for (i, v) in cp_patches.iter().enumerate() {
+        match (v, cf.cp.get(i)) {
+            (Oop::Null, _) => (),
+            (v, Some(ConstantPoolType::String { .. })) => {
+                patches.insert(i, v.clone());
+            }
+            _ => {
+                let msg = format!("unsupported constant pool patch #{}", i);
+                throw_ex(J_ILLEGAL_ARGUMENT, msg);
+                return None;
+            }
+        }
+    }
+
+    let name = {
+        let n = ANONYMOUS_CLASSES.fetch_add(1, Ordering::Relaxed);
+        let mut name = this_name.to_vec();
+        name.extend_from_slice(format!("/{}", n).as_bytes());
+        Arc::new(name)
+    };
+    let name_index = match &cf.cp[cf.this_class as usize] {
+        ConstantPoolType::Class { name_index } => *name_index as usize,
+        _ => unreachable!(),
+    };
+    Arc::make_mut(&mut cf.cp)[name_index] = ConstantPoolType::Utf8 {
+        bytes: name.clone(),
+    };
+    for it in constraints.iter_mut() {
+        if it.from == this_name {
+            it.from = name.clone();
+        }
+        if it.to == this_name {
+            it.to = name.clone();
+        }
+    }
+
+    let cfr = Arc::new(Box::new(cf));
+    let mut class = Class::new_class(cfr, Some(class_loader));
+    if let oop::ClassKind::Instance(cls_obj) = &mut class.kind {
+        cls_obj.cp_patches = patches;
+    }
+    let class = ClassPtr::new(class);
+
+    runtime::sys_dic_put(class_loader, name.as_slice(), class.clone());
+    link_new_class(&class);
+    if !check_constraints(&class, &constraints) {
+        runtime::sys_dic_remove(class_loader, name.as_slice());
+        return None;
+    }
+    let host_name = String::from_utf8_lossy(host.get_class().name.as_slice()).replace("/", ".");
+    trace_loaded(name.as_slice(), &host_name);
+
+    Some(class)
+}
+
+fn link_new_class(class: &ClassRef) {
+    {
+        let this_ref = class.clone();
+        let mut cls = class.get_mut_class();
+        cls.set_class_state(oop::class::State::Loaded);
+        cls.link_class(this_ref);
+    }
+    native::java_lang_Class::create_mirror(class.clone());
+}
+
+//-verbose:class, in the format of HotSpot
+fn trace_loaded(name: &[u8], source: &str) {
+    if arguments::verbose_class() {
+        let name = String::from_utf8_lossy(name).replace("/", ".");
+        println!("[Loaded {} from {}]", name, source);
+    }
+}
+
+//the exception is pending if failed
+fn verify(name: &[u8], cf: &ClassFile) -> Option<Vec<Constraint>> {
+    match class_verification::verify(cf) {
+        Ok(constraints) => Some(constraints),
+        Err(e) => {
+            let name = String::from_utf8_lossy(name);
+            let (cls_name, msg) = match e {
+                VerifyError::Format(e) => (J_CLASS_FORMAT_ERROR, format!("{}: {:?}", name, e)),
+                VerifyError::Verify(e) => (J_VERIFY_ERROR, e.to_string()),
+            };
+            throw_ex(cls_name, msg);
+            None
         }
     }
 }
 
+/*
+The subtyping assumed by the verifier, checked when the class is linked
+
+The classes are loaded by the defining loader of 'class', after it has been
+put into the dictionary, so the classes referring to each other can be loaded.
+As isJavaAssignable of JVMS 4.10.1.2, any class is assignable to an interface.
+*/
+fn check_constraints(class: &ClassRef, constraints: &[Constraint]) -> bool {
+    let (class_loader, this_name) = {
+        let cls = class.get_class();
+        (cls.class_loader, cls.name.clone())
+    };
+    let load = |name: &BytesRef| {
+        let class = require_class(class_loader, name);
+        if class.is_none() && !thread::is_meet_ex() {
+            let name = String::from_utf8_lossy(name.as_slice()).to_string();
+            throw_ex(J_NO_CLASS_DEF_FOUND_ERROR, name);
+        }
+        class
+    };
+
+    for it in constraints {
+        let to = match load(&it.to) {
+            Some(to) => to,
+            None => return false,
+        };
+        if to.get_class().is_interface() {
+            continue;
+        }
+        let from = match load(&it.from) {
+            Some(from) => from,
+            None => return false,
+        };
+        if !runtime::cmp::check_inherit(from, to) {
+            let msg = format!(
+                "(class: {}) Type '{}' is not assignable to '{}'",
+                String::from_utf8_lossy(this_name.as_slice()),
+                String::from_utf8_lossy(it.from.as_slice()),
+                String::from_utf8_lossy(it.to.as_slice())
+            );
+            throw_ex(J_VERIFY_ERROR, msg);
+            return false;
+        }
+    }
+
+    true
+}
+
+//the main class is loaded before the main thread has frames
+fn throw_ex(cls_name: &'static [u8], msg: String) {
+    let jt = runtime::thread::current_java_thread();
+    let has_frame = !jt.read().unwrap().frames.is_empty();
+    if has_frame {
+        exception::meet_ex(cls_name, Some(msg));
+    } else {
+        let ex = exception::new(cls_name, Some(msg));
+        jt.write().unwrap().set_ex(ex);
+    }
+}
+
 fn calc_dimension(name: &[u8]) -> Option<usize> {
     if is_array(name) {
         name.iter().position(|&c| c != b'[')
@@ -185,8 +560,138 @@ fn is_array(name: &[u8]) -> bool {
     name.starts_with(&[b'['])
 }
 
+//"[[Ljava/lang/Object;" => "java/lang/Object", None for the primitive arrays
+fn element_name(name: &[u8]) -> Option<&[u8]> {
+    let dimension = calc_dimension(name)?;
+    let elm = &name[dimension..];
+    if elm.starts_with(b"L") {
+        Some(&elm[1..elm.len() - 1])
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::ClassLoader;
+    use crate::oop::class::{Class, ClassPtr};
+    use crate::runtime::{self, sys_dic};
+    use crate::types::ClassRef;
+    use classfile::flags::{ACC_PUBLIC, ACC_STATIC};
+    use classfile::{ClassBuilder, ClassFile, OpCode, Version};
+    use std::sync::Arc;
+
+    fn build(name: &str, super_name: Option<&str>) -> ClassFile {
+        let version = Version {
+            minor: 0,
+            major: 52,
+        };
+        ClassBuilder::new(version, ACC_PUBLIC, name, super_name)
+            .build()
+            .unwrap()
+    }
+
+    pub(crate) fn new_object() -> ClassRef {
+        let cf = build("java/lang/Object", None);
+        let cls = ClassPtr::new(Class::new_class(Arc::new(Box::new(cf)), None));
+        cls.get_mut_class().link_class(cls.clone());
+        cls
+    }
+
+    //the class is keyed by the defining loader, findLoadedClass0 finds it by sys_dic
+    #[test]
+    fn t_define_by_loaders() {
+        let object = new_object();
+        let (a, b) = (ClassLoader::User(10000), ClassLoader::User(10001));
+        sys_dic::put(a, b"java/lang/Object", object.clone());
+        sys_dic::put(b, b"java/lang/Object", object);
+
+        let buf = build("t/Foo", Some("java/lang/Object")).to_bytes().unwrap();
+        let foo_a = runtime::define_class(Some(a), Some(b"t/Foo"), &buf).unwrap();
+        let foo_b = runtime::define_class(Some(b), Some(b"t/Foo"), &buf).unwrap();
+        assert!(!Arc::ptr_eq(&foo_a, &foo_b));
+        assert_eq!(foo_a.get_class().class_loader, Some(a));
+        assert_eq!(foo_b.get_class().class_loader, Some(b));
+
+        let found = sys_dic::find(a, b"t/Foo").unwrap();
+        assert!(Arc::ptr_eq(&found, &foo_a));
+        let found = sys_dic::find(b, b"t/Foo").unwrap();
+        assert!(Arc::ptr_eq(&found, &foo_b));
+        assert!(sys_dic::find(ClassLoader::Bootstrap, b"t/Foo").is_none());
+    }
+
+    //the subtyping assumed by the verifier is checked by the defining loader
+    #[test]
+    fn t_verify_constraints() {
+        let loader = ClassLoader::User(10002);
+        sys_dic::put(loader, b"java/lang/Object", new_object());
+
+        let define = |cf: ClassFile| {
+            let buf = cf.to_bytes().unwrap();
+            runtime::define_class(Some(loader), None, &buf)
+        };
+        define(build("t/B", Some("java/lang/Object"))).unwrap();
+        define(build("t/A", Some("t/B"))).unwrap();
+
+        //static B f(A a) { return a; }
+        let mut cb = ClassBuilder::new(
+            Version {
+                minor: 0,
+                major: 52,
+            },
+            ACC_PUBLIC,
+            "t/C",
+            Some("java/lang/Object"),
+        );
+        let mut c = cb.code(ACC_STATIC, "f", "(Lt/A;)Lt/B;");
+        c.local(OpCode::aload, 0);
+        c.op(OpCode::areturn);
+        c.finish().unwrap();
+        let cf = cb.build().unwrap();
+        assert_eq!(
+            class_verification::verify(&cf).unwrap(),
+            vec![class_verification::Constraint {
+                from: Arc::new(b"t/A".to_vec()),
+                to: Arc::new(b"t/B".to_vec()),
+            }]
+        );
+
+        let c = define(cf).unwrap();
+        assert_eq!(c.get_class().class_loader, Some(loader));
+    }
+
+    //no StackMapTable before 51, the class of Java 6 is defined without type checking
+    #[test]
+    fn t_define_version_50() {
+        let loader = ClassLoader::User(10008);
+        sys_dic::put(loader, b"java/lang/Object", new_object());
+
+        //static int f(int a) { return a > 0 ? a : 0; }
+        let mut cb = ClassBuilder::new(
+            Version {
+                minor: 0,
+                major: 50,
+            },
+            ACC_PUBLIC,
+            "t/V50",
+            Some("java/lang/Object"),
+        );
+        let mut c = cb.code(ACC_STATIC, "f", "(I)I");
+        let zero = c.new_label();
+        c.local(OpCode::iload, 0);
+        c.jump(OpCode::ifle, zero);
+        c.local(OpCode::iload, 0);
+        c.op(OpCode::ireturn);
+        c.bind(zero);
+        c.op(OpCode::iconst_0);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+        let buf = cb.build().unwrap().to_bytes().unwrap();
+
+        let c = runtime::define_class(Some(loader), None, &buf).unwrap();
+        assert_eq!(c.get_class().class_loader, Some(loader));
+    }
+
     #[test]
     fn t_basic() {
         use super::calc_dimension;
@@ -204,4 +709,17 @@ mod tests {
         let name = "[Ljava/lang/Object;";
         assert_eq!("java/lang/Object", &name[2..name.len() - 1]);
     }
+
+    #[test]
+    fn t_element_name() {
+        use super::element_name;
+        assert_eq!(element_name(b"java/lang/Object"), None);
+        assert_eq!(element_name(b"[I"), None);
+        assert_eq!(element_name(b"[[J"), None);
+        assert_eq!(
+            element_name(b"[Ljava/lang/Object;"),
+            Some(&b"java/lang/Object"[..])
+        );
+        assert_eq!(element_name(b"[[La/B;"), Some(&b"a/B"[..]));
+    }
 }
@@ -1,10 +1,11 @@
-use std::cell::RefCell;
+use std::sync::Mutex;
 
 use rustc_hash::FxHashMap;
 
 use classfile::ConstantPool;
 
-use crate::oop::field;
+use crate::oop::{field, Oop};
+use crate::runtime::ClassLoader;
 use crate::types::{FieldIdRef, MethodIdRef};
 use crate::{oop, runtime};
 
@@ -29,27 +30,37 @@ impl CacheType {
     }
 }
 
+type CallSite = Result<(MethodIdRef, Oop), Oop>;
+
 pub struct ConstantPoolCache {
     cp: ConstantPool,
-    cache: RefCell<FxHashMap<usize, CacheType>>,
+    //the defining loader of the class, resolves the classes referenced
+    class_loader: Option<ClassLoader>,
+    cache: Mutex<FxHashMap<usize, CacheType>>,
+
+    //  FxHashMap<(method offset, invokedynamic pc), the invoker & its appendix, or the error of the bootstrap>
+    //linked by any thread
+    call_sites: Mutex<FxHashMap<(usize, usize), CallSite>>,
 }
 
 impl ConstantPoolCache {
-    pub fn new(cp: ConstantPool) -> Self {
+    pub fn new(cp: ConstantPool, class_loader: Option<ClassLoader>) -> Self {
         Self {
             cp,
-            cache: RefCell::new(FxHashMap::default()),
+            class_loader,
+            cache: Mutex::new(FxHashMap::default()),
+            call_sites: Mutex::new(FxHashMap::default()),
         }
     }
 
     pub fn get_field(&self, idx: usize, is_static: bool) -> FieldIdRef {
-        let cache = self.cache.borrow();
+        let cache = self.cache.lock().unwrap();
         let it = cache.get(&idx);
         match it {
             Some(it) => it.extract_field(),
             None => {
                 drop(cache);
-                let fid = field::get_field_ref(&self.cp, idx, is_static);
+                let fid = field::get_field_ref(self.class_loader, &self.cp, idx, is_static);
                 self.cache_field(idx, fid.clone());
                 fid
             }
@@ -57,19 +68,19 @@ impl ConstantPoolCache {
     }
 
     fn cache_field(&self, k: usize, v: FieldIdRef) {
-        let mut cache = self.cache.borrow_mut();
+        let mut cache = self.cache.lock().unwrap();
         let v = CacheType::Field(v);
         cache.insert(k, v);
     }
 
     pub fn get_method(&self, idx: usize) -> MethodIdRef {
-        let cache = self.cache.borrow();
+        let cache = self.cache.lock().unwrap();
         let it = cache.get(&idx);
         match it {
             Some(it) => it.extract_method(),
             None => {
                 drop(cache);
-                let m = runtime::method::get_method_ref(&self.cp, idx).unwrap();
+                let m = runtime::method::get_method_ref(self.class_loader, &self.cp, idx).unwrap();
                 self.cache_method(idx, m.clone());
                 m
             }
@@ -77,8 +88,32 @@ impl ConstantPoolCache {
     }
 
     fn cache_method(&self, k: usize, v: MethodIdRef) {
-        let mut cache = self.cache.borrow_mut();
+        let mut cache = self.cache.lock().unwrap();
         let v = CacheType::Method(v);
         cache.insert(k, v);
     }
+
+    pub fn get_call_site(&self, method_offset: usize, pc: usize) -> Option<CallSite> {
+        let call_sites = self.call_sites.lock().unwrap();
+        call_sites.get(&(method_offset, pc)).cloned()
+    }
+
+    //JVM spec, 6.5 invokedynamic
+    //if several threads link the same call site at once, the first result wins,
+    //a failed linkage throws the same error at every execution
+    pub fn cache_call_site(&self, method_offset: usize, pc: usize, v: CallSite) -> CallSite {
+        let mut call_sites = self.call_sites.lock().unwrap();
+        call_sites.entry((method_offset, pc)).or_insert(v).clone()
+    }
+
+    //gc roots, the appendixes & the errors cached
+    pub fn oops(&self) -> Vec<Oop> {
+        let call_sites = self.call_sites.lock().unwrap();
+        call_sites
+            .values()
+            .map(|it| match it {
+                Ok((_, v)) | Err(v) => v.clone(),
+            })
+            .collect()
+    }
 }
@@ -24,6 +24,8 @@ In a read-only Frame context, to modify the DataArea, borrow_mut is fine.
 pub struct DataArea {
     pub stack: RefCell<Stack>,
     pub return_v: RefCell<Option<Oop>>,
+    //entered by monitorenter, released when the frame is done
+    pub monitors: RefCell<Vec<Oop>>,
 }
 
 unsafe impl Sync for DataArea {}
@@ -35,6 +37,7 @@ impl DataArea {
         Self {
             stack,
             return_v: RefCell::new(None),
+            monitors: RefCell::new(Vec::new()),
         }
     }
 }
@@ -0,0 +1,316 @@
+use crate::oop::{heap, Oop};
+use crate::runtime::{self, reference, sys_dic, vm};
+use crate::types::JavaThreadRef;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/*
+Stop-the-world for oop::heap
+
+Java threads poll the safepoint in the interpreter loop. The thread which
+requests gc waits until all the other threads are stopped at the safepoint,
+or are in a safe region (blocking natives, such as Object.wait), then collects.
+
+Blocking monitor enters, IO and the user JNI natives run in a safe region too.
+The JNI functions called back by the natives leave it for as long as they run
+(see VmEntry). If some thread still can't reach the safepoint in time, the
+stopped threads are released for a while and the collection is retried.
+*/
+
+const SAFEPOINT_TIMEOUT: Duration = Duration::from_millis(500);
+
+static GC_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref SAFEPOINT: (Mutex<()>, Condvar) = (Mutex::new(()), Condvar::new());
+}
+
+#[inline]
+pub fn safepoint() {
+    if GC_REQUESTED.load(Ordering::Relaxed) {
+        block_for_gc();
+    } else if heap::need_collect() {
+        gc();
+    }
+}
+
+/// Make room for an object of 'size' bytes, collect if it's beyond -Xmx
+///
+/// false if the live objects still leave no room, OutOfMemoryError
+pub fn reserve(size: usize) -> bool {
+    if heap::has_room(size) {
+        return true;
+    }
+
+    gc();
+    heap::has_room(size)
+}
+
+pub fn enter_safe_region() {
+    let (lock, cond) = &*SAFEPOINT;
+    let _guard = lock.lock().unwrap();
+    set_in_safe_point(true);
+    cond.notify_all();
+}
+
+pub fn leave_safe_region() {
+    let (lock, cond) = &*SAFEPOINT;
+    let mut guard = lock.lock().unwrap();
+    while GC_REQUESTED.load(Ordering::Relaxed) {
+        guard = cond.wait(guard).unwrap();
+    }
+    set_in_safe_point(false);
+}
+
+/// Leave the safe region of the native code calling back into the vm
+///
+/// Held by the JNI functions, the safe region is entered again on drop.
+/// Nothing is done for a thread out of a safe region.
+pub struct VmEntry {
+    in_safe_region: bool,
+}
+
+pub fn enter_vm() -> VmEntry {
+    let in_safe_region = is_in_safe_region();
+    if in_safe_region {
+        leave_safe_region();
+    }
+    VmEntry { in_safe_region }
+}
+
+impl Drop for VmEntry {
+    fn drop(&mut self) {
+        if self.in_safe_region {
+            enter_safe_region();
+        }
+    }
+}
+
+pub fn is_in_safe_region() -> bool {
+    let jt = runtime::thread::current_java_thread();
+    let in_safe_point = jt.read().unwrap().in_safe_point;
+    in_safe_point
+}
+
+//Runtime.gc
+pub fn gc() {
+    let start = Instant::now();
+    let pending = stop_the_world(|| {
+        let roots = collect_roots();
+        let refs = reference::prepare_gc();
+        let (n, pending) = heap::collect(&roots, refs.as_ref());
+        info!("gc: {} objects collected, {:?}", n, start.elapsed());
+        pending
+    });
+
+    if let Some(pending) = pending {
+        reference::enqueue_pending(pending);
+    }
+}
+
+//None if another thread stopped the world first, it's done when returns
+fn stop_the_world<R>(op: impl FnOnce() -> R) -> Option<R> {
+    let (lock, cond) = &*SAFEPOINT;
+    let mut guard = lock.lock().unwrap();
+    if GC_REQUESTED.swap(true, Ordering::Relaxed) {
+        //another thread is collecting
+        drop(guard);
+        block_for_gc();
+        return None;
+    }
+
+    let current = runtime::thread::current_java_thread();
+    let mut deadline = Instant::now() + SAFEPOINT_TIMEOUT;
+    while !is_world_stopped(&current) {
+        let now = Instant::now();
+        if now < deadline {
+            guard = cond.wait_timeout(guard, deadline - now).unwrap().0;
+            continue;
+        }
+
+        //the late thread may wait for a stopped one, let them run a while
+        warn!("gc: not all threads reached safepoint, retry");
+        GC_REQUESTED.store(false, Ordering::Relaxed);
+        cond.notify_all();
+        drop(guard);
+        std::thread::yield_now();
+
+        guard = lock.lock().unwrap();
+        if GC_REQUESTED.swap(true, Ordering::Relaxed) {
+            drop(guard);
+            block_for_gc();
+            return None;
+        }
+        deadline = Instant::now() + SAFEPOINT_TIMEOUT;
+    }
+
+    let r = op();
+
+    GC_REQUESTED.store(false, Ordering::Relaxed);
+    cond.notify_all();
+    drop(guard);
+
+    Some(r)
+}
+
+fn block_for_gc() {
+    let (lock, cond) = &*SAFEPOINT;
+    let mut guard = lock.lock().unwrap();
+    set_in_safe_point(true);
+    cond.notify_all();
+    while GC_REQUESTED.load(Ordering::Relaxed) {
+        guard = cond.wait(guard).unwrap();
+    }
+    set_in_safe_point(false);
+}
+
+fn set_in_safe_point(v: bool) {
+    let jt = runtime::thread::current_java_thread();
+    jt.write().unwrap().in_safe_point = v;
+}
+
+fn is_world_stopped(current: &JavaThreadRef) -> bool {
+    let threads = vm::get_vm().threads.java_threads();
+    threads
+        .iter()
+        .all(|jt| Arc::ptr_eq(jt, current) || jt.read().unwrap().in_safe_point)
+}
+
+fn collect_roots() -> Vec<Oop> {
+    let mut roots = Vec::new();
+
+    //thread objects, pending exceptions & frames
+    let threads = vm::get_vm().threads.java_threads();
+    for jt in threads.iter() {
+        let jt = jt.read().unwrap();
+        roots.extend(jt.java_thread_obj.iter().cloned());
+        roots.extend(jt.ex.iter().cloned());
+
+        //every frame, a frame is only read by its thread
+        for frame in jt.frames.iter() {
+            let frame = frame.read().unwrap();
+            //the stack being changed by the stopped thread, its values are
+            //handles, found by their counts, see oop::heap
+            if let Ok(stack) = frame.area.stack.try_borrow() {
+                roots.extend(stack.oops());
+            }
+            if let Ok(v) = frame.area.return_v.try_borrow() {
+                roots.extend(v.iter().cloned());
+            };
+        }
+    }
+
+    //mirrors & static fields
+    for cls in sys_dic::classes() {
+        let cls = cls.get_class();
+        roots.extend(cls.oops());
+    }
+
+    roots
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::oop::heap::tests::{downgrade, new_cycle, HEAP_TEST};
+    use crate::runtime::vm;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc, Once};
+    use std::thread;
+    use std::time::Duration;
+
+    //the threads attached are detached before the test ends
+    pub(crate) fn attach() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            Box::leak(vm::VM::new());
+        });
+        vm::get_vm().threads.attach_current_thread();
+    }
+
+    pub(crate) fn detach() {
+        vm::get_vm().threads.detach_current_thread();
+    }
+
+    //the thread blocked in a native doesn't stop the collection
+    #[test]
+    fn t_gc_with_safe_region() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let other = thread::spawn(move || {
+            attach();
+            super::enter_safe_region();
+            ready_tx.send(()).unwrap();
+            done_rx.recv().unwrap();
+            super::leave_safe_region();
+            detach();
+        });
+
+        ready_rx.recv().unwrap();
+        let (a, b) = new_cycle();
+        let (a, b) = (downgrade(a), downgrade(b));
+        super::gc();
+        assert!(a.upgrade().is_none() && b.upgrade().is_none());
+
+        done_tx.send(()).unwrap();
+        other.join().unwrap();
+        detach();
+    }
+
+    //the JNI functions called back by a native run out of its safe region
+    #[test]
+    fn t_enter_vm() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        super::enter_safe_region();
+        {
+            let _vm = super::enter_vm();
+            assert!(!super::is_in_safe_region());
+        }
+        assert!(super::is_in_safe_region());
+        super::leave_safe_region();
+
+        //called by the vm itself
+        {
+            let _vm = super::enter_vm();
+            assert!(!super::is_in_safe_region());
+        }
+        assert!(!super::is_in_safe_region());
+
+        detach();
+    }
+
+    //the thread reaching the safepoint late doesn't make the collection dropped
+    #[test]
+    fn t_gc_retry() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let other = {
+            let done = done.clone();
+            thread::spawn(move || {
+                attach();
+                thread::sleep(super::SAFEPOINT_TIMEOUT + Duration::from_millis(200));
+                while !done.load(Ordering::Relaxed) {
+                    super::safepoint();
+                    thread::yield_now();
+                }
+                detach();
+            })
+        };
+
+        let (a, b) = new_cycle();
+        let (a, b) = (downgrade(a), downgrade(b));
+        super::gc();
+        assert!(a.upgrade().is_none() && b.upgrade().is_none());
+
+        done.store(true, Ordering::Relaxed);
+        other.join().unwrap();
+        detach();
+    }
+}
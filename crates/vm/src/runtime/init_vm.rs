@@ -1,13 +1,15 @@
 use crate::oop;
 use crate::oop::{Class, Oop};
+use crate::runtime::thread::ThreadState;
 use crate::runtime::{self, require_class3};
 use crate::types::JavaThreadRef;
 use crate::util;
 use crate::{native, new_br};
 use classfile::consts::{
-    J_ARRAY_INDEX_OUT_OF_BOUNDS, J_CLASS, J_CLASS_NOT_FOUND, J_CLONEABLE, J_FIELD, J_INPUT_STREAM,
-    J_INTERNAL_ERROR, J_IOEXCEPTION, J_METHOD_CTOR, J_NPE, J_OBJECT, J_PRINT_STREAM,
-    J_SECURITY_MANAGER, J_SERIALIZABLE, J_STRING, J_SYSTEM, J_THREAD, J_THREAD_GROUP, J_THROWABLE,
+    J_ARRAY_INDEX_OUT_OF_BOUNDS, J_CLASS, J_CLASS_NOT_FOUND, J_CLONEABLE, J_FIELD, J_FINALIZER,
+    J_INPUT_STREAM, J_INTERNAL_ERROR, J_IOEXCEPTION, J_METHOD_CTOR, J_NPE, J_OBJECT,
+    J_PRINT_STREAM, J_SECURITY_MANAGER, J_SERIALIZABLE, J_STRING, J_SYSTEM, J_THREAD,
+    J_THREAD_GROUP, J_THROWABLE,
 };
 use std::borrow::BorrowMut;
 use std::sync::Arc;
@@ -32,9 +34,11 @@ pub fn initialize_jvm() {
     // JavaMainThread is created with java_thread_obj none
     // Now we have created a thread for it.
     let jt = runtime::thread::current_java_thread();
-    jt.write()
-        .unwrap()
-        .set_java_thread_obj(init_thread_oop.clone());
+    {
+        let mut jt = jt.write().unwrap();
+        jt.set_java_thread_obj(init_thread_oop.clone());
+        jt.set_state(ThreadState::Runnable);
+    }
 
     // Create and construct the system thread group.
     let system_thread_group = oop::Oop::new_inst(thread_group_cls.clone());
@@ -81,6 +85,9 @@ pub fn initialize_jvm() {
         args,
     );
 
+    //ReferenceHandler & FinalizerThread are started
+    let _ = oop::class::load_and_init(J_FINALIZER);
+
     hack_classes();
 
     let init_system_classes_method = {
@@ -170,9 +177,8 @@ fn hack_classes() {
 
     {
         let mut cls = system.get_mut_class();
+        //load libs directly, skip Runtime & ClassLoader.NativeLibrary
         cls.hack_as_native(b"load", b"(Ljava/lang/String;)V");
-
-        //todo: support load lib
         cls.hack_as_native(b"loadLibrary", b"(Ljava/lang/String;)V");
 
         //fixme: rm, just for debug
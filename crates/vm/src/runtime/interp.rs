@@ -1,11 +1,13 @@
 use crate::oop::{
-    self, consts as oop_consts, field, Class, ClassKind, Oop, OopPtr, TypeArrayDesc, ValueType,
+    self, consts as oop_consts, field, heap, Class, ClassKind, Oop, OopPtr, TypeArrayDesc,
+    TypeArrayEnum, ValueType,
 };
 use crate::runtime::local::Local;
+use crate::runtime::quick::{InlineCache, Quick};
 use crate::runtime::stack::Stack;
 use crate::runtime::{
-    self, cmp, exception, require_class, require_class2, require_class3, thread, DataArea, Frame,
-    JavaCall,
+    self, cmp, exception, jit, require_class, require_class2, require_class3, thread, DataArea,
+    Frame, JavaCall,
 };
 use crate::types::*;
 use crate::util;
@@ -15,7 +17,7 @@ use classfile::{
 };
 use nix::sys::socket::SockType::Datagram;
 use std::borrow::BorrowMut;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -226,6 +228,8 @@ pub struct Interp<'a> {
     cp: ConstantPool,
     code: Arc<Vec<U1>>,
     op_widen: bool,
+    //a hot loop jumped back, try OSR at its head
+    osr: Cell<bool>,
 }
 
 impl<'a> Interp<'a> {
@@ -239,6 +243,7 @@ impl<'a> Interp<'a> {
             cp,
             code,
             op_widen,
+            osr: Cell::new(false),
         }
     }
 }
@@ -263,6 +268,12 @@ impl<'a> Interp<'a> {
         let codes = self.code.clone();
 
         loop {
+            runtime::gc::safepoint();
+
+            if self.osr.take() && self.try_osr() {
+                break;
+            }
+
             let code = read_byte!(self.frame.pc, codes);
             let code = OpCode::from(code);
             match code {
@@ -743,8 +754,22 @@ impl<'a> Interp<'a> {
                 stack.push_double2(v)
             }
             ConstantPoolType::String { string_index } => {
-                let s = get_cp_utf8(&self.cp, *string_index as usize);
-                let s = util::oop::new_java_lang_string3(s.as_slice());
+                //the object patched by Unsafe.defineAnonymousClass
+                let patched = {
+                    self.frame
+                        .class
+                        .extract_inst()
+                        .cp_patches
+                        .get(&pos)
+                        .cloned()
+                };
+                let s = match patched {
+                    Some(v) => v,
+                    None => {
+                        let s = get_cp_utf8(&self.cp, *string_index as usize);
+                        util::oop::new_java_lang_string3(s.as_slice())
+                    }
+                };
 
                 let mut stack = self.frame.area.stack.borrow_mut();
                 stack.push_ref(s, false);
@@ -754,7 +779,12 @@ impl<'a> Interp<'a> {
                 let name = unsafe { std::str::from_utf8_unchecked(name.as_slice()) };
                 let cl = { self.frame.class.get_class().class_loader };
                 trace!("load_constant name={}, cl={:?}", name, cl);
-                let class = runtime::require_class3(cl, name.as_bytes()).unwrap();
+                let class = match runtime::require_class3(cl, name.as_bytes()) {
+                    Some(class) => class,
+                    //VerifyError, ClassFormatError... thrown by class loader
+                    None if thread::is_meet_ex() => return,
+                    None => unreachable!("Cannot load class {}", name),
+                };
                 oop::class::init_class(&class);
                 oop::class::init_class_fully(&class);
 
@@ -762,6 +792,29 @@ impl<'a> Interp<'a> {
                 let mut stack = self.frame.area.stack.borrow_mut();
                 stack.push_ref(mirror, false);
             }
+            ConstantPoolType::MethodHandle {
+                ref_kind,
+                ref_index,
+            } => {
+                let v = runtime::invoke_dynamic::resolve_method_handle(
+                    &self.frame.class,
+                    *ref_kind,
+                    *ref_index as usize,
+                );
+                if let Some(v) = v {
+                    let mut stack = self.frame.area.stack.borrow_mut();
+                    stack.push_ref(v, false);
+                }
+            }
+            ConstantPoolType::MethodType { desc_index } => {
+                let desc = get_cp_utf8(&self.cp, *desc_index as usize);
+                let cl = { self.frame.class.get_class().class_loader };
+                let v = runtime::invoke_dynamic::resolve_method_type(cl, desc.as_slice());
+                if let Some(v) = v {
+                    let mut stack = self.frame.area.stack.borrow_mut();
+                    stack.push_ref(v, false);
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -789,6 +842,9 @@ impl<'a> Interp<'a> {
         let high = codes[pc as usize] as i16;
         let low = codes[(pc + 1) as usize] as i16;
         let branch = (high << 8) | low;
+        if branch < 0 && jit::on_back_edge(&self.frame.mir) {
+            self.osr.set(true);
+        }
 
         self.goto_by_offset_with_occupied(branch as i32, occupied);
     }
@@ -799,14 +855,59 @@ impl<'a> Interp<'a> {
         self.goto_by_offset(-(occupied - 1));
     }
 
+    //run the rest of the method compiled, from the loop head at pc; true if it's done
+    fn try_osr(&self) -> bool {
+        if !self.frame.area.stack.borrow().is_empty() {
+            return false;
+        }
+
+        let pc = self.frame.pc.load(Ordering::Relaxed) as usize;
+        let frame = {
+            let jt = runtime::thread::current_java_thread();
+            let jt = jt.read().unwrap();
+            jt.frames.last().cloned().unwrap()
+        };
+        match jit::osr(frame, &self.frame.mir, pc, &self.local) {
+            Some(Ok(v)) => {
+                self.set_return(v);
+                true
+            }
+            //the exception is pending, the method has no handler
+            Some(Err(())) => true,
+            None => false,
+        }
+    }
+
     fn set_return(&self, v: Option<Oop>) {
         let mut return_v = self.frame.area.return_v.borrow_mut();
         *return_v = v;
     }
 
-    fn get_field_helper(&self, receiver: Oop, idx: usize, is_static: bool) {
+    //OutOfMemoryError if no room for an object of 'size' bytes, even after gc
+    fn reserve(&self, size: usize) -> bool {
+        let ok = runtime::gc::reserve(size);
+        if !ok {
+            let msg = Some("Java heap space".to_string());
+            exception::meet_ex(cls_const::J_OOM, msg);
+        }
+        ok
+    }
+
+    //the resolved field of the instruction at 'op_pc', quickened by the first execution
+    fn resolve_field(&self, op_pc: usize, idx: usize, is_static: bool) -> FieldIdRef {
+        let quick = &self.frame.mir.quick;
+        if let Some(Quick::Field(fir)) = quick.get(op_pc) {
+            return fir.clone();
+        }
+
         let class = self.frame.class.extract_inst();
         let fir = class.cp_cache.get_field(idx, is_static);
+        quick.set(op_pc, Quick::Field(fir.clone()));
+        fir
+    }
+
+    fn get_field_helper(&self, receiver: Oop, op_pc: usize, idx: usize, is_static: bool) {
+        let fir = self.resolve_field(op_pc, idx, is_static);
         debug_assert_eq!(fir.field.is_static(), is_static);
         trace!("get_field_helper={:?}, is_static={}", fir.field, is_static);
         let value_type = fir.field.value_type;
@@ -853,9 +954,8 @@ impl<'a> Interp<'a> {
         }
     }
 
-    fn put_field_helper(&self, idx: usize, is_static: bool) {
-        let class = self.frame.class.extract_inst();
-        let fir = class.cp_cache.get_field(idx, is_static);
+    fn put_field_helper(&self, op_pc: usize, idx: usize, is_static: bool) {
+        let fir = self.resolve_field(op_pc, idx, is_static);
         debug_assert_eq!(fir.field.is_static(), is_static);
         trace!("put_field_helper={:?}, is_static={}", fir.field, is_static);
         let value_type = fir.field.value_type;
@@ -876,16 +976,43 @@ impl<'a> Interp<'a> {
         }
     }
 
-    fn invoke_helper(&self, is_static: bool, idx: usize, force_no_resolve: bool) {
-        let class = self.frame.class.extract_inst();
-        let mir = class.cp_cache.get_method(idx);
+    //the virtual call sites (not force_no_resolve) select the method by the inline cache,
+    //then by the v_table or itable index
+    fn invoke_helper(&self, is_static: bool, op_pc: usize, idx: usize, force_no_resolve: bool) {
+        let quick = &self.frame.mir.quick;
+        let quick = match quick.get(op_pc) {
+            Some(v) => v,
+            None => {
+                let class = self.frame.class.extract_inst();
+                let mir = class.cp_cache.get_method(idx);
+                let index = if force_no_resolve {
+                    None
+                } else {
+                    oop::class::dispatch_index(&mir)
+                };
+                let v = match index {
+                    Some(index) => Quick::Virtual(mir, index, InlineCache::default()),
+                    None => Quick::Method(mir),
+                };
+                quick.set(op_pc, v)
+            }
+        };
+
+        let (mir, virtual_call) = match quick {
+            Quick::Method(mir) => (mir, None),
+            Quick::Virtual(mir, index, ic) => (mir, Some((index, ic))),
+            Quick::Field(_) => unreachable!(),
+        };
         let caller = match &mir.method.signature.retype {
             classfile::SignatureType::Void => None,
             _ => Some(&self.frame.area),
         };
         debug_assert_eq!(mir.method.is_static(), is_static);
-        if let Ok(mut jc) = runtime::invoke::JavaCall::new(&self.frame.area, mir) {
-            jc.invoke(caller, force_no_resolve);
+        if let Ok(mut jc) = runtime::invoke::JavaCall::new(&self.frame.area, mir.clone()) {
+            match virtual_call {
+                Some((index, ic)) => jc.invoke_cached(caller, index, ic),
+                None => jc.invoke(caller, true),
+            }
         }
     }
 
@@ -893,7 +1020,12 @@ impl<'a> Interp<'a> {
         let pc = &self.frame.pc;
         let codes = &self.code;
         let cp_idx = read_i2!(pc, codes);
-        let target_cls = require_class2(cp_idx as U2, &self.cp).unwrap();
+        let cl = { self.frame.class.get_class().class_loader };
+        let target_cls = match require_class2(cl, cp_idx as U2, &self.cp) {
+            Some(class) => class,
+            None if thread::is_meet_ex() => return,
+            None => unreachable!("Cannot get class info from constant pool"),
+        };
         let obj_rf = self.pop_value(ValueType::OBJECT);
         let obj_rf_clone = obj_rf.clone();
         let op_check_cast = |r: bool, obj_cls: ClassRef, target_cls: ClassRef| {
@@ -1244,14 +1376,13 @@ impl<'a> Interp<'a> {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_array();
-                let ary = &ary.elements;
                 let len = ary.len();
 
                 if (pos < 0) || (pos as usize >= len) {
                     let msg = format!("length is {}, but index is {}", len, pos);
                     exception::meet_ex(cls_const::J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
                 } else {
-                    let v = ary[pos as usize].clone();
+                    let v = ary.get(pos as usize);
                     stack.push_ref(v, false);
                 }
             }
@@ -1418,8 +1549,13 @@ impl<'a> Interp<'a> {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_array();
-                let ary = &mut ary.elements;
-                array_store!(ary, pos, v);
+                let len = ary.len();
+                if (pos < 0) || (pos as usize >= len) {
+                    let msg = format!("length is {}, but index is {}", len, pos);
+                    exception::meet_ex(cls_const::J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
+                } else {
+                    ary.set(pos as usize, &v);
+                }
             }
             _ => unreachable!(),
         }
@@ -2131,22 +2267,25 @@ impl<'a> Interp<'a> {
     fn get_static(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let cp_idx = read_u2!(pc, codes);
-        self.get_field_helper(Oop::Null, cp_idx, true);
+        self.get_field_helper(Oop::Null, op_pc, cp_idx, true);
     }
 
     #[inline]
     fn put_static(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let cp_idx = read_u2!(pc, codes);
-        self.put_field_helper(cp_idx, true);
+        self.put_field_helper(op_pc, cp_idx, true);
     }
 
     #[inline]
     fn get_field(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let idx = read_u2!(pc, codes);
 
         let mut stack = self.frame.area.stack.borrow_mut();
@@ -2158,7 +2297,7 @@ impl<'a> Interp<'a> {
                 exception::meet_ex(cls_const::J_NPE, None);
             }
             _ => {
-                self.get_field_helper(rf, idx, false);
+                self.get_field_helper(rf, op_pc, idx, false);
             }
         }
     }
@@ -2167,38 +2306,43 @@ impl<'a> Interp<'a> {
     fn put_field(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let idx = read_u2!(pc, codes);
-        self.put_field_helper(idx, false);
+        self.put_field_helper(op_pc, idx, false);
     }
 
     #[inline]
     fn invoke_virtual(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let idx = read_u2!(pc, codes);
-        self.invoke_helper(false, idx, false);
+        self.invoke_helper(false, op_pc, idx, false);
     }
 
     #[inline]
     fn invoke_special(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let idx = read_u2!(pc, codes);
-        self.invoke_helper(false, idx, true);
+        self.invoke_helper(false, op_pc, idx, true);
     }
 
     #[inline]
     fn invoke_static(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let idx = read_u2!(pc, codes);
-        self.invoke_helper(true, idx, true);
+        self.invoke_helper(true, op_pc, idx, true);
     }
 
     #[inline]
     fn invoke_interface(&self) {
         let pc = &self.frame.pc;
         let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) as usize - 1;
         let cp_idx = read_u2!(pc, codes);
         let _count = read_u1!(pc, codes);
         let zero = read_u1!(pc, codes);
@@ -2206,13 +2350,27 @@ impl<'a> Interp<'a> {
             warn!("interpreter: invalid invokeinterface: the value of the fourth operand byte must always be zero.");
         }
 
-        self.invoke_helper(false, cp_idx, false);
+        self.invoke_helper(false, op_pc, cp_idx, false);
     }
 
     #[inline]
     fn invoke_dynamic(&self) {
-        //todo: impl
-        unimplemented!()
+        let pc = &self.frame.pc;
+        let codes = &self.code;
+        let op_pc = pc.load(Ordering::Relaxed) - 1;
+        let cp_idx = read_u2!(pc, codes);
+        let zero = read_u2!(pc, codes);
+        if zero != 0 {
+            warn!("interpreter: invalid invokedynamic: the values of the third and fourth operand bytes must always be zero.");
+        }
+
+        runtime::invoke_dynamic::invoke(
+            &self.frame.area,
+            &self.frame.class,
+            &self.frame.mir,
+            op_pc as usize,
+            cp_idx,
+        );
     }
 
     #[inline]
@@ -2221,18 +2379,29 @@ impl<'a> Interp<'a> {
         let codes = &self.code;
         let idx = read_u2!(pc, codes);
 
+        let cl = { self.frame.class.get_class().class_loader };
         let class = {
-            match runtime::require_class2(idx as u16, &self.cp) {
+            match runtime::require_class2(cl, idx as u16, &self.cp) {
                 Some(class) => {
                     oop::class::init_class(&class);
                     oop::class::init_class_fully(&class);
 
                     class
                 }
+                //VerifyError, ClassFormatError... thrown by class loader
+                None if thread::is_meet_ex() => return,
                 None => unreachable!("Cannot get class info from constant pool"),
             }
         };
 
+        let n = match &class.get_class().kind {
+            ClassKind::Instance(cls) => cls.n_inst_fields,
+            _ => 0,
+        };
+        if !self.reserve(n * heap::FIELD_SIZE) {
+            return;
+        }
+
         let v = oop::Oop::new_inst(class);
         let mut stack = self.frame.area.stack.borrow_mut();
         stack.push_ref(v, false);
@@ -2245,12 +2414,17 @@ impl<'a> Interp<'a> {
         let ary_type = read_byte!(pc, codes);
         let mut stack = self.frame.area.stack.borrow_mut();
         let len = stack.pop_int();
+        drop(stack);
         if len < 0 {
-            drop(stack);
             exception::meet_ex(cls_const::J_NASE, Some("length < 0".to_string()));
         } else {
             let len = len as usize;
+            if !self.reserve(len * TypeArrayEnum::from(ary_type).elm_size()) {
+                return;
+            }
+
             let ary = Oop::new_type_ary(ary_type, len);
+            let mut stack = self.frame.area.stack.borrow_mut();
             stack.push_ref(ary, false);
         }
     }
@@ -2269,8 +2443,10 @@ impl<'a> Interp<'a> {
         if length < 0 {
             exception::meet_ex(cls_const::J_NASE, Some("length < 0".to_string()));
         } else {
-            let class = match runtime::require_class2(cp_idx as u16, &self.cp) {
+            let cl = { self.frame.class.get_class().class_loader };
+            let class = match runtime::require_class2(cl, cp_idx as u16, &self.cp) {
                 Some(class) => class,
+                None if thread::is_meet_ex() => return,
                 None => panic!("Cannot get class info from constant pool"),
             };
 
@@ -2310,6 +2486,9 @@ impl<'a> Interp<'a> {
                 Some(ary_cls_obj) => {
                     oop::class::init_class(&ary_cls_obj);
                     oop::class::init_class_fully(&ary_cls_obj);
+                    if !self.reserve(length as usize * heap::FIELD_SIZE) {
+                        return;
+                    }
 
                     let ary = Oop::new_ref_ary(ary_cls_obj, length as usize);
                     let mut stack = self.frame.area.stack.borrow_mut();
@@ -2335,7 +2514,7 @@ impl<'a> Interp<'a> {
                 unsafe {
                     match &(*v).v {
                         oop::RefKind::Array(ary) => {
-                            let len = ary.elements.len();
+                            let len = ary.len();
                             stack.push_int(len as i32);
                         }
                         oop::RefKind::TypeArray(ary) => {
@@ -2379,7 +2558,10 @@ impl<'a> Interp<'a> {
             Oop::Null => {
                 exception::meet_ex(cls_const::J_NPE, None);
             }
-            Oop::Ref(v) => v.monitor_enter(),
+            Oop::Ref(rf) => {
+                rf.monitor_enter();
+                self.frame.area.monitors.borrow_mut().push(Oop::Ref(rf));
+            }
             _ => unreachable!(),
         }
     }
@@ -2394,7 +2576,19 @@ impl<'a> Interp<'a> {
             Oop::Null => {
                 exception::meet_ex(cls_const::J_NPE, None);
             }
-            Oop::Ref(v) => v.monitor_exit(),
+            Oop::Ref(rf) => {
+                if rf.monitor_exit() {
+                    let mut monitors = self.frame.area.monitors.borrow_mut();
+                    let pos = monitors
+                        .iter()
+                        .rposition(|it| Arc::ptr_eq(&it.extract_ref(), &rf));
+                    if let Some(pos) = pos {
+                        monitors.remove(pos);
+                    }
+                } else {
+                    exception::meet_ex(cls_const::J_ILLEGAL_MONITOR_STATE, None);
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -2420,7 +2614,22 @@ impl<'a> Interp<'a> {
         }
         drop(stack);
 
-        let cls = require_class2(cp_idx as u16, &self.cp).unwrap();
+        let cl = { self.frame.class.get_class().class_loader };
+        let cls = match require_class2(cl, cp_idx as u16, &self.cp) {
+            Some(class) => class,
+            None if thread::is_meet_ex() => return,
+            None => unreachable!("Cannot get class info from constant pool"),
+        };
+        //the elements of all the dimensions, as references
+        let (mut n, mut size) = (1usize, 0usize);
+        for len in lens.iter() {
+            n = n.saturating_mul((*len).max(0) as usize);
+            size = size.saturating_add(n);
+        }
+        if !self.reserve(size.saturating_mul(heap::FIELD_SIZE)) {
+            return;
+        }
+
         let ary = new_multi_object_array_helper(cls, &lens, 0);
 
         let mut stack = self.frame.area.stack.borrow_mut();
@@ -1,8 +1,10 @@
 use crate::native;
 use crate::native::JNINativeMethodStruct;
+use crate::oop::class::{DispatchIndex, VTableEntry};
 use crate::oop::{self, Oop, ValueType};
 use crate::runtime::local::Local;
-use crate::runtime::{self, exception, frame::Frame, thread, DataArea, Interp};
+use crate::runtime::quick::InlineCache;
+use crate::runtime::{self, exception, frame::Frame, jit, thread, DataArea, Interp};
 use crate::types::{ClassRef, FrameRef, JavaThreadRef, MethodIdRef};
 use crate::util;
 use class_parser::MethodSignature;
@@ -84,9 +86,20 @@ impl JavaCall {
 
             public native boolean checkAccess(File f, int access);
         */
-        self.resolve_virtual_method(force_no_resolve);
+        let selected = self
+            .resolve_virtual_method(force_no_resolve)
+            .and_then(|_| self.check_abstract());
+        if let Err(ex) = selected {
+            let jt = runtime::thread::current_java_thread();
+            jt.write().unwrap().set_ex(ex);
+            return;
+        }
         self.debug();
 
+        if !self.mir.method.is_native() && self.invoke_compiled(caller) {
+            return;
+        }
+
         if self.mir.method.is_native() {
             self.invoke_native(caller);
         } else {
@@ -96,9 +109,60 @@ impl JavaCall {
         let jt = runtime::thread::current_java_thread();
         let _ = jt.write().unwrap().frames.pop();
     }
+
+    //invoke at a virtual call site, the method selected for the receiver class is cached
+    pub fn invoke_cached(
+        &mut self,
+        caller: Option<&DataArea>,
+        index: &DispatchIndex,
+        ic: &InlineCache,
+    ) {
+        let hit = receiver_class(&self.args).and_then(|cls| ic.lookup(&cls));
+        match hit {
+            Some(mir) => self.mir = mir,
+            None => match self.select_method(index) {
+                Ok(Some(cls)) => ic.update(cls, self.mir.clone()),
+                Ok(None) => (),
+                Err(ex) => {
+                    let jt = runtime::thread::current_java_thread();
+                    jt.write().unwrap().set_ex(ex);
+                    return;
+                }
+            },
+        }
+
+        self.invoke(caller, true);
+    }
 }
 
 impl JavaCall {
+    //the miranda method or an abstract method reached by invokespecial
+    fn check_abstract(&self) -> Result<(), Oop> {
+        if self.mir.method.is_abstract() {
+            Err(exception::new(
+                cls_const::J_ABSTRACT_METHOD_ERROR,
+                Some(method_name(&self.mir)),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    //the frame of the compiled code is pushed by jit when needed
+    fn invoke_compiled(&mut self, caller: Option<&DataArea>) -> bool {
+        match jit::invoke(&self.mir, &self.args) {
+            Some(Ok(v)) => {
+                if let Some(v) = v {
+                    set_return(caller.unwrap(), &self.mir.method.signature.retype, v);
+                }
+                true
+            }
+            //the exception is pending
+            Some(Err(())) => true,
+            None => false,
+        }
+    }
+
     fn invoke_java(&mut self, caller: Option<&DataArea>) {
         self.prepare_sync();
 
@@ -113,6 +177,11 @@ impl JavaCall {
                 let frame_h = frame.try_read().unwrap();
                 let mut interp = Interp::new(frame_h, local);
                 interp.run();
+                release_monitors(&frame);
+
+                if self.is_object_init() && !thread::is_meet_ex() {
+                    runtime::reference::register_finalizer(&self.args[0]);
+                }
 
                 //if return void, not need set return value
                 if !self.is_return_void && !thread::is_meet_ex() {
@@ -145,22 +214,31 @@ impl JavaCall {
                 {
                     jt.write().unwrap().frames.push(frame);
                 }
-                match &self.mir.native_impl {
+                let native_impl = self
+                    .mir
+                    .native_impl
+                    .clone()
+                    .or_else(|| native::jni::find_native(&self.mir.method));
+                match native_impl {
                     Some(method) => {
                         let class = self.mir.method.class.clone();
                         let env = native::new_jni_env(class);
                         method.invoke(env, &self.args)
                     }
                     None => {
-                        let package = self.mir.method.class.get_class().name.as_slice();
+                        let cls_name = self.mir.method.cls_name.as_slice();
                         let desc = self.mir.method.desc.as_slice();
                         let name = self.mir.method.name.as_slice();
-                        panic!(
-                            "Native method not found: {}:{}:{}",
-                            unsafe { std::str::from_utf8_unchecked(package) },
-                            unsafe { std::str::from_utf8_unchecked(name) },
-                            unsafe { std::str::from_utf8_unchecked(desc) },
-                        )
+                        let msg = format!(
+                            "{}.{}{}",
+                            String::from_utf8_lossy(cls_name).replace("/", "."),
+                            String::from_utf8_lossy(name),
+                            String::from_utf8_lossy(desc),
+                        );
+                        Err(exception::new(
+                            cls_const::J_UNSATISFIED_LINK_ERROR,
+                            Some(msg),
+                        ))
                     }
                 }
             }
@@ -181,36 +259,38 @@ impl JavaCall {
         self.fin_sync();
     }
 
+    fn is_object_init(&self) -> bool {
+        let method = &self.mir.method;
+        method.name.as_slice() == b"<init>" && method.class.get_class().super_class.is_none()
+    }
+
     fn prepare_sync(&mut self) {
         if self.mir.method.is_synchronized() {
-            if self.mir.method.is_static() {
-                let class = self.mir.method.class.get_class();
-                class.monitor_enter();
-            } else {
-                let v = self.args.first().unwrap();
-                let v = v.extract_ref();
-                v.monitor_enter();
-            }
+            self.sync_obj().monitor_enter();
         }
     }
 
     fn fin_sync(&mut self) {
         if self.mir.method.is_synchronized() {
-            if self.mir.method.is_static() {
-                let class = self.mir.method.class.get_class();
-                class.monitor_exit();
-            } else {
-                let v = self.args.first().unwrap();
-                let v = v.extract_ref();
-                v.monitor_exit();
-            }
+            self.sync_obj().monitor_exit();
+        }
+    }
+
+    //the mirror for the static method, same as synchronized (X.class)
+    fn sync_obj(&self) -> Arc<oop::OopPtr> {
+        if self.mir.method.is_static() {
+            let class = self.mir.method.class.get_class();
+            class.get_mirror().extract_ref()
+        } else {
+            let v = self.args.first().unwrap();
+            v.extract_ref()
         }
     }
 
     fn prepare_frame(&mut self) -> Result<FrameRef, Oop> {
         let jt = runtime::thread::current_java_thread();
         let frame_len = { jt.read().unwrap().frames.len() };
-        if frame_len >= runtime::consts::THREAD_MAX_STACK_FRAMES {
+        if frame_len >= runtime::arguments::max_stack_frames() {
             let ex = exception::new(cls_const::J_SOE, None);
             return Err(ex);
         }
@@ -256,53 +336,52 @@ impl JavaCall {
         local
     }
 
-    fn resolve_virtual_method(&mut self, force_no_resolve: bool) {
-        let resolve_again = if force_no_resolve {
-            false
-        } else {
-            //todo: why is the value of 0 possible in acc_flags?
-            /*
-            This situation occurs when:
-            java/util/regex/Matcher.java
-            bool search(int from)
-              boolean result = parentPattern.root.match(this, from, text);
-
-            The acc_flags of the match method is 0, and what is found is java/util/regex/Patter$Node#match，
-            Correct should use java/util/regex/Patter$Start#match
-            */
-            self.mir.method.is_abstract()
-                || (self.mir.method.is_public() && !self.mir.method.is_final())
-                || (self.mir.method.is_protected() && !self.mir.method.is_final())
-                || (self.mir.method.acc_flags == 0)
+    //select the method by the class of the receiver, with the v_table or itable
+    fn resolve_virtual_method(&mut self, force_no_resolve: bool) -> Result<(), Oop> {
+        if force_no_resolve {
+            return Ok(());
+        }
+
+        match oop::class::dispatch_index(&self.mir) {
+            Some(index) => self.select_method(&index).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    //the receiver class, none if the method is not selected for it
+    fn select_method(&mut self, index: &DispatchIndex) -> Result<Option<ClassRef>, Oop> {
+        let cls = match receiver_class(&self.args) {
+            Some(cls) => cls,
+            None => return Ok(None),
         };
-        trace!(
-            "resolve_virtual_method resolve_again={}, acc_flags = {}",
-            resolve_again,
-            self.mir.method.acc_flags
-        );
-        if resolve_again {
-            let this = self.args.get(0).unwrap();
-            let rf = this.extract_ref();
-            let ptr = rf.get_raw_ptr();
-            unsafe {
-                if let oop::RefKind::Inst(inst) = &(*ptr).v {
-                    let name = self.mir.method.name.clone();
-                    let desc = self.mir.method.desc.clone();
-                    let cls = inst.class.get_class();
-                    match cls.get_virtual_method(&name, &desc) {
-                        Ok(mir) => self.mir = mir,
-                        _ => {
-                            let cls = self.mir.method.class.get_class();
-                            warn!(
-                                "resolve again failed, {}:{}:{}, acc_flags = {}",
-                                String::from_utf8_lossy(cls.name.as_slice()),
-                                String::from_utf8_lossy(name.as_slice()),
-                                String::from_utf8_lossy(desc.as_slice()),
-                                self.mir.method.acc_flags
-                            );
-                        }
-                    }
-                }
+
+        let entry = cls.get_class().select_method(index).cloned();
+        match entry {
+            Some(VTableEntry::Method(mir)) => {
+                self.mir = mir;
+                Ok(Some(cls))
+            }
+            Some(VTableEntry::Conflict(m1, m2)) => {
+                let msg = format!(
+                    "Conflicting default methods: {} {}",
+                    method_name(&m1),
+                    method_name(&m2)
+                );
+                Err(exception::new(
+                    cls_const::J_INCOMPATIBLE_CLASS_CHANGE_ERROR,
+                    Some(msg),
+                ))
+            }
+            None => {
+                let msg = format!(
+                    "Class {} does not implement the requested interface {}",
+                    external_name(&cls),
+                    external_name(&self.mir.method.class)
+                );
+                Err(exception::new(
+                    cls_const::J_INCOMPATIBLE_CLASS_CHANGE_ERROR,
+                    Some(msg),
+                ))
             }
         }
     }
@@ -318,7 +397,35 @@ impl JavaCall {
     }
 }
 
-fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec<Oop> {
+//java.lang.Class instances are mirrors, the arrays use the methods of Object
+fn receiver_class(args: &[Oop]) -> Option<ClassRef> {
+    match args.first() {
+        Some(Oop::Ref(rf)) => unsafe {
+            match &(*rf.get_raw_ptr()).v {
+                oop::RefKind::Inst(inst) => Some(inst.class.clone()),
+                oop::RefKind::Mirror(_) => runtime::require_class3(None, cls_const::J_CLASS),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+fn external_name(cls: &ClassRef) -> String {
+    let cls = cls.get_class();
+    String::from_utf8_lossy(cls.name.as_slice()).replace("/", ".")
+}
+
+fn method_name(mir: &MethodIdRef) -> String {
+    format!(
+        "{}.{}{}",
+        external_name(&mir.method.class),
+        String::from_utf8_lossy(mir.method.name.as_slice()),
+        String::from_utf8_lossy(mir.method.desc.as_slice())
+    )
+}
+
+pub fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec<Oop> {
     let mut caller = caller.stack.borrow_mut();
     let mut args = Vec::with_capacity(sig.args.len() + 1);
 
@@ -366,3 +473,30 @@ pub fn set_return(caller: &DataArea, return_type: &SignatureType, v: Oop) {
     let mut stack = caller.stack.borrow_mut();
     stack.push_ref(v, with_nop);
 }
+
+/*
+The monitors entered by monitorenter and still held when the frame is done,
+released as the exception unwinding. If it returns normally, the structured
+locking is violated, IllegalMonitorStateException is thrown.
+*/
+fn release_monitors(frame: &FrameRef) {
+    let monitors = {
+        let frame = frame.try_read().unwrap();
+        let mut monitors = frame.area.monitors.borrow_mut();
+        std::mem::take(&mut *monitors)
+    };
+
+    if monitors.is_empty() {
+        return;
+    }
+
+    for v in monitors.iter().rev() {
+        v.extract_ref().monitor_exit();
+    }
+
+    if !thread::is_meet_ex() {
+        let ex = exception::new(cls_const::J_ILLEGAL_MONITOR_STATE, None);
+        let jt = runtime::thread::current_java_thread();
+        jt.write().unwrap().set_ex(ex);
+    }
+}
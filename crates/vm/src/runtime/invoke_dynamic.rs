@@ -0,0 +1,449 @@
+use crate::native::common::reflect::create_value_type2;
+use crate::oop::{self, Oop};
+use crate::runtime::invoke::{self, JavaCall};
+use crate::runtime::{
+    self, exception, method_handle, require_class2, require_class3, thread, ClassLoader, DataArea,
+};
+use crate::types::{ClassRef, MethodIdRef};
+use crate::{new_br, util};
+use class_parser::{FieldSignature, MethodSignature};
+use classfile::{
+    constant_pool, consts as cls_const, ConstantPool, ConstantPoolType, SignatureType,
+};
+
+/*
+JVM spec, 5.4.3.6 Dynamically-Computed Call Site Specifier Resolution
+
+The linkage of a call site is delegated to the java side, just like hotspot:
+  MethodHandle & MethodType constants, by MethodHandleNatives.linkMethodHandleConstant
+  and MethodType.methodType
+  the call site, by MethodHandleNatives.linkCallSite, which runs the bootstrap
+  method by CallSite.makeSite (any error is wrapped into BootstrapMethodError),
+  and returns an invoker with its appendix: the target of a ConstantCallSite,
+  or the CallSite itself, whose target is read by the invoker at every call
+The java side needs the natives of MethodHandleNatives (init, resolve...),
+see native/java_lang_invoke_MethodHandleNatives.rs.
+
+A call site is linked once, the invoker & the appendix, or the error thrown,
+are cached by the ConstantPoolCache, the error is rethrown by the later
+executions. An execution calls the invoker with the arguments on the stack of
+the caller, as typed by the descriptor of the call site, and the appendix.
+*/
+
+const REF_GET_FIELD: u8 = 1;
+const REF_GET_STATIC: u8 = 2;
+const REF_PUT_FIELD: u8 = 3;
+const REF_PUT_STATIC: u8 = 4;
+
+//invokedynamic
+pub fn invoke(caller: &DataArea, class: &ClassRef, mir: &MethodIdRef, pc: usize, cp_idx: usize) {
+    let cls_obj = class.extract_inst();
+    let linked = match cls_obj.cp_cache.get_call_site(mir.offset, pc) {
+        Some(linked) => linked,
+        None => {
+            let linked = link_call_site(class, cp_idx).ok_or_else(|| {
+                let jt = runtime::thread::current_java_thread();
+                let ex = jt.write().unwrap().take_ex();
+                ex.unwrap_or_else(|| exception::new(cls_const::J_BOOTSTRAP_METHOD_ERROR, None))
+            });
+            cls_obj.cp_cache.cache_call_site(mir.offset, pc, linked)
+        }
+    };
+    let (invoker, appendix) = match linked {
+        Ok(linked) => linked,
+        Err(ex) => {
+            let jt = runtime::thread::current_java_thread();
+            jt.write().unwrap().set_ex(ex);
+            return;
+        }
+    };
+
+    //checked by link_call_site
+    let desc = {
+        let cp = &cls_obj.class_file.cp;
+        let nat_idx = match get_invoke_dynamic(cp, cp_idx) {
+            Ok((_, nat_idx)) => nat_idx,
+            Err(msg) => {
+                exception::meet_ex(cls_const::J_CLASS_FORMAT_ERROR, Some(msg));
+                return;
+            }
+        };
+        let (_, desc) = constant_pool::get_name_and_type(cp, nat_idx as usize);
+        desc.clone()
+    };
+    let signature = MethodSignature::new(desc.as_slice());
+    let mut args = invoke::build_args_from_caller_stack(caller, &signature);
+    args.push(appendix);
+
+    //the invoker takes the basic types, int for the subword ones & Object for references
+    if let Some(v) = call(invoker, args, true) {
+        if signature.retype != SignatureType::Void {
+            invoke::set_return(caller, &signature.retype, v);
+        }
+    }
+}
+
+//the classes are loaded by 'class_loader', the loader of the caller
+pub fn resolve_method_type(class_loader: Option<ClassLoader>, desc: &[u8]) -> Option<Oop> {
+    let signature = MethodSignature::new(desc);
+    let rtype = create_value_type2(signature.retype, class_loader);
+    let ptypes: Vec<Oop> = signature
+        .args
+        .iter()
+        .map(|t| create_value_type2(t.clone(), class_loader))
+        .collect();
+    let ary_cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
+    let ptypes = Oop::new_ref_ary2(ary_cls, ptypes);
+
+    call_static(
+        cls_const::J_METHODTYPE,
+        "methodType",
+        "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        vec![rtype, ptypes],
+    )
+}
+
+pub fn resolve_method_handle(caller: &ClassRef, ref_kind: u8, ref_idx: usize) -> Option<Oop> {
+    let cp = caller.extract_inst().class_file.cp.clone();
+    let is_field = matches!(
+        ref_kind,
+        REF_GET_FIELD | REF_GET_STATIC | REF_PUT_FIELD | REF_PUT_STATIC
+    );
+    let (class_idx, nat_idx) = if is_field {
+        constant_pool::get_field_ref(&cp, ref_idx)
+    } else {
+        let (_, class_idx, nat_idx) = constant_pool::get_method_ref(&cp, ref_idx);
+        (class_idx, nat_idx)
+    };
+    let (name, desc) = constant_pool::get_name_and_type(&cp, nat_idx as usize);
+
+    let cl = { caller.get_class().class_loader };
+    let defc = match require_class2(cl, class_idx, &cp) {
+        Some(defc) => defc,
+        None => {
+            let name = constant_pool::get_class_name(&cp, class_idx as usize);
+            let name = String::from_utf8_lossy(name.as_slice()).replace("/", ".");
+            exception::meet_ex(cls_const::J_CLASS_NOT_FOUND, Some(name));
+            return None;
+        }
+    };
+    oop::class::init_class(&defc);
+    let defc = defc.get_class().get_mirror();
+
+    let typ = if is_field {
+        let sig = FieldSignature::new(desc.as_slice());
+        create_value_type2(sig.field_type, cl)
+    } else {
+        resolve_method_type(cl, desc.as_slice())?
+    };
+
+    let caller = caller.get_class().get_mirror();
+    let name = util::oop::new_java_lang_string3(name.as_slice());
+    call_static(
+        b"java/lang/invoke/MethodHandleNatives",
+        "linkMethodHandleConstant",
+        "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+        vec![caller, Oop::new_int(ref_kind as i32), defc, name, typ],
+    )
+}
+
+//the invoker & the appendix, None with the exception pending
+fn link_call_site(caller: &ClassRef, cp_idx: usize) -> Option<(MethodIdRef, Oop)> {
+    let cls_obj = caller.extract_inst();
+    let cp = cls_obj.class_file.cp.clone();
+    let (bsm_idx, nat_idx) = match get_invoke_dynamic(&cp, cp_idx) {
+        Ok(v) => v,
+        Err(msg) => {
+            exception::meet_ex(cls_const::J_CLASS_FORMAT_ERROR, Some(msg));
+            return None;
+        }
+    };
+    let (name, desc) = constant_pool::get_name_and_type(&cp, nat_idx as usize);
+
+    let bsm = match &cls_obj.bootstrap_methods {
+        Some(methods) => methods.get(bsm_idx as usize).cloned(),
+        None => None,
+    };
+    let bsm = match bsm {
+        Some(bsm) => bsm,
+        None => {
+            let msg = format!("Missing BootstrapMethods entry #{}", bsm_idx);
+            exception::meet_ex(cls_const::J_CLASS_FORMAT_ERROR, Some(msg));
+            return None;
+        }
+    };
+
+    let bsm_handle = match get_method_handle(&cp, bsm.method_ref as usize) {
+        Ok((ref_kind, ref_index)) => resolve_method_handle(caller, ref_kind, ref_index as usize)?,
+        Err(msg) => {
+            exception::meet_ex(cls_const::J_CLASS_FORMAT_ERROR, Some(msg));
+            return None;
+        }
+    };
+
+    let mut static_args = Vec::with_capacity(bsm.args.len());
+    for idx in bsm.args.iter() {
+        static_args.push(resolve_static_arg(caller, &cp, *idx as usize)?);
+    }
+    let info = if static_args.is_empty() {
+        Oop::Null
+    } else {
+        let ary_cls = require_class3(None, b"[Ljava/lang/Object;").unwrap();
+        Oop::new_ref_ary2(ary_cls, static_args)
+    };
+
+    let name = util::oop::new_java_lang_string3(name.as_slice());
+    let cl = { caller.get_class().class_loader };
+    let typ = resolve_method_type(cl, desc.as_slice())?;
+    let caller = caller.get_class().get_mirror();
+    let ary_cls = require_class3(None, b"[Ljava/lang/Object;").unwrap();
+    let appendix = Oop::new_ref_ary(ary_cls, 1);
+
+    let invoker = call_static(
+        b"java/lang/invoke/MethodHandleNatives",
+        "linkCallSite",
+        "(Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+        vec![caller, bsm_handle, name, typ, info, appendix.clone()],
+    )?;
+    let invoker = match method_handle::member_method(&invoker, cl) {
+        Ok(invoker) => invoker,
+        Err(ex) => {
+            let jt = runtime::thread::current_java_thread();
+            jt.write().unwrap().set_ex(ex);
+            return None;
+        }
+    };
+    let appendix = {
+        let rf = appendix.extract_ref();
+        let ary = rf.extract_array();
+        ary.get(0)
+    };
+
+    Some((invoker, appendix))
+}
+
+fn resolve_static_arg(caller: &ClassRef, cp: &ConstantPool, idx: usize) -> Option<Oop> {
+    let t = match cp.get(idx) {
+        Some(t) => t,
+        None => {
+            let msg = format!("Invalid constant pool index {} in BootstrapMethods", idx);
+            exception::meet_ex(cls_const::J_CLASS_FORMAT_ERROR, Some(msg));
+            return None;
+        }
+    };
+
+    match t {
+        ConstantPoolType::Integer { v } => {
+            let v = Oop::new_int(i32::from_be_bytes(*v));
+            Some(box_value(v, &SignatureType::Int))
+        }
+        ConstantPoolType::Float { v } => {
+            let v = Oop::new_float(f32::from_be_bytes(*v));
+            Some(box_value(v, &SignatureType::Float))
+        }
+        ConstantPoolType::Long { v } => {
+            let v = Oop::new_long(i64::from_be_bytes(*v));
+            Some(box_value(v, &SignatureType::Long))
+        }
+        ConstantPoolType::Double { v } => {
+            let v = Oop::new_double(f64::from_be_bytes(*v));
+            Some(box_value(v, &SignatureType::Double))
+        }
+        ConstantPoolType::String { string_index } => {
+            let s = constant_pool::get_utf8(cp, *string_index as usize);
+            Some(util::oop::new_java_lang_string3(s.as_slice()))
+        }
+        ConstantPoolType::Class { .. } => {
+            let cl = { caller.get_class().class_loader };
+            match require_class2(cl, idx as u16, cp) {
+                Some(cls) => Some(cls.get_class().get_mirror()),
+                None => {
+                    let name = constant_pool::get_class_name(cp, idx);
+                    let name = String::from_utf8_lossy(name.as_slice()).replace("/", ".");
+                    exception::meet_ex(cls_const::J_CLASS_NOT_FOUND, Some(name));
+                    None
+                }
+            }
+        }
+        ConstantPoolType::MethodHandle {
+            ref_kind,
+            ref_index,
+        } => resolve_method_handle(caller, *ref_kind, *ref_index as usize),
+        ConstantPoolType::MethodType { desc_index } => {
+            let desc = constant_pool::get_utf8(cp, *desc_index as usize);
+            let cl = { caller.get_class().class_loader };
+            resolve_method_type(cl, desc.as_slice())
+        }
+        //not loadable, or a dynamically-computed constant, unsupported
+        t => {
+            let msg = format!("Invalid bootstrap method argument #{}: {:?}", idx, t);
+            exception::meet_ex(cls_const::J_BOOTSTRAP_METHOD_ERROR, Some(msg));
+            None
+        }
+    }
+}
+
+//the bootstrap method index & name_and_type of an InvokeDynamic entry
+fn get_invoke_dynamic(cp: &ConstantPool, idx: usize) -> Result<(u16, u16), String> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }) => Ok((*bootstrap_method_attr_index, *name_and_type_index)),
+        _ => Err(format!("Invalid InvokeDynamic constant #{}", idx)),
+    }
+}
+
+//the ref_kind & ref_index of a MethodHandle entry, the bootstrap method
+fn get_method_handle(cp: &ConstantPool, idx: usize) -> Result<(u8, u16), String> {
+    match cp.get(idx) {
+        Some(ConstantPoolType::MethodHandle {
+            ref_kind,
+            ref_index,
+        }) => Ok((*ref_kind, *ref_index)),
+        _ => Err(format!("Invalid bootstrap method handle #{}", idx)),
+    }
+}
+
+fn box_value(v: Oop, t: &SignatureType) -> Oop {
+    let (cls, desc) = match t {
+        SignatureType::Boolean => ("java/lang/Boolean", "(Z)Ljava/lang/Boolean;"),
+        SignatureType::Byte => ("java/lang/Byte", "(B)Ljava/lang/Byte;"),
+        SignatureType::Char => ("java/lang/Character", "(C)Ljava/lang/Character;"),
+        SignatureType::Short => ("java/lang/Short", "(S)Ljava/lang/Short;"),
+        SignatureType::Int => ("java/lang/Integer", "(I)Ljava/lang/Integer;"),
+        SignatureType::Long => ("java/lang/Long", "(J)Ljava/lang/Long;"),
+        SignatureType::Float => ("java/lang/Float", "(F)Ljava/lang/Float;"),
+        SignatureType::Double => ("java/lang/Double", "(D)Ljava/lang/Double;"),
+        _ => return v,
+    };
+
+    call_static(cls.as_bytes(), "valueOf", desc, vec![v]).unwrap_or(Oop::Null)
+}
+
+pub fn call_static(cls: &[u8], name: &str, desc: &str, args: Vec<Oop>) -> Option<Oop> {
+    let cls = require_class3(None, cls).unwrap();
+    oop::class::init_class(&cls);
+    oop::class::init_class_fully(&cls);
+
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br(name), &new_br(desc)).unwrap()
+    };
+
+    call(mir, args, true)
+}
+
+//None with the exception pending
+pub fn call(mir: MethodIdRef, args: Vec<Oop>, force_no_resolve: bool) -> Option<Oop> {
+    let mut jc = JavaCall::new_with_args(mir, args);
+    let area = DataArea::new(1);
+    jc.invoke(Some(&area), force_no_resolve);
+
+    if thread::is_meet_ex() {
+        return None;
+    }
+
+    let mut stack = area.stack.borrow_mut();
+    let v = match jc.mir.method.signature.retype {
+        SignatureType::Byte
+        | SignatureType::Char
+        | SignatureType::Boolean
+        | SignatureType::Short
+        | SignatureType::Int => Oop::new_int(stack.pop_int()),
+        SignatureType::Long => Oop::new_long(stack.pop_long()),
+        SignatureType::Float => Oop::new_float(stack.pop_float()),
+        SignatureType::Double => Oop::new_double(stack.pop_double()),
+        SignatureType::Object(_, _, _) | SignatureType::Array(_) => stack.pop_ref(),
+        SignatureType::Void => Oop::Null,
+    };
+
+    Some(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_invoke_dynamic, get_method_handle};
+    use crate::oop::{class::ClassPtr, Class, Oop};
+    use crate::runtime::method::{Method, MethodId};
+    use crate::runtime::ConstantPoolCache;
+    use classfile::flags::{ACC_PUBLIC, ACC_STATIC};
+    use classfile::{constant_pool, ClassBuilder, Version};
+    use std::sync::Arc;
+    use std::thread;
+
+    //the first linkage of a call site wins, an error is kept as well
+    #[test]
+    fn t_call_site_cache() {
+        let version = Version {
+            minor: 0,
+            major: 52,
+        };
+        let mut cb = ClassBuilder::new(version, ACC_PUBLIC, "t/Indy", Some("java/lang/Object"));
+        cb.method(
+            ACC_STATIC,
+            "invoker",
+            "(Ljava/lang/Object;)Ljava/lang/Object;",
+        );
+        let cf = Arc::new(Box::new(cb.build().unwrap()));
+        let cache = Arc::new(ConstantPoolCache::new(cf.cp.clone(), None));
+        let name = constant_pool::get_class_name(&cf.cp, cf.this_class as usize).clone();
+        let class = ClassPtr::new(Class::new_class(cf.clone(), None));
+        let method = Method::new(&cf.cp, &cf.methods[0], class, cf.clone(), 0, name);
+        let invoker = MethodId::new(0, method);
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let cache = cache.clone();
+                let invoker = invoker.clone();
+                thread::spawn(move || cache.cache_call_site(0, 1, Ok((invoker, Oop::new_int(i)))))
+            })
+            .collect();
+        let linked: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        let first = match cache.get_call_site(0, 1) {
+            Some(Ok((mir, Oop::Int(v)))) if Arc::ptr_eq(&mir, &invoker) => v,
+            _ => panic!("call site not linked"),
+        };
+        assert!(linked
+            .iter()
+            .all(|it| matches!(it, Ok((_, Oop::Int(v))) if *v == first)));
+
+        assert!(matches!(
+            cache.cache_call_site(0, 5, Err(Oop::new_int(-1))),
+            Err(Oop::Int(-1))
+        ));
+        assert!(matches!(
+            cache.cache_call_site(0, 5, Ok((invoker, Oop::new_int(7)))),
+            Err(Oop::Int(-1))
+        ));
+        assert!(matches!(cache.get_call_site(0, 5), Some(Err(Oop::Int(-1)))));
+        assert!(cache.get_call_site(1, 5).is_none());
+        assert_eq!(cache.oops().len(), 2);
+    }
+
+    #[test]
+    fn t_malformed_entries() {
+        let version = Version {
+            minor: 0,
+            major: 52,
+        };
+        let mut cb = ClassBuilder::new(version, ACC_PUBLIC, "t/Indy", Some("java/lang/Object"));
+        let cp = cb.cp();
+        let bsm = cp.method_ref("t/Indy", "bsm", "()Ljava/lang/invoke/CallSite;");
+        let handle = cp.method_handle(6, bsm);
+        let indy = cp.invoke_dynamic(0, "run", "()V");
+        let utf8 = cp.utf8("run");
+        let cf = cb.build().unwrap();
+        let cp = &cf.cp;
+        let n = cp.len();
+
+        assert!(get_invoke_dynamic(cp, indy as usize).is_ok());
+        assert!(get_invoke_dynamic(cp, utf8 as usize).is_err());
+        assert!(get_invoke_dynamic(cp, 0).is_err());
+        assert!(get_invoke_dynamic(cp, n).is_err());
+
+        assert_eq!(get_method_handle(cp, handle as usize), Ok((6, bsm)));
+        assert!(get_method_handle(cp, bsm as usize).is_err());
+        assert!(get_method_handle(cp, n).is_err());
+    }
+}
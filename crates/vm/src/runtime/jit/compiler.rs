@@ -0,0 +1,1183 @@
+use crate::runtime::gc;
+use crate::runtime::method::MethodId;
+use class_parser::MethodSignature;
+use classfile::{constant_pool, ConstantPool, ConstantPoolType, OpCode, SignatureType};
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::condcodes::IntCC;
+use cranelift_codegen::ir::{
+    types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, StackSlot, StackSlotData,
+    StackSlotKind, Type, Value,
+};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+//args: the java arguments, each takes 8 bytes; ret: the return value, or the
+//pc of the instruction throwing
+pub type CompiledFn = unsafe extern "C" fn(args: *const i64, ret: *mut i64) -> i32;
+
+//the runtime called by the compiled code, 'caller' is the MethodId compiled
+pub type InvokeStaticFn =
+    extern "C" fn(caller: *const MethodId, op_pc: i32, args: *const i64, ret: *mut i64) -> i32;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Long,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Status {
+    Ok,
+    //java.lang.ArithmeticException: / by zero
+    DivByZero,
+    //thrown by the method called, the exception is pending
+    Pending,
+}
+
+impl From<i32> for Status {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => Status::Ok,
+            1 => Status::DivByZero,
+            2 => Status::Pending,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Ty {
+    fn ir(self) -> Type {
+        match self {
+            Ty::Int => types::I32,
+            Ty::Long => types::I64,
+        }
+    }
+}
+
+//what is compiled
+pub struct Bytecode<'a> {
+    //the address of the MethodId, passed to the runtime
+    pub id: usize,
+    pub code: &'a [u8],
+    pub cp: &'a ConstantPool,
+    pub max_locals: usize,
+    pub params: Vec<Ty>,
+    pub ret: Option<Ty>,
+    //the loop head entered by OSR, the args are the locals by slot then
+    pub osr: Option<usize>,
+}
+
+impl<'a> Bytecode<'a> {
+    pub fn new(mir: &'a MethodId) -> Result<Self, String> {
+        let method = &mir.method;
+        let code = match &method.code {
+            Some(code) if code.exceptions.is_empty() => code,
+            Some(_) => return Err("exception handlers".to_string()),
+            None => return Err("no code".to_string()),
+        };
+
+        let mut params = Vec::new();
+        for t in method.signature.args.iter() {
+            params.push(to_ty(t).ok_or("param type")?);
+        }
+        let ret = match &method.signature.retype {
+            SignatureType::Void => None,
+            t => Some(to_ty(t).ok_or("return type")?),
+        };
+
+        Ok(Self {
+            id: mir as *const MethodId as usize,
+            code: code.code.as_slice(),
+            cp: &method.class_file.cp,
+            max_locals: code.max_locals as usize,
+            params,
+            ret,
+            osr: None,
+        })
+    }
+}
+
+pub fn to_ty(t: &SignatureType) -> Option<Ty> {
+    match t {
+        SignatureType::Int
+        | SignatureType::Boolean
+        | SignatureType::Byte
+        | SignatureType::Char
+        | SignatureType::Short => Some(Ty::Int),
+        SignatureType::Long => Some(Ty::Long),
+        _ => None,
+    }
+}
+
+//the compiled code lives as long as the vm, it's never freed
+pub struct CodeCache {
+    module: JITModule,
+    ctx: Context,
+    builder_ctx: FunctionBuilderContext,
+    safepoint: FuncId,
+    invoke_static: FuncId,
+}
+
+extern "C" fn jit_safepoint() {
+    gc::safepoint();
+}
+
+impl CodeCache {
+    pub fn new(invoke_static: InvokeStaticFn) -> Self {
+        let mut flags = settings::builder();
+        flags.set("use_colocated_libcalls", "false").unwrap();
+        flags.set("is_pic", "false").unwrap();
+        flags.set("opt_level", "speed").unwrap();
+        let isa = cranelift_native::builder()
+            .unwrap()
+            .finish(settings::Flags::new(flags))
+            .unwrap();
+
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        builder.symbol("jit_safepoint", jit_safepoint as *const u8);
+        builder.symbol("jit_invoke_static", invoke_static as *const u8);
+        let mut module = JITModule::new(builder);
+
+        let sig = module.make_signature();
+        let safepoint = module
+            .declare_function("jit_safepoint", Linkage::Import, &sig)
+            .unwrap();
+
+        let ptr = module.target_config().pointer_type();
+        let mut sig = module.make_signature();
+        sig.params.push(AbiParam::new(ptr));
+        sig.params.push(AbiParam::new(types::I32));
+        sig.params.push(AbiParam::new(ptr));
+        sig.params.push(AbiParam::new(ptr));
+        sig.returns.push(AbiParam::new(types::I32));
+        let invoke_static = module
+            .declare_function("jit_invoke_static", Linkage::Import, &sig)
+            .unwrap();
+
+        Self {
+            ctx: module.make_context(),
+            module,
+            builder_ctx: FunctionBuilderContext::new(),
+            safepoint,
+            invoke_static,
+        }
+    }
+
+    pub fn compile(&mut self, bc: &Bytecode) -> Result<CompiledFn, String> {
+        let ptr = self.module.target_config().pointer_type();
+        self.module.clear_context(&mut self.ctx);
+        let sig = &mut self.ctx.func.signature;
+        sig.params.push(AbiParam::new(ptr));
+        sig.params.push(AbiParam::new(ptr));
+        sig.returns.push(AbiParam::new(types::I32));
+
+        let safepoint = self
+            .module
+            .declare_func_in_func(self.safepoint, &mut self.ctx.func);
+        let invoke_static = self
+            .module
+            .declare_func_in_func(self.invoke_static, &mut self.ctx.func);
+        let b = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_ctx);
+        let translator = Translator {
+            bc,
+            b,
+            ptr,
+            safepoint,
+            invoke_static,
+            blocks: FxHashMap::default(),
+            stack: Vec::new(),
+            throw_blocks: FxHashMap::default(),
+            locals: Vec::new(),
+        };
+        translator.translate()?;
+
+        let id = self
+            .module
+            .declare_anonymous_function(&self.ctx.func.signature)
+            .map_err(|e| e.to_string())?;
+        self.module
+            .define_function(id, &mut self.ctx)
+            .map_err(|e| e.to_string())?;
+        self.module
+            .finalize_definitions()
+            .map_err(|e| e.to_string())?;
+
+        let code = self.module.get_finalized_function(id);
+        Ok(unsafe { std::mem::transmute::<*const u8, CompiledFn>(code) })
+    }
+}
+
+struct Translator<'a, 'b> {
+    bc: &'a Bytecode<'a>,
+    b: FunctionBuilder<'b>,
+    ptr: Type,
+    safepoint: FuncRef,
+    invoke_static: FuncRef,
+    //the blocks by pc, with the types of the operand stack on entry
+    blocks: FxHashMap<usize, (Block, Vec<Type>)>,
+    stack: Vec<Value>,
+    //by the status & the pc throwing
+    throw_blocks: FxHashMap<(i32, usize), Block>,
+    //the type of each local slot, the 2nd slot of a long is none
+    locals: Vec<Option<Ty>>,
+}
+
+//an instruction decoded
+struct Insn {
+    pc: usize,
+    op: OpCode,
+    len: usize,
+}
+
+impl<'a, 'b> Translator<'a, 'b> {
+    fn translate(mut self) -> Result<(), String> {
+        let insns = self.decode()?;
+        self.type_locals(&insns)?;
+        let leaders = leaders(self.bc.code, &insns);
+
+        //entry, load the args or the locals of the interpreter
+        let entry = self.b.create_block();
+        self.b.append_block_params_for_function_params(entry);
+        self.b.switch_to_block(entry);
+        let (args, ret) = {
+            let params = self.b.block_params(entry);
+            (params[0], params[1])
+        };
+
+        for (i, ty) in self.locals.clone().into_iter().enumerate() {
+            if let Some(ty) = ty {
+                self.b.declare_var(Variable::new(i), ty.ir());
+            }
+        }
+
+        if self.bc.osr.is_some() {
+            self.load_locals(args);
+        } else {
+            self.load_params(args);
+        }
+
+        //'reachable' is false after goto, return...; OSR enters at the loop head,
+        //the code before it is dead
+        let mut reachable = match self.bc.osr {
+            Some(pc) if leaders.contains(&pc) => {
+                self.jump(pc)?;
+                false
+            }
+            Some(pc) => return Err(format!("no loop head at {}", pc)),
+            None => true,
+        };
+        for insn in insns.iter() {
+            if leaders.contains(&insn.pc) {
+                let block = if reachable {
+                    self.jump(insn.pc)?
+                } else {
+                    self.block(insn.pc, None)?
+                };
+                self.b.switch_to_block(block);
+                self.stack = self.b.block_params(block).to_vec();
+                reachable = true;
+            } else if !reachable {
+                continue;
+            }
+
+            reachable = self.translate_insn(insn, ret)?;
+        }
+
+        if reachable {
+            return Err("falls off the code".to_string());
+        }
+
+        for ((status, pc), block) in self.throw_blocks.iter() {
+            self.b.switch_to_block(*block);
+            if *status != Status::Pending as i32 {
+                let pc = self.b.ins().iconst(types::I64, *pc as i64);
+                self.b.ins().store(MemFlags::trusted(), pc, ret, 0);
+            }
+            let v = self.b.ins().iconst(types::I32, *status as i64);
+            self.b.ins().return_(&[v]);
+        }
+
+        self.b.seal_all_blocks();
+        self.b.finalize();
+        Ok(())
+    }
+
+    //the params take the first slots, the other locals are 0
+    fn load_params(&mut self, args: Value) {
+        let mut slot = 0;
+        let mut params = self.bc.params.clone().into_iter().enumerate();
+        for i in 0..self.locals.len() {
+            let ty = match self.locals[i] {
+                Some(ty) => ty,
+                None => continue,
+            };
+
+            let v = if slot == i {
+                match params.next() {
+                    Some((n, param)) => {
+                        slot += if param == Ty::Long { 2 } else { 1 };
+                        let v = self.b.ins().load(
+                            types::I64,
+                            MemFlags::trusted(),
+                            args,
+                            (n * 8) as i32,
+                        );
+                        match param {
+                            Ty::Int => self.b.ins().ireduce(types::I32, v),
+                            Ty::Long => v,
+                        }
+                    }
+                    None => self.b.ins().iconst(ty.ir(), 0),
+                }
+            } else {
+                self.b.ins().iconst(ty.ir(), 0)
+            };
+            self.b.def_var(Variable::new(i), v);
+        }
+    }
+
+    //each local takes 8 bytes by its slot, for OSR
+    fn load_locals(&mut self, args: Value) {
+        for i in 0..self.locals.len() {
+            let ty = match self.locals[i] {
+                Some(ty) => ty,
+                None => continue,
+            };
+
+            let v = self
+                .b
+                .ins()
+                .load(types::I64, MemFlags::trusted(), args, (i * 8) as i32);
+            let v = match ty {
+                Ty::Int => self.b.ins().ireduce(types::I32, v),
+                Ty::Long => v,
+            };
+            self.b.def_var(Variable::new(i), v);
+        }
+    }
+
+    fn decode(&self) -> Result<Vec<Insn>, String> {
+        let code = self.bc.code;
+        let mut insns = Vec::new();
+        let mut pc = 0;
+        while pc < code.len() {
+            let op = OpCode::from(code[pc]);
+            let len = match op {
+                OpCode::bipush
+                | OpCode::ldc
+                | OpCode::iload
+                | OpCode::lload
+                | OpCode::istore
+                | OpCode::lstore => 2,
+                OpCode::sipush
+                | OpCode::ldc_w
+                | OpCode::ldc2_w
+                | OpCode::iinc
+                | OpCode::ifeq
+                | OpCode::ifne
+                | OpCode::iflt
+                | OpCode::ifge
+                | OpCode::ifgt
+                | OpCode::ifle
+                | OpCode::if_icmpeq
+                | OpCode::if_icmpne
+                | OpCode::if_icmplt
+                | OpCode::if_icmpge
+                | OpCode::if_icmpgt
+                | OpCode::if_icmple
+                | OpCode::goto => 3,
+                OpCode::invokestatic => {
+                    static_call(self.bc.cp, read_u2(code, pc + 1))?;
+                    3
+                }
+                OpCode::goto_w => 5,
+                OpCode::nop
+                | OpCode::iconst_m1
+                | OpCode::iconst_0
+                | OpCode::iconst_1
+                | OpCode::iconst_2
+                | OpCode::iconst_3
+                | OpCode::iconst_4
+                | OpCode::iconst_5
+                | OpCode::lconst_0
+                | OpCode::lconst_1
+                | OpCode::iload_0
+                | OpCode::iload_1
+                | OpCode::iload_2
+                | OpCode::iload_3
+                | OpCode::lload_0
+                | OpCode::lload_1
+                | OpCode::lload_2
+                | OpCode::lload_3
+                | OpCode::istore_0
+                | OpCode::istore_1
+                | OpCode::istore_2
+                | OpCode::istore_3
+                | OpCode::lstore_0
+                | OpCode::lstore_1
+                | OpCode::lstore_2
+                | OpCode::lstore_3
+                | OpCode::pop
+                | OpCode::pop2
+                | OpCode::dup
+                | OpCode::dup2
+                | OpCode::swap
+                | OpCode::iadd
+                | OpCode::ladd
+                | OpCode::isub
+                | OpCode::lsub
+                | OpCode::imul
+                | OpCode::lmul
+                | OpCode::idiv
+                | OpCode::ldiv
+                | OpCode::irem
+                | OpCode::lrem
+                | OpCode::ineg
+                | OpCode::lneg
+                | OpCode::ishl
+                | OpCode::lshl
+                | OpCode::ishr
+                | OpCode::lshr
+                | OpCode::iushr
+                | OpCode::lushr
+                | OpCode::iand
+                | OpCode::land
+                | OpCode::ior
+                | OpCode::lor
+                | OpCode::ixor
+                | OpCode::lxor
+                | OpCode::i2l
+                | OpCode::l2i
+                | OpCode::i2b
+                | OpCode::i2c
+                | OpCode::i2s
+                | OpCode::lcmp
+                | OpCode::ireturn
+                | OpCode::lreturn
+                | OpCode::return_void => 1,
+                op => return Err(format!("unsupported {:?}", op)),
+            };
+
+            if pc + len > code.len() {
+                return Err("truncated code".to_string());
+            }
+            insns.push(Insn { pc, op, len });
+            pc += len;
+        }
+
+        Ok(insns)
+    }
+
+    //a slot is either int or long in the whole method
+    fn type_locals(&mut self, insns: &[Insn]) -> Result<(), String> {
+        let code = self.bc.code;
+        let mut locals = vec![None; self.bc.max_locals];
+        let mut set = |i: usize, ty: Ty| -> Result<(), String> {
+            let n = if ty == Ty::Long { 2 } else { 1 };
+            if i + n > locals.len() {
+                return Err("local out of range".to_string());
+            }
+            match locals[i] {
+                Some(t) if t != ty => Err(format!("local {} reused", i)),
+                _ if ty == Ty::Long && locals[i + 1].is_some() => {
+                    Err(format!("local {} reused", i + 1))
+                }
+                _ if i > 0 && locals[i - 1] == Some(Ty::Long) => Err(format!("local {} reused", i)),
+                _ => {
+                    locals[i] = Some(ty);
+                    Ok(())
+                }
+            }
+        };
+
+        let mut slot = 0;
+        for ty in self.bc.params.iter() {
+            set(slot, *ty)?;
+            slot += if *ty == Ty::Long { 2 } else { 1 };
+        }
+
+        for insn in insns {
+            let (i, ty) = match local_access(code, insn) {
+                Some(v) => v,
+                None => continue,
+            };
+            set(i, ty)?;
+        }
+
+        self.locals = locals;
+        Ok(())
+    }
+
+    //the block at 'pc', created with the types of the stack if it's new
+    fn block(&mut self, pc: usize, types: Option<Vec<Type>>) -> Result<Block, String> {
+        if let Some((block, params)) = self.blocks.get(&pc) {
+            return match types {
+                Some(types) if types != *params => Err(format!("stack mismatch at {}", pc)),
+                _ => Ok(*block),
+            };
+        }
+
+        //the stack is empty at a loop head
+        let types = types.unwrap_or_default();
+        let block = self.b.create_block();
+        for t in types.iter() {
+            self.b.append_block_param(block, *t);
+        }
+        self.blocks.insert(pc, (block, types));
+        Ok(block)
+    }
+
+    fn stack_types(&self) -> Vec<Type> {
+        self.stack
+            .iter()
+            .map(|v| self.b.func.dfg.value_type(*v))
+            .collect()
+    }
+
+    //the target block, with the current stack as the args
+    fn target(&mut self, pc: usize) -> Result<(Block, Vec<Value>), String> {
+        let types = self.stack_types();
+        let block = self.block(pc, Some(types))?;
+        Ok((block, self.stack.clone()))
+    }
+
+    fn jump(&mut self, pc: usize) -> Result<Block, String> {
+        let (block, args) = self.target(pc)?;
+        self.b.ins().jump(block, &args);
+        Ok(block)
+    }
+
+    //the shared block returning the status & the pc, filled at the end
+    fn throw_block(&mut self, status: Status, pc: usize) -> Block {
+        let b = &mut self.b;
+        *self
+            .throw_blocks
+            .entry((status as i32, pc))
+            .or_insert_with(|| b.create_block())
+    }
+
+    //as the interpreter, gc may run here
+    fn poll_safepoint(&mut self) {
+        self.b.ins().call(self.safepoint, &[]);
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack
+            .pop()
+            .ok_or_else(|| "stack underflow".to_string())
+    }
+
+    fn push(&mut self, v: Value) {
+        self.stack.push(v);
+    }
+
+    fn binary<F: FnOnce(&mut FunctionBuilder, Value, Value) -> Value>(
+        &mut self,
+        f: F,
+    ) -> Result<(), String> {
+        let v2 = self.pop()?;
+        let v1 = self.pop()?;
+        let v = f(&mut self.b, v1, v2);
+        self.push(v);
+        Ok(())
+    }
+
+    //java doesn't trap on MIN / -1, the divisor -1 is handled alone
+    fn div(&mut self, pc: usize, rem: bool) -> Result<(), String> {
+        let v2 = self.pop()?;
+        let v1 = self.pop()?;
+        let ty = self.b.func.dfg.value_type(v2);
+
+        let throw = self.throw_block(Status::DivByZero, pc);
+        let cont = self.b.create_block();
+        self.b.ins().brif(v2, cont, &[], throw, &[]);
+        self.b.switch_to_block(cont);
+
+        let is_m1 = self.b.ins().icmp_imm(IntCC::Equal, v2, -1);
+        let one = self.b.ins().iconst(ty, 1);
+        let divisor = self.b.ins().select(is_m1, one, v2);
+        let v = if rem {
+            let r = self.b.ins().srem(v1, divisor);
+            let zero = self.b.ins().iconst(ty, 0);
+            self.b.ins().select(is_m1, zero, r)
+        } else {
+            let q = self.b.ins().sdiv(v1, divisor);
+            let neg = self.b.ins().ineg(v1);
+            self.b.ins().select(is_m1, neg, q)
+        };
+        self.push(v);
+        Ok(())
+    }
+
+    fn branch(&mut self, insn: &Insn, cond: Value) -> Result<(), String> {
+        let offset = read_i2(self.bc.code, insn.pc + 1) as i64;
+        let target = branch_target(insn.pc, offset)?;
+        if offset < 0 {
+            self.poll_safepoint();
+        }
+
+        let (then_block, then_args) = self.target(target)?;
+        let (else_block, else_args) = self.target(insn.pc + insn.len)?;
+        self.b
+            .ins()
+            .brif(cond, then_block, &then_args, else_block, &else_args);
+        Ok(())
+    }
+
+    fn goto(&mut self, insn: &Insn, offset: i64) -> Result<(), String> {
+        let target = branch_target(insn.pc, offset)?;
+        if offset < 0 {
+            self.poll_safepoint();
+        }
+        self.jump(target)?;
+        Ok(())
+    }
+
+    fn load_local(&mut self, i: usize) {
+        let v = self.b.use_var(Variable::new(i));
+        self.push(v);
+    }
+
+    fn store_local(&mut self, i: usize) -> Result<(), String> {
+        let v = self.pop()?;
+        self.b.def_var(Variable::new(i), v);
+        Ok(())
+    }
+
+    fn ldc(&mut self, idx: usize, wide: bool) -> Result<(), String> {
+        let v = match (self.bc.cp.get(idx), wide) {
+            (Some(ConstantPoolType::Integer { v }), false) => self
+                .b
+                .ins()
+                .iconst(types::I32, i32::from_be_bytes(*v) as i64),
+            (Some(ConstantPoolType::Long { v }), true) => {
+                self.b.ins().iconst(types::I64, i64::from_be_bytes(*v))
+            }
+            _ => return Err("unsupported constant".to_string()),
+        };
+        self.push(v);
+        Ok(())
+    }
+
+    /*
+    The method is called by the runtime, with the args in a stack slot as
+    CompiledFn, the return value follows them. The runtime resolves it on the
+    first call, then calls the compiled code of it, or the interpreter.
+    */
+    fn invoke_static(&mut self, pc: usize) -> Result<(), String> {
+        let (params, ret_ty) = static_call(self.bc.cp, read_u2(self.bc.code, pc + 1))?;
+        let n = params.len();
+        let slot = self.b.create_sized_stack_slot(StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            ((n + 1) * 8) as u32,
+            3,
+        ));
+        for i in (0..n).rev() {
+            let v = self.pop()?;
+            let v = self.widen(v);
+            self.b.ins().stack_store(v, slot, (i * 8) as i32);
+        }
+
+        let caller = self.b.ins().iconst(self.ptr, self.bc.id as i64);
+        let op_pc = self.b.ins().iconst(types::I32, pc as i64);
+        let args = self.b.ins().stack_addr(self.ptr, slot, 0);
+        let ret = self.b.ins().stack_addr(self.ptr, slot, (n * 8) as i32);
+        let call = self
+            .b
+            .ins()
+            .call(self.invoke_static, &[caller, op_pc, args, ret]);
+        let status = self.b.inst_results(call)[0];
+
+        let pending = self.throw_block(Status::Pending, 0);
+        let cont = self.b.create_block();
+        self.b.ins().brif(status, pending, &[], cont, &[]);
+        self.b.switch_to_block(cont);
+
+        if let Some(ty) = ret_ty {
+            let v = self.load_ret(slot, (n * 8) as i32, ty);
+            self.push(v);
+        }
+        Ok(())
+    }
+
+    fn load_ret(&mut self, slot: StackSlot, offset: i32, ty: Ty) -> Value {
+        let v = self.b.ins().stack_load(types::I64, slot, offset);
+        match ty {
+            Ty::Int => self.b.ins().ireduce(types::I32, v),
+            Ty::Long => v,
+        }
+    }
+
+    //the int is passed in 8 bytes, as CompiledFn
+    fn widen(&mut self, v: Value) -> Value {
+        if self.b.func.dfg.value_type(v) == types::I32 {
+            self.b.ins().sextend(types::I64, v)
+        } else {
+            v
+        }
+    }
+
+    fn ret(&mut self, ret: Value, with_value: bool) -> Result<(), String> {
+        if with_value {
+            let v = self.pop()?;
+            let v = self.widen(v);
+            self.b.ins().store(MemFlags::trusted(), v, ret, 0);
+        }
+        let ok = self.b.ins().iconst(types::I32, Status::Ok as i64);
+        self.b.ins().return_(&[ok]);
+        Ok(())
+    }
+
+    //false if the next instruction is not reachable from this one
+    fn translate_insn(&mut self, insn: &Insn, ret: Value) -> Result<bool, String> {
+        let code = self.bc.code;
+        let pc = insn.pc;
+
+        if let Some((i, _)) = local_access(code, insn) {
+            match insn.op {
+                OpCode::iload
+                | OpCode::lload
+                | OpCode::iload_0
+                | OpCode::iload_1
+                | OpCode::iload_2
+                | OpCode::iload_3
+                | OpCode::lload_0
+                | OpCode::lload_1
+                | OpCode::lload_2
+                | OpCode::lload_3 => self.load_local(i),
+                OpCode::iinc => {
+                    let v = self.b.use_var(Variable::new(i));
+                    let n = code[pc + 2] as i8 as i64;
+                    let v = self.b.ins().iadd_imm(v, n);
+                    self.b.def_var(Variable::new(i), v);
+                }
+                _ => self.store_local(i)?,
+            }
+            return Ok(true);
+        }
+
+        match insn.op {
+            OpCode::nop => (),
+            OpCode::iconst_m1
+            | OpCode::iconst_0
+            | OpCode::iconst_1
+            | OpCode::iconst_2
+            | OpCode::iconst_3
+            | OpCode::iconst_4
+            | OpCode::iconst_5 => {
+                let n = code[pc] as i64 - OpCode::iconst_0 as i64;
+                let v = self.b.ins().iconst(types::I32, n);
+                self.push(v);
+            }
+            OpCode::lconst_0 | OpCode::lconst_1 => {
+                let n = code[pc] as i64 - OpCode::lconst_0 as i64;
+                let v = self.b.ins().iconst(types::I64, n);
+                self.push(v);
+            }
+            OpCode::bipush => {
+                let v = self.b.ins().iconst(types::I32, code[pc + 1] as i8 as i64);
+                self.push(v);
+            }
+            OpCode::sipush => {
+                let v = self
+                    .b
+                    .ins()
+                    .iconst(types::I32, read_i2(code, pc + 1) as i64);
+                self.push(v);
+            }
+            OpCode::ldc => self.ldc(code[pc + 1] as usize, false)?,
+            OpCode::ldc_w => self.ldc(read_u2(code, pc + 1), false)?,
+            OpCode::ldc2_w => self.ldc(read_u2(code, pc + 1), true)?,
+
+            OpCode::pop => {
+                self.pop()?;
+            }
+            OpCode::pop2 => {
+                let v = self.pop()?;
+                if self.b.func.dfg.value_type(v) == types::I32 {
+                    self.pop()?;
+                }
+            }
+            OpCode::dup => {
+                let v = self.pop()?;
+                self.push(v);
+                self.push(v);
+            }
+            OpCode::dup2 => {
+                let v = self.pop()?;
+                if self.b.func.dfg.value_type(v) == types::I64 {
+                    self.push(v);
+                    self.push(v);
+                } else {
+                    let v1 = self.pop()?;
+                    self.stack.extend_from_slice(&[v1, v, v1, v]);
+                }
+            }
+            OpCode::swap => {
+                let v2 = self.pop()?;
+                let v1 = self.pop()?;
+                self.push(v2);
+                self.push(v1);
+            }
+
+            OpCode::iadd | OpCode::ladd => self.binary(|b, x, y| b.ins().iadd(x, y))?,
+            OpCode::isub | OpCode::lsub => self.binary(|b, x, y| b.ins().isub(x, y))?,
+            OpCode::imul | OpCode::lmul => self.binary(|b, x, y| b.ins().imul(x, y))?,
+            OpCode::idiv | OpCode::ldiv => self.div(pc, false)?,
+            OpCode::irem | OpCode::lrem => self.div(pc, true)?,
+            OpCode::ineg | OpCode::lneg => {
+                let v = self.pop()?;
+                let v = self.b.ins().ineg(v);
+                self.push(v);
+            }
+            //the shift distance is masked by the bits of type, as java
+            OpCode::ishl | OpCode::lshl => self.binary(|b, x, y| b.ins().ishl(x, y))?,
+            OpCode::ishr | OpCode::lshr => self.binary(|b, x, y| b.ins().sshr(x, y))?,
+            OpCode::iushr | OpCode::lushr => self.binary(|b, x, y| b.ins().ushr(x, y))?,
+            OpCode::iand | OpCode::land => self.binary(|b, x, y| b.ins().band(x, y))?,
+            OpCode::ior | OpCode::lor => self.binary(|b, x, y| b.ins().bor(x, y))?,
+            OpCode::ixor | OpCode::lxor => self.binary(|b, x, y| b.ins().bxor(x, y))?,
+
+            OpCode::i2l => {
+                let v = self.pop()?;
+                let v = self.b.ins().sextend(types::I64, v);
+                self.push(v);
+            }
+            OpCode::l2i => {
+                let v = self.pop()?;
+                let v = self.b.ins().ireduce(types::I32, v);
+                self.push(v);
+            }
+            OpCode::i2b | OpCode::i2c | OpCode::i2s => {
+                let v = self.pop()?;
+                let narrow = if insn.op == OpCode::i2b {
+                    types::I8
+                } else {
+                    types::I16
+                };
+                let v = self.b.ins().ireduce(narrow, v);
+                let v = if insn.op == OpCode::i2c {
+                    self.b.ins().uextend(types::I32, v)
+                } else {
+                    self.b.ins().sextend(types::I32, v)
+                };
+                self.push(v);
+            }
+            OpCode::lcmp => self.binary(|b, x, y| {
+                let gt = b.ins().icmp(IntCC::SignedGreaterThan, x, y);
+                let lt = b.ins().icmp(IntCC::SignedLessThan, x, y);
+                let gt = b.ins().uextend(types::I32, gt);
+                let lt = b.ins().uextend(types::I32, lt);
+                b.ins().isub(gt, lt)
+            })?,
+
+            OpCode::ifeq
+            | OpCode::ifne
+            | OpCode::iflt
+            | OpCode::ifge
+            | OpCode::ifgt
+            | OpCode::ifle => {
+                let v = self.pop()?;
+                let cond = self.b.ins().icmp_imm(int_cc(insn.op), v, 0);
+                self.branch(insn, cond)?;
+                return Ok(false);
+            }
+            OpCode::if_icmpeq
+            | OpCode::if_icmpne
+            | OpCode::if_icmplt
+            | OpCode::if_icmpge
+            | OpCode::if_icmpgt
+            | OpCode::if_icmple => {
+                let v2 = self.pop()?;
+                let v1 = self.pop()?;
+                let cond = self.b.ins().icmp(int_cc(insn.op), v1, v2);
+                self.branch(insn, cond)?;
+                return Ok(false);
+            }
+            OpCode::goto => {
+                self.goto(insn, read_i2(code, pc + 1) as i64)?;
+                return Ok(false);
+            }
+            OpCode::goto_w => {
+                let offset =
+                    i32::from_be_bytes([code[pc + 1], code[pc + 2], code[pc + 3], code[pc + 4]]);
+                self.goto(insn, offset as i64)?;
+                return Ok(false);
+            }
+
+            OpCode::invokestatic => self.invoke_static(pc)?,
+
+            OpCode::ireturn | OpCode::lreturn => {
+                self.ret(ret, true)?;
+                return Ok(false);
+            }
+            OpCode::return_void => {
+                self.ret(ret, false)?;
+                return Ok(false);
+            }
+            op => return Err(format!("unsupported {:?}", op)),
+        }
+
+        Ok(true)
+    }
+}
+
+//the params & the return type of the method called by invokestatic, by the descriptor
+pub fn static_call(cp: &ConstantPool, idx: usize) -> Result<(Vec<Ty>, Option<Ty>), String> {
+    let desc = match cp.get(idx) {
+        Some(ConstantPoolType::MethodRef { .. })
+        | Some(ConstantPoolType::InterfaceMethodRef { .. }) => {
+            let (_, _, name_and_type) = constant_pool::get_method_ref(cp, idx);
+            let (_, desc) = constant_pool::get_name_and_type(cp, name_and_type as usize);
+            desc.clone()
+        }
+        _ => return Err("bad method ref".to_string()),
+    };
+
+    let sig = MethodSignature::new(desc.as_slice());
+    let mut params = Vec::new();
+    for t in sig.args.iter() {
+        params.push(to_ty(t).ok_or("call param type")?);
+    }
+    let ret = match &sig.retype {
+        SignatureType::Void => None,
+        t => Some(to_ty(t).ok_or("call return type")?),
+    };
+    Ok((params, ret))
+}
+
+//the first instructions of the basic blocks
+fn leaders(code: &[u8], insns: &[Insn]) -> FxHashSet<usize> {
+    let mut leaders = FxHashSet::default();
+    for insn in insns {
+        let next = insn.pc + insn.len;
+        let offset = match insn.op {
+            OpCode::goto_w => Some(i32::from_be_bytes([
+                code[insn.pc + 1],
+                code[insn.pc + 2],
+                code[insn.pc + 3],
+                code[insn.pc + 4],
+            ]) as i64),
+            op if is_branch(op) => Some(read_i2(code, insn.pc + 1) as i64),
+            _ => None,
+        };
+
+        if let Some(offset) = offset {
+            leaders.insert((insn.pc as i64 + offset) as usize);
+            leaders.insert(next);
+        }
+    }
+    leaders
+}
+
+fn is_branch(op: OpCode) -> bool {
+    matches!(
+        op,
+        OpCode::ifeq
+            | OpCode::ifne
+            | OpCode::iflt
+            | OpCode::ifge
+            | OpCode::ifgt
+            | OpCode::ifle
+            | OpCode::if_icmpeq
+            | OpCode::if_icmpne
+            | OpCode::if_icmplt
+            | OpCode::if_icmpge
+            | OpCode::if_icmpgt
+            | OpCode::if_icmple
+            | OpCode::goto
+    )
+}
+
+fn branch_target(pc: usize, offset: i64) -> Result<usize, String> {
+    let target = pc as i64 + offset;
+    if target < 0 {
+        Err("branch out of code".to_string())
+    } else {
+        Ok(target as usize)
+    }
+}
+
+//the local slot & type accessed by the load, store and iinc
+fn local_access(code: &[u8], insn: &Insn) -> Option<(usize, Ty)> {
+    let pc = insn.pc;
+    let v = match insn.op {
+        OpCode::iload | OpCode::istore | OpCode::iinc => (code[pc + 1] as usize, Ty::Int),
+        OpCode::lload | OpCode::lstore => (code[pc + 1] as usize, Ty::Long),
+        OpCode::iload_0 | OpCode::iload_1 | OpCode::iload_2 | OpCode::iload_3 => {
+            ((code[pc] - OpCode::iload_0 as u8) as usize, Ty::Int)
+        }
+        OpCode::lload_0 | OpCode::lload_1 | OpCode::lload_2 | OpCode::lload_3 => {
+            ((code[pc] - OpCode::lload_0 as u8) as usize, Ty::Long)
+        }
+        OpCode::istore_0 | OpCode::istore_1 | OpCode::istore_2 | OpCode::istore_3 => {
+            ((code[pc] - OpCode::istore_0 as u8) as usize, Ty::Int)
+        }
+        OpCode::lstore_0 | OpCode::lstore_1 | OpCode::lstore_2 | OpCode::lstore_3 => {
+            ((code[pc] - OpCode::lstore_0 as u8) as usize, Ty::Long)
+        }
+        _ => return None,
+    };
+    Some(v)
+}
+
+fn int_cc(op: OpCode) -> IntCC {
+    match op {
+        OpCode::ifeq | OpCode::if_icmpeq => IntCC::Equal,
+        OpCode::ifne | OpCode::if_icmpne => IntCC::NotEqual,
+        OpCode::iflt | OpCode::if_icmplt => IntCC::SignedLessThan,
+        OpCode::ifge | OpCode::if_icmpge => IntCC::SignedGreaterThanOrEqual,
+        OpCode::ifgt | OpCode::if_icmpgt => IntCC::SignedGreaterThan,
+        OpCode::ifle | OpCode::if_icmple => IntCC::SignedLessThanOrEqual,
+        _ => unreachable!(),
+    }
+}
+
+fn read_i2(code: &[u8], pc: usize) -> i16 {
+    i16::from_be_bytes([code[pc], code[pc + 1]])
+}
+
+fn read_u2(code: &[u8], pc: usize) -> usize {
+    u16::from_be_bytes([code[pc], code[pc + 1]]) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bytecode, CodeCache, Status, Ty};
+    use crate::runtime::method::MethodId;
+    use classfile::flags::{ACC_PUBLIC, ACC_STATIC};
+    use classfile::{ClassBuilder, OpCode, Version};
+    use std::sync::Arc;
+
+    //g(int a, long b) { return a + b; }, throws if a is 0
+    extern "C" fn invoke_g(
+        caller: *const MethodId,
+        op_pc: i32,
+        args: *const i64,
+        ret: *mut i64,
+    ) -> i32 {
+        assert_eq!((caller as usize, op_pc), (42, 3));
+        let (a, b) = unsafe { (*args, *args.add(1)) };
+        if a == 0 {
+            return Status::Pending as i32;
+        }
+        unsafe { *ret = a + b };
+        Status::Ok as i32
+    }
+
+    fn run(bc: &Bytecode, args: &[i64]) -> (Status, i64) {
+        let mut cache = CodeCache::new(invoke_g);
+        let code = cache.compile(bc).unwrap();
+        let mut ret = 0;
+        let status = unsafe { code(args.as_ptr(), &mut ret) };
+        (Status::from(status), ret)
+    }
+
+    #[test]
+    fn t_sum_loop() {
+        /*
+        static long sum(int n) {
+            long s = 0;
+            for (int i = 0; i < n; i++) s += i;
+            return s;
+        }
+        */
+        let code = [
+            0x09, //lconst_0
+            0x40, //lstore_1
+            0x03, //iconst_0
+            0x3e, //istore_3
+            0x1d, //iload_3
+            0x1a, //iload_0
+            0xa2, 0x00, 0x0e, //if_icmpge +14
+            0x1f, //lload_1
+            0x1d, //iload_3
+            0x85, //i2l
+            0x61, //ladd
+            0x40, //lstore_1
+            0x84, 0x03, 0x01, //iinc 3, 1
+            0xa7, 0xff, 0xf3, //goto -13
+            0x1f, //lload_1
+            0xad, //lreturn
+        ];
+        let cp = Arc::new(Vec::new());
+        let bc = Bytecode {
+            id: 0,
+            code: &code,
+            cp: &cp,
+            max_locals: 4,
+            params: vec![Ty::Int],
+            ret: Some(Ty::Long),
+            osr: None,
+        };
+        assert_eq!(run(&bc, &[100000]), (Status::Ok, 4999950000));
+
+        //entered at the loop head, n = 100, s = 10, i = 98
+        let bc = Bytecode { osr: Some(4), ..bc };
+        assert_eq!(run(&bc, &[100, 10, 0, 98]), (Status::Ok, 10 + 98 + 99));
+    }
+
+    #[test]
+    fn t_div() {
+        //static int div(int a, int b) { return a / b; }
+        let code = [0x1a, 0x1b, 0x6c, 0xac];
+        let cp = Arc::new(Vec::new());
+        let bc = Bytecode {
+            id: 0,
+            code: &code,
+            cp: &cp,
+            max_locals: 2,
+            params: vec![Ty::Int, Ty::Int],
+            ret: Some(Ty::Int),
+            osr: None,
+        };
+        assert_eq!(run(&bc, &[-7, 2]), (Status::Ok, -3));
+        assert_eq!(
+            run(&bc, &[i32::MIN as i64, -1]),
+            (Status::Ok, i32::MIN as i64)
+        );
+        assert_eq!(run(&bc, &[1, 0]), (Status::DivByZero, 2));
+    }
+
+    #[test]
+    fn t_invoke_static() {
+        //static int f(int a, long b) { return a / g(a, b); }
+        let mut cb = ClassBuilder::new(
+            Version {
+                minor: 0,
+                major: 52,
+            },
+            ACC_PUBLIC,
+            "t/F",
+            Some("java/lang/Object"),
+        );
+        let mut c = cb.code(ACC_STATIC, "f", "(IJ)I");
+        c.local(OpCode::iload, 0);
+        c.local(OpCode::iload, 0);
+        c.local(OpCode::lload, 1);
+        c.invoke(OpCode::invokestatic, "t/F", "g", "(IJ)I", false);
+        c.op(OpCode::idiv);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+        let cf = cb.build().unwrap();
+
+        let code = cf.methods[0].get_code().unwrap();
+        let bc = Bytecode {
+            id: 42,
+            code: code.code.as_slice(),
+            cp: &cf.cp,
+            max_locals: 3,
+            params: vec![Ty::Int, Ty::Long],
+            ret: Some(Ty::Int),
+            osr: None,
+        };
+        assert_eq!(run(&bc, &[6, -3]), (Status::Ok, 2));
+        assert_eq!(run(&bc, &[0, 5]).0, Status::Pending);
+        //the pc of idiv
+        assert_eq!(run(&bc, &[1, -1]), (Status::DivByZero, 6));
+    }
+}
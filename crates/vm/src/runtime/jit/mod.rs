@@ -0,0 +1,566 @@
+use crate::oop::Oop;
+use crate::runtime::local::Local;
+use crate::runtime::method::MethodId;
+use crate::runtime::quick::Quick;
+use crate::runtime::{self, exception, thread, DataArea, Frame, JavaCall};
+use crate::types::{FrameRef, MethodIdRef};
+use classfile::consts as cls_const;
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+mod compiler;
+
+pub use compiler::{Status, Ty};
+
+use compiler::CompiledFn;
+
+/*
+Baseline JIT
+
+A method is compiled by cranelift when its invocations plus the back-edges
+taken by the interpreter reach COMPILE_THRESHOLD, the next invocation runs
+the compiled code. A loop running in the interpreter is replaced on the stack
+(OSR) when its back-edges reach COMPILE_THRESHOLD: the method is compiled
+again, entered at the loop head with the locals of the interpreter, the
+interpreted frame returns what the compiled code returns.
+
+Only static methods computing on int & long are compiled, with the calls to
+such static methods: the locals and the operand stack are kept in registers,
+no oop is touched, so the compiled code needn't stack maps. Instance methods,
+and the methods using any other bytecode (fields, objects, arrays, float...)
+fail to compile, and stay in the interpreter forever.
+
+The compiled code calls the runtime for invokestatic: the method is resolved
+on the first call as the interpreter does, then its compiled code is called,
+or the interpreter if it's not compiled.
+
+No frame is pushed for the compiled code, a record of the method & the pc is
+kept per thread instead (see Activation). The records get frames when some
+other code needs the stack: the compiled code calls the interpreter or a
+native, resolves a method (<clinit> may run) or throws. So the compiled
+methods are in the stack traces, at the lines running.
+
+The compiled code polls the safepoint at the back-edges, and returns a
+Status instead of throwing. It depends on no class, no call is inlined and
+no type is speculated, so nothing invalidates it, it's never deoptimized.
+*/
+
+const COMPILE_THRESHOLD: u32 = 2000;
+
+const INTERPRETED: u8 = 0;
+const COMPILING: u8 = 1;
+const COMPILED: u8 = 2;
+const FAILED: u8 = 3;
+
+lazy_static! {
+    static ref CODE_CACHE: Mutex<compiler::CodeCache> =
+        Mutex::new(compiler::CodeCache::new(jit_invoke_static));
+}
+
+thread_local! {
+    //the compiled code running in the thread, the bottom first
+    static ACTIVATIONS: RefCell<Vec<Activation>> = const { RefCell::new(Vec::new()) };
+}
+
+//the state of a method, kept by MethodId
+#[derive(Default)]
+pub struct JitState {
+    counter: AtomicU32,
+    state: AtomicU8,
+    code: AtomicUsize,
+    //the OSR code by the pc of the loop head, 0 if failed
+    osr: Mutex<FxHashMap<usize, usize>>,
+}
+
+//a cloned method is interpreted again
+impl Clone for JitState {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/*
+The compiled method running, and the pc of the call or the instruction
+throwing, the frame is pushed to the thread on demand by materialize.
+
+The activations with frames are the bottom ones: the compiled code calling
+other code has got frames for all the activations below. An OSR activation
+runs in the frame of the interpreter, it isn't popped by the activation.
+*/
+struct Activation {
+    mir: *const MethodId,
+    pc: usize,
+    frame: Option<FrameRef>,
+    own_frame: bool,
+}
+
+//by the interpreter, the backward branch taken, true if the loop is hot
+#[inline]
+pub fn on_back_edge(mir: &MethodId) -> bool {
+    let jit = &mir.jit;
+    jit.counter.fetch_add(1, Ordering::Relaxed) >= COMPILE_THRESHOLD
+        && jit.state.load(Ordering::Relaxed) != FAILED
+}
+
+/*
+Run the compiled code of 'mir', compile it if hot enough.
+
+None if it's interpreted, or the result: the return value (none for void),
+Err if an exception is thrown, it's pending.
+*/
+pub fn invoke(mir: &MethodIdRef, args: &[Oop]) -> Option<Result<Option<Oop>, ()>> {
+    let code = code_of(mir)?;
+
+    let mut values = Vec::with_capacity(args.len());
+    for v in args {
+        match v {
+            Oop::Int(v) => values.push(*v as i64),
+            Oop::Long(v) => values.push(*v),
+            _ => return None,
+        }
+    }
+
+    let mut ret = 0i64;
+    if !run(mir, code, &values, &mut ret, None) {
+        return Some(Err(()));
+    }
+
+    Some(Ok(ret_value(mir, ret)))
+}
+
+/*
+OSR of the loop at 'pc' in 'frame', the interpreter is there with an empty
+operand stack and 'local'.
+
+None if the loop isn't compiled, the interpreter goes on. Or the result as
+invoke, the frame returns it.
+*/
+pub fn osr(
+    frame: FrameRef,
+    mir: &MethodIdRef,
+    pc: usize,
+    local: &Local,
+) -> Option<Result<Option<Oop>, ()>> {
+    let code = osr_code_of(mir, pc)?;
+
+    let max_locals = mir.method.code.as_ref()?.max_locals as usize;
+    let values: Vec<i64> = (0..max_locals)
+        .map(|i| local.get_i64(i).unwrap_or(0))
+        .collect();
+
+    let mut ret = 0i64;
+    if !run(mir, code, &values, &mut ret, Some(frame)) {
+        return Some(Err(()));
+    }
+
+    Some(Ok(ret_value(mir, ret)))
+}
+
+fn ret_value(mir: &MethodId, ret: i64) -> Option<Oop> {
+    match compiler::to_ty(&mir.method.signature.retype) {
+        Some(Ty::Int) => Some(Oop::new_int(ret as i32)),
+        Some(Ty::Long) => Some(Oop::new_long(ret)),
+        None => None,
+    }
+}
+
+//the compiled code, None if 'mir' is interpreted
+fn code_of(mir: &MethodIdRef) -> Option<usize> {
+    let jit = &mir.jit;
+    match jit.state.load(Ordering::Acquire) {
+        COMPILED => Some(jit.code.load(Ordering::Relaxed)),
+        INTERPRETED => {
+            let n = jit.counter.fetch_add(1, Ordering::Relaxed);
+            if n < COMPILE_THRESHOLD
+                || jit
+                    .state
+                    .compare_exchange(INTERPRETED, COMPILING, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_err()
+            {
+                return None;
+            }
+            compile(mir)
+        }
+        _ => None,
+    }
+}
+
+//the OSR code entered at 'pc', compiled once per loop
+fn osr_code_of(mir: &MethodIdRef, pc: usize) -> Option<usize> {
+    let jit = &mir.jit;
+    let mut osr = jit.osr.lock().unwrap();
+    let code = match osr.get(&pc) {
+        Some(code) => *code,
+        None => {
+            let code = compile_osr(mir, pc).unwrap_or(0);
+            osr.insert(pc, code);
+            code
+        }
+    };
+
+    if code == 0 {
+        None
+    } else {
+        Some(code)
+    }
+}
+
+//false if an exception is thrown, it's pending, 'frame' is the interpreted one by OSR
+fn run(
+    mir: &MethodIdRef,
+    code: usize,
+    args: &[i64],
+    ret: &mut i64,
+    frame: Option<FrameRef>,
+) -> bool {
+    //the interpreter checks its frames in prepare_frame
+    let depth = ACTIVATIONS.with(|acts| acts.borrow().len());
+    if depth >= runtime::arguments::max_stack_frames() {
+        materialize();
+        exception::meet_ex(cls_const::J_SOE, None);
+        return false;
+    }
+
+    ACTIVATIONS.with(|acts| {
+        acts.borrow_mut().push(Activation {
+            mir: Arc::as_ptr(mir),
+            pc: 0,
+            frame,
+            own_frame: false,
+        })
+    });
+
+    let code = unsafe { std::mem::transmute::<usize, CompiledFn>(code) };
+    let status = Status::from(unsafe { code(args.as_ptr(), ret) });
+    if status == Status::DivByZero {
+        set_pc(*ret as usize);
+        materialize();
+        exception::meet_ex(cls_const::J_ARITHMETIC_EX, Some("/ by zero".to_string()));
+    }
+
+    let act = ACTIVATIONS.with(|acts| acts.borrow_mut().pop()).unwrap();
+    if act.own_frame {
+        let jt = runtime::thread::current_java_thread();
+        let _ = jt.write().unwrap().frames.pop();
+    }
+
+    status == Status::Ok
+}
+
+//the pc of the top activation, the instruction being executed
+fn set_pc(pc: usize) {
+    ACTIVATIONS.with(|acts| {
+        let mut acts = acts.borrow_mut();
+        let act = acts.last_mut().unwrap();
+        act.pc = pc;
+        if let Some(frame) = &act.frame {
+            let frame = frame.try_read().unwrap();
+            frame.pc.store(pc as i32 + 1, Ordering::Relaxed);
+        }
+    });
+}
+
+//push the frames of the activations having none, the stack is seen by the other code
+fn materialize() {
+    ACTIVATIONS.with(|acts| {
+        let mut acts = acts.borrow_mut();
+        let start = acts
+            .iter()
+            .rposition(|it| it.frame.is_some())
+            .map_or(0, |i| i + 1);
+        if start == acts.len() {
+            return;
+        }
+
+        let jt = runtime::thread::current_java_thread();
+        let mut jt = jt.write().unwrap();
+        for act in acts[start..].iter_mut() {
+            let mir = unsafe {
+                Arc::increment_strong_count(act.mir);
+                Arc::from_raw(act.mir)
+            };
+            let frame = Frame::new(mir, jt.frames.len() + 1);
+            //as the interpreter, the pc is past the opcode
+            frame.pc.store(act.pc as i32 + 1, Ordering::Relaxed);
+            let frame = new_sync_ref!(frame);
+            jt.frames.push(frame.clone());
+            act.frame = Some(frame);
+            act.own_frame = true;
+        }
+    });
+}
+
+//invokestatic of the compiled code of 'caller', at 'op_pc'
+extern "C" fn jit_invoke_static(
+    caller: *const MethodId,
+    op_pc: i32,
+    args: *const i64,
+    ret: *mut i64,
+) -> i32 {
+    let caller = unsafe { &*caller };
+    let op_pc = op_pc as usize;
+    set_pc(op_pc);
+
+    let mir = match caller.quick.get(op_pc) {
+        Some(Quick::Method(mir)) => mir,
+        _ => match resolve_static(caller, op_pc) {
+            Some(mir) => mir,
+            None => return Status::Pending as i32,
+        },
+    };
+
+    let args = unsafe { std::slice::from_raw_parts(args, mir.method.signature.args.len()) };
+    let ret = unsafe { &mut *ret };
+    let ok = match code_of(mir) {
+        Some(code) => run(mir, code, args, ret, None),
+        None => call_out(mir, args, ret),
+    };
+    if ok {
+        Status::Ok as i32
+    } else {
+        Status::Pending as i32
+    }
+}
+
+//as the interpreter, the class of the method is initialized
+fn resolve_static(caller: &MethodId, op_pc: usize) -> Option<&MethodIdRef> {
+    materialize();
+    let code = &caller.method.code.as_ref().unwrap().code;
+    let idx = u16::from_be_bytes([code[op_pc + 1], code[op_pc + 2]]) as usize;
+    let class = caller.method.class.extract_inst();
+    let mir = class.cp_cache.get_method(idx);
+    if thread::is_meet_ex() {
+        return None;
+    }
+
+    match caller.quick.set(op_pc, Quick::Method(mir)) {
+        Quick::Method(mir) => Some(mir),
+        _ => unreachable!(),
+    }
+}
+
+//the interpreted or native method, invoked by JavaCall
+fn call_out(mir: &MethodIdRef, args: &[i64], ret: &mut i64) -> bool {
+    materialize();
+    let args = mir
+        .method
+        .signature
+        .args
+        .iter()
+        .zip(args)
+        .map(|(t, v)| match compiler::to_ty(t) {
+            Some(Ty::Long) => Oop::new_long(*v),
+            _ => Oop::new_int(*v as i32),
+        })
+        .collect();
+
+    let mut jc = JavaCall::new_with_args(mir.clone(), args);
+    let area = DataArea::new(1);
+    jc.invoke(Some(&area), true);
+    if thread::is_meet_ex() {
+        return false;
+    }
+
+    let mut stack = area.stack.borrow_mut();
+    match compiler::to_ty(&mir.method.signature.retype) {
+        Some(Ty::Int) => *ret = stack.pop_int() as i64,
+        Some(Ty::Long) => *ret = stack.pop_long(),
+        None => (),
+    }
+    true
+}
+
+fn compile(mir: &MethodId) -> Option<usize> {
+    let jit = &mir.jit;
+    let name = method_name(mir);
+    match translate(mir, None) {
+        Ok(code) => {
+            info!("jit: compiled {}", name);
+            jit.code.store(code as usize, Ordering::Relaxed);
+            jit.state.store(COMPILED, Ordering::Release);
+            Some(code as usize)
+        }
+        Err(e) => {
+            info!("jit: {} not compiled, {}", name, e);
+            jit.state.store(FAILED, Ordering::Release);
+            None
+        }
+    }
+}
+
+//the method not compilable isn't tried by the interpreter again
+fn compile_osr(mir: &MethodId, pc: usize) -> Option<usize> {
+    let name = method_name(mir);
+    match translate(mir, Some(pc)) {
+        Ok(code) => {
+            info!("jit: compiled {} for OSR at {}", name, pc);
+            Some(code as usize)
+        }
+        Err(e) => {
+            info!("jit: {} not compiled for OSR at {}, {}", name, pc, e);
+            if compiler::Bytecode::new(mir).is_err() {
+                mir.jit.state.store(FAILED, Ordering::Release);
+            }
+            None
+        }
+    }
+}
+
+fn translate(mir: &MethodId, osr: Option<usize>) -> Result<CompiledFn, String> {
+    let method = &mir.method;
+    if !method.is_static() || method.is_synchronized() {
+        return Err("not static, or synchronized".to_string());
+    }
+
+    let mut m = compiler::Bytecode::new(mir)?;
+    m.osr = osr;
+    CODE_CACHE.lock().unwrap().compile(&m)
+}
+
+fn method_name(mir: &MethodId) -> String {
+    let method = &mir.method;
+    format!(
+        "{}.{}{}",
+        String::from_utf8_lossy(method.cls_name.as_slice()),
+        String::from_utf8_lossy(method.name.as_slice()),
+        String::from_utf8_lossy(method.desc.as_slice())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::oop::heap::tests::HEAP_TEST;
+    use crate::oop::Oop;
+    use crate::runtime::class_loader::tests::new_object;
+    use crate::runtime::gc::tests::{attach, detach};
+    use crate::runtime::{self, sys_dic, thread, ClassLoader, DataArea, JavaCall};
+    use classfile::flags::{ACC_PUBLIC, ACC_STATIC};
+    use classfile::{ClassBuilder, OpCode, Version};
+    use std::sync::Arc;
+
+    //the compiled code calls the interpreter, then the compiled code of the callee
+    #[test]
+    fn t_invoke_static() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        //static int f(int a) { return g(a) + 1; }
+        //static int g(int a) { return a * 2; }
+        let mut cb = ClassBuilder::new(
+            Version {
+                minor: 0,
+                major: 52,
+            },
+            ACC_PUBLIC,
+            "t/J",
+            Some("java/lang/Object"),
+        );
+        let mut c = cb.code(ACC_STATIC, "f", "(I)I");
+        c.local(OpCode::iload, 0);
+        c.invoke(OpCode::invokestatic, "t/J", "g", "(I)I", false);
+        c.op(OpCode::iconst_1);
+        c.op(OpCode::iadd);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+        let mut c = cb.code(ACC_STATIC, "g", "(I)I");
+        c.local(OpCode::iload, 0);
+        c.op(OpCode::iconst_2);
+        c.op(OpCode::imul);
+        c.op(OpCode::ireturn);
+        c.finish().unwrap();
+        let buf = cb.build().unwrap().to_bytes().unwrap();
+
+        let loader = ClassLoader::User(10004);
+        sys_dic::put(loader, b"java/lang/Object", new_object());
+        let class = runtime::define_class(Some(loader), None, &buf).unwrap();
+        let method = |name: &str| {
+            let k = (
+                Arc::new(name.as_bytes().to_vec()),
+                Arc::new(b"(I)I".to_vec()),
+            );
+            class.extract_inst().all_methods[&k].clone()
+        };
+        let (f, g) = (method("f"), method("g"));
+
+        for _ in 0..super::COMPILE_THRESHOLD {
+            assert!(super::invoke(&f, &[Oop::new_int(3)]).is_none());
+        }
+        for i in 0..super::COMPILE_THRESHOLD + 2 {
+            let v = super::invoke(&f, &[Oop::new_int(i as i32)]);
+            assert!(matches!(v, Some(Ok(Some(Oop::Int(v)))) if v == i as i32 * 2 + 1));
+        }
+        assert_eq!(
+            g.jit.state.load(std::sync::atomic::Ordering::Relaxed),
+            super::COMPILED
+        );
+
+        let jt = runtime::thread::current_java_thread();
+        assert!(jt.read().unwrap().frames.is_empty());
+        assert!(!thread::is_meet_ex());
+        detach();
+    }
+
+    //the loop of a single invocation gets hot, it's finished by the compiled code
+    #[test]
+    fn t_osr() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        //static long sum(int n) { long s = 0; for (int i = 0; i < n; i++) s += i; return s; }
+        let mut cb = ClassBuilder::new(
+            Version {
+                minor: 0,
+                major: 52,
+            },
+            ACC_PUBLIC,
+            "t/K",
+            Some("java/lang/Object"),
+        );
+        let mut c = cb.code(ACC_STATIC, "sum", "(I)J");
+        let (head, end) = (c.new_label(), c.new_label());
+        c.op(OpCode::lconst_0);
+        c.local(OpCode::lstore, 1);
+        c.op(OpCode::iconst_0);
+        c.local(OpCode::istore, 3);
+        c.bind(head);
+        c.local(OpCode::iload, 3);
+        c.local(OpCode::iload, 0);
+        c.jump(OpCode::if_icmpge, end);
+        c.local(OpCode::lload, 1);
+        c.local(OpCode::iload, 3);
+        c.op(OpCode::i2l);
+        c.op(OpCode::ladd);
+        c.local(OpCode::lstore, 1);
+        c.iinc(3, 1);
+        c.jump(OpCode::goto, head);
+        c.bind(end);
+        c.local(OpCode::lload, 1);
+        c.op(OpCode::lreturn);
+        c.finish().unwrap();
+        let buf = cb.build().unwrap().to_bytes().unwrap();
+
+        let loader = ClassLoader::User(10005);
+        sys_dic::put(loader, b"java/lang/Object", new_object());
+        let class = runtime::define_class(Some(loader), None, &buf).unwrap();
+        let k = (Arc::new(b"sum".to_vec()), Arc::new(b"(I)J".to_vec()));
+        let sum = class.extract_inst().all_methods[&k].clone();
+
+        let n = super::COMPILE_THRESHOLD as i64 * 3;
+        let mut jc = JavaCall::new_with_args(sum.clone(), vec![Oop::new_int(n as i32)]);
+        let area = DataArea::new(1);
+        jc.invoke(Some(&area), true);
+        assert!(!thread::is_meet_ex());
+        assert_eq!(area.stack.borrow_mut().pop_long(), n * (n - 1) / 2);
+
+        //entered at the loop head, pc 4
+        let osr = sum.jit.osr.lock().unwrap();
+        assert!(matches!(osr.get(&4), Some(code) if *code != 0));
+        drop(osr);
+
+        let jt = runtime::thread::current_java_thread();
+        assert!(jt.read().unwrap().frames.is_empty());
+        detach();
+    }
+}
@@ -56,6 +56,15 @@ impl Local {
         }
     }
 
+    //the int or long of the slot, for the compiled code
+    pub fn get_i64(&self, pos: usize) -> Option<i64> {
+        match self.locals.get(pos) {
+            Some(Slot::I32(v)) => Some(*v as i64),
+            Some(Slot::I64(v)) => Some(*v),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn get_float(&self, pos: usize) -> f32 {
         if let Slot::F32(v) = self.locals.get(pos).unwrap() {
@@ -1,27 +1,34 @@
 use crate::native::JNINativeMethod;
 use crate::oop::{self, ValueType};
+use crate::runtime::jit;
 use crate::runtime::local::Local;
+use crate::runtime::method_handle;
+use crate::runtime::quick::QuickTable;
 use crate::runtime::stack::Stack;
-use crate::runtime::{self, require_class2};
+use crate::runtime::{self, require_class2, ClassLoader};
 use crate::types::ClassRef;
 use crate::types::*;
 use crate::util::PATH_SEP;
 use crate::{native, util};
 use class_parser::MethodSignature;
 use classfile::{
-    attributes::Code, attributes::LineNumber, constant_pool, consts, flags::*, AttributeType,
-    BytesRef, ConstantPool, FieldInfo, MethodInfo, U2,
+    attributes::Code, attributes::LineNumber, attributes::MethodParameter, constant_pool, consts,
+    flags::*, AttributeType, BytesRef, ConstantPool, FieldInfo, MethodInfo, U2,
 };
 use std::fmt;
 use std::fmt::Formatter;
 use std::ops::Deref;
 use std::sync::Arc;
 
-pub fn get_method_ref(cp: &ConstantPool, idx: usize) -> Result<MethodIdRef, ()> {
+pub fn get_method_ref(
+    class_loader: Option<ClassLoader>,
+    cp: &ConstantPool,
+    idx: usize,
+) -> Result<MethodIdRef, ()> {
     let (tag, class_index, name_and_type_index) = constant_pool::get_method_ref(cp, idx);
 
     //load Method's Class, then init it
-    let class = require_class2(class_index, cp).unwrap_or_else(|| {
+    let class = require_class2(class_loader, class_index, cp).unwrap_or_else(|| {
         panic!(
             "Unknown method class {:?}",
             cp.get(class_index as usize)
@@ -44,7 +51,12 @@ pub fn get_method_ref(cp: &ConstantPool, idx: usize) -> Result<MethodIdRef, ()>
     );
     if tag == consts::CONSTANT_METHOD_REF_TAG {
         // invokespecial, invokestatic and invokevirtual
-        class.get_class_method(name, desc)
+        // JVM spec, 5.4.3.3, then the methods of the super interfaces, such as the default methods
+        // the signature polymorphic methods of MethodHandle are linked for the descriptor
+        class
+            .get_class_method(name, desc)
+            .or_else(|_| class.get_interface_method(name, desc))
+            .or_else(|_| method_handle::find_polymorphic_method(class, name, desc, class_loader))
     } else {
         // invokeinterface
         class.get_interface_method(name, desc)
@@ -56,6 +68,8 @@ pub struct MethodId {
     pub offset: usize,
     pub method: Method,
     pub native_impl: Option<JNINativeMethod>,
+    pub jit: jit::JitState,
+    pub quick: QuickTable,
 }
 
 impl MethodId {
@@ -69,10 +83,14 @@ impl MethodId {
             None
         };
 
+        let quick = QuickTable::new(method.code.as_ref().map_or(0, |code| code.code.len()));
+
         Arc::new(Self {
             offset,
             method,
             native_impl,
+            jit: jit::JitState::default(),
+            quick,
         })
     }
 }
@@ -155,7 +173,8 @@ impl Method {
                         return Some(e.handler_pc);
                     }
 
-                    if let Some(class) = runtime::require_class2(e.catch_type, cp) {
+                    let class_loader = self.class.get_class().class_loader;
+                    if let Some(class) = runtime::require_class2(class_loader, e.catch_type, cp) {
                         if runtime::cmp::instance_of(ex.clone(), class) {
                             return Some(e.handler_pc);
                         }
@@ -203,6 +222,40 @@ impl Method {
         util::attributes::assemble_annotation_default(&method_info.attrs)
     }
 
+    //the generic signature, none if the method is not generic
+    pub fn get_signature(&self) -> Option<BytesRef> {
+        let method_info = self.class_file.methods.get(self.method_info_index).unwrap();
+        let idx = util::attributes::get_signature(&method_info.attrs);
+        if idx != 0 {
+            Some(constant_pool::get_utf8(&self.class_file.cp, idx as usize).clone())
+        } else {
+            None
+        }
+    }
+
+    //the cp indexes of the classes in the throws clause
+    pub fn get_exceptions(&self) -> Vec<U2> {
+        let method_info = self.class_file.methods.get(self.method_info_index).unwrap();
+        for it in method_info.attrs.iter() {
+            if let AttributeType::Exceptions { exceptions } = it {
+                return exceptions.clone();
+            }
+        }
+
+        vec![]
+    }
+
+    pub fn get_parameters(&self) -> Option<Vec<MethodParameter>> {
+        let method_info = self.class_file.methods.get(self.method_info_index).unwrap();
+        for it in method_info.attrs.iter() {
+            if let AttributeType::MethodParameters { parameters } = it {
+                return Some(parameters.clone());
+            }
+        }
+
+        None
+    }
+
     pub fn check_annotation(&self, name: &[u8]) -> bool {
         let method_info = self.class_file.methods.get(self.method_info_index).unwrap();
 
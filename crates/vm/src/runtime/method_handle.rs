@@ -0,0 +1,470 @@
+use crate::native::common::io::{get_field, put_field};
+use crate::native::{self, JNIEnv, JNINativeMethod, JNIResult};
+use crate::oop::{self, Class, Oop, OopPtr, RefKind};
+use crate::runtime::invoke_dynamic::{call, call_static, resolve_method_type};
+use crate::runtime::method::MethodId;
+use crate::runtime::quick::QuickTable;
+use crate::runtime::{exception, jit, thread, ClassLoader};
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use crate::{new_br, util};
+use class_parser::MethodSignature;
+use classfile::{consts as cls_const, BytesRef};
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+/*
+java.lang.invoke, the VM side
+
+A MemberName names a method, a constructor or a field by clazz, name, type &
+flags; there is no injected vmtarget as HotSpot, the method is looked up by
+them, and cached by the MemberName object once it is invoked.
+
+The signature polymorphic methods of MethodHandle are linked per descriptor
+of the call site, as a native method with the descriptor:
+  invokeBasic, calls the LambdaForm of the handle, form.vmentry
+  linkToStatic, linkToSpecial, linkToVirtual & linkToInterface, call the
+  MemberName passed as the last argument
+  invokeExact & invoke, call the invoker linked by MethodHandleNatives.linkMethod,
+  with the appendix appended to the arguments
+*/
+
+//MethodHandleNatives.Constants
+pub const MN_IS_METHOD: i32 = 0x0001_0000;
+pub const MN_IS_CONSTRUCTOR: i32 = 0x0002_0000;
+pub const MN_IS_FIELD: i32 = 0x0004_0000;
+pub const MN_CALLER_SENSITIVE: i32 = 0x0010_0000;
+pub const MN_REFERENCE_KIND_SHIFT: i32 = 24;
+pub const MN_REFERENCE_KIND_MASK: i32 = 0x0F;
+pub const MN_SEARCH_SUPERCLASSES: i32 = 0x0010_0000;
+pub const MN_SEARCH_INTERFACES: i32 = 0x0020_0000;
+
+pub const REF_GET_FIELD: i32 = 1;
+pub const REF_GET_STATIC: i32 = 2;
+pub const REF_PUT_FIELD: i32 = 3;
+pub const REF_PUT_STATIC: i32 = 4;
+pub const REF_INVOKE_VIRTUAL: i32 = 5;
+pub const REF_INVOKE_STATIC: i32 = 6;
+pub const REF_INVOKE_SPECIAL: i32 = 7;
+pub const REF_NEW_INVOKE_SPECIAL: i32 = 8;
+pub const REF_INVOKE_INTERFACE: i32 = 9;
+
+//the descriptor declared by the signature polymorphic methods
+const POLYMORPHIC_DESC: &str = "([Ljava/lang/Object;)Ljava/lang/Object;";
+
+//(name, desc, class loader)
+type PolymorphicKey = (BytesRef, BytesRef, Option<ClassLoader>);
+
+lazy_static! {
+    static ref POLYMORPHIC_METHODS: Mutex<FxHashMap<PolymorphicKey, MethodIdRef>> =
+        Mutex::new(FxHashMap::default());
+    //the methods of the MemberName objects invoked, by the address of the object,
+    //the weak reference keeps the address from being reused
+    static ref VM_TARGETS: Mutex<FxHashMap<usize, (Weak<OopPtr>, MethodIdRef)>> =
+        Mutex::new(FxHashMap::default());
+}
+
+pub fn is_signature_polymorphic(cls_name: &[u8], name: &[u8]) -> bool {
+    cls_name == cls_const::J_METHODHANDLE && polymorphic_name(name).is_some()
+}
+
+/// The signature polymorphic method 'name' of MethodHandle, linked for 'desc'
+///
+/// Err if 'class' is not MethodHandle, or 'name' is not signature polymorphic.
+/// The MethodType of invokeExact & invoke is made by 'class_loader', the
+/// loader of the caller.
+pub(crate) fn find_polymorphic_method(
+    class: &Class,
+    name: &BytesRef,
+    desc: &BytesRef,
+    class_loader: Option<ClassLoader>,
+) -> Result<MethodIdRef, ()> {
+    if !is_signature_polymorphic(class.name.as_slice(), name.as_slice()) {
+        return Err(());
+    }
+
+    let k = (name.clone(), desc.clone(), class_loader);
+    let mut methods = POLYMORPHIC_METHODS.lock().unwrap();
+    if let Some(mir) = methods.get(&k) {
+        return Ok(mir.clone());
+    }
+
+    let decl = class.get_this_class_method(name, &new_br(POLYMORPHIC_DESC))?;
+    let mut method = decl.method.clone();
+    method.desc = desc.clone();
+    method.signature = MethodSignature::new(desc.as_slice());
+    let native_impl = new_polymorphic_native(name.as_slice(), desc.clone(), class_loader);
+    let mir = Arc::new(MethodId {
+        offset: decl.offset,
+        method,
+        native_impl: Some(native_impl),
+        jit: jit::JitState::default(),
+        quick: QuickTable::new(0),
+    });
+    methods.insert(k, mir.clone());
+
+    Ok(mir)
+}
+
+fn polymorphic_name(name: &[u8]) -> Option<&'static str> {
+    match name {
+        b"invokeExact" => Some("invokeExact"),
+        b"invoke" => Some("invoke"),
+        b"invokeBasic" => Some("invokeBasic"),
+        b"linkToVirtual" => Some("linkToVirtual"),
+        b"linkToStatic" => Some("linkToStatic"),
+        b"linkToSpecial" => Some("linkToSpecial"),
+        b"linkToInterface" => Some("linkToInterface"),
+        _ => None,
+    }
+}
+
+fn new_polymorphic_native(
+    name: &[u8],
+    desc: BytesRef,
+    class_loader: Option<ClassLoader>,
+) -> JNINativeMethod {
+    let fn_name = polymorphic_name(name).unwrap();
+    match name {
+        b"invokeBasic" => native::new_fn(fn_name, POLYMORPHIC_DESC, Box::new(jvm_invokeBasic)),
+        b"linkToStatic" | b"linkToSpecial" => {
+            native::new_fn(fn_name, POLYMORPHIC_DESC, Box::new(jvm_linkToStatic))
+        }
+        b"linkToVirtual" | b"linkToInterface" => {
+            native::new_fn(fn_name, POLYMORPHIC_DESC, Box::new(jvm_linkToVirtual))
+        }
+        _ => {
+            //the invoker & the appendix, linked by the first call
+            let linked: Mutex<Option<(MethodIdRef, Oop)>> = Mutex::new(None);
+            let fnptr = move |_env: JNIEnv, args: &[Oop]| -> JNIResult {
+                let cached = { linked.lock().unwrap().clone() };
+                let (invoker, appendix) = match cached {
+                    Some(v) => v,
+                    None => match link_invoker(fn_name, &desc, class_loader)? {
+                        Some(v) => {
+                            *linked.lock().unwrap() = Some(v.clone());
+                            v
+                        }
+                        None => return Ok(None),
+                    },
+                };
+
+                let mut args = args.to_vec();
+                args.push(appendix);
+                Ok(call(invoker, args, true))
+            };
+            native::new_fn(fn_name, POLYMORPHIC_DESC, Box::new(fnptr))
+        }
+    }
+}
+
+//the handle & the arguments are passed to the LambdaForm
+#[allow(non_snake_case)]
+fn jvm_invokeBasic(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mh = args.first().unwrap();
+    let form = get_field(mh, "form", "Ljava/lang/invoke/LambdaForm;");
+    let vmentry = get_field(&form, "vmentry", "Ljava/lang/invoke/MemberName;");
+    let target = vmtarget(&vmentry)?;
+    Ok(call(target, args.to_vec(), true))
+}
+
+#[allow(non_snake_case)]
+fn jvm_linkToStatic(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let (mn, args) = args.split_last().unwrap();
+    let target = vmtarget(mn)?;
+    Ok(call(target, args.to_vec(), true))
+}
+
+//the method is selected by the class of the receiver
+#[allow(non_snake_case)]
+fn jvm_linkToVirtual(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let (mn, args) = args.split_last().unwrap();
+    if args.first().is_none_or(|it| it.is_null()) {
+        return Err(exception::new(cls_const::J_NPE, None));
+    }
+
+    let target = vmtarget(mn)?;
+    Ok(call(target, args.to_vec(), false))
+}
+
+//MethodHandleNatives.linkMethod, None with the exception pending
+fn link_invoker(
+    name: &str,
+    desc: &BytesRef,
+    class_loader: Option<ClassLoader>,
+) -> Result<Option<(MethodIdRef, Oop)>, Oop> {
+    let mh_cls = runtime_class(cls_const::J_METHODHANDLE);
+    let mtype = match resolve_method_type(class_loader, desc.as_slice()) {
+        Some(mtype) => mtype,
+        None => return Ok(None),
+    };
+    let ary_cls = runtime_class(b"[Ljava/lang/Object;");
+    let appendix = Oop::new_ref_ary(ary_cls, 1);
+
+    let args = vec![
+        caller_mirror(),
+        Oop::new_int(REF_INVOKE_VIRTUAL),
+        mh_cls.get_class().get_mirror(),
+        util::oop::new_java_lang_string2(name),
+        mtype,
+        appendix.clone(),
+    ];
+    let invoker = match call_static(
+        b"java/lang/invoke/MethodHandleNatives",
+        "linkMethod",
+        "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+        args,
+    ) {
+        Some(invoker) => invoker,
+        None => return Ok(None),
+    };
+
+    let invoker = vmtarget(&invoker)?;
+    let appendix = {
+        let rf = appendix.extract_ref();
+        let ary = rf.extract_array();
+        ary.get(0)
+    };
+    Ok(Some((invoker, appendix)))
+}
+
+//the class of the frame below the native frame of the polymorphic method
+fn caller_mirror() -> Oop {
+    let jt = thread::current_java_thread();
+    let frame = { jt.read().unwrap().frames.iter().rev().nth(1).cloned() };
+    match frame {
+        Some(frame) => {
+            let frame = frame.try_read().unwrap();
+            let cls = frame.class.get_class();
+            cls.get_mirror()
+        }
+        None => runtime_class(cls_const::J_METHODHANDLE)
+            .get_class()
+            .get_mirror(),
+    }
+}
+
+//the method of a MemberName to invoke, cached by the object
+fn vmtarget(mn: &Oop) -> Result<MethodIdRef, Oop> {
+    let rf = match mn {
+        Oop::Ref(rf) => rf.clone(),
+        _ => return Err(exception::new(cls_const::J_NPE, None)),
+    };
+    let k = Arc::as_ptr(&rf) as usize;
+    if let Some((_, mir)) = VM_TARGETS.lock().unwrap().get(&k) {
+        return Ok(mir.clone());
+    }
+
+    let mir = member_method(mn, None)?;
+    let mut targets = VM_TARGETS.lock().unwrap();
+    //drop the dead objects, before it grows
+    if targets.len() == targets.capacity() {
+        targets.retain(|_, (obj, _)| obj.strong_count() > 0);
+    }
+    targets.insert(k, (Arc::downgrade(&rf), mir.clone()));
+
+    Ok(mir)
+}
+
+/// The method or constructor named by a MemberName
+///
+/// The signature polymorphic methods are linked with 'class_loader'.
+pub fn member_method(mn: &Oop, class_loader: Option<ClassLoader>) -> Result<MethodIdRef, Oop> {
+    let cls = member_class(mn)?;
+    let name = member_name(mn)?;
+    let desc = member_desc(mn)?;
+    let ref_kind = member_ref_kind(mn);
+
+    let class = cls.get_class();
+    let mir = if name.as_slice() == b"<init>" {
+        class.get_this_class_method(&name, &desc)
+    } else if ref_kind == REF_INVOKE_INTERFACE {
+        class.get_interface_method(&name, &desc)
+    } else {
+        class
+            .get_class_method(&name, &desc)
+            .or_else(|_| class.get_interface_method(&name, &desc))
+            .or_else(|_| find_polymorphic_method(class, &name, &desc, class_loader))
+    };
+
+    mir.map_err(|_| {
+        let msg = format!(
+            "{}.{}{}",
+            String::from_utf8_lossy(class.name.as_slice()).replace("/", "."),
+            String::from_utf8_lossy(name.as_slice()),
+            String::from_utf8_lossy(desc.as_slice())
+        );
+        exception::new(cls_const::J_NO_SUCH_METHOD_ERROR, Some(msg))
+    })
+}
+
+/// The field named by a MemberName, static for REF_getStatic & REF_putStatic
+pub fn member_field(mn: &Oop) -> Result<FieldIdRef, Oop> {
+    let cls = member_class(mn)?;
+    let name = member_name(mn)?;
+    let desc = member_desc(mn)?;
+    let is_static = matches!(member_ref_kind(mn), REF_GET_STATIC | REF_PUT_STATIC);
+
+    let class = cls.get_class();
+    class.find_field_id(&name, &desc, is_static).ok_or_else(|| {
+        let msg = String::from_utf8_lossy(name.as_slice()).to_string();
+        exception::new(cls_const::J_NO_SUCH_FIELD_ERROR, Some(msg))
+    })
+}
+
+pub fn member_flags(mn: &Oop) -> i32 {
+    get_field(mn, "flags", "I").extract_int()
+}
+
+pub fn member_ref_kind(mn: &Oop) -> i32 {
+    (member_flags(mn) >> MN_REFERENCE_KIND_SHIFT) & MN_REFERENCE_KIND_MASK
+}
+
+/// MemberName.clazz & flags
+pub fn set_member(mn: &Oop, cls: &ClassRef, flags: i32) {
+    let mirror = cls.get_class().get_mirror();
+    put_field(mn, "clazz", "Ljava/lang/Class;", mirror);
+    put_field(mn, "flags", "I", Oop::new_int(flags));
+}
+
+/// MemberName.name & type, the type is the descriptor
+pub fn set_member_name_type(mn: &Oop, name: &[u8], desc: &[u8]) {
+    let name = util::oop::new_java_lang_string3(name);
+    let desc = util::oop::new_java_lang_string3(desc);
+    put_field(mn, "name", "Ljava/lang/String;", name);
+    put_field(mn, "type", "Ljava/lang/Object;", desc);
+}
+
+/// The flags of a resolved method, the modifiers, the kind & the reference kind
+pub fn method_flags(mir: &MethodIdRef, ref_kind: i32) -> i32 {
+    let method = &mir.method;
+    let kind = if method.name.as_slice() == b"<init>" {
+        MN_IS_CONSTRUCTOR
+    } else {
+        MN_IS_METHOD
+    };
+
+    let mut flags = method.acc_flags as i32 | kind | (ref_kind << MN_REFERENCE_KIND_SHIFT);
+    if method.check_annotation(b"Lsun/reflect/CallerSensitive;") {
+        flags |= MN_CALLER_SENSITIVE;
+    }
+
+    flags
+}
+
+pub fn field_flags(fid: &FieldIdRef, ref_kind: i32) -> i32 {
+    fid.field.acc_flags as i32 | MN_IS_FIELD | (ref_kind << MN_REFERENCE_KIND_SHIFT)
+}
+
+/// The reference kind to invoke a method, as it is invoked by the bytecode
+pub fn method_ref_kind(mir: &MethodIdRef) -> i32 {
+    let method = &mir.method;
+    if method.name.as_slice() == b"<init>" {
+        REF_INVOKE_SPECIAL
+    } else if method.is_static() {
+        REF_INVOKE_STATIC
+    } else if method.class.get_class().is_interface() {
+        REF_INVOKE_INTERFACE
+    } else if oop::class::dispatch_index(mir).is_some() {
+        REF_INVOKE_VIRTUAL
+    } else {
+        REF_INVOKE_SPECIAL
+    }
+}
+
+fn member_class(mn: &Oop) -> Result<ClassRef, Oop> {
+    let clazz = get_field(mn, "clazz", "Ljava/lang/Class;");
+    match clazz {
+        Oop::Null => Err(exception::new(
+            cls_const::J_ILLEGAL_ARGUMENT,
+            Some("nothing to resolve".to_string()),
+        )),
+        clazz => {
+            let rf = clazz.extract_ref();
+            let mirror = rf.extract_mirror();
+            mirror.target.clone().ok_or_else(|| {
+                exception::new(cls_const::J_ILLEGAL_ARGUMENT, Some("primitive".to_string()))
+            })
+        }
+    }
+}
+
+fn member_name(mn: &Oop) -> Result<BytesRef, Oop> {
+    match get_field(mn, "name", "Ljava/lang/String;") {
+        Oop::Null => Err(exception::new(
+            cls_const::J_ILLEGAL_ARGUMENT,
+            Some("nothing to resolve".to_string()),
+        )),
+        name => Ok(new_br(&OopPtr::java_lang_string(name.extract_ref()))),
+    }
+}
+
+//MemberName.type: a MethodType, a descriptor, {rtype, ptypes} or the class of a field
+fn member_desc(mn: &Oop) -> Result<BytesRef, Oop> {
+    let typ = get_field(mn, "type", "Ljava/lang/Object;");
+    let rf = match &typ {
+        Oop::Ref(rf) => rf.clone(),
+        _ => {
+            return Err(exception::new(
+                cls_const::J_ILLEGAL_ARGUMENT,
+                Some("nothing to resolve".to_string()),
+            ))
+        }
+    };
+
+    let mut desc = Vec::new();
+    let ptr = rf.get_raw_ptr();
+    unsafe {
+        match &(*ptr).v {
+            RefKind::Mirror(_) => class_desc(&typ, &mut desc),
+            RefKind::Array(ary) => {
+                method_desc(&ary.get(0), &ary.get(1), &mut desc);
+            }
+            RefKind::Inst(_) if OopPtr::is_java_lang_string(rf.clone()) => {
+                desc.extend_from_slice(OopPtr::java_lang_string(rf.clone()).as_bytes());
+            }
+            _ => {
+                let rtype = get_field(&typ, "rtype", "Ljava/lang/Class;");
+                let ptypes = get_field(&typ, "ptypes", "[Ljava/lang/Class;");
+                method_desc(&rtype, &ptypes, &mut desc);
+            }
+        }
+    }
+
+    Ok(Arc::new(desc))
+}
+
+fn method_desc(rtype: &Oop, ptypes: &Oop, out: &mut Vec<u8>) {
+    out.push(b'(');
+    {
+        let rf = ptypes.extract_ref();
+        let ary = rf.extract_array();
+        for it in ary.iter() {
+            class_desc(&it, out);
+        }
+    }
+    out.push(b')');
+    class_desc(rtype, out);
+}
+
+//I, [I, Ljava/lang/String; of a class mirror
+fn class_desc(mirror: &Oop, out: &mut Vec<u8>) {
+    let rf = mirror.extract_ref();
+    let mirror = rf.extract_mirror();
+    match &mirror.target {
+        Some(cls) => {
+            let cls = cls.get_class();
+            if cls.is_array() {
+                out.extend_from_slice(cls.name.as_slice());
+            } else {
+                out.push(b'L');
+                out.extend_from_slice(cls.name.as_slice());
+                out.push(b';');
+            }
+        }
+        None => out.extend_from_slice(mirror.value_type.into()),
+    }
+}
+
+fn runtime_class(name: &[u8]) -> ClassRef {
+    crate::runtime::require_class3(None, name).unwrap()
+}
@@ -1,6 +1,9 @@
 #![allow(unused)]
 
-pub use class_loader::{require_class, require_class2, require_class3, ClassLoader};
+pub use class_loader::{
+    define_anonymous_class, define_class, require_class, require_class2, require_class3,
+    ClassLoader,
+};
 pub use class_path_manager::{
     add_path as add_class_path, add_paths as add_class_paths,
     find_class as find_class_in_classpath, ClassPathResult,
@@ -12,10 +15,11 @@ pub use frame::Frame;
 pub use interp::Interp;
 pub use invoke::JavaCall;
 pub use slot::Slot;
-pub use sys_dic::{find as sys_dic_find, put as sys_dic_put};
+pub use sys_dic::{find as sys_dic_find, put as sys_dic_put, remove as sys_dic_remove};
 pub use thread::JavaThread;
 
-mod class_loader;
+pub mod arguments;
+pub(crate) mod class_loader;
 mod class_path_manager;
 pub mod cmp;
 mod constant_pool;
@@ -23,11 +27,17 @@ mod consts;
 mod dataarea;
 pub mod exception;
 mod frame;
-mod init_vm;
+pub mod gc;
+pub mod init_vm;
 pub mod interp;
 pub mod invoke;
+pub mod invoke_dynamic;
+pub mod jit;
 mod local;
 pub mod method;
+pub mod method_handle;
+pub mod quick;
+pub mod reference;
 mod slot;
 mod stack;
 mod sys_dic;
@@ -0,0 +1,242 @@
+use crate::oop::class::DispatchIndex;
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/*
+Quickening
+
+The first execution of getfield, putfield, getstatic, putstatic and the
+invokes resolves the constant pool entry by ConstantPoolCache, then the
+resolved field or method is recorded in the QuickTable of the method, by the
+pc of the instruction. The next executions take it from the table, by index,
+no hashing, no RefCell.
+
+The code is shared by all the threads and never rewritten, the table is the
+quickened side of it: one slot per pc, allocated on the first quickening,
+installed by CAS, the first wins. The entries live as long as the method.
+
+The invokevirtual & invokeinterface sites keep the v_table or itable index of
+the resolved method, and a monomorphic inline cache: the class of the first
+receiver and the method selected for it. A site meeting another class
+becomes megamorphic, and selects by the index every time.
+*/
+
+pub enum Quick {
+    Field(FieldIdRef),
+    //invokestatic & invokespecial, nothing to select
+    Method(MethodIdRef),
+    //invokevirtual & invokeinterface, the resolved method, its slot & the cache
+    Virtual(MethodIdRef, DispatchIndex, InlineCache),
+}
+
+pub struct QuickTable {
+    code_len: usize,
+    slots: OnceLock<Box<[AtomicPtr<Quick>]>>,
+}
+
+impl QuickTable {
+    pub fn new(code_len: usize) -> Self {
+        Self {
+            code_len,
+            slots: OnceLock::new(),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, pc: usize) -> Option<&Quick> {
+        let slot = self.slots.get()?.get(pc)?;
+        let p = slot.load(Ordering::Acquire);
+        if p.is_null() {
+            None
+        } else {
+            Some(unsafe { &*p })
+        }
+    }
+
+    //the entry installed, by this thread or the first one
+    pub fn set(&self, pc: usize, v: Quick) -> &Quick {
+        let slots = self.slots.get_or_init(|| {
+            (0..self.code_len)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect()
+        });
+
+        let p = Box::into_raw(Box::new(v));
+        match slots[pc].compare_exchange(ptr::null_mut(), p, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => unsafe { &*p },
+            Err(cur) => {
+                let _ = unsafe { Box::from_raw(p) };
+                unsafe { &*cur }
+            }
+        }
+    }
+}
+
+//a cloned method is quickened again
+impl Clone for QuickTable {
+    fn clone(&self) -> Self {
+        Self::new(self.code_len)
+    }
+}
+
+impl Drop for QuickTable {
+    fn drop(&mut self) {
+        if let Some(slots) = self.slots.get_mut() {
+            for slot in slots.iter_mut() {
+                let p = *slot.get_mut();
+                if !p.is_null() {
+                    let _ = unsafe { Box::from_raw(p) };
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InlineCache {
+    entry: AtomicPtr<(ClassRef, MethodIdRef)>,
+    megamorphic: AtomicBool,
+}
+
+impl InlineCache {
+    //the method selected for the receiver class before
+    #[inline]
+    pub fn lookup(&self, cls: &ClassRef) -> Option<MethodIdRef> {
+        let p = self.entry.load(Ordering::Acquire);
+        if p.is_null() {
+            return None;
+        }
+
+        let (cached, mir) = unsafe { &*p };
+        if Arc::ptr_eq(cached, cls) {
+            Some(mir.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn update(&self, cls: ClassRef, mir: MethodIdRef) {
+        if self.megamorphic.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let p = Box::into_raw(Box::new((cls, mir)));
+        if self
+            .entry
+            .compare_exchange(ptr::null_mut(), p, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            let _ = unsafe { Box::from_raw(p) };
+            self.megamorphic.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for InlineCache {
+    fn drop(&mut self) {
+        let p = *self.entry.get_mut();
+        if !p.is_null() {
+            let _ = unsafe { Box::from_raw(p) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InlineCache, Quick, QuickTable};
+    use crate::oop::class::tests::{define, define_object, method};
+    use crate::oop::class::{dispatch_index, VTableEntry};
+    use crate::runtime::ClassLoader;
+    use crate::types::{ClassRef, MethodIdRef};
+    use classfile::flags::ACC_PUBLIC;
+    use std::sync::Arc;
+
+    //as JavaCall::invoke_cached, the cache first, then the index
+    fn select(ic: &InlineCache, cls: &ClassRef, mir: &MethodIdRef) -> (MethodIdRef, bool) {
+        if let Some(m) = ic.lookup(cls) {
+            return (m, true);
+        }
+
+        let index = dispatch_index(mir).unwrap();
+        match cls.get_class().select_method(&index) {
+            Some(VTableEntry::Method(m)) => {
+                ic.update(cls.clone(), m.clone());
+                (m.clone(), false)
+            }
+            _ => panic!("not selected"),
+        }
+    }
+
+    #[test]
+    fn t_quick_table() {
+        let l = ClassLoader::User(30000);
+        define_object(l);
+        let a = define(
+            l,
+            ACC_PUBLIC,
+            "q/A",
+            Some("java/lang/Object"),
+            &[],
+            &[(ACC_PUBLIC, "m", true), (ACC_PUBLIC, "n", true)],
+        );
+        let (m, n) = (method(&a, "m"), method(&a, "n"));
+
+        let table = QuickTable::new(8);
+        assert!(table.get(3).is_none());
+        table.set(3, Quick::Method(m.clone()));
+        //the first one wins
+        match table.set(3, Quick::Method(n)) {
+            Quick::Method(it) => assert!(Arc::ptr_eq(it, &m)),
+            _ => panic!("should be a method"),
+        }
+        assert!(table.get(2).is_none());
+        assert!(table.get(8).is_none());
+    }
+
+    //a site meeting another receiver class selects by the index again
+    #[test]
+    fn t_inline_cache() {
+        let l = ClassLoader::User(30001);
+        define_object(l);
+        let object = Some("java/lang/Object");
+        let a = define(
+            l,
+            ACC_PUBLIC,
+            "q/A",
+            object,
+            &[],
+            &[(ACC_PUBLIC, "m", true)],
+        );
+        let b = define(
+            l,
+            ACC_PUBLIC,
+            "q/B",
+            Some("q/A"),
+            &[],
+            &[(ACC_PUBLIC, "m", true)],
+        );
+        let c = define(l, ACC_PUBLIC, "q/C", Some("q/A"), &[], &[]);
+        let (a_m, b_m) = (method(&a, "m"), method(&b, "m"));
+
+        let ic = InlineCache::default();
+        assert!(ic.lookup(&a).is_none());
+        let (m, hit) = select(&ic, &a, &a_m);
+        assert!(Arc::ptr_eq(&m, &a_m) && !hit);
+        let (m, hit) = select(&ic, &a, &a_m);
+        assert!(Arc::ptr_eq(&m, &a_m) && hit);
+
+        //a miss, B.m selected, the site is megamorphic
+        let (m, hit) = select(&ic, &b, &a_m);
+        assert!(Arc::ptr_eq(&m, &b_m) && !hit);
+        let (m, hit) = select(&ic, &b, &a_m);
+        assert!(Arc::ptr_eq(&m, &b_m) && !hit);
+        let (m, hit) = select(&ic, &c, &a_m);
+        assert!(Arc::ptr_eq(&m, &a_m) && !hit);
+
+        //the first class still hits
+        let (m, hit) = select(&ic, &a, &a_m);
+        assert!(Arc::ptr_eq(&m, &a_m) && hit);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::new_br;
+use crate::oop::class::{self, State};
+use crate::oop::heap::ReferenceLayout;
+use crate::oop::{Class, ClassKind, Oop, RefKind};
+use crate::runtime::{self, require_class3, ClassLoader, JavaCall};
+use crate::types::ClassRef;
+use classfile::consts::{J_FINALIZER, J_REFERENCE, J_SOFT_REFERENCE};
+use std::time::SystemTime;
+
+/*
+The vm side of java.lang.ref
+
+Before collecting, SoftReference.clock is advanced and the layout of Reference
+is given to oop::heap. The references processed by gc are linked to
+Reference.pending by 'discovered', then ReferenceHandler is notified on
+Reference.lock, it enqueues them and runs the Cleaners. FinalizerThread
+takes the Finalizers from its queue and runs finalize().
+*/
+
+//a softly reachable referent not accessed for it is cleared
+const SOFT_REF_LRU_MS: i64 = 60 * 1000;
+
+//none if Reference is not loaded, no reference exists yet
+//the world is stopped, no class is loaded here
+pub fn prepare_gc() -> Option<ReferenceLayout> {
+    let cls = find_linked(J_REFERENCE)?;
+    let (referent, next) = {
+        let cls = cls.get_class();
+        let referent = cls.get_field_id(&new_br("referent"), &new_br("Ljava/lang/Object;"), false);
+        let next = cls.get_field_id(&new_br("next"), &new_br("Ljava/lang/ref/Reference;"), false);
+        (referent.offset, next.offset)
+    };
+
+    let now = current_millis();
+    let timestamp = find_linked(J_SOFT_REFERENCE).map(|cls| {
+        let cls = cls.get_mut_class();
+        let clock = cls.get_field_id(&new_br("clock"), &new_br("J"), true);
+        cls.put_static_field_value(clock, Oop::new_long(now));
+        cls.get_field_id(&new_br("timestamp"), &new_br("J"), false)
+            .offset
+    });
+
+    Some(ReferenceLayout {
+        referent,
+        next,
+        timestamp,
+        soft_expired: now - SOFT_REF_LRU_MS,
+    })
+}
+
+//the world is resumed, Reference.lock may be held by ReferenceHandler
+pub fn enqueue_pending(refs: Vec<Oop>) {
+    if refs.is_empty() {
+        return;
+    }
+
+    let cls = require_class3(None, J_REFERENCE).unwrap();
+    let cls = cls.get_mut_class();
+    let lock = cls.get_field_id(
+        &new_br("lock"),
+        &new_br("Ljava/lang/ref/Reference$Lock;"),
+        true,
+    );
+    let lock = cls.get_static_field_value(lock);
+    let pending = cls.get_field_id(
+        &new_br("pending"),
+        &new_br("Ljava/lang/ref/Reference;"),
+        true,
+    );
+    let discovered = cls.get_field_id(
+        &new_br("discovered"),
+        &new_br("Ljava/lang/ref/Reference;"),
+        false,
+    );
+
+    if let Oop::Ref(lock) = &lock {
+        lock.monitor_enter();
+    }
+
+    for rf in refs {
+        let next = cls.get_static_field_value(pending.clone());
+        Class::put_field_value(rf.extract_ref(), discovered.clone(), next);
+        cls.put_static_field_value(pending.clone(), rf);
+    }
+
+    if let Oop::Ref(lock) = &lock {
+        lock.notify_all();
+        lock.monitor_exit();
+    }
+}
+
+//as hotspot RegisterFinalizersAtInit, called when Object.<init> returns
+pub fn register_finalizer(obj: &Oop) {
+    let has_finalizer = match obj {
+        Oop::Ref(rf) => unsafe {
+            match &(*rf.get_raw_ptr()).v {
+                RefKind::Inst(inst) => match &inst.class.get_class().kind {
+                    ClassKind::Instance(cls) => cls.has_finalizer,
+                    _ => false,
+                },
+                _ => false,
+            }
+        },
+        _ => false,
+    };
+
+    if has_finalizer {
+        let cls = class::load_and_init(J_FINALIZER);
+        let mir = {
+            let cls = cls.get_class();
+            cls.get_static_method(&new_br("register"), &new_br("(Ljava/lang/Object;)V"))
+                .unwrap()
+        };
+        let mut jc = JavaCall::new_with_args(mir, vec![obj.clone()]);
+        jc.invoke(None, true);
+    }
+}
+
+fn find_linked(name: &[u8]) -> Option<ClassRef> {
+    runtime::sys_dic_find(ClassLoader::Bootstrap, name)
+        .filter(|cls| cls.get_class().get_class_state() >= State::Linked)
+}
+
+fn current_millis() -> i64 {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(n) => n.as_millis() as i64,
+        Err(_) => 0,
+    }
+}
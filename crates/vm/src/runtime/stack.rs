@@ -196,6 +196,20 @@ impl Stack {
         let _ = self.inner.pop();
     }
 
+    pub fn oops(&self) -> Vec<Oop> {
+        self.inner
+            .iter()
+            .filter_map(|it| match it {
+                Slot::Ref(v) => Some(v.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     pub fn clear(&mut self) {
         self.inner.clear();
     }
@@ -1,30 +1,63 @@
+use crate::runtime::ClassLoader;
 use crate::types::ClassRef;
 use crate::util;
 
 use rustc_hash::FxHashMap;
 use std::sync::{Arc, Mutex};
 
-type SystemDictionary = Mutex<FxHashMap<String, ClassRef>>;
+/*
+The classes of each initiating loader, keyed by name
+
+A class is put under its defining loader when defined, and under the loaders
+which get it by delegation, so the same name may be defined by different
+loaders.
+*/
+type SystemDictionary = Mutex<FxHashMap<ClassLoader, FxHashMap<String, ClassRef>>>;
 
 lazy_static! {
     static ref SYS_DIC: SystemDictionary = { Mutex::new(FxHashMap::default()) };
 }
 
-pub fn put(key: &[u8], klass: ClassRef) {
+pub fn put(class_loader: ClassLoader, key: &[u8], klass: ClassRef) {
     debug_assert!(!key.contains(&b'.'));
 
     let key = Vec::from(key);
     let key = unsafe { String::from_utf8_unchecked(key) };
     let mut dict = SYS_DIC.lock().unwrap();
-    dict.insert(key, klass);
+    dict.entry(class_loader).or_default().insert(key, klass);
+}
+
+//the class failed to link, as the verifier constraints
+pub fn remove(class_loader: ClassLoader, key: &[u8]) {
+    let key = unsafe { std::str::from_utf8_unchecked(key) };
+    let mut dict = SYS_DIC.lock().unwrap();
+    if let Some(classes) = dict.get_mut(&class_loader) {
+        classes.remove(key);
+    }
 }
 
 //key style: "sun/security/provider/Sun"
-pub fn find(key: &[u8]) -> Option<ClassRef> {
+pub fn find(class_loader: ClassLoader, key: &[u8]) -> Option<ClassRef> {
     debug_assert!(!key.contains(&b'.'));
     let key = unsafe { std::str::from_utf8_unchecked(key) };
     let dict = SYS_DIC.lock().unwrap();
-    dict.get(key).cloned()
+    dict.get(&class_loader)
+        .and_then(|classes| classes.get(key))
+        .cloned()
+}
+
+//each class once, under its defining loader
+pub fn classes() -> Vec<ClassRef> {
+    let dict = SYS_DIC.lock().unwrap();
+    dict.iter()
+        .flat_map(|(class_loader, classes)| {
+            classes.values().filter(move |cls| {
+                let defining = cls.get_class().class_loader;
+                defining.unwrap_or(ClassLoader::Bootstrap) == *class_loader
+            })
+        })
+        .cloned()
+        .collect()
 }
 
 pub fn init() {
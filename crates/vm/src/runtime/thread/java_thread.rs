@@ -1,4 +1,6 @@
-use crate::oop::{self, consts, Oop};
+use crate::new_br;
+use crate::oop::{self, consts, Class, Oop};
+use crate::runtime::thread::Parker;
 use crate::types::{FrameRef, JavaThreadRef};
 use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -23,15 +25,43 @@ fn set_meet_ex(val: bool) {
     IS_MEET_EX.with(|v| v.store(val, Ordering::Relaxed));
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThreadState {
+    New,
+    Runnable,
+    Blocked,
+    Waiting,
+    TimedWaiting,
+    Terminated,
+}
+
+impl ThreadState {
+    //java.lang.Thread.threadStatus, made of the JVMTI thread state bits
+    fn java_thread_status(self) -> i32 {
+        match self {
+            ThreadState::New => 0,
+            ThreadState::Runnable => 0x0005,     //ALIVE | RUNNABLE
+            ThreadState::Blocked => 0x0401,      //ALIVE | BLOCKED_ON_MONITOR_ENTER
+            ThreadState::Waiting => 0x0091,      //ALIVE | WAITING | WAITING_INDEFINITELY
+            ThreadState::TimedWaiting => 0x00a1, //ALIVE | WAITING | WAITING_WITH_TIMEOUT
+            ThreadState::Terminated => 0x0002,
+        }
+    }
+}
+
 pub struct JavaThread {
     pub frames: Vec<FrameRef>,
-    in_safe_point: bool,
+    pub in_safe_point: bool,
 
     pub java_thread_obj: Option<Oop>,
     pub ex: Option<Oop>,
-    pub is_alive: bool,
+    pub state: ThreadState,
+    pub daemon: bool,
     pub eetop: i64,
 
+    //cloned out before blocking, the thread lock is not held while blocked
+    pub parker: Arc<Parker>,
+
     pub tag: String, //for debug
 }
 
@@ -44,8 +74,10 @@ impl JavaThread {
 
             java_thread_obj: None,
             ex: None,
-            is_alive: false,
+            state: ThreadState::New,
+            daemon: false,
             eetop,
+            parker: Arc::new(Parker::default()),
             tag,
         };
         Arc::new(RwLock::new(Box::new(t)))
@@ -58,6 +90,25 @@ impl JavaThread {
     pub fn set_java_thread_obj(&mut self, obj: Oop) {
         self.java_thread_obj = Some(obj);
     }
+
+    pub fn is_alive(&self) -> bool {
+        self.state != ThreadState::New && self.state != ThreadState::Terminated
+    }
+
+    //keep java.lang.Thread.threadStatus in sync, for Thread.getState
+    pub fn set_state(&mut self, state: ThreadState) {
+        self.state = state;
+
+        if let Some(obj) = &self.java_thread_obj {
+            let rf = obj.extract_ref();
+            let fid = {
+                let inst = rf.extract_inst();
+                let cls = inst.class.get_class();
+                cls.get_field_id(&new_br("threadStatus"), &new_br("I"), false)
+            };
+            Class::put_field_value(rf, fid, Oop::new_int(state.java_thread_status()));
+        }
+    }
 }
 
 //exception
@@ -1,8 +1,8 @@
 use crate::oop::{self, Class, Oop, OopPtr};
-use crate::runtime::thread::thread_pool;
-use crate::runtime::{self, init_vm, vm, DataArea, JavaCall, JavaThread};
+use crate::runtime::{self, init_vm, vm, ClassLoader, DataArea, JavaCall, JavaThread};
 use crate::types::{ClassRef, FrameRef, JavaThreadRef, MethodIdRef};
 use crate::{new_br, util};
+use classfile::consts::J_CLASS_LOADER;
 use std::borrow::Borrow;
 
 pub struct MainThread {
@@ -21,7 +21,7 @@ impl MainThread {
     }
 
     pub fn run(&mut self) {
-        let vm = vm::VM::new(3);
+        let vm = vm::VM::new();
 
         //attach 'main' thread
         vm.threads.attach_current_thread();
@@ -30,7 +30,21 @@ impl MainThread {
         init_vm::initialize_jvm();
         info!("init vm end");
 
-        let main_class = oop::class::load_and_init(self.class.as_bytes());
+        let jt = runtime::thread::current_java_thread();
+        let main_class = match self.load_main_class() {
+            Some(class) => {
+                oop::class::init_class(&class);
+                oop::class::init_class_fully(&class);
+                class
+            }
+            //VerifyError, ClassFormatError...
+            None if jt.read().unwrap().ex.is_some() => {
+                self.uncaught_ex_internal();
+                vm.threads.detach_current_thread();
+                return;
+            }
+            None => panic!("Error: Could not find or load main class {}", self.class),
+        };
 
         let mir = {
             let cls = main_class.get_class();
@@ -58,14 +72,11 @@ impl MainThread {
             cls.get_static_method(&new_br("main"), &new_br("([Ljava/lang/String;)V"))
         };
 
-        let jt = runtime::thread::current_java_thread();
         match mir {
             Ok(mir) => {
                 let args = self.build_main_arg();
                 let mut jc = JavaCall::new_with_args(mir, args);
-                jt.write().unwrap().is_alive = true;
                 jc.invoke(None, true);
-                jt.write().unwrap().is_alive = false;
             }
             _ => unreachable!("NotFound \"main\""),
         }
@@ -75,13 +86,39 @@ impl MainThread {
         }
 
         //detach main thread
-        vm.threads.detach_current_thread();
+        vm.threads.exit_current_thread();
 
         vm.threads.join_all();
     }
 }
 
 impl MainThread {
+    //by the application class loader, ClassLoader.getSystemClassLoader
+    fn load_main_class(&self) -> Option<ClassRef> {
+        let mir = {
+            let cls = runtime::require_class3(None, J_CLASS_LOADER).unwrap();
+            oop::class::init_class(&cls);
+            oop::class::init_class_fully(&cls);
+            let cls = cls.get_class();
+            cls.get_static_method(
+                &new_br("getSystemClassLoader"),
+                &new_br("()Ljava/lang/ClassLoader;"),
+            )
+            .unwrap()
+        };
+
+        let mut jc = JavaCall::new_with_args(mir, vec![]);
+        let area = DataArea::new(1);
+        jc.invoke(Some(&area), true);
+        if runtime::thread::is_meet_ex() {
+            return None;
+        }
+
+        let loader = area.stack.borrow_mut().pop_ref();
+        let class_loader = ClassLoader::from_oop(&loader);
+        runtime::require_class3(class_loader, self.class.as_bytes())
+    }
+
     fn build_main_arg(&self) -> Vec<Oop> {
         let args = self
             .args
@@ -1,18 +1,21 @@
 mod condvar;
 mod java_thread;
 mod main;
+mod monitor;
 mod mutex;
-mod thread_pool;
+mod parker;
 mod threads;
 
 pub use condvar::Condvar;
 pub use java_thread::current_java_thread;
 pub use java_thread::JavaThread;
+pub use java_thread::ThreadState;
 pub use java_thread::THREAD;
 pub use main::MainThread;
+pub use monitor::Monitor;
 pub use mutex::raw as mutex_raw;
 pub use mutex::ReentrantMutex;
-pub use thread_pool::ThreadPool;
+pub use parker::Parker;
 pub use threads::Threads;
 
 pub use java_thread::is_meet_ex;
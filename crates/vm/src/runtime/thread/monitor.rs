@@ -0,0 +1,416 @@
+use crate::runtime::gc;
+use crate::runtime::thread::{self, ThreadState};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/*
+The monitor of an object, a lock word in the object header, inflated lazily.
+
+  0                          unlocked
+  owner << 16 | count << 1   thin locked by 'owner', entered 'count' times
+  ptr | 1                    inflated, points to the ObjectMonitor
+
+The thin lock is taken by a CAS, nothing allocated. It's inflated to an
+ObjectMonitor on contention, on wait, or when the count overflows. The
+owner is recorded as an id, so the contending thread can inflate the lock
+on behalf of the owner. An inflated monitor lives as long as the object.
+
+The operations return false if the current thread is not the owner.
+
+Thread.interrupt wakes up the threads in wait without owning the monitor, it
+takes the lock of the ObjectMonitor only. A waiter checks its interrupt
+status under that lock before blocking, so the wakeup can't be lost. The
+other waiters wake up too, as spurious wakeups of Object.wait.
+*/
+const INFLATED: usize = 1;
+const COUNT_SHIFT: usize = 1;
+const COUNT_MAX: usize = 0x7fff;
+const OWNER_SHIFT: usize = 16;
+
+static NEXT_OWNER_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static OWNER_ID: usize = NEXT_OWNER_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Default)]
+pub struct Monitor {
+    word: AtomicUsize,
+}
+
+struct ObjectMonitor {
+    state: Mutex<OwnerState>,
+    //the threads blocked to enter
+    entry: Condvar,
+    //the threads in Object.wait
+    wait_set: Condvar,
+}
+
+struct OwnerState {
+    owner: usize,
+    count: usize,
+}
+
+impl Monitor {
+    pub fn enter(&self) {
+        let me = current_owner();
+        loop {
+            let word = self.word.load(Ordering::Acquire);
+            if word & INFLATED != 0 {
+                return self.inflated(word).enter(me);
+            }
+
+            let new = if word == 0 {
+                thin(me, 1)
+            } else if thin_owner(word) == me && thin_count(word) < COUNT_MAX {
+                thin(me, thin_count(word) + 1)
+            } else {
+                //contended or overflowed
+                self.inflate(word);
+                continue;
+            };
+
+            if self.cas(word, new) {
+                return;
+            }
+        }
+    }
+
+    pub fn exit(&self) -> bool {
+        let me = current_owner();
+        loop {
+            let word = self.word.load(Ordering::Acquire);
+            if word & INFLATED != 0 {
+                return self.inflated(word).exit(me);
+            }
+
+            if word == 0 || thin_owner(word) != me {
+                return false;
+            }
+
+            let count = thin_count(word);
+            let new = if count == 1 { 0 } else { thin(me, count - 1) };
+            if self.cas(word, new) {
+                return true;
+            }
+        }
+    }
+
+    pub fn is_owner(&self) -> bool {
+        let me = current_owner();
+        let word = self.word.load(Ordering::Acquire);
+        if word & INFLATED != 0 {
+            self.inflated(word).lock_state().owner == me
+        } else {
+            word != 0 && thin_owner(word) == me
+        }
+    }
+
+    //none timeout means forever
+    pub fn wait(&self, timeout: Option<Duration>) -> bool {
+        let me = current_owner();
+        loop {
+            let word = self.word.load(Ordering::Acquire);
+            if word & INFLATED != 0 {
+                return self.inflated(word).wait(me, timeout);
+            }
+
+            if word == 0 || thin_owner(word) != me {
+                return false;
+            }
+
+            self.inflate(word);
+        }
+    }
+
+    pub fn notify(&self) -> bool {
+        self.notify_inner(false)
+    }
+
+    pub fn notify_all(&self) -> bool {
+        self.notify_inner(true)
+    }
+
+    //for Thread.interrupt, the owner isn't checked
+    pub fn wake_waiters(&self) {
+        let word = self.word.load(Ordering::Acquire);
+        //no one waits on a thin lock, a waiter inflates it first
+        if word & INFLATED != 0 {
+            self.inflated(word).wake_waiters();
+        }
+    }
+
+    //no one waits on a thin lock
+    fn notify_inner(&self, all: bool) -> bool {
+        let me = current_owner();
+        let word = self.word.load(Ordering::Acquire);
+        if word & INFLATED != 0 {
+            self.inflated(word).notify(me, all)
+        } else {
+            word != 0 && thin_owner(word) == me
+        }
+    }
+
+    fn inflate(&self, word: usize) {
+        let (owner, count) = if word == 0 {
+            (0, 0)
+        } else {
+            (thin_owner(word), thin_count(word))
+        };
+
+        let monitor = Box::new(ObjectMonitor {
+            state: Mutex::new(OwnerState { owner, count }),
+            entry: Condvar::new(),
+            wait_set: Condvar::new(),
+        });
+        let ptr = Box::into_raw(monitor);
+
+        //someone else changed the word first, retry
+        if !self.cas(word, ptr as usize | INFLATED) {
+            let _ = unsafe { Box::from_raw(ptr) };
+        }
+    }
+
+    //the inflated monitor lives as long as the object
+    fn inflated(&self, word: usize) -> &ObjectMonitor {
+        unsafe { &*((word & !INFLATED) as *const ObjectMonitor) }
+    }
+
+    fn cas(&self, current: usize, new: usize) -> bool {
+        self.word
+            .compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        let word = *self.word.get_mut();
+        if word & INFLATED != 0 {
+            let _ = unsafe { Box::from_raw((word & !INFLATED) as *mut ObjectMonitor) };
+        }
+    }
+}
+
+impl ObjectMonitor {
+    fn lock_state(&self) -> MutexGuard<'_, OwnerState> {
+        self.state.lock().unwrap()
+    }
+
+    fn enter(&self, me: usize) {
+        let mut state = self.lock_state();
+        if state.owner == me {
+            state.count += 1;
+            return;
+        }
+
+        if state.owner == 0 {
+            state.owner = me;
+            state.count = 1;
+            return;
+        }
+
+        //contended, blocked in a safe region, gc needn't wait for the owner
+        let jt = thread::current_java_thread();
+        jt.write().unwrap().set_state(ThreadState::Blocked);
+        drop(state);
+        gc::enter_safe_region();
+
+        let mut state = self.lock_state();
+        while state.owner != 0 {
+            state = self.entry.wait(state).unwrap();
+        }
+        state.owner = me;
+        state.count = 1;
+        drop(state);
+
+        gc::leave_safe_region();
+        jt.write().unwrap().set_state(ThreadState::Runnable);
+    }
+
+    fn exit(&self, me: usize) -> bool {
+        let mut state = self.lock_state();
+        if state.owner != me {
+            return false;
+        }
+
+        state.count -= 1;
+        if state.count == 0 {
+            state.owner = 0;
+            self.entry.notify_one();
+        }
+        true
+    }
+
+    //the monitor is released entirely while waiting, and entered again after
+    fn wait(&self, me: usize, timeout: Option<Duration>) -> bool {
+        let parker = thread::current_java_thread().read().unwrap().parker.clone();
+        let mut state = self.lock_state();
+        if state.owner != me {
+            return false;
+        }
+
+        let count = state.count;
+        state.owner = 0;
+        state.count = 0;
+        self.entry.notify_one();
+
+        //interrupted before blocking, the interrupter has woken up the wait set already
+        if !parker.is_interrupted(false) {
+            state = match timeout {
+                Some(timeout) => self.wait_set.wait_timeout(state, timeout).unwrap().0,
+                None => self.wait_set.wait(state).unwrap(),
+            };
+        }
+
+        while state.owner != 0 {
+            state = self.entry.wait(state).unwrap();
+        }
+        state.owner = me;
+        state.count = count;
+        true
+    }
+
+    fn wake_waiters(&self) {
+        let _state = self.lock_state();
+        self.wait_set.notify_all();
+    }
+
+    fn notify(&self, me: usize, all: bool) -> bool {
+        let state = self.lock_state();
+        if state.owner != me {
+            return false;
+        }
+
+        if all {
+            self.wait_set.notify_all();
+        } else {
+            self.wait_set.notify_one();
+        }
+        true
+    }
+}
+
+fn current_owner() -> usize {
+    OWNER_ID.with(|v| *v)
+}
+
+fn thin(owner: usize, count: usize) -> usize {
+    owner << OWNER_SHIFT | count << COUNT_SHIFT
+}
+
+fn thin_owner(word: usize) -> usize {
+    word >> OWNER_SHIFT
+}
+
+fn thin_count(word: usize) -> usize {
+    (word >> COUNT_SHIFT) & COUNT_MAX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Monitor, COUNT_MAX, INFLATED};
+    use crate::oop::heap::tests::HEAP_TEST;
+    use crate::runtime::gc::tests::{attach, detach};
+    use crate::runtime::thread;
+    use std::sync::atomic::Ordering;
+    use std::sync::{mpsc, Arc};
+
+    fn is_inflated(m: &Monitor) -> bool {
+        m.word.load(Ordering::Relaxed) & INFLATED != 0
+    }
+
+    #[test]
+    fn t_thin_lock() {
+        let m = Monitor::default();
+        m.enter();
+        m.enter();
+        assert!(m.notify_all());
+        assert!(m.exit());
+        assert!(m.exit());
+        assert!(!m.exit());
+        assert!(!is_inflated(&m));
+    }
+
+    #[test]
+    fn t_inflate_overflow() {
+        let m = Monitor::default();
+        for _ in 0..=COUNT_MAX {
+            m.enter();
+        }
+        assert!(is_inflated(&m));
+        for _ in 0..=COUNT_MAX {
+            assert!(m.exit());
+        }
+        assert!(!m.is_owner());
+    }
+
+    #[test]
+    fn t_inflate_contended() {
+        let m = Arc::new(Monitor::default());
+        m.enter();
+
+        let other = {
+            let m = m.clone();
+            std::thread::spawn(move || {
+                m.enter();
+                m.is_owner() && m.exit()
+            })
+        };
+
+        while !is_inflated(&m) {
+            std::thread::yield_now();
+        }
+        assert!(m.is_owner());
+        assert!(m.exit());
+        assert!(other.join().unwrap());
+    }
+
+    //the waiter is woken up by the interrupter while another thread owns the monitor
+    #[test]
+    fn t_wake_waiters() {
+        let _lock = HEAP_TEST.lock().unwrap();
+        attach();
+
+        //interrupted before wait, it returns at once
+        let m = Arc::new(Monitor::default());
+        let parker = thread::current_java_thread().read().unwrap().parker.clone();
+        parker.interrupt();
+        m.enter();
+        assert!(m.wait(None));
+        assert!(parker.is_interrupted(true));
+        assert!(m.exit());
+
+        let (tx, rx) = mpsc::channel();
+        let waiter = {
+            let m = m.clone();
+            std::thread::spawn(move || {
+                attach();
+                let parker = thread::current_java_thread().read().unwrap().parker.clone();
+                m.enter();
+                tx.send(parker).unwrap();
+                m.wait(None);
+                let owner = m.is_owner() && m.exit();
+                detach();
+                owner
+            })
+        };
+
+        //entered when the waiter is in the wait set
+        let parker = rx.recv().unwrap();
+        m.enter();
+        let interrupter = {
+            let m = m.clone();
+            std::thread::spawn(move || {
+                parker.interrupt();
+                m.wake_waiters();
+            })
+        };
+        interrupter.join().unwrap();
+        assert!(m.exit());
+        assert!(waiter.join().unwrap());
+
+        detach();
+    }
+}
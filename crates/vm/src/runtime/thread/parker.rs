@@ -0,0 +1,143 @@
+use crate::oop::Oop;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/*
+Blocking support of a java thread: Thread.sleep, Unsafe.park/unpark and Object.wait.
+
+The interrupt status lives here, so setting it and waking up the blocked
+thread are done under the same lock, an interrupt can't be lost.
+*/
+#[derive(Default)]
+pub struct Parker {
+    state: Mutex<ParkerState>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct ParkerState {
+    permit: bool,
+    interrupted: bool,
+    //the object of Object.wait, notified by the interrupter
+    waiting_on: Option<Oop>,
+}
+
+//park & sleep
+impl Parker {
+    //return when unparked, interrupted or the deadline reached
+    pub fn park(&self, deadline: Option<Instant>) {
+        let mut state = self.state.lock().unwrap();
+        while !state.permit && !state.interrupted {
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    self.cond.wait_timeout(state, deadline - now).unwrap().0
+                }
+                None => self.cond.wait(state).unwrap(),
+            };
+        }
+        state.permit = false;
+    }
+
+    pub fn unpark(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.permit = true;
+        self.cond.notify_all();
+    }
+
+    //false if interrupted, the interrupt status is cleared
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now().checked_add(duration);
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.interrupted {
+                state.interrupted = false;
+                return false;
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return true;
+                    }
+                    self.cond.wait_timeout(state, deadline - now).unwrap().0
+                }
+                None => self.cond.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+//interrupt
+impl Parker {
+    //wake up the sleeping or parked thread, the object waited on is returned
+    pub fn interrupt(&self) -> Option<Oop> {
+        let mut state = self.state.lock().unwrap();
+        state.interrupted = true;
+        self.cond.notify_all();
+        state.waiting_on.clone()
+    }
+
+    pub fn is_interrupted(&self, clear: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let v = state.interrupted;
+        if clear {
+            state.interrupted = false;
+        }
+        v
+    }
+
+    //Object.wait, false if interrupted already, the interrupt status is cleared
+    pub fn begin_wait(&self, obj: Oop) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.interrupted {
+            state.interrupted = false;
+            false
+        } else {
+            state.waiting_on = Some(obj);
+            true
+        }
+    }
+
+    //false if interrupted while waiting, the interrupt status is cleared
+    pub fn end_wait(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.waiting_on = None;
+        !std::mem::replace(&mut state.interrupted, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parker;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn t_park_permit() {
+        let parker = Parker::default();
+        parker.unpark();
+        //the permit is consumed, return immediately
+        parker.park(None);
+        let start = Instant::now();
+        parker.park(Some(start + Duration::from_millis(10)));
+        assert!(start.elapsed() >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn t_interrupt_sleep() {
+        let parker = Arc::new(Parker::default());
+        let sleeper = {
+            let parker = parker.clone();
+            std::thread::spawn(move || parker.sleep(Duration::from_secs(60)))
+        };
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(parker.interrupt().is_none());
+        assert!(!sleeper.join().unwrap());
+        assert!(!parker.is_interrupted(false));
+    }
+}
@@ -1,21 +1,22 @@
-use crate::runtime;
-use crate::runtime::thread::ThreadPool;
+use crate::new_br;
+use crate::oop::Oop;
+use crate::runtime::thread::ThreadState;
+use crate::runtime::{self, require_class3, JavaCall};
 use crate::types::JavaThreadRef;
+use classfile::consts::J_THREAD;
 use std::borrow::Borrow;
-use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
 
 pub struct Threads {
-    pool: Mutex<ThreadPool>,
     threads: Mutex<Vec<JavaThreadRef>>,
     cond_join: Condvar,
     next_id: AtomicI64,
 }
 
 impl Threads {
-    pub fn new(thread_pool_count: usize) -> Threads {
+    pub fn new() -> Threads {
         Threads {
-            pool: Mutex::new(ThreadPool::new(thread_pool_count)),
             threads: Mutex::new(Vec::new()),
             cond_join: Condvar::new(),
             next_id: AtomicI64::new(1),
@@ -23,6 +24,12 @@ impl Threads {
     }
 }
 
+impl Default for Threads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Threads {
     pub fn next_id(&self) -> i64 {
         self.next_id.fetch_add(1, Ordering::SeqCst)
@@ -42,30 +49,103 @@ impl Threads {
 
     pub fn detach_current_thread(&self) {
         runtime::thread::THREAD.with(|thread| {
-            let mut threads = self.threads.lock().unwrap();
-            threads.retain(|elem| !Arc::ptr_eq(elem, &*thread.borrow()));
-            self.cond_join.notify_all();
+            self.detach_java_thread(&thread.borrow());
         });
     }
 
-    pub fn find_java_thread(&self, eetop: i64) -> Option<JavaThreadRef> {
+    pub fn detach_java_thread(&self, thread: &JavaThreadRef) {
         let mut threads = self.threads.lock().unwrap();
+        threads.retain(|elem| !Arc::ptr_eq(elem, thread));
+        self.cond_join.notify_all();
+    }
+
+    /*
+    The end of a java thread, as JavaThread::exit of hotspot:
+      the uncaught exception is dispatched, Thread.exit is called,
+      then the threads joining it are notified, and it's detached
+    */
+    pub fn exit_current_thread(&self) {
+        let jt = runtime::thread::current_java_thread();
+        let thread_obj = jt.read().unwrap().java_thread_obj.clone();
+
+        if let Some(obj) = &thread_obj {
+            if runtime::thread::is_meet_ex() {
+                let ex = jt.write().unwrap().take_ex().unwrap();
+                invoke_thread_method(
+                    "dispatchUncaughtException",
+                    "(Ljava/lang/Throwable;)V",
+                    vec![obj.clone(), ex],
+                );
+            }
+
+            invoke_thread_method("exit", "()V", vec![obj.clone()]);
+        }
+
+        jt.write().unwrap().set_state(ThreadState::Terminated);
+
+        //Thread.join waits on the thread object
+        if let Some(obj) = thread_obj {
+            let rf = obj.extract_ref();
+            rf.monitor_enter();
+            rf.notify_all();
+            rf.monitor_exit();
+        }
+
+        self.detach_current_thread();
+    }
+
+    pub fn java_threads(&self) -> Vec<JavaThreadRef> {
+        let threads = self.threads.lock().unwrap();
+        threads.clone()
+    }
+
+    pub fn find_java_thread(&self, eetop: i64) -> Option<JavaThreadRef> {
+        let threads = self.threads.lock().unwrap();
         threads
             .iter()
             .find(|t| t.read().unwrap().eetop == eetop)
             .cloned()
     }
 
+    //the vm exits when the last non-daemon thread ends
     pub fn join_all(&self) {
         let mut threads = self.threads.lock().unwrap();
 
-        while threads.len() > 0 {
+        while threads.iter().any(|t| !t.read().unwrap().daemon) {
             threads = self.cond_join.wait(threads).unwrap();
         }
     }
 
-    pub fn spawn_java_thread<F: FnOnce() + Send + 'static>(&self, f: F) {
-        let pool = self.pool.lock().unwrap();
-        pool.execute(f);
+    //each java thread runs on its own native thread, daemon threads are not joined
+    pub fn spawn_java_thread<F: FnOnce() + Send + 'static>(
+        &self,
+        name: String,
+        f: F,
+    ) -> std::io::Result<()> {
+        std::thread::Builder::new()
+            .name(name)
+            .stack_size(runtime::arguments::native_stack_size())
+            .spawn(f)
+            .map(|_| ())
+    }
+}
+
+//the exception thrown is discarded, the thread is ending
+fn invoke_thread_method(name: &str, desc: &str, args: Vec<Oop>) {
+    let cls = require_class3(None, J_THREAD).unwrap();
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_this_class_method(&new_br(name), &new_br(desc))
+    };
+
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, args);
+        jc.invoke(None, true);
+
+        if runtime::thread::is_meet_ex() {
+            let jt = runtime::thread::current_java_thread();
+            let _ = jt.write().unwrap().take_ex();
+            error!("Thread.{} failed", name);
+        }
     }
 }
@@ -20,9 +20,9 @@ pub struct VM {
 }
 
 impl VM {
-    pub fn new(thread_pool_count: usize) -> Box<VM> {
+    pub fn new() -> Box<VM> {
         let vm = Box::new(VM {
-            threads: Threads::new(thread_pool_count),
+            threads: Threads::new(),
         });
 
         set_vm(&vm);
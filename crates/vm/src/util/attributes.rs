@@ -1,72 +1,44 @@
-use classfile::{AttributeType, BytesRef};
+use classfile::AttributeType;
 
+//only the RuntimeVisible* attributes are reflected, the invisible ones are for the tools
 pub fn assemble_annotation(attrs: &[AttributeType]) -> Option<Vec<u8>> {
-    let mut vis = None;
-    let mut in_vis = None;
-
     for it in attrs.iter() {
-        match it {
-            AttributeType::RuntimeVisibleAnnotations { raw, .. } => {
-                vis = Some(raw.clone());
-            }
-            AttributeType::RuntimeInvisibleAnnotations { raw, .. } => {
-                in_vis = Some(raw.clone());
-            }
-            _ => (),
+        if let AttributeType::RuntimeVisibleAnnotations { raw, .. } = it {
+            return Some(raw.to_vec());
         }
     }
 
-    do_assemble(vis, in_vis)
+    None
 }
 
 pub fn assemble_param_annotation(attrs: &[AttributeType]) -> Option<Vec<u8>> {
-    let mut vis = None;
-    let mut in_vis = None;
-
     for it in attrs.iter() {
-        match it {
-            AttributeType::RuntimeVisibleParameterAnnotations { raw, .. } => {
-                vis = Some(raw.clone());
-            }
-            AttributeType::RuntimeInvisibleParameterAnnotations { raw, .. } => {
-                in_vis = Some(raw.clone());
-            }
-            _ => (),
+        if let AttributeType::RuntimeVisibleParameterAnnotations { raw, .. } = it {
+            return Some(raw.to_vec());
         }
     }
 
-    do_assemble(vis, in_vis)
+    None
 }
 
 pub fn assemble_type_annotation(attrs: &[AttributeType]) -> Option<Vec<u8>> {
-    let mut vis = None;
-    let mut in_vis = None;
-
     for it in attrs.iter() {
-        match it {
-            AttributeType::RuntimeVisibleTypeAnnotations { raw, .. } => {
-                vis = Some(raw.clone());
-            }
-            AttributeType::RuntimeInvisibleTypeAnnotations { raw, .. } => {
-                in_vis = Some(raw.clone());
-            }
-            _ => (),
+        if let AttributeType::RuntimeVisibleTypeAnnotations { raw, .. } = it {
+            return Some(raw.to_vec());
         }
     }
 
-    do_assemble(vis, in_vis)
+    None
 }
 
 pub fn assemble_annotation_default(attrs: &[AttributeType]) -> Option<Vec<u8>> {
-    let mut vis = None;
-
     for it in attrs.iter() {
         if let AttributeType::AnnotationDefault { raw, .. } = it {
-            vis = Some(raw.clone());
+            return Some(raw.to_vec());
         }
     }
 
-    do_assemble(vis, None)
+    None
 }
 
 pub fn get_signature(attrs: &[AttributeType]) -> u16 {
@@ -78,19 +50,3 @@ pub fn get_signature(attrs: &[AttributeType]) -> u16 {
 
     0
 }
-
-fn do_assemble(vis: Option<BytesRef>, in_vis: Option<BytesRef>) -> Option<Vec<u8>> {
-    let mut raw = None;
-
-    if let Some(v) = vis {
-        raw = Some(Vec::from(v.as_slice()));
-    }
-
-    if let Some(v) = in_vis {
-        if let Some(raw) = raw.as_mut() {
-            raw.extend_from_slice(v.as_slice());
-        }
-    }
-
-    raw
-}
@@ -16,13 +16,17 @@ lazy_static! {
     pub static ref S_SIGNATURE: BytesRef = new_br("signature");
     pub static ref S_CONSTANT_POOL_OOP: BytesRef = new_br("constantPoolOop");
     pub static ref S_RUN: BytesRef = new_br("run");
+    pub static ref S_FINALIZE: BytesRef = new_br("finalize");
+    pub static ref S_FINALIZE_SIG: BytesRef = new_br("()V");
     pub static ref S_ERR: BytesRef = new_br("err");
     pub static ref S_OUT: BytesRef = new_br("out");
     pub static ref S_IN: BytesRef = new_br("in");
+    pub static ref S_CLASS_LOADER: BytesRef = new_br("classLoader");
     pub static ref S_JAVA_LANG_CLASS: BytesRef = new_br("Ljava/lang/Class;");
     pub static ref S_JAVA_LANG_OBJECT: BytesRef = new_br("Ljava/lang/Object;");
     pub static ref S_JAVA_LANG_STRING: BytesRef = new_br("Ljava/lang/String;");
     pub static ref S_JAVA_IO_FD: BytesRef = new_br("Ljava/io/FileDescriptor;");
     pub static ref S_JAVA_IO_PRINT_STREAM: BytesRef = new_br("Ljava/io/PrintStream;");
     pub static ref S_JAVA_IO_INPUT_STREAM: BytesRef = new_br("Ljava/io/InputStream;");
+    pub static ref S_JAVA_LANG_CLASS_LOADER: BytesRef = new_br("Ljava/lang/ClassLoader;");
 }
@@ -41,9 +41,12 @@ pub fn new_java_lang_string2(v: &str) -> Oop {
 
 pub fn new_java_lang_string3(bs: &[u8]) -> Oop {
     let buffer = classfile::constant_pool::construct_string_raw(bs);
+    new_java_lang_string4(buffer.as_slice())
+}
 
+pub fn new_java_lang_string4(chars: &[u16]) -> Oop {
     //build "char value[]"
-    let ary = Oop::char_ary_from1(buffer.as_slice());
+    let ary = Oop::char_ary_from1(chars);
 
     //new String(char value[])
     let string_cls = require_class3(None, b"java/lang/String").unwrap();
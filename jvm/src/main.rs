@@ -1,29 +1,65 @@
-extern crate clap;
 extern crate env_logger;
 
 mod options;
 
+use options::Command;
 use vm;
-use vm::runtime::{self, thread::MainThread};
+use vm::runtime::{self, arguments, thread::MainThread};
 use vm::util;
 
 fn main() {
     env_logger::init();
-    vm::init_vm();
 
-    let opt = options::parse();
+    let opt = match options::parse() {
+        Ok(Command::Run(opt)) => opt,
+        Ok(Command::Version) => {
+            print_version();
+            return;
+        }
+        Ok(Command::Help) => {
+            eprintln!("{}", options::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", options::USAGE);
+            std::process::exit(1);
+        }
+    };
 
-    if let Some(cp) = &opt.cp {
-        runtime::add_class_paths(cp);
-    }
+    arguments::set(opt.vm);
+    vm::init_vm();
+    #[cfg(feature = "jni")]
+    jni::invocation::init();
 
-    if let Some(classpath) = &opt.classpath {
-        runtime::add_class_path(classpath);
-    }
+    runtime::add_class_paths(&opt.boot_class_path);
 
-    let class = opt.class;
+    let class = opt.class.replace(".", util::FILE_SEP);
     let args = opt.args;
     // println!("main class: {}, args: {:?}", class, args);
-    let mut thread = MainThread::new(class.replace(".", util::FILE_SEP), args);
-    thread.run();
+
+    //the stack of the main thread is by -Xss too
+    let main = std::thread::Builder::new()
+        .name(String::from("main"))
+        .stack_size(arguments::native_stack_size())
+        .spawn(move || {
+            let mut thread = MainThread::new(class, args);
+            thread.run();
+        })
+        .expect("spawn main thread failed");
+    if main.join().is_err() {
+        std::process::exit(1);
+    }
+}
+
+fn print_version() {
+    eprintln!("java version \"1.8\"");
+    eprintln!(
+        "Rust JVM Runtime Environment (build {})",
+        env!("CARGO_PKG_VERSION")
+    );
+    eprintln!(
+        "Rust JVM (build {}, interpreted mode)",
+        env!("CARGO_PKG_VERSION")
+    );
 }
@@ -1,22 +1,335 @@
-use clap::Clap;
+use std::collections::VecDeque;
+use std::path::Path;
+use vm::runtime::arguments::Arguments;
+use vm::util;
 
-#[derive(Clap, Debug)]
-#[clap(version)]
-pub struct Opt {
-    /// class search path of directories and zip/jar files
-    #[clap(long)]
-    pub cp: Option<String>,
+pub const USAGE: &str = "Usage: jvm [options] <mainclass> [args...]
+           (to execute a class)
+   or  jvm [options] -jar <jarfile> [args...]
+           (to execute a jar file)
+where options include:
+    -cp <class search path of directories and zip/jar files>
+    -classpath <class search path of directories and zip/jar files>
+                  the application class path, CLASSPATH or . by default
+    --cp <path>, --classpath <path>
+                  the bootstrap class path, the jars of JAVA_HOME by default
+    -D<name>=<value>
+                  set a system property
+    -verbose:class
+                  print the classes loaded
+    -version      print product version and exit
+    -ea[:<packagename>...|:<classname>]
+    -enableassertions[:<packagename>...|:<classname>]
+                  enable assertions
+    -da[:<packagename>...|:<classname>]
+    -disableassertions[:<packagename>...|:<classname>]
+                  disable assertions
+    -esa | -enablesystemassertions
+                  enable system assertions
+    -dsa | -disablesystemassertions
+                  disable system assertions
+    -Xss<size>    set java thread stack size
+    -Xmx<size>    set maximum java heap size
+    -? -help      print this help message
+    @<filepath>   read options from the specified file";
 
-    /// class search path of directories and zip/jar files
-    #[clap(long)]
-    pub classpath: Option<String>,
+//the jars of the bootstrap class path, in the order of HotSpot
+const BOOT_JARS: &[&str] = &[
+    "resources.jar",
+    "rt.jar",
+    "sunrsasign.jar",
+    "jsse.jar",
+    "jce.jar",
+    "charsets.jar",
+    "jfr.jar",
+];
 
-    #[clap(required = true)]
-    pub class: String,
+pub enum Command {
+    Run(Opt),
+    Version,
+    Help,
+}
 
+pub struct Opt {
+    /// -D, -ea, -Xss... & java.class.path
+    pub vm: Arguments,
+    /// the search path of the bootstrap class loader
+    pub boot_class_path: String,
+    pub class: String,
     pub args: Vec<String>,
 }
 
-pub fn parse() -> Opt {
-    Opt::parse()
+pub fn parse() -> Result<Command, String> {
+    parse_args(std::env::args().skip(1).collect())
+}
+
+fn parse_args(args: Vec<String>) -> Result<Command, String> {
+    let mut args: VecDeque<String> = args.into();
+    let mut vm = Arguments::default();
+    let mut class_path = None;
+    let mut boot_class_path: Vec<String> = Vec::new();
+    let mut jar = None;
+    let mut class = None;
+
+    while let Some(arg) = args.pop_front() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" => {
+                class_path = Some(required(&mut args, &arg)?);
+            }
+            "--cp" | "--classpath" => boot_class_path.push(required(&mut args, &arg)?),
+            "-jar" => {
+                jar = Some(required(&mut args, &arg)?);
+                break;
+            }
+            "-version" | "--version" => return Ok(Command::Version),
+            "-?" | "-h" | "-help" | "--help" => return Ok(Command::Help),
+            _ if arg.starts_with("--cp=") || arg.starts_with("--classpath=") => {
+                let i = arg.find('=').unwrap();
+                boot_class_path.push(arg[i + 1..].to_string());
+            }
+            _ if arg.starts_with("@@") => {
+                class = Some(arg[1..].to_string());
+                break;
+            }
+            _ if arg.starts_with('@') => {
+                let content = std::fs::read_to_string(&arg[1..])
+                    .map_err(|e| format!("cannot read the argument file {}: {}", &arg[1..], e))?;
+                for it in split_arg_file(&content).into_iter().rev() {
+                    args.push_front(it);
+                }
+            }
+            _ if arg.starts_with('-') => {
+                if !vm.parse_vm_option(&arg)? {
+                    return Err(format!("Unrecognized option: {}", arg));
+                }
+            }
+            _ => {
+                class = Some(arg);
+                break;
+            }
+        }
+    }
+
+    let class = match (&jar, class) {
+        (Some(jar), _) => {
+            let (main_class, jar_class_path) = read_manifest(jar)?;
+            let mut paths = vec![jar.clone()];
+            paths.extend(jar_class_path);
+            class_path = Some(paths.join(util::PATH_SEP));
+            main_class
+        }
+        (None, Some(class)) => class,
+        (None, None) => return Err(String::from("missing the main class")),
+    };
+    let args: Vec<String> = args.into_iter().collect();
+
+    vm.class_path = class_path
+        .or_else(|| std::env::var("CLASSPATH").ok())
+        .unwrap_or_else(|| String::from("."));
+
+    let boot_class_path = if boot_class_path.is_empty() {
+        java_home_class_path()?
+    } else {
+        boot_class_path.join(util::PATH_SEP)
+    };
+
+    //computed ones, the -D given take precedence
+    let mut command = vec![jar.as_ref().unwrap_or(&class).clone()];
+    command.extend(args.iter().cloned());
+    let computed = vec![
+        ("sun.boot.class.path".to_string(), boot_class_path.clone()),
+        ("sun.java.command".to_string(), command.join(" ")),
+    ];
+    vm.properties.splice(0..0, computed);
+
+    Ok(Command::Run(Opt {
+        vm,
+        boot_class_path,
+        class,
+        args,
+    }))
+}
+
+fn required(args: &mut VecDeque<String>, option: &str) -> Result<String, String> {
+    args.pop_front()
+        .ok_or_else(|| format!("{} requires an argument", option))
+}
+
+//split by white spaces, a quoted one may have spaces, '#' starts a comment line
+fn split_arg_file(content: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    for line in content.lines() {
+        let line = line.trim_start();
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let mut arg: Option<String> = None;
+        let mut quote = None;
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), '\\') => {
+                    if let Some(c) = chars.next() {
+                        arg.get_or_insert_with(String::new).push(c);
+                    }
+                }
+                (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+                (None, '"') | (None, '\'') => {
+                    quote = Some(c);
+                    arg.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => args.extend(arg.take()),
+                (None, c) => arg.get_or_insert_with(String::new).push(c),
+            }
+        }
+        args.extend(arg);
+    }
+
+    args
+}
+
+//Main-Class & the paths of Class-Path, relative to the directory of the jar
+fn read_manifest(jar: &str) -> Result<(String, Vec<String>), String> {
+    let f = std::fs::File::open(jar).map_err(|e| format!("Unable to access jarfile {}: {}", jar, e))?;
+    let mut zip =
+        zip::ZipArchive::new(f).map_err(|e| format!("Invalid or corrupt jarfile {}: {}", jar, e))?;
+    let manifest = {
+        let mut entry = zip
+            .by_name("META-INF/MANIFEST.MF")
+            .map_err(|_| format!("no main manifest attribute, in {}", jar))?;
+        let mut buf = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut buf)
+            .map_err(|e| format!("Invalid or corrupt jarfile {}: {}", jar, e))?;
+        buf
+    };
+
+    let attrs = parse_manifest(&manifest);
+    let attr = |name: &str| {
+        attrs
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().to_string())
+    };
+
+    let main_class = attr("Main-Class")
+        .filter(|it| !it.is_empty())
+        .ok_or_else(|| format!("no main manifest attribute, in {}", jar))?;
+
+    let dir = Path::new(jar).parent().unwrap_or_else(|| Path::new(""));
+    let class_path = attr("Class-Path")
+        .map(|it| {
+            it.split_whitespace()
+                .map(|p| dir.join(p).to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok((main_class, class_path))
+}
+
+//the main attributes, a line beginning with a space continues the previous one
+fn parse_manifest(content: &str) -> Vec<(String, String)> {
+    let mut attrs: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(rest) = line.strip_prefix(' ') {
+            if let Some((_, v)) = attrs.last_mut() {
+                v.push_str(rest);
+            }
+        } else if let Some(i) = line.find(':') {
+            attrs.push((line[..i].to_string(), line[i + 1..].trim_start().to_string()));
+        }
+    }
+
+    attrs
+}
+
+//the jars in lib of JAVA_HOME, a JRE or a JDK
+fn java_home_class_path() -> Result<String, String> {
+    let java_home = std::env::var("JAVA_HOME")
+        .map_err(|_| String::from("JAVA_HOME is not set, or give the bootstrap class path by --cp"))?;
+    let home = Path::new(&java_home);
+    let lib = if home.join("lib").join("rt.jar").exists() {
+        home.join("lib")
+    } else {
+        home.join("jre").join("lib")
+    };
+
+    let jars: Vec<String> = BOOT_JARS
+        .iter()
+        .map(|it| lib.join(it))
+        .filter(|it| it.exists())
+        .map(|it| it.to_string_lossy().to_string())
+        .collect();
+    if jars.is_empty() {
+        return Err(format!("rt.jar is not found in {}", java_home));
+    }
+
+    Ok(jars.join(util::PATH_SEP))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Opt {
+        let args = args.iter().map(|it| it.to_string()).collect();
+        match parse_args(args) {
+            Ok(Command::Run(opt)) => opt,
+            _ => panic!("not run"),
+        }
+    }
+
+    #[test]
+    fn t_parse_args() {
+        let opt = run(&[
+            "--cp", "rt.jar", "-cp", "a:b", "-Dk=v", "-ea", "-Xss2m", "Main", "-Dx", "1",
+        ]);
+        assert_eq!(opt.boot_class_path, "rt.jar");
+        assert_eq!(opt.vm.class_path, "a:b");
+        assert_eq!(opt.class, "Main");
+        assert_eq!(opt.args, vec!["-Dx", "1"]);
+        assert_eq!(opt.vm.thread_stack_size, 2 * 1024 * 1024);
+        assert_eq!(
+            opt.vm.properties.last(),
+            Some(&("k".to_string(), "v".to_string()))
+        );
+
+        let opt = run(&["--cp=rt.jar", "@@Main"]);
+        assert_eq!(opt.boot_class_path, "rt.jar");
+        assert_eq!(opt.class, "@Main");
+
+        assert!(parse_args(vec!["--cp".to_string()]).is_err());
+        assert!(parse_args(vec!["-foo".to_string(), "Main".to_string()]).is_err());
+        assert!(matches!(
+            parse_args(vec!["-version".to_string()]),
+            Ok(Command::Version)
+        ));
+    }
+
+    #[test]
+    fn t_split_arg_file() {
+        let content = "-cp a.jar\n# comment\n  -Dk=\"v 1\" 'it''s'\n\"\" Main\n";
+        assert_eq!(
+            split_arg_file(content),
+            vec!["-cp", "a.jar", "-Dk=v 1", "its", "", "Main"]
+        );
+    }
+
+    #[test]
+    fn t_parse_manifest() {
+        let content = "Manifest-Version: 1.0\r\nMain-Class: a.Main\r\nClass-Path: lib/x.jar\r\n  lib/y.jar\r\n\r\nName: a\r\n";
+        let attrs = parse_manifest(content);
+        assert_eq!(attrs[1], ("Main-Class".to_string(), "a.Main".to_string()));
+        assert_eq!(
+            attrs[2],
+            ("Class-Path".to_string(), "lib/x.jar lib/y.jar".to_string())
+        );
+        assert_eq!(attrs.len(), 3);
+    }
 }
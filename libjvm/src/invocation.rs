@@ -3,14 +3,21 @@
 
 //https://docs.oracle.com/javase/7/docs/technotes/guides/jni/spec/invocation.html
 
-use jni_sys::{jboolean, jint, jsize, JNIInvokeInterface_, JNINativeInterface_, JavaVM};
+use classfile::consts::J_THREAD;
+use jni_sys::{
+	jboolean, jint, jobject, jsize, JNIInvokeInterface_, JNINativeInterface_, JavaVM, JNI_ERR,
+	JNI_EVERSION, JNI_OK, JNI_VERSION_1_8,
+};
 use lazy_static::lazy_static;
 use libc::c_void;
 use std::cell::RefCell;
-use std::sync::Mutex;
-use vm::runtime::thread::MainThread;
+use std::sync::{Arc, Mutex};
+use vm::new_br;
+use vm::oop::{Class, Oop};
+use vm::runtime::thread::{self, JavaThread, MainThread, ThreadState};
 
 use crate::native;
+use crate::util;
 
 #[no_mangle]
 extern "C" fn JNI_GetDefaultJavaVMInitArgs(args: *mut c_void) -> jint {
@@ -36,25 +43,89 @@ lazy_static! {
 }
 
 unsafe extern "system" fn DestroyJavaVM(_vm: *mut JavaVM) -> jint {
-	todo!();
+	let jvm = vm::runtime::vm::get_vm();
+	jvm.threads.detach_current_thread();
+	jvm.threads.join_all();
+	JNI_OK
 }
 unsafe extern "system" fn AttachCurrentThread(
 	_vm: *mut JavaVM,
-	_penv: *mut *mut c_void,
-	_args: *mut c_void,
+	penv: *mut *mut c_void,
+	args: *mut c_void,
 ) -> jint {
-	todo!();
+	attach_current_thread(penv, args as *const JavaVMAttachArgs, false)
 }
 unsafe extern "system" fn DetachCurrentThread(_vm: *mut JavaVM) -> jint {
-	todo!();
+	let jvm = vm::runtime::vm::get_vm();
+	let jt = thread::current_java_thread();
+	if !jt.read().unwrap().frames.is_empty() {
+		// Can't detach a thread running java code
+		return JNI_ERR;
+	}
+
+	// Thread.exit runs java code, out of the safe region of native code
+	if vm::runtime::gc::is_in_safe_region() {
+		vm::runtime::gc::leave_safe_region();
+	}
+
+	// Thread.exit, and notify threads that invoke 'join'
+	jvm.threads.exit_current_thread();
+	JNI_OK
 }
 unsafe extern "system" fn GetEnv(
 	_vm: *mut JavaVM,
 	penv: *mut *mut core::ffi::c_void,
-	_version: jint,
+	version: jint,
 ) -> jint {
+	if !vm::native::jni::is_supported_version(version) {
+		*penv = std::ptr::null_mut();
+		return JNI_EVERSION;
+	}
+	*penv = vm::native::jni::current_env();
+	JNI_OK
+}
+
+struct NativeInterface(JNINativeInterface_);
+unsafe impl Send for NativeInterface {}
+unsafe impl Sync for NativeInterface {}
+
+// The function table of JNIEnv, shared by all threads
+lazy_static! {
+	static ref NATIVE_INTERFACE: NativeInterface = NativeInterface(new_native_interface());
+}
+
+/// Register the JNIEnv function table and the JavaVM to vm,
+/// used by the natives in user libraries (System.loadLibrary)
+pub fn register_jni(java_vm: *mut JavaVM) {
+	let functions = &NATIVE_INTERFACE.0 as *const JNINativeInterface_;
+	vm::native::jni::init(functions as *const c_void, java_vm as *mut c_void);
+}
+
+/// Used by the launcher which creates vm by itself, without JNI_CreateJavaVM
+pub fn init() {
+	let functions: &'static JNIInvokeInterface_ = Box::leak(Box::new(new_invoke_interface()));
+	let java_vm: &'static mut JavaVM = Box::leak(Box::new(functions as JavaVM));
+	register_jni(java_vm);
+}
+
+fn new_invoke_interface() -> JNIInvokeInterface_ {
+	use std::ptr::null_mut;
+	JNIInvokeInterface_ {
+		// We can use reserved fields for implementation details
+		reserved0: null_mut(),
+		reserved1: null_mut(),
+		reserved2: null_mut(),
+		DestroyJavaVM: Some(DestroyJavaVM),
+		AttachCurrentThread: Some(AttachCurrentThread),
+		DetachCurrentThread: Some(DetachCurrentThread),
+		GetEnv: Some(GetEnv),
+		AttachCurrentThreadAsDaemon: Some(AttachCurrentThreadAsDaemon),
+	}
+}
+
+fn new_native_interface() -> JNINativeInterface_ {
 	use std::ptr::null_mut;
-	*penv = Box::into_raw(Box::new(JNINativeInterface_ {
+	JNINativeInterface_ {
 		reserved0: null_mut(),
 		reserved1: null_mut(),
 		reserved2: null_mut(),
@@ -288,15 +359,134 @@ unsafe extern "system" fn GetEnv(
 		GetDirectBufferAddress: Some(native::GetDirectBufferAddress),
 		GetDirectBufferCapacity: Some(native::GetDirectBufferCapacity),
 		GetObjectRefType: Some(native::GetObjectRefType),
-	})) as *mut core::ffi::c_void;
-	0
+	}
 }
+
 unsafe extern "system" fn AttachCurrentThreadAsDaemon(
 	_vm: *mut JavaVM,
-	_penv: *mut *mut c_void,
-	_args: *mut c_void,
+	penv: *mut *mut c_void,
+	args: *mut c_void,
+) -> jint {
+	attach_current_thread(penv, args as *const JavaVMAttachArgs, true)
+}
+
+#[repr(C)]
+struct JavaVMAttachArgs {
+	version: jint,
+	name: *const libc::c_char,
+	group: jobject,
+}
+
+unsafe fn attach_current_thread(
+	penv: *mut *mut c_void,
+	args: *const JavaVMAttachArgs,
+	daemon: bool,
 ) -> jint {
-	todo!();
+	let jvm = vm::runtime::vm::get_vm();
+	let current = thread::current_java_thread();
+	let attached = jvm
+		.threads
+		.java_threads()
+		.iter()
+		.any(|it| Arc::ptr_eq(it, &current));
+
+	if !attached {
+		let jt = JavaThread::new(None, jvm.threads.next_id());
+		thread::THREAD.with(|t| {
+			*t.borrow_mut() = jt.clone();
+		});
+		jvm.threads.attach_java_thread(jt);
+
+		let args = args.as_ref();
+		if create_thread_obj(args, daemon).is_none() {
+			jvm.threads.detach_current_thread();
+			return JNI_ERR;
+		}
+
+		// Back to native code, gc goes on without the thread
+		vm::runtime::gc::enter_safe_region();
+	}
+
+	*penv = vm::native::jni::current_env();
+	JNI_OK
+}
+
+// Create the java.lang.Thread of the attached thread, as the main thread is created
+fn create_thread_obj(args: Option<&JavaVMAttachArgs>, daemon: bool) -> Option<()> {
+	let jt = thread::current_java_thread();
+	let eetop = jt.read().unwrap().eetop;
+	let thread_cls = vm::oop::class::load_and_init(J_THREAD);
+	let thread_obj = Oop::new_inst(thread_cls.clone());
+	{
+		let cls = thread_cls.get_class();
+		let id = cls.get_field_id(&new_br("priority"), &new_br("I"), false);
+		Class::put_field_value(thread_obj.extract_ref(), id, Oop::new_int(5));
+		let id = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
+		Class::put_field_value(thread_obj.extract_ref(), id, Oop::new_long(eetop));
+	}
+	jt.write().unwrap().set_java_thread_obj(thread_obj.clone());
+
+	let group = match args {
+		Some(args) if !args.group.is_null() => unsafe { util::jobject_to_oop(args.group) },
+		_ => main_thread_group(),
+	};
+	let name = match args {
+		Some(args) if !args.name.is_null() => unsafe {
+			std::ffi::CStr::from_ptr(args.name)
+				.to_string_lossy()
+				.into_owned()
+		},
+		_ => format!("Thread-{}", eetop),
+	};
+
+	let args = vec![
+		thread_obj.clone(),
+		group,
+		vm::util::oop::new_java_lang_string2(&name),
+	];
+	vm::runtime::invoke::invoke_ctor(
+		thread_cls.clone(),
+		new_br("(Ljava/lang/ThreadGroup;Ljava/lang/String;)V"),
+		args,
+	);
+	if thread::is_meet_ex() {
+		return None;
+	}
+
+	if daemon {
+		let cls = thread_cls.get_class();
+		let id = cls.get_field_id(&new_br("daemon"), &new_br("Z"), false);
+		Class::put_field_value(thread_obj.extract_ref(), id, Oop::new_int(1));
+	}
+	{
+		let mut jt = jt.write().unwrap();
+		jt.daemon = daemon;
+		jt.set_state(ThreadState::Runnable);
+	}
+
+	Some(())
+}
+
+// The group of the first thread, it's the main thread
+fn main_thread_group() -> Oop {
+	let jvm = vm::runtime::vm::get_vm();
+	let main = jvm
+		.threads
+		.java_threads()
+		.iter()
+		.find_map(|it| it.read().unwrap().java_thread_obj.clone());
+	match main {
+		Some(main) => {
+			let cls = util::object_class(&main);
+			let id = cls.get_class().get_field_id(
+				&new_br("group"),
+				&new_br("Ljava/lang/ThreadGroup;"),
+				false,
+			);
+			Class::get_field_value(main.extract_ref(), id)
+		}
+		None => Oop::Null,
+	}
 }
 
 #[repr(C)]
@@ -360,38 +550,44 @@ extern "C" fn JNI_CreateJavaVM(
 		vm::runtime::add_class_path("./lib/rt.jar");
 		vm::runtime::add_class_path("./lib/jsse.jar");
 		let args = unsafe { &*args };
-		// TODO: Pass to jvm
-		let mut properties: std::collections::HashMap<String, String> =
-			std::collections::HashMap::new();
+		let mut arguments = vm::runtime::arguments::Arguments::default();
 		for option in args.options() {
 			let option: String = option.string().to_string_lossy().into();
-			if option.starts_with("-D") {
-				let idx = option.find("=").expect("bad property argument format");
-				properties.insert(option[2..idx].to_owned(), option[idx..].to_owned());
-			} else if args.ignore_unrecognized == 0 {
-				panic!("unknown option: {}", option);
+			match arguments.parse_vm_option(&option) {
+				Ok(true) => (),
+				Ok(false) if args.ignore_unrecognized != 0 => (),
+				Ok(false) => panic!("unknown option: {}", option),
+				Err(e) => panic!("{}", e),
 			}
 		}
+		vm::runtime::arguments::set(arguments);
 
 		let holder = VMHolder {
-			jvm: Box::new(JNIInvokeInterface_ {
-				// We can use reserved fields for implementation details
-				reserved0: null_mut(),
-				reserved1: null_mut(),
-				reserved2: null_mut(),
-				DestroyJavaVM: Some(DestroyJavaVM),
-				AttachCurrentThread: Some(AttachCurrentThread),
-				DetachCurrentThread: Some(DetachCurrentThread),
-				GetEnv: Some(GetEnv),
-				AttachCurrentThreadAsDaemon: Some(AttachCurrentThreadAsDaemon),
-			}),
+			jvm: Box::new(new_invoke_interface()),
 		};
 		unsafe {
 			*pvm = Box::into_raw(Box::new(holder.inner()));
-			holder.jvm.GetEnv.unwrap()(&mut holder.inner(), penv, 0);
+			register_jni(*pvm);
+		}
+
+		// The creating thread becomes the main thread
+		let jvm = vm::runtime::vm::VM::new();
+		jvm.threads.attach_current_thread();
+		vm::runtime::init_vm::initialize_jvm();
+		Box::leak(jvm);
+
+		unsafe {
+			holder.jvm.GetEnv.unwrap()(*pvm, penv, JNI_VERSION_1_8);
 		}
 		lock.replace(holder);
-		0
+
+		// Back to native code, gc goes on without the thread
+		vm::runtime::gc::enter_safe_region();
+		if thread::is_meet_ex() {
+			JNI_ERR
+		} else {
+			JNI_OK
+		}
 	}
 }
 
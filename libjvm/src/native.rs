@@ -6,13 +6,29 @@ use jni_sys::{
 	jarray, jboolean, jbooleanArray, jbyte, jbyteArray, jchar, jcharArray, jclass, jdouble,
 	jdoubleArray, jfieldID, jfloat, jfloatArray, jint, jintArray, jlong, jlongArray, jmethodID,
 	jobject, jobjectArray, jobjectRefType, jshort, jshortArray, jsize, jstring, jthrowable, jvalue,
-	jweak, JNIEnv, JNIInvokeInterface_, JNINativeInterface_, JNINativeMethod, JavaVM,
+	jweak, JNIEnv, JNIInvokeInterface_, JNINativeInterface_, JNINativeMethod, JavaVM, JNI_ERR,
+	JNI_OK, JNI_TRUE, JNI_VERSION_1_8,
 };
 use libc::{c_char, c_void};
+use std::ffi::CStr;
+use std::ptr;
+use std::sync::Arc;
+use classfile::consts::{
+	J_ARRAY_INDEX_OUT_OF_BOUNDS, J_ILLEGAL_MONITOR_STATE, J_NASE, J_NO_SUCH_METHOD_ERROR, J_NPE,
+};
+use classfile::constant_pool;
+use vm::native::common::reflect;
+use vm::native::jni::refs;
+use vm::new_br;
+use vm::oop::{Oop, OopPtr};
+use vm::runtime::{self, cmp, thread, ClassLoader};
+
+use crate::util::{self, Invoke, JArgs};
 pub type va_list = *mut c_void;
 
 pub unsafe extern "system" fn GetVersion(env: *mut JNIEnv) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	JNI_VERSION_1_8
 }
 pub unsafe extern "system" fn DefineClass(
 	env: *mut JNIEnv,
@@ -21,16 +37,43 @@ pub unsafe extern "system" fn DefineClass(
 	buf: *const jbyte,
 	len: jsize,
 ) -> jclass {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let name = if name.is_null() {
+		None
+	} else {
+		Some(CStr::from_ptr(name).to_bytes())
+	};
+	let buf = std::slice::from_raw_parts(buf as *const u8, len as usize);
+	let class_loader = ClassLoader::from_oop(&util::jobject_to_oop(loader));
+	match vm::runtime::define_class(class_loader, name, buf) {
+		Some(class) => util::class_ref_to_jclass(Some(class)),
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn FindClass(env: *mut JNIEnv, name: *const c_char) -> jclass {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let name = CStr::from_ptr(name).to_bytes();
+	util::find_class(name)
 }
 pub unsafe extern "system" fn FromReflectedMethod(env: *mut JNIEnv, method: jobject) -> jmethodID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(method) {
+		Oop::Null => ptr::null_mut(),
+		method => match util::reflected_method(&method) {
+			Some(mir) => util::mir_to_jmethod_id(&mir),
+			None => ptr::null_mut(),
+		},
+	}
 }
 pub unsafe extern "system" fn FromReflectedField(env: *mut JNIEnv, field: jobject) -> jfieldID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(field) {
+		Oop::Null => ptr::null_mut(),
+		field => match util::reflected_field(&field) {
+			Some(fir) => util::fir_to_jfield_id(&fir),
+			None => ptr::null_mut(),
+		},
+	}
 }
 pub unsafe extern "system" fn ToReflectedMethod(
 	env: *mut JNIEnv,
@@ -38,17 +81,41 @@ pub unsafe extern "system" fn ToReflectedMethod(
 	methodID: jmethodID,
 	isStatic: jboolean,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let mir = util::jmethod_id_to_mir(methodID);
+	let v = if mir.method.name.as_slice() == b"<init>" {
+		reflect::new_method_ctor(mir)
+	} else {
+		reflect::new_method_normal(mir)
+	};
+	util::oop_to_jobject(&v)
 }
 pub unsafe extern "system" fn GetSuperclass(env: *mut JNIEnv, sub: jclass) -> jclass {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jclass_to_class_ref(sub) {
+		Some(class) => {
+			let cls = class.get_class();
+			if cls.is_interface() {
+				ptr::null_mut()
+			} else {
+				util::class_ref_to_jclass(cls.get_super_class())
+			}
+		}
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn IsAssignableFrom(
 	env: *mut JNIEnv,
 	sub: jclass,
 	sup: jclass,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let r = match (util::jclass_to_class_ref(sub), util::jclass_to_class_ref(sup)) {
+		(Some(sub), Some(sup)) => cmp::instance_of(sub, sup),
+		//primitive types
+		_ => OopPtr::is_eq(&util::jobject_to_oop(sub), &util::jobject_to_oop(sup)),
+	};
+	r as jboolean
 }
 pub unsafe extern "system" fn ToReflectedField(
 	env: *mut JNIEnv,
@@ -56,68 +123,135 @@ pub unsafe extern "system" fn ToReflectedField(
 	fieldID: jfieldID,
 	isStatic: jboolean,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let fir = util::jfield_id_to_fir(fieldID);
+	util::oop_to_jobject(&reflect::new_field(fir))
 }
 pub unsafe extern "system" fn Throw(env: *mut JNIEnv, obj: jthrowable) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(obj) {
+		Oop::Null => JNI_ERR,
+		ex => {
+			util::throw_oop(ex);
+			JNI_OK
+		}
+	}
 }
 pub unsafe extern "system" fn ThrowNew(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	msg: *const c_char,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let name = match util::jclass_to_class_ref(clazz) {
+		Some(class) => class.get_class().name.clone(),
+		None => return JNI_ERR,
+	};
+	let msg = if msg.is_null() {
+		None
+	} else {
+		Some(CStr::from_ptr(msg).to_string_lossy().into_owned())
+	};
+	util::throw(name.as_slice(), msg);
+	JNI_OK
 }
 pub unsafe extern "system" fn ExceptionOccurred(env: *mut JNIEnv) -> jthrowable {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let jt = thread::current_java_thread();
+	let ex = jt.read().unwrap().ex.clone();
+	match ex {
+		Some(ex) => util::oop_to_jobject(&ex),
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn ExceptionDescribe(env: *mut JNIEnv) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let jt = thread::current_java_thread();
+	let ex = jt.write().unwrap().take_ex();
+	if let Some(ex) = ex {
+		let class = util::object_class(&ex);
+		let mir = class
+			.get_class()
+			.get_virtual_method(&new_br("printStackTrace"), &new_br("()V"));
+		if let Ok(mir) = mir {
+			let _ = util::invoke(mir, vec![ex], false);
+		}
+		//the exception thrown by printStackTrace is ignored
+		let _ = jt.write().unwrap().take_ex();
+	}
 }
 pub unsafe extern "system" fn ExceptionClear(env: *mut JNIEnv) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let jt = thread::current_java_thread();
+	let _ = jt.write().unwrap().take_ex();
 }
 pub unsafe extern "system" fn FatalError(env: *mut JNIEnv, msg: *const c_char) -> ! {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let msg = CStr::from_ptr(msg).to_string_lossy();
+	eprintln!("FATAL ERROR in native method: {}", msg);
+	std::process::abort();
 }
 pub unsafe extern "system" fn PushLocalFrame(env: *mut JNIEnv, capacity: jint) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::push_local_frame();
+	JNI_OK
 }
 pub unsafe extern "system" fn PopLocalFrame(env: *mut JNIEnv, result: jobject) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let result = util::jobject_to_oop(result);
+	refs::pop_local_frame();
+	util::oop_to_jobject(&result)
 }
 pub unsafe extern "system" fn NewGlobalRef(env: *mut JNIEnv, lobj: jobject) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::new_global(&util::jobject_to_oop(lobj)) as jobject
 }
 pub unsafe extern "system" fn DeleteGlobalRef(env: *mut JNIEnv, gref: jobject) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::delete_global(gref as refs::JObject);
 }
 pub unsafe extern "system" fn DeleteLocalRef(env: *mut JNIEnv, obj: jobject) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::delete_local(obj as refs::JObject);
 }
 pub unsafe extern "system" fn IsSameObject(
 	env: *mut JNIEnv,
 	obj1: jobject,
 	obj2: jobject,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let obj1 = util::jobject_to_oop(obj1);
+	let obj2 = util::jobject_to_oop(obj2);
+	let r = match (&obj1, &obj2) {
+		(Oop::Null, Oop::Null) => true,
+		(Oop::Ref(l), Oop::Ref(r)) => Arc::ptr_eq(l, r),
+		_ => false,
+	};
+	r as jboolean
 }
 pub unsafe extern "system" fn NewLocalRef(env: *mut JNIEnv, ref_: jobject) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::oop_to_jobject(&util::jobject_to_oop(ref_))
 }
 pub unsafe extern "system" fn EnsureLocalCapacity(env: *mut JNIEnv, capacity: jint) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the local reference frame grows as needed
+	JNI_OK
 }
 pub unsafe extern "system" fn AllocObject(env: *mut JNIEnv, clazz: jclass) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::alloc_object(clazz) {
+		Some(obj) => util::oop_to_jobject(&obj),
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "C" fn NewObject(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	util::new_object(clazz, methodID, JArgs::variadic(&mut args))
 }
 pub unsafe extern "system" fn NewObjectV(
 	env: *mut JNIEnv,
@@ -125,7 +259,8 @@ pub unsafe extern "system" fn NewObjectV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_object(clazz, methodID, JArgs::V(args))
 }
 pub unsafe extern "system" fn NewObjectA(
 	env: *mut JNIEnv,
@@ -133,17 +268,30 @@ pub unsafe extern "system" fn NewObjectA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_object(clazz, methodID, JArgs::A(args))
 }
 pub unsafe extern "system" fn GetObjectClass(env: *mut JNIEnv, obj: jobject) -> jclass {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(obj) {
+		Oop::Null => ptr::null_mut(),
+		obj => util::class_ref_to_jclass(Some(util::object_class(&obj))),
+	}
 }
 pub unsafe extern "system" fn IsInstanceOf(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let r = match util::jobject_to_oop(obj) {
+		Oop::Null => true,
+		obj => match util::jclass_to_class_ref(clazz) {
+			Some(class) => cmp::instance_of(util::object_class(&obj), class),
+			None => false,
+		},
+	};
+	r as jboolean
 }
 pub unsafe extern "system" fn GetMethodID(
 	env: *mut JNIEnv,
@@ -151,15 +299,16 @@ pub unsafe extern "system" fn GetMethodID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jmethodID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_method_id(clazz, name, sig, false)
 }
 pub unsafe extern "C" fn CallObjectMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallObjectMethodV(
 	env: *mut JNIEnv,
@@ -167,7 +316,8 @@ pub unsafe extern "system" fn CallObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallObjectMethodA(
 	env: *mut JNIEnv,
@@ -175,15 +325,16 @@ pub unsafe extern "system" fn CallObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallBooleanMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallBooleanMethodV(
 	env: *mut JNIEnv,
@@ -191,7 +342,8 @@ pub unsafe extern "system" fn CallBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallBooleanMethodA(
 	env: *mut JNIEnv,
@@ -199,15 +351,16 @@ pub unsafe extern "system" fn CallBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallByteMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallByteMethodV(
 	env: *mut JNIEnv,
@@ -215,7 +368,8 @@ pub unsafe extern "system" fn CallByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallByteMethodA(
 	env: *mut JNIEnv,
@@ -223,15 +377,16 @@ pub unsafe extern "system" fn CallByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallCharMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallCharMethodV(
 	env: *mut JNIEnv,
@@ -239,7 +394,8 @@ pub unsafe extern "system" fn CallCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallCharMethodA(
 	env: *mut JNIEnv,
@@ -247,15 +403,16 @@ pub unsafe extern "system" fn CallCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallShortMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallShortMethodV(
 	env: *mut JNIEnv,
@@ -263,7 +420,8 @@ pub unsafe extern "system" fn CallShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallShortMethodA(
 	env: *mut JNIEnv,
@@ -271,15 +429,16 @@ pub unsafe extern "system" fn CallShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallIntMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallIntMethodV(
 	env: *mut JNIEnv,
@@ -287,7 +446,8 @@ pub unsafe extern "system" fn CallIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallIntMethodA(
 	env: *mut JNIEnv,
@@ -295,15 +455,16 @@ pub unsafe extern "system" fn CallIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallLongMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallLongMethodV(
 	env: *mut JNIEnv,
@@ -311,7 +472,8 @@ pub unsafe extern "system" fn CallLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallLongMethodA(
 	env: *mut JNIEnv,
@@ -319,15 +481,16 @@ pub unsafe extern "system" fn CallLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallFloatMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallFloatMethodV(
 	env: *mut JNIEnv,
@@ -335,7 +498,8 @@ pub unsafe extern "system" fn CallFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallFloatMethodA(
 	env: *mut JNIEnv,
@@ -343,15 +507,16 @@ pub unsafe extern "system" fn CallFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallDoubleMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallDoubleMethodV(
 	env: *mut JNIEnv,
@@ -359,7 +524,8 @@ pub unsafe extern "system" fn CallDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallDoubleMethodA(
 	env: *mut JNIEnv,
@@ -367,10 +533,11 @@ pub unsafe extern "system" fn CallDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
-pub unsafe extern "C" fn CallVoidMethod(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...) {
-	todo!();
+pub unsafe extern "C" fn CallVoidMethod(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, mut args: ...) {
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallVoidMethodV(
 	env: *mut JNIEnv,
@@ -378,7 +545,8 @@ pub unsafe extern "system" fn CallVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Virtual)
 }
 pub unsafe extern "system" fn CallVoidMethodA(
 	env: *mut JNIEnv,
@@ -386,16 +554,17 @@ pub unsafe extern "system" fn CallVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Virtual)
 }
 pub unsafe extern "C" fn CallNonvirtualObjectMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualObjectMethodV(
 	env: *mut JNIEnv,
@@ -404,7 +573,8 @@ pub unsafe extern "system" fn CallNonvirtualObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualObjectMethodA(
 	env: *mut JNIEnv,
@@ -413,16 +583,17 @@ pub unsafe extern "system" fn CallNonvirtualObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualBooleanMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualBooleanMethodV(
 	env: *mut JNIEnv,
@@ -431,7 +602,8 @@ pub unsafe extern "system" fn CallNonvirtualBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualBooleanMethodA(
 	env: *mut JNIEnv,
@@ -440,16 +612,17 @@ pub unsafe extern "system" fn CallNonvirtualBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualByteMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualByteMethodV(
 	env: *mut JNIEnv,
@@ -458,7 +631,8 @@ pub unsafe extern "system" fn CallNonvirtualByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualByteMethodA(
 	env: *mut JNIEnv,
@@ -467,16 +641,17 @@ pub unsafe extern "system" fn CallNonvirtualByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualCharMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualCharMethodV(
 	env: *mut JNIEnv,
@@ -485,7 +660,8 @@ pub unsafe extern "system" fn CallNonvirtualCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualCharMethodA(
 	env: *mut JNIEnv,
@@ -494,16 +670,17 @@ pub unsafe extern "system" fn CallNonvirtualCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualShortMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualShortMethodV(
 	env: *mut JNIEnv,
@@ -512,7 +689,8 @@ pub unsafe extern "system" fn CallNonvirtualShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualShortMethodA(
 	env: *mut JNIEnv,
@@ -521,16 +699,17 @@ pub unsafe extern "system" fn CallNonvirtualShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualIntMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualIntMethodV(
 	env: *mut JNIEnv,
@@ -539,7 +718,8 @@ pub unsafe extern "system" fn CallNonvirtualIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualIntMethodA(
 	env: *mut JNIEnv,
@@ -548,16 +728,17 @@ pub unsafe extern "system" fn CallNonvirtualIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualLongMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualLongMethodV(
 	env: *mut JNIEnv,
@@ -566,7 +747,8 @@ pub unsafe extern "system" fn CallNonvirtualLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualLongMethodA(
 	env: *mut JNIEnv,
@@ -575,16 +757,17 @@ pub unsafe extern "system" fn CallNonvirtualLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualFloatMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualFloatMethodV(
 	env: *mut JNIEnv,
@@ -593,7 +776,8 @@ pub unsafe extern "system" fn CallNonvirtualFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualFloatMethodA(
 	env: *mut JNIEnv,
@@ -602,16 +786,17 @@ pub unsafe extern "system" fn CallNonvirtualFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualDoubleMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualDoubleMethodV(
 	env: *mut JNIEnv,
@@ -620,7 +805,8 @@ pub unsafe extern "system" fn CallNonvirtualDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualDoubleMethodA(
 	env: *mut JNIEnv,
@@ -629,16 +815,17 @@ pub unsafe extern "system" fn CallNonvirtualDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "C" fn CallNonvirtualVoidMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) {
-	todo!();
+	util::call_method(obj, methodID, JArgs::variadic(&mut args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualVoidMethodV(
 	env: *mut JNIEnv,
@@ -647,7 +834,8 @@ pub unsafe extern "system" fn CallNonvirtualVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::V(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn CallNonvirtualVoidMethodA(
 	env: *mut JNIEnv,
@@ -656,7 +844,8 @@ pub unsafe extern "system" fn CallNonvirtualVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(obj, methodID, JArgs::A(args), Invoke::Nonvirtual)
 }
 pub unsafe extern "system" fn GetFieldID(
 	env: *mut JNIEnv,
@@ -664,70 +853,80 @@ pub unsafe extern "system" fn GetFieldID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jfieldID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field_id(clazz, name, sig, false)
 }
 pub unsafe extern "system" fn GetObjectField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetBooleanField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetByteField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetCharField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetShortField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetIntField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetLongField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetFloatField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn GetDoubleField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field(obj, fieldID)
 }
 pub unsafe extern "system" fn SetObjectField(
 	env: *mut JNIEnv,
@@ -735,7 +934,8 @@ pub unsafe extern "system" fn SetObjectField(
 	fieldID: jfieldID,
 	val: jobject,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetBooleanField(
 	env: *mut JNIEnv,
@@ -743,7 +943,8 @@ pub unsafe extern "system" fn SetBooleanField(
 	fieldID: jfieldID,
 	val: jboolean,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetByteField(
 	env: *mut JNIEnv,
@@ -751,7 +952,8 @@ pub unsafe extern "system" fn SetByteField(
 	fieldID: jfieldID,
 	val: jbyte,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetCharField(
 	env: *mut JNIEnv,
@@ -759,7 +961,8 @@ pub unsafe extern "system" fn SetCharField(
 	fieldID: jfieldID,
 	val: jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetShortField(
 	env: *mut JNIEnv,
@@ -767,7 +970,8 @@ pub unsafe extern "system" fn SetShortField(
 	fieldID: jfieldID,
 	val: jshort,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetIntField(
 	env: *mut JNIEnv,
@@ -775,7 +979,8 @@ pub unsafe extern "system" fn SetIntField(
 	fieldID: jfieldID,
 	val: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetLongField(
 	env: *mut JNIEnv,
@@ -783,7 +988,8 @@ pub unsafe extern "system" fn SetLongField(
 	fieldID: jfieldID,
 	val: jlong,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetFloatField(
 	env: *mut JNIEnv,
@@ -791,7 +997,8 @@ pub unsafe extern "system" fn SetFloatField(
 	fieldID: jfieldID,
 	val: jfloat,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn SetDoubleField(
 	env: *mut JNIEnv,
@@ -799,7 +1006,8 @@ pub unsafe extern "system" fn SetDoubleField(
 	fieldID: jfieldID,
 	val: jdouble,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_field(obj, fieldID, val)
 }
 pub unsafe extern "system" fn GetStaticMethodID(
 	env: *mut JNIEnv,
@@ -807,15 +1015,16 @@ pub unsafe extern "system" fn GetStaticMethodID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jmethodID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_method_id(clazz, name, sig, true)
 }
 pub unsafe extern "C" fn CallStaticObjectMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticObjectMethodV(
 	env: *mut JNIEnv,
@@ -823,7 +1032,8 @@ pub unsafe extern "system" fn CallStaticObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticObjectMethodA(
 	env: *mut JNIEnv,
@@ -831,15 +1041,16 @@ pub unsafe extern "system" fn CallStaticObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticBooleanMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticBooleanMethodV(
 	env: *mut JNIEnv,
@@ -847,7 +1058,8 @@ pub unsafe extern "system" fn CallStaticBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticBooleanMethodA(
 	env: *mut JNIEnv,
@@ -855,15 +1067,16 @@ pub unsafe extern "system" fn CallStaticBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticByteMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticByteMethodV(
 	env: *mut JNIEnv,
@@ -871,7 +1084,8 @@ pub unsafe extern "system" fn CallStaticByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticByteMethodA(
 	env: *mut JNIEnv,
@@ -879,15 +1093,16 @@ pub unsafe extern "system" fn CallStaticByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticCharMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticCharMethodV(
 	env: *mut JNIEnv,
@@ -895,7 +1110,8 @@ pub unsafe extern "system" fn CallStaticCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticCharMethodA(
 	env: *mut JNIEnv,
@@ -903,15 +1119,16 @@ pub unsafe extern "system" fn CallStaticCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticShortMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticShortMethodV(
 	env: *mut JNIEnv,
@@ -919,7 +1136,8 @@ pub unsafe extern "system" fn CallStaticShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticShortMethodA(
 	env: *mut JNIEnv,
@@ -927,15 +1145,16 @@ pub unsafe extern "system" fn CallStaticShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticIntMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticIntMethodV(
 	env: *mut JNIEnv,
@@ -943,7 +1162,8 @@ pub unsafe extern "system" fn CallStaticIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticIntMethodA(
 	env: *mut JNIEnv,
@@ -951,15 +1171,16 @@ pub unsafe extern "system" fn CallStaticIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticLongMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticLongMethodV(
 	env: *mut JNIEnv,
@@ -967,7 +1188,8 @@ pub unsafe extern "system" fn CallStaticLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticLongMethodA(
 	env: *mut JNIEnv,
@@ -975,15 +1197,16 @@ pub unsafe extern "system" fn CallStaticLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticFloatMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticFloatMethodV(
 	env: *mut JNIEnv,
@@ -991,7 +1214,8 @@ pub unsafe extern "system" fn CallStaticFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticFloatMethodA(
 	env: *mut JNIEnv,
@@ -999,15 +1223,16 @@ pub unsafe extern "system" fn CallStaticFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticDoubleMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticDoubleMethodV(
 	env: *mut JNIEnv,
@@ -1015,7 +1240,8 @@ pub unsafe extern "system" fn CallStaticDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticDoubleMethodA(
 	env: *mut JNIEnv,
@@ -1023,15 +1249,16 @@ pub unsafe extern "system" fn CallStaticDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "C" fn CallStaticVoidMethod(
 	env: *mut JNIEnv,
 	cls: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) {
-	todo!();
+	util::call_method(ptr::null_mut(), methodID, JArgs::variadic(&mut args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticVoidMethodV(
 	env: *mut JNIEnv,
@@ -1039,7 +1266,8 @@ pub unsafe extern "system" fn CallStaticVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::V(args), Invoke::Static)
 }
 pub unsafe extern "system" fn CallStaticVoidMethodA(
 	env: *mut JNIEnv,
@@ -1047,7 +1275,8 @@ pub unsafe extern "system" fn CallStaticVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::call_method(ptr::null_mut(), methodID, JArgs::A(args), Invoke::Static)
 }
 pub unsafe extern "system" fn GetStaticFieldID(
 	env: *mut JNIEnv,
@@ -1055,70 +1284,80 @@ pub unsafe extern "system" fn GetStaticFieldID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jfieldID {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_field_id(clazz, name, sig, true)
 }
 pub unsafe extern "system" fn GetStaticObjectField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticBooleanField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticByteField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticCharField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticShortField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticIntField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticLongField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticFloatField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn GetStaticDoubleField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_static_field(fieldID)
 }
 pub unsafe extern "system" fn SetStaticObjectField(
 	env: *mut JNIEnv,
@@ -1126,7 +1365,8 @@ pub unsafe extern "system" fn SetStaticObjectField(
 	fieldID: jfieldID,
 	value: jobject,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticBooleanField(
 	env: *mut JNIEnv,
@@ -1134,7 +1374,8 @@ pub unsafe extern "system" fn SetStaticBooleanField(
 	fieldID: jfieldID,
 	value: jboolean,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticByteField(
 	env: *mut JNIEnv,
@@ -1142,7 +1383,8 @@ pub unsafe extern "system" fn SetStaticByteField(
 	fieldID: jfieldID,
 	value: jbyte,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticCharField(
 	env: *mut JNIEnv,
@@ -1150,7 +1392,8 @@ pub unsafe extern "system" fn SetStaticCharField(
 	fieldID: jfieldID,
 	value: jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticShortField(
 	env: *mut JNIEnv,
@@ -1158,7 +1401,8 @@ pub unsafe extern "system" fn SetStaticShortField(
 	fieldID: jfieldID,
 	value: jshort,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticIntField(
 	env: *mut JNIEnv,
@@ -1166,7 +1410,8 @@ pub unsafe extern "system" fn SetStaticIntField(
 	fieldID: jfieldID,
 	value: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticLongField(
 	env: *mut JNIEnv,
@@ -1174,7 +1419,8 @@ pub unsafe extern "system" fn SetStaticLongField(
 	fieldID: jfieldID,
 	value: jlong,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticFloatField(
 	env: *mut JNIEnv,
@@ -1182,7 +1428,8 @@ pub unsafe extern "system" fn SetStaticFloatField(
 	fieldID: jfieldID,
 	value: jfloat,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn SetStaticDoubleField(
 	env: *mut JNIEnv,
@@ -1190,54 +1437,91 @@ pub unsafe extern "system" fn SetStaticDoubleField(
 	fieldID: jfieldID,
 	value: jdouble,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_static_field(fieldID, value)
 }
 pub unsafe extern "system" fn NewString(
 	env: *mut JNIEnv,
 	unicode: *const jchar,
 	len: jsize,
 ) -> jstring {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let chars = std::slice::from_raw_parts(unicode, len as usize);
+	util::oop_to_jobject(&vm::util::oop::new_java_lang_string4(chars))
 }
 pub unsafe extern "system" fn GetStringLength(env: *mut JNIEnv, str: jstring) -> jsize {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::string_chars(str) {
+		Some(chars) => chars.len() as jsize,
+		None => 0,
+	}
 }
 pub unsafe extern "system" fn GetStringChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	isCopy: *mut jboolean,
 ) -> *const jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if !isCopy.is_null() {
+		*isCopy = JNI_TRUE;
+	}
+	match util::string_chars(str) {
+		Some(chars) => util::malloc_copy(&chars, 0),
+		None => ptr::null(),
+	}
 }
 pub unsafe extern "system" fn ReleaseStringChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	chars: *const jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	libc::free(chars as *mut c_void);
 }
 pub unsafe extern "system" fn NewStringUTF(env: *mut JNIEnv, utf: *const c_char) -> jstring {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let utf = CStr::from_ptr(utf).to_bytes();
+	util::oop_to_jobject(&vm::util::oop::new_java_lang_string3(utf))
 }
 pub unsafe extern "system" fn GetStringUTFLength(env: *mut JNIEnv, str: jstring) -> jsize {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::string_chars(str) {
+		Some(chars) => constant_pool::to_modified_utf8(&chars).len() as jsize,
+		None => 0,
+	}
 }
 pub unsafe extern "system" fn GetStringUTFChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	isCopy: *mut jboolean,
 ) -> *const c_char {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if !isCopy.is_null() {
+		*isCopy = JNI_TRUE;
+	}
+	match util::string_chars(str) {
+		Some(chars) => {
+			let utf = constant_pool::to_modified_utf8(&chars);
+			let ptr = util::malloc_copy(&utf, 1);
+			if !ptr.is_null() {
+				*ptr.add(utf.len()) = 0;
+			}
+			ptr as *const c_char
+		}
+		None => ptr::null(),
+	}
 }
 pub unsafe extern "system" fn ReleaseStringUTFChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	chars: *const c_char,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	libc::free(chars as *mut c_void);
 }
 pub unsafe extern "system" fn GetArrayLength(env: *mut JNIEnv, array: jarray) -> jsize {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_length(array)
 }
 pub unsafe extern "system" fn NewObjectArray(
 	env: *mut JNIEnv,
@@ -1245,14 +1529,56 @@ pub unsafe extern "system" fn NewObjectArray(
 	clazz: jclass,
 	init: jobject,
 ) -> jobjectArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if len < 0 {
+		util::throw(J_NASE, Some(len.to_string()));
+		return ptr::null_mut();
+	}
+	let class = match util::jclass_to_class_ref(clazz) {
+		Some(class) => class,
+		None => {
+			util::throw(J_NPE, None);
+			return ptr::null_mut();
+		}
+	};
+	
+	let name = class.get_class().name.clone();
+	let mut ary_name = vec![b'['];
+	if name.first() == Some(&b'[') {
+		ary_name.extend_from_slice(name.as_slice());
+	} else {
+		ary_name.push(b'L');
+		ary_name.extend_from_slice(name.as_slice());
+		ary_name.push(b';');
+	}
+	let ary_class = runtime::require_class3(None, &ary_name).unwrap();
+	let init = util::jobject_to_oop(init);
+	let elements = vec![init; len as usize];
+	util::oop_to_jobject(&Oop::new_ref_ary2(ary_class, elements))
 }
 pub unsafe extern "system" fn GetObjectArrayElement(
 	env: *mut JNIEnv,
 	array: jobjectArray,
 	index: jsize,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(array) {
+		Oop::Null => {
+			util::throw(J_NPE, None);
+			ptr::null_mut()
+		}
+		array => {
+			let rf = array.extract_ref();
+			let ary = rf.extract_array();
+			match index as usize {
+				i if index >= 0 && i < ary.len() => util::oop_to_jobject(&ary.get(i)),
+				_ => {
+					util::throw(J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(index.to_string()));
+					ptr::null_mut()
+				}
+			}
+		}
+	}
 }
 pub unsafe extern "system" fn SetObjectArrayElement(
 	env: *mut JNIEnv,
@@ -1260,87 +1586,114 @@ pub unsafe extern "system" fn SetObjectArrayElement(
 	index: jsize,
 	val: jobject,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(array) {
+		Oop::Null => util::throw(J_NPE, None),
+		array => {
+			let rf = array.extract_ref();
+			let ary = rf.extract_mut_array();
+			match index as usize {
+				i if index >= 0 && i < ary.len() => ary.set(i, &util::jobject_to_oop(val)),
+				_ => util::throw(J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(index.to_string())),
+			}
+		}
+	}
 }
 pub unsafe extern "system" fn NewBooleanArray(env: *mut JNIEnv, len: jsize) -> jbooleanArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_bool_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewByteArray(env: *mut JNIEnv, len: jsize) -> jbyteArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_byte_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewCharArray(env: *mut JNIEnv, len: jsize) -> jcharArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_char_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewShortArray(env: *mut JNIEnv, len: jsize) -> jshortArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_short_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewIntArray(env: *mut JNIEnv, len: jsize) -> jintArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_int_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewLongArray(env: *mut JNIEnv, len: jsize) -> jlongArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_long_ary2(vec![0; n]))
 }
 pub unsafe extern "system" fn NewFloatArray(env: *mut JNIEnv, len: jsize) -> jfloatArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_float_ary2(vec![0.0; n]))
 }
 pub unsafe extern "system" fn NewDoubleArray(env: *mut JNIEnv, len: jsize) -> jdoubleArray {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::new_type_array(len, |n| Oop::new_double_ary2(vec![0.0; n]))
 }
 pub unsafe extern "system" fn GetBooleanArrayElements(
 	env: *mut JNIEnv,
 	array: jbooleanArray,
 	isCopy: *mut jboolean,
 ) -> *mut jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jboolean
 }
 pub unsafe extern "system" fn GetByteArrayElements(
 	env: *mut JNIEnv,
 	array: jbyteArray,
 	isCopy: *mut jboolean,
 ) -> *mut jbyte {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jbyte
 }
 pub unsafe extern "system" fn GetCharArrayElements(
 	env: *mut JNIEnv,
 	array: jcharArray,
 	isCopy: *mut jboolean,
 ) -> *mut jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jchar
 }
 pub unsafe extern "system" fn GetShortArrayElements(
 	env: *mut JNIEnv,
 	array: jshortArray,
 	isCopy: *mut jboolean,
 ) -> *mut jshort {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jshort
 }
 pub unsafe extern "system" fn GetIntArrayElements(
 	env: *mut JNIEnv,
 	array: jintArray,
 	isCopy: *mut jboolean,
 ) -> *mut jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jint
 }
 pub unsafe extern "system" fn GetLongArrayElements(
 	env: *mut JNIEnv,
 	array: jlongArray,
 	isCopy: *mut jboolean,
 ) -> *mut jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jlong
 }
 pub unsafe extern "system" fn GetFloatArrayElements(
 	env: *mut JNIEnv,
 	array: jfloatArray,
 	isCopy: *mut jboolean,
 ) -> *mut jfloat {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jfloat
 }
 pub unsafe extern "system" fn GetDoubleArrayElements(
 	env: *mut JNIEnv,
 	array: jdoubleArray,
 	isCopy: *mut jboolean,
 ) -> *mut jdouble {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy) as *mut jdouble
 }
 pub unsafe extern "system" fn ReleaseBooleanArrayElements(
 	env: *mut JNIEnv,
@@ -1348,7 +1701,8 @@ pub unsafe extern "system" fn ReleaseBooleanArrayElements(
 	elems: *mut jboolean,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseByteArrayElements(
 	env: *mut JNIEnv,
@@ -1356,7 +1710,8 @@ pub unsafe extern "system" fn ReleaseByteArrayElements(
 	elems: *mut jbyte,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseCharArrayElements(
 	env: *mut JNIEnv,
@@ -1364,7 +1719,8 @@ pub unsafe extern "system" fn ReleaseCharArrayElements(
 	elems: *mut jchar,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseShortArrayElements(
 	env: *mut JNIEnv,
@@ -1372,7 +1728,8 @@ pub unsafe extern "system" fn ReleaseShortArrayElements(
 	elems: *mut jshort,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseIntArrayElements(
 	env: *mut JNIEnv,
@@ -1380,7 +1737,8 @@ pub unsafe extern "system" fn ReleaseIntArrayElements(
 	elems: *mut jint,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseLongArrayElements(
 	env: *mut JNIEnv,
@@ -1388,7 +1746,8 @@ pub unsafe extern "system" fn ReleaseLongArrayElements(
 	elems: *mut jlong,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseFloatArrayElements(
 	env: *mut JNIEnv,
@@ -1396,7 +1755,8 @@ pub unsafe extern "system" fn ReleaseFloatArrayElements(
 	elems: *mut jfloat,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn ReleaseDoubleArrayElements(
 	env: *mut JNIEnv,
@@ -1404,7 +1764,8 @@ pub unsafe extern "system" fn ReleaseDoubleArrayElements(
 	elems: *mut jdouble,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn GetBooleanArrayRegion(
 	env: *mut JNIEnv,
@@ -1413,7 +1774,8 @@ pub unsafe extern "system" fn GetBooleanArrayRegion(
 	l: jsize,
 	buf: *mut jboolean,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, l, buf)
 }
 pub unsafe extern "system" fn GetByteArrayRegion(
 	env: *mut JNIEnv,
@@ -1422,7 +1784,8 @@ pub unsafe extern "system" fn GetByteArrayRegion(
 	len: jsize,
 	buf: *mut jbyte,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetCharArrayRegion(
 	env: *mut JNIEnv,
@@ -1431,7 +1794,8 @@ pub unsafe extern "system" fn GetCharArrayRegion(
 	len: jsize,
 	buf: *mut jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetShortArrayRegion(
 	env: *mut JNIEnv,
@@ -1440,7 +1804,8 @@ pub unsafe extern "system" fn GetShortArrayRegion(
 	len: jsize,
 	buf: *mut jshort,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetIntArrayRegion(
 	env: *mut JNIEnv,
@@ -1449,7 +1814,8 @@ pub unsafe extern "system" fn GetIntArrayRegion(
 	len: jsize,
 	buf: *mut jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetLongArrayRegion(
 	env: *mut JNIEnv,
@@ -1458,7 +1824,8 @@ pub unsafe extern "system" fn GetLongArrayRegion(
 	len: jsize,
 	buf: *mut jlong,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetFloatArrayRegion(
 	env: *mut JNIEnv,
@@ -1467,7 +1834,8 @@ pub unsafe extern "system" fn GetFloatArrayRegion(
 	len: jsize,
 	buf: *mut jfloat,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn GetDoubleArrayRegion(
 	env: *mut JNIEnv,
@@ -1476,7 +1844,8 @@ pub unsafe extern "system" fn GetDoubleArrayRegion(
 	len: jsize,
 	buf: *mut jdouble,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::get_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetBooleanArrayRegion(
 	env: *mut JNIEnv,
@@ -1485,7 +1854,8 @@ pub unsafe extern "system" fn SetBooleanArrayRegion(
 	l: jsize,
 	buf: *const jboolean,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, l, buf)
 }
 pub unsafe extern "system" fn SetByteArrayRegion(
 	env: *mut JNIEnv,
@@ -1494,7 +1864,8 @@ pub unsafe extern "system" fn SetByteArrayRegion(
 	len: jsize,
 	buf: *const jbyte,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetCharArrayRegion(
 	env: *mut JNIEnv,
@@ -1503,7 +1874,8 @@ pub unsafe extern "system" fn SetCharArrayRegion(
 	len: jsize,
 	buf: *const jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetShortArrayRegion(
 	env: *mut JNIEnv,
@@ -1512,7 +1884,8 @@ pub unsafe extern "system" fn SetShortArrayRegion(
 	len: jsize,
 	buf: *const jshort,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetIntArrayRegion(
 	env: *mut JNIEnv,
@@ -1521,7 +1894,8 @@ pub unsafe extern "system" fn SetIntArrayRegion(
 	len: jsize,
 	buf: *const jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetLongArrayRegion(
 	env: *mut JNIEnv,
@@ -1530,7 +1904,8 @@ pub unsafe extern "system" fn SetLongArrayRegion(
 	len: jsize,
 	buf: *const jlong,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetFloatArrayRegion(
 	env: *mut JNIEnv,
@@ -1539,7 +1914,8 @@ pub unsafe extern "system" fn SetFloatArrayRegion(
 	len: jsize,
 	buf: *const jfloat,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn SetDoubleArrayRegion(
 	env: *mut JNIEnv,
@@ -1548,7 +1924,8 @@ pub unsafe extern "system" fn SetDoubleArrayRegion(
 	len: jsize,
 	buf: *const jdouble,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::set_array_region(array, start, len, buf)
 }
 pub unsafe extern "system" fn RegisterNatives(
 	env: *mut JNIEnv,
@@ -1556,19 +1933,74 @@ pub unsafe extern "system" fn RegisterNatives(
 	methods: *const JNINativeMethod,
 	nMethods: jint,
 ) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let class = match util::jclass_to_class_ref(clazz) {
+		Some(class) => class,
+		None => {
+			util::throw(J_NPE, None);
+			return JNI_ERR;
+		}
+	};
+
+	let methods = std::slice::from_raw_parts(methods, nMethods as usize);
+	for it in methods {
+		let name = CStr::from_ptr(it.name).to_bytes();
+		let signature = CStr::from_ptr(it.signature).to_bytes();
+		if !vm::native::jni::register_native(&class, name, signature, it.fnPtr as usize) {
+			let msg = format!(
+				"{}.{}{}",
+				String::from_utf8_lossy(class.get_class().name.as_slice()).replace("/", "."),
+				String::from_utf8_lossy(name),
+				String::from_utf8_lossy(signature)
+			);
+			util::throw(J_NO_SUCH_METHOD_ERROR, Some(msg));
+			return JNI_ERR;
+		}
+	}
+
+	JNI_OK
 }
 pub unsafe extern "system" fn UnregisterNatives(env: *mut JNIEnv, clazz: jclass) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if let Some(class) = util::jclass_to_class_ref(clazz) {
+		vm::native::jni::unregister_natives(&class);
+	}
+	JNI_OK
 }
 pub unsafe extern "system" fn MonitorEnter(env: *mut JNIEnv, obj: jobject) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(obj) {
+		Oop::Null => {
+			util::throw(J_NPE, None);
+			JNI_ERR
+		}
+		obj => {
+			obj.extract_ref().monitor_enter();
+			JNI_OK
+		}
+	}
 }
 pub unsafe extern "system" fn MonitorExit(env: *mut JNIEnv, obj: jobject) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::jobject_to_oop(obj) {
+		Oop::Null => {
+			util::throw(J_NPE, None);
+			JNI_ERR
+		}
+		obj => {
+			if obj.extract_ref().monitor_exit() {
+				JNI_OK
+			} else {
+				util::throw(J_ILLEGAL_MONITOR_STATE, None);
+				JNI_ERR
+			}
+		}
+	}
 }
 pub unsafe extern "system" fn GetJavaVM(env: *mut JNIEnv, vm: *mut *mut JavaVM) -> jint {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	*vm = vm::native::jni::java_vm() as *mut JavaVM;
+	JNI_OK
 }
 pub unsafe extern "system" fn GetStringRegion(
 	env: *mut JNIEnv,
@@ -1577,7 +2009,10 @@ pub unsafe extern "system" fn GetStringRegion(
 	len: jsize,
 	buf: *mut jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if let Some(chars) = util::string_region(str, start, len) {
+		ptr::copy_nonoverlapping(chars.as_ptr(), buf, chars.len());
+	}
 }
 pub unsafe extern "system" fn GetStringUTFRegion(
 	env: *mut JNIEnv,
@@ -1586,14 +2021,20 @@ pub unsafe extern "system" fn GetStringUTFRegion(
 	len: jsize,
 	buf: *mut c_char,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	if let Some(chars) = util::string_region(str, start, len) {
+		let utf = constant_pool::to_modified_utf8(&chars);
+		ptr::copy_nonoverlapping(utf.as_ptr(), buf as *mut u8, utf.len());
+		*buf.add(utf.len()) = 0;
+	}
 }
 pub unsafe extern "system" fn GetPrimitiveArrayCritical(
 	env: *mut JNIEnv,
 	array: jarray,
 	isCopy: *mut jboolean,
 ) -> *mut c_void {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::array_elements(array, isCopy)
 }
 pub unsafe extern "system" fn ReleasePrimitiveArrayCritical(
 	env: *mut JNIEnv,
@@ -1601,47 +2042,89 @@ pub unsafe extern "system" fn ReleasePrimitiveArrayCritical(
 	carray: *mut c_void,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	//the elements are not copied, see array_elements
 }
 pub unsafe extern "system" fn GetStringCritical(
 	env: *mut JNIEnv,
 	string: jstring,
 	isCopy: *mut jboolean,
 ) -> *const jchar {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	GetStringChars(env, string, isCopy)
 }
 pub unsafe extern "system" fn ReleaseStringCritical(
 	env: *mut JNIEnv,
 	string: jstring,
 	cstring: *const jchar,
 ) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	ReleaseStringChars(env, string, cstring)
 }
 pub unsafe extern "system" fn NewWeakGlobalRef(env: *mut JNIEnv, obj: jobject) -> jweak {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::new_weak_global(&util::jobject_to_oop(obj)) as jweak
 }
 pub unsafe extern "system" fn DeleteWeakGlobalRef(env: *mut JNIEnv, ref_: jweak) {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	refs::delete_weak_global(ref_ as refs::JObject);
 }
 pub unsafe extern "system" fn ExceptionCheck(env: *mut JNIEnv) -> jboolean {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	util::has_ex() as jboolean
 }
 pub unsafe extern "system" fn NewDirectByteBuffer(
 	env: *mut JNIEnv,
 	address: *mut c_void,
 	capacity: jlong,
 ) -> jobject {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	let class = match runtime::require_class3(None, b"java/nio/DirectByteBuffer") {
+		Some(class) => class,
+		None => return ptr::null_mut(),
+	};
+	if util::init_class(&class).is_none() {
+		return ptr::null_mut();
+	}
+	
+	let ctor = class
+		.get_class()
+		.get_this_class_method(&new_br("<init>"), &new_br("(JI)V"))
+		.unwrap();
+	let buf = Oop::new_inst(class);
+	let args = vec![
+		buf.clone(),
+		Oop::new_long(address as i64),
+		Oop::new_int(capacity as i32),
+	];
+	match util::invoke(ctor, args, true) {
+		Some(_) => util::oop_to_jobject(&buf),
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn GetDirectBufferAddress(
 	env: *mut JNIEnv,
 	buf: jobject,
 ) -> *mut c_void {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::buffer_field(buf, b"address", b"J") {
+		Some(v) => v.extract_long() as *mut c_void,
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn GetDirectBufferCapacity(env: *mut JNIEnv, buf: jobject) -> jlong {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match util::buffer_field(buf, b"capacity", b"I") {
+		Some(v) => v.extract_int() as jlong,
+		None => -1,
+	}
 }
 pub unsafe extern "system" fn GetObjectRefType(env: *mut JNIEnv, obj: jobject) -> jobjectRefType {
-	todo!();
+	let _vm = runtime::gc::enter_vm();
+	match refs::ref_type(obj as refs::JObject) {
+		refs::RefType::Local => jobjectRefType::JNILocalRefType,
+		refs::RefType::Global => jobjectRefType::JNIGlobalRefType,
+		refs::RefType::WeakGlobal => jobjectRefType::JNIWeakGlobalRefType,
+		refs::RefType::Invalid => jobjectRefType::JNIInvalidRefType,
+	}
 }
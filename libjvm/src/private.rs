@@ -8,6 +8,7 @@ pub unsafe extern "system" fn JVM_FindClassFromBootLoader(
 	env: *const JNIEnv,
 	name: *const i8,
 ) -> jclass {
+	let _vm = vm::runtime::gc::enter_vm();
 	let class_name = std::ffi::CStr::from_ptr(name);
 	let bytes = class_name.to_bytes();
 	let class = vm::runtime::require_class3(None, bytes);
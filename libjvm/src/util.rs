@@ -1,12 +1,728 @@
 #![allow(unused_imports)]
+// the jni handles passed in should be valid, as the jni spec requires
+#![allow(clippy::missing_safety_doc)]
 
 /// Conversions between jvm and jni api
-use jni_sys::{jclass, JNIEnv};
+use classfile::consts::{
+	J_ARRAY_INDEX_OUT_OF_BOUNDS, J_CLASS, J_CLASS_LOADER, J_INSTANTIATION_EXCEPTION, J_NASE,
+	J_NO_CLASS_DEF_FOUND_ERROR, J_NO_SUCH_FIELD_ERROR, J_NO_SUCH_METHOD_ERROR, J_NPE, J_OBJECT,
+	J_STRING_INDEX_OUT_OF_BOUNDS,
+};
+use classfile::flags::ACC_STATIC;
+use classfile::SignatureType;
+use jni_sys::{
+	jarray, jboolean, jbyte, jchar, jclass, jdouble, jfieldID, jfloat, jint, jlong, jmethodID,
+	jobject, jshort, jsize, jvalue, JNIEnv,
+};
+use libc::{c_char, c_void};
+use std::ffi::{CStr, VaList};
+use std::sync::Arc;
+use vm::new_br;
+use vm::oop::{self, Class, Oop, OopPtr, RefKind, TypeArrayDesc};
+use vm::runtime::{self, thread, ClassLoader, DataArea, JavaCall};
+use vm::types::{ClassRef, FieldIdRef, MethodIdRef};
 
-pub fn class_ref_to_jclass(class_ref: Option<vm::types::ClassRef>) -> jclass {
+/// jclass is a reference to the mirror of class
+pub fn class_ref_to_jclass(class_ref: Option<ClassRef>) -> jclass {
 	if let Some(class_ref) = class_ref {
-		class_ref.as_ref() as *const _ as jclass
+		let mirror = class_ref.get_class().get_mirror();
+		vm::native::jni::new_local(&mirror) as jclass
 	} else {
 		std::ptr::null_mut()
 	}
 }
+
+pub unsafe fn jclass_to_class_ref(cls: jclass) -> Option<ClassRef> {
+	match jobject_to_oop(cls) {
+		Oop::Null => None,
+		mirror => mirror.extract_ref().extract_mirror().target.clone(),
+	}
+}
+
+pub unsafe fn jobject_to_oop(obj: jobject) -> Oop {
+	vm::native::jni::from_jobject(obj as vm::native::jni::JObject)
+}
+
+pub fn oop_to_jobject(v: &Oop) -> jobject {
+	vm::native::jni::new_local(v) as jobject
+}
+
+/// jmethodID is the address of MethodId, which is held by its class
+pub fn mir_to_jmethod_id(mir: &MethodIdRef) -> jmethodID {
+	Arc::as_ptr(mir) as jmethodID
+}
+
+pub unsafe fn jmethod_id_to_mir(id: jmethodID) -> MethodIdRef {
+	let ptr = id as *const vm::runtime::method::MethodId;
+	Arc::increment_strong_count(ptr);
+	Arc::from_raw(ptr)
+}
+
+/// jfieldID is the address of FieldId, which is held by its class
+pub fn fir_to_jfield_id(fir: &FieldIdRef) -> jfieldID {
+	Arc::as_ptr(fir) as jfieldID
+}
+
+pub unsafe fn jfield_id_to_fir(id: jfieldID) -> FieldIdRef {
+	let ptr = id as *const vm::oop::field::FieldId;
+	Arc::increment_strong_count(ptr);
+	Arc::from_raw(ptr)
+}
+
+/// Make the exception pending in current thread
+pub fn throw(name: &[u8], msg: Option<String>) {
+	let ex = vm::runtime::exception::new(name, msg);
+	throw_oop(ex);
+}
+
+pub fn throw_oop(ex: Oop) {
+	let jt = vm::runtime::thread::current_java_thread();
+	jt.write().unwrap().set_ex(ex);
+}
+
+pub fn has_ex() -> bool {
+	let jt = vm::runtime::thread::current_java_thread();
+	let jt = jt.read().unwrap();
+	jt.ex.is_some()
+}
+
+/// Load & initialize the class, None if failed, with the exception pending
+pub fn init_class(class: &ClassRef) -> Option<()> {
+	oop::class::init_class(class);
+	oop::class::init_class_fully(class);
+	if thread::is_meet_ex() {
+		None
+	} else {
+		Some(())
+	}
+}
+
+/// The class of the object, the same as Object.getClass()
+pub fn object_class(v: &Oop) -> ClassRef {
+	let rf = v.extract_ref();
+	let ptr = rf.get_raw_ptr();
+	unsafe {
+		match &(*ptr).v {
+			RefKind::Inst(inst) => inst.class.clone(),
+			RefKind::Array(ary) => ary.class.clone(),
+			RefKind::Mirror(_) => runtime::require_class3(None, J_CLASS).unwrap(),
+			RefKind::TypeArray(ary) => {
+				let name: &[u8] = match ary {
+					TypeArrayDesc::Byte(_) => b"[B",
+					TypeArrayDesc::Bool(_) => b"[Z",
+					TypeArrayDesc::Char(_) => b"[C",
+					TypeArrayDesc::Short(_) => b"[S",
+					TypeArrayDesc::Float(_) => b"[F",
+					TypeArrayDesc::Double(_) => b"[D",
+					TypeArrayDesc::Int(_) => b"[I",
+					TypeArrayDesc::Long(_) => b"[J",
+				};
+				runtime::require_class3(None, name).unwrap()
+			}
+		}
+	}
+}
+
+/// The jni value types
+pub trait JValue: Sized {
+	fn zero() -> Self;
+	fn from_oop(v: &Oop) -> Self;
+	/// # Safety
+	///
+	/// A jobject should be null or a live reference
+	unsafe fn into_oop(self) -> Oop;
+}
+
+macro_rules! def_jvalue_int {
+	($t:ty) => {
+		impl JValue for $t {
+			fn zero() -> Self {
+				0
+			}
+			fn from_oop(v: &Oop) -> Self {
+				v.extract_int() as $t
+			}
+			unsafe fn into_oop(self) -> Oop {
+				Oop::new_int(self as i32)
+			}
+		}
+	};
+}
+
+def_jvalue_int!(jbyte);
+def_jvalue_int!(jchar);
+def_jvalue_int!(jshort);
+def_jvalue_int!(jint);
+
+impl JValue for jboolean {
+	fn zero() -> Self {
+		0
+	}
+	fn from_oop(v: &Oop) -> Self {
+		(v.extract_int() != 0) as jboolean
+	}
+	unsafe fn into_oop(self) -> Oop {
+		Oop::new_int((self != 0) as i32)
+	}
+}
+
+impl JValue for jlong {
+	fn zero() -> Self {
+		0
+	}
+	fn from_oop(v: &Oop) -> Self {
+		v.extract_long()
+	}
+	unsafe fn into_oop(self) -> Oop {
+		Oop::new_long(self)
+	}
+}
+
+impl JValue for jfloat {
+	fn zero() -> Self {
+		0.0
+	}
+	fn from_oop(v: &Oop) -> Self {
+		v.extract_float()
+	}
+	unsafe fn into_oop(self) -> Oop {
+		Oop::new_float(self)
+	}
+}
+
+impl JValue for jdouble {
+	fn zero() -> Self {
+		0.0
+	}
+	fn from_oop(v: &Oop) -> Self {
+		v.extract_double()
+	}
+	unsafe fn into_oop(self) -> Oop {
+		Oop::new_double(self)
+	}
+}
+
+impl JValue for jobject {
+	fn zero() -> Self {
+		std::ptr::null_mut()
+	}
+	fn from_oop(v: &Oop) -> Self {
+		oop_to_jobject(v)
+	}
+	unsafe fn into_oop(self) -> Oop {
+		jobject_to_oop(self)
+	}
+}
+
+//for the methods returning void
+impl JValue for () {
+	fn zero() -> Self {}
+	fn from_oop(_v: &Oop) -> Self {}
+	unsafe fn into_oop(self) -> Oop {
+		Oop::Null
+	}
+}
+
+/// The arguments of Call<Type>Method, NewObject...
+pub enum JArgs {
+	/// va_list of C, or the args of the variadic function
+	V(*mut c_void),
+	/// the array of jvalue
+	A(*const jvalue),
+}
+
+impl JArgs {
+	pub fn variadic(args: &mut VaList) -> Self {
+		JArgs::V(args as *mut VaList as *mut c_void)
+	}
+
+	/// Read the args by the types of method, the narrow types are promoted
+	/// to int and float to double when passed through va_list
+	pub unsafe fn to_oops(&self, types: &[SignatureType]) -> Vec<Oop> {
+		match self {
+			JArgs::V(ap) => {
+				let ap = &mut *(*ap as *mut VaList);
+				types
+					.iter()
+					.map(|t| match t {
+						SignatureType::Boolean => Oop::new_int((ap.arg::<jint>() as u8 != 0) as i32),
+						SignatureType::Byte => Oop::new_int(ap.arg::<jint>() as i8 as i32),
+						SignatureType::Char => Oop::new_int(ap.arg::<jint>() as u16 as i32),
+						SignatureType::Short => Oop::new_int(ap.arg::<jint>() as i16 as i32),
+						SignatureType::Int => Oop::new_int(ap.arg::<jint>()),
+						SignatureType::Long => Oop::new_long(ap.arg::<jlong>()),
+						SignatureType::Float => Oop::new_float(ap.arg::<jdouble>() as f32),
+						SignatureType::Double => Oop::new_double(ap.arg::<jdouble>()),
+						SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+							jobject_to_oop(ap.arg::<jobject>())
+						}
+						SignatureType::Void => unreachable!(),
+					})
+					.collect()
+			}
+			JArgs::A(values) => types
+				.iter()
+				.enumerate()
+				.map(|(i, t)| {
+					let v = *values.add(i);
+					match t {
+						SignatureType::Boolean => v.z.into_oop(),
+						SignatureType::Byte => v.b.into_oop(),
+						SignatureType::Char => v.c.into_oop(),
+						SignatureType::Short => v.s.into_oop(),
+						SignatureType::Int => v.i.into_oop(),
+						SignatureType::Long => v.j.into_oop(),
+						SignatureType::Float => v.f.into_oop(),
+						SignatureType::Double => v.d.into_oop(),
+						SignatureType::Object(_, _, _) | SignatureType::Array(_) => v.l.into_oop(),
+						SignatureType::Void => unreachable!(),
+					}
+				})
+				.collect(),
+		}
+	}
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Invoke {
+	Virtual,
+	Nonvirtual,
+	Static,
+}
+
+/// Invoke the method, None if an exception is thrown
+pub fn invoke(mir: MethodIdRef, args: Vec<Oop>, force_no_resolve: bool) -> Option<Oop> {
+	let mut jc = JavaCall::new_with_args(mir, args);
+	let area = DataArea::new(0);
+	jc.invoke(Some(&area), force_no_resolve);
+
+	if thread::is_meet_ex() {
+		return None;
+	}
+
+	let mut stack = area.stack.borrow_mut();
+	let v = match jc.mir.method.signature.retype {
+		SignatureType::Byte
+		| SignatureType::Char
+		| SignatureType::Boolean
+		| SignatureType::Short
+		| SignatureType::Int => Oop::new_int(stack.pop_int()),
+		SignatureType::Long => Oop::new_long(stack.pop_long()),
+		SignatureType::Float => Oop::new_float(stack.pop_float()),
+		SignatureType::Double => Oop::new_double(stack.pop_double()),
+		SignatureType::Object(_, _, _) | SignatureType::Array(_) => stack.pop_ref(),
+		SignatureType::Void => Oop::Null,
+	};
+	Some(v)
+}
+
+/// Call<Type>Method, CallNonvirtual<Type>Method & CallStatic<Type>Method
+pub unsafe fn call_method<T: JValue>(
+	obj: jobject,
+	method_id: jmethodID,
+	args: JArgs,
+	kind: Invoke,
+) -> T {
+	let mir = jmethod_id_to_mir(method_id);
+	let mut args = args.to_oops(&mir.method.signature.args);
+	if kind != Invoke::Static {
+		let this = jobject_to_oop(obj);
+		if this.is_null() {
+			throw(J_NPE, None);
+			return T::zero();
+		}
+		args.insert(0, this);
+	}
+
+	match invoke(mir, args, kind != Invoke::Virtual) {
+		Some(v) => T::from_oop(&v),
+		None => T::zero(),
+	}
+}
+
+/// AllocObject, without invoking the ctor
+pub unsafe fn alloc_object(clazz: jclass) -> Option<Oop> {
+	let class = match jclass_to_class_ref(clazz) {
+		Some(class) => class,
+		None => {
+			throw(J_INSTANTIATION_EXCEPTION, None);
+			return None;
+		}
+	};
+
+	{
+		let cls = class.get_class();
+		if !cls.is_instance() || cls.is_abstract() || cls.is_interface() {
+			let name = String::from_utf8_lossy(cls.name.as_slice()).replace("/", ".");
+			throw(J_INSTANTIATION_EXCEPTION, Some(name));
+			return None;
+		}
+	}
+
+	init_class(&class)?;
+	Some(Oop::new_inst(class))
+}
+
+/// NewObject, NewObjectV & NewObjectA
+pub unsafe fn new_object(clazz: jclass, method_id: jmethodID, args: JArgs) -> jobject {
+	let obj = match alloc_object(clazz) {
+		Some(obj) => obj,
+		None => return std::ptr::null_mut(),
+	};
+
+	let mir = jmethod_id_to_mir(method_id);
+	let mut args = args.to_oops(&mir.method.signature.args);
+	args.insert(0, obj.clone());
+	match invoke(mir, args, true) {
+		Some(_) => oop_to_jobject(&obj),
+		None => std::ptr::null_mut(),
+	}
+}
+
+pub unsafe fn get_field<T: JValue>(obj: jobject, field_id: jfieldID) -> T {
+	let fir = jfield_id_to_fir(field_id);
+	match jobject_to_oop(obj) {
+		Oop::Null => {
+			throw(J_NPE, None);
+			T::zero()
+		}
+		v => T::from_oop(&Class::get_field_value(v.extract_ref(), fir)),
+	}
+}
+
+pub unsafe fn set_field<T: JValue>(obj: jobject, field_id: jfieldID, value: T) {
+	let fir = jfield_id_to_fir(field_id);
+	match jobject_to_oop(obj) {
+		Oop::Null => throw(J_NPE, None),
+		v => Class::put_field_value(v.extract_ref(), fir, value.into_oop()),
+	}
+}
+
+pub unsafe fn get_static_field<T: JValue>(field_id: jfieldID) -> T {
+	let fir = jfield_id_to_fir(field_id);
+	let class = fir.field.class.clone();
+	let v = class.get_class().get_static_field_value(fir);
+	T::from_oop(&v)
+}
+
+pub unsafe fn set_static_field<T: JValue>(field_id: jfieldID, value: T) {
+	let fir = jfield_id_to_fir(field_id);
+	let class = fir.field.class.clone();
+	let cls = class.get_mut_class();
+	cls.put_static_field_value(fir, value.into_oop());
+}
+
+/// The chars of java.lang.String, None with NPE pending if it's null
+pub unsafe fn string_chars(str: jobject) -> Option<Vec<u16>> {
+	match jobject_to_oop(str) {
+		Oop::Null => {
+			throw(J_NPE, None);
+			None
+		}
+		v => Some(OopPtr::java_lang_string_value(v.extract_ref())),
+	}
+}
+
+/// Copy 'v' to the memory allocated by malloc, released by free
+pub unsafe fn malloc_copy<T: Copy>(v: &[T], extra: usize) -> *mut T {
+	let size = std::mem::size_of::<T>() * (v.len() + extra);
+	let ptr = libc::malloc(size.max(1)) as *mut T;
+	if !ptr.is_null() {
+		std::ptr::copy_nonoverlapping(v.as_ptr(), ptr, v.len());
+	}
+	ptr
+}
+
+pub fn new_type_array<F: FnOnce(usize) -> Oop>(len: jsize, f: F) -> jarray {
+	if len < 0 {
+		throw(J_NASE, Some(len.to_string()));
+		return std::ptr::null_mut();
+	}
+	oop_to_jobject(&f(len as usize))
+}
+
+pub unsafe fn array_length(array: jarray) -> jsize {
+	match jobject_to_oop(array) {
+		Oop::Null => {
+			throw(J_NPE, None);
+			0
+		}
+		v => {
+			let rf = v.extract_ref();
+			let ptr = rf.get_raw_ptr();
+			match &(*ptr).v {
+				RefKind::Array(ary) => ary.len() as jsize,
+				RefKind::TypeArray(ary) => ary.len() as jsize,
+				t => unreachable!("not an array: {:?}", t),
+			}
+		}
+	}
+}
+
+/// The address of the elements of primitive array, the array is never moved,
+/// so the elements are not copied
+pub unsafe fn array_elements(array: jarray, is_copy: *mut jboolean) -> *mut c_void {
+	if !is_copy.is_null() {
+		*is_copy = jni_sys::JNI_FALSE;
+	}
+
+	match jobject_to_oop(array) {
+		Oop::Null => {
+			throw(J_NPE, None);
+			std::ptr::null_mut()
+		}
+		v => {
+			let rf = v.extract_ref();
+			let ary = rf.extract_mut_type_array();
+			match ary {
+				TypeArrayDesc::Byte(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Bool(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Char(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Short(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Float(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Double(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Int(v) => v.as_mut_ptr() as *mut c_void,
+				TypeArrayDesc::Long(v) => v.as_mut_ptr() as *mut c_void,
+			}
+		}
+	}
+}
+
+//the address of array[start], None with the exception pending if out of bounds
+unsafe fn array_region<T>(array: jarray, start: jsize, len: jsize) -> Option<*mut T> {
+	let length = array_length(array);
+	if has_ex() {
+		return None;
+	}
+
+	if start < 0 || len < 0 || start as i64 + len as i64 > length as i64 {
+		let msg = format!(
+			"Array region {}..{} out of bounds for length {}",
+			start,
+			start as i64 + len as i64,
+			length
+		);
+		throw(J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
+		return None;
+	}
+
+	let elements = array_elements(array, std::ptr::null_mut()) as *mut T;
+	Some(elements.add(start as usize))
+}
+
+pub unsafe fn get_array_region<T>(array: jarray, start: jsize, len: jsize, buf: *mut T) {
+	if let Some(src) = array_region::<T>(array, start, len) {
+		std::ptr::copy_nonoverlapping(src, buf, len as usize);
+	}
+}
+
+pub unsafe fn set_array_region<T>(array: jarray, start: jsize, len: jsize, buf: *const T) {
+	if let Some(dst) = array_region::<T>(array, start, len) {
+		std::ptr::copy_nonoverlapping(buf, dst, len as usize);
+	}
+}
+
+/// FindClass, the class is initialized
+pub fn find_class(name: &[u8]) -> jclass {
+	let class = if name.contains(&b'.') {
+		None
+	} else {
+		let class_loader = match caller_class_loader() {
+			Some(class_loader) => class_loader,
+			None => return std::ptr::null_mut(),
+		};
+		runtime::require_class3(class_loader, name)
+	};
+
+	match class {
+		Some(class) => match init_class(&class) {
+			Some(_) => class_ref_to_jclass(Some(class)),
+			None => std::ptr::null_mut(),
+		},
+		None => {
+			if !has_ex() {
+				let name = String::from_utf8_lossy(name).into_owned();
+				throw(J_NO_CLASS_DEF_FOUND_ERROR, Some(name));
+			}
+			std::ptr::null_mut()
+		}
+	}
+}
+
+//the loader of the current native method's class, or the system class loader
+//if called through the invocation interface, None with the exception pending
+fn caller_class_loader() -> Option<Option<ClassLoader>> {
+	let jt = thread::current_java_thread();
+	let frame = jt.read().unwrap().frames.last().cloned();
+	if let Some(frame) = frame {
+		let frame = frame.read().unwrap();
+		return Some(frame.class.get_class().class_loader);
+	}
+
+	let class = runtime::require_class3(None, J_CLASS_LOADER)?;
+	init_class(&class)?;
+	let mir = class
+		.get_class()
+		.get_static_method(&new_br("getSystemClassLoader"), &new_br("()Ljava/lang/ClassLoader;"))
+		.ok()?;
+	let loader = invoke(mir, vec![], true)?;
+	Some(ClassLoader::from_oop(&loader))
+}
+
+//the methods of array are the ones of java.lang.Object
+unsafe fn lookup_class(clazz: jclass) -> Option<ClassRef> {
+	let class = jclass_to_class_ref(clazz)?;
+	if class.get_class().is_instance() {
+		Some(class)
+	} else {
+		runtime::require_class3(None, J_OBJECT)
+	}
+}
+
+/// GetMethodID & GetStaticMethodID, the class is initialized
+pub unsafe fn get_method_id(
+	clazz: jclass,
+	name: *const c_char,
+	sig: *const c_char,
+	is_static: bool,
+) -> jmethodID {
+	let name = Arc::new(CStr::from_ptr(name).to_bytes().to_vec());
+	let sig = Arc::new(CStr::from_ptr(sig).to_bytes().to_vec());
+	let class = lookup_class(clazz);
+	if let Some(class) = &class {
+		if init_class(class).is_none() {
+			return std::ptr::null_mut();
+		}
+	}
+
+	let mir = class.and_then(|class| {
+		let cls = class.get_class();
+		let mir = if name.as_slice() == b"<init>" {
+			cls.get_this_class_method(&name, &sig)
+		} else if is_static {
+			cls.get_static_method(&name, &sig)
+		} else {
+			cls.get_class_method(&name, &sig)
+				.or_else(|_| cls.get_interface_method(&name, &sig))
+		};
+		mir.ok().filter(|mir| mir.method.is_static() == is_static)
+	});
+
+	match mir {
+		Some(mir) => mir_to_jmethod_id(&mir),
+		None => {
+			let name = String::from_utf8_lossy(name.as_slice()).into_owned();
+			throw(J_NO_SUCH_METHOD_ERROR, Some(name));
+			std::ptr::null_mut()
+		}
+	}
+}
+
+/// GetFieldID & GetStaticFieldID, the class is initialized
+pub unsafe fn get_field_id(
+	clazz: jclass,
+	name: *const c_char,
+	sig: *const c_char,
+	is_static: bool,
+) -> jfieldID {
+	let name = Arc::new(CStr::from_ptr(name).to_bytes().to_vec());
+	let sig = Arc::new(CStr::from_ptr(sig).to_bytes().to_vec());
+	let class = jclass_to_class_ref(clazz);
+	if let Some(class) = &class {
+		if init_class(class).is_none() {
+			return std::ptr::null_mut();
+		}
+	}
+
+	let fir = class.and_then(|class| class.get_class().find_field_id(&name, &sig, is_static));
+	match fir {
+		Some(fir) => fir_to_jfield_id(&fir),
+		None => {
+			let name = String::from_utf8_lossy(name.as_slice()).into_owned();
+			throw(J_NO_SUCH_FIELD_ERROR, Some(name));
+			std::ptr::null_mut()
+		}
+	}
+}
+
+//the value of field 'name' of the reflection object
+fn reflected_value(obj: &Oop, name: &[u8], desc: &[u8]) -> Option<Oop> {
+	let class = object_class(obj);
+	let fir = class
+		.get_class()
+		.find_field_id(&new_br_bytes(name), &new_br_bytes(desc), false)?;
+	Some(Class::get_field_value(obj.extract_ref(), fir))
+}
+
+fn new_br_bytes(v: &[u8]) -> classfile::BytesRef {
+	Arc::new(v.to_vec())
+}
+
+/// The method of java.lang.reflect.Method or Constructor, located by 'slot'
+pub fn reflected_method(method: &Oop) -> Option<MethodIdRef> {
+	let clazz = reflected_value(method, b"clazz", b"Ljava/lang/Class;")?;
+	let slot = reflected_value(method, b"slot", b"I")?.extract_int() as usize;
+	let class = clazz.extract_ref().extract_mirror().target.clone()?;
+	let cls = class.get_class();
+	if !cls.is_instance() {
+		return None;
+	}
+	let mir = class
+		.extract_inst()
+		.all_methods
+		.values()
+		.find(|it| it.offset == slot)
+		.cloned();
+	mir
+}
+
+/// The field of java.lang.reflect.Field, located by 'slot'
+pub fn reflected_field(field: &Oop) -> Option<FieldIdRef> {
+	let clazz = reflected_value(field, b"clazz", b"Ljava/lang/Class;")?;
+	let slot = reflected_value(field, b"slot", b"I")?.extract_int() as usize;
+	let modifiers = reflected_value(field, b"modifiers", b"I")?.extract_int() as u16;
+	let class = clazz.extract_ref().extract_mirror().target.clone()?;
+	let cls = class.get_class();
+	if !cls.is_instance() {
+		return None;
+	}
+	let cls_obj = class.extract_inst();
+	let fields = if modifiers & ACC_STATIC != 0 {
+		&cls_obj.static_fields
+	} else {
+		&cls_obj.inst_fields
+	};
+	let fir = fields.values().find(|it| it.offset == slot).cloned();
+	fir
+}
+
+/// The chars of java.lang.String in [start, start + len), None with the exception
+/// pending if out of bounds
+pub unsafe fn string_region(str: jobject, start: jsize, len: jsize) -> Option<Vec<u16>> {
+	let chars = string_chars(str)?;
+	if start < 0 || len < 0 || start as usize + len as usize > chars.len() {
+		let msg = format!(
+			"String region {}..{} out of bounds for length {}",
+			start,
+			start as i64 + len as i64,
+			chars.len()
+		);
+		throw(J_STRING_INDEX_OUT_OF_BOUNDS, Some(msg));
+		return None;
+	}
+	Some(chars[start as usize..(start + len) as usize].to_vec())
+}
+
+/// The field of java.nio.Buffer, None if 'buf' is not a Buffer
+pub unsafe fn buffer_field(buf: jobject, name: &[u8], desc: &[u8]) -> Option<Oop> {
+	match jobject_to_oop(buf) {
+		Oop::Null => None,
+		buf => {
+			let buffer_class = runtime::require_class3(None, b"java/nio/Buffer")?;
+			if !runtime::cmp::instance_of(object_class(&buf), buffer_class) {
+				return None;
+			}
+			reflected_value(&buf, name, desc)
+		}
+	}
+}
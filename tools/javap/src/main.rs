@@ -144,13 +144,10 @@ fn main() {
         Some(classes) => {
             for it in classes {
                 match misc::find_class(it) {
-                    Ok(r) => {
-                        if let Ok((_, cf)) = parse_class(&r.1) {
-                            commander.run(&r.0, cf);
-                        } else {
-                            error!("parse class error: {}", it);
-                        }
-                    }
+                    Ok(r) => match parse_class(&r.1) {
+                        Ok(cf) => commander.run(&r.0, cf),
+                        Err(e) => error!("parse class error: {}: {}", it, e),
+                    },
                     Err(_e) => {
                         println!("Error: class not found: {}", it);
                     }
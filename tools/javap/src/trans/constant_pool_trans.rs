@@ -191,12 +191,34 @@ impl<'a> Translator<'a> {
                 Type::MethodType { desc_index: _ } => {
                     pool.push("todo: MethodType".to_string());
                 }
+                Type::Dynamic {
+                    bootstrap_method_attr_index: _,
+                    name_and_type_index: _,
+                } => {
+                    pool.push("todo: Dynamic".to_string());
+                }
                 Type::InvokeDynamic {
                     bootstrap_method_attr_index: _,
                     name_and_type_index: _,
                 } => {
                     pool.push("todo: InvokeDynamic".to_string());
                 }
+                Type::Module { name_index } | Type::Package { name_index } => {
+                    let tag = match it {
+                        Type::Module { .. } => "Module",
+                        _ => "Package",
+                    };
+                    let index = format!("#{}", *name_index);
+                    let name = constant_pool::get_utf8(&self.cf.cp, *name_index as usize);
+                    let v = format!(
+                        "{:>6} = {:18} {:14} // {}",
+                        pos,
+                        tag,
+                        index,
+                        String::from_utf8_lossy(name.as_slice())
+                    );
+                    pool.push(v);
+                }
                 Type::Unknown => (),
             }
         }
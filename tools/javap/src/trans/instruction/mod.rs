@@ -602,10 +602,16 @@ impl InstructionInfo {
                 ref_index: _,
             } => "todo: MethodHandle".to_string(),
             Type::MethodType { desc_index: _ } => "todo: MethodType".to_string(),
+            Type::Dynamic {
+                bootstrap_method_attr_index: _,
+                name_and_type_index: _,
+            } => "todo: Dynamic".to_string(),
             Type::InvokeDynamic {
                 bootstrap_method_attr_index: _,
                 name_and_type_index: _,
             } => "todo: InvokeDynamic".to_string(),
+            Type::Module { name_index: _ } => "todo: Module".to_string(),
+            Type::Package { name_index: _ } => "todo: Package".to_string(),
             Type::Unknown => unreachable!(),
         }
     }